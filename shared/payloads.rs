@@ -0,0 +1,254 @@
+//! Arguments et réponses des commandes Tauri échangés entre le backend et l'interface Yew.
+//!
+//! Ce fichier est compilé dans les deux crates (`#[path]` dans `src-tauri/src/lib.rs` et
+//! `src/main.rs`) : une commande et son appel ne peuvent plus diverger sans erreur de
+//! compilation. Il ne dépend que de `serde` ; les méthodes propres au backend sont dans
+//! des blocs `impl` de ses modules (`utils`, `events`, `app_setup`).
+
+use serde::{Deserialize, Serialize};
+
+/// Emprise d'un projet, dans le système de coordonnées du projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Copy)]
+pub struct BoundingBox {
+    pub xmin: f64,
+    pub ymin: f64,
+    pub xmax: f64,
+    pub ymax: f64,
+}
+
+/// Enveloppe des commandes qui reçoivent leurs paramètres dans une seule structure :
+/// Tauri associe chaque clé de l'objet transmis à un argument de la commande.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandArgs<T> {
+    pub args: T,
+}
+
+/// Conduite à tenir quand un projet du même nom existe déjà.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictMode {
+    /// Remplacer le projet existant, conservé jusqu'à la fin de la nouvelle création.
+    Overwrite,
+    /// Créer le projet sous un nom libre (`<nom>-2`, `<nom>-3`...).
+    Rename,
+    /// Ne rien créer et décrire le projet existant.
+    #[default]
+    Abort,
+}
+
+/// Paramètres de `create_project_com` (voir la commande pour le détail de chaque champ).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateProjectArgs {
+    pub name: String,
+    pub project_bb: BoundingBox,
+    /// "bdforet" (par défaut), "oso" ou "file:<chemin>".
+    #[serde(default)]
+    pub vegetation_source: Option<String>,
+    #[serde(default)]
+    pub on_conflict: Option<ConflictMode>,
+    /// Couches retenues, toutes si `None`.
+    #[serde(default)]
+    pub layers: Option<Vec<String>>,
+    /// Découpe d'un projet qui contient l'emprise : non renseignée, elle est proposée.
+    #[serde(default)]
+    pub allow_reuse: Option<bool>,
+    /// Dérogation aux limites de taille d'un projet.
+    #[serde(default)]
+    pub allow_oversize: Option<bool>,
+    /// Projet dont les paramètres de création complètent ceux-ci.
+    #[serde(default)]
+    pub template_project: Option<String>,
+}
+
+impl CreateProjectArgs {
+    /// Création d'un projet avec les paramètres par défaut.
+    pub fn new(name: impl Into<String>, project_bb: BoundingBox) -> Self {
+        CreateProjectArgs {
+            name: name.into(),
+            project_bb,
+            vegetation_source: None,
+            on_conflict: None,
+            layers: None,
+            allow_reuse: None,
+            allow_oversize: None,
+            template_project: None,
+        }
+    }
+}
+
+/// Format de sortie d'un export de projet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportTarget {
+    /// Archive zip du dossier du projet.
+    #[default]
+    Zip,
+    /// Dossier au format attendu par le simulateur (`tiles/` + `manifest.json`).
+    Simulator,
+}
+
+/// Contenu optionnel d'un export zip. Par défaut, seules les images découpées et
+/// les petits fichiers du projet sont exportés.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Inclure les GeoPackages du dossier `resources/`.
+    pub include_resources: bool,
+    /// Inclure le GeoTIFF complet du projet.
+    pub include_full_images: bool,
+    /// Générer et inclure le projet QGIS (`<nom>.qgs`), dont les couches pointent vers les
+    /// ressources et le GeoTIFF s'ils sont inclus.
+    pub include_qgis_project: bool,
+}
+
+/// Paramètres de `export`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportArgs {
+    pub project_name: String,
+    #[serde(default)]
+    pub target: ExportTarget,
+    /// Dossier d'export à utiliser à la place de celui des paramètres.
+    #[serde(default)]
+    pub override_output: Option<String>,
+    #[serde(default)]
+    pub options: ExportOptions,
+}
+
+/// Avancement d'une tâche, transmis avec l'événement `progress-update`.
+/// Les clés du catalogue (`stage.download`, `step.extract`...) sont stables : l'interface
+/// s'en sert pour situer l'étape, et affiche les textes traduits dans la langue configurée.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressUpdate {
+    pub stage: String,
+    pub stage_text: String,
+    pub step: Option<String>,
+    pub step_text: Option<String>,
+    /// Paramètres du texte de la sous-tâche (nom de couche, code de département...).
+    pub params: Vec<String>,
+    /// Avancement de la sous-tâche (courante, total).
+    pub count: Option<(usize, usize)>,
+}
+
+/// Projet de la liste de l'accueil, renvoyé par `list_projects`, avec les informations
+/// de tri et de recherche.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    pub name: String,
+    pub preview_path: String,
+    pub folder: String,
+    /// Résumé des millésimes des données (`BDTOPO 2025-03, BDFORET 2017-05, RPG 2023`).
+    pub vintages: String,
+    /// Date de création (RFC 3339), si elle est connue.
+    pub created_at: Option<String>,
+    /// Taille du dossier du projet en octets.
+    pub size_bytes: u64,
+    /// Codes des départements couverts, triés.
+    pub regions: Vec<String>,
+    /// Centre de l'emprise du projet, si son raster est lisible.
+    pub center: Option<(f64, f64)>,
+    /// La création du projet est terminée.
+    pub complete: bool,
+    /// Projet importé par référence, dont le dossier est hors du dossier des projets.
+    pub external: bool,
+}
+
+/// Compression du GeoTIFF des projets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TiffCompression {
+    /// Sans compression, le plus rapide à lire et à écrire.
+    #[default]
+    None,
+    Deflate,
+    Lzw,
+    /// Compression JPEG dans le GeoTIFF : la plus compacte, mais avec pertes.
+    JpegInTiff,
+}
+
+impl TiffCompression {
+    pub const ALL: [TiffCompression; 4] = [
+        TiffCompression::None,
+        TiffCompression::Deflate,
+        TiffCompression::Lzw,
+        TiffCompression::JpegInTiff,
+    ];
+
+    /// Valeur sérialisée de la compression (`none`, `deflate`, `lzw`, `jpeg_in_tiff`).
+    pub fn code(self) -> &'static str {
+        match self {
+            TiffCompression::None => "none",
+            TiffCompression::Deflate => "deflate",
+            TiffCompression::Lzw => "lzw",
+            TiffCompression::JpegInTiff => "jpeg_in_tiff",
+        }
+    }
+
+    /// Compression correspondant à une valeur sérialisée, `None` si elle est inconnue.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|compression| compression.code() == code)
+    }
+}
+
+/// Qualité des images produites : orthophotographie, image de végétation, découpes
+/// et GeoTIFF du projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageQuality {
+    /// Qualité (1-100) du JPEG de l'orthophotographie et des aperçus satellite.
+    pub ortho_jpeg_quality: u8,
+    /// Qualité (1-100) de l'image de végétation exportée en JPEG.
+    pub veget_jpeg_quality: u8,
+    /// Qualité (1-100) des découpes JPEG.
+    pub slice_jpeg_quality: u8,
+    pub tiff_compression: TiffCompression,
+}
+
+impl Default for ImageQuality {
+    fn default() -> Self {
+        Self {
+            ortho_jpeg_quality: 95,
+            veget_jpeg_quality: 95,
+            slice_jpeg_quality: 75,
+            tiff_compression: TiffCompression::None,
+        }
+    }
+}
+
+/// Langue proposée dans les paramètres.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageOption {
+    pub code: String,
+    pub label: String,
+}
+
+/// Paramètres de l'application, renvoyés par `get_settings`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsPayload {
+    pub output_location: String,
+    pub gdal_path: Option<String>,
+    pub python_path: Option<String>,
+    pub proxy_url: Option<String>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+    /// Hôtes joignables sans proxy, séparés par des virgules.
+    pub no_proxy: Option<String>,
+    /// Source d'orthophotos essayée en premier.
+    pub preferred_ortho_source: Option<String>,
+    /// Noms des sources d'orthophotos, par ordre de priorité.
+    pub ortho_sources: Vec<String>,
+    pub log_level: String,
+    pub log_levels: Vec<String>,
+    pub language: String,
+    pub languages: Vec<LanguageOption>,
+    pub image_quality: ImageQuality,
+    pub resolution: f64,
+    pub slice_factor: u32,
+    pub temp_dir: String,
+    /// Création des projets à partir du cache, sans accès réseau ni orthophoto.
+    pub offline_mode: bool,
+    /// URL du GeoTIFF OSO utilisé par la source de végétation `oso`.
+    pub oso_url: Option<String>,
+}
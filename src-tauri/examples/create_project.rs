@@ -0,0 +1,61 @@
+//! Crée un projet sans interface à partir des archives de test (`tests/res`).
+//!
+//! ```sh
+//! cargo run --example create_project -- porto-vecchio
+//! ```
+
+use firefront_gis_lib::fetcher::FixtureFetcher;
+use firefront_gis_lib::gis_operation::creation::{CreationRequest, create_project_pipeline};
+use firefront_gis_lib::progress::ProgressSink;
+use firefront_gis_lib::utils::BoundingBox;
+
+/// Affiche chaque étape de la création dans le terminal.
+struct ConsoleProgress;
+
+impl ProgressSink for ConsoleProgress {
+    fn report(&self, stage: &str, detail: Option<&str>, current: u64, total: u64) {
+        match detail {
+            Some(detail) => println!("{} / {} ({}/{})", stage, detail, current, total),
+            None => println!("{}", stage),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "exemple".to_string());
+    // Emprise couverte par les archives de test (Porto-Vecchio, Corse-du-Sud).
+    let project_bb = BoundingBox {
+        xmin: 1210000.0,
+        ymin: 6070000.0,
+        xmax: 1235000.0,
+        ymax: 6095000.0,
+    };
+    let fetcher = FixtureFetcher::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/res"));
+
+    let mut warnings = Vec::new();
+    let result = create_project_pipeline(
+        CreationRequest::new(name, project_bb),
+        &mut warnings,
+        &ConsoleProgress,
+        &fetcher,
+    )
+    .await;
+    for warning in &warnings {
+        println!("Avertissement: {}", warning);
+    }
+    match result {
+        Ok(summary) => println!(
+            "Projet {} créé dans {} (départements: {})",
+            summary.name,
+            summary.folder,
+            summary.regions.join(", ")
+        ),
+        Err(e) => {
+            eprintln!("Échec de la création: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,16 +1,84 @@
-use crate::dependency::{DependencyError, check_dependencies};
-use crate::gis_operation::regions::build_regions_graph;
-use crate::utils::{OUTPUT_DIR, create_directory_if_not_exists};
+use crate::dependency::{DependencyError, DependencyStatus, dependency_statuses};
+use crate::estimate::Throughput;
+use crate::gdal_version::GdalVersion;
+use crate::gis_operation::colormap::{self, BuildingHeights, Rgb};
+use crate::gis_operation::layer_config::load_layer_config;
+use crate::gis_operation::ortho::{OrthoSource, default_ortho_sources, prefer_source};
+use crate::gis_operation::regions::{
+    REGIONS_GEOJSON_FILE, REGIONS_GRAPH_FILE, build_regions_graph, load_regions_graph,
+    set_regions_index,
+};
+use crate::gis_operation::slicing::DEFAULT_SLICE_NAME_TEMPLATE;
+use crate::i18n::{self, Language};
+use crate::logging::parse_log_level;
+pub use crate::payloads::{ImageQuality, TiffCompression};
+use crate::utils::{
+    InstanceLock, OUTPUT_DIR, cache_dir, create_directory_if_not_exists, startup_cleanup, temp_dir,
+    tmp_max_age, try_lock_instance,
+};
+use crate::web_request::GEOPF_DOWNLOAD_API;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// Proxy HTTP(S) utilisé pour les téléchargements et les requêtes WMS de GDAL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// URL du proxy, par exemple `http://proxy.sdis.fr:8080`.
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hôtes joignables sans passer par le proxy.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+impl TiffCompression {
+    /// Options de création GTiff de la compression.
+    ///
+    /// # Arguments
+    ///
+    /// * `jpeg_quality` - qualité (1-100) utilisée par `JpegInTiff`
+    pub fn creation_options(self, jpeg_quality: u8) -> Vec<(&'static str, String)> {
+        match self {
+            TiffCompression::None => Vec::new(),
+            TiffCompression::Deflate => vec![("COMPRESS", "DEFLATE".to_string())],
+            TiffCompression::Lzw => vec![("COMPRESS", "LZW".to_string())],
+            TiffCompression::JpegInTiff => vec![
+                ("COMPRESS", "JPEG".to_string()),
+                ("JPEG_QUALITY", jpeg_quality.to_string()),
+            ],
+        }
+    }
+}
+
+impl ImageQuality {
+    /// Vérifie que les qualités JPEG sont comprises entre 1 et 100.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, quality) in [
+            ("ortho_jpeg_quality", self.ortho_jpeg_quality),
+            ("veget_jpeg_quality", self.veget_jpeg_quality),
+            ("slice_jpeg_quality", self.slice_jpeg_quality),
+        ] {
+            if !(1..=100).contains(&quality) {
+                return Err(format!(
+                    "Qualité JPEG invalide pour {}: {} (1-100)",
+                    name, quality
+                ));
+            }
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub cache_dir: PathBuf,
     pub projects_dir: PathBuf,
@@ -18,7 +86,74 @@ pub struct Config {
     pub resource_dir: PathBuf,
     pub resolution: f64,
     pub slice_factor: u32,
+    /// Débruite les découpes ORTHO lors du découpage (désactivé par défaut). Les découpes
+    /// VEGET, dont les couleurs sont des classes, ne sont jamais filtrées.
+    #[serde(alias = "enhance_slices")]
+    pub enhance_ortho_slices: bool,
+    /// Modèle des noms de tuiles découpées, voir `slicing::slice_file_name`.
+    pub slice_name_template: String,
+    /// Mémoire maximale (en Mo) d'une image VEGET ou ORTHO décodée pour le découpage ;
+    /// au-delà, les tuiles sont lues par fenêtres dans l'image avec GDAL.
+    pub slice_memory_limit_mb: u64,
+    /// Écrit à chaque découpage un aperçu de la grille des tuiles par type
+    /// (`slices/overview_veget.jpg`, `slices/overview_ortho.jpg`), inclus dans les exports zip.
+    pub slice_overview: bool,
+    /// URL d'un GeoTIFF OSO optimisé pour le cloud (lu via `/vsicurl/`).
+    pub oso_url: Option<String>,
+    /// Nombre maximal de téléchargements d'archives simultanés.
+    pub download_parallelism: usize,
+    /// Taille maximale du cache des archives, en Go, au-delà de laquelle
+    /// les archives les moins récemment utilisées sont supprimées.
+    pub max_cache_size_gb: f64,
+    /// Surface maximale (en km²) de l'emprise d'un projet, sauf dérogation à la création.
+    pub max_project_area_km2: f64,
+    /// Nombre maximal de départements intersectés par un projet, sauf dérogation à la création.
+    pub max_project_regions: usize,
+    /// Débits de référence pour l'estimation de la durée de création d'un projet.
+    pub throughput: Throughput,
+    /// Couleur (RGB) des pixels sans données dans les exports sans transparence (JPEG).
+    pub nodata_color: [u8; 3],
+    /// Qualité des JPEG et compression du GeoTIFF des projets.
+    pub image_quality: ImageQuality,
+    /// Seuil de hauteur et couleurs des bâtiments bas et hauts.
+    pub building_heights: BuildingHeights,
+    /// Couleur (RGB) du fond départemental des nouveaux projets : terres sans autre
+    /// couverture connue. Les projets antérieurs gardent le noir (voir `ProjectMetadata::land_color`).
+    pub default_land_color: Rgb,
+    /// Services WMS des orthophotos, essayés par ordre de priorité.
+    pub ortho_sources: Vec<OrthoSource>,
+    /// Ne télécharge que les tuiles de l'orthophoto qui touchent les départements du projet ;
+    /// les tuiles en mer sont remplies de `ortho_sea_color` (voir `download_satellite_jpeg`).
+    pub clip_ortho_to_land: bool,
+    /// Couleur (RGB) des tuiles de l'orthophoto en mer.
+    pub ortho_sea_color: [u8; 3],
+    /// Niveau minimal des journaux (`error`, `warn`, `info`, `debug`, `trace`),
+    /// appliqué au démarrage de l'application.
+    pub log_level: String,
+    /// Langue des messages de progression et des erreurs, celle du système par défaut.
+    pub language: Language,
+    /// Âge (en heures) au-delà duquel le contenu du dossier temporaire est supprimé au démarrage ;
+    /// 0 supprime tout, les dossiers de travail ne servant qu'à une exécution.
+    pub tmp_max_age_hours: u64,
+    /// Dossier d'archives locales (`BDTOPO_2A.7z`...) utilisées à la place des
+    /// téléchargements : les projets sont alors créés sans accès réseau.
+    pub offline_fixture_dir: Option<PathBuf>,
+    /// Crée les projets sans accès réseau : les archives sont lues dans le cache sans
+    /// rechercher leurs URLs, et l'orthophoto n'est pas téléchargée. Activé automatiquement
+    /// pour une création lorsque les serveurs sont injoignables.
+    pub offline_mode: bool,
+    /// Adresse de l'API de téléchargement de la Géoplateforme, source principale des URLs
+    /// d'archives (la page de geoservices.ign.fr n'est lue qu'en repli).
+    pub geopf_download_api: String,
+    /// URLs d'archives saisies par l'utilisateur, par base (`BDTOPO`, `BDFORET`, `RPG`)
+    /// puis par code de département : utilisées telles quelles, sans recherche, lorsque
+    /// la résolution automatique échoue.
+    pub dataset_url_overrides: BTreeMap<String, BTreeMap<String, String>>,
+    /// Date (RFC 3339) de la dernière recherche de nouveaux millésimes, qui n'est
+    /// relancée au démarrage qu'une fois par `DATA_UPDATE_CHECK_INTERVAL_HOURS`.
+    pub last_data_update_check: Option<String>,
     // User configurable settings
+    pub proxy: Option<ProxyConfig>,
     pub output_location: PathBuf,
     pub gdal_path: Option<PathBuf>,
     pub python_path: Option<PathBuf>,
@@ -26,6 +161,8 @@ pub struct Config {
 
 lazy_static! {
     pub static ref CONFIG: Mutex<Config> = Mutex::new(Config::load().unwrap_or_default());
+    /// Verrou d'instance pris au démarrage, conservé jusqu'à l'arrêt de l'application.
+    static ref INSTANCE_LOCK: Mutex<Option<InstanceLock>> = Mutex::new(None);
 }
 
 impl Default for Config {
@@ -37,6 +174,32 @@ impl Default for Config {
             resource_dir: PathBuf::from("resources"),
             resolution: 10.0,
             slice_factor: 500,
+            enhance_ortho_slices: false,
+            slice_name_template: DEFAULT_SLICE_NAME_TEMPLATE.to_string(),
+            slice_memory_limit_mb: 2048,
+            slice_overview: false,
+            oso_url: None,
+            download_parallelism: 3,
+            max_cache_size_gb: 30.0,
+            max_project_area_km2: 2500.0,
+            max_project_regions: 4,
+            throughput: Throughput::default(),
+            nodata_color: [0, 0, 0],
+            image_quality: ImageQuality::default(),
+            building_heights: BuildingHeights::default(),
+            default_land_color: colormap::REGIONAL,
+            ortho_sources: default_ortho_sources(),
+            clip_ortho_to_land: true,
+            ortho_sea_color: [22, 58, 92],
+            log_level: "info".to_string(),
+            language: Language::system(),
+            tmp_max_age_hours: 0,
+            offline_fixture_dir: None,
+            offline_mode: false,
+            geopf_download_api: GEOPF_DOWNLOAD_API.to_string(),
+            dataset_url_overrides: BTreeMap::new(),
+            last_data_update_check: None,
+            proxy: None,
             output_location: OUTPUT_DIR.lock().unwrap().clone(),
             gdal_path: None,
             python_path: None,
@@ -64,43 +227,372 @@ impl Config {
         let mut file = File::open(config_path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        let config: Config = serde_json::from_str(&contents)?;
+        let value: serde_json::Value = serde_json::from_str(&contents)?;
+        let missing_ortho_sources = value.get("ortho_sources").is_none();
+        // Ancien réglage unique, remplacé par la section `image_quality`.
+        let legacy_jpeg_quality = value
+            .get("jpeg_quality")
+            .filter(|_| value.get("image_quality").is_none())
+            .and_then(|quality| quality.as_u64());
+        let mut config: Config = serde_json::from_value(value)?;
+        if let Some(quality) = legacy_jpeg_quality {
+            config.image_quality.veget_jpeg_quality = quality.clamp(1, 100) as u8;
+        }
+        if missing_ortho_sources || legacy_jpeg_quality.is_some() {
+            // Écrit les sources par défaut et la section `image_quality` pour qu'elles
+            // puissent être modifiées dans le fichier.
+            config.save()?;
+        }
         Ok(config)
     }
 
-    pub fn update_settings(
+    /// Applique les modifications des paramètres sans les enregistrer. Les paramètres
+    /// utilisés par les créations et les exports (`PIPELINE_SETTINGS`) ne sont pas modifiés
+    /// tant qu'un projet est en cours de traitement : ils sont renvoyés comme bloqués,
+    /// les autres modifications étant appliquées.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - les modifications demandées
+    /// * `projects_busy` - vrai si un projet est en cours de création, d'export...
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>, Box<dyn Error>>` - les paramètres bloqués, ou une erreur si une valeur est invalide
+    pub fn apply_settings(
         &mut self,
-        output_location: Option<String>,
-        gdal_path: Option<String>,
-        python_path: Option<String>,
-    ) -> Result<(), Box<dyn Error>> {
-        if let Some(output) = output_location {
-            self.output_location = PathBuf::from(output);
+        update: SettingsUpdate,
+        projects_busy: bool,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        if let Some(resolution) = update.resolution {
+            if !resolution.is_finite() || resolution <= 0.0 {
+                return Err(format!("Résolution invalide: {}", resolution).into());
+            }
+        }
+        if update.slice_factor == Some(0) {
+            return Err("Le facteur de découpe doit être positif".into());
+        }
+        if let Some(image_quality) = &update.image_quality {
+            image_quality.validate()?;
+        }
+        if let Some(name) = &update.preferred_ortho_source {
+            if !prefer_source(&mut self.ortho_sources, name) {
+                return Err(format!("Source d'orthophotos inconnue: {}", name).into());
+            }
+        }
+        if let Some(level) = update.log_level {
+            self.log_level = parse_log_level(&level)?;
+        }
+        if let Some(code) = update.language {
+            self.language = Language::from_code(&code)?;
         }
 
-        self.gdal_path = gdal_path.map(PathBuf::from);
-        self.python_path = python_path.map(PathBuf::from);
+        let mut blocked = Vec::new();
+        let resolution = update
+            .resolution
+            .filter(|resolution| *resolution != self.resolution);
+        let slice_factor = update
+            .slice_factor
+            .filter(|slice_factor| *slice_factor != self.slice_factor);
+        let temp_dir = update
+            .temp_dir
+            .map(PathBuf::from)
+            .filter(|temp_dir| *temp_dir != self.temp_dir);
+        if projects_busy {
+            for (name, changed) in PIPELINE_SETTINGS.into_iter().zip([
+                resolution.is_some(),
+                slice_factor.is_some(),
+                temp_dir.is_some(),
+            ]) {
+                if changed {
+                    blocked.push(name.to_string());
+                }
+            }
+        } else {
+            self.resolution = resolution.unwrap_or(self.resolution);
+            self.slice_factor = slice_factor.unwrap_or(self.slice_factor);
+            if let Some(temp_dir) = temp_dir {
+                self.temp_dir = temp_dir;
+            }
+        }
 
+        if let Some(output) = update.output_location {
+            self.output_location = PathBuf::from(output);
+        }
+        if let Some(image_quality) = update.image_quality {
+            self.image_quality = image_quality;
+        }
+        if let Some(offline_mode) = update.offline_mode {
+            self.offline_mode = offline_mode;
+        }
+        self.gdal_path = update.gdal_path.map(PathBuf::from);
+        self.python_path = update.python_path.map(PathBuf::from);
+        self.proxy = update.proxy;
+        self.oso_url = update.oso_url;
+        Ok(blocked)
+    }
+
+    /// Applique les modifications des paramètres (voir `apply_settings`) puis les enregistre.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>, Box<dyn Error>>` - les paramètres bloqués par un projet en cours de traitement
+    pub fn update_settings(
+        &mut self,
+        update: SettingsUpdate,
+        projects_busy: bool,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let blocked = self.apply_settings(update, projects_busy)?;
         self.save()?;
-        Ok(())
+        Ok(blocked)
+    }
+}
+
+/// Paramètres lus pendant les créations et les exports, figés dans un `RunConfig`
+/// et non modifiables tant qu'un projet est en cours de traitement.
+pub const PIPELINE_SETTINGS: [&str; 3] = ["resolution", "slice_factor", "temp_dir"];
+
+/// Modifications des paramètres demandées par l'interface. `None` conserve la valeur
+/// actuelle, sauf pour `gdal_path`, `python_path`, `proxy` et `oso_url` qui sont remplacés
+/// tels quels.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsUpdate {
+    pub output_location: Option<String>,
+    pub gdal_path: Option<String>,
+    pub python_path: Option<String>,
+    pub proxy: Option<ProxyConfig>,
+    pub preferred_ortho_source: Option<String>,
+    pub log_level: Option<String>,
+    pub language: Option<String>,
+    pub image_quality: Option<ImageQuality>,
+    pub resolution: Option<f64>,
+    pub slice_factor: Option<u32>,
+    pub temp_dir: Option<String>,
+    pub offline_mode: Option<bool>,
+    pub oso_url: Option<String>,
+}
+
+/// Résultat de l'enregistrement des paramètres, transmis à l'interface.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSettings {
+    pub message: String,
+    /// Paramètres non modifiés car un projet est en cours de traitement.
+    pub blocked: Vec<String>,
+}
+
+/// Paramètres d'une création ou d'un export, figés à son lancement : une modification
+/// des paramètres pendant le traitement ne change pas les valeurs utilisées par ses étapes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub resolution: f64,
+    pub slice_factor: u32,
+    pub temp_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    /// Création sans accès réseau, à partir des archives du cache.
+    pub offline_mode: bool,
+    /// Couleur (RGB) des pixels sans données dans les exports JPEG.
+    pub nodata_color: [u8; 3],
+    pub image_quality: ImageQuality,
+    pub building_heights: BuildingHeights,
+    pub ortho_sources: Vec<OrthoSource>,
+    pub clip_ortho_to_land: bool,
+    pub ortho_sea_color: [u8; 3],
+    pub enhance_ortho_slices: bool,
+    pub slice_name_template: String,
+    pub slice_memory_limit_mb: u64,
+    pub slice_overview: bool,
+}
+
+impl RunConfig {
+    /// Copie des paramètres actuels.
+    pub fn snapshot() -> Self {
+        let config = CONFIG.lock().unwrap();
+        RunConfig {
+            resolution: config.resolution,
+            slice_factor: config.slice_factor,
+            temp_dir: config.temp_dir.clone(),
+            cache_dir: config.cache_dir.clone(),
+            offline_mode: config.offline_mode,
+            nodata_color: config.nodata_color,
+            image_quality: config.image_quality.clone(),
+            building_heights: config.building_heights,
+            ortho_sources: config.ortho_sources.clone(),
+            clip_ortho_to_land: config.clip_ortho_to_land,
+            ortho_sea_color: config.ortho_sea_color,
+            enhance_ortho_slices: config.enhance_ortho_slices,
+            slice_name_template: config.slice_name_template.clone(),
+            slice_memory_limit_mb: config.slice_memory_limit_mb,
+            slice_overview: config.slice_overview,
+        }
+    }
+
+    pub fn ortho_jpeg_quality(&self) -> u8 {
+        self.image_quality.ortho_jpeg_quality.clamp(1, 100)
+    }
+
+    pub fn veget_jpeg_quality(&self) -> u8 {
+        self.image_quality.veget_jpeg_quality.clamp(1, 100)
+    }
+
+    pub fn slice_jpeg_quality(&self) -> u8 {
+        self.image_quality.slice_jpeg_quality.clamp(1, 100)
+    }
+}
+
+/// Résultat de la création d'un dossier de l'application au démarrage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectorySetup {
+    pub path: String,
+    pub error: Option<String>,
+}
+
+/// Rapport des vérifications du démarrage, conservé dans l'état de l'application
+/// et affiché par l'écran « Configuration requise » tant qu'un élément manque.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SetupReport {
+    pub dependencies: Vec<DependencyStatus>,
+    pub directories: Vec<DirectorySetup>,
+    /// Erreur de construction ou de chargement du graphe des départements.
+    pub regions_graph_error: Option<String>,
+    /// Erreur de lecture de la configuration des couches.
+    pub layer_config_error: Option<String>,
+    /// Version de la bibliothèque GDAL, `None` si elle est illisible.
+    pub gdal_version: Option<String>,
+    /// Avertissement pour une version de GDAL antérieure à la version minimale prise en
+    /// charge ; il ne bloque pas l'application.
+    pub gdal_warning: Option<String>,
+    /// Vrai si rien ne manque ; recalculé à chaque vérification pour le frontend.
+    pub ready: bool,
+}
+
+impl SetupReport {
+    /// Libellés des éléments manquants ou en erreur.
+    pub fn missing(&self) -> Vec<String> {
+        let mut missing: Vec<String> = self
+            .dependencies
+            .iter()
+            .filter(|status| !status.found)
+            .map(|status| status.label.clone())
+            .collect();
+        missing.extend(
+            self.directories
+                .iter()
+                .filter(|directory| directory.error.is_some())
+                .map(|directory| directory.path.clone()),
+        );
+        if self.regions_graph_error.is_some() {
+            missing.push("graphe des départements".to_string());
+        }
+        if self.layer_config_error.is_some() {
+            missing.push("configuration des couches".to_string());
+        }
+        missing
+    }
+
+    /// Refuse une commande tant que la configuration est incomplète, en renvoyant
+    /// à l'écran « Configuration requise ».
+    ///
+    /// # Returns
+    /// - Result<(), String> - Une erreur listant les éléments manquants.
+    pub fn ensure_ready(&self) -> Result<(), String> {
+        let missing = self.missing();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(i18n::text("error.setup_incomplete", &[&missing.join(", ")]))
+        }
     }
 }
 
 /// Vérifie si les dépendances sont installées et crée les répertoires nécessaires.
+/// Aucune vérification n'interrompt les suivantes : le rapport liste tout ce qui manque,
+/// et l'application démarre même s'il est incomplet.
 ///
 /// # Returns
-/// - Result<(), DependencyError>
-pub fn setup_check() -> Result<(), String> {
+/// - SetupReport - Le résultat de chaque vérification.
+pub fn setup_check() -> SetupReport {
     let mut config = CONFIG.lock().unwrap();
+    let mut report = SetupReport::default();
+
+    for dir in [&config.cache_dir, &config.temp_dir] {
+        let path = dir.to_string_lossy().to_string();
+        let error = create_directory_if_not_exists(&path)
+            .err()
+            .map(|e| e.to_string());
+        report.directories.push(DirectorySetup { path, error });
+    }
+
+    report.dependencies = dependency_statuses(&mut config);
+    drop(config);
+
+    let regions = build_regions_graph(
+        Path::new(REGIONS_GEOJSON_FILE),
+        Path::new(REGIONS_GRAPH_FILE),
+        false,
+    )
+    .and_then(|_| load_regions_graph());
+    match regions {
+        Ok(graph) => set_regions_index(graph),
+        Err(e) => report.regions_graph_error = Some(e.to_string()),
+    }
+
+    if report.directories.iter().all(|dir| dir.error.is_none()) {
+        clean_previous_runs();
+    }
+
+    report.layer_config_error = load_layer_config().err().map(|e| e.to_string());
+    let gdal_version = GdalVersion::detect();
+    report.gdal_version = gdal_version.map(|version| version.to_string());
+    report.gdal_warning = gdal_version.and_then(GdalVersion::support_warning);
+    if let Some(warning) = &report.gdal_warning {
+        tracing::warn!(warning = %warning, "Version de GDAL non prise en charge");
+    }
+    report.ready = report.missing().is_empty();
+    if !report.ready {
+        tracing::warn!(missing = ?report.missing(), "Configuration incomplète");
+    }
+    report
+}
 
-    create_directory_if_not_exists(&config.cache_dir.to_string_lossy())
-        .map_err(|e| e.to_string())?;
-    create_directory_if_not_exists(&config.temp_dir.to_string_lossy())
-        .map_err(|e| e.to_string())?;
+/// Relance les vérifications du démarrage et remplace le rapport de l'application.
+///
+/// # Arguments
+/// - `state`: Le rapport conservé dans l'état de l'application.
+///
+/// # Returns
+/// - SetupReport - Le nouveau rapport.
+pub fn recheck_setup(state: &RwLock<SetupReport>) -> SetupReport {
+    let report = setup_check();
+    *state.write().unwrap() = report.clone();
+    report
+}
 
-    check_dependencies(&mut config).map_err(|e| e.to_string())?;
-    build_regions_graph(Some("resources/regions_graph.json")).map_err(|e| e.to_string())?;
-    Ok(())
+/// Supprime les données temporaires laissées par les exécutions précédentes
+/// (voir `startup_cleanup`), sauf si une autre instance de l'application est en cours :
+/// elle tient alors le verrou d'instance du dossier temporaire.
+fn clean_previous_runs() {
+    let mut held = INSTANCE_LOCK.lock().unwrap();
+    if held.is_some() {
+        return;
+    }
+    match try_lock_instance(&temp_dir()) {
+        Ok(Some(lock)) => {
+            let report = startup_cleanup(&temp_dir(), &cache_dir(), tmp_max_age());
+            tracing::info!(
+                removed = report.removed,
+                freed_bytes = report.freed_bytes,
+                "Données temporaires des exécutions précédentes supprimées"
+            );
+            *held = Some(lock);
+        }
+        Ok(None) => {
+            tracing::info!("Une autre instance est en cours : nettoyage du démarrage ignoré")
+        }
+        Err(e) => tracing::warn!(
+            error = %e,
+            "Verrou d'instance indisponible : nettoyage du démarrage ignoré"
+        ),
+    }
 }
 
 impl fmt::Display for DependencyError {
@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use geojson::GeoJson;
+
+use crate::app_setup::RunConfig;
+use crate::busy::{BusyKind, BusyProjects};
+use crate::estimate::{ProjectLimits, check_project_size};
+use crate::events::{BatchProgress, ProgressUpdate, Warning, write_creation_log};
+use crate::fetcher::DataFetcher;
+use crate::gis_operation::oso::VegetationSource;
+use crate::i18n;
+use crate::pipeline::{CreationOutcome, create_project_pipeline, creation_layer_config};
+use crate::progress::ProgressSink;
+use crate::utils::{BoundingBox, PROJECT_GRID_PIXELS, project_conflict, validate_project_name};
+
+/// Entrée lue dans un fichier de définitions de projets, avant validation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchDefinition {
+    /// Position de l'entrée dans le fichier, à partir de 1.
+    pub index: usize,
+    pub name: String,
+    /// L'emprise, ou la raison pour laquelle elle n'a pas pu être lue.
+    pub project_bb: Result<BoundingBox, String>,
+}
+
+/// Projet validé, prêt à être créé.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchEntry {
+    pub index: usize,
+    pub name: String,
+    pub project_bb: BoundingBox,
+}
+
+/// Issue de la création d'un projet du lot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Created,
+    /// Entrée refusée à la validation, avant toute création.
+    Invalid,
+    Failed,
+    Cancelled,
+}
+
+/// Résultat d'un projet du lot, renvoyé par `create_projects_batch`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub index: usize,
+    pub name: String,
+    pub status: BatchStatus,
+    /// Dossier du projet créé.
+    pub folder: Option<String>,
+    /// Raison du refus ou de l'échec.
+    pub message: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+impl BatchResult {
+    fn rejected(index: usize, name: &str, status: BatchStatus, message: String) -> Self {
+        BatchResult {
+            index,
+            name: name.to_string(),
+            status,
+            folder: None,
+            message: Some(message),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+fn parse_coordinate(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("Coordonnée invalide: '{}'", value.trim()))
+}
+
+/// Lit les définitions d'un fichier CSV `name,xmin,ymin,xmax,ymax` (séparateur `,` ou `;`).
+/// Une ligne d'en-tête commençant par `name` ou `nom`, les lignes vides et les lignes
+/// commençant par `#` sont ignorées.
+///
+/// # Arguments
+///
+/// * `text` - Le contenu du fichier.
+///
+/// # Returns
+///
+/// * `Vec<BatchDefinition>` - Une définition par ligne de données ; une ligne mal formée
+///   donne une définition dont l'emprise est en erreur.
+pub fn parse_batch_csv(text: &str) -> Vec<BatchDefinition> {
+    let mut definitions = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let separator = if line.contains(';') { ';' } else { ',' };
+        let fields: Vec<&str> = line.split(separator).map(str::trim).collect();
+        if definitions.is_empty()
+            && (fields[0].eq_ignore_ascii_case("name") || fields[0].eq_ignore_ascii_case("nom"))
+        {
+            continue;
+        }
+
+        let project_bb = if fields.len() != 5 {
+            Err(format!(
+                "5 colonnes attendues (name,xmin,ymin,xmax,ymax), {} trouvée(s)",
+                fields.len()
+            ))
+        } else {
+            fields[1..]
+                .iter()
+                .map(|value| parse_coordinate(value))
+                .collect::<Result<Vec<f64>, String>>()
+                .map(|values| BoundingBox::new(values[0], values[1], values[2], values[3]))
+        };
+        definitions.push(BatchDefinition {
+            index: definitions.len() + 1,
+            name: fields[0].to_string(),
+            project_bb,
+        });
+    }
+    definitions
+}
+
+/// Étend `extent` (xmin, ymin, xmax, ymax) aux positions d'un tableau de coordonnées GeoJSON.
+fn extend_extent(coordinates: &serde_json::Value, extent: &mut Option<[f64; 4]>) {
+    let Some(items) = coordinates.as_array() else {
+        return;
+    };
+    let position = match items.as_slice() {
+        [x, y, ..] => x.as_f64().zip(y.as_f64()),
+        _ => None,
+    };
+    if let Some((x, y)) = position {
+        let [xmin, ymin, xmax, ymax] = extent.get_or_insert([x, y, x, y]);
+        *xmin = xmin.min(x);
+        *ymin = ymin.min(y);
+        *xmax = xmax.max(x);
+        *ymax = ymax.max(y);
+        return;
+    }
+    for item in items {
+        extend_extent(item, extent);
+    }
+}
+
+/// Lit les définitions d'une FeatureCollection GeoJSON : le nom de chaque projet est la
+/// propriété `name`, son emprise le `bbox` de l'entité ou, à défaut, l'emprise de sa géométrie.
+/// Les coordonnées sont dans le système des départements (Lambert-93 en métropole).
+///
+/// # Arguments
+///
+/// * `text` - Le contenu du fichier.
+///
+/// # Returns
+///
+/// * `Result<Vec<BatchDefinition>, String>` - Une définition par entité, ou une erreur si le
+///   fichier n'est pas une FeatureCollection.
+pub fn parse_batch_geojson(text: &str) -> Result<Vec<BatchDefinition>, String> {
+    let collection = match text.parse::<GeoJson>().map_err(|e| e.to_string())? {
+        GeoJson::FeatureCollection(collection) => collection,
+        _ => return Err("Une FeatureCollection GeoJSON est attendue".to_string()),
+    };
+
+    Ok(collection
+        .features
+        .iter()
+        .enumerate()
+        .map(|(position, feature)| {
+            let name = feature
+                .property("name")
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let extent = match &feature.bbox {
+                Some(bbox) if bbox.len() == 4 => Some([bbox[0], bbox[1], bbox[2], bbox[3]]),
+                _ => feature.geometry.as_ref().and_then(|geometry| {
+                    let value = serde_json::to_value(geometry).ok()?;
+                    let mut extent = None;
+                    extend_extent(value.get("coordinates")?, &mut extent);
+                    extent
+                }),
+            };
+            BatchDefinition {
+                index: position + 1,
+                name,
+                project_bb: extent
+                    .map(|[xmin, ymin, xmax, ymax]| BoundingBox::new(xmin, ymin, xmax, ymax))
+                    .ok_or_else(|| "Entité sans géométrie ni bbox".to_string()),
+            }
+        })
+        .collect())
+}
+
+/// Lit un fichier de définitions de projets, CSV (`.csv`, `.txt`) ou GeoJSON
+/// (`.geojson`, `.json`).
+///
+/// # Arguments
+///
+/// * `path` - Le fichier de définitions.
+///
+/// # Returns
+///
+/// * `Result<Vec<BatchDefinition>, String>` - Les définitions, ou une erreur si le fichier
+///   ne peut pas être lu ou n'a pas un format reconnu.
+pub fn parse_batch_file(path: &Path) -> Result<Vec<BatchDefinition>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "csv" | "txt" => Ok(parse_batch_csv(&text)),
+        "geojson" | "json" => parse_batch_geojson(&text),
+        _ => Err(format!(
+            "Format de fichier non reconnu (CSV ou GeoJSON attendu): {}",
+            path.display()
+        )),
+    }
+}
+
+/// Valide toutes les définitions avant la première création : nom de projet utilisable,
+/// emprise lisible et alignée sur la grille du projet, nom unique dans le lot (sans
+/// distinction de casse, comme les dossiers sous Windows).
+///
+/// # Arguments
+///
+/// * `definitions` - Les définitions lues dans le fichier.
+/// * `resolution` - La résolution des projets en mètres par pixel.
+///
+/// # Returns
+///
+/// * `(Vec<BatchEntry>, Vec<BatchResult>)` - Les projets à créer et les entrées refusées.
+pub fn validate_batch(
+    definitions: &[BatchDefinition],
+    resolution: f64,
+) -> (Vec<BatchEntry>, Vec<BatchResult>) {
+    let mut entries = Vec::new();
+    let mut rejected = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for definition in definitions {
+        let checked = validate_project_name(&definition.name)
+            .map_err(|e| e.to_string())
+            .and_then(|_| match seen.get(&definition.name.to_lowercase()) {
+                Some(first) => Err(format!("Nom déjà utilisé par l'entrée {}", first)),
+                None => Ok(()),
+            })
+            .and_then(|_| definition.project_bb.clone())
+            .and_then(|project_bb| {
+                let (width, height) = project_bb
+                    .raster_size(resolution)
+                    .map_err(|e| e.to_string())?;
+                if width % PROJECT_GRID_PIXELS != 0 || height % PROJECT_GRID_PIXELS != 0 {
+                    return Err(format!(
+                        "Largeur et hauteur ({} × {} pixels) non multiples de {} pixels",
+                        width, height, PROJECT_GRID_PIXELS
+                    ));
+                }
+                Ok(project_bb)
+            });
+
+        match checked {
+            Ok(project_bb) => {
+                seen.insert(definition.name.to_lowercase(), definition.index);
+                entries.push(BatchEntry {
+                    index: definition.index,
+                    name: definition.name.clone(),
+                    project_bb,
+                });
+            }
+            Err(message) => rejected.push(BatchResult::rejected(
+                definition.index,
+                &definition.name,
+                BatchStatus::Invalid,
+                message,
+            )),
+        }
+    }
+    (entries, rejected)
+}
+
+/// Transmet la progression d'une création du lot avec la position du projet dans le lot.
+struct BatchProgressSink<'a> {
+    index: usize,
+    total: usize,
+    name: &'a str,
+    on_progress: &'a (dyn Fn(BatchProgress) + Sync),
+}
+
+impl ProgressSink for BatchProgressSink<'_> {
+    fn report(&self, stage: &str, detail: Option<&str>, current: u64, total: u64) {
+        let update = match detail {
+            Some(step) => ProgressUpdate::step(stage, step, &[], current as usize, total as usize),
+            None => ProgressUpdate::stage(stage),
+        };
+        self.report_update(update);
+    }
+
+    fn report_update(&self, update: ProgressUpdate) {
+        (self.on_progress)(BatchProgress {
+            index: self.index,
+            total: self.total,
+            name: self.name.to_string(),
+            update,
+        });
+    }
+}
+
+/// Crée les projets du lot l'un après l'autre, avec le pipeline de `create_project_com` :
+/// les archives communes à des emprises voisines sont reprises du cache. L'échec d'un projet
+/// n'interrompt pas le lot ; un projet existant ou occupé est signalé sans être remplacé.
+///
+/// # Arguments
+///
+/// * `entries` - Les projets validés par `validate_batch`.
+/// * `total` - Le nombre d'entrées du lot, entrées refusées comprises.
+/// * `vegetation_source` - La source de végétation de tous les projets.
+/// * `busy` - Les projets occupés : chaque projet est réservé pendant sa création.
+/// * `fetcher` - Accès aux archives et à l'orthophoto.
+/// * `on_progress` - Reçoit l'avancement de chaque création.
+///
+/// # Returns
+///
+/// * `Vec<BatchResult>` - Le résultat de chaque projet, dans l'ordre du lot.
+pub async fn run_batch(
+    entries: &[BatchEntry],
+    total: usize,
+    vegetation_source: &VegetationSource,
+    busy: &BusyProjects,
+    fetcher: &dyn DataFetcher,
+    on_progress: &(dyn Fn(BatchProgress) + Sync),
+) -> Vec<BatchResult> {
+    let mut results = Vec::new();
+    for entry in entries {
+        let start = Instant::now();
+        let failed = |message: String| {
+            BatchResult::rejected(entry.index, &entry.name, BatchStatus::Failed, message)
+        };
+
+        let guard = match busy.try_acquire(&entry.name, BusyKind::Creating) {
+            Ok(guard) => guard,
+            Err(kind) => {
+                results.push(failed(kind.label()));
+                continue;
+            }
+        };
+        if project_conflict(&entry.name).is_some() {
+            results.push(failed(i18n::text("error.project_exists", &[&entry.name])));
+            continue;
+        }
+        if let Err(e) = check_project_size(&entry.project_bb, &ProjectLimits::current(), false) {
+            results.push(failed(i18n::error("error.project_size", e)));
+            continue;
+        }
+
+        let sink = BatchProgressSink {
+            index: entry.index,
+            total,
+            name: &entry.name,
+            on_progress,
+        };
+        let mut warnings: Vec<Warning> = Vec::new();
+        let outcome = match creation_layer_config(vegetation_source.clone(), None) {
+            Ok(layer_config) => {
+                create_project_pipeline(
+                    &entry.name,
+                    &entry.project_bb,
+                    &layer_config,
+                    guard.cancel_token(),
+                    &mut warnings,
+                    &sink,
+                    fetcher,
+                    RunConfig::snapshot(),
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        drop(guard);
+
+        let warning_messages = warnings.iter().map(Warning::to_string).collect();
+        let result = match outcome {
+            Ok(CreationOutcome::Created { folder, .. }) => {
+                let summary = format!(
+                    "Projet {} créé en {:.1} s (lot, entrée {}/{})",
+                    entry.name,
+                    start.elapsed().as_secs_f64(),
+                    entry.index,
+                    total
+                );
+                tracing::info!(project = %entry.name, "{}", summary);
+                if let Err(e) = write_creation_log(Path::new(&folder), &summary, &warnings) {
+                    tracing::warn!(
+                        project = %entry.name,
+                        error = ?e,
+                        "Erreur lors de l'écriture du journal de création"
+                    );
+                }
+                BatchResult {
+                    index: entry.index,
+                    name: entry.name.clone(),
+                    status: BatchStatus::Created,
+                    folder: Some(folder),
+                    message: None,
+                    warnings: warning_messages,
+                }
+            }
+            Ok(CreationOutcome::Cancelled) => BatchResult {
+                warnings: warning_messages,
+                ..BatchResult::rejected(
+                    entry.index,
+                    &entry.name,
+                    BatchStatus::Cancelled,
+                    i18n::text("error.creation_cancelled", &[]),
+                )
+            },
+            Err(message) => {
+                tracing::error!(project = %entry.name, error = %message, "Échec de la création du projet du lot");
+                BatchResult {
+                    warnings: warning_messages,
+                    ..failed(message)
+                }
+            }
+        };
+        results.push(result);
+    }
+    results
+}
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::i18n;
+
+/// Durée maximale d'attente de l'arrêt d'une tâche avant une suppression forcée.
+pub const FORCE_DELETE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Intervalle de vérification de la libération d'un projet.
+const RELEASE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tâche en cours sur un projet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BusyKind {
+    Creating,
+    Exporting,
+    RefreshingOrtho,
+    AddingLayer,
+    /// Projet source d'une duplication en cours.
+    Cloning,
+    /// Ancien ou nouveau nom d'un renommage en cours.
+    Renaming,
+    /// Projet en cours de suppression.
+    Deleting,
+}
+
+impl BusyKind {
+    /// Libellé de la tâche dans la langue de la configuration.
+    pub fn label(&self) -> String {
+        let key = match self {
+            BusyKind::Creating => "busy.creating",
+            BusyKind::Exporting => "busy.exporting",
+            BusyKind::RefreshingOrtho => "busy.refreshing_ortho",
+            BusyKind::AddingLayer => "busy.adding_layer",
+            BusyKind::Cloning => "busy.cloning",
+            BusyKind::Renaming => "busy.renaming",
+            BusyKind::Deleting => "busy.deleting",
+        };
+        i18n::text(key, &[])
+    }
+}
+
+/// Demande d'annulation partagée entre une tâche et le registre des projets occupés.
+/// La tâche la consulte entre ses étapes et s'arrête dès qu'elle est levée.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug)]
+struct BusyEntry {
+    kind: BusyKind,
+    cancel: CancelToken,
+}
+
+/// Registre des projets en cours de création, d'export ou de mise à jour,
+/// conservé dans l'état de l'application. Un projet occupé ne peut pas être supprimé.
+#[derive(Debug, Clone, Default)]
+pub struct BusyProjects {
+    projects: Arc<Mutex<HashMap<String, BusyEntry>>>,
+}
+
+/// Marque d'un projet occupé : le projet est libéré quand elle est détruite.
+#[derive(Debug)]
+pub struct BusyGuard {
+    registry: BusyProjects,
+    name: String,
+    cancel: CancelToken,
+}
+
+impl BusyGuard {
+    /// La demande d'annulation à consulter pendant la tâche.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        self.registry.projects.lock().unwrap().remove(&self.name);
+    }
+}
+
+impl BusyProjects {
+    /// Marque un projet comme occupé pendant la durée de vie de la marque retournée.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Le nom du projet.
+    /// * `kind` - La tâche lancée sur le projet.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BusyGuard, String>` - La marque, ou une erreur si le projet est déjà occupé.
+    pub fn acquire(&self, name: &str, kind: BusyKind) -> Result<BusyGuard, String> {
+        self.try_acquire(name, kind)
+            .map_err(|running| busy_error(name, running))
+    }
+
+    /// Comme `acquire`, mais renvoie la tâche déjà en cours plutôt qu'un message :
+    /// la vérification et l'inscription du projet se font sous le même verrou, si bien que
+    /// de deux demandes simultanées (double envoi du formulaire), une seule obtient la marque.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Le nom du projet.
+    /// * `kind` - La tâche lancée sur le projet.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BusyGuard, BusyKind>` - La marque, ou la tâche en cours si le projet est occupé.
+    pub fn try_acquire(&self, name: &str, kind: BusyKind) -> Result<BusyGuard, BusyKind> {
+        let mut projects = self.projects.lock().unwrap();
+        if let Some(entry) = projects.get(name) {
+            return Err(entry.kind);
+        }
+
+        let cancel = CancelToken::default();
+        projects.insert(
+            name.to_string(),
+            BusyEntry {
+                kind,
+                cancel: cancel.clone(),
+            },
+        );
+        Ok(BusyGuard {
+            registry: self.clone(),
+            name: name.to_string(),
+            cancel,
+        })
+    }
+
+    /// La tâche en cours sur un projet, s'il est occupé.
+    pub fn kind(&self, name: &str) -> Option<BusyKind> {
+        self.projects
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|entry| entry.kind)
+    }
+
+    /// Les projets occupés et leur tâche en cours.
+    pub fn busy_projects(&self) -> BTreeMap<String, BusyKind> {
+        self.projects
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.kind))
+            .collect()
+    }
+
+    /// Demande l'arrêt de la tâche en cours sur un projet.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Vrai si le projet était occupé.
+    pub fn request_cancel(&self, name: &str) -> bool {
+        match self.projects.lock().unwrap().get(name) {
+            Some(entry) => {
+                entry.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Attend qu'un projet soit libéré.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Vrai si le projet a été libéré avant l'expiration du délai.
+    pub async fn wait_until_released(&self, name: &str, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.kind(name).is_some() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(RELEASE_POLL_INTERVAL).await;
+        }
+        true
+    }
+
+    /// Réserve un projet pour sa suppression. Sans `force`, un projet occupé est refusé ;
+    /// avec `force`, la tâche en cours est annulée et son arrêt attendu. Le nom reste réservé
+    /// (`BusyKind::Deleting`) tant que la marque retournée n'est pas détruite : aucune
+    /// création, export ou duplication du même nom ne peut démarrer pendant la suppression.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Le nom du projet.
+    /// * `force` - Annuler la tâche en cours au lieu de refuser la suppression.
+    /// * `timeout` - Le délai d'attente de l'arrêt de la tâche.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BusyGuard, String>` - La marque de la suppression, ou une erreur si le projet
+    ///   est (toujours) occupé.
+    pub async fn reserve_for_deletion(
+        &self,
+        name: &str,
+        force: bool,
+        timeout: Duration,
+    ) -> Result<BusyGuard, String> {
+        let kind = match self.try_acquire(name, BusyKind::Deleting) {
+            Ok(guard) => return Ok(guard),
+            Err(kind) => kind,
+        };
+        if !force {
+            return Err(busy_error(name, kind));
+        }
+
+        tracing::info!(project = name, kind = ?kind, "Annulation demandée avant suppression");
+        self.request_cancel(name);
+        if self.wait_until_released(name, timeout).await {
+            self.acquire(name, BusyKind::Deleting)
+        } else {
+            Err(busy_error(name, kind))
+        }
+    }
+}
+
+/// Message d'erreur d'un projet occupé par la tâche `kind`.
+pub fn busy_error(name: &str, kind: BusyKind) -> String {
+    i18n::text("error.project_busy", &[name, &kind.label()])
+}
@@ -1,353 +1,882 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Instant,
+};
 
-use tauri::{Emitter, command};
-use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
-use tokio::fs;
+use serde::Serialize;
+use tauri::{Emitter, Manager, command};
 
 use crate::{
-    app_setup,
+    app_setup::{
+        self, ImageQuality, ProxyConfig, RunConfig, SavedSettings, SettingsUpdate, SetupReport,
+    },
+    batch::{self, BatchResult},
+    busy::{BusyKind, BusyProjects, FORCE_DELETE_TIMEOUT, busy_error},
+    data_updates::{self, DataUpdateStatus, DataUpdates},
+    diagnostics::{self, DiagnosticsReport},
+    estimate::{self, ProjectEstimate},
+    events::{
+        BATCH_PROGRESS_EVENT, BatchProgress, DATA_UPDATES_EVENT, EXPORT_PROGRESS_EVENT,
+        PROGRESS_EVENT, PROJECT_CREATED_EVENT, PROJECT_CREATION_FAILED_EVENT, ProgressUpdate,
+        ProjectCreated, ProjectCreationFailed, Warning, write_creation_log,
+    },
+    export_queue::{ExportJob, ExportQueue},
+    fetcher::data_fetcher,
     gis_operation::{
-        create_project, fusion_datasets,
-        layers::{add_layers, download_satellite_jpeg, prepare_layers},
-        regions::find_intersecting_regions,
+        colormap::load_rpg_classes,
+        create_project_in_crs,
+        creation::{self, CreationRequest, FirefrontError},
+        custom_layers::{self, CustomLayer},
+        derive::clip_project_resources,
+        gpkg_covers_bb,
+        identify::{self, PixelInfo},
+        layer_config::{LayerConfig, load_layer_config, user_layer_config_path},
+        layers::{add_layers, download_satellite_jpeg},
+        legend::{LegendEntry, build_legend},
+        ortho::sources_by_priority,
+        oso::VegetationSource,
+        qgis,
+        regions::{
+            REGIONS_GEOJSON_FILE, REGIONS_GRAPH_FILE, RegionDirectory, RegionsGraphReport,
+            build_regions_graph, load_regions_graph, set_regions_index,
+        },
+        report::{self, ViewMode},
+        tiles::{self, TileGrid},
     },
+    i18n::{self, Language},
+    logging::{LOG_LEVELS, logs_dir, recent_log_lines},
+    payloads::{CreateProjectArgs, ExportArgs, LanguageOption, SettingsPayload},
+    pipeline::{CreationOutcome, PIPELINE_STATE_FILE, PipelineState, run_creation_stages},
+    progress::{NoProgress, ProgressSink},
+    timings::StageTimings,
     utils::{
-        BoundingBox, cache_dir, clean_tmp_except_gpkg, create_directory_if_not_exists,
-        export_project, export_to_jpg, get_operating_system, get_previous_projects, projects_dir,
+        self, BoundingBox, CacheScope, CacheStatus, CleanupReport, ConflictMode, CreationParams,
+        ExportOptions, ExportReport, ExportTarget, ExportTargetInfo, GridSnap, PROJECT_GRID_PIXELS,
+        ProjectConflict, ProjectDetails, ProjectSort, ProjectSummary, SnapMode, WorkDir, cache_dir,
+        create_directory_if_not_exists, default_land_color, export_project_to, export_to_jpg,
+        generate_project_preview, get_operating_system, get_previous_projects,
+        load_project_metadata, mark_project_complete, open_in_file_manager, project_conflict,
+        project_dir, project_ortho_jpeg, project_tiff, project_veget_jpeg, projects_dir,
+        resource_gpkg, save_project_metadata, validate_project_name, verify_cache_archives,
     },
-    web_request::{download_shp_file, get_shp_file_urls},
+    web_request,
 };
 
+/// Réponse d'une création de projet. L'issue détaillée (durées, avertissements, erreur)
+/// est transmise par les événements de création.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CreationResponse {
+    Created {
+        folder: String,
+    },
+    Cancelled,
+    /// Un projet du même nom existe et `on_conflict` vaut `abort` : rien n'a été créé.
+    Conflict(ProjectConflict),
+    /// Le projet `source` contient l'emprise demandée et `allow_reuse` n'est pas renseigné :
+    /// rien n'a été créé.
+    ReuseAvailable {
+        source: String,
+    },
+    /// Une création ou une autre tâche est déjà en cours sous ce nom (par exemple un double
+    /// envoi du formulaire) : rien n'a été fait, la tâche en cours n'est pas perturbée.
+    InProgress {
+        kind: BusyKind,
+    },
+}
+
 #[command(rename_all = "snake_case")]
-/// Crée un projet avec les fichiers SHP associés.
-/// Télécharge les fichiers SHP nécessaires, crée un projet de carte,
-/// fusionne les couches et ajoute les couches au projet.
-/// Télécharge également une image satellite et l'exporte en JPEG.
-/// Nettoie les fichiers temporaires après la création du projet.
+/// Crée un projet avec `creation::create_project_pipeline`, qui reçoit la progression,
+/// et transmet l'issue de la création à l'interface par les événements de création.
 ///
 /// # Arguments
 ///
 /// * `app_handle` - Handle de l'application Tauri.
-/// * `name` - Nom du projet.
-/// * `project_bb` - Boîte englobante du projet.
+/// * `busy` - Les projets occupés de l'application.
+/// * `setup` - Le rapport de configuration : la commande est refusée s'il est incomplet.
+/// * `args` - Les paramètres de la création (`CreateProjectArgs`, partagé avec l'interface) :
+///   * `name` - Nom du projet, refusé s'il ne peut pas servir de nom de dossier.
+///   * `project_bb` - Boîte englobante du projet.
+///   * `vegetation_source` - Source de végétation : "bdforet" (par défaut), "oso" ou "file:<chemin>".
+///   * `on_conflict` - Si un projet du même nom existe : "abort" (par défaut) le décrit sans rien
+///     créer, "rename" crée le projet sous un nom libre et "overwrite" le remplace. Le projet
+///     remplacé n'est supprimé qu'une fois la nouvelle création terminée, et restauré en cas d'échec.
+///   * `layers` - Les couches retenues : `FORMATION_VEGETALE`, `PARCELLES_GRAPHIQUES` et les noms
+///     des couches BDTOPO (voir `get_layer_config`), toutes par défaut. Les archives sans couche
+///     retenue ne sont pas téléchargées.
+///   * `allow_reuse` - Si un projet terminé contient l'emprise demandée : non renseigné, le projet
+///     source est signalé sans rien créer ; `true` dérive le projet en découpant le projet source,
+///     sans téléchargement (voir `derive_project`) ; `false` lance la création complète.
+///   * `allow_oversize` - `true` accepte une emprise dépassant `max_project_area_km2` ou
+///     `max_project_regions` ; la limite dépassée est journalisée et enregistrée dans les
+///     métadonnées du projet. Sans dérogation, une telle emprise est refusée.
+///   * `template_project` - Projet dont les paramètres de création (voir `get_creation_params`)
+///     remplacent `vegetation_source` et `layers` non renseignés. La résolution et la source
+///     d'orthophoto restent celles des paramètres de l'application ; un écart est signalé.
 ///
 /// # Retourne
 ///
-/// * `Result<String, String>` - Chemin du dossier du projet créé ou un message d'erreur.
+/// * `Result<CreationResponse, String>` - Le dossier du projet créé, l'annulation, le projet
+///   existant en conflit, le projet réutilisable ou la tâche déjà en cours sous ce nom,
+///   ou un message d'erreur, avec un nom corrigé suggéré si le nom est refusé.
 pub async fn create_project_com(
     app_handle: tauri::AppHandle,
-    name: String,
-    project_bb: BoundingBox,
-) -> Result<String, String> {
-    let _ = app_handle.emit("progress-update", "Recherche des fichiers");
-
-    create_directory_if_not_exists("tmp")
-        .map_err(|e| format!("Erreur lors de la création du dossier tmp: {:?}", e))?;
-
-    let mut region_codes: Vec<String> = Vec::new();
-    match find_intersecting_regions(&project_bb) {
-        Ok(result) => {
-            if result.is_empty() {
-                return Err("La surface de travail est incorrecte".to_string());
-            } else {
-                for region in result {
-                    region_codes.push(region.code);
-                }
-            }
-        }
-        Err(_) => return Err("La surface de travail est incorrecte".to_string()),
-    }
-
-    let urls = get_shp_file_urls(&region_codes)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let _ = app_handle.emit("progress-update", "Téléchargement des données");
-
-    let file_types = ["BDTOPO", "BDFORET", "RPG"];
-    let total_downloads = urls.len();
-    let mut download_count = 0;
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    args: CreateProjectArgs,
+) -> Result<CreationResponse, String> {
+    let CreateProjectArgs {
+        name,
+        project_bb,
+        vegetation_source,
+        on_conflict,
+        layers,
+        allow_reuse,
+        allow_oversize,
+        template_project,
+    } = args;
 
-    for (code_index, code) in region_codes.iter().enumerate() {
-        for (file_type_index, file_type) in file_types.iter().enumerate() {
-            let url_index = code_index * 3 + file_type_index;
-            if url_index >= urls.len() {
-                break;
-            }
+    setup.read().unwrap().ensure_ready()?;
+    let request = CreationRequest {
+        params: CreationParams {
+            vegetation_source,
+            layers,
+            ..CreationParams::default()
+        },
+        on_conflict: on_conflict.unwrap_or_default(),
+        allow_reuse,
+        allow_oversize: allow_oversize.unwrap_or(false),
+        template_project,
+        busy: busy.inner().clone(),
+        ..CreationRequest::new(name, project_bb)
+    };
+    run_creation_request(&app_handle, request).await
+}
 
-            let url = &urls[url_index];
-            download_count += 1;
+/// Exécute une demande de création et transmet son issue à l'interface par les événements
+/// de création (voir `create_project_com`).
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `request` - La demande de création.
+///
+/// # Retourne
+///
+/// * `Result<CreationResponse, String>` - L'issue de la création, ou un message d'erreur.
+async fn run_creation_request(
+    app_handle: &tauri::AppHandle,
+    request: CreationRequest,
+) -> Result<CreationResponse, String> {
+    let start = Instant::now();
+    let mut warnings = Vec::new();
+    let name = request.name.clone();
+    let fetcher = data_fetcher();
+    let outcome =
+        creation::create_project_pipeline(request, &mut warnings, app_handle, fetcher.as_ref())
+            .await;
 
+    let outcome = match outcome {
+        // Sous un nom libre, le projet créé porte un autre nom que celui demandé.
+        Ok(summary) => {
+            let outcome = Ok(CreationOutcome::Created {
+                timings: load_project_metadata(&summary.name).timings,
+                folder: summary.folder,
+                regions: summary.regions,
+            });
+            return report_creation_outcome(app_handle, summary.name, start, outcome, &warnings);
+        }
+        Err(FirefrontError::Cancelled) => Ok(CreationOutcome::Cancelled),
+        Err(FirefrontError::Conflict(conflict)) => {
+            return Ok(CreationResponse::Conflict(conflict));
+        }
+        Err(FirefrontError::InProgress(kind)) => {
+            tracing::warn!(project = %name, kind = ?kind, "Création déjà en cours, demande ignorée");
+            return Ok(CreationResponse::InProgress { kind });
+        }
+        Err(FirefrontError::ReuseAvailable { source }) => {
+            return Ok(CreationResponse::ReuseAvailable { source });
+        }
+        Err(e @ FirefrontError::InvalidName(_)) => {
+            let message = e.to_string();
+            tracing::warn!(project = %name, error = %message, "Nom de projet refusé");
             let _ = app_handle.emit(
-                "progress-update",
-                format!(
-                    "Téléchargement des données|{}|{}/{}",
-                    file_type, download_count, total_downloads
-                ),
-            );
-
-            let cache_path = format!(
-                "{}/{}_{}.7z",
-                cache_dir().to_string_lossy(),
-                file_type,
-                code
+                PROJECT_CREATION_FAILED_EVENT,
+                ProjectCreationFailed {
+                    name,
+                    message: message.clone(),
+                    cancelled: false,
+                    resumable: false,
+                    warnings: Vec::new(),
+                },
             );
-            if !Path::new(&cache_path).exists() {
-                download_shp_file(url, code).await.map_err(|e| {
-                    format!(
-                        "Erreur lors du téléchargement du fichier SHP depuis {}: {:?}",
-                        url, e
-                    )
-                })?;
-            }
+            return Err(message);
         }
-    }
-
-    let _ = app_handle.emit("progress-update", "Initialisation du projet");
-    let project_folder = format!("{}/{}", projects_dir().to_string_lossy(), name);
-    let project_file_path = format!("{}/{}.tiff", project_folder, name);
-
-    if std::path::Path::new(&project_file_path).exists() {
-        let should_overwrite = app_handle
-            .dialog()
-            .message("project_exists")
-            .title("Project already exists")
-            .buttons(MessageDialogButtons::YesNo)
-            .blocking_show();
-
-        if !should_overwrite {
-            return Ok("Project creation cancelled".to_string());
+        Err(FirefrontError::Failed { project, message }) => {
+            return report_creation_outcome(app_handle, project, start, Err(message), &warnings);
         }
+        Err(e) => Err(e.to_string()),
+    };
+    report_creation_outcome(app_handle, name, start, outcome, &warnings)
+}
 
-        std::fs::remove_dir_all(&project_folder).unwrap();
-    }
+#[command(rename_all = "snake_case")]
+/// Obtient les paramètres de création d'un projet, pour en créer un semblable.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Retourne
+///
+/// * `Result<CreationParams, String>` - Les paramètres du projet ou un message d'erreur.
+pub fn get_creation_params(project_name: &str) -> Result<CreationParams, String> {
+    checked_project_name(project_name)?;
+    utils::project_creation_params(project_name)
+        .map_err(|e| i18n::error("error.creation_template", e))
+}
 
-    let _ = app_handle.emit(
-        "progress-update",
-        "Initialisation du projet|Création des dossiers|1/2",
+#[command(rename_all = "snake_case")]
+/// Crée une série de projets décrits dans un fichier CSV (`name,xmin,ymin,xmax,ymax`) ou
+/// une FeatureCollection GeoJSON (propriété `name`). Toutes les entrées sont validées avant
+/// la première création ; les projets sont ensuite créés l'un après l'autre, l'échec de l'un
+/// n'interrompant pas les suivants. L'avancement est émis avec l'événement `batch-progress`.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `busy` - Les projets occupés de l'application.
+/// * `setup` - Le rapport de configuration : la commande est refusée s'il est incomplet.
+/// * `definitions_path` - Le fichier de définitions des projets.
+/// * `vegetation_source` - Source de végétation de tous les projets, voir `create_project_com`.
+///
+/// # Retourne
+///
+/// * `Result<Vec<BatchResult>, String>` - Le résultat de chaque entrée dans l'ordre du fichier,
+///   ou une erreur si le fichier ne peut pas être lu.
+pub async fn create_projects_batch(
+    app_handle: tauri::AppHandle,
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    definitions_path: String,
+    vegetation_source: Option<String>,
+) -> Result<Vec<BatchResult>, String> {
+    setup.read().unwrap().ensure_ready()?;
+    let vegetation_source =
+        VegetationSource::parse(vegetation_source.as_deref().unwrap_or_default())?;
+    let definitions = batch::parse_batch_file(Path::new(&definitions_path))
+        .map_err(|e| i18n::error("error.batch_definitions", e))?;
+    let (entries, mut results) = batch::validate_batch(&definitions, utils::resolution());
+    tracing::info!(
+        entries = definitions.len(),
+        invalid = results.len(),
+        "Création par lot"
     );
-    std::fs::create_dir_all(&project_folder).map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(format!("{}/resources", project_folder)).map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(format!("{}/slices", project_folder)).map_err(|e| e.to_string())?;
 
-    let _ = app_handle.emit(
-        "progress-update",
-        "Initialisation du projet|Configuration du projet|2/2",
+    let fetcher = data_fetcher();
+    let emit = |progress: BatchProgress| {
+        let _ = app_handle.emit(BATCH_PROGRESS_EVENT, progress);
+    };
+    results.extend(
+        batch::run_batch(
+            &entries,
+            definitions.len(),
+            &vegetation_source,
+            &busy,
+            fetcher.as_ref(),
+            &emit,
+        )
+        .await,
     );
-    if let Err(e) = create_project(&project_file_path, &project_bb) {
-        return Err(format!("Erreur lors de la création du projet: {:?}", e));
-    }
-
-    let _ = app_handle.emit("progress-update", "Préparation des Couches");
+    results.sort_by_key(|result| result.index);
+    Ok(results)
+}
 
-    let mut regional_gpkgs: Vec<String> = Vec::new();
-    let mut vegetation_gpkgs: Vec<String> = Vec::new();
-    let mut rpg_gpkgs: Vec<String> = Vec::new();
-    let mut topo_gpkgs: HashMap<String, Vec<String>> = HashMap::new();
+#[command(rename_all = "snake_case")]
+/// Décrit le projet existant qui empêcherait la création d'un projet de ce nom,
+/// pour proposer de le remplacer ou de créer le projet sous un autre nom.
+///
+/// # Arguments
+///
+/// * `name` - Le nom du projet à créer.
+///
+/// # Retourne
+///
+/// * `Option<ProjectConflict>` - Le projet existant (date de création, taille, emprise
+///   et nom libre suggéré), ou `None` si le nom est libre ou invalide (la création le refusera).
+pub fn check_project_conflict(name: &str) -> Option<ProjectConflict> {
+    checked_project_name(name).ok()?;
+    project_conflict(name)
+}
 
-    let total_regions = region_codes.len();
-    for (idx, code) in region_codes.iter().enumerate() {
-        let _ = app_handle.emit(
-            "progress-update",
-            format!(
-                "Préparation des Couches|Traitement de la région {}|{}/{}",
-                code,
-                idx + 1,
-                total_regions
-            ),
-        );
+#[command(rename_all = "snake_case")]
+/// Reprend la création d'un projet interrompue, à partir de la première étape
+/// non terminée enregistrée dans `pipeline_state.json`.
+/// Les GPKG fusionnés et le raster du projet déjà présents sont réutilisés.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `busy` - Les projets occupés de l'application.
+/// * `setup` - Le rapport de configuration : la commande est refusée s'il est incomplet.
+/// * `project_name` - Nom du projet à reprendre.
+///
+/// # Retourne
+///
+/// * `Result<CreationResponse, String>` - Le dossier du projet, l'annulation ou la tâche déjà
+///   en cours sur le projet, ou un message d'erreur.
+pub async fn resume_project_creation(
+    app_handle: tauri::AppHandle,
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    project_name: String,
+) -> Result<CreationResponse, String> {
+    let start = Instant::now();
+    let mut warnings = Vec::new();
 
-        if idx > 0 {
-            if let Err(e) = clean_tmp_except_gpkg() {
-                return Err(format!(
-                    "Erreur lors du nettoyage des fichiers temporaires: {:?}",
-                    e
-                ));
-            }
+    setup.read().unwrap().ensure_ready()?;
+    checked_project_name(&project_name)?;
+    let busy_guard = match busy.try_acquire(&project_name, BusyKind::Creating) {
+        Ok(guard) => guard,
+        Err(kind) => return Ok(CreationResponse::InProgress { kind }),
+    };
+    let project_folder = project_dir(&project_name);
+    let outcome = match PipelineState::load(&project_folder) {
+        Ok(state) => {
+            let fetcher = data_fetcher();
+            run_creation_stages(
+                project_folder,
+                state,
+                busy_guard.cancel_token(),
+                &mut warnings,
+                &app_handle,
+                fetcher.as_ref(),
+                RunConfig::snapshot(),
+            )
+            .await
         }
+        Err(e) => Err(format!(
+            "{}: {}",
+            i18n::text("error.nothing_to_resume", &[&project_name]),
+            e
+        )),
+    };
+
+    report_creation_outcome(&app_handle, project_name, start, outcome, &warnings)
+}
 
-        let (r_gpkg, v_gpkg, rp_gpkg, t_gpkg) =
-            prepare_layers(&app_handle, &project_bb, code).await?;
+/// Refuse les noms de projet inutilisables comme nom de dossier, notamment ceux qui
+/// sortiraient du dossier des projets (`../..`).
+fn checked_project_name(name: &str) -> Result<(), String> {
+    validate_project_name(name)
+        .map(|_| ())
+        .map_err(|e| i18n::error("error.invalid_project_name", e))
+}
 
-        regional_gpkgs.push(r_gpkg);
-        vegetation_gpkgs.push(v_gpkg);
-        rpg_gpkgs.push(rp_gpkg);
+/// Émet l'événement de fin de création et complète le journal de création du projet.
+fn report_creation_outcome(
+    app_handle: &tauri::AppHandle,
+    name: String,
+    start: Instant,
+    outcome: Result<CreationOutcome, String>,
+    warnings: &[Warning],
+) -> Result<CreationResponse, String> {
+    let warning_messages: Vec<String> = warnings.iter().map(Warning::to_string).collect();
+    match outcome {
+        Ok(CreationOutcome::Created {
+            folder,
+            regions,
+            timings,
+        }) => {
+            let duration_seconds = start.elapsed().as_secs_f64();
+            let summary = format!(
+                "Projet {} créé en {:.1} s (départements: {}) [{}]",
+                name,
+                duration_seconds,
+                regions.join(", "),
+                timings.summary()
+            );
+            tracing::info!(project = %name, duration_seconds, "{}", summary);
+            if let Err(e) = write_creation_log(Path::new(&folder), &summary, warnings) {
+                tracing::warn!(
+                    project = %name,
+                    error = ?e,
+                    "Erreur lors de l'écriture du journal de création"
+                );
+            }
 
-        for (layer_name, paths) in t_gpkg {
-            topo_gpkgs.entry(layer_name).or_default().extend(paths);
+            // Conservé pour les interfaces qui suivent encore le message de progression.
+            let _ = app_handle.emit(PROGRESS_EVENT, ProgressUpdate::stage("stage.created"));
+            let _ = app_handle.emit(
+                PROJECT_CREATED_EVENT,
+                ProjectCreated::new(&name, &folder, duration_seconds, regions, warnings, timings),
+            );
+            Ok(CreationResponse::Created { folder })
+        }
+        Ok(CreationOutcome::Cancelled) => {
+            let _ = app_handle.emit(
+                PROJECT_CREATION_FAILED_EVENT,
+                ProjectCreationFailed {
+                    name,
+                    message: i18n::text("error.creation_cancelled", &[]),
+                    cancelled: true,
+                    resumable: false,
+                    warnings: warning_messages,
+                },
+            );
+            Ok(CreationResponse::Cancelled)
         }
+        Err(message) => {
+            tracing::error!(project = %name, error = %message, "Échec de la création du projet");
+            let project_folder = projects_dir().join(&name);
+            if project_folder.exists() {
+                let summary = format!("Échec de la création du projet {}: {}", name, message);
+                if let Err(e) = write_creation_log(&project_folder, &summary, warnings) {
+                    tracing::warn!(
+                        project = %name,
+                        error = ?e,
+                        "Erreur lors de l'écriture du journal de création"
+                    );
+                }
+            }
 
-        if let Err(e) = clean_tmp_except_gpkg() {
-            return Err(format!(
-                "Erreur lors du nettoyage des fichiers temporaires: {:?}",
-                e
-            ));
+            let _ = app_handle.emit(
+                PROJECT_CREATION_FAILED_EVENT,
+                ProjectCreationFailed {
+                    name,
+                    message: message.clone(),
+                    cancelled: false,
+                    resumable: project_folder.join(PIPELINE_STATE_FILE).exists(),
+                    warnings: warning_messages,
+                },
+            );
+            Err(message)
         }
     }
+}
 
-    create_directory_if_not_exists("tmp")
-        .map_err(|e| format!("Erreur lors de la création du dossier tmp: {:?}", e))?;
-
-    let _ = app_handle.emit(
-        "progress-update",
-        "Fusion des données|Fusion des régions|1/4",
-    );
+#[command]
+/// Obtient la liste des projets précédents.
+///
+/// # Retourne
+/// - HashMap<String, Vec<String>> : Une hashmap contenant le nom du projet, le chemin de son aperçu,
+///   son dossier, le résumé des millésimes des données utilisées, son état
+///   (`complete` ou `incomplete` si la création a été interrompue) et son emplacement
+///   (`local`, ou `external` pour un projet importé par référence, voir `import_project`).
+pub fn get_projects() -> HashMap<String, Vec<String>> {
+    get_previous_projects().unwrap()
+}
 
-    let regional_merged_gpkg = format!("{}/resources/{}.gpkg", project_folder, name);
-    let vegetation_merged_gpkg = format!("{}/resources/FORMATION_VEGETALE.gpkg", project_folder);
-    let rpg_merged_gpkg = format!("{}/resources/PARCELLES_GRAPHIQUES.gpkg", project_folder);
+#[command(rename_all = "snake_case")]
+/// Liste les projets de l'accueil avec leur date de création, leur taille, leurs départements
+/// et le centre de leur emprise, filtrés et triés côté backend.
+///
+/// # Paramètres
+/// - `sort_by` : critère de tri (`name` par défaut, `created_at`, `size` ou `department`).
+/// - `filter` : texte recherché dans le nom du projet, ou code de département.
+///
+/// # Retourne
+/// - Result<Vec<ProjectSummary>, String> : Les projets dans l'ordre demandé.
+pub fn list_projects(
+    sort_by: Option<ProjectSort>,
+    filter: Option<String>,
+) -> Result<Vec<ProjectSummary>, String> {
+    utils::list_projects(sort_by.unwrap_or_default(), filter.as_deref())
+        .map_err(|e| i18n::error("error.read_projects", e))
+}
 
-    if region_codes.len() > 1 {
-        let _ = app_handle.emit(
-            "progress-update",
-            "Fusion des données|Fusion des couches régionales|1/4",
-        );
-        if let Err(e) = fusion_datasets(&regional_gpkgs, &regional_merged_gpkg) {
-            return Err(format!(
-                "Erreur lors de la fusion des couches régionales: {:?}",
-                e
-            ));
-        }
+#[command(rename_all = "snake_case")]
+/// Obtient les informations d'un projet : date de création, source de végétation
+/// et millésimes des données IGN.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Retourne
+///
+/// * `Result<ProjectDetails, String>` - Les informations du projet ou un message d'erreur.
+pub fn get_project_details(project_name: &str) -> Result<ProjectDetails, String> {
+    checked_project_name(project_name)?;
+    utils::get_project_details(project_name).map_err(|e| i18n::error("error.read_project_info", e))
+}
 
-        let _ = app_handle.emit(
-            "progress-update",
-            "Fusion des données|Fusion des couches de végétation|2/4",
-        );
-        if let Err(e) = fusion_datasets(&vegetation_gpkgs, &vegetation_merged_gpkg) {
-            return Err(format!(
-                "Erreur lors de la fusion des couches de végétation: {:?}",
-                e
-            ));
-        }
+/// Prépare le dossier et les ressources d'un clone à partir des GeoPackages fusionnés
+/// du projet source, découpés sur la nouvelle emprise avec `clip_to_bb`.
+/// Aucune donnée n'est téléchargée.
+///
+/// # Arguments
+///
+/// * `source_name` - Le nom du projet source.
+/// * `new_name` - Le nom du nouveau projet.
+/// * `new_bb` - L'emprise du nouveau projet.
+/// * `run_config` - La configuration figée au début de la duplication (résolution, compression).
+///
+/// # Retourne
+///
+/// * `Ok(true)` - Les ressources ont été préparées et le raster du projet créé.
+/// * `Ok(false)` - Les ressources du projet source ne couvrent pas la nouvelle emprise.
+/// * `Err` - Une erreur lors de la découpe ou de la création du projet.
+pub fn clone_project_resources(
+    source_name: &str,
+    new_name: &str,
+    new_bb: &BoundingBox,
+    run_config: &RunConfig,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let source_resources = project_dir(source_name).join("resources");
+    let source_regional = source_resources.join(format!("{}.gpkg", source_name));
+    if !source_regional.exists() || !gpkg_covers_bb(&source_regional.to_string_lossy(), new_bb)? {
+        return Ok(false);
+    }
 
-        let _ = app_handle.emit(
-            "progress-update",
-            "Fusion des données|Fusion des couches RPG|3/4",
-        );
-        if let Err(e) = fusion_datasets(&rpg_gpkgs, &rpg_merged_gpkg) {
-            return Err(format!("Erreur lors de la fusion des couches RPG: {:?}", e));
-        }
+    let project_folder = projects_dir().join(new_name);
+    std::fs::create_dir_all(project_folder.join("resources"))?;
+    std::fs::create_dir_all(project_folder.join("slices"))?;
 
-        let _ = app_handle.emit(
-            "progress-update",
-            "Fusion des données|Fusion des couches topographiques|4/4",
-        );
+    create_project_in_crs(
+        &project_tiff(new_name).to_string_lossy(),
+        new_bb,
+        load_project_metadata(source_name).project_epsg(),
+        run_config,
+    )?;
+    clip_project_resources(source_name, new_name, new_bb)?;
 
-        let total_topo_layers = topo_gpkgs.len();
-        let mut topo_count = 1;
-        for (layer_name, paths) in &topo_gpkgs {
-            let _ = app_handle.emit(
-                "progress-update",
-                format!(
-                    "Fusion des données|Fusion de {}|{}/{}",
-                    layer_name, topo_count, total_topo_layers
-                ),
-            );
-            let topo_merged_path = format!("{}/resources/{}.gpkg", project_folder, layer_name);
-            if let Err(e) = fusion_datasets(paths, &topo_merged_path) {
-                return Err(format!(
-                    "Erreur lors de la fusion des couches topo {}: {:?}",
-                    layer_name, e
-                ));
-            }
-            topo_count += 1;
-        }
-    } else {
-        let _ = app_handle.emit(
-            "progress-update",
-            "Fusion des données|Copie des fichiers (une seule région)|1/1",
-        );
+    Ok(true)
+}
 
-        if let Err(e) = fs::rename(&regional_gpkgs[0], &regional_merged_gpkg).await {
-            return Err(format!(
-                "Erreur lors du renommage de la couche régionale: {:?}",
-                e
-            ));
-        }
+#[command(rename_all = "snake_case")]
+/// Duplique un projet existant.
+/// Sans nouvelle emprise, le dossier est copié et les fichiers portant le nom du projet renommés.
+/// Avec une nouvelle emprise, validée comme pour une création (alignement sur la grille et
+/// limites de taille), les couches fusionnées du projet source sont réutilisées si elles
+/// couvrent cette emprise ; sinon la création est lancée en reprenant les couches du projet
+/// source pour les départements qu'il couvre, et seules les archives des autres départements
+/// sont téléchargées (voir `pipeline::create_project_reusing`).
+/// Le nouveau nom et le projet source restent occupés pendant toute la duplication ; un clone
+/// interrompu par une erreur est supprimé.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `busy` - Les projets occupés de l'application.
+/// * `setup` - Le rapport de configuration : une duplication avec une nouvelle emprise est
+///   refusée s'il est incomplet.
+/// * `source_name` - Le nom du projet à dupliquer.
+/// * `new_name` - Le nom du nouveau projet.
+/// * `new_bb` - La nouvelle emprise, optionnelle.
+/// * `update_land_color` - Applique au clone la couleur de fond des nouveaux projets
+///   (`default_land_color`) ; sinon, les couches réutilisées gardent celle du projet source.
+///   Une création complète applique toujours la couleur des nouveaux projets.
+///
+/// # Retourne
+///
+/// * `Result<String, String>` - Chemin du dossier du nouveau projet ou un message d'erreur.
+pub async fn clone_project(
+    app_handle: tauri::AppHandle,
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    source_name: String,
+    new_name: String,
+    new_bb: Option<BoundingBox>,
+    update_land_color: Option<bool>,
+) -> Result<String, String> {
+    checked_project_name(&source_name)?;
+    checked_project_name(&new_name)?;
+    // Les deux noms sont réservés avant tout accès au disque : une autre tâche ne peut ni
+    // créer le nouveau projet, ni modifier ou supprimer le projet source pendant la copie.
+    let new_guard = busy.acquire(&new_name, BusyKind::Creating)?;
+    let _source_guard = busy.acquire(&source_name, BusyKind::Cloning)?;
+    if project_dir(&new_name).exists() {
+        return Err(i18n::text("error.project_exists", &[&new_name]));
+    }
+    let Some(new_bb) = new_bb else {
+        return utils::copy_project(&source_name, &new_name)
+            .map(|path| path.to_string_lossy().to_string())
+            .map_err(|e| i18n::error("error.copy_project", e));
+    };
+    setup.read().unwrap().ensure_ready()?;
 
-        if let Err(e) = fs::rename(&vegetation_gpkgs[0], &vegetation_merged_gpkg).await {
-            return Err(format!(
-                "Erreur lors du renommage de la couche de végétation: {:?}",
-                e
-            ));
-        }
+    let run_config = RunConfig::snapshot();
+    creation::check_project_extent(&new_name, &new_bb, run_config.resolution, false)
+        .map_err(|e| e.to_string())?;
+    let mut metadata = load_project_metadata(&source_name);
+    metadata.created_at = Some(chrono::Local::now().to_rfc3339());
+    metadata.timings = StageTimings::default();
+    metadata.size_limit_override = None;
+    if update_land_color.unwrap_or(false) {
+        metadata.land_color = Some(default_land_color());
+    }
+    let vegetation_source = VegetationSource::parse(&metadata.vegetation_source)?;
 
-        if let Err(e) = fs::rename(&rpg_gpkgs[0], &rpg_merged_gpkg).await {
-            return Err(format!(
-                "Erreur lors du renommage de la couche RPG: {:?}",
-                e
-            ));
+    let _ = app_handle.emit(
+        PROGRESS_EVENT,
+        ProgressUpdate::step("stage.init", "step.reuse_source", &[], 1, 2),
+    );
+    let result = match clone_project_resources(&source_name, &new_name, &new_bb, &run_config) {
+        Ok(true) => finish_clone(
+            &app_handle,
+            &source_name,
+            &new_name,
+            &new_bb,
+            &metadata,
+            &vegetation_source,
+            &run_config,
+        ),
+        Ok(false) => {
+            tracing::info!(
+                project = %new_name,
+                source = %source_name,
+                "Les données du projet source ne couvrent pas la nouvelle emprise, création des départements manquants"
+            );
+            // La création reprend la réservation du nouveau nom et supprime ses propres échecs.
+            let request = CreationRequest {
+                params: CreationParams {
+                    vegetation_source: Some(metadata.vegetation_source),
+                    layers: metadata.layers,
+                    ..CreationParams::default()
+                },
+                on_conflict: ConflictMode::Abort,
+                allow_reuse: Some(true),
+                busy: busy.inner().clone(),
+                reservation: Some(new_guard),
+                partial_source: Some(source_name.clone()),
+                ..CreationRequest::new(new_name.clone(), new_bb)
+            };
+            let response = run_creation_request(&app_handle, request).await?;
+            return match response {
+                CreationResponse::Created { folder } => Ok(folder),
+                CreationResponse::Cancelled => Ok("Project creation cancelled".to_string()),
+                CreationResponse::Conflict(_) => {
+                    Err(i18n::text("error.project_exists", &[&new_name]))
+                }
+                // `allow_reuse` étant renseigné, la création ne s'interrompt pas pour le signaler.
+                CreationResponse::ReuseAvailable { .. } => {
+                    Err(i18n::text("error.derive_project", &[]))
+                }
+                CreationResponse::InProgress { kind } => Err(busy_error(&new_name, kind)),
+            };
         }
+        Err(e) => Err(i18n::error("error.prepare_project", e)),
+    };
 
-        for (layer_name, paths) in &topo_gpkgs {
-            if !paths.is_empty() {
-                let topo_merged_path = format!("{}/resources/{}.gpkg", project_folder, layer_name);
-                if let Err(e) = fs::rename(&paths[0], &topo_merged_path).await {
-                    return Err(format!(
-                        "Erreur lors du renommage de la couche topo {}: {:?}",
-                        layer_name, e
-                    ));
-                }
+    if result.is_err() {
+        if let Err(e) = std::fs::remove_dir_all(project_dir(&new_name)) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(
+                    project = %new_name,
+                    error = ?e,
+                    "Impossible de supprimer le clone inachevé"
+                );
             }
         }
     }
+    result
+}
 
-    if let Err(e) = clean_tmp_except_gpkg() {
-        return Err(format!(
-            "Erreur lors du nettoyage des fichiers temporaires: {:?}",
-            e
-        ));
-    }
+/// Termine un clone dont les ressources ont été préparées par `clone_project_resources` :
+/// métadonnées, couches, images et marque de projet terminé.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `source_name` - Le nom du projet source.
+/// * `new_name` - Le nom du nouveau projet.
+/// * `new_bb` - L'emprise du nouveau projet.
+/// * `metadata` - Les métadonnées du nouveau projet, reprises du projet source.
+/// * `vegetation_source` - La source de végétation du projet source.
+/// * `run_config` - La configuration figée au début de la duplication.
+///
+/// # Retourne
+///
+/// * `Result<String, String>` - Chemin du dossier du nouveau projet ou un message d'erreur.
+fn finish_clone(
+    app_handle: &tauri::AppHandle,
+    source_name: &str,
+    new_name: &str,
+    new_bb: &BoundingBox,
+    metadata: &utils::ProjectMetadata,
+    vegetation_source: &VegetationSource,
+    run_config: &RunConfig,
+) -> Result<String, String> {
+    save_project_metadata(new_name, metadata).map_err(|e| i18n::error("error.save_metadata", e))?;
 
-    let _ = app_handle.emit("progress-update", "Ajout des Couches");
-    if let Err(e) = add_layers(&app_handle, &project_folder, &project_file_path, &name) {
-        return Err(format!("Erreur lors de l'ajout des couches: {:?}", e));
-    }
+    let work_dir = WorkDir::new(run_config, &format!("clone_{}", new_name))
+        .map_err(|e| i18n::error("error.work_dir", e))?;
+    let project_folder = project_dir(new_name).to_string_lossy().to_string();
+    let project_file_path = project_tiff(new_name).to_string_lossy().to_string();
+    // Le clone reprend la sélection de couches du projet source.
+    let layer_config = load_layer_config()
+        .map_err(|e| i18n::error("error.load_layer_config", e))?
+        .with_selection(metadata.layers.as_deref())
+        .map_err(|e| i18n::error("error.layer_selection", e))?;
+    let mut warnings = Vec::new();
 
-    let _ = app_handle.emit("progress-update", "Finalisation");
-    let _ = app_handle.emit("progress-update", "Finalisation|Export en JPEG|1/2");
-    if let Err(e) = export_to_jpg(
+    let _ = app_handle.emit(PROGRESS_EVENT, ProgressUpdate::stage("stage.add_layers"));
+    if let Err(e) = add_layers(
+        app_handle,
+        &work_dir,
+        &project_folder,
         &project_file_path,
-        format!("{}/{}_VEGET.jpeg", project_folder, name).as_str(),
+        new_name,
+        vegetation_source,
+        &layer_config,
+        &mut warnings,
+        run_config,
     ) {
-        return Err(format!("Erreur lors de l'exportation de l'image: {:?}", e));
+        return Err(i18n::error("error.add_layers", e));
     }
 
+    let _ = app_handle.emit(PROGRESS_EVENT, ProgressUpdate::stage("stage.finalize"));
     let _ = app_handle.emit(
-        "progress-update",
-        "Finalisation|Téléchargement d'orthophoto|2/2",
+        PROGRESS_EVENT,
+        ProgressUpdate::step("stage.finalize", "step.export_jpeg", &[], 1, 2),
+    );
+    if let Err(e) = export_to_jpg(&project_file_path, project_veget_jpeg(new_name), run_config) {
+        return Err(i18n::error("error.export_image", e));
+    }
+
+    let _ = app_handle.emit(
+        PROGRESS_EVENT,
+        ProgressUpdate::step("stage.finalize", "step.download_ortho", &[], 2, 2),
     );
     if let Err(e) = download_satellite_jpeg(
-        format!("{}/{}_ORTHO.jpeg", project_folder, name).as_str(),
-        &project_bb,
+        &work_dir,
+        &project_ortho_jpeg(new_name).to_string_lossy(),
+        new_bb,
+        metadata.project_epsg(),
+        Some(
+            resource_gpkg(Path::new(&project_folder), new_name)
+                .to_string_lossy()
+                .as_ref(),
+        ),
+        app_handle,
+        run_config,
     ) {
-        return Err(format!(
-            "Erreur lors du téléchargement de l'image satellite: {:?}",
-            e
-        ));
+        return Err(i18n::error("error.download_satellite", e));
     }
 
-    let _ = app_handle.emit("progress-update", "Nettoyage");
-    fs::remove_dir_all("tmp")
-        .await
-        .map_err(|e| format!("Erreur lors de la suppression du dossier tmp: {:?}", e))?;
+    if let Err(e) = generate_project_preview(new_name) {
+        warnings.push(Warning::new("Aperçu", e.to_string()));
+    }
+    if let Err(e) = mark_project_complete(Path::new(&project_folder)) {
+        return Err(i18n::error("error.mark_complete", e));
+    }
 
-    fs::create_dir("tmp")
-        .await
-        .map_err(|e| format!("Erreur lors de la création du dossier tmp: {:?}", e))?;
+    let summary = format!("Projet {} cloné depuis {}", new_name, source_name);
+    if let Err(e) = write_creation_log(Path::new(&project_folder), &summary, &warnings) {
+        tracing::warn!(
+            project = %new_name,
+            error = ?e,
+            "Erreur lors de l'écriture du journal de création"
+        );
+    }
 
-    let _ = app_handle.emit("progress-update", "Projet créé avec succès");
+    let _ = app_handle.emit(PROGRESS_EVENT, ProgressUpdate::stage("stage.created"));
 
     Ok(project_folder)
 }
 
-#[command]
-/// Obtient la liste des projets précédents.
+#[command(rename_all = "snake_case")]
+/// Met à jour l'orthophotographie d'un projet existant, sans reconstruire le raster de végétation.
+/// L'ancienne image est conservée si le téléchargement échoue.
+/// La progression est émise sur `progress-update`.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `busy` - Les projets occupés de l'application.
+/// * `setup` - Le rapport de configuration : la commande est refusée s'il est incomplet.
+/// * `project_name` - Le nom du projet.
 ///
 /// # Retourne
-/// - HashMap<String, Vec<String>> : Une hashmap contenant le nom du projet et la liste des fichiers associés.
-pub fn get_projects() -> HashMap<String, Vec<String>> {
-    get_previous_projects().unwrap()
+///
+/// * `Result<String, String>` - Le chemin de la nouvelle orthophotographie ou un message d'erreur.
+pub async fn refresh_ortho(
+    app_handle: tauri::AppHandle,
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    project_name: String,
+) -> Result<String, String> {
+    checked_project_name(&project_name)?;
+    setup.read().unwrap().ensure_ready()?;
+    let _busy_guard = busy.acquire(&project_name, BusyKind::RefreshingOrtho)?;
+    let on_progress = |step: &str, done: usize, total: usize| {
+        let _ = app_handle.emit(
+            PROGRESS_EVENT,
+            ProgressUpdate::step("stage.refresh_ortho", step, &[], done, total),
+        );
+    };
+    // Les nouvelles tentatives sont signalées sous l'étape `stage.finalize`, propre à la création.
+    let epsg = load_project_metadata(&project_name).project_epsg();
+    let regional_gpkg = resource_gpkg(&project_dir(&project_name), &project_name);
+    let regional_gpkg =
+        Some(regional_gpkg.to_string_lossy().to_string()).filter(|_| regional_gpkg.exists());
+    let run_config = RunConfig::snapshot();
+    let download = |work_dir: &WorkDir, output: &str, project_bb: &BoundingBox| {
+        download_satellite_jpeg(
+            work_dir,
+            output,
+            project_bb,
+            epsg,
+            regional_gpkg.as_deref(),
+            &NoProgress,
+            &run_config,
+        )
+    };
+    let result = utils::refresh_project_ortho(&project_name, &run_config, download, &on_progress);
+
+    match result {
+        Ok(ortho_path) => {
+            let _ = app_handle.emit(
+                PROGRESS_EVENT,
+                ProgressUpdate::stage("stage.ortho_refreshed"),
+            );
+            Ok(ortho_path.to_string_lossy().to_string())
+        }
+        Err(e) => Err(i18n::error("error.refresh_ortho", e)),
+    }
+}
+
+#[command(rename_all = "snake_case")]
+/// Ajoute une couche vectorielle de l'utilisateur (coupures de combustible, points d'eau...)
+/// à un projet existant : la couche est découpée à l'emprise du projet, rastérisée avec sa
+/// couleur et enregistrée dans les métadonnées pour être réappliquée à la régénération.
+///
+/// # Arguments
+///
+/// * `busy` - Les projets occupés de l'application.
+/// * `setup` - Le rapport de configuration : la commande est refusée s'il est incomplet.
+/// * `project_name` - Le nom du projet.
+/// * `file_path` - Le fichier vectoriel (GeoPackage, Shapefile...).
+/// * `color` - La couleur de la couche.
+/// * `priority` - L'ordre d'application de la couche par rapport aux couches du projet.
+/// * `filter` - Le filtre attributaire optionnel des entités à rastériser.
+///
+/// # Retourne
+///
+/// * `Result<CustomLayer, String>` - La couche ajoutée ou un message d'erreur.
+pub async fn add_custom_layer(
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    project_name: String,
+    file_path: String,
+    color: [u8; 3],
+    priority: u8,
+    filter: Option<String>,
+) -> Result<CustomLayer, String> {
+    checked_project_name(&project_name)?;
+    setup.read().unwrap().ensure_ready()?;
+    let _busy_guard = busy.acquire(&project_name, BusyKind::AddingLayer)?;
+    custom_layers::add_custom_layer(
+        &project_name,
+        Path::new(&file_path),
+        color,
+        priority,
+        filter.as_deref(),
+    )
+    .map_err(|e| i18n::error("error.add_custom_layer", e))
 }
 
 #[command]
@@ -356,55 +885,473 @@ pub fn get_os() -> String {
 }
 
 #[command(rename_all = "snake_case")]
-/// Exporte un projet, fais la decoupe puis le zip
+/// Exporte un projet, fais la decoupe puis le zip (ou la disposition du simulateur)
+/// Le dossier d'export est vérifié avant la découpe ; en cas d'échec, l'interface peut
+/// relancer l'export vers un autre dossier avec `override_output`.
+/// La progression de la compression est émise sur `progress-update`.
 ///
 /// # Paramètres
-/// - project_name: &str : Le nom du projet à exporter.
+/// - app_handle: tauri::AppHandle : Handle de l'application Tauri.
+/// - busy: tauri::State<BusyProjects> : Les projets occupés de l'application.
+/// - setup: tauri::State<RwLock<SetupReport>> : Le rapport de configuration, l'export est refusé s'il est incomplet.
+/// - args: ExportArgs : Le projet à exporter, le format d'export (zip par défaut), le dossier
+///   d'export à utiliser à la place de celui des paramètres et le contenu optionnel de l'archive zip.
 ///
 /// # Retourne
-/// - Result<String, String> : Un résultat contenant le message de succès ou l'erreur.
-pub fn export(project_name: &str) -> Result<String, String> {
-    match export_project(project_name) {
-        Ok(_) => {
-            println!("Exportation réussie");
-            Ok("success".to_string())
+/// - Result<ExportReport, String> : Le chemin de l'archive ou du dossier créé, ses fichiers, leur taille et la durée de l'export, ou l'erreur.
+pub fn export(
+    app_handle: tauri::AppHandle,
+    busy: tauri::State<'_, BusyProjects>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    args: ExportArgs,
+) -> Result<ExportReport, String> {
+    let ExportArgs {
+        project_name,
+        target,
+        override_output,
+        options,
+    } = args;
+    let project_name = project_name.as_str();
+    setup.read().unwrap().ensure_ready()?;
+    checked_project_name(project_name)?;
+    let _busy_guard = busy.acquire(project_name, BusyKind::Exporting)?;
+    let output_dir = override_output
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(utils::output_location);
+    let on_progress = |done: usize, total: usize| {
+        let _ = app_handle.emit(
+            PROGRESS_EVENT,
+            ProgressUpdate::step("stage.export", "step.compression", &[], done, total),
+        );
+    };
+    match export_project_to(
+        project_name,
+        target,
+        &options,
+        &output_dir,
+        Some(&on_progress),
+    ) {
+        Ok(report) => {
+            tracing::info!(
+                project = project_name,
+                file = %report.zip_path.display(),
+                files = report.files.len(),
+                total_bytes = report.total_bytes,
+                duration = report.duration,
+                "Exportation réussie"
+            );
+            Ok(report)
         }
         Err(e) => {
-            println!("Erreur lors de l'exportation: {:?}", e);
-            Err("error".to_string())
+            tracing::error!(project = project_name, error = %e, "Erreur lors de l'exportation");
+            Err(i18n::error("error.export", e))
         }
     }
 }
 
 #[command(rename_all = "snake_case")]
-/// Supprime un projet existant.
+/// Ajoute des projets à la file d'exports, traitée un projet après l'autre par une tâche
+/// de fond vers le dossier d'export des paramètres. Chaque changement d'état d'un export
+/// est émis sur `export-progress`.
 ///
-/// # Arguments
-///
-/// * `project_name` - Le nom du projet à supprimer.
+/// # Paramètres
+/// - app_handle: tauri::AppHandle : Handle de l'application Tauri.
+/// - queue: tauri::State<ExportQueue> : La file d'exports de l'application.
+/// - setup: tauri::State<RwLock<SetupReport>> : Le rapport de configuration, les exports sont refusés s'il est incomplet.
+/// - project_names: Vec<String> : Les projets à exporter, dans l'ordre de traitement.
+/// - target: Option<ExportTarget> : Le format d'export, zip par défaut.
+/// - options: Option<ExportOptions> : Le contenu optionnel des archives zip.
 ///
 /// # Retourne
-///
-/// * `Ok(String)` - "success" si la suppression a réussi.
-/// * `Err(String)` - Un message d'erreur descriptif en cas de problème.
-pub async fn delete_project(project_name: &str) -> Result<String, String> {
-    let project_folder = format!("{}/{}", projects_dir().to_string_lossy(), project_name);
-    if !std::path::Path::new(&project_folder).exists() {
-        return Err(format!("Le projet '{}' n'existe pas", project_name));
+/// - Result<Vec<ExportJob>, String> : Les exports de la file, ou une erreur si un projet n'existe pas.
+pub fn queue_export(
+    app_handle: tauri::AppHandle,
+    queue: tauri::State<'_, ExportQueue>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    project_names: Vec<String>,
+    target: Option<ExportTarget>,
+    options: Option<ExportOptions>,
+) -> Result<Vec<ExportJob>, String> {
+    setup.read().unwrap().ensure_ready()?;
+    for name in &project_names {
+        checked_project_name(name).map_err(|e| i18n::error("error.queue_export", e))?;
     }
-
-    match tokio::fs::remove_dir_all(&project_folder).await {
-        Ok(_) => {
-            println!("Projet '{}' supprimé avec succès", project_name);
-            Ok("success".to_string())
-        }
-        Err(e) => {
-            println!(
-                "Erreur lors de la suppression du projet '{}': {:?}",
-                project_name, e
-            );
-            Err(format!("Erreur lors de la suppression du projet: {}", e))
-        }
+    if let Some(missing) = project_names
+        .iter()
+        .find(|name| !project_dir(name).is_dir())
+    {
+        return Err(i18n::error(
+            "error.queue_export",
+            i18n::text("error.project_not_found", &[missing]),
+        ));
+    }
+
+    for progress in queue.enqueue(
+        &project_names,
+        target.unwrap_or_default(),
+        options.unwrap_or_default(),
+    ) {
+        let _ = app_handle.emit(EXPORT_PROGRESS_EVENT, progress);
+    }
+
+    if queue.claim_worker() {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let queue = app_handle.state::<ExportQueue>();
+            let busy = app_handle.state::<BusyProjects>();
+            queue.run_worker(
+                |job| {
+                    let _busy_guard = busy.acquire(&job.project, BusyKind::Exporting)?;
+                    export_project_to(
+                        &job.project,
+                        job.target,
+                        &job.options,
+                        &utils::output_location(),
+                        None,
+                    )
+                    .map(|report| report.zip_path)
+                    .map_err(|e| e.to_string())
+                },
+                |progress| {
+                    tracing::info!(
+                        project = %progress.project,
+                        state = ?progress.state,
+                        "Export de la file"
+                    );
+                    let _ = app_handle.emit(EXPORT_PROGRESS_EVENT, progress);
+                },
+            );
+        });
+    }
+
+    Ok(queue.jobs())
+}
+
+#[command]
+/// Liste les exports de la file : en attente, en cours et terminés.
+///
+/// # Retourne
+/// - Vec<ExportJob> : Les exports dans leur ordre de traitement.
+pub fn get_export_queue(queue: tauri::State<'_, ExportQueue>) -> Vec<ExportJob> {
+    queue.jobs()
+}
+
+#[command]
+/// Annule l'export d'un projet qui n'a pas encore commencé.
+///
+/// # Paramètres
+/// - app_handle: tauri::AppHandle : Handle de l'application Tauri.
+/// - queue: tauri::State<ExportQueue> : La file d'exports de l'application.
+/// - project: String : Le nom du projet.
+///
+/// # Retourne
+/// - Result<Vec<ExportJob>, String> : Les exports de la file, ou une erreur si l'export est déjà en cours.
+pub fn cancel_export(
+    app_handle: tauri::AppHandle,
+    queue: tauri::State<'_, ExportQueue>,
+    project: String,
+) -> Result<Vec<ExportJob>, String> {
+    checked_project_name(&project)?;
+    let progress = queue
+        .cancel(&project)
+        .map_err(|e| i18n::error("error.cancel_export", e))?;
+    let _ = app_handle.emit(EXPORT_PROGRESS_EVENT, progress);
+    Ok(queue.jobs())
+}
+
+#[command(rename_all = "snake_case")]
+/// Exporte une carte imprimable (PDF) d'un projet dans le dossier d'export :
+/// image du projet, cartouche, barre d'échelle, flèche du nord, emprise et légende.
+///
+/// # Arguments
+/// - project_name: &str : Le nom du projet.
+/// - view: ViewMode : L'image à mettre en page (végétation ou orthophoto).
+///
+/// # Retourne
+/// - Result<String, String> : Le chemin du PDF créé, ou l'erreur.
+pub fn export_map_pdf(project_name: &str, view: ViewMode) -> Result<String, String> {
+    checked_project_name(project_name)?;
+    report::export_map_pdf(project_name, view)
+        .map(|pdf_path| pdf_path.to_string_lossy().to_string())
+        .map_err(|e| i18n::error("error.export_pdf", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Écrit le projet QGIS d'un projet (`<nom>.qgs` dans son dossier) : GeoTIFF, raster de classes,
+/// GeoPackages des ressources et orthophotographie, stylés avec les couleurs du projet.
+///
+/// # Arguments
+/// - project_name: &str : Le nom du projet.
+///
+/// # Retourne
+/// - Result<String, String> : Le chemin du projet QGIS écrit, ou l'erreur.
+pub fn generate_qgis_project(project_name: &str) -> Result<String, String> {
+    checked_project_name(project_name)?;
+    qgis::generate_qgis_project(project_name)
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| i18n::error("error.qgis_project", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Donne la pyramide de tuiles d'une image de projet, pour l'affichage tuilé de la carte.
+///
+/// # Arguments
+/// - project_name: &str : Le nom du projet.
+/// - kind: ViewMode : L'image du projet (végétation ou orthophoto).
+///
+/// # Retourne
+/// - Result<TileGrid, String> : Les dimensions de l'image et son niveau de zoom maximal, ou l'erreur.
+pub fn get_tile_grid(project_name: &str, kind: ViewMode) -> Result<TileGrid, String> {
+    checked_project_name(project_name)?;
+    tiles::project_tile_grid(project_name, kind).map_err(|e| i18n::error("error.project_tile", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Renvoie une tuile de 256 × 256 pixels d'une image de projet (PNG pour la végétation,
+/// JPEG pour l'orthophoto), découpée à la demande et conservée dans le dossier `.tiles` du projet.
+///
+/// # Arguments
+/// - project_name: &str : Le nom du projet.
+/// - kind: ViewMode : L'image du projet (végétation ou orthophoto).
+/// - z: u32 : Le niveau de zoom, 0 pour l'image entière dans une tuile.
+/// - x: usize : La colonne de la tuile.
+/// - y: usize : La ligne de la tuile.
+///
+/// # Retourne
+/// - Result<String, String> : Le chemin de la tuile, ou l'erreur.
+pub fn get_project_tile(
+    project_name: &str,
+    kind: ViewMode,
+    z: u32,
+    x: usize,
+    y: usize,
+) -> Result<String, String> {
+    checked_project_name(project_name)?;
+    tiles::get_project_tile(project_name, kind, z, x, y)
+        .map(|tile| tile.path.to_string_lossy().to_string())
+        .map_err(|e| i18n::error("error.project_tile", e))
+}
+
+#[command]
+/// Liste les formats d'export disponibles.
+///
+/// # Retourne
+/// - Vec<ExportTargetInfo> : Les formats d'export et leur libellé.
+pub fn list_export_targets() -> Vec<ExportTargetInfo> {
+    ExportTarget::ALL
+        .iter()
+        .map(|target| ExportTargetInfo {
+            target: *target,
+            label: target.label().to_string(),
+        })
+        .collect()
+}
+
+#[command(rename_all = "snake_case")]
+/// Ouvre le dossier d'un projet dans le gestionnaire de fichiers du système.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Retourne
+///
+/// * `Ok(String)` - "success" si le gestionnaire de fichiers a été lancé.
+/// * `Err(String)` - Un message d'erreur si le dossier n'existe plus ou n'a pas pu être ouvert.
+pub fn open_project_folder(project_name: &str) -> Result<String, String> {
+    checked_project_name(project_name)?;
+    let project_folder = project_dir(project_name);
+    open_in_file_manager(&project_folder, false)
+        .map(|_| "success".to_string())
+        .map_err(|e| i18n::error("error.open_folder", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Affiche une archive exportée dans le gestionnaire de fichiers du système.
+///
+/// # Arguments
+///
+/// * `path` - Le chemin de l'archive retourné par `export`.
+///
+/// # Retourne
+///
+/// * `Ok(String)` - "success" si le gestionnaire de fichiers a été lancé.
+/// * `Err(String)` - Un message d'erreur si l'archive n'existe plus ou n'a pas pu être affichée.
+pub fn reveal_export(path: &str) -> Result<String, String> {
+    open_in_file_manager(Path::new(path), true)
+        .map(|_| "success".to_string())
+        .map_err(|e| i18n::error("error.reveal_export", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Supprime un projet existant.
+/// Un projet en cours de création, d'export ou de mise à jour n'est pas supprimé, sauf avec
+/// `force` : la création est alors annulée avant sa prochaine étape, et la suppression attend
+/// la fin de la tâche en cours.
+/// Un projet externe (voir `import_project`) est seulement retiré du registre des projets
+/// externes ; son dossier n'est supprimé qu'avec `delete_files`.
+///
+/// # Arguments
+///
+/// * `busy` - Les projets occupés de l'application.
+/// * `project_name` - Le nom du projet à supprimer.
+/// * `force` - Annuler la tâche en cours sur le projet au lieu de refuser la suppression.
+/// * `delete_files` - Supprimer aussi le dossier d'un projet externe.
+///
+/// # Retourne
+///
+/// * `Ok(String)` - "success" si la suppression a réussi.
+/// * `Err(String)` - Un message d'erreur descriptif en cas de problème, notamment si le projet est occupé.
+pub async fn delete_project(
+    busy: tauri::State<'_, BusyProjects>,
+    project_name: &str,
+    force: Option<bool>,
+    delete_files: Option<bool>,
+) -> Result<String, String> {
+    // Un nom comme `../..` désignerait un dossier hors du dossier des projets.
+    checked_project_name(project_name)?;
+    let external = utils::external_project_dir(project_name).is_some();
+    let project_folder = project_dir(project_name);
+    if !external && !project_folder.exists() {
+        return Err(i18n::text("error.project_not_found", &[project_name]));
+    }
+
+    // Le nom reste réservé jusqu'à la fin de la suppression et de la mise à jour du registre.
+    let _guard = busy
+        .reserve_for_deletion(project_name, force.unwrap_or(false), FORCE_DELETE_TIMEOUT)
+        .await?;
+
+    if external && !delete_files.unwrap_or(false) {
+        return utils::unregister_external_project(project_name)
+            .map(|_| {
+                tracing::info!(project = project_name, "Projet externe retiré de la liste");
+                "success".to_string()
+            })
+            .map_err(|e| i18n::error("error.delete_project", e));
+    }
+
+    match tokio::fs::remove_dir_all(&project_folder).await {
+        Ok(_) => {
+            if external {
+                utils::unregister_external_project(project_name)
+                    .map_err(|e| i18n::error("error.delete_project", e))?;
+            }
+            tracing::info!(project = project_name, "Projet supprimé");
+            Ok("success".to_string())
+        }
+        Err(e) => {
+            tracing::error!(
+                project = project_name,
+                error = %e,
+                "Erreur lors de la suppression du projet"
+            );
+            Err(i18n::error("error.delete_project", e))
+        }
+    }
+}
+
+#[command(rename_all = "snake_case")]
+/// Importe un projet existant depuis un dossier quelconque, par exemple un lecteur réseau partagé.
+/// Le dossier doit contenir `<nom du dossier>.tiff` en Lambert-93 ; les images de végétation
+/// et d'orthophoto sont facultatives. Par défaut, le dossier reste en place et le projet est
+/// inscrit au registre des projets externes, listé avec les autres projets.
+///
+/// # Arguments
+///
+/// * `path` - Le dossier du projet.
+/// * `mode` - `reference` (par défaut), `copy` ou `move` pour le placer dans le dossier des projets.
+///
+/// # Retourne
+///
+/// * `Result<String, String>` - Le nom du projet importé ou un message d'erreur.
+pub fn import_project(path: String, mode: Option<utils::ImportMode>) -> Result<String, String> {
+    let mode = mode.unwrap_or_default();
+    match utils::import_project(Path::new(&path), mode) {
+        Ok((name, folder)) => {
+            tracing::info!(project = %name, folder = %folder.display(), ?mode, "Projet importé");
+            Ok(name)
+        }
+        Err(e) => {
+            tracing::error!(folder = %path, error = %e, "Erreur lors de l'import du projet");
+            Err(i18n::error("error.import_project", e))
+        }
+    }
+}
+
+#[command(rename_all = "snake_case")]
+/// Obtient les durées des étapes de la création d'un projet, enregistrées dans ses métadonnées.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Retourne
+///
+/// * `Result<StageTimings, String>` - Les durées en secondes par étape (`download`, `prepare`...)
+///   et par détail d'étape (`download/BDTOPO 2A`...), vides pour un projet créé avant leur mesure.
+pub fn get_timings(project_name: &str) -> Result<StageTimings, String> {
+    checked_project_name(project_name)?;
+    if !project_dir(project_name).is_dir() {
+        return Err(i18n::text("error.project_not_found", &[project_name]));
+    }
+    Ok(load_project_metadata(project_name).timings)
+}
+
+#[command]
+/// Liste les projets en cours de création, d'export ou de mise à jour,
+/// qui ne peuvent pas être supprimés.
+///
+/// # Retourne
+/// - BTreeMap<String, BusyKind> : Le nom de chaque projet occupé et sa tâche en cours.
+pub fn get_busy_projects(busy: tauri::State<'_, BusyProjects>) -> BTreeMap<String, BusyKind> {
+    busy.busy_projects()
+}
+
+#[command(rename_all = "snake_case")]
+/// Renomme un projet existant ainsi que tous les fichiers portant son nom.
+/// Un projet occupé (création, export, duplication...) n'est pas renommé, et le nouveau nom
+/// reste réservé pendant le renommage.
+///
+/// # Arguments
+///
+/// * `busy` - Les projets occupés de l'application.
+/// * `old_name` - Le nom actuel du projet.
+/// * `new_name` - Le nouveau nom du projet.
+///
+/// # Retourne
+///
+/// * `Ok(String)` - "success" si le renommage a réussi.
+/// * `Err(String)` - Un message d'erreur si le nom est invalide, déjà pris, si l'un des deux
+///   projets est occupé ou si le renommage a échoué.
+pub fn rename_project(
+    busy: tauri::State<'_, BusyProjects>,
+    old_name: &str,
+    new_name: &str,
+) -> Result<String, String> {
+    checked_project_name(old_name)?;
+    checked_project_name(new_name)?;
+    let _old_guard = busy.acquire(old_name, BusyKind::Renaming)?;
+    // Un renommage vers le même nom est refusé par `utils::rename_project`.
+    let _new_guard = if new_name != old_name {
+        Some(busy.acquire(new_name, BusyKind::Renaming)?)
+    } else {
+        None
+    };
+    match utils::rename_project(old_name, new_name) {
+        Ok(_) => {
+            tracing::info!(project = old_name, new_name, "Projet renommé");
+            Ok("success".to_string())
+        }
+        Err(e) => {
+            tracing::error!(
+                project = old_name,
+                new_name,
+                error = %e,
+                "Erreur lors du renommage du projet"
+            );
+            Err(i18n::error("error.rename_project", e))
+        }
     }
 }
 
@@ -412,65 +1359,564 @@ pub async fn delete_project(project_name: &str) -> Result<String, String> {
 /// Récupère les paramètres de configuration de l'application.
 ///
 /// # Retourne
-/// - `Result<serde_json::Value, String>` : Un objet JSON contenant les paramètres de configuration ou une erreur.
-pub fn get_settings() -> Result<serde_json::Value, String> {
+/// - `Result<SettingsPayload, String>` : Les paramètres de configuration ou une erreur.
+pub fn get_settings() -> Result<SettingsPayload, String> {
     let config = app_setup::CONFIG.lock().unwrap();
-    let output_location = config.output_location.to_string_lossy().to_string();
-    let gdal_path = config
-        .gdal_path
-        .as_ref()
-        .map(|p| p.to_string_lossy().to_string());
-    let python_path = config
-        .python_path
-        .as_ref()
-        .map(|p| p.to_string_lossy().to_string());
+    let proxy = config.proxy.as_ref();
+    let ortho_sources: Vec<String> = sources_by_priority(&config.ortho_sources)
+        .into_iter()
+        .map(|source| source.name.clone())
+        .collect();
 
-    Ok(serde_json::json!({
-        "output_location": output_location,
-        "gdal_path": gdal_path,
-        "python_path": python_path,
-    }))
+    Ok(SettingsPayload {
+        output_location: config.output_location.to_string_lossy().to_string(),
+        gdal_path: config
+            .gdal_path
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string()),
+        python_path: config
+            .python_path
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string()),
+        proxy_url: proxy.map(|proxy| proxy.url.clone()),
+        proxy_username: proxy.and_then(|proxy| proxy.username.clone()),
+        proxy_password: proxy.and_then(|proxy| proxy.password.clone()),
+        no_proxy: proxy.map(|proxy| proxy.no_proxy.join(", ")),
+        preferred_ortho_source: ortho_sources.first().cloned(),
+        ortho_sources,
+        log_level: config.log_level.clone(),
+        log_levels: LOG_LEVELS.iter().map(|level| level.to_string()).collect(),
+        language: config.language.code().to_string(),
+        languages: Language::ALL
+            .iter()
+            .map(|language| LanguageOption {
+                code: language.code().to_string(),
+                label: language.label().to_string(),
+            })
+            .collect(),
+        image_quality: config.image_quality.clone(),
+        resolution: config.resolution,
+        slice_factor: config.slice_factor,
+        temp_dir: config.temp_dir.to_string_lossy().to_string(),
+        offline_mode: config.offline_mode,
+        oso_url: config.oso_url.clone(),
+    })
 }
 
 #[command(rename_all = "snake_case")]
 /// Enregistre les paramètres de configuration de l'application.
-///     
+/// La résolution, le facteur de découpe et le dossier temporaire ne sont pas modifiés
+/// tant qu'un projet est en cours de traitement : ils sont listés dans `blocked`.
+///
 /// # Arguments
 ///
+/// * `busy` - tauri::State<BusyProjects> : Les projets en cours de traitement.
 /// * `output_location` - Option<String> : L'emplacement de sortie.
 /// * `gdal_path` - Option<String> : Le chemin vers GDAL.
 /// * `python_path` - Option<String> : Le chemin vers Python.
+/// * `proxy_url` - Option<String> : L'URL du proxy HTTP(S), aucun proxy si vide.
+/// * `proxy_username` - Option<String> : L'identifiant du proxy.
+/// * `proxy_password` - Option<String> : Le mot de passe du proxy.
+/// * `no_proxy` - Option<String> : Les hôtes à joindre sans proxy, séparés par des virgules.
+/// * `preferred_ortho_source` - Option<String> : Le nom de la source d'orthophotos à essayer en premier.
+/// * `log_level` - Option<String> : Le niveau des journaux, appliqué au prochain démarrage.
+/// * `language` - Option<String> : La langue des messages (`fr`, `en`).
+/// * `image_quality` - Option<ImageQuality> : Les qualités JPEG (1-100) et la compression du GeoTIFF.
+/// * `resolution` - Option<f64> : La résolution des projets, en mètres par pixel.
+/// * `slice_factor` - Option<u32> : La taille des découpes, en pixels.
+/// * `temp_dir` - Option<String> : Le dossier des fichiers temporaires.
+/// * `offline_mode` - Option<bool> : Créer les projets à partir du cache, sans accès réseau.
+/// * `oso_url` - Option<String> : L'URL du GeoTIFF OSO de la source de végétation `oso`, aucune si vide.
 ///
 /// # Retourne
 ///
-/// * `String` : Un message de succès ou d'erreur.
+/// * `Result<SavedSettings, String>` : Le message à afficher et les paramètres bloqués, ou l'erreur.
+#[allow(clippy::too_many_arguments)]
 pub fn save_settings(
+    busy: tauri::State<'_, BusyProjects>,
     output_location: Option<String>,
     gdal_path: Option<String>,
     python_path: Option<String>,
-) -> String {
-    let mut config = app_setup::CONFIG.lock().unwrap();
-    match config.update_settings(output_location, gdal_path, python_path) {
-        Ok(_) => "Paramètres sauvegardés avec succès".to_string(),
-        Err(e) => {
-            format!("Échec de sauvegarde des paramètres: {}", e)
+    proxy_url: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    no_proxy: Option<String>,
+    preferred_ortho_source: Option<String>,
+    log_level: Option<String>,
+    language: Option<String>,
+    image_quality: Option<ImageQuality>,
+    resolution: Option<f64>,
+    slice_factor: Option<u32>,
+    temp_dir: Option<String>,
+    offline_mode: Option<bool>,
+    oso_url: Option<String>,
+) -> Result<SavedSettings, String> {
+    let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let proxy = non_empty(proxy_url).map(|url| ProxyConfig {
+        url: url.trim().to_string(),
+        username: non_empty(proxy_username),
+        password: non_empty(proxy_password),
+        no_proxy: no_proxy
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(str::to_string)
+            .collect(),
+    });
+    let update = SettingsUpdate {
+        output_location,
+        gdal_path,
+        python_path,
+        proxy,
+        preferred_ortho_source: non_empty(preferred_ortho_source),
+        log_level: non_empty(log_level),
+        language: non_empty(language),
+        image_quality,
+        resolution,
+        slice_factor,
+        temp_dir: non_empty(temp_dir),
+        offline_mode,
+        oso_url: non_empty(oso_url).map(|url| url.trim().to_string()),
+    };
+    let projects_busy = !busy.busy_projects().is_empty();
+
+    // Le verrou est relâché avant de traduire le message, qui relit la langue configurée.
+    let result = app_setup::CONFIG
+        .lock()
+        .unwrap()
+        .update_settings(update, projects_busy);
+    let blocked = result.map_err(|e| i18n::error("error.save_settings", e))?;
+    web_request::reset_http_client();
+    let message = if blocked.is_empty() {
+        i18n::text("settings.saved", &[])
+    } else {
+        tracing::warn!(blocked = ?blocked, "Paramètres non modifiés pendant un traitement");
+        i18n::text("settings.partially_saved", &[&blocked.join(", ")])
+    };
+    Ok(SavedSettings { message, blocked })
+}
+
+#[command(rename_all = "snake_case")]
+/// Renvoie les dernières lignes du journal de l'application, pour le panneau des paramètres.
+///
+/// # Arguments
+///
+/// * `lines` - Le nombre de lignes à renvoyer.
+///
+/// # Retourne
+///
+/// * `Result<Vec<String>, String>` - Les lignes, des plus anciennes aux plus récentes.
+pub fn get_recent_logs(lines: usize) -> Result<Vec<String>, String> {
+    recent_log_lines(&logs_dir(), lines).map_err(|e| i18n::error("error.read_logs", e))
+}
+
+#[command]
+/// Vide tout ou partie du cache : archives téléchargées (`archives`), GPKG préparés des créations
+/// interrompues (`prepared`), tuiles WMS (`wms`) et dossier temporaire (`tmp`).
+/// Un périmètre utilisé par une tâche en cours (voir `CacheScope::used_by`) n'est pas vidé.
+///
+/// # Arguments
+///
+/// * `busy` - Les projets occupés de l'application.
+/// * `scopes` - Les périmètres à vider, tous par défaut.
+///
+/// # Retourne
+///
+/// * `Result<BTreeMap<CacheScope, CleanupReport>, String>` : Le nombre d'éléments supprimés et
+///   les octets libérés par périmètre, ou un message d'erreur si un périmètre est utilisé.
+pub fn clear_cache(
+    busy: tauri::State<'_, BusyProjects>,
+    scopes: Option<Vec<CacheScope>>,
+) -> Result<BTreeMap<CacheScope, CleanupReport>, String> {
+    let scopes = scopes.unwrap_or_else(|| CacheScope::ALL.to_vec());
+    let busy_projects = busy.busy_projects();
+    for scope in &scopes {
+        if let Some((project, kind)) = busy_projects.iter().find(|(_, kind)| scope.used_by(**kind))
+        {
+            return Err(i18n::text(
+                "error.cache_in_use",
+                &[scope.key(), project, &kind.label()],
+            ));
         }
     }
+
+    let mut reports = BTreeMap::new();
+    for scope in scopes {
+        let report = utils::clear_cache_scope(scope);
+        tracing::info!(
+            ?scope,
+            removed = report.removed,
+            freed_bytes = report.freed_bytes,
+            "Cache vidé"
+        );
+        reports.insert(scope, report);
+    }
+    create_directory_if_not_exists(cache_dir().to_string_lossy().as_ref())
+        .map_err(|e| i18n::error("error.clear_cache", e))?;
+    Ok(reports)
 }
 
 #[command]
-/// Vide le cache des projets.
+/// Liste les départements connus du graphe des régions.
 ///
 /// # Retourne
 ///
-/// * `Result<String, String>` : Un message de succès ou d'erreur.
-pub fn clear_cache() -> Result<String, String> {
-    match std::fs::remove_dir_all(cache_dir()) {
-        Ok(_) => {
-            create_directory_if_not_exists(cache_dir().to_string_lossy().as_ref())
-                .map_err(|e| e.to_string())?;
-            Ok("Cache vidé avec succès".to_string())
+/// * `BTreeMap<String, String>` : Les noms des départements, indexés par code.
+pub fn get_dpts_list(
+    regions: tauri::State<'_, RwLock<RegionDirectory>>,
+) -> BTreeMap<String, String> {
+    regions.read().unwrap().names().clone()
+}
+
+#[command]
+/// Liste les départements voisins d'un département, pour suggérer les départements
+/// adjacents lorsqu'une emprise chevauche une limite.
+///
+/// # Arguments
+///
+/// * `code` - Le code du département.
+///
+/// # Retourne
+///
+/// * `Result<Vec<String>, String>` : Les codes des départements voisins, ou un message d'erreur si le code est inconnu.
+pub fn get_region_neighbors(
+    regions: tauri::State<'_, RwLock<RegionDirectory>>,
+    code: String,
+) -> Result<Vec<String>, String> {
+    regions
+        .read()
+        .unwrap()
+        .neighbors(&code)
+        .map(<[String]>::to_vec)
+        .ok_or_else(|| i18n::text("error.unknown_department", &[&code]))
+}
+
+#[command]
+/// Reconstruit le graphe des départements depuis `regions.geojson`, même s'il est à jour,
+/// puis recharge l'index des régions et la liste des départements.
+///
+/// # Retourne
+///
+/// * `Result<RegionsGraphReport, String>` : Le nombre de départements et de voisinages du graphe, ou un message d'erreur.
+pub fn rebuild_regions_graph(
+    regions: tauri::State<'_, RwLock<RegionDirectory>>,
+) -> Result<RegionsGraphReport, String> {
+    let report = build_regions_graph(
+        Path::new(REGIONS_GEOJSON_FILE),
+        Path::new(REGIONS_GRAPH_FILE),
+        true,
+    )
+    .map_err(|e| i18n::error("error.rebuild_regions_graph", e))?;
+    let graph = load_regions_graph().map_err(|e| i18n::error("error.rebuild_regions_graph", e))?;
+    *regions.write().unwrap() = RegionDirectory::from_graph(&graph);
+    set_regions_index(graph);
+    Ok(report)
+}
+
+#[command]
+/// Applique la limite de taille du cache et décrit son contenu.
+///
+/// # Retourne
+///
+/// * `Result<CacheStatus, String>` : La taille du cache, ses archives et celles évincées, ou un message d'erreur.
+pub fn get_cache_status() -> Result<CacheStatus, String> {
+    utils::get_cache_status().map_err(|e| i18n::error("error.cache_status", e))
+}
+
+/// Lance la recherche des nouveaux millésimes au démarrage, sans bloquer l'ouverture de la
+/// fenêtre : la recherche n'est faite qu'une fois par jour (voir `data_updates::is_check_due`)
+/// et son résultat est émis sur `data-updates`.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+pub fn start_data_update_check(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let updates = app_handle.state::<DataUpdates>();
+        if !updates.start() {
+            return;
+        }
+        let result = data_updates::check_data_updates(data_fetcher().as_ref(), false).await;
+        let result = result.unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Recherche des nouveaux millésimes impossible");
+            None
+        });
+        let checked = result.is_some();
+        let status = updates.finish(result);
+        if checked {
+            let _ = app_handle.emit(DATA_UPDATES_EVENT, status);
         }
-        Err(e) => Err(format!("Échec du vidage du cache: {}", e)),
+    });
+}
+
+#[command]
+/// Renvoie le résultat de la dernière recherche de nouveaux millésimes.
+///
+/// # Retourne
+///
+/// * `DataUpdateStatus` : Les archives du cache dont un millésime plus récent est publié.
+pub fn get_data_update_status(updates: tauri::State<'_, DataUpdates>) -> DataUpdateStatus {
+    updates.status()
+}
+
+#[command]
+/// Recherche les nouveaux millésimes des archives du cache et des projets, sans attendre
+/// l'intervalle entre deux recherches automatiques.
+///
+/// # Arguments
+///
+/// * `app_handle` - Handle de l'application Tauri.
+/// * `updates` - Le résultat de la dernière recherche.
+///
+/// # Retourne
+///
+/// * `Result<DataUpdateStatus, String>` : Le résultat de la recherche, ou un message d'erreur.
+pub async fn check_data_updates(
+    app_handle: tauri::AppHandle,
+    updates: tauri::State<'_, DataUpdates>,
+) -> Result<DataUpdateStatus, String> {
+    if !updates.start() {
+        return Ok(updates.status());
     }
+    let result = data_updates::check_data_updates(data_fetcher().as_ref(), true).await;
+    let status = updates.finish(result.as_ref().ok().cloned().flatten());
+    result.map_err(|e| i18n::error("error.check_data_updates", e))?;
+    let _ = app_handle.emit(DATA_UPDATES_EVENT, status.clone());
+    Ok(status)
+}
+
+#[command]
+/// Télécharge dans le cache les nouveaux millésimes trouvés par la dernière recherche.
+///
+/// # Arguments
+///
+/// * `updates` - Le résultat de la dernière recherche.
+/// * `setup` - Le rapport de configuration : la commande est refusée s'il est incomplet.
+/// * `departments` - Option<Vec<String>> : Les départements à mettre à jour, tous par défaut.
+///
+/// # Retourne
+///
+/// * `Result<DataUpdateStatus, String>` : Les mises à jour restantes, ou un message d'erreur.
+pub async fn refresh_cache(
+    updates: tauri::State<'_, DataUpdates>,
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    departments: Option<Vec<String>>,
+) -> Result<DataUpdateStatus, String> {
+    setup.read().unwrap().ensure_ready()?;
+    let selected: Vec<_> = updates
+        .status()
+        .updates
+        .into_iter()
+        .filter(|update| {
+            departments
+                .as_ref()
+                .is_none_or(|departments| departments.contains(&update.region))
+        })
+        .collect();
+    let refreshed = data_updates::refresh_archives(data_fetcher().as_ref(), &selected)
+        .await
+        .map_err(|e| i18n::error("error.refresh_cache", e))?;
+    Ok(updates.remove_updates(&refreshed))
+}
+
+#[command(rename_all = "snake_case")]
+/// Propose une emprise dont la largeur et la hauteur sont des multiples de
+/// `PROJECT_GRID_PIXELS` pixels à la résolution des paramètres.
+///
+/// # Arguments
+///
+/// * `xmin`, `ymin`, `xmax`, `ymax` - L'emprise saisie.
+/// * `mode` - L'ajustement : "expand", "shrink" ou "center".
+///
+/// # Retourne
+///
+/// * `Result<GridSnap, String>` : L'emprise corrigée et le déplacement de chaque bord,
+///   ou un message d'erreur si l'emprise est vide ou inversée.
+pub fn suggest_valid_bbox(
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64,
+    mode: SnapMode,
+) -> Result<GridSnap, String> {
+    let cell_m = PROJECT_GRID_PIXELS as f64 * utils::resolution();
+    GridSnap::new(&BoundingBox::new(xmin, ymin, xmax, ymax), cell_m, mode)
+        .map_err(|e| i18n::error("error.snap_bbox", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Estime la taille et la durée de création d'un projet, sans rien télécharger.
+///
+/// # Arguments
+///
+/// * `project_bb` - Boîte englobante du projet.
+///
+/// # Retourne
+///
+/// * `Result<ProjectEstimate, String>` : Les départements, archives, dimensions et la durée estimée, ou un message d'erreur.
+pub async fn estimate_project(project_bb: BoundingBox) -> Result<ProjectEstimate, String> {
+    estimate::estimate_project(&project_bb)
+        .await
+        .map_err(|e| i18n::error("error.estimate", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Vérifie l'intégrité des archives du cache.
+///
+/// # Arguments
+///
+/// * `repair` - Option<bool> : Supprime les archives corrompues pour qu'elles soient retéléchargées.
+///
+/// # Retourne
+///
+/// * `Result<Vec<String>, String>` : La liste des archives corrompues ou un message d'erreur.
+pub fn verify_cache(repair: Option<bool>) -> Result<Vec<String>, String> {
+    verify_cache_archives(repair.unwrap_or(false)).map_err(|e| i18n::error("error.verify_cache", e))
+}
+
+#[command]
+/// Retourne le rapport des vérifications du démarrage : dépendances, dossiers de
+/// l'application et graphe des départements.
+///
+/// # Retourne
+///
+/// * `SetupReport` : Le rapport conservé dans l'état de l'application.
+pub fn get_setup_report(setup: tauri::State<'_, RwLock<SetupReport>>) -> SetupReport {
+    setup.read().unwrap().clone()
+}
+
+#[command]
+/// Relance les vérifications du démarrage, par exemple après l'installation d'une dépendance,
+/// et recharge la liste des départements si le graphe est devenu disponible.
+///
+/// # Retourne
+///
+/// * `SetupReport` : Le nouveau rapport.
+pub fn recheck_setup(
+    setup: tauri::State<'_, RwLock<SetupReport>>,
+    regions: tauri::State<'_, RwLock<RegionDirectory>>,
+) -> SetupReport {
+    let report = app_setup::recheck_setup(&setup);
+    if report.regions_graph_error.is_none() {
+        match RegionDirectory::load() {
+            Ok(directory) => *regions.write().unwrap() = directory,
+            Err(e) => tracing::warn!(error = %e, "Liste des départements indisponible"),
+        }
+    }
+    report
+}
+
+#[command(rename_all = "snake_case")]
+/// Établit un diagnostic de l'installation : version de GDAL, exécutables externes,
+/// droits d'écriture, espace disque, ressources régionales et accès au site de l'IGN.
+///
+/// # Arguments
+///
+/// * `network_probe` - Option<bool> : Teste l'accès réseau à l'IGN (vrai par défaut).
+///
+/// # Retourne
+///
+/// * `DiagnosticsReport` : Le rapport structuré du diagnostic.
+pub async fn run_diagnostics(network_probe: Option<bool>) -> DiagnosticsReport {
+    diagnostics::run_diagnostics(network_probe.unwrap_or(true)).await
+}
+
+#[command(rename_all = "snake_case")]
+/// Retourne la configuration des couches BDTOPO appliquées lors de la création des projets.
+///
+/// # Retourne
+/// - Result<LayerConfig, String> - La configuration active (utilisateur ou par défaut).
+pub fn get_layer_config() -> Result<LayerConfig, String> {
+    load_layer_config().map_err(|e| i18n::error("error.load_layer_config", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Enregistre la configuration des couches choisie par l'utilisateur.
+/// Elle remplace la configuration par défaut pour les créations suivantes.
+///
+/// # Arguments
+/// - `config`: La configuration à enregistrer.
+///
+/// # Retourne
+/// - Result<(), String> - Une erreur si la configuration est invalide ou ne peut être écrite.
+pub fn save_layer_config(config: LayerConfig) -> Result<(), String> {
+    config
+        .save_to(&user_layer_config_path())
+        .map_err(|e| i18n::error("error.save_layer_config", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Retourne la légende de l'image de végétation, construite à partir de la configuration
+/// des couches et des classes du RPG actives.
+///
+/// # Arguments
+/// - project_name: Option<String> : Le projet affiché, dont la légende reprend la couleur
+///   du fond départemental ; sans projet, celle des nouveaux projets.
+///
+/// # Retourne
+/// - Result<Vec<LegendEntry>, String> - Une entrée par couleur, de la plus prioritaire à la moins prioritaire.
+pub fn get_legend(project_name: Option<String>) -> Result<Vec<LegendEntry>, String> {
+    let layer_config = load_layer_config().map_err(|e| i18n::error("error.legend", e))?;
+    let rpg_classes = load_rpg_classes().map_err(|e| i18n::error("error.legend", e))?;
+    let land_color = project_name.map_or_else(default_land_color, |name| {
+        load_project_metadata(&name).project_land_color()
+    });
+    Ok(build_legend(&layer_config, &rpg_classes, land_color))
+}
+
+/// Légende utilisée pour nommer les pixels identifiés ; sans elle, les libellés sont tirés
+/// du raster de classes.
+fn identify_legend(project_name: &str) -> Vec<LegendEntry> {
+    get_legend(Some(project_name.to_string())).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Légende indisponible pour l'identification");
+        Vec::new()
+    })
+}
+
+#[command(rename_all = "snake_case")]
+/// Identifie un pixel du raster d'un projet : couleur, classe, libellé de la légende et
+/// coordonnées dans le système du projet.
+///
+/// # Arguments
+/// - project_name: &str : Le nom du projet.
+/// - px: u32 : La colonne du pixel.
+/// - py: u32 : La ligne du pixel.
+///
+/// # Retourne
+/// - Result<PixelInfo, String> : Le contenu du pixel, « hors zone » si son alpha est nul,
+///   ou une erreur s'il est hors de l'image.
+pub fn identify_pixel(project_name: &str, px: u32, py: u32) -> Result<PixelInfo, String> {
+    checked_project_name(project_name)?;
+    let epsg = load_project_metadata(project_name).project_epsg();
+    identify::identify_pixel(
+        &project_tiff(project_name).to_string_lossy(),
+        epsg,
+        &identify_legend(project_name),
+        px,
+        py,
+    )
+    .map_err(|e| i18n::error("error.identify_pixel", e))
+}
+
+#[command(rename_all = "snake_case")]
+/// Identifie le pixel d'un projet contenant un point donné dans le système du projet.
+///
+/// # Arguments
+/// - project_name: &str : Le nom du projet.
+/// - x: f64, y: f64 : Les coordonnées du point.
+///
+/// # Retourne
+/// - Result<PixelInfo, String> : Le contenu du pixel, ou une erreur si le point est hors de l'emprise.
+pub fn identify_point(project_name: &str, x: f64, y: f64) -> Result<PixelInfo, String> {
+    checked_project_name(project_name)?;
+    let epsg = load_project_metadata(project_name).project_epsg();
+    identify::identify_point(
+        &project_tiff(project_name).to_string_lossy(),
+        epsg,
+        &identify_legend(project_name),
+        x,
+        y,
+    )
+    .map_err(|e| i18n::error("error.identify_pixel", e))
 }
@@ -0,0 +1,354 @@
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::events::Warning;
+use crate::fetcher::DataFetcher;
+use crate::utils::{
+    ProjectMetadata, all_project_names, cache_dir, get_config, get_config_mut,
+    is_cache_entry_pinned, list_cache_entries, load_project_metadata, read_cache_vintages,
+    touch_cache_entry,
+};
+use crate::web_request::{
+    ArchiveUrl, DepartmentUrls, DownloadJob, record_cache_vintage, remove_part_files,
+};
+
+/// Intervalle minimal, en heures, entre deux recherches automatiques de nouveaux millésimes.
+pub const DATA_UPDATE_CHECK_INTERVAL_HOURS: i64 = 24;
+
+/// Durée maximale de la recherche des archives de tous les départements : au-delà,
+/// la recherche est abandonnée et retentée au prochain démarrage.
+pub const DATA_UPDATE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Archive (base et département) identifiée dans le cache ou les projets.
+pub type ArchiveKey = (String, String);
+
+/// Archive pour laquelle un millésime plus récent que celui du cache est publié.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataUpdate {
+    /// Type de données (`BDTOPO`, `BDFORET` ou `RPG`).
+    pub data_type: String,
+    /// Code du département.
+    pub region: String,
+    /// Millésime le plus récent du cache et des projets (`AAAA-MM-JJ`).
+    pub current: String,
+    /// Millésime publié (`AAAA-MM-JJ`).
+    pub latest: String,
+    /// URL de l'archive publiée, téléchargée par `refresh_cache`.
+    pub url: String,
+}
+
+/// Résultat de la dernière recherche de nouveaux millésimes, renvoyé par `get_data_update_status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataUpdateStatus {
+    /// Date de la recherche (RFC 3339), `None` tant qu'aucune recherche n'a abouti.
+    pub checked_at: Option<String>,
+    /// Une recherche est en cours.
+    pub checking: bool,
+    pub updates: Vec<DataUpdate>,
+    /// Départements dont les archives n'ont pas pu être recherchées.
+    pub errors: Vec<Warning>,
+}
+
+/// État des recherches de nouveaux millésimes, conservé dans l'état de l'application.
+#[derive(Debug, Default)]
+pub struct DataUpdates {
+    status: RwLock<DataUpdateStatus>,
+}
+
+impl DataUpdates {
+    pub fn status(&self) -> DataUpdateStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Marque le début d'une recherche ; renvoie faux si une recherche est déjà en cours.
+    pub fn start(&self) -> bool {
+        let mut status = self.status.write().unwrap();
+        if status.checking {
+            return false;
+        }
+        status.checking = true;
+        true
+    }
+
+    /// Termine une recherche : le résultat remplace le précédent, qui est conservé
+    /// si la recherche n'a pas été faite (`None`).
+    pub fn finish(&self, result: Option<DataUpdateStatus>) -> DataUpdateStatus {
+        let mut status = self.status.write().unwrap();
+        if let Some(result) = result {
+            *status = result;
+        }
+        status.checking = false;
+        status.clone()
+    }
+
+    /// Retire des mises à jour disponibles les archives téléchargées par `refresh_cache`.
+    pub fn remove_updates(&self, refreshed: &[ArchiveKey]) -> DataUpdateStatus {
+        let mut status = self.status.write().unwrap();
+        status.updates.retain(|update| {
+            !refreshed.iter().any(|(data_type, region)| {
+                update.data_type == *data_type && update.region == *region
+            })
+        });
+        status.clone()
+    }
+}
+
+/// Indique si la recherche automatique doit être relancée : jamais faite, date illisible
+/// ou faite il y a plus de `DATA_UPDATE_CHECK_INTERVAL_HOURS` heures.
+///
+/// # Arguments
+///
+/// * `last_check` - Date de la dernière recherche (RFC 3339), enregistrée dans la configuration.
+/// * `now` - Date courante.
+///
+/// # Returns
+///
+/// * `bool` - Vrai si la recherche est due.
+pub fn is_check_due(last_check: Option<&str>, now: DateTime<Local>) -> bool {
+    let Some(last_check) = last_check.and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+    else {
+        return true;
+    };
+    now.signed_duration_since(last_check)
+        >= chrono::Duration::hours(DATA_UPDATE_CHECK_INTERVAL_HOURS)
+}
+
+fn parse_vintage_date(date: Option<&str>) -> Option<NaiveDate> {
+    date.and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+/// Millésimes connus de chaque archive : ceux des archives du cache (voir `CACHE_VINTAGES_FILE`)
+/// et ceux utilisés par les projets. Le plus récent est retenu ; `None` si aucun n'est daté.
+///
+/// # Arguments
+///
+/// * `cache` - Le dossier du cache.
+/// * `projects` - Les métadonnées des projets existants.
+///
+/// # Returns
+///
+/// * `BTreeMap<ArchiveKey, Option<NaiveDate>>` - Le millésime connu de chaque archive.
+pub fn known_vintages(
+    cache: &Path,
+    projects: &[ProjectMetadata],
+) -> BTreeMap<ArchiveKey, Option<NaiveDate>> {
+    let mut known: BTreeMap<ArchiveKey, Option<NaiveDate>> = BTreeMap::new();
+    let cache_vintages = read_cache_vintages(cache);
+    for entry in list_cache_entries(cache).unwrap_or_default() {
+        let Some((data_type, code)) = entry
+            .name
+            .strip_suffix(".7z")
+            .and_then(|stem| stem.split_once('_'))
+        else {
+            continue;
+        };
+        let date = cache_vintages
+            .get(&entry.name)
+            .and_then(|vintage| parse_vintage_date(vintage.date.as_deref()));
+        let known_date = known
+            .entry((data_type.to_string(), code.to_string()))
+            .or_default();
+        *known_date = (*known_date).max(date);
+    }
+    for vintage in projects.iter().flat_map(|metadata| &metadata.vintages) {
+        let known_date = known
+            .entry((vintage.data_type.clone(), vintage.region.clone()))
+            .or_default();
+        *known_date = (*known_date).max(parse_vintage_date(vintage.date.as_deref()));
+    }
+    known
+}
+
+/// Compare les millésimes connus aux archives publiées. Une mise à jour n'est signalée
+/// que si les deux millésimes sont datés et que l'archive publiée est plus récente.
+///
+/// # Arguments
+///
+/// * `known` - Les millésimes connus (voir `known_vintages`).
+/// * `resolved` - Les archives publiées de chaque département.
+///
+/// # Returns
+///
+/// * `(Vec<DataUpdate>, Vec<Warning>)` - Les mises à jour disponibles et les départements
+///   dont les archives n'ont pas pu être recherchées.
+pub fn compare_vintages(
+    known: &BTreeMap<ArchiveKey, Option<NaiveDate>>,
+    resolved: &[DepartmentUrls],
+) -> (Vec<DataUpdate>, Vec<Warning>) {
+    let mut updates = Vec::new();
+    let mut errors = Vec::new();
+    for department in resolved {
+        let archives = match &department.archives {
+            Ok(archives) => archives,
+            Err(e) => {
+                errors.push(Warning::new(format!("Département {}", department.code), e));
+                continue;
+            }
+        };
+        let latest: [(&str, &ArchiveUrl); 3] = [
+            ("BDTOPO", &archives.topo),
+            ("BDFORET", &archives.foret),
+            ("RPG", &archives.rpg),
+        ];
+        for (data_type, archive) in latest {
+            let key = (data_type.to_string(), department.code.clone());
+            let (Some(Some(current)), Some(published)) = (known.get(&key), archive.date) else {
+                continue;
+            };
+            if published > *current {
+                updates.push(DataUpdate {
+                    data_type: key.0,
+                    region: key.1,
+                    current: current.format("%Y-%m-%d").to_string(),
+                    latest: published.format("%Y-%m-%d").to_string(),
+                    url: archive.url.clone(),
+                });
+            }
+        }
+    }
+    (updates, errors)
+}
+
+/// Recherche les nouveaux millésimes des archives du cache et des projets. Hors recherche
+/// forcée, la recherche n'est faite qu'une fois par `DATA_UPDATE_CHECK_INTERVAL_HOURS` :
+/// sa date est enregistrée dans la configuration avant les appels réseau, pour qu'un échec
+/// ne la relance pas à chaque démarrage.
+///
+/// # Arguments
+///
+/// * `fetcher` - La recherche des URLs d'archives.
+/// * `force` - Ignore l'intervalle minimal (recherche demandée par l'utilisateur).
+///
+/// # Returns
+///
+/// * `Result<Option<DataUpdateStatus>, String>` - Le résultat, `None` si la recherche n'était
+///   pas due, ou une erreur si elle a dépassé `DATA_UPDATE_TIMEOUT`.
+pub async fn check_data_updates(
+    fetcher: &dyn DataFetcher,
+    force: bool,
+) -> Result<Option<DataUpdateStatus>, String> {
+    let now = Local::now();
+    if !force && !is_check_due(get_config().last_data_update_check.as_deref(), now) {
+        return Ok(None);
+    }
+    {
+        let mut config = get_config_mut();
+        config.last_data_update_check = Some(now.to_rfc3339());
+        if let Err(e) = config.save() {
+            tracing::warn!(error = %e, "Impossible d'enregistrer la date de la recherche");
+        }
+    }
+
+    let projects: Vec<ProjectMetadata> = all_project_names()
+        .unwrap_or_default()
+        .iter()
+        .map(|name| load_project_metadata(name))
+        .collect();
+    let known = known_vintages(&cache_dir(), &projects);
+    let codes: Vec<String> = known
+        .keys()
+        .map(|(_, code)| code.clone())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect();
+
+    let resolved = if codes.is_empty() {
+        Vec::new()
+    } else {
+        tokio::time::timeout(DATA_UPDATE_TIMEOUT, fetcher.resolve_urls(&codes))
+            .await
+            .map_err(|_| {
+                format!(
+                    "Recherche des millésimes interrompue après {} s",
+                    DATA_UPDATE_TIMEOUT.as_secs()
+                )
+            })?
+    };
+    let (updates, errors) = compare_vintages(&known, &resolved);
+    tracing::info!(
+        departments = codes.len(),
+        updates = updates.len(),
+        "Recherche des nouveaux millésimes terminée"
+    );
+    Ok(Some(DataUpdateStatus {
+        checked_at: Some(now.to_rfc3339()),
+        checking: false,
+        updates,
+        errors,
+    }))
+}
+
+/// Remplace dans le cache les archives dont un nouveau millésime est publié : la nouvelle
+/// archive est téléchargée à côté de l'ancienne, qui n'est remplacée qu'une fois le
+/// téléchargement réussi ; son millésime est enregistré dans `CACHE_VINTAGES_FILE`.
+/// Les archives utilisées par une création en cours, avant le téléchargement ou au moment
+/// du remplacement, sont conservées.
+///
+/// # Arguments
+///
+/// * `fetcher` - Le téléchargement des archives.
+/// * `updates` - Les mises à jour à appliquer.
+///
+/// # Returns
+///
+/// * `Result<Vec<ArchiveKey>, String>` - Les archives remplacées, ou l'erreur du téléchargement.
+pub async fn refresh_archives(
+    fetcher: &dyn DataFetcher,
+    updates: &[DataUpdate],
+) -> Result<Vec<ArchiveKey>, String> {
+    let mut jobs = Vec::new();
+    for update in updates {
+        let job = DownloadJob {
+            file_type: update.data_type.clone(),
+            code: update.region.clone(),
+            url: update.url.clone(),
+        };
+        if is_cache_entry_pinned(&job.cache_path()) {
+            log_pinned_update(&job.cache_path());
+            continue;
+        }
+        jobs.push(job);
+    }
+    if jobs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let downloaded = fetcher.download_replacements(jobs).await?;
+    let part_paths: Vec<PathBuf> = downloaded.iter().map(|(_, part)| part.clone()).collect();
+    let mut refreshed = Vec::new();
+    for (job, part_path) in &downloaded {
+        let archive_path = job.cache_path();
+        if is_cache_entry_pinned(&archive_path) {
+            log_pinned_update(&archive_path);
+            continue;
+        }
+        if let Err(e) = std::fs::rename(part_path, &archive_path) {
+            remove_part_files(&part_paths);
+            return Err(e.to_string());
+        }
+        record_cache_vintage(job);
+        if let Err(e) = touch_cache_entry(&archive_path) {
+            tracing::warn!(
+                file = %archive_path.display(),
+                error = %e,
+                "Impossible d'enregistrer l'utilisation de l'archive"
+            );
+        }
+        refreshed.push((job.file_type.clone(), job.code.clone()));
+    }
+    // Fichiers des archives épinglées, non remplacées.
+    remove_part_files(&part_paths);
+    Ok(refreshed)
+}
+
+/// Signale une mise à jour reportée car l'archive est utilisée par une création en cours.
+fn log_pinned_update(archive_path: &Path) {
+    tracing::info!(
+        file = %archive_path.display(),
+        "Archive utilisée par une création en cours, mise à jour reportée"
+    );
+}
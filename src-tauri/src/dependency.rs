@@ -1,14 +1,101 @@
 use crate::app_setup::Config;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::process::Command;
 use std::str;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DependencyError {
     GDALNotInstalled,
     PythonNotInstalled,
     SevenZipNotInstalled,
 }
 
+/// Dépendance externe vérifiée au démarrage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dependency {
+    Gdal,
+    Python,
+    SevenZip,
+}
+
+impl Dependency {
+    pub const ALL: [Dependency; 3] = [Dependency::Gdal, Dependency::Python, Dependency::SevenZip];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Dependency::Gdal => "GDAL",
+            Dependency::Python => "Python",
+            Dependency::SevenZip => "7-Zip",
+        }
+    }
+
+    /// Exécutable de la dépendance et argument utilisé pour vérifier qu'il se lance.
+    pub fn command(&self) -> (&'static str, &'static str) {
+        let windows = cfg!(target_os = "windows");
+        match self {
+            Dependency::Gdal if windows => ("gdalinfo.exe", "--version"),
+            Dependency::Gdal => ("gdalinfo", "--version"),
+            Dependency::Python if windows => ("python", "--version"),
+            Dependency::Python => ("python3", "--version"),
+            Dependency::SevenZip if windows => ("7z.exe", "--help"),
+            Dependency::SevenZip => ("7z", "--help"),
+        }
+    }
+
+    /// Indication d'installation pour le système courant.
+    pub fn install_hint(&self) -> &'static str {
+        if cfg!(target_os = "windows") {
+            match self {
+                Dependency::Gdal => {
+                    "Installer GDAL avec OSGeo4W (https://trac.osgeo.org/osgeo4w/) et ajouter son dossier bin au PATH"
+                }
+                Dependency::Python => {
+                    "Installer Python depuis https://www.python.org/downloads/ en cochant « Add python.exe to PATH »"
+                }
+                Dependency::SevenZip => {
+                    "Installer 7-Zip (https://www.7-zip.org/) et ajouter son dossier au PATH"
+                }
+            }
+        } else if cfg!(target_os = "macos") {
+            match self {
+                Dependency::Gdal => "brew install gdal",
+                Dependency::Python => "brew install python",
+                Dependency::SevenZip => "brew install p7zip",
+            }
+        } else {
+            match self {
+                Dependency::Gdal => {
+                    "sudo apt install gdal-bin (ou le paquet gdal de la distribution)"
+                }
+                Dependency::Python => "sudo apt install python3",
+                Dependency::SevenZip => "sudo apt install p7zip-full",
+            }
+        }
+    }
+
+    fn missing_error(&self) -> DependencyError {
+        match self {
+            Dependency::Gdal => DependencyError::GDALNotInstalled,
+            Dependency::Python => DependencyError::PythonNotInstalled,
+            Dependency::SevenZip => DependencyError::SevenZipNotInstalled,
+        }
+    }
+}
+
+/// État d'une dépendance dans le rapport de configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DependencyStatus {
+    pub dependency: Dependency,
+    pub label: String,
+    pub command: String,
+    pub found: bool,
+    pub path: Option<String>,
+    /// Indication d'installation, pour les dépendances absentes.
+    pub install_hint: Option<String>,
+}
+
 /// Vérifie si une commande existe en l'exécutant avec un argument spécifique.
 ///
 /// # Arguments
@@ -22,52 +109,97 @@ fn check_command(command: &str, arg: &str, error: DependencyError) -> Result<(),
     if Command::new(command).arg(arg).output().is_err() {
         Err(error)
     } else {
-        println!("{} is found", command);
+        tracing::debug!(command, "Dépendance trouvée");
         Ok(())
     }
 }
 
-/// Vérifie si toutes les dépendances sont installées.
+/// Retourne la commande système permettant de localiser un exécutable.
+fn path_command() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "where"
+    } else {
+        "which"
+    }
+}
+
+/// Localise un exécutable dans le PATH.
+///
+/// # Arguments
+/// - `command`: Le nom de l'exécutable à localiser.
 ///
 /// # Retourne
-/// - Result<(), DependencyError>
-pub fn check_dependencies(config: &mut Config) -> Result<(), DependencyError> {
-    let (gdal_command, python_command, path_command, seven_zip_command) =
-        if cfg!(target_os = "windows") {
-            ("gdalinfo.exe", "python", "where", "7z.exe")
-        } else {
-            ("gdalinfo", "python3", "which", "7z")
-        };
+/// - Option<PathBuf> - Le chemin de l'exécutable, ou `None` s'il est introuvable.
+pub fn find_command_path(command: &str) -> Option<PathBuf> {
+    let output = Command::new(path_command()).arg(command).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
 
-    for (command, arg, error, path_field) in [
-        (
-            gdal_command,
-            "--version",
-            DependencyError::GDALNotInstalled,
-            &mut config.gdal_path,
-        ),
-        (
-            python_command,
-            "--version",
-            DependencyError::PythonNotInstalled,
-            &mut config.python_path,
-        ),
-    ] {
-        check_command(command, arg, error)?;
-        if let Ok(path_output) = Command::new(path_command).arg(command).output() {
-            let path = str::from_utf8(&path_output.stdout)
-                .unwrap_or_default()
-                .trim();
-            *path_field = Some(path.into());
-            println!("{} path set to: {}", command, path);
-        }
+    let path = str::from_utf8(&output.stdout)
+        .unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim();
+
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
     }
+}
 
-    check_command(
-        seven_zip_command,
-        "--help",
-        DependencyError::SevenZipNotInstalled,
-    )?;
+/// Vérifie chaque dépendance sans s'arrêter à la première absente, et enregistre
+/// le chemin de GDAL et de Python dans la configuration.
+///
+/// # Arguments
+/// - `config`: La configuration de l'application.
+///
+/// # Retourne
+/// - Vec<DependencyStatus> - L'état de chaque dépendance, dans l'ordre de `Dependency::ALL`.
+pub fn dependency_statuses(config: &mut Config) -> Vec<DependencyStatus> {
+    Dependency::ALL
+        .into_iter()
+        .map(|dependency| {
+            let (command, arg) = dependency.command();
+            let found = check_command(command, arg, dependency.missing_error()).is_ok();
+            let path = if found {
+                find_command_path(command)
+            } else {
+                None
+            };
+            let path_field = match dependency {
+                Dependency::Gdal => Some(&mut config.gdal_path),
+                Dependency::Python => Some(&mut config.python_path),
+                Dependency::SevenZip => None,
+            };
+            if let (Some(path_field), Some(path)) = (path_field, &path) {
+                tracing::info!(command, path = %path.display(), "Chemin de la dépendance enregistré");
+                *path_field = Some(path.clone());
+            }
+            DependencyStatus {
+                dependency,
+                label: dependency.label().to_string(),
+                command: command.to_string(),
+                found,
+                path: path.map(|p| p.to_string_lossy().to_string()),
+                install_hint: (!found).then(|| dependency.install_hint().to_string()),
+            }
+        })
+        .collect()
+}
 
-    Ok(())
+/// Vérifie si toutes les dépendances sont installées.
+///
+/// # Retourne
+/// - Result<(), DependencyError> - L'erreur de la première dépendance absente.
+pub fn check_dependencies(config: &mut Config) -> Result<(), DependencyError> {
+    match dependency_statuses(config)
+        .into_iter()
+        .find(|status| !status.found)
+    {
+        Some(status) => Err(status.dependency.missing_error()),
+        None => Ok(()),
+    }
 }
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use geojson::GeoJson;
+
+use crate::dependency::find_command_path;
+use crate::gdal_version::GdalVersion;
+use crate::gis_operation::regions::load_regions_graph;
+use crate::utils::{in_resource_dir, output_location, projects_dir, proxy_config};
+use crate::web_request::client_builder;
+
+/// Exécutables externes utilisés par le pipeline de création et d'export.
+pub const REQUIRED_BINARIES: [&str; 4] = ["gdal_rasterize", "ogr2ogr", "gdal_translate", "7z"];
+
+/// Page IGN utilisée pour tester l'accès réseau.
+pub const IGN_PROBE_URL: &str = "https://geoservices.ign.fr/bdtopo";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryStatus {
+    pub name: String,
+    pub found: bool,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryStatus {
+    pub path: String,
+    pub writable: bool,
+    pub free_space_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkProbe {
+    pub url: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub gdal_version: String,
+    /// Avertissement si la version de GDAL est antérieure à la version minimale prise en charge.
+    pub gdal_warning: Option<String>,
+    pub binaries: Vec<BinaryStatus>,
+    pub projects_dir: DirectoryStatus,
+    pub output_location: DirectoryStatus,
+    pub regions_geojson_loaded: bool,
+    pub regions_graph_loaded: bool,
+    pub network: Option<NetworkProbe>,
+}
+
+/// Vérifie la présence d'un exécutable dans le PATH.
+///
+/// # Arguments
+/// - `name`: Le nom de l'exécutable.
+///
+/// # Retourne
+/// - BinaryStatus - L'état de l'exécutable (jamais d'erreur, même s'il est absent).
+pub fn check_binary(name: &str) -> BinaryStatus {
+    let path = find_command_path(name);
+    BinaryStatus {
+        name: name.to_string(),
+        found: path.is_some(),
+        path: path.map(|p| p.to_string_lossy().to_string()),
+    }
+}
+
+/// Vérifie qu'un dossier est accessible en écriture en y créant puis supprimant un fichier témoin.
+///
+/// # Arguments
+/// - `path`: Le dossier à vérifier.
+///
+/// # Retourne
+/// - DirectoryStatus - L'état du dossier et l'espace disque disponible.
+pub fn check_directory(path: &Path) -> DirectoryStatus {
+    let probe = path.join(".firefront_probe");
+    let writable = fs::write(&probe, b"probe").is_ok() && fs::remove_file(&probe).is_ok();
+
+    DirectoryStatus {
+        path: path.to_string_lossy().to_string(),
+        writable,
+        free_space_bytes: fs2::available_space(path).ok(),
+    }
+}
+
+/// Teste l'accès à une URL et mesure la latence de la réponse.
+///
+/// # Arguments
+/// - `url`: L'URL à interroger.
+/// - `timeout`: Le délai maximal d'attente.
+///
+/// # Retourne
+/// - NetworkProbe - Le résultat du test réseau.
+pub async fn probe_network(url: &str, timeout: Duration) -> NetworkProbe {
+    let client = match client_builder(proxy_config().as_ref())
+        .and_then(|builder| builder.timeout(timeout).build())
+    {
+        Ok(client) => client,
+        Err(e) => {
+            return NetworkProbe {
+                url: url.to_string(),
+                reachable: false,
+                latency_ms: None,
+                error: Some(e.to_string()),
+            };
+        }
+    };
+
+    let start = Instant::now();
+    match client.get(url).send().await {
+        Ok(response) => NetworkProbe {
+            url: url.to_string(),
+            reachable: response.status().is_success(),
+            latency_ms: Some(start.elapsed().as_millis() as u64),
+            error: if response.status().is_success() {
+                None
+            } else {
+                Some(response.status().to_string())
+            },
+        },
+        Err(e) => NetworkProbe {
+            url: url.to_string(),
+            reachable: false,
+            latency_ms: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+fn regions_geojson_loads() -> bool {
+    fs::read_to_string(in_resource_dir("regions.geojson"))
+        .ok()
+        .and_then(|content| content.parse::<GeoJson>().ok())
+        .is_some()
+}
+
+/// Construit le rapport de diagnostic complet de l'application.
+///
+/// # Arguments
+/// - `network_probe`: Si vrai, teste également l'accès au site de l'IGN.
+///
+/// # Retourne
+/// - DiagnosticsReport - Le rapport structuré.
+pub async fn run_diagnostics(network_probe: bool) -> DiagnosticsReport {
+    let network = if network_probe {
+        Some(probe_network(IGN_PROBE_URL, Duration::from_secs(10)).await)
+    } else {
+        None
+    };
+
+    DiagnosticsReport {
+        gdal_version: gdal::version::version_info("RELEASE_NAME"),
+        gdal_warning: GdalVersion::detect().and_then(GdalVersion::support_warning),
+        binaries: REQUIRED_BINARIES
+            .iter()
+            .map(|name| check_binary(name))
+            .collect(),
+        projects_dir: check_directory(&projects_dir()),
+        output_location: check_directory(&output_location()),
+        regions_geojson_loaded: regions_geojson_loads(),
+        regions_graph_loaded: load_regions_graph().is_ok(),
+        network,
+    }
+}
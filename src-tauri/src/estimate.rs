@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+
+use crate::gis_operation::regions::find_project_regions;
+use crate::utils::{
+    BoundingBox, GridError, max_project_area_km2, max_project_regions, resolution, slice_factor,
+    throughput,
+};
+use crate::web_request::{archive_cache_path, get_shp_file_urls, remote_file_size};
+
+/// Nombre de bandes du raster du projet (RGBA).
+pub const PROJECT_BANDS: u64 = 4;
+
+/// Taille supposée d'une archive dont la taille n'a pas pu être obtenue,
+/// utilisée uniquement pour l'estimation de la durée.
+pub const UNKNOWN_ARCHIVE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Délai maximal de la recherche des URLs des archives sur les pages de l'IGN.
+pub const URL_LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Délai maximal de chaque requête HEAD sur une archive.
+pub const SIZE_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Débits utilisés pour estimer la durée de création d'un projet.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Throughput {
+    /// Débit de téléchargement des archives, en Mo/s.
+    pub download_mb_per_sec: f64,
+    /// Durée d'extraction et de conversion des couches d'un département, en secondes.
+    pub seconds_per_department: f64,
+    /// Vitesse de rastérisation et de composition des couches, en mégapixels/s.
+    pub megapixels_per_sec: f64,
+    /// Durée de découpe et d'amélioration d'une tuile, en secondes.
+    pub seconds_per_slice: f64,
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Self {
+            download_mb_per_sec: 10.0,
+            seconds_per_department: 90.0,
+            megapixels_per_sec: 2.0,
+            seconds_per_slice: 2.0,
+        }
+    }
+}
+
+/// Limites de taille d'un projet, au-delà desquelles la création est refusée
+/// sauf dérogation explicite.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProjectLimits {
+    /// Surface maximale de l'emprise, en km².
+    pub max_area_km2: f64,
+    /// Nombre maximal de départements intersectés.
+    pub max_regions: usize,
+}
+
+impl ProjectLimits {
+    /// Limites définies par `max_project_area_km2` et `max_project_regions`.
+    pub fn current() -> Self {
+        ProjectLimits {
+            max_area_km2: max_project_area_km2(),
+            max_regions: max_project_regions(),
+        }
+    }
+
+    /// Vérifie la surface d'une emprise puis le nombre de départements qu'elle intersecte.
+    ///
+    /// # Arguments
+    /// - `bb`: L'emprise du projet.
+    /// - `regions`: Le nombre de départements intersectés.
+    ///
+    /// # Returns
+    /// - Result<(), ProjectSizeError> - La première limite dépassée, le cas échéant.
+    pub fn check(&self, bb: &BoundingBox, regions: usize) -> Result<(), ProjectSizeError> {
+        let area_km2 = bb.area_km2();
+        if area_km2 > self.max_area_km2 {
+            return Err(ProjectSizeError::AreaTooLarge {
+                requested_km2: area_km2,
+                allowed_km2: self.max_area_km2,
+            });
+        }
+        if regions > self.max_regions {
+            return Err(ProjectSizeError::TooManyRegions {
+                requested: regions,
+                allowed: self.max_regions,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Limite de taille dépassée par l'emprise d'un projet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectSizeError {
+    /// La surface de l'emprise dépasse `max_project_area_km2`.
+    AreaTooLarge {
+        requested_km2: f64,
+        allowed_km2: f64,
+    },
+    /// L'emprise intersecte plus de `max_project_regions` départements.
+    TooManyRegions { requested: usize, allowed: usize },
+}
+
+impl fmt::Display for ProjectSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectSizeError::AreaTooLarge {
+                requested_km2,
+                allowed_km2,
+            } => write!(
+                f,
+                "Surface demandée de {:.0} km² pour {:.0} km² autorisés",
+                requested_km2, allowed_km2
+            ),
+            ProjectSizeError::TooManyRegions { requested, allowed } => write!(
+                f,
+                "{} départements intersectés pour {} autorisés",
+                requested, allowed
+            ),
+        }
+    }
+}
+
+impl Error for ProjectSizeError {}
+
+/// Vérifie qu'une emprise respecte les limites de taille d'un projet.
+/// Une dérogation laisse passer une emprise trop grande : la limite dépassée est alors
+/// renvoyée pour être journalisée et enregistrée dans les métadonnées du projet.
+///
+/// # Arguments
+/// - `bb`: L'emprise du projet.
+/// - `limits`: Les limites à appliquer.
+/// - `allow_oversize`: Accepte une emprise dépassant les limites.
+///
+/// # Returns
+/// - Result<Option<ProjectSizeError>, Box<dyn Error>> - La limite dépassée avec dérogation,
+///   ou une erreur si une limite est dépassée sans dérogation ou si les départements
+///   n'ont pas pu être déterminés.
+pub fn check_project_size(
+    bb: &BoundingBox,
+    limits: &ProjectLimits,
+    allow_oversize: bool,
+) -> Result<Option<ProjectSizeError>, Box<dyn Error>> {
+    // La surface est vérifiée avant de chercher les départements d'une emprise démesurée.
+    let exceeded = match limits.check(bb, 0) {
+        Err(exceeded) => Some(exceeded),
+        Ok(()) => limits.check(bb, find_project_regions(bb)?.0.len()).err(),
+    };
+    match exceeded {
+        None => Ok(None),
+        Some(exceeded) if allow_oversize => {
+            tracing::warn!(limit = %exceeded, "Limite de taille du projet ignorée sur demande");
+            Ok(Some(exceeded))
+        }
+        Some(exceeded) => Err(exceeded.into()),
+    }
+}
+
+/// Archive nécessaire à la création d'un projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEstimate {
+    pub file_type: String,
+    pub code: String,
+    /// L'archive est déjà présente dans le cache.
+    pub cached: bool,
+    /// Taille de l'archive en octets, `None` si elle n'a pas pu être obtenue.
+    pub size_bytes: Option<u64>,
+}
+
+/// Estimation de la taille et de la durée de création d'un projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectEstimate {
+    pub regions: Vec<String>,
+    pub archives: Vec<ArchiveEstimate>,
+    /// Octets à télécharger, pour les archives de taille connue.
+    pub download_bytes: u64,
+    /// Nombre d'archives à télécharger dont la taille est inconnue.
+    pub unknown_sizes: usize,
+    pub width: u64,
+    pub height: u64,
+    pub tiff_size_bytes: u64,
+    pub slice_count: u64,
+    pub duration_secs: u64,
+    /// Surface de l'emprise, en km².
+    pub area_km2: f64,
+    /// Limites de taille appliquées à la création.
+    pub limits: ProjectLimits,
+    /// Limite de taille dépassée par l'emprise, la création sera refusée sans dérogation.
+    pub size_error: Option<String>,
+}
+
+/// Calcule les dimensions du raster du projet, comme `create_project`.
+/// Une emprise non alignée sur la grille est estimée d'après l'emprise suggérée.
+///
+/// # Arguments
+/// - `bb`: L'emprise du projet.
+/// - `resolution`: La résolution en mètres par pixel.
+///
+/// # Returns
+/// - (u64, u64) - La largeur et la hauteur en pixels, nulles pour une emprise invalide.
+pub fn raster_dimensions(bb: &BoundingBox, resolution: f64) -> (u64, u64) {
+    let size = match bb.raster_size(resolution) {
+        Err(GridError::InvalidBoundingBox { suggested, .. }) => suggested.raster_size(resolution),
+        size => size,
+    };
+    size.map(|(width, height)| (width as u64, height as u64))
+        .unwrap_or((0, 0))
+}
+
+/// Taille approximative du GeoTIFF du projet, non compressé.
+///
+/// # Arguments
+/// - `width`: La largeur en pixels.
+/// - `height`: La hauteur en pixels.
+///
+/// # Returns
+/// - u64 - La taille en octets.
+pub fn tiff_size_bytes(width: u64, height: u64) -> u64 {
+    width * height * PROJECT_BANDS
+}
+
+/// Nombre de tuiles produites par la découpe, les tuiles partielles des bords comprises.
+///
+/// # Arguments
+/// - `width`: La largeur en pixels.
+/// - `height`: La hauteur en pixels.
+/// - `slice_factor`: La taille d'une tuile en pixels.
+///
+/// # Returns
+/// - u64 - Le nombre de tuiles.
+pub fn slice_count(width: u64, height: u64, slice_factor: u32) -> u64 {
+    if slice_factor == 0 {
+        return 0;
+    }
+    let slice_factor = slice_factor as u64;
+    width.div_ceil(slice_factor) * height.div_ceil(slice_factor)
+}
+
+/// Estime la durée de création d'un projet.
+///
+/// # Arguments
+/// - `download_bytes`: Les octets à télécharger.
+/// - `departments`: Le nombre de départements traités.
+/// - `pixels`: Le nombre de pixels du raster du projet.
+/// - `slices`: Le nombre de tuiles à produire.
+/// - `throughput`: Les débits de référence.
+///
+/// # Returns
+/// - u64 - La durée estimée en secondes.
+pub fn estimate_duration_secs(
+    download_bytes: u64,
+    departments: usize,
+    pixels: u64,
+    slices: u64,
+    throughput: &Throughput,
+) -> u64 {
+    let rate = |value: f64| if value > 0.0 { value } else { f64::INFINITY };
+
+    let download = download_bytes as f64 / (rate(throughput.download_mb_per_sec) * 1024.0 * 1024.0);
+    let preparation = departments as f64 * throughput.seconds_per_department.max(0.0);
+    let rasterization = pixels as f64 / (rate(throughput.megapixels_per_sec) * 1_000_000.0);
+    let slicing = slices as f64 * throughput.seconds_per_slice.max(0.0);
+
+    (download + preparation + rasterization + slicing).ceil() as u64
+}
+
+/// Construit l'estimation d'un projet à partir des archives nécessaires.
+///
+/// # Arguments
+/// - `bb`: L'emprise du projet.
+/// - `regions`: Les codes des départements intersectés.
+/// - `archives`: Les archives nécessaires, en cache ou à télécharger.
+/// - `resolution`: La résolution en mètres par pixel.
+/// - `slice_factor`: La taille d'une tuile en pixels.
+/// - `throughput`: Les débits de référence.
+/// - `limits`: Les limites de taille d'un projet.
+///
+/// # Returns
+/// - ProjectEstimate - L'estimation.
+pub fn build_estimate(
+    bb: &BoundingBox,
+    regions: Vec<String>,
+    archives: Vec<ArchiveEstimate>,
+    resolution: f64,
+    slice_factor: u32,
+    throughput: &Throughput,
+    limits: &ProjectLimits,
+) -> ProjectEstimate {
+    let to_download: Vec<&ArchiveEstimate> = archives.iter().filter(|a| !a.cached).collect();
+    let download_bytes = to_download.iter().filter_map(|a| a.size_bytes).sum::<u64>();
+    let unknown_sizes = to_download
+        .iter()
+        .filter(|a| a.size_bytes.is_none())
+        .count();
+
+    let (width, height) = raster_dimensions(bb, resolution);
+    let slices = slice_count(width, height, slice_factor);
+    let duration_secs = estimate_duration_secs(
+        download_bytes + unknown_sizes as u64 * UNKNOWN_ARCHIVE_BYTES,
+        regions.len(),
+        width * height,
+        slices,
+        throughput,
+    );
+    let size_error = limits.check(bb, regions.len()).err().map(|e| e.to_string());
+
+    ProjectEstimate {
+        regions,
+        archives,
+        download_bytes,
+        unknown_sizes,
+        width,
+        height,
+        tiff_size_bytes: tiff_size_bytes(width, height),
+        slice_count: slices,
+        duration_secs,
+        area_km2: bb.area_km2(),
+        limits: *limits,
+        size_error,
+    }
+}
+
+/// Estime la taille et la durée de création d'un projet, sans rien télécharger.
+/// La taille des archives absentes du cache est obtenue par des requêtes HEAD :
+/// en cas d'échec ou de dépassement du délai, elle est simplement inconnue.
+///
+/// # Arguments
+/// - `bb`: L'emprise du projet.
+///
+/// # Returns
+/// - Result<ProjectEstimate, Box<dyn Error>> - L'estimation, ou une erreur si l'emprise ne couvre aucun département.
+pub async fn estimate_project(bb: &BoundingBox) -> Result<ProjectEstimate, Box<dyn Error>> {
+    let mut regions: Vec<String> = find_project_regions(bb)?
+        .0
+        .into_iter()
+        .map(|region| region.code)
+        .collect();
+    if regions.is_empty() {
+        return Err("La surface de travail est incorrecte".into());
+    }
+    regions.sort();
+
+    let mut archives = Vec::new();
+    for code in &regions {
+        for file_type in ["BDTOPO", "BDFORET", "RPG"] {
+            let cached_size = fs::metadata(archive_cache_path(file_type, code))
+                .ok()
+                .map(|metadata| metadata.len());
+            archives.push(ArchiveEstimate {
+                file_type: file_type.to_string(),
+                code: code.clone(),
+                cached: cached_size.is_some(),
+                size_bytes: cached_size,
+            });
+        }
+    }
+
+    let missing: Vec<String> = regions
+        .iter()
+        .filter(|code| archives.iter().any(|a| &a.code == *code && !a.cached))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        let urls = tokio::time::timeout(URL_LOOKUP_TIMEOUT, get_shp_file_urls(&missing))
+            .await
+            .unwrap_or_default();
+        for department in urls {
+            let Ok(department_archives) = department.archives else {
+                continue;
+            };
+            for (file_type, archive) in [
+                ("BDTOPO", &department_archives.topo),
+                ("BDFORET", &department_archives.foret),
+                ("RPG", &department_archives.rpg),
+            ] {
+                if let Some(estimate) = archives
+                    .iter_mut()
+                    .find(|a| !a.cached && a.code == department.code && a.file_type == file_type)
+                {
+                    estimate.size_bytes =
+                        remote_file_size(&archive.url, SIZE_REQUEST_TIMEOUT).await;
+                }
+            }
+        }
+    }
+
+    Ok(build_estimate(
+        bb,
+        regions,
+        archives,
+        resolution(),
+        slice_factor(),
+        &throughput(),
+        &ProjectLimits::current(),
+    ))
+}
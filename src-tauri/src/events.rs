@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::export_queue::{ExportJob, ExportJobState};
+use crate::i18n::{self, Language};
+pub use crate::payloads::ProgressUpdate;
+use crate::timings::StageTimings;
+
+/// Événement émis avec l'avancement de la création d'un projet (voir `ProgressUpdate`).
+pub const PROGRESS_EVENT: &str = "progress-update";
+
+/// Événement émis à la fin d'une création de projet réussie.
+pub const PROJECT_CREATED_EVENT: &str = "project-created";
+
+/// Événement émis lorsque la création d'un projet échoue ou est annulée.
+pub const PROJECT_CREATION_FAILED_EVENT: &str = "project-creation-failed";
+
+/// Événement émis à chaque changement d'état d'un export de la file.
+pub const EXPORT_PROGRESS_EVENT: &str = "export-progress";
+
+/// Événement émis à la fin d'une recherche de nouveaux millésimes (voir `DataUpdateStatus`).
+pub const DATA_UPDATES_EVENT: &str = "data-updates";
+
+/// Événement émis avec l'avancement d'une création par lot (voir `BatchProgress`).
+pub const BATCH_PROGRESS_EVENT: &str = "batch-progress";
+
+impl ProgressUpdate {
+    /// Début d'une étape, dans la langue de la configuration.
+    pub fn stage(stage: &str) -> Self {
+        Self::stage_in(crate::utils::language(), stage)
+    }
+
+    /// Sous-tâche d'une étape, dans la langue de la configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - La clé de l'étape.
+    /// * `step` - La clé de la sous-tâche.
+    /// * `params` - Les paramètres du texte de la sous-tâche.
+    /// * `done` - Le numéro de la sous-tâche.
+    /// * `total` - Le nombre de sous-tâches.
+    pub fn step(stage: &str, step: &str, params: &[&str], done: usize, total: usize) -> Self {
+        Self::step_in(crate::utils::language(), stage, step, params, done, total)
+    }
+
+    pub fn stage_in(language: Language, stage: &str) -> Self {
+        ProgressUpdate {
+            stage: stage.to_string(),
+            stage_text: i18n::translate(stage, language, &[]),
+            step: None,
+            step_text: None,
+            params: Vec::new(),
+            count: None,
+        }
+    }
+
+    pub fn step_in(
+        language: Language,
+        stage: &str,
+        step: &str,
+        params: &[&str],
+        done: usize,
+        total: usize,
+    ) -> Self {
+        ProgressUpdate {
+            step: Some(step.to_string()),
+            step_text: Some(i18n::translate(step, language, params)),
+            params: params.iter().map(|param| param.to_string()).collect(),
+            count: Some((done, total)),
+            ..Self::stage_in(language, stage)
+        }
+    }
+}
+
+/// Journal écrit dans le dossier du projet à la fin de sa création.
+pub const CREATION_LOG_FILE: &str = "creation.log";
+
+/// Problème non bloquant rencontré pendant la création d'un projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    /// Élément concerné (couche, étape...).
+    pub context: String,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(context: impl Into<String>, message: impl Into<String>) -> Self {
+        Warning {
+            context: context.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.message)
+    }
+}
+
+/// Ajoute au journal de création (`creation.log`) du dossier du projet
+/// le résultat de la création suivi des avertissements collectés.
+///
+/// # Arguments
+///
+/// * `project_folder` - Le dossier du projet.
+/// * `summary` - Le résultat de la création.
+/// * `warnings` - Les avertissements collectés pendant la création.
+///
+/// # Returns
+///
+/// * `std::io::Result<()>` - Une erreur si le journal ne peut pas être écrit.
+pub fn write_creation_log(
+    project_folder: &Path,
+    summary: &str,
+    warnings: &[Warning],
+) -> std::io::Result<()> {
+    let mut content = format!(
+        "{} {}\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        summary
+    );
+    for warning in warnings {
+        content.push_str(&format!("AVERTISSEMENT {}\n", warning));
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(project_folder.join(CREATION_LOG_FILE))?
+        .write_all(content.as_bytes())
+}
+
+/// Résumé d'un projet créé, transmis avec l'événement `project-created`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectCreated {
+    pub name: String,
+    pub folder: String,
+    pub veget_path: String,
+    pub ortho_path: String,
+    pub duration_seconds: f64,
+    pub regions: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Durées des étapes de la création.
+    pub timings: StageTimings,
+}
+
+impl ProjectCreated {
+    /// Construit le résumé d'un projet à partir de son dossier, en suivant
+    /// la convention de nommage des images (`<nom>_VEGET.jpeg`, `<nom>_ORTHO.jpeg`).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Le nom du projet.
+    /// * `folder` - Le dossier du projet.
+    /// * `duration_seconds` - La durée de la création.
+    /// * `regions` - Les codes des départements traités.
+    /// * `warnings` - Les avertissements collectés pendant la création.
+    /// * `timings` - Les durées des étapes de la création.
+    ///
+    /// # Returns
+    ///
+    /// * `ProjectCreated` - Le résumé du projet.
+    pub fn new(
+        name: &str,
+        folder: &str,
+        duration_seconds: f64,
+        regions: Vec<String>,
+        warnings: &[Warning],
+        timings: StageTimings,
+    ) -> Self {
+        ProjectCreated {
+            name: name.to_string(),
+            folder: folder.to_string(),
+            veget_path: Path::new(folder)
+                .join(format!("{}_VEGET.jpeg", name))
+                .to_string_lossy()
+                .to_string(),
+            ortho_path: Path::new(folder)
+                .join(format!("{}_ORTHO.jpeg", name))
+                .to_string_lossy()
+                .to_string(),
+            duration_seconds,
+            regions,
+            warnings: warnings.iter().map(Warning::to_string).collect(),
+            timings,
+        }
+    }
+}
+
+/// Erreur de création d'un projet, transmise avec l'événement `project-creation-failed`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectCreationFailed {
+    pub name: String,
+    pub message: String,
+    /// Vrai si l'utilisateur a refusé d'écraser un projet existant.
+    pub cancelled: bool,
+    /// Vrai si l'état de la création a été enregistré : `resume_project_creation` peut la reprendre.
+    #[serde(default)]
+    pub resumable: bool,
+    pub warnings: Vec<String>,
+}
+
+/// Avancement d'un projet d'une création par lot, transmis avec l'événement `batch-progress`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchProgress {
+    /// Position du projet dans le lot, à partir de 1.
+    pub index: usize,
+    /// Nombre de projets du lot, entrées invalides comprises.
+    pub total: usize,
+    pub name: String,
+    /// Avancement de la création du projet.
+    pub update: ProgressUpdate,
+}
+
+/// État d'un export de la file, transmis avec l'événement `export-progress`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportProgress {
+    pub project: String,
+    /// Position de l'export dans la file, à partir de 1.
+    pub position: usize,
+    pub state: ExportJobState,
+    pub zip_path: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ExportProgress {
+    pub fn new(position: usize, job: &ExportJob) -> Self {
+        ExportProgress {
+            project: job.project.clone(),
+            position,
+            state: job.state,
+            zip_path: job.zip_path.clone(),
+            error: job.error.clone(),
+        }
+    }
+}
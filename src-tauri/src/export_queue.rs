@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::events::ExportProgress;
+use crate::utils::{ExportOptions, ExportTarget};
+
+/// État d'un export de la file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportJobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl ExportJobState {
+    /// Vrai si l'export ne sera plus traité (terminé, en échec ou annulé).
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            ExportJobState::Done | ExportJobState::Failed | ExportJobState::Cancelled
+        )
+    }
+}
+
+/// Export d'un projet enregistré dans la file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportJob {
+    pub project: String,
+    pub target: ExportTarget,
+    pub options: ExportOptions,
+    pub state: ExportJobState,
+    /// Le chemin de l'archive ou du dossier créé, une fois l'export terminé.
+    pub zip_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    jobs: Vec<ExportJob>,
+    worker_running: bool,
+}
+
+/// File d'exports traitée un projet après l'autre par une seule tâche de fond.
+/// Elle est conservée dans l'état de l'application : les exports se poursuivent
+/// quand l'interface change de vue.
+#[derive(Debug, Default)]
+pub struct ExportQueue {
+    state: Mutex<QueueState>,
+}
+
+impl ExportQueue {
+    /// Ajoute des exports à la file. Les projets déjà en attente ou en cours d'export
+    /// sont ignorés ; si la file est inactive, les exports terminés en sont retirés.
+    ///
+    /// # Arguments
+    ///
+    /// * `projects` - Les noms des projets à exporter, dans l'ordre de traitement.
+    /// * `target` - Le format d'export.
+    /// * `options` - Le contenu optionnel des archives zip.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<ExportProgress>` - L'état des exports ajoutés.
+    pub fn enqueue(
+        &self,
+        projects: &[String],
+        target: ExportTarget,
+        options: ExportOptions,
+    ) -> Vec<ExportProgress> {
+        let mut state = self.state.lock().unwrap();
+        if !state.worker_running {
+            state.jobs.retain(|job| !job.state.is_finished());
+        }
+
+        let mut queued = Vec::new();
+        for project in projects {
+            let pending = state
+                .jobs
+                .iter()
+                .any(|job| job.project == *project && !job.state.is_finished());
+            if pending {
+                continue;
+            }
+            state.jobs.push(ExportJob {
+                project: project.clone(),
+                target,
+                options,
+                state: ExportJobState::Queued,
+                zip_path: None,
+                error: None,
+            });
+            queued.push(ExportProgress::new(
+                state.jobs.len(),
+                &state.jobs[state.jobs.len() - 1],
+            ));
+        }
+        queued
+    }
+
+    /// Les exports de la file, dans leur ordre de traitement.
+    pub fn jobs(&self) -> Vec<ExportJob> {
+        self.state.lock().unwrap().jobs.clone()
+    }
+
+    /// Annule un export qui n'a pas encore commencé.
+    ///
+    /// # Arguments
+    ///
+    /// * `project` - Le nom du projet.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ExportProgress, String>` - Le nouvel état de l'export, ou une erreur
+    ///   s'il est déjà en cours ou absent de la file.
+    pub fn cancel(&self, project: &str) -> Result<ExportProgress, String> {
+        let mut state = self.state.lock().unwrap();
+        let Some(index) = state
+            .jobs
+            .iter()
+            .position(|job| job.project == project && !job.state.is_finished())
+        else {
+            return Err(format!(
+                "Aucun export en attente pour le projet {}",
+                project
+            ));
+        };
+
+        let job = &mut state.jobs[index];
+        if job.state == ExportJobState::Running {
+            return Err(format!("L'export du projet {} est déjà en cours", project));
+        }
+        job.state = ExportJobState::Cancelled;
+        Ok(ExportProgress::new(index + 1, job))
+    }
+
+    /// Réserve le traitement de la file pour l'appelant.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Vrai si aucune tâche ne traite la file et qu'un export est en attente :
+    ///   l'appelant doit alors lancer `run_worker`.
+    pub fn claim_worker(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let has_queued = state
+            .jobs
+            .iter()
+            .any(|job| job.state == ExportJobState::Queued);
+        if state.worker_running || !has_queued {
+            return false;
+        }
+        state.worker_running = true;
+        true
+    }
+
+    /// Traite les exports en attente un par un jusqu'à ce que la file soit vide.
+    /// À n'appeler qu'après avoir obtenu la file avec `claim_worker`.
+    ///
+    /// # Arguments
+    ///
+    /// * `export` - Exporte un projet et renvoie le chemin de l'export.
+    /// * `on_progress` - Reçoit chaque changement d'état d'un export.
+    pub fn run_worker<F, P>(&self, mut export: F, mut on_progress: P)
+    where
+        F: FnMut(&ExportJob) -> Result<PathBuf, String>,
+        P: FnMut(ExportProgress),
+    {
+        while let Some((index, job)) = self.start_next() {
+            on_progress(ExportProgress::new(index + 1, &job));
+
+            let result = export(&job);
+
+            let mut state = self.state.lock().unwrap();
+            let job = &mut state.jobs[index];
+            match result {
+                Ok(path) => {
+                    job.state = ExportJobState::Done;
+                    job.zip_path = Some(path.to_string_lossy().to_string());
+                }
+                Err(message) => {
+                    job.state = ExportJobState::Failed;
+                    job.error = Some(message);
+                }
+            }
+            let progress = ExportProgress::new(index + 1, job);
+            drop(state);
+            on_progress(progress);
+        }
+    }
+
+    /// Passe le prochain export en attente en cours, ou libère la file s'il n'y en a plus.
+    fn start_next(&self) -> Option<(usize, ExportJob)> {
+        let mut state = self.state.lock().unwrap();
+        match state
+            .jobs
+            .iter()
+            .position(|job| job.state == ExportJobState::Queued)
+        {
+            Some(index) => {
+                state.jobs[index].state = ExportJobState::Running;
+                Some((index, state.jobs[index].clone()))
+            }
+            None => {
+                state.worker_running = false;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use futures_util::future::BoxFuture;
+use image::{Rgb, RgbImage};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app_setup::RunConfig;
+use crate::gis_operation::layers::download_satellite_jpeg;
+use crate::progress::ProgressSink;
+use crate::utils::{
+    BoundingBox, WorkDir, download_parallelism, offline_fixture_dir, save_rgb_jpeg,
+    write_world_file,
+};
+use crate::web_request::{
+    ArchiveUrl, DepartmentArchives, DepartmentUrls, DownloadJob, DownloadProgress,
+    download_archives, download_replacements, get_shp_file_urls, remove_part_files,
+    unique_part_path,
+};
+
+/// Accès réseau de la création d'un projet : recherche des archives IGN,
+/// téléchargement des archives dans le cache et de l'orthophoto.
+/// Permet de créer un projet hors ligne à partir d'archives locales (voir `FixtureFetcher`).
+pub trait DataFetcher: Send + Sync {
+    /// Recherche les archives (BDTOPO, BD Forêt, RPG) de chaque département.
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>>;
+
+    /// Place les archives dans le cache, voir `web_request::download_archives`.
+    fn download_archives<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+        on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>>;
+
+    /// Télécharge une nouvelle version d'archives du cache dans des fichiers `.part`, sans
+    /// toucher aux archives en place, voir `web_request::download_replacements`.
+    fn download_replacements<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>>;
+
+    /// Écrit l'orthophoto JPEG (et son fichier de géoréférencement) de l'emprise du projet,
+    /// exprimée dans le système de coordonnées `epsg`. Le GeoPackage régional du projet
+    /// permet de ne pas télécharger les zones en mer (voir `download_satellite_jpeg`).
+    /// La résolution, les sources et la qualité de l'image sont celles de `run_config`.
+    fn download_ortho(
+        &self,
+        run_config: &RunConfig,
+        output_jpg_path: &str,
+        project_bb: &BoundingBox,
+        epsg: u32,
+        regional_gpkg: Option<&str>,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), String>;
+}
+
+/// Téléchargements depuis les serveurs de l'IGN et les services WMS configurés.
+pub struct IgnFetcher;
+
+impl DataFetcher for IgnFetcher {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        Box::pin(get_shp_file_urls(codes))
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+        on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        Box::pin(async move {
+            download_archives(jobs, download_parallelism(), on_progress)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        Box::pin(async move {
+            download_replacements(jobs, download_parallelism())
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    fn download_ortho(
+        &self,
+        run_config: &RunConfig,
+        output_jpg_path: &str,
+        project_bb: &BoundingBox,
+        epsg: u32,
+        regional_gpkg: Option<&str>,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        let work_dir = WorkDir::new(run_config, "ortho").map_err(|e| e.to_string())?;
+        download_satellite_jpeg(
+            &work_dir,
+            output_jpg_path,
+            project_bb,
+            epsg,
+            regional_gpkg,
+            progress,
+            run_config,
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Nom de l'orthophoto facultative d'un dossier d'archives locales.
+pub const FIXTURE_ORTHO_FILE: &str = "ORTHO.jpeg";
+
+/// Couleur de l'orthophoto générée lorsque le dossier d'archives locales n'en contient pas.
+const FIXTURE_ORTHO_COLOR: [u8; 3] = [128, 128, 128];
+
+/// Archives lues dans un dossier local, sans accès réseau.
+/// Les archives suivent le nommage du cache (`BDTOPO_2A.7z`, `BDFORET_2A.7z`, `RPG_2A.7z`).
+/// L'orthophoto est copiée depuis `ORTHO.jpeg` si le dossier en contient une,
+/// sinon une image grise de la taille du projet est générée.
+pub struct FixtureFetcher {
+    pub fixture_dir: PathBuf,
+}
+
+impl FixtureFetcher {
+    pub fn new(fixture_dir: impl Into<PathBuf>) -> Self {
+        FixtureFetcher {
+            fixture_dir: fixture_dir.into(),
+        }
+    }
+
+    fn archive_path(&self, file_type: &str, code: &str) -> PathBuf {
+        self.fixture_dir.join(format!("{}_{}.7z", file_type, code))
+    }
+
+    fn archive_url(&self, file_type: &str, code: &str) -> Result<ArchiveUrl, String> {
+        let path = self.archive_path(file_type, code);
+        if !path.exists() {
+            return Err(format!(
+                "{} introuvable pour le département {}: {} absent",
+                file_type,
+                code,
+                path.display()
+            ));
+        }
+        Ok(ArchiveUrl::new(&format!("file://{}", path.display())))
+    }
+
+    fn department_archives(&self, code: &str) -> Result<DepartmentArchives, String> {
+        Ok(DepartmentArchives {
+            topo: self.archive_url("BDTOPO", code)?,
+            foret: self.archive_url("BDFORET", code)?,
+            rpg: self.archive_url("RPG", code)?,
+        })
+    }
+}
+
+impl DataFetcher for FixtureFetcher {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        Box::pin(async move {
+            codes
+                .iter()
+                .map(|code| DepartmentUrls {
+                    code: code.clone(),
+                    archives: self.department_archives(code),
+                    network_error: false,
+                })
+                .collect()
+        })
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+        on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        Box::pin(async move {
+            let pending: Vec<DownloadJob> = jobs
+                .into_iter()
+                .filter(|job| !job.cache_path().exists())
+                .collect();
+            let total = pending.len();
+            let mut copied_bytes = 0;
+            for (idx, job) in pending.iter().enumerate() {
+                let source = self.archive_path(&job.file_type, &job.code);
+                let destination = job.cache_path();
+                if let Some(parent) = destination.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                copied_bytes += fs::copy(&source, &destination)
+                    .map_err(|e| format!("Échec de la copie de {}: {}", source.display(), e))?;
+                on_progress(DownloadProgress {
+                    completed: idx + 1,
+                    total,
+                    downloaded_bytes: copied_bytes,
+                    label: format!("{} {}", job.file_type, job.code),
+                    archive_seconds: Some(0.0),
+                });
+            }
+            Ok(total)
+        })
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        Box::pin(async move {
+            let mut copied: Vec<(DownloadJob, PathBuf)> = Vec::new();
+            for job in jobs {
+                let source = self.archive_path(&job.file_type, &job.code);
+                let part_path = unique_part_path(&job.cache_path());
+                if let Err(e) = fs::copy(&source, &part_path) {
+                    let mut part_paths: Vec<PathBuf> =
+                        copied.into_iter().map(|(_, part)| part).collect();
+                    part_paths.push(part_path);
+                    remove_part_files(&part_paths);
+                    return Err(format!("Échec de la copie de {}: {}", source.display(), e));
+                }
+                copied.push((job, part_path));
+            }
+            Ok(copied)
+        })
+    }
+
+    fn download_ortho(
+        &self,
+        run_config: &RunConfig,
+        output_jpg_path: &str,
+        project_bb: &BoundingBox,
+        _epsg: u32,
+        _regional_gpkg: Option<&str>,
+        _progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        let resolution = run_config.resolution;
+        let fixture_ortho = self.fixture_dir.join(FIXTURE_ORTHO_FILE);
+        if fixture_ortho.exists() {
+            fs::copy(&fixture_ortho, output_jpg_path).map_err(|e| e.to_string())?;
+        } else {
+            let (width, height) = project_bb
+                .raster_size(resolution)
+                .map_err(|e| e.to_string())?;
+            let image = RgbImage::from_pixel(width as u32, height as u32, Rgb(FIXTURE_ORTHO_COLOR));
+            save_rgb_jpeg(&image, output_jpg_path, run_config.ortho_jpeg_quality())
+                .map_err(|e| e.to_string())?;
+        }
+        let geo_transform = [
+            project_bb.xmin,
+            resolution,
+            0.0,
+            project_bb.ymax,
+            0.0,
+            -resolution,
+        ];
+        write_world_file(output_jpg_path, &geo_transform).map_err(|e| e.to_string())
+    }
+}
+
+/// Accès aux données de la création : les archives du dossier `offline_fixture_dir`
+/// de la configuration s'il est renseigné, les serveurs de l'IGN sinon.
+pub fn data_fetcher() -> Box<dyn DataFetcher> {
+    match offline_fixture_dir() {
+        Some(fixture_dir) => {
+            tracing::info!(
+                folder = %fixture_dir.display(),
+                "Création hors ligne à partir des archives locales"
+            );
+            Box::new(FixtureFetcher::new(fixture_dir))
+        }
+        None => Box::new(IgnFetcher),
+    }
+}
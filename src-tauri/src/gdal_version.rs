@@ -0,0 +1,93 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version de GDAL détectée au démarrage (`RELEASE_NAME` de la bibliothèque liée).
+/// Les utilitaires en ligne de commande (`ogr2ogr`, `gdal_rasterize`) sont supposés
+/// provenir de la même installation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GdalVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+/// Version minimale de GDAL prise en charge : en dessous, un avertissement est affiché
+/// par l'écran de configuration et le diagnostic.
+pub const MIN_SUPPORTED_GDAL: GdalVersion = GdalVersion::new(3, 4, 0);
+
+/// Première version dont ogr2ogr corrige les anneaux non fermés avec `-makevalid` plutôt
+/// qu'avec les options `OGR_GEOMETRY_*`, que les versions récentes n'appliquent plus.
+pub const MAKEVALID_GDAL: GdalVersion = GdalVersion::new(3, 10, 0);
+
+lazy_static! {
+    static ref CURRENT: GdalVersion = GdalVersion::detect().unwrap_or_else(|| {
+        tracing::warn!(
+            release = %gdal::version::version_info("RELEASE_NAME"),
+            "Version de GDAL illisible, arguments de la version minimale utilisés"
+        );
+        MIN_SUPPORTED_GDAL
+    });
+}
+
+impl GdalVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        GdalVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Lit une version au format `RELEASE_NAME` (`3.8.4`, `3.11.0dev`, `3.10.0rc1`...).
+    /// Les suffixes de préversion sont ignorés ; un numéro de correctif absent vaut 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `release_name` - le nom de version renvoyé par GDAL
+    ///
+    /// # Returns
+    ///
+    /// * `Option<GdalVersion>` - la version, ou `None` si le texte n'en est pas une
+    pub fn parse(release_name: &str) -> Option<Self> {
+        let mut numbers = release_name.trim().split('.').map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse::<u32>().ok()
+        });
+        let major = numbers.next()??;
+        let minor = numbers.next()??;
+        let patch = numbers.next().flatten().unwrap_or(0);
+        Some(GdalVersion::new(major, minor, patch))
+    }
+
+    /// Version de la bibliothèque GDAL liée à l'application.
+    pub fn detect() -> Option<Self> {
+        Self::parse(&gdal::version::version_info("RELEASE_NAME"))
+    }
+
+    /// Version détectée une fois pour toute l'application ; la version minimale prise en
+    /// charge si elle est illisible.
+    pub fn current() -> Self {
+        *CURRENT
+    }
+
+    pub fn is_supported(self) -> bool {
+        self >= MIN_SUPPORTED_GDAL
+    }
+
+    /// Avertissement affiché pour une version antérieure à la version minimale.
+    pub fn support_warning(self) -> Option<String> {
+        (!self.is_supported()).then(|| {
+            format!(
+                "GDAL {} est antérieur à la version minimale prise en charge ({}) : les couches produites peuvent être incorrectes",
+                self, MIN_SUPPORTED_GDAL
+            )
+        })
+    }
+}
+
+impl fmt::Display for GdalVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
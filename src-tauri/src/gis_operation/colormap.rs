@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::sql::{build_in_clause, build_not_in_clause, sql_literal};
+use crate::utils::in_resource_dir;
+
+/// Couleur RGB appliquée sur le raster du projet.
+pub type Rgb = [u8; 3];
+
+/// Fond départemental (terres sans autre couverture), par défaut des nouveaux projets :
+/// distinct du noir des bâtiments et des routes, non combustibles.
+pub const REGIONAL: Rgb = [120, 110, 70];
+
+/// Fond départemental des projets antérieurs, confondu avec le noir de `TOPO`.
+/// Conservé à leur régénération pour ne pas changer leur palette.
+pub const LEGACY_REGIONAL: Rgb = [0, 0, 0];
+
+/// Forêts de feuillus.
+pub const FEUILLUS: Rgb = [80, 200, 120];
+
+/// Formations végétales non renseignées (NC / NR).
+pub const UNDEFINED_VEGETATION: Rgb = [25, 50, 60];
+
+/// Autres formations végétales (conifères, landes, mélanges...).
+pub const OTHER_VEGETATION: Rgb = [50, 200, 80];
+
+/// Parcelles agricoles du RPG dont le groupe de cultures n'est pas classé
+/// (voir `RPG_CLASSES_FILE`).
+pub const RPG: Rgb = [25, 50, 60];
+
+/// Éléments topographiques non combustibles (bâtiments, routes, voies ferrées...).
+pub const TOPO: Rgb = [0, 0, 0];
+
+/// Surfaces et cours d'eau.
+pub const HYDRO: Rgb = [30, 80, 200];
+
+/// Bâtiments hauts (voir `BuildingHeights`), distingués des autres bâtiments
+/// pour repérer les zones bâties denses.
+pub const BUILDING_HIGH: Rgb = [90, 90, 90];
+
+/// Attribut de la BDTOPO donnant la hauteur d'un bâtiment, en mètres.
+pub const HEIGHT_ATTRIBUTE: &str = "HAUTEUR";
+
+/// Code de classe : aucune donnée (hors région).
+pub const CLASS_UNKNOWN: u8 = 0;
+/// Code de classe : feuillus.
+pub const CLASS_FEUILLUS: u8 = 1;
+/// Code de classe : conifères et autres formations végétales.
+pub const CLASS_OTHER_VEGETATION: u8 = 2;
+/// Code de classe : parcelles agricoles.
+pub const CLASS_RPG: u8 = 3;
+/// Code de classe : bâtiments et constructions.
+pub const CLASS_BUILDING: u8 = 4;
+/// Code de classe : routes et voies ferrées.
+pub const CLASS_ROAD: u8 = 5;
+/// Code de classe : eau.
+pub const CLASS_WATER: u8 = 6;
+/// Code de classe : végétation non renseignée.
+pub const CLASS_UNDEFINED_VEGETATION: u8 = 7;
+/// Code de classe : autres infrastructures topographiques.
+pub const CLASS_OTHER_TOPO: u8 = 8;
+/// Code de classe : terres de la région sans autre couverture.
+pub const CLASS_LAND: u8 = 9;
+/// Code de classe : bâtiments hauts.
+pub const CLASS_BUILDING_HIGH: u8 = 10;
+
+/// Entrée de la table des classes exportée avec le raster de classes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ClassEntry {
+    pub code: u8,
+    pub key: &'static str,
+    pub label: &'static str,
+    pub color: Rgb,
+}
+
+/// Table des classes du raster `<nom>_CLASSES.tif`.
+pub const CLASSES: [ClassEntry; 11] = [
+    ClassEntry {
+        code: CLASS_UNKNOWN,
+        key: "unknown",
+        label: "Hors zone",
+        color: [0, 0, 0],
+    },
+    ClassEntry {
+        code: CLASS_FEUILLUS,
+        key: "feuillus",
+        label: "Feuillus",
+        color: FEUILLUS,
+    },
+    ClassEntry {
+        code: CLASS_OTHER_VEGETATION,
+        key: "other_vegetation",
+        label: "Conifères et autres formations",
+        color: OTHER_VEGETATION,
+    },
+    ClassEntry {
+        code: CLASS_RPG,
+        key: "rpg",
+        label: "Parcelles agricoles",
+        color: RPG,
+    },
+    ClassEntry {
+        code: CLASS_BUILDING,
+        key: "building",
+        label: "Bâtiments",
+        color: TOPO,
+    },
+    ClassEntry {
+        code: CLASS_ROAD,
+        key: "road",
+        label: "Routes et voies ferrées",
+        color: TOPO,
+    },
+    ClassEntry {
+        code: CLASS_WATER,
+        key: "water",
+        label: "Eau",
+        color: HYDRO,
+    },
+    ClassEntry {
+        code: CLASS_UNDEFINED_VEGETATION,
+        key: "undefined_vegetation",
+        label: "Végétation non renseignée",
+        color: UNDEFINED_VEGETATION,
+    },
+    ClassEntry {
+        code: CLASS_OTHER_TOPO,
+        key: "other_topo",
+        label: "Autres infrastructures",
+        color: TOPO,
+    },
+    ClassEntry {
+        code: CLASS_LAND,
+        key: "land",
+        label: "Terres sans couverture connue",
+        color: REGIONAL,
+    },
+    ClassEntry {
+        code: CLASS_BUILDING_HIGH,
+        key: "building_high",
+        label: "Bâtiments hauts",
+        color: BUILDING_HIGH,
+    },
+];
+
+/// Répartition des bâtiments selon leur hauteur (`HEIGHT_ATTRIBUTE`), section
+/// `building_heights` de la configuration. Les bâtiments de hauteur inconnue sont
+/// considérés comme bas.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildingHeights {
+    /// Hauteur (en mètres) à partir de laquelle un bâtiment est haut.
+    pub threshold_m: f64,
+    /// Couleur des bâtiments bas ou de hauteur inconnue (classe `building`).
+    pub low_color: Rgb,
+    /// Couleur des bâtiments hauts (classe `building_high`).
+    pub high_color: Rgb,
+}
+
+impl Default for BuildingHeights {
+    fn default() -> Self {
+        Self {
+            threshold_m: 8.0,
+            low_color: TOPO,
+            high_color: BUILDING_HIGH,
+        }
+    }
+}
+
+impl BuildingHeights {
+    /// Vérifie que le seuil est un nombre positif et que les deux couleurs diffèrent.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.threshold_m.is_finite() || self.threshold_m <= 0.0 {
+            return Err(format!(
+                "Seuil de hauteur des bâtiments invalide: {}",
+                self.threshold_m
+            ));
+        }
+        if self.low_color == self.high_color {
+            return Err("Les bâtiments bas et hauts doivent avoir des couleurs différentes".into());
+        }
+        Ok(())
+    }
+
+    /// Filtre attributaire sélectionnant les bâtiments hauts.
+    pub fn high_where(&self) -> String {
+        format!("{} >= {}", HEIGHT_ATTRIBUTE, self.threshold_m)
+    }
+
+    /// Filtre attributaire sélectionnant les bâtiments bas ou de hauteur inconnue.
+    pub fn low_where(&self) -> String {
+        format!(
+            "{attribute} IS NULL OR {attribute} < {threshold}",
+            attribute = HEIGHT_ATTRIBUTE,
+            threshold = self.threshold_m
+        )
+    }
+}
+
+/// Retrouve la classe d'un pixel du raster de végétation à partir de sa couleur.
+pub fn vegetation_class(color: Rgb) -> u8 {
+    match color {
+        FEUILLUS => CLASS_FEUILLUS,
+        UNDEFINED_VEGETATION => CLASS_UNDEFINED_VEGETATION,
+        _ => CLASS_OTHER_VEGETATION,
+    }
+}
+
+/// Retrouve la classe d'une couche topographique à partir de son nom.
+pub fn topo_class(layer_name: &str) -> u8 {
+    match layer_name.to_uppercase().as_str() {
+        "BATIMENT" | "CONSTRUCTION_SURFACIQUE" => CLASS_BUILDING,
+        "TRONCON_DE_ROUTE" | "VOIE_NOMMEE" | "TRONCON_DE_VOIE_FERREE" => CLASS_ROAD,
+        _ => CLASS_OTHER_TOPO,
+    }
+}
+
+/// Écrit la table des classes au format JSON.
+///
+/// # Arguments
+///
+/// * `output_path` - chemin du fichier JSON de sortie
+/// * `land_color` - couleur du fond départemental du projet (voir `ProjectMetadata::land_color`)
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'écriture a réussi ou échoué
+pub fn write_class_legend(
+    output_path: &Path,
+    land_color: Rgb,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let classes: Vec<ClassEntry> = CLASSES
+        .iter()
+        .map(|entry| ClassEntry {
+            color: if entry.code == CLASS_LAND {
+                land_color
+            } else {
+                entry.color
+            },
+            ..*entry
+        })
+        .collect();
+    let json = serde_json::to_string_pretty(&classes)?;
+    std::fs::write(output_path, json)?;
+    Ok(())
+}
+
+/// Fichier de correspondance entre les groupes de cultures du RPG et les classes de combustible.
+pub const RPG_CLASSES_FILE: &str = "rpg_classes.json";
+
+/// Classe de combustible regroupant des cultures du RPG.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpgClass {
+    pub key: String,
+    pub label: String,
+    /// Libellé anglais de la légende, le libellé français à défaut.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_en: Option<String>,
+    pub color: Rgb,
+    /// Valeurs de l'attribut de culture rattachées à la classe.
+    pub codes: Vec<String>,
+}
+
+/// Table des classes de combustible du RPG.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpgClassTable {
+    /// Attribut des parcelles portant le code de culture (`CODE_GROUP` ou `CODE_CULTU`).
+    pub attribute: String,
+    pub classes: Vec<RpgClass>,
+}
+
+impl RpgClassTable {
+    /// Lit et valide une table de classes RPG.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - chemin du fichier JSON
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RpgClassTable, Box<dyn std::error::Error>>` - la table, ou une erreur si elle est invalide
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let table: RpgClassTable = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// Vérifie que l'attribut est un nom de champ valide et qu'aucun code n'appartient à deux classes.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.attribute.is_empty()
+            || !self
+                .attribute
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(format!("Attribut RPG invalide: '{}'", self.attribute));
+        }
+
+        let mut seen = HashSet::new();
+        for class in &self.classes {
+            if class.color == RPG {
+                return Err(format!(
+                    "La classe RPG '{}' utilise la couleur des parcelles non classées",
+                    class.key
+                ));
+            }
+            for code in &class.codes {
+                sql_literal(code).map_err(|e| format!("Code RPG invalide: {}", e))?;
+                if !seen.insert(code.as_str()) {
+                    return Err(format!("Code RPG en double: '{}'", code));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Filtre attributaire sélectionnant les parcelles d'une classe.
+    pub fn where_clause(&self, class: &RpgClass) -> Result<String, String> {
+        build_in_clause(&self.attribute, &class.codes)
+    }
+
+    /// Filtre attributaire sélectionnant les parcelles sans code ou de code inconnu.
+    pub fn unclassified_where(&self) -> Result<String, String> {
+        let codes: Vec<String> = self
+            .classes
+            .iter()
+            .flat_map(|class| class.codes.iter().cloned())
+            .collect();
+        if codes.is_empty() {
+            return Ok("1 = 1".to_string());
+        }
+        Ok(format!(
+            "{} IS NULL OR {}",
+            self.attribute,
+            build_not_in_clause(&self.attribute, &codes)?
+        ))
+    }
+}
+
+/// Charge la table des classes RPG fournie avec l'application (`resources/rpg_classes.json`).
+///
+/// # Returns
+///
+/// * `Result<RpgClassTable, Box<dyn std::error::Error>>` - la table des classes
+pub fn load_rpg_classes() -> Result<RpgClassTable, Box<dyn std::error::Error>> {
+    RpgClassTable::load_from(&in_resource_dir(RPG_CLASSES_FILE))
+}
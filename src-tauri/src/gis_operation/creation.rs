@@ -0,0 +1,443 @@
+//! Création d'un projet sans interface : la création complète de `create_project_com`
+//! (nom, conflit, limites de taille, réutilisation d'un projet existant, pipeline), appelable
+//! depuis un script ou une intégration continue. La progression est transmise à un
+//! `ProgressSink` et les archives sont obtenues par un `DataFetcher`.
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use super::derive::{derive_project, find_containing_project};
+use super::ortho::sources_by_priority;
+use super::oso::VegetationSource;
+
+use crate::app_setup::RunConfig;
+use crate::busy::{BusyGuard, BusyKind, BusyProjects};
+use crate::estimate::{ProjectLimits, ProjectSizeError, check_project_size};
+use crate::events::Warning;
+use crate::fetcher::DataFetcher;
+use crate::i18n;
+use crate::pipeline::{self, CreationOutcome};
+use crate::progress::ProgressSink;
+use crate::utils::{
+    self, BoundingBox, ConflictMode, CreationParams, NameError, PROJECT_GRID_PIXELS,
+    ProjectConflict, ProjectSummary, load_project_metadata, move_project_to_trash,
+    project_conflict, project_summary, restore_project_from_trash, save_project_metadata,
+    validate_project_name,
+};
+
+/// Demande de création d'un projet.
+#[derive(Debug)]
+pub struct CreationRequest {
+    /// Nom du projet, refusé s'il ne peut pas servir de nom de dossier.
+    pub name: String,
+    pub project_bb: BoundingBox,
+    /// Source de végétation et couches retenues. La résolution et la source d'orthophoto
+    /// sont toujours celles des paramètres de l'application.
+    pub params: CreationParams,
+    /// Conduite à tenir si un projet du même nom existe.
+    pub on_conflict: ConflictMode,
+    /// Découpe d'un projet terminé qui contient l'emprise : non renseignée, le projet
+    /// est signalé sans rien créer (`FirefrontError::ReuseAvailable`).
+    pub allow_reuse: Option<bool>,
+    /// Dérogation aux limites de taille d'un projet.
+    pub allow_oversize: bool,
+    /// Projet dont les paramètres de création complètent `params`.
+    pub template_project: Option<String>,
+    /// Projets occupés : le nom du projet est réservé pendant la création, et l'annulation
+    /// se demande par `BusyProjects::request_cancel`.
+    pub busy: BusyProjects,
+    /// Réservation du nom déjà obtenue par l'appelant (`clone_project`), reprise par la
+    /// création : le nom n'est jamais libéré entre les deux tâches.
+    pub reservation: Option<BusyGuard>,
+    /// Projet terminé dont les couches sont reprises pour les départements qu'il couvre,
+    /// faute de projet contenant toute l'emprise (voir `pipeline::create_project_reusing`).
+    pub partial_source: Option<String>,
+}
+
+impl CreationRequest {
+    /// Création d'un projet avec les paramètres par défaut.
+    pub fn new(name: impl Into<String>, project_bb: BoundingBox) -> Self {
+        CreationRequest {
+            name: name.into(),
+            project_bb,
+            params: CreationParams::default(),
+            on_conflict: ConflictMode::default(),
+            allow_reuse: None,
+            allow_oversize: false,
+            template_project: None,
+            busy: BusyProjects::default(),
+            reservation: None,
+            partial_source: None,
+        }
+    }
+}
+
+/// Raison pour laquelle une création n'a pas abouti.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirefrontError {
+    /// Le nom ne peut pas servir de nom de dossier.
+    InvalidName(NameError),
+    /// Un projet du même nom existe et `on_conflict` vaut `abort` : rien n'a été créé.
+    Conflict(ProjectConflict),
+    /// Une tâche est déjà en cours sous ce nom : rien n'a été fait.
+    InProgress(BusyKind),
+    /// Le projet `source` contient l'emprise et `allow_reuse` n'est pas renseigné :
+    /// rien n'a été créé.
+    ReuseAvailable {
+        source: String,
+    },
+    /// L'emprise dépasse les limites de taille, sans dérogation.
+    ProjectSize(ProjectSizeError),
+    Cancelled,
+    /// Échec de la création du projet `project` (nom libre retenu compris).
+    Failed {
+        project: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for FirefrontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirefrontError::InvalidName(e) => {
+                f.write_str(&i18n::error("error.invalid_project_name", e))
+            }
+            FirefrontError::Conflict(conflict) => {
+                f.write_str(&i18n::text("error.project_exists", &[&conflict.name]))
+            }
+            FirefrontError::InProgress(kind) => f.write_str(&kind.label()),
+            FirefrontError::ReuseAvailable { source } => {
+                f.write_str(&i18n::text("error.reuse_available", &[source]))
+            }
+            FirefrontError::ProjectSize(e) => f.write_str(&i18n::error("error.project_size", e)),
+            FirefrontError::Cancelled => f.write_str(&i18n::text("error.creation_cancelled", &[])),
+            FirefrontError::Failed { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl Error for FirefrontError {}
+
+/// Crée un projet : vérifie son nom et ses limites de taille, résout un conflit de nom,
+/// découpe un projet existant qui contient l'emprise si la réutilisation est autorisée,
+/// ou exécute le pipeline de création complet. Un projet remplacé (`ConflictMode::Overwrite`)
+/// n'est supprimé qu'une fois la création terminée, et restauré en cas d'échec.
+/// Les paramètres de la création sont enregistrés dans les métadonnées du projet.
+///
+/// # Arguments
+///
+/// * `request` - La demande de création.
+/// * `warnings` - Avertissements de la création, complétés au fil des étapes.
+/// * `progress` - Destination des messages de progression.
+/// * `fetcher` - Accès aux archives et à l'orthophoto (`FixtureFetcher` pour des archives locales).
+///
+/// # Returns
+///
+/// * `Result<ProjectSummary, FirefrontError>` - Le résumé du projet créé, ou la raison pour
+///   laquelle il ne l'a pas été.
+pub async fn create_project_pipeline(
+    request: CreationRequest,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+) -> Result<ProjectSummary, FirefrontError> {
+    let CreationRequest {
+        name,
+        project_bb,
+        params,
+        on_conflict,
+        allow_reuse,
+        allow_oversize,
+        template_project,
+        busy,
+        reservation,
+        partial_source,
+    } = request;
+    let failed = |project: &str, message: String| FirefrontError::Failed {
+        project: project.to_string(),
+        message,
+    };
+
+    // Les paramètres modifiés pendant la création ne s'appliquent qu'aux créations suivantes.
+    let run_config = RunConfig::snapshot();
+    let params = creation_params(params, template_project.as_deref(), &run_config, warnings)
+        .map_err(|message| failed(&name, message))?;
+    validate_project_name(&name).map_err(FirefrontError::InvalidName)?;
+
+    // Le nom demandé reste occupé jusqu'à la fin de la création : une seconde demande
+    // (double clic, nouvel envoi du formulaire) est écartée avant toute écriture, et le projet
+    // ne peut pas être supprimé entre-temps. La marque est rendue même en cas de panique.
+    let mut busy_guard = match reservation {
+        Some(reservation) => reservation,
+        None => busy
+            .try_acquire(&name, BusyKind::Creating)
+            .map_err(FirefrontError::InProgress)?,
+    };
+    // Sous un nom libre, le nom demandé reste réservé en plus du nouveau nom.
+    let mut _requested_guard = None;
+
+    let mut name = name;
+    let mut overwrite = false;
+    if let Some(conflict) = project_conflict(&name) {
+        match on_conflict {
+            ConflictMode::Abort => return Err(FirefrontError::Conflict(conflict)),
+            ConflictMode::Rename => {
+                name = conflict.suggested_name;
+                let renamed_guard = busy
+                    .try_acquire(&name, BusyKind::Creating)
+                    .map_err(FirefrontError::InProgress)?;
+                _requested_guard = Some(std::mem::replace(&mut busy_guard, renamed_guard));
+            }
+            ConflictMode::Overwrite => overwrite = true,
+        }
+    }
+
+    let size_override =
+        check_project_extent(&name, &project_bb, run_config.resolution, allow_oversize)?;
+    if let Some(exceeded) = &size_override {
+        tracing::warn!(project = %name, limit = %exceeded, "Création au-delà des limites de taille");
+    }
+
+    let vegetation_source =
+        VegetationSource::parse(params.vegetation_source.as_deref().unwrap_or_default())
+            .map_err(|message| failed(&name, message))?;
+    let layer_config =
+        pipeline::creation_layer_config(vegetation_source.clone(), params.layers.as_deref())
+            .map_err(|message| failed(&name, message))?;
+    let reuse_source = match allow_reuse {
+        None | Some(true) => find_containing_project(
+            &project_bb,
+            &vegetation_source,
+            params.layers.as_deref(),
+            &name,
+            run_config.resolution,
+        ),
+        Some(false) => None,
+    };
+    if let (Some(source), None) = (&reuse_source, allow_reuse) {
+        return Err(FirefrontError::ReuseAvailable {
+            source: source.clone(),
+        });
+    }
+
+    // Le projet remplacé est écarté plutôt que supprimé, le temps de la nouvelle création.
+    let trashed = if overwrite {
+        let trashed = move_project_to_trash(&name)
+            .map_err(|e| failed(&name, i18n::error("error.trash_project", e)))?;
+        Some(trashed)
+    } else {
+        None
+    };
+
+    let outcome = match reuse_source {
+        Some(source) => {
+            derive_creation(&source, &name, &project_bb, warnings, progress, &run_config)
+        }
+        None => match &partial_source {
+            Some(source) => {
+                pipeline::create_project_reusing(
+                    source,
+                    &name,
+                    &project_bb,
+                    &layer_config,
+                    busy_guard.cancel_token(),
+                    warnings,
+                    progress,
+                    fetcher,
+                    run_config,
+                )
+                .await
+            }
+            None => {
+                pipeline::create_project_pipeline(
+                    &name,
+                    &project_bb,
+                    &layer_config,
+                    busy_guard.cancel_token(),
+                    warnings,
+                    progress,
+                    fetcher,
+                    run_config,
+                )
+                .await
+            }
+        },
+    };
+    if let Ok(CreationOutcome::Created { .. }) = &outcome {
+        let mut metadata = load_project_metadata(&name);
+        metadata.creation_params = Some(params);
+        if let Some(exceeded) = &size_override {
+            metadata.size_limit_override = Some(exceeded.to_string());
+        }
+        if let Err(e) = save_project_metadata(&name, &metadata) {
+            warnings.push(Warning::new("Métadonnées", e.to_string()));
+        }
+    }
+    if let Some(trashed) = trashed {
+        let created = matches!(outcome, Ok(CreationOutcome::Created { .. }));
+        if let Err(e) = settle_replaced_project(&name, &trashed, created) {
+            warnings.push(Warning::new("Projet remplacé", e));
+        }
+    }
+
+    match outcome {
+        Ok(CreationOutcome::Created { .. }) => Ok(project_summary(&name)),
+        Ok(CreationOutcome::Cancelled) => Err(FirefrontError::Cancelled),
+        Err(message) => Err(failed(&name, message)),
+    }
+}
+
+/// Vérifie l'emprise d'un nouveau projet avant toute écriture, pour une création comme pour
+/// une duplication sur une nouvelle emprise : alignement sur la grille de `PROJECT_GRID_PIXELS`
+/// pixels à la résolution du projet, puis limites de taille (voir `check_project_size`).
+///
+/// # Arguments
+///
+/// * `name` - Le nom du projet, rapporté dans l'erreur.
+/// * `project_bb` - L'emprise du projet.
+/// * `resolution` - La résolution du projet en mètres par pixel.
+/// * `allow_oversize` - Accepte une emprise dépassant les limites de taille.
+///
+/// # Returns
+///
+/// * `Result<Option<ProjectSizeError>, FirefrontError>` - La limite dépassée avec dérogation,
+///   ou la raison pour laquelle l'emprise est refusée.
+pub fn check_project_extent(
+    name: &str,
+    project_bb: &BoundingBox,
+    resolution: f64,
+    allow_oversize: bool,
+) -> Result<Option<ProjectSizeError>, FirefrontError> {
+    let failed = |message: String| FirefrontError::Failed {
+        project: name.to_string(),
+        message,
+    };
+    project_bb
+        .raster_size(PROJECT_GRID_PIXELS as f64 * resolution)
+        .map_err(|e| failed(i18n::error("error.project_grid", e)))?;
+    check_project_size(project_bb, &ProjectLimits::current(), allow_oversize).map_err(|e| {
+        match e.downcast_ref::<ProjectSizeError>() {
+            Some(exceeded) => FirefrontError::ProjectSize(exceeded.clone()),
+            None => failed(i18n::error("error.project_size", e)),
+        }
+    })
+}
+
+/// Assemble les paramètres d'une création : les paramètres renseignés, complétés par ceux du
+/// projet modèle, avec la résolution et la source d'orthophoto de `run_config`.
+fn creation_params(
+    params: CreationParams,
+    template_project: Option<&str>,
+    run_config: &RunConfig,
+    warnings: &mut Vec<Warning>,
+) -> Result<CreationParams, String> {
+    let params = CreationParams {
+        resolution: Some(run_config.resolution),
+        ortho_source: sources_by_priority(&run_config.ortho_sources)
+            .first()
+            .map(|source| source.name.clone()),
+        ..params
+    };
+    let Some(template_project) = template_project else {
+        return Ok(params);
+    };
+
+    let template = utils::project_creation_params(template_project)
+        .map_err(|e| i18n::error("error.creation_template", e))?;
+    tracing::info!(template = %template_project, params = ?template, "Paramètres repris d'un projet");
+    // La résolution et l'orthophoto sont des paramètres de l'application : celles du modèle
+    // ne sont pas reprises, un écart est seulement signalé.
+    let resolution = run_config.resolution;
+    if let Some(previous) = template
+        .resolution
+        .filter(|previous| *previous != resolution)
+    {
+        warnings.push(Warning::new(
+            "Projet modèle",
+            format!(
+                "Le projet {} a été créé à {} m/pixel ; la résolution actuelle ({} m/pixel) est utilisée",
+                template_project, previous, resolution
+            ),
+        ));
+    }
+    if let Some(previous) = template
+        .ortho_source
+        .as_ref()
+        .filter(|previous| params.ortho_source.as_ref() != Some(*previous))
+    {
+        warnings.push(Warning::new(
+            "Projet modèle",
+            format!(
+                "Le projet {} a utilisé l'orthophoto {} ; la source actuelle ({}) est utilisée",
+                template_project,
+                previous,
+                params.ortho_source.as_deref().unwrap_or("-")
+            ),
+        ));
+    }
+    Ok(params.or_template(&template))
+}
+
+/// Crée un projet en découpant le projet `source` qui contient son emprise, voir `derive_project`.
+/// Les départements sont ceux des millésimes du projet source.
+fn derive_creation(
+    source: &str,
+    name: &str,
+    project_bb: &BoundingBox,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    run_config: &RunConfig,
+) -> Result<CreationOutcome, String> {
+    tracing::info!(
+        project = name,
+        source,
+        "Dérivation du projet sans téléchargement"
+    );
+    let folder = derive_project(source, name, project_bb, warnings, progress, run_config)
+        .map_err(|e| i18n::error("error.derive_project", e))?;
+    let metadata = load_project_metadata(name);
+    let mut regions: Vec<String> = metadata
+        .vintages
+        .iter()
+        .map(|vintage| vintage.region.clone())
+        .collect();
+    regions.sort();
+    regions.dedup();
+    Ok(CreationOutcome::Created {
+        folder: folder.to_string_lossy().to_string(),
+        regions,
+        timings: metadata.timings,
+    })
+}
+
+/// Supprime le projet remplacé une fois la nouvelle création terminée, ou le restaure
+/// à la place de la création inachevée si elle a échoué ou a été annulée.
+///
+/// # Arguments
+///
+/// * `name` - Le nom du projet.
+/// * `trashed` - Le dossier du projet remplacé, écarté dans `.trash`.
+/// * `created` - La nouvelle création est terminée.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Une erreur si le dossier écarté n'a pas pu être supprimé ou restauré.
+pub fn settle_replaced_project(name: &str, trashed: &Path, created: bool) -> Result<(), String> {
+    let result = if created {
+        std::fs::remove_dir_all(trashed).map_err(|e| e.to_string())
+    } else {
+        restore_project_from_trash(name, trashed).map_err(|e| e.to_string())
+    };
+    match &result {
+        Ok(()) if !created => tracing::info!(project = name, "Projet remplacé restauré"),
+        Ok(()) => {}
+        Err(e) => tracing::error!(
+            project = name,
+            folder = %trashed.display(),
+            error = %e,
+            "Impossible de supprimer ou de restaurer le projet remplacé"
+        ),
+    }
+    result.map_err(|e| format!("{}: {}", trashed.display(), e))
+}
@@ -0,0 +1,362 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gdal::Dataset;
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use gdal::vector::{LayerAccess, OGRwkbGeometryType};
+use serde::{Deserialize, Serialize};
+
+use super::colormap::{self, Rgb};
+use super::processing::{ProjectRaster, apply_overlay, overlay_in_place, rasterize_layer};
+use super::tiles::build_project_overviews;
+use super::{clip_to_bb, convert_to_gpkg_in_crs, layer_names};
+
+use crate::app_setup::RunConfig;
+use crate::events::Warning;
+use crate::utils::{
+    WorkDir, crs_label, export_to_jpg, generate_project_preview, get_project_bounding_box,
+    is_project_complete, load_project_metadata, project_dir, project_tiff, project_veget_jpeg,
+    save_project_metadata,
+};
+
+/// Dossier des couches personnalisées, dans le dossier `resources` du projet.
+pub const CUSTOM_LAYERS_DIR: &str = "custom";
+
+/// Couche vectorielle fournie par l'utilisateur (coupures de combustible, points d'eau,
+/// zones interdites...) appliquée sur le raster du projet avec sa propre couleur.
+/// Son code de classe est `CLASS_OTHER_TOPO`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomLayer {
+    /// Nom de la couche, tiré du nom du fichier importé.
+    pub name: String,
+    /// Fichier importé.
+    pub source: String,
+    pub color: Rgb,
+    /// Ordre d'application par `add_layers` : la couche recouvre les couches intégrées
+    /// de priorité inférieure ou égale (voir `legend::PRIORITY_*`).
+    pub priority: u8,
+    /// Filtre attributaire OGR SQL (`NATURE = 'Coupe-feu'`) limitant les entités rastérisées.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+impl CustomLayer {
+    /// GeoPackage de la couche, découpé à l'emprise du projet
+    /// (`resources/custom/<nom>.gpkg`).
+    pub fn gpkg_path(&self, project_folder: &Path) -> PathBuf {
+        project_folder
+            .join("resources")
+            .join(CUSTOM_LAYERS_DIR)
+            .join(format!("{}.gpkg", self.name))
+    }
+}
+
+/// Nom d'une couche personnalisée d'après le nom de son fichier : les caractères autres que
+/// lettres, chiffres, `-` et `_` sont remplacés par `_`.
+pub fn custom_layer_name(file_path: &Path) -> Result<String, Box<dyn Error>> {
+    let stem = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .filter(|stem| !stem.is_empty())
+        .ok_or_else(|| format!("Nom de fichier invalide: {}", file_path.display()))?;
+    Ok(stem
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect())
+}
+
+/// Vérifie que chaque couche du fichier a un système de coordonnées convertible dans
+/// celui du projet (`epsg`).
+fn check_source_crs(file_path: &Path, epsg: u32) -> Result<(), Box<dyn Error>> {
+    let dataset = Dataset::open(file_path)?;
+    let project_srs = SpatialRef::from_epsg(epsg)?;
+    let mut layer_count = 0;
+    for layer in dataset.layers() {
+        layer_count += 1;
+        let srs = layer.spatial_ref().ok_or_else(|| {
+            format!(
+                "La couche {} n'a pas de système de coordonnées",
+                layer.name()
+            )
+        })?;
+        CoordTransform::new(&srs, &project_srs).map_err(|e| {
+            format!(
+                "Le système de coordonnées de la couche {} n'est pas convertible en {}: {}",
+                layer.name(),
+                crs_label(epsg),
+                e
+            )
+        })?;
+    }
+    if layer_count == 0 {
+        return Err(format!("Aucune couche vectorielle dans {}", file_path.display()).into());
+    }
+    Ok(())
+}
+
+/// Vérifie les types de géométrie d'un GeoPackage (points, lignes, polygones et leurs
+/// variantes multiples) et indique s'il ne contient aucun polygone : ses entités sont
+/// alors rastérisées avec `-at`, pour que les lignes fines et les points restent visibles.
+fn check_geometry_types(gpkg_path: &str) -> Result<bool, Box<dyn Error>> {
+    let dataset = Dataset::open(gpkg_path)?;
+    let mut has_polygons = false;
+    for mut layer in dataset.layers() {
+        let name = layer.name();
+        for feature in layer.features() {
+            let Some(geometry) = feature.geometry() else {
+                continue;
+            };
+            match geometry.geometry_type() {
+                OGRwkbGeometryType::wkbPolygon | OGRwkbGeometryType::wkbMultiPolygon => {
+                    has_polygons = true
+                }
+                OGRwkbGeometryType::wkbPoint
+                | OGRwkbGeometryType::wkbMultiPoint
+                | OGRwkbGeometryType::wkbLineString
+                | OGRwkbGeometryType::wkbMultiLineString => {}
+                _ => {
+                    return Err(format!(
+                        "Type de géométrie non pris en charge dans la couche {}: {}",
+                        name,
+                        geometry.geometry_name()
+                    )
+                    .into());
+                }
+            }
+        }
+    }
+    Ok(!has_polygons)
+}
+
+/// Vérifie un filtre attributaire saisi par l'utilisateur en l'appliquant à chaque couche
+/// du GeoPackage, et compte les entités sélectionnées.
+///
+/// # Arguments
+///
+/// * `gpkg_path` - GeoPackage de la couche
+/// * `filter` - filtre attributaire OGR SQL
+///
+/// # Returns
+///
+/// * `Result<u64, Box<dyn Error>>` - le nombre d'entités sélectionnées, ou l'erreur d'OGR
+///   telle quelle si le filtre est invalide
+pub fn check_attribute_filter(gpkg_path: &str, filter: &str) -> Result<u64, Box<dyn Error>> {
+    let dataset = Dataset::open(gpkg_path)?;
+    let mut features = 0;
+    for mut layer in dataset.layers() {
+        let name = layer.name();
+        layer
+            .set_attribute_filter(filter)
+            .map_err(|e| format!("Filtre invalide pour la couche {}: {}", name, e))?;
+        features += layer.feature_count();
+        layer.clear_attribute_filter();
+    }
+    Ok(features)
+}
+
+/// Rastérise une couche personnalisée sur la grille du projet, dans `custom_layer.tif`
+/// du dossier de travail.
+fn rasterize_custom_layer(
+    work_dir: &WorkDir,
+    project: &Dataset,
+    layer_gpkg: &str,
+    layer: &CustomLayer,
+) -> Result<String, Box<dyn Error>> {
+    let lines_only = check_geometry_types(layer_gpkg)?;
+    let layers = layer_names(&Dataset::open(layer_gpkg)?);
+    let temp_layer = work_dir.path_str("custom_layer.tif");
+    rasterize_layer(
+        project,
+        layer_gpkg,
+        &layers,
+        &temp_layer,
+        layer.color,
+        layer.filter.as_deref(),
+        lines_only.then(|| vec!["-at"]),
+    )?;
+    Ok(temp_layer)
+}
+
+/// Applique une couche personnalisée sur un projet ouvert en écriture, lors de l'ajout
+/// des couches (`add_layers`). Une couche dont le GeoPackage a disparu est ignorée
+/// et signalée dans `warnings`.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project` - projet ouvert en écriture
+/// * `project_folder` - dossier du projet
+/// * `layer` - couche personnalisée enregistrée dans les métadonnées
+/// * `warnings` - avertissements de la création
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_custom_layer_to(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    project_folder: &Path,
+    layer: &CustomLayer,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn Error>> {
+    let layer_gpkg = layer.gpkg_path(project_folder);
+    if !layer_gpkg.exists() {
+        warnings.push(Warning::new(
+            format!("Couche {}", layer.name),
+            "fichier de la couche personnalisée introuvable, couche ignorée",
+        ));
+        return Ok(());
+    }
+    let temp_layer = rasterize_custom_layer(
+        work_dir,
+        project.dataset(),
+        &layer_gpkg.to_string_lossy(),
+        layer,
+    )?;
+    overlay_in_place(
+        project,
+        &temp_layer,
+        |&value| value > 0,
+        Some(&|_| colormap::CLASS_OTHER_TOPO),
+        &run_config,
+    )?;
+    fs::remove_file(&temp_layer)?;
+    Ok(())
+}
+
+/// Ajoute une couche vectorielle de l'utilisateur (GeoPackage, Shapefile...) à un projet existant :
+/// le fichier est converti en GeoPackage dans le système du projet, découpé à l'emprise du projet et copié dans
+/// `resources/custom/`, puis rastérisé avec sa couleur sur le raster du projet. L'image de
+/// végétation, l'aperçu et les aperçus du raster sont régénérés, et la couche est enregistrée
+/// dans les métadonnées pour être réappliquée par `add_layers`.
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet
+/// * `file_path` - fichier vectoriel à ajouter
+/// * `color` - couleur de la couche, le noir étant réservé au fond et à la topographie
+/// * `priority` - ordre d'application de la couche, voir `CustomLayer::priority`
+/// * `filter` - filtre attributaire optionnel, vérifié sur la couche avant la rastérisation
+///
+/// # Returns
+///
+/// * `Result<CustomLayer, Box<dyn Error>>` - la couche enregistrée, ou une erreur si le fichier
+///   n'est pas utilisable, si le filtre est invalide ou si aucune entité n'est retenue
+///   dans l'emprise du projet
+pub fn add_custom_layer(
+    project_name: &str,
+    file_path: &Path,
+    color: Rgb,
+    priority: u8,
+    filter: Option<&str>,
+) -> Result<CustomLayer, Box<dyn Error>> {
+    let project_folder = project_dir(project_name);
+    if !is_project_complete(&project_folder) {
+        return Err(format!("Le projet '{}' n'est pas terminé", project_name).into());
+    }
+    if color == [0, 0, 0] {
+        return Err("Le noir est réservé au fond et aux éléments topographiques".into());
+    }
+    if !file_path.is_file() {
+        return Err(format!("Fichier introuvable: {}", file_path.display()).into());
+    }
+
+    let layer = CustomLayer {
+        name: custom_layer_name(file_path)?,
+        source: file_path.to_string_lossy().to_string(),
+        color,
+        priority,
+        filter: filter
+            .map(str::trim)
+            .filter(|filter| !filter.is_empty())
+            .map(str::to_string),
+    };
+    let mut metadata = load_project_metadata(project_name);
+    if metadata
+        .custom_layers
+        .iter()
+        .any(|existing| existing.name == layer.name)
+    {
+        return Err(format!(
+            "Une couche personnalisée nommée '{}' existe déjà",
+            layer.name
+        )
+        .into());
+    }
+    let epsg = metadata.project_epsg();
+    check_source_crs(file_path, epsg)?;
+
+    let run_config = RunConfig::snapshot();
+    let work_dir = WorkDir::new(&run_config, "custom_layer")?;
+    let converted = work_dir.path_str("converted.gpkg");
+    convert_to_gpkg_in_crs(&file_path.to_string_lossy(), &converted, epsg)?;
+    check_geometry_types(&converted)?;
+
+    let project_bb = get_project_bounding_box(project_name)?;
+    let layer_gpkg = layer.gpkg_path(&project_folder);
+    fs::create_dir_all(layer_gpkg.parent().ok_or("Invalid custom layer path")?)?;
+    if layer_gpkg.exists() {
+        fs::remove_file(&layer_gpkg)?;
+    }
+    let clip_report = clip_to_bb(&converted, &layer_gpkg.to_string_lossy(), &project_bb)?;
+    if clip_report.output_features == 0 {
+        if layer_gpkg.exists() {
+            fs::remove_file(&layer_gpkg)?;
+        }
+        return Err(format!(
+            "La couche {} n'a aucune entité dans l'emprise du projet",
+            layer.name
+        )
+        .into());
+    }
+    if let Some(filter) = &layer.filter {
+        let selected =
+            check_attribute_filter(&layer_gpkg.to_string_lossy(), filter).inspect_err(|_| {
+                let _ = fs::remove_file(&layer_gpkg);
+            })?;
+        if selected == 0 {
+            fs::remove_file(&layer_gpkg)?;
+            return Err(format!(
+                "Aucune entité de la couche {} ne correspond au filtre '{}'",
+                layer.name, filter
+            )
+            .into());
+        }
+    }
+
+    let tiff = project_tiff(project_name).to_string_lossy().to_string();
+    let project = Dataset::open(&tiff)?;
+    let temp_layer =
+        rasterize_custom_layer(&work_dir, &project, &layer_gpkg.to_string_lossy(), &layer)?;
+    project.close()?;
+    apply_overlay(
+        &work_dir,
+        &tiff,
+        &temp_layer,
+        |&value| value > 0,
+        Some(&|_| colormap::CLASS_OTHER_TOPO),
+        &run_config,
+    )?;
+
+    metadata.custom_layers.push(layer.clone());
+    save_project_metadata(project_name, &metadata)?;
+
+    if let Err(e) = build_project_overviews(&tiff) {
+        tracing::warn!(
+            project = project_name,
+            error = %e,
+            "Échec du calcul des aperçus du raster"
+        );
+    }
+    export_to_jpg(&tiff, project_veget_jpeg(project_name), &run_config)?;
+    generate_project_preview(project_name)?;
+
+    Ok(layer)
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+
+use super::colormap::write_class_legend;
+use super::oso::VegetationSource;
+pub use super::processing::RasterWindow;
+use super::processing::{ProjectRaster, class_raster_path, create_class_raster};
+use super::regions::find_project_regions;
+use super::tiles::build_project_overviews;
+use super::{clip_to_bb, create_project_in_crs};
+
+use crate::app_setup::RunConfig;
+use crate::events::{ProgressUpdate, Warning};
+use crate::progress::ProgressSink;
+use crate::timings::StageTimings;
+use crate::utils::{
+    BoundingBox, PROJECT_GRID_PIXELS, default_land_color, export_to_jpg, generate_project_preview,
+    get_previous_projects, get_project_bounding_box, is_project_complete, load_project_metadata,
+    mark_project_complete, project_dir, project_ortho_jpeg, project_tiff, project_veget_jpeg,
+    projects_dir, save_project_metadata, save_rgb_jpeg, update_project_size, write_world_file,
+};
+
+/// Nombre de lignes copiées à la fois lors du découpage d'un raster.
+const COPY_STRIPE_ROWS: usize = PROJECT_GRID_PIXELS;
+
+/// Calcule la fenêtre du raster d'un projet correspondant à une emprise.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet source.
+/// * `bb` - L'emprise recherchée.
+/// * `resolution` - La résolution du nouveau projet en mètres par pixel.
+///
+/// # Returns
+///
+/// * `Result<Option<RasterWindow>, Box<dyn Error>>` - La fenêtre, ou `None` si l'emprise
+///   dépasse le raster ou n'est pas alignée sur ses pixels (résolution différente).
+pub fn project_window(
+    project_name: &str,
+    bb: &BoundingBox,
+    resolution: f64,
+) -> Result<Option<RasterWindow>, Box<dyn Error>> {
+    let dataset = Dataset::open(project_tiff(project_name))?;
+    let geo_transform = dataset.geo_transform()?;
+    let (raster_width, raster_height) = dataset.raster_size();
+    if (geo_transform[1] - resolution).abs() > 1e-6 || (geo_transform[5] + resolution).abs() > 1e-6
+    {
+        return Ok(None);
+    }
+
+    let x = (bb.xmin - geo_transform[0]) / resolution;
+    let y = (geo_transform[3] - bb.ymax) / resolution;
+    if x < 0.0 || y < 0.0 || (x - x.round()).abs() > 1e-6 || (y - y.round()).abs() > 1e-6 {
+        return Ok(None);
+    }
+    let (width, height) = bb.raster_size(resolution)?;
+    let window = RasterWindow {
+        x: x.round() as usize,
+        y: y.round() as usize,
+        width,
+        height,
+    };
+    if window.x + width > raster_width || window.y + height > raster_height {
+        return Ok(None);
+    }
+    Ok(Some(window))
+}
+
+/// Recherche un projet terminé dont le raster contient entièrement une emprise,
+/// pour en dériver un nouveau projet sans téléchargement (voir `derive_project`).
+/// Parmi les candidats, le plus petit projet est retenu. Les projets auxquels l'utilisateur
+/// a ajouté ses propres couches (voir `add_custom_layer`), ou dont le fond départemental
+/// n'a pas la couleur des nouveaux projets, ne sont pas réutilisés.
+///
+/// # Arguments
+///
+/// * `bb` - L'emprise du nouveau projet.
+/// * `vegetation_source` - La source de végétation demandée, qui doit être celle du projet source.
+/// * `layers` - La sélection de couches demandée, qui doit être celle du projet source.
+/// * `exclude` - Le nom du projet créé, écarté des candidats (cas du remplacement).
+/// * `resolution` - La résolution du nouveau projet, qui doit être celle du projet source.
+///
+/// # Returns
+///
+/// * `Option<String>` - Le nom du projet source, ou `None` si aucun projet ne convient.
+pub fn find_containing_project(
+    bb: &BoundingBox,
+    vegetation_source: &VegetationSource,
+    layers: Option<&[String]>,
+    exclude: &str,
+    resolution: f64,
+) -> Option<String> {
+    let layers: Option<BTreeSet<&str>> =
+        layers.map(|layers| layers.iter().map(String::as_str).collect());
+    let projects = get_previous_projects().ok()?;
+    let mut candidates: Vec<(f64, String)> = projects
+        .into_keys()
+        .filter(|name| name != exclude && is_project_complete(&project_dir(name)))
+        .filter(|name| {
+            let metadata = load_project_metadata(name);
+            let source_layers: Option<BTreeSet<&str>> = metadata
+                .layers
+                .as_ref()
+                .map(|layers| layers.iter().map(String::as_str).collect());
+            source_layers == layers
+                && metadata.custom_layers.is_empty()
+                && metadata.project_land_color() == default_land_color()
+                && VegetationSource::parse(&metadata.vegetation_source)
+                    .is_ok_and(|source| &source == vegetation_source)
+        })
+        .filter(|name| project_window(name, bb, resolution).is_ok_and(|window| window.is_some()))
+        .filter(|name| project_ortho_jpeg(name).exists())
+        .filter_map(|name| {
+            let source_bb = get_project_bounding_box(&name).ok()?;
+            Some((source_bb.width() * source_bb.height(), name))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    candidates.into_iter().next().map(|(_, name)| name)
+}
+
+/// Copie une fenêtre de chaque bande d'un raster dans un raster de même nombre de bandes,
+/// par bandes de `COPY_STRIPE_ROWS` lignes.
+///
+/// # Arguments
+///
+/// * `source_path` - Le raster source.
+/// * `target_path` - Le raster cible, de la taille de la fenêtre.
+/// * `window` - La fenêtre du raster source.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si la lecture ou l'écriture échoue.
+pub fn copy_raster_window(
+    source_path: &Path,
+    target_path: &Path,
+    window: &RasterWindow,
+) -> Result<(), Box<dyn Error>> {
+    let source = Dataset::open(source_path)?;
+    let target = Dataset::open_ex(
+        target_path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+            ..Default::default()
+        },
+    )?;
+    if source.raster_count() != target.raster_count()
+        || target.raster_size() != (window.width, window.height)
+    {
+        return Err(format!(
+            "{} ne correspond pas à la fenêtre de {}",
+            target_path.display(),
+            source_path.display()
+        )
+        .into());
+    }
+
+    for band_idx in 1..=source.raster_count() {
+        let source_band = source.rasterband(band_idx)?;
+        let mut target_band = target.rasterband(band_idx)?;
+        for row in (0..window.height).step_by(COPY_STRIPE_ROWS) {
+            let size = (window.width, COPY_STRIPE_ROWS.min(window.height - row));
+            let mut stripe = source_band.read_as::<u8>(
+                (window.x as isize, (window.y + row) as isize),
+                size,
+                size,
+                None,
+            )?;
+            target_band.write((0, row as isize), size, &mut stripe)?;
+        }
+    }
+    Ok(())
+}
+
+/// Découpe l'orthophoto du projet source sur la fenêtre du nouveau projet.
+fn crop_ortho(
+    source_name: &str,
+    new_name: &str,
+    new_bb: &BoundingBox,
+    window: &RasterWindow,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn Error>> {
+    let source_ortho = image::open(project_ortho_jpeg(source_name))?;
+    let (source_width, source_height) = Dataset::open(project_tiff(source_name))?.raster_size();
+    if (
+        source_ortho.width() as usize,
+        source_ortho.height() as usize,
+    ) != (source_width, source_height)
+    {
+        return Err(format!(
+            "L'orthophoto de {} ne correspond pas à son raster ({}x{} pixels au lieu de {}x{})",
+            source_name,
+            source_ortho.width(),
+            source_ortho.height(),
+            source_width,
+            source_height
+        )
+        .into());
+    }
+
+    let cropped = source_ortho
+        .crop_imm(
+            window.x as u32,
+            window.y as u32,
+            window.width as u32,
+            window.height as u32,
+        )
+        .to_rgb8();
+    let output_path = project_ortho_jpeg(new_name).to_string_lossy().to_string();
+    save_rgb_jpeg(&cropped, &output_path, run_config.ortho_jpeg_quality())?;
+    let resolution = run_config.resolution;
+    write_world_file(
+        &output_path,
+        &[new_bb.xmin, resolution, 0.0, new_bb.ymax, 0.0, -resolution],
+    )
+}
+
+/// Découpe les GeoPackages du dossier `resources` d'un projet source sur une nouvelle emprise.
+/// La couche régionale, nommée d'après le projet, prend le nom du nouveau projet.
+///
+/// # Arguments
+///
+/// * `source_name` - Le nom du projet source.
+/// * `new_name` - Le nom du nouveau projet, dont le dossier `resources` existe.
+/// * `new_bb` - L'emprise du nouveau projet.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si la découpe d'une couche échoue.
+pub fn clip_project_resources(
+    source_name: &str,
+    new_name: &str,
+    new_bb: &BoundingBox,
+) -> Result<(), Box<dyn Error>> {
+    let source_resources = project_dir(source_name).join("resources");
+    let source_regional = source_resources.join(format!("{}.gpkg", source_name));
+    let resources_folder = projects_dir().join(new_name).join("resources");
+
+    for entry in fs::read_dir(&source_resources)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "gpkg") {
+            continue;
+        }
+        let file_name = if path == source_regional {
+            format!("{}.gpkg", new_name)
+        } else {
+            path.file_name().unwrap().to_string_lossy().to_string()
+        };
+        clip_to_bb(
+            &path.to_string_lossy(),
+            &resources_folder.join(file_name).to_string_lossy(),
+            new_bb,
+        )?;
+    }
+    Ok(())
+}
+
+/// Crée un projet en découpant un projet existant dont l'emprise le contient :
+/// raster et raster de classes découpés fenêtre par fenêtre, ressources découpées avec
+/// `clip_to_bb`, image de végétation régénérée et orthophoto découpée dans celle du source.
+/// Aucune donnée n'est téléchargée. Les métadonnées reprennent celles du projet source
+/// (source de végétation, millésimes) avec une nouvelle date de création et la durée
+/// de la dérivation (`derive`).
+/// En cas d'échec, le dossier du nouveau projet est supprimé.
+///
+/// # Arguments
+///
+/// * `source_name` - Le nom du projet source, voir `find_containing_project`.
+/// * `new_name` - Le nom du nouveau projet.
+/// * `new_bb` - L'emprise du nouveau projet.
+/// * `warnings` - Les avertissements de la création.
+/// * `progress` - La destination des messages de progression.
+/// * `run_config` - Les paramètres figés au début de la création (résolution, qualité des images).
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le dossier du nouveau projet.
+pub fn derive_project(
+    source_name: &str,
+    new_name: &str,
+    new_bb: &BoundingBox,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    run_config: &RunConfig,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let project_folder = projects_dir().join(new_name);
+    if project_folder.exists() {
+        return Err(format!("Le projet {} existe déjà", new_name).into());
+    }
+    let result = derive_project_files(
+        source_name,
+        new_name,
+        new_bb,
+        warnings,
+        progress,
+        run_config,
+    );
+    if result.is_err() && project_folder.exists() {
+        let _ = fs::remove_dir_all(&project_folder);
+    }
+    result.map(|_| project_folder)
+}
+
+fn derive_project_files(
+    source_name: &str,
+    new_name: &str,
+    new_bb: &BoundingBox,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let window = project_window(source_name, new_bb, run_config.resolution)?.ok_or_else(|| {
+        format!(
+            "Le projet {} ne contient pas l'emprise demandée",
+            source_name
+        )
+    })?;
+    let project_folder = projects_dir().join(new_name);
+
+    progress.report_update(ProgressUpdate::step(
+        "stage.init",
+        "step.crop_raster",
+        &[],
+        1,
+        4,
+    ));
+    // Le raster source est vérifié avant d'être découpé.
+    ProjectRaster::open_read_only(&project_tiff(source_name).to_string_lossy())?;
+    fs::create_dir_all(project_folder.join("resources"))?;
+    fs::create_dir_all(project_folder.join("slices"))?;
+    let new_tiff = project_tiff(new_name);
+    create_project_in_crs(
+        &new_tiff.to_string_lossy(),
+        new_bb,
+        load_project_metadata(source_name).project_epsg(),
+        run_config,
+    )?;
+    copy_raster_window(&project_tiff(source_name), &new_tiff, &window)?;
+
+    let source_classes = class_raster_path(&project_tiff(source_name).to_string_lossy());
+    if source_classes.exists() {
+        let new_classes = create_class_raster(&new_tiff.to_string_lossy())?;
+        copy_raster_window(&source_classes, &new_classes, &window)?;
+        write_class_legend(
+            &project_folder.join(format!("{}_CLASSES.json", new_name)),
+            load_project_metadata(source_name).project_land_color(),
+        )?;
+    }
+
+    progress.report_update(ProgressUpdate::step(
+        "stage.init",
+        "step.reuse_source",
+        &[],
+        2,
+        4,
+    ));
+    clip_project_resources(source_name, new_name, new_bb)?;
+
+    progress.report_update(ProgressUpdate::step(
+        "stage.finalize",
+        "step.export_jpeg",
+        &[],
+        3,
+        4,
+    ));
+    export_to_jpg(&new_tiff, project_veget_jpeg(new_name), run_config)?;
+    build_project_overviews(&new_tiff)?;
+
+    progress.report_update(ProgressUpdate::step(
+        "stage.finalize",
+        "step.crop_ortho",
+        &[],
+        4,
+        4,
+    ));
+    crop_ortho(source_name, new_name, new_bb, &window, run_config)?;
+
+    let mut metadata = load_project_metadata(source_name);
+    metadata.created_at = Some(chrono::Local::now().to_rfc3339());
+    metadata.timings = StageTimings::default();
+    metadata
+        .timings
+        .add("derive", start.elapsed().as_secs_f64());
+    metadata.derived_from = Some(source_name.to_string());
+    metadata.size_limit_override = None;
+    if let Ok((regions, _)) = find_project_regions(new_bb) {
+        metadata.regions = regions.into_iter().map(|region| region.code).collect();
+    }
+    save_project_metadata(new_name, &metadata)?;
+
+    if let Err(e) = generate_project_preview(new_name) {
+        warnings.push(Warning::new("Aperçu", e.to_string()));
+    }
+    if let Err(e) = update_project_size(new_name) {
+        warnings.push(Warning::new("Taille du projet", e.to_string()));
+    }
+    mark_project_complete(&project_folder)
+}
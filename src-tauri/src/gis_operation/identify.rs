@@ -0,0 +1,181 @@
+use std::error::Error;
+
+use gdal::Dataset;
+use serde::Serialize;
+
+use super::colormap::{CLASS_UNKNOWN, CLASSES};
+use super::legend::LegendEntry;
+use super::processing::{class_raster_path, open_raster};
+
+use crate::i18n::{self, Language};
+
+/// Contenu d'un pixel du raster d'un projet, renvoyé par l'outil d'identification.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PixelInfo {
+    /// Colonne du pixel dans le raster.
+    pub px: u32,
+    /// Ligne du pixel dans le raster.
+    pub py: u32,
+    /// Coordonnées du centre du pixel, dans le système du projet.
+    pub x: f64,
+    pub y: f64,
+    pub epsg: u32,
+    /// Valeurs des quatre bandes (rouge, vert, bleu, alpha).
+    pub rgba: [u8; 4],
+    /// Code du raster de classes, `None` si le projet n'en a pas.
+    pub class_code: Option<u8>,
+    /// Clé de la classe (`water`, `feuillus`...), d'après le raster de classes.
+    pub class_key: Option<String>,
+    /// Libellés de la légende correspondant à la couleur du pixel.
+    pub label_fr: String,
+    pub label_en: String,
+    /// Le pixel est dans la zone du projet ; sinon (alpha nul), il est « hors zone ».
+    pub in_zone: bool,
+}
+
+/// Coordonnées du centre d'un pixel d'après la géotransformation du raster.
+pub fn pixel_to_map(geo_transform: &[f64; 6], px: u32, py: u32) -> (f64, f64) {
+    let (col, row) = (px as f64 + 0.5, py as f64 + 0.5);
+    (
+        geo_transform[0] + col * geo_transform[1] + row * geo_transform[2],
+        geo_transform[3] + col * geo_transform[4] + row * geo_transform[5],
+    )
+}
+
+/// Pixel contenant un point, pour un raster sans rotation : `None` si le point est
+/// hors du raster de `size` pixels.
+pub fn map_to_pixel(
+    geo_transform: &[f64; 6],
+    size: (usize, usize),
+    x: f64,
+    y: f64,
+) -> Option<(u32, u32)> {
+    let col = ((x - geo_transform[0]) / geo_transform[1]).floor();
+    let row = ((y - geo_transform[3]) / geo_transform[5]).floor();
+    if !col.is_finite() || !row.is_finite() || col < 0.0 || row < 0.0 {
+        return None;
+    }
+    if col >= size.0 as f64 || row >= size.1 as f64 {
+        return None;
+    }
+    Some((col as u32, row as u32))
+}
+
+fn read_pixel(dataset: &Dataset, band_idx: usize, px: u32, py: u32) -> Result<u8, Box<dyn Error>> {
+    Ok(dataset
+        .rasterband(band_idx)?
+        .read_as::<u8>((px as isize, py as isize), (1, 1), (1, 1), None)?
+        .data()[0])
+}
+
+/// Lit un pixel du raster d'un projet : ses quatre bandes, son code dans le raster de classes
+/// s'il existe et les libellés de la légende de sa couleur. Un pixel d'alpha nul est hors de
+/// la zone du projet.
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+/// * `epsg` - code EPSG du système du projet
+/// * `legend` - la légende de l'image de végétation
+/// * `px`, `py` - colonne et ligne du pixel
+///
+/// # Returns
+///
+/// * `Result<PixelInfo, Box<dyn Error>>` - le contenu du pixel, ou une erreur s'il est hors du raster
+pub fn identify_pixel(
+    project_file_path: &str,
+    epsg: u32,
+    legend: &[LegendEntry],
+    px: u32,
+    py: u32,
+) -> Result<PixelInfo, Box<dyn Error>> {
+    let project = open_raster(project_file_path, "fichier projet")?;
+    let (width, height) = project.raster_size();
+    if px as usize >= width || py as usize >= height {
+        return Err(format!(
+            "Le pixel ({}, {}) est hors de l'image ({} × {})",
+            px, py, width, height
+        )
+        .into());
+    }
+
+    let mut rgba = [0u8; 4];
+    for (band_idx, value) in rgba.iter_mut().enumerate() {
+        *value = read_pixel(&project, band_idx + 1, px, py)?;
+    }
+    let (x, y) = pixel_to_map(&project.geo_transform()?, px, py);
+
+    let class_path = class_raster_path(project_file_path);
+    let class_code = if class_path.exists() {
+        let classes = open_raster(&class_path, "raster de classes")?;
+        Some(read_pixel(&classes, 1, px, py)?)
+    } else {
+        None
+    };
+    let class_entry = class_code
+        .filter(|&code| code != CLASS_UNKNOWN)
+        .and_then(|code| CLASSES.iter().find(|entry| entry.code == code));
+
+    let in_zone = rgba[3] != 0;
+    let (label_fr, label_en) = if !in_zone {
+        (
+            i18n::translate("identify.outside", Language::Fr, &[]),
+            i18n::translate("identify.outside", Language::En, &[]),
+        )
+    } else if let Some(entry) = legend
+        .iter()
+        .find(|entry| entry.rgb == [rgba[0], rgba[1], rgba[2]])
+    {
+        (entry.label_fr.clone(), entry.label_en.clone())
+    } else if let Some(entry) = class_entry {
+        (
+            entry.label.to_string(),
+            i18n::translate(&format!("class.{}", entry.key), Language::En, &[]),
+        )
+    } else {
+        (
+            i18n::translate("identify.unknown", Language::Fr, &[]),
+            i18n::translate("identify.unknown", Language::En, &[]),
+        )
+    };
+
+    Ok(PixelInfo {
+        px,
+        py,
+        x,
+        y,
+        epsg,
+        rgba,
+        class_code,
+        class_key: class_entry.map(|entry| entry.key.to_string()),
+        label_fr,
+        label_en,
+        in_zone,
+    })
+}
+
+/// Variante de `identify_pixel` prenant les coordonnées d'un point dans le système du projet.
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+/// * `epsg` - code EPSG du système du projet
+/// * `legend` - la légende de l'image de végétation
+/// * `x`, `y` - coordonnées du point
+///
+/// # Returns
+///
+/// * `Result<PixelInfo, Box<dyn Error>>` - le contenu du pixel, ou une erreur si le point est hors du raster
+pub fn identify_point(
+    project_file_path: &str,
+    epsg: u32,
+    legend: &[LegendEntry],
+    x: f64,
+    y: f64,
+) -> Result<PixelInfo, Box<dyn Error>> {
+    let project = open_raster(project_file_path, "fichier projet")?;
+    let (px, py) = map_to_pixel(&project.geo_transform()?, project.raster_size(), x, y)
+        .ok_or_else(|| format!("Le point ({}, {}) est hors de l'emprise du projet", x, y))?;
+    drop(project);
+    identify_pixel(project_file_path, epsg, legend, px, py)
+}
@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::colormap::{CLASSES, ClassEntry, Rgb};
+use super::oso::VegetationSource;
+
+use crate::utils::in_resource_dir;
+
+/// Fichier de ressources décrivant les couches BDTOPO disponibles.
+pub const LAYER_SETS_FILE: &str = "layer_sets.json";
+
+/// Configuration des couches modifiée par l'utilisateur, à côté de `config.json`.
+pub const USER_LAYER_CONFIG_FILE: &str = "layer_config.json";
+
+/// Couche de végétation de la BD Forêt, hors de la configuration BDTOPO.
+pub const VEGETATION_LAYER: &str = "FORMATION_VEGETALE";
+
+/// Couche des parcelles agricoles du RPG, hors de la configuration BDTOPO.
+pub const RPG_LAYER: &str = "PARCELLES_GRAPHIQUES";
+
+/// Couche des bâtiments de la BDTOPO, rastérisée selon leur hauteur (voir `add_building_layer`).
+pub const BUILDING_LAYER: &str = "BATIMENT";
+
+/// Groupe d'une couche, qui fixe sa priorité : les couches topographiques recouvrent l'hydrographie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerGroup {
+    Hydro,
+    Topo,
+}
+
+/// Type de géométrie attendu, qui détermine le mode de rastérisation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeometryHint {
+    /// Déduit de la première entité de la couche.
+    #[default]
+    Auto,
+    /// Lignes, rastérisées avec `-at` pour conserver leur continuité.
+    Line,
+    Polygon,
+}
+
+/// Description d'une couche vectorielle à extraire d'une archive et à rastériser.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerDefinition {
+    /// Nom du shapefile dans l'archive (sans extension), repris pour le GPKG du projet.
+    pub name: String,
+    /// Archive d'origine (`BDTOPO`).
+    pub archive: String,
+    pub group: LayerGroup,
+    #[serde(default)]
+    pub geometry: GeometryHint,
+    /// Clé de la classe appliquée (voir `colormap::CLASSES`), qui fixe aussi la couleur.
+    pub class: String,
+    pub enabled: bool,
+    /// Ordre d'application au sein du groupe : une couche de priorité plus élevée est appliquée
+    /// plus tard et recouvre les autres (surfaces, puis bâtiments, puis routes et voies ferrées).
+    /// Les couches de même priorité gardent l'ordre de la configuration.
+    #[serde(default)]
+    pub priority: u8,
+}
+
+impl LayerDefinition {
+    /// Entrée de la table des classes associée à la couche.
+    pub fn class_entry(&self) -> Option<&'static ClassEntry> {
+        CLASSES.iter().find(|entry| entry.key == self.class)
+    }
+
+    /// Couleur appliquée sur le projet.
+    pub fn color(&self) -> Rgb {
+        self.class_entry()
+            .map(|entry| entry.color)
+            .unwrap_or_default()
+    }
+
+    /// Code écrit dans le raster de classes.
+    pub fn class_code(&self) -> u8 {
+        self.class_entry()
+            .map(|entry| entry.code)
+            .unwrap_or_default()
+    }
+}
+
+/// Ensemble des couches BDTOPO traitées lors de la création d'un projet.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LayerConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub layers: Vec<LayerDefinition>,
+    /// Couches retenues pour une création (voir `with_selection`), toutes si `None`.
+    /// Propre à une création : n'est pas enregistré avec la configuration.
+    #[serde(skip)]
+    pub selection: Option<BTreeSet<String>>,
+    /// Source de la végétation (`VEGETATION_LAYER`) d'une création, BD Forêt par défaut.
+    /// Propre à une création : n'est pas enregistrée avec la configuration.
+    #[serde(skip)]
+    pub vegetation_source: VegetationSource,
+}
+
+impl LayerConfig {
+    /// Couches activées et sélectionnées d'un groupe, dans leur ordre d'application :
+    /// par priorité croissante, puis dans l'ordre de la configuration.
+    pub fn enabled_layers(&self, group: LayerGroup) -> Vec<&LayerDefinition> {
+        let mut layers: Vec<&LayerDefinition> = self
+            .layers
+            .iter()
+            .filter(|layer| layer.enabled && layer.group == group && self.includes(&layer.name))
+            .collect();
+        layers.sort_by_key(|layer| layer.priority);
+        layers
+    }
+
+    /// Noms des couches activées et sélectionnées, tous groupes confondus :
+    /// les couches extraites des archives.
+    pub fn processing_set(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.enabled && self.includes(&layer.name))
+            .map(|layer| layer.name.as_str())
+            .collect()
+    }
+
+    /// Indique si une couche (BDTOPO, `VEGETATION_LAYER` ou `RPG_LAYER`) fait partie
+    /// de la sélection de la création.
+    pub fn includes(&self, name: &str) -> bool {
+        self.selection
+            .as_ref()
+            .is_none_or(|selection| selection.contains(name))
+    }
+
+    /// Types d'archives (`BDFORET`, `RPG`, `BDTOPO`...) contenant au moins une couche traitée :
+    /// les autres ne sont ni téléchargées ni ouvertes.
+    pub fn required_archives(&self) -> BTreeSet<&str> {
+        let mut archives = BTreeSet::new();
+        if self.includes(VEGETATION_LAYER) {
+            archives.insert("BDFORET");
+        }
+        if self.includes(RPG_LAYER) {
+            archives.insert("RPG");
+        }
+        for layer in &self.layers {
+            if layer.enabled && self.includes(&layer.name) {
+                archives.insert(layer.archive.as_str());
+            }
+        }
+        archives
+    }
+
+    /// Restreint la création à une sélection de couches : la végétation (`VEGETATION_LAYER`),
+    /// les parcelles agricoles (`RPG_LAYER`) et les couches BDTOPO nommées.
+    ///
+    /// # Arguments
+    ///
+    /// * `selection` - les couches retenues, toutes si `None`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LayerConfig, String>` - la configuration restreinte, ou une erreur
+    ///   nommant la première couche inconnue
+    pub fn with_selection(mut self, selection: Option<&[String]>) -> Result<Self, String> {
+        let Some(selection) = selection else {
+            self.selection = None;
+            return Ok(self);
+        };
+        for name in selection {
+            let known = name == VEGETATION_LAYER
+                || name == RPG_LAYER
+                || self.layers.iter().any(|layer| &layer.name == name);
+            if !known {
+                return Err(format!("Couche inconnue: {}", name));
+            }
+        }
+        self.selection = Some(selection.iter().cloned().collect());
+        Ok(self)
+    }
+
+    /// Fixe la source de la végétation d'une création.
+    pub fn with_vegetation_source(mut self, vegetation_source: VegetationSource) -> Self {
+        self.vegetation_source = vegetation_source;
+        self
+    }
+
+    /// Sélection triée et sans doublon, telle qu'enregistrée dans les métadonnées du projet.
+    pub fn selected_layers(&self) -> Option<Vec<String>> {
+        self.selection
+            .as_ref()
+            .map(|selection| selection.iter().cloned().collect())
+    }
+
+    /// Active ou désactive une couche.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - nom de la couche
+    /// * `enabled` - nouvel état
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - une erreur si la couche n'existe pas
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let layer = self
+            .layers
+            .iter_mut()
+            .find(|layer| layer.name == name)
+            .ok_or(format!("Couche inconnue: {}", name))?;
+        layer.enabled = enabled;
+        Ok(())
+    }
+
+    /// Vérifie que les noms sont uniques et que chaque classe existe.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - un message décrivant la première incohérence
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for layer in &self.layers {
+            if !names.insert(layer.name.as_str()) {
+                return Err(format!("Couche en double: {}", layer.name));
+            }
+            if layer.class_entry().is_none() {
+                return Err(format!(
+                    "Classe inconnue pour la couche {}: {}",
+                    layer.name, layer.class
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lit et valide une configuration de couches.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - chemin du fichier JSON
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LayerConfig, Box<dyn std::error::Error>>` - la configuration lue
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let config: LayerConfig = serde_json::from_str(&content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Valide puis enregistre la configuration de couches.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - chemin du fichier JSON
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'enregistrement a réussi
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.validate()?;
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Chemin de la configuration de couches modifiée par l'utilisateur.
+pub fn user_layer_config_path() -> PathBuf {
+    PathBuf::from(USER_LAYER_CONFIG_FILE)
+}
+
+/// Charge la configuration de couches : celle de l'utilisateur si elle existe,
+/// sinon celle fournie avec l'application (`resources/layer_sets.json`).
+///
+/// # Returns
+///
+/// * `Result<LayerConfig, Box<dyn std::error::Error>>` - la configuration active
+pub fn load_layer_config() -> Result<LayerConfig, Box<dyn std::error::Error>> {
+    let user_path = user_layer_config_path();
+    if user_path.exists() {
+        return LayerConfig::load_from(&user_path);
+    }
+    LayerConfig::load_from(&in_resource_dir(LAYER_SETS_FILE))
+}
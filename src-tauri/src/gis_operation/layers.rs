@@ -1,170 +1,212 @@
+use gdal::raster::{GdalDataType, RasterBand};
+use gdal::vector::sql::Dialect;
 use gdal::vector::{LayerAccess, OGRwkbGeometryType};
 use gdal::{Dataset, DriverManager};
+use image::RgbImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
-use tauri::Emitter;
 
-use super::processing::{apply_overlay, rasterize_layer};
+use super::colormap::{self, BuildingHeights, Rgb, RpgClassTable};
+use super::custom_layers::{CustomLayer, add_custom_layer_to};
+use super::layer_config::{
+    BUILDING_LAYER, GeometryHint, LayerConfig, LayerDefinition, LayerGroup, RPG_LAYER,
+    VEGETATION_LAYER,
+};
+use super::legend::{PRIORITY_HYDRO, PRIORITY_RPG, PRIORITY_TOPO, PRIORITY_VEGETATION};
+use super::ortho::{WMS_CACHE_DIR, gdal_http_env, sources_by_priority, wms_xml};
+use super::oso::{VegetationSource, add_oso_layer};
+use super::processing::{
+    ProjectRaster, RasterWindow, burn_classes, create_class_raster, fill_in_place, open_raster,
+    open_vector, overlay_in_place, rasterize_layer, rasterize_layer_on_grid, read_band,
+    write_alpha_mask, write_band,
+};
 use super::regions::create_region_geojson;
-use super::{clip_to_bb, convert_to_gpkg};
+use super::sql::{build_in_clause, build_not_in_clause};
+use super::{
+    CLIP_SKIPPED_WARNING_RATIO, ClipReport, clip_to_bb, convert_to_gpkg_in_crs, layer_names,
+};
 
+use crate::app_setup::RunConfig;
+use crate::events::{ProgressUpdate, Warning};
+use crate::progress::ProgressSink;
+use crate::timings::{StageTimings, processing_timer, stage_timer};
 use crate::utils::{
-    BoundingBox, cache_dir, create_directory_if_not_exists, extract_files_by_name, resolution,
-    temp_dir,
+    BoundingBox, FileNotInArchive, WorkDir, department_epsg, extract_files_by_name,
+    load_project_metadata, missing_basenames, probe_archive_contents, proxy_config, resource_gpkg,
+    save_project_metadata, save_rgb_jpeg, write_world_file,
 };
+use crate::web_request::gdal_proxy_env;
 
 /// Prépare les couches pour le projet, en les convertissant au format GPKG et en les découpant à l'extent régional.
 /// Retourne les chemins vers les fichiers GPKG pour chaque type de couche
 ///
 /// # Arguments
 ///
-/// * `app_handle` - Handle de l'application Tauri
+/// * `progress` - destination des messages de progression
+/// * `work_dir` - Dossier de travail de la création, qui reçoit les fichiers extraits et les GPKG
 /// * `project_bb` - BoundingBox du projet
 /// * `code` - Code départemental de la région traitée
+/// * `layer_config` - Couches BDTOPO à extraire (seules les couches activées et sélectionnées
+///   sont traitées) ; la végétation et le RPG ne sont préparés que s'ils sont sélectionnés
+/// * `warnings` - Avertissements de la création, complétés pour les couches absentes d'une archive
+///   et les couches dont trop d'entités ont été perdues au découpage (voir `ClipReport`)
+/// * `timings` - Durées de la création, complétées de la préparation de chaque couche
+///   (`prepare/<département>/<couche>`)
+/// * `run_config` - Paramètres figés de la création, dont le dossier des archives
 ///
 /// # Returns
 ///
-/// * `Result<(String, String, String, HashMap<String, Vec<String>>), String>` - Un tuple contenant les chemins vers les fichiers GPKG pour la région, la végétation, le RPG et les couches topographiques
+/// * `Result<PreparedLayers, String>` - Un tuple contenant les chemins vers les fichiers GPKG pour la région, la végétation, le RPG et les couches topographiques (chemin vide pour une couche non sélectionnée), puis les couches sans entité dans l'emprise du projet
 pub async fn prepare_layers(
-    app_handle: &tauri::AppHandle,
+    progress: &dyn ProgressSink,
+    work_dir: &WorkDir,
     project_bb: &BoundingBox,
     code: &str,
-) -> Result<(String, String, String, HashMap<String, Vec<String>>), String> {
-    let cache_folder_path = cache_dir().to_string_lossy().to_string();
-    let temp_dir = temp_dir().to_string_lossy().to_string();
-
-    let _ = app_handle.emit(
-        "progress-update",
-        "Préparation des Couches|Préparation de l'étendue régionale|1/4",
-    );
+    layer_config: &LayerConfig,
+    warnings: &mut Vec<Warning>,
+    timings: &mut StageTimings,
+    run_config: &RunConfig,
+) -> Result<PreparedLayers, String> {
+    let cache_folder_path = run_config.cache_dir.clone();
+    let temp_dir = work_dir.path().to_string_lossy().to_string();
+
+    let layers = archive_layers(code, layer_config);
+    let total_archives = layers.len();
 
-    let regional_geojson_path = format!("{}/{}.geojson", temp_dir, code);
-    create_region_geojson(code, &regional_geojson_path).unwrap();
-
-    let temp_regional_gpkg = format!("{}/{}.gpkg", temp_dir, code);
-    let regional_gpkg = format!("{}/{}_region.gpkg", temp_dir, code);
-
-    let _ = convert_to_gpkg(&regional_geojson_path, &temp_regional_gpkg);
-    let _ = clip_to_bb(&temp_regional_gpkg, &regional_gpkg, project_bb);
-
-    let mut layers: HashMap<String, Vec<&str>> = HashMap::new();
-    layers.insert(format!("BDFORET_{}.7z", code), vec!["FORMATION_VEGETALE"]);
-    layers.insert(format!("RPG_{}.7z", code), vec!["PARCELLES_GRAPHIQUES"]);
-    layers.insert(
-        format!("BDTOPO_{}.7z", code),
-        vec![
-            "AERODROME",
-            "CONSTRUCTION_SURFACIQUE",
-            "EQUIPEMENT_DE_TRANSPORT",
-            "RESERVOIR",
-            "TERRAIN_DE_SPORT",
-            "TRONCON_DE_VOIE_FERREE",
-            "ZONE_D_ESTRAN",
-            "BATIMENT",
-            "COURS_D_EAU",
-            "PLAN_D_EAU",
-            "SURFACE_HYDROGRAPHIQUE",
-            "TRONCON_DE_ROUTE",
-            "VOIE_NOMMEE",
-        ],
-    );
+    progress.report_update(ProgressUpdate::step(
+        "stage.prepare",
+        "step.regional_extent",
+        &[],
+        1,
+        total_archives + 1,
+    ));
+
+    let regional_gpkg = {
+        let _timer = stage_timer(timings, format!("prepare/{}/regional", code));
+        prepare_regional_layer(work_dir, project_bb, code)?
+    };
 
     let mut vegetation_gpkg = String::new();
     let mut rpg_gpkg = String::new();
     let mut topo_gpkgs: HashMap<String, Vec<String>> = HashMap::new();
+    let mut empty_layers = Vec::new();
 
     let mut layer_index = 2;
-    let total_archives = layers.len();
-
-    for (archive, files) in layers {
-        let layer_type = if archive.contains("BDFORET") {
-            "Végétation"
-        } else if archive.contains("RPG") {
-            "Parcelles agricoles"
-        } else if archive.contains("BDTOPO") {
-            "Topographie"
-        } else {
-            "Inconnu"
-        };
-
-        let _ = app_handle.emit(
-            "progress-update",
-            format!(
-                "Préparation des Couches|Préparation des couches {}|{}/{}",
-                layer_type,
-                layer_index,
-                total_archives + 1
-            ),
-        );
 
-        let archive_path = format!("{}/{}", cache_folder_path, archive);
+    for ArchiveLayers {
+        archive,
+        layer_type,
+        files,
+        required,
+    } in layers
+    {
+        progress.report_update(ProgressUpdate::step(
+            "stage.prepare",
+            "step.prepare_group",
+            &[&layer_type],
+            layer_index,
+            total_archives + 1,
+        ));
+
+        let archive_path = cache_folder_path
+            .join(&archive)
+            .to_string_lossy()
+            .to_string();
 
         let total_files = files.len();
         for (file_index, file) in files.iter().enumerate() {
-            let _ = app_handle.emit(
-                "progress-update",
-                format!(
-                    "Préparation des Couches|Extraction de {}|{}/{}",
-                    file,
-                    file_index + 1,
-                    total_files
-                ),
-            );
-
-            extract_files_by_name(&archive_path, file, &temp_dir).map_err(|e| {
-                format!(
+            let _timer = stage_timer(timings, format!("prepare/{}/{}", code, file));
+            progress.report_update(ProgressUpdate::step(
+                "stage.prepare",
+                "step.extract",
+                &[&file],
+                file_index + 1,
+                total_files,
+            ));
+
+            if let Err(e) = extract_files_by_name(&archive_path, file, &temp_dir) {
+                if !required && e.downcast_ref::<FileNotInArchive>().is_some() {
+                    let warning = missing_layer_warning(&archive, file);
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                    continue;
+                }
+                return Err(format!(
                     "Erreur lors de l'extraction du fichier {} depuis l'archive {}: {:?}",
                     file, archive, e
-                )
-            })?;
+                ));
+            }
 
-            let temp_file = format!("{}/{}/{}.shp", temp_dir, file, file);
-            let temp_gpkg = format!("{}/{}.gpkg", temp_dir, file);
-            let output_gpkg = format!("{}/{}_{}.gpkg", temp_dir, code, file);
+            let temp_file = work_dir.path_str(Path::new(file).join(format!("{}.shp", file)));
+            let temp_gpkg = work_dir.path_str(format!("{}.gpkg", file));
+            let output_gpkg = work_dir.path_str(format!("{}_{}.gpkg", code, file));
 
-            let _ = app_handle.emit(
-                "progress-update",
-                format!(
-                    "Préparation des Couches|Conversion de {}|{}/{}",
-                    file,
-                    file_index + 1,
-                    total_files
-                ),
-            );
+            progress.report_update(ProgressUpdate::step(
+                "stage.prepare",
+                "step.convert",
+                &[&file],
+                file_index + 1,
+                total_files,
+            ));
 
-            if let Err(e) = convert_to_gpkg(&temp_file, &temp_gpkg) {
+            if let Err(e) = convert_to_gpkg_in_crs(&temp_file, &temp_gpkg, department_epsg(code)) {
                 return Err(format!(
                     "Erreur lors de la conversion du fichier {} en GPKG: {:?}",
                     temp_file, e
                 ));
             }
 
-            let _ = app_handle.emit(
-                "progress-update",
-                format!(
-                    "Préparation des Couches|Découpage de {}|{}/{}",
-                    file,
-                    file_index + 1,
-                    total_files
-                ),
+            progress.report_update(ProgressUpdate::step(
+                "stage.prepare",
+                "step.clip",
+                &[&file],
+                file_index + 1,
+                total_files,
+            ));
+
+            let clip_report = match clip_to_bb(&temp_gpkg, &output_gpkg, project_bb) {
+                Ok(clip_report) => clip_report,
+                Err(e) => {
+                    return Err(format!(
+                        "Erreur lors du découpage du fichier {}: {:?}",
+                        temp_gpkg, e
+                    ));
+                }
+            };
+            tracing::debug!(
+                layer = %file,
+                region = code,
+                input = clip_report.input_features,
+                output = clip_report.output_features,
+                skipped = clip_report.skipped,
+                "Couche découpée"
             );
-
-            if let Err(e) = clip_to_bb(&temp_gpkg, &output_gpkg, project_bb) {
-                return Err(format!(
-                    "Erreur lors du découpage du fichier {}: {:?}",
-                    temp_gpkg, e
-                ));
+            if clip_report.skipped_ratio() > CLIP_SKIPPED_WARNING_RATIO {
+                warnings.push(skipped_features_warning(file, code, &clip_report));
+            }
+            // Le GPKG vide est conservé : il tient lieu de couche du projet si aucun
+            // département n'a d'entité dans l'emprise, mais il n'est pas fusionné.
+            if clip_report.output_features == 0 {
+                tracing::info!(
+                    layer = %file,
+                    region = code,
+                    "Aucune entité dans l'emprise du projet, couche non fusionnée"
+                );
+                empty_layers.push(file.to_string());
             }
 
             // Stocker les chemins des fichiers GPKG selon leur type
-            if file == &"FORMATION_VEGETALE" {
+            if file == VEGETATION_LAYER {
                 vegetation_gpkg = output_gpkg.clone();
-            } else if file == &"PARCELLES_GRAPHIQUES" {
+            } else if file == RPG_LAYER {
                 rpg_gpkg = output_gpkg.clone();
             } else {
-                // Pour les couches topo, on les stocke par nom de fichier
+                // Pour les couches topo et hydro, on les stocke par nom de fichier
                 topo_gpkgs
                     .entry(file.to_string())
                     .or_default()
@@ -175,689 +217,2196 @@ pub async fn prepare_layers(
         layer_index += 1;
     }
 
-    Ok((regional_gpkg, vegetation_gpkg, rpg_gpkg, topo_gpkgs))
+    Ok((
+        regional_gpkg,
+        vegetation_gpkg,
+        rpg_gpkg,
+        topo_gpkgs,
+        empty_layers,
+    ))
+}
+
+/// GPKG préparés d'un département (voir `prepare_layers`) : région, végétation, RPG,
+/// couches topographiques par nom de couche, et noms des couches sans entité dans l'emprise.
+pub type PreparedLayers = (
+    String,
+    String,
+    String,
+    HashMap<String, Vec<String>>,
+    Vec<String>,
+);
+
+/// Fichiers à extraire d'une archive départementale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveLayers {
+    /// Nom de l'archive dans le cache (`BDTOPO_2A.7z`).
+    pub archive: String,
+    /// Type de couche affiché dans la progression.
+    pub layer_type: &'static str,
+    /// Noms des shapefiles, sans extension.
+    pub files: Vec<String>,
+    /// Une couche absente rend la création impossible ; sinon elle est ignorée avec un avertissement.
+    pub required: bool,
+}
+
+/// Archives d'un département et fichiers à en extraire : végétation et RPG (obligatoires),
+/// puis les couches BDTOPO activées, regroupées par archive.
+/// Les couches hors de la sélection de la création (`LayerConfig::includes`) sont écartées,
+/// ainsi que les archives dont aucune couche n'est retenue.
+///
+/// # Arguments
+///
+/// * `code` - Code départemental
+/// * `layer_config` - Couches BDTOPO à extraire
+///
+/// # Returns
+///
+/// * `Vec<ArchiveLayers>` - Les archives, dans l'ordre de leur traitement
+pub fn archive_layers(code: &str, layer_config: &LayerConfig) -> Vec<ArchiveLayers> {
+    let mut layers = Vec::new();
+    if layer_config.includes(VEGETATION_LAYER) {
+        layers.push(ArchiveLayers {
+            archive: format!("BDFORET_{}.7z", code),
+            layer_type: "Végétation",
+            files: vec![VEGETATION_LAYER.to_string()],
+            required: true,
+        });
+    }
+    if layer_config.includes(RPG_LAYER) {
+        layers.push(ArchiveLayers {
+            archive: format!("RPG_{}.7z", code),
+            layer_type: "Parcelles agricoles",
+            files: vec![RPG_LAYER.to_string()],
+            required: true,
+        });
+    }
+
+    for (group, layer_type) in [
+        (LayerGroup::Topo, "Topographie"),
+        (LayerGroup::Hydro, "Hydrographie"),
+    ] {
+        let mut files_by_archive: Vec<(&str, Vec<String>)> = Vec::new();
+        for layer in layer_config.enabled_layers(group) {
+            match files_by_archive
+                .iter_mut()
+                .find(|(archive, _)| *archive == layer.archive)
+            {
+                Some((_, files)) => files.push(layer.name.clone()),
+                None => files_by_archive.push((&layer.archive, vec![layer.name.clone()])),
+            }
+        }
+        for (archive, files) in files_by_archive {
+            layers.push(ArchiveLayers {
+                archive: format!("{}_{}.7z", archive, code),
+                layer_type,
+                files,
+                required: false,
+            });
+        }
+    }
+
+    layers
+}
+
+fn missing_layer_warning(archive: &str, file: &str) -> Warning {
+    Warning::new(
+        format!("Couche {}", file),
+        format!("absente de l'archive {}, couche ignorée", archive),
+    )
+}
+
+fn skipped_features_warning(file: &str, code: &str, clip_report: &ClipReport) -> Warning {
+    Warning::new(
+        format!("Couche {} ({})", file, code),
+        format!(
+            "{} entités sur {} ignorées lors du découpage (géométries invalides)",
+            clip_report.skipped,
+            clip_report.output_features + clip_report.skipped
+        ),
+    )
+}
+
+/// Vérifie, avant toute extraction, que les archives des départements contiennent
+/// les couches attendues. Les couches obligatoires manquantes de toutes les archives
+/// sont réunies dans une seule erreur ; les couches facultatives manquantes
+/// donnent un avertissement.
+///
+/// # Arguments
+///
+/// * `archive_dir` - Dossier contenant les archives (le cache)
+/// * `regions` - Codes des départements du projet
+/// * `layer_config` - Couches BDTOPO à extraire
+///
+/// # Returns
+///
+/// * `Result<Vec<Warning>, String>` - Les avertissements des couches facultatives absentes,
+///   ou une erreur nommant chaque archive et ses couches obligatoires absentes
+pub fn check_archive_layers(
+    archive_dir: &Path,
+    regions: &[String],
+    layer_config: &LayerConfig,
+) -> Result<Vec<Warning>, String> {
+    let mut warnings = Vec::new();
+    let mut errors = Vec::new();
+
+    for code in regions {
+        for layers in archive_layers(code, layer_config) {
+            let archive_path = archive_dir.join(&layers.archive);
+            let entries = match probe_archive_contents(&archive_path.to_string_lossy()) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    errors.push(format!("{} ({})", layers.archive, e));
+                    continue;
+                }
+            };
+
+            let files: Vec<&str> = layers.files.iter().map(String::as_str).collect();
+            let missing = missing_basenames(&entries, &files);
+            if missing.is_empty() {
+                continue;
+            }
+            if layers.required {
+                errors.push(format!("{} : {}", layers.archive, missing.join(", ")));
+            } else {
+                for file in &missing {
+                    warnings.push(missing_layer_warning(&layers.archive, file));
+                }
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(format!(
+            "Couches obligatoires absentes des archives téléchargées: {}",
+            errors.join("; ")
+        ));
+    }
+    Ok(warnings)
+}
+
+/// Prépare la couche départementale : extraction du contour du département en GeoJSON,
+/// conversion en GPKG puis découpage à l'emprise du projet.
+/// Sans cette couche le projet ne peut pas être construit : toute erreur est donc bloquante,
+/// de même qu'un GPKG découpé sans entité. Les fichiers d'une exécution précédente sont
+/// supprimés au préalable pour qu'un échec ne puisse pas être masqué par un fichier périmé.
+///
+/// # Arguments
+///
+/// * `work_dir` - Dossier de travail de la création
+/// * `project_bb` - BoundingBox du projet
+/// * `code` - Code départemental de la région traitée
+///
+/// # Returns
+///
+/// * `Result<String, String>` - Le chemin du GPKG départemental découpé, ou un message d'erreur
+pub fn prepare_regional_layer(
+    work_dir: &WorkDir,
+    project_bb: &BoundingBox,
+    code: &str,
+) -> Result<String, String> {
+    let regional_geojson_path = work_dir.path_str(format!("{}.geojson", code));
+    let temp_regional_gpkg = work_dir.path_str(format!("{}.gpkg", code));
+    let regional_gpkg = work_dir.path_str(format!("{}_region.gpkg", code));
+
+    for stale in [&regional_geojson_path, &temp_regional_gpkg, &regional_gpkg] {
+        if Path::new(stale).is_file() {
+            fs::remove_file(stale).map_err(|e| {
+                format!(
+                    "Erreur lors de la suppression de {} (département {}): {}",
+                    stale, code, e
+                )
+            })?;
+        }
+    }
+
+    create_region_geojson(code, &regional_geojson_path).map_err(|e| {
+        format!(
+            "Erreur lors de la création de l'étendue du département {}: {:?}",
+            code, e
+        )
+    })?;
+
+    convert_to_gpkg_in_crs(
+        &regional_geojson_path,
+        &temp_regional_gpkg,
+        department_epsg(code),
+    )
+    .map_err(|e| {
+        format!(
+            "Erreur lors de la conversion de l'étendue du département {} en GPKG: {:?}",
+            code, e
+        )
+    })?;
+
+    let report = clip_to_bb(&temp_regional_gpkg, &regional_gpkg, project_bb).map_err(|e| {
+        format!(
+            "Erreur lors du découpage de l'étendue du département {}: {:?}",
+            code, e
+        )
+    })?;
+
+    if !Path::new(&regional_gpkg).is_file() || report.output_features == 0 {
+        return Err(format!(
+            "Étendue du département {} vide après découpage à l'emprise du projet",
+            code
+        ));
+    }
+
+    Ok(regional_gpkg)
 }
 
 /// Ajoute une couche départementale à un projet
 ///
 /// # Arguments
 ///
+/// * `work_dir` - dossier de travail de l'opération
 /// * `project_file_path` - chemin du fichier projet
 /// * `regional_gpkg` - chemin du fichier GeoPackage contenant les données départementales
+/// * `land_color` - couleur des terres sans autre couverture (voir `ProjectMetadata::land_color`)
 ///
 /// # Returns
 ///
 /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
 pub fn add_regional_layer(
+    work_dir: &WorkDir,
     project_file_path: &str,
     regional_gpkg: &str,
+    land_color: Rgb,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    create_directory_if_not_exists("tmp")?;
+    with_project(project_file_path, |project| {
+        add_regional_layer_to(work_dir, project, regional_gpkg, land_color)
+    })
+}
 
-    let project = Dataset::open(project_file_path)?;
-    let regional_dataset = Dataset::open(regional_gpkg)?;
-    let regional_layer = regional_dataset.layer(0)?;
-    let temp_layer = "tmp/temp_layer.tif";
+/// Variante de `add_regional_layer` écrivant dans un projet déjà ouvert.
+pub fn add_regional_layer_to(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    regional_gpkg: &str,
+    land_color: Rgb,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let regional_layers = layer_names(&open_vector(
+        regional_gpkg,
+        &format!("couche régionale du projet {}", project.name()),
+    )?);
+    let temp_mask = work_dir.path_str("temp_regional_mask.tif");
 
     rasterize_layer(
-        &project,
+        project.dataset(),
         regional_gpkg,
-        &regional_layer.name(),
-        temp_layer,
-        ["0", "0", "0"],
+        &regional_layers,
+        &temp_mask,
+        [255, 255, 255],
         None,
         None,
     )?;
 
-    apply_overlay(project_file_path, temp_layer, |&value| value > 0)?;
+    // Le masque, brûlé en blanc, donne la couverture du département : le fond noir des
+    // projets antérieurs (`LEGACY_REGIONAL`) est appliqué et classé comme les autres couleurs.
+    fill_in_place(project, &temp_mask, land_color, colormap::CLASS_LAND)?;
 
-    std::fs::remove_file(temp_layer)?;
+    // Hors du département (mer, territoire étranger), le projet n'a pas de données :
+    // la bande alpha y est mise à 0 pour que les exports et le simulateur puissent les masquer.
+    write_alpha_mask(project, &temp_mask)?;
+
+    std::fs::remove_file(&temp_mask)?;
 
     Ok(())
 }
 
-/// Ajoute une couche RPG (Registre Parcellaire Graphique) à un projet
+/// Ouvre le projet en écriture, y applique une couche puis le ferme : les fonctions
+/// `add_*_layer` par chemin s'appuient dessus pour un ajout isolé.
+fn with_project(
+    project_file_path: &str,
+    add: impl FnOnce(&ProjectRaster) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project = ProjectRaster::open(project_file_path)?;
+    add(&project)?;
+    project.close()
+}
+
+/// Nombre de parcelles RPG par classe de combustible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpgClassCount {
+    /// Clé de la classe (`cereals`, `vineyards`...), ou `None` pour les parcelles non classées.
+    pub key: Option<String>,
+    pub features: u64,
+}
+
+/// Compte les parcelles d'une couche RPG dans chaque classe de la table, plus les parcelles
+/// non classées (code absent ou inconnu, ou attribut manquant dans la couche).
 ///
 /// # Arguments
 ///
-/// * `project_file_path` - chemin du fichier projet
 /// * `rpg_gpkg` - chemin du fichier GeoPackage contenant les données RPG
+/// * `table` - table des classes RPG
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
-pub fn add_rpg_layer(
-    project_file_path: &str,
+/// * `Result<Vec<RpgClassCount>, Box<dyn std::error::Error>>` - le décompte par classe, les parcelles non classées en dernier
+pub fn count_rpg_classes(
     rpg_gpkg: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    create_directory_if_not_exists("tmp")?;
-
-    let project = Dataset::open(project_file_path)?;
-    let rpg_dataset = Dataset::open(rpg_gpkg)?;
-    let rpg_layer = rpg_dataset.layer(0)?;
-    let temp_rpg_layer = "tmp/temp_rpg_layer.tif";
-
-    rasterize_layer(
-        &project,
-        rpg_gpkg,
-        &rpg_layer.name(),
-        temp_rpg_layer,
-        ["25", "50", "60"],
-        None,
-        None,
-    )?;
+    table: &RpgClassTable,
+) -> Result<Vec<RpgClassCount>, Box<dyn std::error::Error>> {
+    let rpg_dataset = open_vector(rpg_gpkg, "couche RPG")?;
+    let has_attribute = rpg_dataset.layers().all(|layer| {
+        layer
+            .defn()
+            .fields()
+            .any(|field| field.name().eq_ignore_ascii_case(&table.attribute))
+    });
+
+    // Les entités de toutes les couches du fichier sont comptées.
+    let count = |filter: Option<&str>| -> Result<u64, Box<dyn std::error::Error>> {
+        let mut features = 0;
+        for mut layer in rpg_dataset.layers() {
+            if let Some(filter) = filter {
+                layer.set_attribute_filter(filter)?;
+            }
+            features += layer.feature_count();
+            layer.clear_attribute_filter();
+        }
+        Ok(features)
+    };
 
-    apply_overlay(project_file_path, temp_rpg_layer, |&value| value > 0)?;
+    let mut counts = Vec::new();
+    if !has_attribute {
+        counts.push(RpgClassCount {
+            key: None,
+            features: count(None)?,
+        });
+        return Ok(counts);
+    }
 
-    std::fs::remove_file(temp_rpg_layer)?;
+    for class in &table.classes {
+        counts.push(RpgClassCount {
+            key: Some(class.key.clone()),
+            features: count(Some(&table.where_clause(class)?))?,
+        });
+    }
+    counts.push(RpgClassCount {
+        key: None,
+        features: count(Some(&table.unclassified_where()?))?,
+    });
 
-    Ok(())
+    Ok(counts)
 }
 
-/// Ajoute une couche de végétation à un projet en distinguant différents types
+/// Ajoute une couche RPG (Registre Parcellaire Graphique) à un projet.
+/// Les parcelles sont réparties en classes de combustible selon leur groupe de cultures
+/// (voir `resources/rpg_classes.json`), chacune brûlée avec sa propre couleur ;
+/// les parcelles sans code ou de code inconnu gardent la couleur générique du RPG
+/// et sont signalées par un avertissement.
 ///
 /// # Arguments
 ///
+/// * `work_dir` - dossier de travail de l'opération
 /// * `project_file_path` - chemin du fichier projet
-/// * `vegetation_gpkg` - chemin du fichier GeoPackage contenant les données de végétation
+/// * `rpg_gpkg` - chemin du fichier GeoPackage contenant les données RPG
+/// * `warnings` - avertissements de la création, complétés pour les parcelles non classées
 ///
 /// # Returns
 ///
 /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
-pub fn add_vegetation_layer(
+pub fn add_rpg_layer(
+    work_dir: &WorkDir,
     project_file_path: &str,
-    vegetation_gpkg: &str,
+    rpg_gpkg: &str,
+    warnings: &mut Vec<Warning>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    create_directory_if_not_exists("tmp")?;
-    let vegetation_dataset = Dataset::open(vegetation_gpkg)?;
-    let vegetation_layer = vegetation_dataset.layer(0)?;
-    let project = Dataset::open(project_file_path)?;
-
-    let feuillus_types = [
-        "Feuillus",
-        "Châtaignier",
-        "Chênes sempervirents",
-        "Chênes décidus",
-        "Hêtre",
-    ];
-    let undefined_types = ["NC", "NR"];
-
-    let feuillus_where = format!(
-        "ESSENCE IN ('{}', '{}', '{}', '{}', '{}')",
-        feuillus_types[0],
-        feuillus_types[1],
-        feuillus_types[2],
-        feuillus_types[3],
-        feuillus_types[4]
-    );
-
-    let undefined_where = format!(
-        "ESSENCE IN ('{}', '{}')",
-        undefined_types[0], undefined_types[1]
-    );
-
-    let all_types = feuillus_types
-        .iter()
-        .chain(undefined_types.iter())
-        .map(|t| format!("'{}'", t))
-        .collect::<Vec<String>>()
-        .join(", ");
-    let other_where = format!("ESSENCE NOT IN ({})", all_types);
-    let temp_vegetation = "tmp/temp_vegetation.tif";
-    let temp_feuillus = "tmp/temp_feuillus.tif";
-    let temp_undefined = "tmp/temp_undefined.tif";
-    let temp_other = "tmp/temp_other.tif";
+    with_project(project_file_path, |project| {
+        add_rpg_layer_to(work_dir, project, rpg_gpkg, warnings)
+    })
+}
 
-    rasterize_layer(
-        &project,
-        vegetation_gpkg,
-        &vegetation_layer.name(),
-        temp_feuillus,
-        ["80", "200", "120"],
-        Some(&feuillus_where),
-        None,
+/// Variante de `add_rpg_layer` écrivant dans un projet déjà ouvert.
+pub fn add_rpg_layer_to(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    rpg_gpkg: &str,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rpg_dataset = open_vector(
+        rpg_gpkg,
+        &format!("couche RPG du projet {}", project.name()),
     )?;
+    if skip_empty_layer(&rpg_dataset, "Couche RPG", warnings) {
+        return Ok(());
+    }
+    let table = colormap::load_rpg_classes()?;
+    let counts = count_rpg_classes(rpg_gpkg, &table)?;
+
+    let rpg_layers = layer_names(&rpg_dataset);
+
+    let mut class_rasters = Vec::new();
+    for count in counts.iter().filter(|count| count.features > 0) {
+        let (key, color, where_clause) = match &count.key {
+            Some(key) => {
+                let class = table
+                    .classes
+                    .iter()
+                    .find(|class| &class.key == key)
+                    .unwrap();
+                (key.as_str(), class.color, Some(table.where_clause(class)?))
+            }
+            None => {
+                warnings.push(Warning::new(
+                    "Couche RPG",
+                    format!(
+                        "{} parcelle(s) sans groupe de cultures reconnu, couleur générique appliquée",
+                        count.features
+                    ),
+                ));
+                // Sans l'attribut, toutes les parcelles sont brûlées sans filtre.
+                let has_classes = counts.iter().any(|count| count.key.is_some());
+                (
+                    "unclassified",
+                    colormap::RPG,
+                    has_classes
+                        .then(|| table.unclassified_where())
+                        .transpose()?,
+                )
+            }
+        };
 
-    rasterize_layer(
-        &project,
-        vegetation_gpkg,
-        &vegetation_layer.name(),
-        temp_undefined,
-        ["25", "50", "60"],
-        Some(&undefined_where),
-        None,
-    )?;
+        let class_raster = work_dir.path_str(&format!("temp_rpg_{}.tif", key));
+        rasterize_layer(
+            project.dataset(),
+            rpg_gpkg,
+            &rpg_layers,
+            &class_raster,
+            color,
+            where_clause.as_deref(),
+            None,
+        )?;
+        class_rasters.push(class_raster);
+    }
 
-    rasterize_layer(
-        &project,
-        vegetation_gpkg,
-        &vegetation_layer.name(),
-        temp_other,
-        ["50", "200", "80"],
-        Some(&other_where),
-        None,
-    )?;
-    let driver_manager = DriverManager::get_driver_by_name("GTiff")?;
-    let (width, height) = project.raster_size();
+    if class_rasters.is_empty() {
+        return Ok(());
+    }
 
-    let mut vegetation_raster = driver_manager.create(temp_vegetation, width, height, 3)?;
+    let temp_rpg_layer = work_dir.path_str("temp_rpg_layer.tif");
+    combine_class_rasters(project.dataset(), &class_rasters, &temp_rpg_layer)?;
 
-    vegetation_raster.set_geo_transform(&project.geo_transform()?)?;
-    vegetation_raster.set_projection(&project.projection())?;
+    overlay_in_place(
+        project,
+        &temp_rpg_layer,
+        |&value| value > 0,
+        Some(&|_| colormap::CLASS_RPG),
+    )?;
 
-    for i in 1..=3 {
-        let mut band = vegetation_raster.rasterband(i)?;
-        let zeros = vec![0u8; width * height];
-        band.write(
-            (0, 0),
-            (width, height),
-            &mut gdal::raster::Buffer::new((width, height), zeros),
-        )?;
+    std::fs::remove_file(&temp_rpg_layer)?;
+    for class_raster in class_rasters {
+        std::fs::remove_file(class_raster)?;
     }
-    let feuillus_dataset = Dataset::open(temp_feuillus)?;
-    let undefined_dataset = Dataset::open(temp_undefined)?;
-    let other_dataset = Dataset::open(temp_other)?;
-
-    for band_idx in 1..=3 {
-        let mut veg_band = vegetation_raster.rasterband(band_idx)?;
 
-        let feuillus_band = feuillus_dataset.rasterband(band_idx)?;
-        let feuillus_data: Vec<u8> = feuillus_band
-            .read_as::<u8>((0, 0), (width, height), (width, height), None)?
-            .data()
-            .to_vec();
-
-        let undefined_band = undefined_dataset.rasterband(band_idx)?;
-        let undefined_data: Vec<u8> = undefined_band
-            .read_as::<u8>((0, 0), (width, height), (width, height), None)?
-            .data()
-            .to_vec();
+    Ok(())
+}
 
-        let other_band = other_dataset.rasterband(band_idx)?;
-        let other_data: Vec<u8> = other_band
-            .read_as::<u8>((0, 0), (width, height), (width, height), None)?
-            .data()
-            .to_vec();
+/// Combine des rasters RGB brûlés classe par classe en un seul raster sur la grille du projet.
+/// Pour chaque pixel, la couleur du premier raster non nul l'emporte.
+fn combine_class_rasters(
+    project: &Dataset,
+    class_rasters: &[String],
+    output_raster: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = project.raster_size();
+    let mut combined = [
+        vec![0u8; width * height],
+        vec![0u8; width * height],
+        vec![0u8; width * height],
+    ];
+    let mut filled = vec![false; width * height];
+
+    for class_raster in class_rasters {
+        let dataset = open_raster(class_raster, "raster d'une classe RPG")?;
+        let bands = (1..=3)
+            .map(|band_idx| {
+                Ok(dataset
+                    .rasterband(band_idx)?
+                    .read_as::<u8>((0, 0), (width, height), (width, height), None)?
+                    .data()
+                    .to_vec())
+            })
+            .collect::<Result<Vec<Vec<u8>>, gdal::errors::GdalError>>()?;
 
-        let combined_data: Vec<u8> = feuillus_data
-            .iter()
-            .zip(undefined_data.iter())
-            .zip(other_data.iter())
-            .map(|((&f, &u), &o)| {
-                if f > 0 {
-                    f
-                } else if u > 0 {
-                    u
-                } else if o > 0 {
-                    o
-                } else {
-                    0
+        for i in 0..width * height {
+            if !filled[i] && bands.iter().any(|band| band[i] > 0) {
+                filled[i] = true;
+                for (combined_band, band) in combined.iter_mut().zip(bands.iter()) {
+                    combined_band[i] = band[i];
                 }
-            })
-            .collect();
+            }
+        }
+        dataset.close()?;
+    }
 
-        veg_band.write(
+    let driver_manager = DriverManager::get_driver_by_name("GTiff")?;
+    let mut output = driver_manager.create(output_raster, width, height, 3)?;
+    output.set_geo_transform(&project.geo_transform()?)?;
+    output.set_projection(&project.projection())?;
+    for (band_idx, data) in (1..=3).zip(combined) {
+        output.rasterband(band_idx)?.write(
             (0, 0),
             (width, height),
-            &mut gdal::raster::Buffer::new((width, height), combined_data),
+            &mut gdal::raster::Buffer::new((width, height), data),
         )?;
     }
+    output.close()?;
 
-    feuillus_dataset.close().unwrap();
-    undefined_dataset.close().unwrap();
-    other_dataset.close().unwrap();
-    vegetation_raster.close().unwrap();
-    apply_overlay(project_file_path, temp_vegetation, |&value| value > 0)?;
+    Ok(())
+}
 
-    std::fs::remove_file(temp_vegetation)?;
-    std::fs::remove_file(temp_feuillus)?;
-    std::fs::remove_file(temp_undefined)?;
-    std::fs::remove_file(temp_other)?;
+/// Essences de la BD Forêt brûlées avec la couleur des feuillus.
+const FEUILLUS_ESSENCES: [&str; 5] = [
+    "Feuillus",
+    "Châtaignier",
+    "Chênes sempervirents",
+    "Chênes décidus",
+    "Hêtre",
+];
+
+/// Essences de la BD Forêt non renseignées (`NC` : non concerné, `NR` : non renseigné).
+const UNDEFINED_ESSENCES: [&str; 2] = ["NC", "NR"];
+
+/// Autres essences connues de la BD Forêt, brûlées avec la couleur générique de la végétation.
+const OTHER_ESSENCES: [&str; 14] = [
+    "Robinier",
+    "Peuplier",
+    "Pin maritime",
+    "Pin sylvestre",
+    "Pin laricio, pin noir",
+    "Pin d'Alep",
+    "Pin à crochets, pin cembro",
+    "Pin autre",
+    "Pins mélangés",
+    "Sapin, épicéa",
+    "Douglas",
+    "Mélèze",
+    "Conifères",
+    "Mixte",
+];
+
+/// Forme de comparaison d'une valeur d'ESSENCE : en minuscules, sans accents
+/// et sans espaces superflus, les éditions départementales de la BD Forêt variant sur ces points.
+pub fn normalize_essence(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'ç' => 'c',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            '’' => '\'',
+            c => c,
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
 
-    Ok(())
+/// Attribut de la BD Forêt v2 portant l'essence des formations végétales.
+pub const ESSENCE_FIELD: &str = "ESSENCE";
+/// Attribut de la BD Forêt v1 portant le code du type de formation végétale (`FF1-00-00`...).
+pub const CODE_TFV_FIELD: &str = "CODE_TFV";
+/// Attribut de la BD Forêt v1 portant le libellé du type de formation végétale.
+pub const TFV_FIELD: &str = "TFV";
+
+/// Classe de végétation d'une valeur de la BD Forêt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VegetationClass {
+    Feuillus,
+    Undefined,
+    Other,
 }
 
-/// Ajoute une couche topographique à un projet
+/// Types de formation végétale de la BD Forêt v1, par préfixe de leur code (`CODE_TFV`)
+/// et de leur libellé (`TFV`). Comme pour les essences de la v2, les peupleraies et les
+/// mélanges sont classés avec les autres formations.
+const TFV_CLASSES: [(&str, &str, VegetationClass); 11] = [
+    ("FF1", "Forêt fermée de feuillus", VegetationClass::Feuillus),
+    (
+        "FO1",
+        "Forêt ouverte de feuillus",
+        VegetationClass::Feuillus,
+    ),
+    (
+        "FF0",
+        "Forêt fermée sans couvert arboré",
+        VegetationClass::Undefined,
+    ),
+    (
+        "FO0",
+        "Forêt ouverte sans couvert arboré",
+        VegetationClass::Undefined,
+    ),
+    ("FF2", "Forêt fermée de conifères", VegetationClass::Other),
+    ("FF3", "Forêt fermée à mélange", VegetationClass::Other),
+    ("FO2", "Forêt ouverte de conifères", VegetationClass::Other),
+    ("FO3", "Forêt ouverte à mélange", VegetationClass::Other),
+    ("FP", "Peupleraie", VegetationClass::Other),
+    ("LA4", "Formation herbacée", VegetationClass::Other),
+    ("LA6", "Lande", VegetationClass::Other),
+];
+
+/// Classe d'un type de formation végétale de la BD Forêt v1, reconnu par son code ou son libellé.
+fn tfv_class(value: &str) -> Option<VegetationClass> {
+    let value = normalize_essence(value);
+    TFV_CLASSES
+        .iter()
+        .find(|(code, label, _)| {
+            value.starts_with(&normalize_essence(code))
+                || value.starts_with(&normalize_essence(label))
+        })
+        .map(|(_, _, class)| *class)
+}
+
+/// Attribut de classement d'une couche de végétation d'après son schéma : `ESSENCE` pour la
+/// BD Forêt v2, sinon `CODE_TFV` ou `TFV` pour la v1.
 ///
 /// # Arguments
 ///
-/// * `project_file_path` - chemin du fichier projet
-/// * `topo_gpkg` - chemin du fichier GeoPackage contenant les données topographiques
+/// * `field_names` - les noms des champs de la couche
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
-pub fn add_topo_layer(
-    project_file_path: &str,
-    topo_gpkg: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    create_directory_if_not_exists("tmp")?;
+/// * `Option<&'static str>` - l'attribut, ou `None` si la couche n'en contient aucun
+pub fn vegetation_attribute(field_names: &[String]) -> Option<&'static str> {
+    [ESSENCE_FIELD, CODE_TFV_FIELD, TFV_FIELD]
+        .into_iter()
+        .find(|attribute| {
+            field_names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(attribute))
+        })
+}
 
-    let project = Dataset::open(project_file_path)?;
-    let topo_dataset = Dataset::open(topo_gpkg)?;
-    let mut topo_layer = topo_dataset.layer(0)?;
+/// Valeurs de l'attribut de classement d'une couche de végétation, réparties par classe.
+/// Les valeurs brutes de la couche sont conservées pour construire les filtres de rastérisation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EssenceClassification {
+    /// Attribut classé : `ESSENCE` (BD Forêt v2), `CODE_TFV` ou `TFV` (BD Forêt v1).
+    pub attribute: String,
+    pub feuillus: Vec<String>,
+    pub undefined: Vec<String>,
+    pub other: Vec<String>,
+    /// Valeurs d'aucune classe connue, avec leur nombre d'entités ;
+    /// elles sont brûlées avec les autres essences.
+    pub unmatched: Vec<(String, u64)>,
+}
 
-    if topo_layer.features().next().is_none() {
-        println!("Layer has no features");
-        return Ok(());
+impl Default for EssenceClassification {
+    fn default() -> Self {
+        EssenceClassification {
+            attribute: ESSENCE_FIELD.to_string(),
+            feuillus: Vec::new(),
+            undefined: Vec::new(),
+            other: Vec::new(),
+            unmatched: Vec::new(),
+        }
     }
+}
 
-    let geom_type = topo_layer
-        .features()
-        .next()
-        .ok_or("No features in layer")?
-        .geometry()
-        .ok_or("Feature has no geometry")?
-        .geometry_type();
-
-    let temp_topo_layer = "tmp/temp_topo_layer.tif";
+impl EssenceClassification {
+    /// Répartit les valeurs d'ESSENCE d'une couche par classe, en comparant leurs formes normalisées.
+    ///
+    /// # Arguments
+    ///
+    /// * `counts` - les valeurs distinctes de la couche et leur nombre d'entités
+    pub fn from_counts(counts: &[(String, u64)]) -> Self {
+        let matches = |known: &[&str], value: &str| {
+            let value = normalize_essence(value);
+            known
+                .iter()
+                .any(|essence| normalize_essence(essence) == value)
+        };
 
-    let driver_manager = DriverManager::get_driver_by_name("GTiff")?;
-    let mut dummy_raster = driver_manager.create(
-        temp_topo_layer,
-        project.raster_size().0,
-        project.raster_size().1,
-        3,
-    )?;
+        Self::classify(ESSENCE_FIELD, counts, |value| {
+            if matches(&FEUILLUS_ESSENCES, value) {
+                Some(VegetationClass::Feuillus)
+            } else if matches(&UNDEFINED_ESSENCES, value) {
+                Some(VegetationClass::Undefined)
+            } else if matches(&OTHER_ESSENCES, value) {
+                Some(VegetationClass::Other)
+            } else {
+                None
+            }
+        })
+    }
 
-    dummy_raster.set_geo_transform(&project.geo_transform()?)?;
-    dummy_raster.set_projection(&project.projection())?;
+    /// Répartit les types de formation végétale d'une couche de la BD Forêt v1 par classe,
+    /// d'après la table `TFV_CLASSES`.
+    ///
+    /// # Arguments
+    ///
+    /// * `attribute` - l'attribut classé (`CODE_TFV` ou `TFV`)
+    /// * `counts` - les valeurs distinctes de la couche et leur nombre d'entités
+    pub fn from_tfv_counts(attribute: &str, counts: &[(String, u64)]) -> Self {
+        Self::classify(attribute, counts, tfv_class)
+    }
 
-    for i in 1..=3 {
-        let mut band = dummy_raster.rasterband(i)?;
-        let dummy_data = vec![255u8; project.raster_size().0 * project.raster_size().1];
-        band.write(
-            (0, 0),
-            (project.raster_size().0, project.raster_size().1),
-            &mut gdal::raster::Buffer::new(
-                (project.raster_size().0, project.raster_size().1),
-                dummy_data,
-            ),
-        )?;
+    fn classify(
+        attribute: &str,
+        counts: &[(String, u64)],
+        class_of: impl Fn(&str) -> Option<VegetationClass>,
+    ) -> Self {
+        let mut classification = EssenceClassification {
+            attribute: attribute.to_string(),
+            ..EssenceClassification::default()
+        };
+        for (value, features) in counts {
+            match class_of(value) {
+                Some(VegetationClass::Feuillus) => classification.feuillus.push(value.clone()),
+                Some(VegetationClass::Undefined) => classification.undefined.push(value.clone()),
+                Some(VegetationClass::Other) => classification.other.push(value.clone()),
+                None => classification.unmatched.push((value.clone(), *features)),
+            }
+        }
+        classification
     }
 
-    dummy_raster.close().unwrap();
+    /// La couche provient de la BD Forêt v1.
+    pub fn is_bdforet_v1(&self) -> bool {
+        self.attribute != ESSENCE_FIELD
+    }
 
-    let layer_name = topo_layer.name();
-    let args = if geom_type == OGRwkbGeometryType::wkbLineString
-        || geom_type == OGRwkbGeometryType::wkbMultiLineString
-    {
-        vec![
-            "-burn",
-            "0",
-            "-burn",
-            "0",
-            "-burn",
-            "0",
-            "-l",
-            &layer_name,
-            "-at",
-            topo_gpkg,
-            temp_topo_layer,
-        ]
-    } else {
-        vec![
-            "-burn",
-            "0",
-            "-burn",
-            "0",
-            "-burn",
-            "0",
-            "-l",
-            &layer_name,
-            topo_gpkg,
-            temp_topo_layer,
-        ]
-    };
+    pub fn feuillus_where(&self) -> Result<String, String> {
+        build_in_clause(&self.attribute, &self.feuillus)
+    }
 
-    let status = Command::new("gdal_rasterize").args(args).status()?;
+    pub fn undefined_where(&self) -> Result<String, String> {
+        build_in_clause(&self.attribute, &self.undefined)
+    }
 
-    if !status.success() {
-        return Err("gdal_rasterize failed".into());
+    /// Filtre des autres essences : toute valeur renseignée qui n'est ni un feuillu
+    /// ni une essence non renseignée, y compris les valeurs inconnues.
+    pub fn other_where(&self) -> Result<String, String> {
+        let classified: Vec<String> = self
+            .feuillus
+            .iter()
+            .chain(self.undefined.iter())
+            .cloned()
+            .collect();
+        if classified.is_empty() {
+            Ok(format!("{} IS NOT NULL", self.attribute))
+        } else {
+            build_not_in_clause(&self.attribute, &classified)
+        }
     }
+}
 
-    let output_file = "tmp/output.tif";
-    let mut output_dataset = driver_manager.create(
-        output_file,
-        project.raster_size().0,
-        project.raster_size().1,
-        4,
-    )?;
+/// Relève les valeurs distinctes de l'attribut de classement d'une couche de végétation
+/// (`ESSENCE`, ou `CODE_TFV`/`TFV` pour la BD Forêt v1) et les répartit par classe.
+/// L'attribut est déterminé d'après la première couche du fichier ; les valeurs de
+/// toutes ses couches sont prises en compte.
+///
+/// # Arguments
+///
+/// * `vegetation_gpkg` - chemin du fichier GeoPackage contenant les données de végétation
+///
+/// # Returns
+///
+/// * `Result<EssenceClassification, Box<dyn std::error::Error>>` - les valeurs de chaque classe
+pub fn classify_essences(
+    vegetation_gpkg: &str,
+) -> Result<EssenceClassification, Box<dyn std::error::Error>> {
+    let dataset = open_vector(vegetation_gpkg, "couche de végétation")?;
+    let layers = layer_names(&dataset);
+    let first_layer = layers.first().ok_or("Aucune couche de végétation")?;
+    let field_names: Vec<String> = dataset
+        .layer_by_name(first_layer)?
+        .defn()
+        .fields()
+        .map(|field| field.name())
+        .collect();
+    let attribute = vegetation_attribute(&field_names).ok_or(format!(
+        "Aucun champ {}, {} ou {} dans la couche de végétation {}",
+        ESSENCE_FIELD, CODE_TFV_FIELD, TFV_FIELD, first_layer
+    ))?;
+
+    // Les valeurs de toutes les couches du fichier sont cumulées.
+    let mut counts: Vec<(String, u64)> = Vec::new();
+    for layer_name in &layers {
+        let mut result = dataset
+            .execute_sql(
+                format!(
+                    "SELECT {attribute}, COUNT(*) FROM \"{layer_name}\" WHERE {attribute} IS NOT NULL GROUP BY {attribute}"
+                ),
+                None,
+                Dialect::DEFAULT,
+            )?
+            .ok_or("Lecture des essences impossible")?;
+
+        for feature in result.features() {
+            if let Some(value) = feature.field_as_string(0)? {
+                let features = feature.field_as_integer64(1)?.unwrap_or(0) as u64;
+                match counts.iter_mut().find(|(known, _)| *known == value) {
+                    Some((_, count)) => *count += features,
+                    None => counts.push((value, features)),
+                }
+            }
+        }
+    }
+    if attribute == ESSENCE_FIELD {
+        Ok(EssenceClassification::from_counts(&counts))
+    } else {
+        tracing::info!(
+            attribute,
+            "Couche de végétation BD Forêt v1, classement par type de formation"
+        );
+        Ok(EssenceClassification::from_tfv_counts(attribute, &counts))
+    }
+}
 
-    output_dataset.set_geo_transform(&project.geo_transform()?)?;
-    output_dataset.set_projection(&project.projection())?;
+/// Nombre de lignes lues à la fois lors de la fusion des classes de végétation.
+const VEGETATION_MERGE_STRIPE_ROWS: usize = 256;
+
+/// Fusionne une bande de lignes des rasters de classes de végétation, donnés par ordre
+/// de priorité : chaque pixel prend la première valeur non nulle.
+fn merge_vegetation_stripes(stripes: &[Vec<u8>]) -> Vec<u8> {
+    let mut merged = vec![0u8; stripes.first().map_or(0, Vec::len)];
+    for stripe in stripes {
+        for (value, &candidate) in merged.iter_mut().zip(stripe) {
+            if *value == 0 {
+                *value = candidate;
+            }
+        }
+    }
+    merged
+}
 
-    let topo_raster = Dataset::open(temp_topo_layer)?;
+/// Ajoute une couche de végétation à un projet en distinguant différents types
+/// (feuillus, essences non renseignées et autres essences). Les valeurs d'ESSENCE
+/// sont reconnues sans tenir compte de la casse ni des accents ; celles d'aucune
+/// classe connue sont brûlées avec les autres essences et signalées par un avertissement.
+/// Une couche de la BD Forêt v1, sans champ ESSENCE, est classée par type de formation
+/// végétale (`CODE_TFV` ou `TFV`). Les trois classes sont rastérisées en parallèle puis
+/// fusionnées par ordre de priorité (feuillus, essences non renseignées, autres essences).
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project_file_path` - chemin du fichier projet
+/// * `vegetation_gpkg` - chemin du fichier GeoPackage contenant les données de végétation
+/// * `warnings` - avertissements de la création, complétés pour les essences inconnues
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_vegetation_layer(
+    work_dir: &WorkDir,
+    project_file_path: &str,
+    vegetation_gpkg: &str,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    with_project(project_file_path, |project| {
+        add_vegetation_layer_to(work_dir, project, vegetation_gpkg, warnings)
+    })
+}
 
-    let base_data = [
-        project.rasterband(1)?,
-        project.rasterband(2)?,
-        project.rasterband(3)?,
-        project.rasterband(4)?,
-    ];
+/// Variante de `add_vegetation_layer` écrivant dans un projet déjà ouvert.
+pub fn add_vegetation_layer_to(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    vegetation_gpkg: &str,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let vegetation_dataset = open_vector(
+        vegetation_gpkg,
+        &format!("couche de végétation du projet {}", project.name()),
+    )?;
+    if skip_empty_layer(&vegetation_dataset, "Couche de végétation", warnings) {
+        return Ok(());
+    }
+    let vegetation_layers = layer_names(&vegetation_dataset);
+    let project_dataset = project.dataset();
 
-    let overlay_data = [
-        topo_raster.rasterband(1)?,
-        topo_raster.rasterband(2)?,
-        topo_raster.rasterband(3)?,
+    let essences = classify_essences(vegetation_gpkg)?;
+    let unknown = if essences.is_bdforet_v1() {
+        "type de formation inconnu"
+    } else {
+        "essence inconnue"
+    };
+    for (value, features) in &essences.unmatched {
+        tracing::warn!(
+            essence = %value,
+            features,
+            "Essence inconnue, couleur des autres essences appliquée"
+        );
+        warnings.push(Warning::new(
+            "Couche de végétation",
+            format!(
+                "{} '{}' ({} entité(s)), couleur des autres essences appliquée",
+                unknown, value, features
+            ),
+        ));
+    }
+    let feuillus_where = essences.feuillus_where()?;
+    let undefined_where = essences.undefined_where()?;
+    let other_where = essences.other_where()?;
+    let temp_vegetation = work_dir.path_str("temp_vegetation.tif");
+    let temp_feuillus = work_dir.path_str("temp_feuillus.tif");
+    let temp_undefined = work_dir.path_str("temp_undefined.tif");
+    let temp_other = work_dir.path_str("temp_other.tif");
+
+    // Par ordre de priorité : les feuillus masquent les essences non renseignées,
+    // qui masquent les autres essences.
+    let sub_classes = [
+        (&temp_feuillus, colormap::FEUILLUS, &feuillus_where),
+        (
+            &temp_undefined,
+            colormap::UNDEFINED_VEGETATION,
+            &undefined_where,
+        ),
+        (&temp_other, colormap::OTHER_VEGETATION, &other_where),
     ];
+    let geo_transform = project_dataset.geo_transform()?;
+    let (width, height) = project_dataset.raster_size();
 
-    let mut mask = vec![false; project.raster_size().0 * project.raster_size().1];
-    for band in &overlay_data {
-        let band_data: Vec<u8> = band
-            .read_as::<u8>(
-                (0, 0),
-                (project.raster_size().0, project.raster_size().1),
-                (project.raster_size().0, project.raster_size().1),
-                None,
-            )?
-            .data()
-            .to_vec();
-        for (i, &value) in band_data.iter().enumerate() {
-            if value != 255 {
-                mask[i] = true;
+    {
+        let _timer = processing_timer("rasterize_vegetation_parallel");
+        tracing::debug!(
+            sub_classes = sub_classes.len(),
+            "Rastérisation parallèle des classes de végétation"
+        );
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = sub_classes
+                .iter()
+                .map(|(output, burn_values, where_clause)| {
+                    let vegetation_layers = &vegetation_layers;
+                    scope.spawn(move || {
+                        rasterize_layer_on_grid(
+                            &geo_transform,
+                            (width, height),
+                            vegetation_gpkg,
+                            vegetation_layers,
+                            output.as_str(),
+                            *burn_values,
+                            Some(where_clause.as_str()),
+                            None,
+                        )
+                        .map_err(|e| e.to_string())
+                    })
+                })
+                .collect();
+            handles.into_iter().try_for_each(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| "Rastérisation de la végétation interrompue".to_string())?
+            })
+        })?;
+    }
+
+    {
+        let _timer = processing_timer("merge_vegetation");
+        let driver_manager = DriverManager::get_driver_by_name("GTiff")?;
+        let mut vegetation_raster = driver_manager.create(&temp_vegetation, width, height, 3)?;
+        vegetation_raster.set_geo_transform(&geo_transform)?;
+        vegetation_raster.set_projection(&project_dataset.projection())?;
+
+        let sources = [
+            open_raster(&temp_feuillus, "raster des feuillus")?,
+            open_raster(&temp_undefined, "raster de la végétation non renseignée")?,
+            open_raster(&temp_other, "raster des autres formations")?,
+        ];
+        // Les trois rasters sont fusionnés par bandes de lignes : seules quelques lignes
+        // de chaque raster sont en mémoire, et les trois bandes RGB sont combinées en parallèle.
+        for y in (0..height).step_by(VEGETATION_MERGE_STRIPE_ROWS) {
+            let rows = VEGETATION_MERGE_STRIPE_ROWS.min(height - y);
+            let mut stripes = Vec::with_capacity(3);
+            for band_idx in 1..=3 {
+                let mut band_stripes = Vec::with_capacity(sources.len());
+                for source in &sources {
+                    band_stripes.push(
+                        source
+                            .rasterband(band_idx)?
+                            .read_as::<u8>((0, y as isize), (width, rows), (width, rows), None)?
+                            .data()
+                            .to_vec(),
+                    );
+                }
+                stripes.push(band_stripes);
             }
+
+            let merged: Vec<Vec<u8>> = stripes
+                .par_iter()
+                .map(|band_stripes| merge_vegetation_stripes(band_stripes))
+                .collect();
+
+            for (band_idx, data) in merged.into_iter().enumerate() {
+                vegetation_raster.rasterband(band_idx + 1)?.write(
+                    (0, y as isize),
+                    (width, rows),
+                    &mut gdal::raster::Buffer::new((width, rows), data),
+                )?;
+            }
+        }
+
+        for source in sources {
+            source.close()?;
         }
+        vegetation_raster.close()?;
     }
 
-    for (i, base_band) in base_data.iter().enumerate() {
-        let mut out_band = output_dataset.rasterband(i + 1)?;
-        let base_band_data: Vec<u8> = base_band
-            .read_as::<u8>(
-                (0, 0),
-                (project.raster_size().0, project.raster_size().1),
-                (project.raster_size().0, project.raster_size().1),
-                None,
-            )?
-            .data()
-            .to_vec();
+    overlay_in_place(
+        project,
+        &temp_vegetation,
+        |&value| value > 0,
+        Some(&colormap::vegetation_class),
+    )?;
 
-        let data = if i < 3 {
-            base_band_data
-                .iter()
-                .zip(mask.iter())
-                .map(
-                    |(&base_value, &mask_value)| {
-                        if mask_value { 0 } else { base_value }
-                    },
-                )
-                .collect::<Vec<u8>>()
-        } else {
-            base_band_data
-        };
+    std::fs::remove_file(&temp_vegetation)?;
+    std::fs::remove_file(&temp_feuillus)?;
+    std::fs::remove_file(&temp_undefined)?;
+    std::fs::remove_file(&temp_other)?;
 
-        out_band.write(
-            (0, 0),
-            (project.raster_size().0, project.raster_size().1),
-            &mut gdal::raster::Buffer::new(
-                (project.raster_size().0, project.raster_size().1),
-                data,
+    Ok(())
+}
+
+/// Ajoute une couche topographique à un projet
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project_file_path` - chemin du fichier projet
+/// * `topo_gpkg` - chemin du fichier GeoPackage contenant les données topographiques
+/// * `warnings` - avertissements de la création, complétés si la couche est vide
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_topo_layer(
+    work_dir: &WorkDir,
+    project_file_path: &str,
+    topo_gpkg: &str,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    with_project(project_file_path, |project| {
+        burn_feature_layer(
+            work_dir,
+            project,
+            topo_gpkg,
+            colormap::TOPO,
+            &colormap::topo_class,
+            GeometryHint::Auto,
+            warnings,
+        )
+    })
+}
+
+/// Ajoute une couche hydrographique (cours d'eau, plans d'eau, surfaces hydrographiques) à un projet.
+/// L'eau n'est pas combustible mais constitue une ressource pour le simulateur :
+/// elle reçoit donc une couleur distincte des éléments topographiques.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project_file_path` - chemin du fichier projet
+/// * `hydro_gpkg` - chemin du fichier GeoPackage contenant les données hydrographiques
+/// * `warnings` - avertissements de la création, complétés si la couche est vide
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_hydro_layer(
+    work_dir: &WorkDir,
+    project_file_path: &str,
+    hydro_gpkg: &str,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    with_project(project_file_path, |project| {
+        burn_feature_layer(
+            work_dir,
+            project,
+            hydro_gpkg,
+            colormap::HYDRO,
+            &|_| colormap::CLASS_WATER,
+            GeometryHint::Auto,
+            warnings,
+        )
+    })
+}
+
+/// Ajoute une couche décrite dans la configuration des couches (`layer_sets.json`) à un projet,
+/// avec la couleur et le code de sa classe.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project` - projet ouvert en écriture
+/// * `layer_gpkg` - chemin du fichier GeoPackage de la couche
+/// * `layer` - description de la couche
+/// * `warnings` - avertissements de la création, complétés si la couche est vide
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_configured_layer(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    layer_gpkg: &str,
+    layer: &LayerDefinition,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let class_code = layer.class_code();
+    burn_feature_layer(
+        work_dir,
+        project,
+        layer_gpkg,
+        layer.color(),
+        &|_| class_code,
+        layer.geometry,
+        warnings,
+    )
+}
+
+/// Côté (en mètres) des cases de la grille de densité du bâti.
+pub const BUILT_DENSITY_CELL_M: f64 = 1000.0;
+
+/// Densité du bâti d'un projet, enregistrée dans ses métadonnées : part des pixels couverts
+/// par un bâtiment dans chaque case d'une grille de `cell_m` mètres.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuiltDensity {
+    /// Côté des cases, en mètres.
+    pub cell_m: f64,
+    /// Part du bâti (de 0 à 1) de chaque case, par ligne du nord au sud puis par colonne
+    /// d'ouest en est.
+    pub cells: Vec<Vec<f32>>,
+}
+
+/// Agrège un masque de pixels par blocs de `block` × `block` pixels. Les cases du bord est
+/// et du bord sud, incomplètes, sont rapportées à leur nombre réel de pixels.
+///
+/// # Arguments
+///
+/// * `mask` - masque de l'image, ligne par ligne
+/// * `width` - largeur de l'image en pixels
+/// * `height` - hauteur de l'image en pixels
+/// * `block` - côté des cases en pixels
+///
+/// # Returns
+///
+/// * `Vec<Vec<f32>>` - la part des pixels du masque dans chaque case, par ligne puis par colonne
+pub fn built_density(mask: &[bool], width: usize, height: usize, block: usize) -> Vec<Vec<f32>> {
+    let block = block.max(1);
+    let mut counts = vec![vec![0u32; width.div_ceil(block)]; height.div_ceil(block)];
+    for (index, _) in mask.iter().enumerate().filter(|(_, masked)| **masked) {
+        counts[index / width / block][index % width / block] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let cell_height = block.min(height - row * block);
+            line.into_iter()
+                .enumerate()
+                .map(|(column, count)| {
+                    let cell_width = block.min(width - column * block);
+                    count as f32 / (cell_width * cell_height) as f32
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Ajoute la couche des bâtiments (`BUILDING_LAYER`) à un projet, en deux passes selon leur
+/// hauteur (voir `BuildingHeights`) : les bâtiments bas ou de hauteur inconnue, puis les
+/// bâtiments hauts, chacun avec sa couleur et son code de classe. La densité du bâti est
+/// ensuite calculée sur le masque des bâtiments rastérisés.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project_file_path` - chemin du fichier projet
+/// * `building_gpkg` - chemin du fichier GeoPackage contenant les bâtiments
+/// * `heights` - seuil de hauteur et couleurs des bâtiments
+/// * `warnings` - avertissements de la création, complétés si la couche est vide
+///   ou sans hauteur
+///
+/// # Returns
+///
+/// * `Result<Option<BuiltDensity>, Box<dyn std::error::Error>>` - la densité du bâti,
+///   `None` si la couche est vide
+pub fn add_building_layer(
+    work_dir: &WorkDir,
+    project_file_path: &str,
+    building_gpkg: &str,
+    heights: &BuildingHeights,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<BuiltDensity>, Box<dyn std::error::Error>> {
+    let project = ProjectRaster::open(project_file_path)?;
+    let density = add_building_layer_to(work_dir, &project, building_gpkg, heights, warnings)?;
+    project.close()?;
+    Ok(density)
+}
+
+/// Variante de `add_building_layer` écrivant dans un projet déjà ouvert.
+pub fn add_building_layer_to(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    building_gpkg: &str,
+    heights: &BuildingHeights,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<BuiltDensity>, Box<dyn std::error::Error>> {
+    heights.validate()?;
+    let building_dataset = open_vector(
+        building_gpkg,
+        &format!("couche des bâtiments du projet {}", project.name()),
+    )?;
+    let building_layers = layer_names(&building_dataset);
+    let context = format!(
+        "Couche {}",
+        building_layers
+            .first()
+            .map_or(BUILDING_LAYER, String::as_str)
+    );
+
+    if skip_empty_layer(&building_dataset, &context, warnings) {
+        return Ok(None);
+    }
+
+    let has_height = building_dataset.layers().all(|layer| {
+        layer.defn().fields().any(|field| {
+            field
+                .name()
+                .eq_ignore_ascii_case(colormap::HEIGHT_ATTRIBUTE)
+        })
+    });
+    // Les bâtiments hauts sont appliqués en dernier : un pixel touché par les deux passes est haut.
+    let mut passes = vec![(
+        heights.low_color,
+        colormap::CLASS_BUILDING,
+        has_height.then(|| heights.low_where()),
+    )];
+    if has_height {
+        passes.push((
+            heights.high_color,
+            colormap::CLASS_BUILDING_HIGH,
+            Some(heights.high_where()),
+        ));
+    } else {
+        warnings.push(Warning::new(
+            context,
+            format!(
+                "attribut {} absent, tous les bâtiments sont considérés comme bas",
+                colormap::HEIGHT_ATTRIBUTE
             ),
+        ));
+    }
+
+    let (width, height) = project.size();
+    let window = RasterWindow::full(project.size());
+    let mut rgb = project.read_rgb_stripe(window)?;
+    let mut codes = vec![colormap::CLASS_UNKNOWN; width * height];
+    for (color, class_code, where_clause) in passes {
+        let temp_building_layer = work_dir.path_str(&format!("temp_building_{}.tif", class_code));
+        rasterize_layer(
+            project.dataset(),
+            building_gpkg,
+            &building_layers,
+            &temp_building_layer,
+            [255, 255, 255],
+            where_clause.as_deref(),
+            None,
+        )?;
+        let burnt = read_band(
+            &open_raster(&temp_building_layer, "raster des bâtiments")?,
+            1,
         )?;
+        std::fs::remove_file(&temp_building_layer)?;
+
+        for (index, _) in burnt.iter().enumerate().filter(|(_, value)| **value > 0) {
+            for (band, &value) in rgb.iter_mut().zip(color.iter()) {
+                band[index] = value;
+            }
+            codes[index] = class_code;
+        }
+    }
+    project.write_rgb_stripe(window, rgb)?;
+    burn_classes(project.path(), &codes)?;
+
+    // La densité est agrégée sur le masque déjà rastérisé, sans nouvel appel à GDAL.
+    let pixel_size = project.dataset().geo_transform()?[1];
+    let block = (BUILT_DENSITY_CELL_M / pixel_size).round() as usize;
+    let mask: Vec<bool> = codes
+        .iter()
+        .map(|&code| code != colormap::CLASS_UNKNOWN)
+        .collect();
+    Ok(Some(BuiltDensity {
+        cell_m: BUILT_DENSITY_CELL_M,
+        cells: built_density(&mask, width, height, block),
+    }))
+}
+
+/// Signale une couche sans entité dans l'emprise du projet : son ajout est ignoré, sans
+/// lancer de rastérisation.
+///
+/// # Arguments
+///
+/// * `dataset` - GeoPackage de la couche, dont toutes les couches sont comptées
+/// * `context` - Libellé de la couche dans l'avertissement (`Couche RPG`)
+/// * `warnings` - Avertissements de la création, complétés si la couche est vide
+///
+/// # Returns
+///
+/// * `bool` - `true` si la couche est vide et doit être ignorée
+fn skip_empty_layer(dataset: &Dataset, context: &str, warnings: &mut Vec<Warning>) -> bool {
+    let features: u64 = dataset.layers().map(|layer| layer.feature_count()).sum();
+    if features > 0 {
+        return false;
+    }
+    tracing::info!(
+        layer = context,
+        "Aucune entité dans l'emprise du projet, couche ignorée"
+    );
+    warnings.push(Warning::new(
+        context,
+        "aucune entité dans l'emprise du projet, couche ignorée",
+    ));
+    true
+}
+
+/// Rastérise une couche BDTOPO (lignes ou surfaces) et l'applique sur le projet avec la couleur donnée.
+/// Les lignes sont rastérisées avec l'option `-at` afin de conserver leur continuité ;
+/// sans indication (`GeometryHint::Auto`), le type est déduit de la première entité.
+/// Toutes les couches du GeoPackage sont rastérisées ensemble.
+/// Le code de classe, déduit du nom de la première couche, est écrit dans le raster de classes.
+/// Une couche sans entité dans l'emprise est ignorée et signalée dans `warnings`.
+fn burn_feature_layer(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    topo_gpkg: &str,
+    color: Rgb,
+    class_of_layer: &dyn Fn(&str) -> u8,
+    geometry: GeometryHint,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project_dataset = project.dataset();
+    let topo_dataset = open_vector(
+        topo_gpkg,
+        &format!("couche BDTOPO du projet {}", project.name()),
+    )?;
+    let topo_layers = layer_names(&topo_dataset);
+    let layer_name = topo_layers.first().cloned().unwrap_or_default();
+    if skip_empty_layer(&topo_dataset, &format!("Couche {}", layer_name), warnings) {
+        return Ok(());
+    }
+
+    // Première entité de la première couche non vide du fichier.
+    let first_geometry_type = topo_dataset
+        .layers()
+        .find_map(|mut layer| {
+            layer
+                .features()
+                .next()
+                .map(|feature| feature.geometry().map(|geometry| geometry.geometry_type()))
+        })
+        .flatten();
+
+    let is_line = match geometry {
+        GeometryHint::Line => true,
+        GeometryHint::Polygon => false,
+        GeometryHint::Auto => {
+            let geom_type = first_geometry_type.ok_or("Feature has no geometry")?;
+            geom_type == OGRwkbGeometryType::wkbLineString
+                || geom_type == OGRwkbGeometryType::wkbMultiLineString
+        }
+    };
+
+    let temp_topo_layer = work_dir.path_str("temp_topo_layer.tif");
+    let (width, height) = project_dataset.raster_size();
+
+    let driver_manager = DriverManager::get_driver_by_name("GTiff")?;
+    let mut dummy_raster = driver_manager.create(&temp_topo_layer, width, height, 3)?;
+
+    dummy_raster.set_geo_transform(&project_dataset.geo_transform()?)?;
+    dummy_raster.set_projection(&project_dataset.projection())?;
+
+    for i in 1..=3 {
+        write_band(&dummy_raster, i, vec![255u8; width * height])?;
+    }
+
+    dummy_raster.close().unwrap();
+
+    let mut args = vec!["-burn", "0", "-burn", "0", "-burn", "0"];
+    for topo_layer in &topo_layers {
+        args.push("-l");
+        args.push(topo_layer);
+    }
+    if is_line {
+        args.push("-at");
     }
+    args.push(topo_gpkg);
+    args.push(&temp_topo_layer);
 
-    output_dataset.close().unwrap();
+    let status = Command::new("gdal_rasterize").args(args).status()?;
+
+    if !status.success() {
+        return Err("gdal_rasterize failed".into());
+    }
+
+    let topo_raster = open_raster(&temp_topo_layer, &format!("raster de {}", layer_name))?;
+    let mut mask = vec![false; width * height];
+    for band_index in 1..=3 {
+        for (i, &value) in read_band(&topo_raster, band_index)?.iter().enumerate() {
+            if value != 255 {
+                mask[i] = true;
+            }
+        }
+    }
     topo_raster.close().unwrap();
-    project.close().unwrap();
 
-    std::fs::rename(output_file, project_file_path)?;
-    std::fs::remove_file(temp_topo_layer)?;
+    // Les bandes RGB du projet sont écrites en place ; la bande alpha n'est pas modifiée.
+    let window = RasterWindow::full(project.size());
+    let mut rgb = project.read_rgb_stripe(window)?;
+    for (band, &value) in rgb.iter_mut().zip(color.iter()) {
+        for (base_value, &masked) in band.iter_mut().zip(mask.iter()) {
+            if masked {
+                *base_value = value;
+            }
+        }
+    }
+    project.write_rgb_stripe(window, rgb)?;
+
+    std::fs::remove_file(&temp_topo_layer)?;
+
+    let class_code = class_of_layer(&layer_name);
+    let codes: Vec<u8> = mask
+        .iter()
+        .map(|&masked| {
+            if masked {
+                class_code
+            } else {
+                colormap::CLASS_UNKNOWN
+            }
+        })
+        .collect();
+    burn_classes(project.path(), &codes)?;
+
+    Ok(())
+}
 
+/// Applique les couches personnalisées sur le projet ouvert, dans leur ordre.
+fn add_custom_layers(
+    progress: &dyn ProgressSink,
+    work_dir: &WorkDir,
+    project: &mut ProjectRaster,
+    project_folder: &Path,
+    custom_layers: &[CustomLayer],
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (index, layer) in custom_layers.iter().enumerate() {
+        progress.report_update(ProgressUpdate::step(
+            "stage.add_layers",
+            "step.add_layer",
+            &[&layer.name],
+            index + 1,
+            custom_layers.len(),
+        ));
+        add_custom_layer_to(work_dir, project, project_folder, layer, warnings)?;
+        project.flush()?;
+    }
     Ok(())
 }
 
 /// Ajoute les couches au projet.
 /// Cette fonction est responsable de l'ajout des couches régionales, de végétation, de RPG et topographiques
 /// au projet en utilisant les chemins fournis.
+/// Les couches personnalisées des métadonnées du projet sont intercalées selon leur priorité :
+/// chacune est appliquée après les groupes de priorité inférieure ou égale.
+/// Un raster de classes mono-bande (`<nom>_CLASSES.tif`) et sa légende JSON (`<nom>_CLASSES.json`)
+/// sont produits en parallèle du raster RGB.
 /// Elle émet également des événements de mise à jour de progression pour informer l'utilisateur
 /// de l'état d'avancement de l'ajout des couches.
 ///
 /// # Arguments
 ///
-/// * `app_handle` - Handle de l'application Tauri
+/// * `progress` - destination des messages de progression
+/// * `work_dir` - dossier de travail de l'opération
 /// * `project_folder` - chemin du dossier du projet
 /// * `project_file_path` - chemin du fichier projet
 /// * `project_name` - nom du projet
+/// * `vegetation_source` - source des données de végétation (BD Forêt ou OSO)
+/// * `layer_config` - couches BDTOPO à appliquer (seules les couches activées sont traitées)
+///   et sélection de la création, qui peut écarter la végétation et le RPG
+/// * `warnings` - avertissements de la création
+/// * `run_config` - paramètres figés de l'opération, dont la résolution du projet
 ///
 /// # Returns
 ///
 /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+#[allow(clippy::too_many_arguments)]
 pub fn add_layers(
-    app_handle: &tauri::AppHandle,
+    progress: &dyn ProgressSink,
+    work_dir: &WorkDir,
     project_folder: &str,
     project_file_path: &str,
     project_name: &str,
+    vegetation_source: &VegetationSource,
+    layer_config: &LayerConfig,
+    warnings: &mut Vec<Warning>,
+    run_config: &RunConfig,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let _ = app_handle.emit(
-        "progress-update",
-        "Ajout des Couches|Ajout de la couche régionale|1/4",
-    );
+    // L'ordre des clés fixe la priorité : une couche appliquée plus tard recouvre les précédentes.
+    // L'hydrographie recouvre la végétation et le RPG, mais pas les bâtiments ni les routes.
+    // Dans un groupe, `enabled_layers` donne les couches par priorité croissante
+    // (`LayerDefinition::priority`) : les routes recouvrent les surfaces qu'elles traversent.
+    let configured_names = |group| {
+        layer_config
+            .enabled_layers(group)
+            .into_iter()
+            .map(|layer| layer.name.as_str())
+            .collect::<Vec<&str>>()
+    };
+    // La légende (`legend::build_legend`) reprend les mêmes priorités.
+    // Les couches hors de la sélection de la création ne sont pas appliquées.
+    let mut layers: BTreeMap<u8, Vec<&str>> = BTreeMap::new();
+    if layer_config.includes(VEGETATION_LAYER) {
+        layers.insert(PRIORITY_VEGETATION, vec![VEGETATION_LAYER]);
+    }
+    if layer_config.includes(RPG_LAYER) {
+        layers.insert(PRIORITY_RPG, vec![RPG_LAYER]);
+    }
+    layers.insert(PRIORITY_HYDRO, configured_names(LayerGroup::Hydro));
+    layers.insert(PRIORITY_TOPO, configured_names(LayerGroup::Topo));
+    let total_layer_types = layers.len() + 1;
 
-    if let Err(e) = add_regional_layer(
-        project_file_path,
-        &format!("{}/resources/{}.gpkg", project_folder, project_name),
+    progress.report_update(ProgressUpdate::step(
+        "stage.add_layers",
+        "step.add_regional",
+        &[],
+        1,
+        total_layer_types,
+    ));
+
+    let metadata = load_project_metadata(project_name);
+    let land_color = metadata.project_land_color();
+    create_class_raster(project_file_path)?;
+    colormap::write_class_legend(
+        &Path::new(project_folder).join(format!("{}_CLASSES.json", project_name)),
+        land_color,
+    )?;
+
+    // Le projet est ouvert une seule fois : chaque couche écrit en place dans ses bandes.
+    let mut project = ProjectRaster::open_at(project_file_path, run_config.resolution)?;
+
+    if let Err(e) = add_regional_layer_to(
+        work_dir,
+        &project,
+        &resource_gpkg(Path::new(project_folder), project_name).to_string_lossy(),
+        land_color,
     ) {
-        println!("Failed to add regional layer: {:?}", e);
+        tracing::error!(
+            project = project_name,
+            error = %e,
+            "Échec de l'ajout de la couche départementale"
+        );
         return Err(e);
     }
+    project.flush()?;
 
-    let mut layers: BTreeMap<i8, Vec<&str>> = BTreeMap::new();
-    layers.insert(1, vec!["FORMATION_VEGETALE"]);
-    layers.insert(2, vec!["PARCELLES_GRAPHIQUES"]);
-    layers.insert(
-        3,
-        vec![
-            "AERODROME",
-            "CONSTRUCTION_SURFACIQUE",
-            "EQUIPEMENT_DE_TRANSPORT",
-            "RESERVOIR",
-            "TERRAIN_DE_SPORT",
-            "TRONCON_DE_VOIE_FERREE",
-            "ZONE_D_ESTRAN",
-            "BATIMENT",
-            "COURS_D_EAU",
-            "PLAN_D_EAU",
-            "SURFACE_HYDROGRAPHIQUE",
-            "TRONCON_DE_ROUTE",
-            "VOIE_NOMMEE",
-        ],
-    );
+    let mut custom_layers = metadata.custom_layers;
+    custom_layers.sort_by_key(|layer| layer.priority);
+    let mut next_custom = 0;
 
     let mut layer_index = 2;
-    let total_layer_types = layers.len() + 1;
+    let mut built_density = None;
 
     for (key, value) in layers {
+        let end = custom_layers[next_custom..]
+            .iter()
+            .position(|layer| layer.priority >= key)
+            .map_or(custom_layers.len(), |position| next_custom + position);
+        add_custom_layers(
+            progress,
+            work_dir,
+            &mut project,
+            Path::new(project_folder),
+            &custom_layers[next_custom..end],
+            warnings,
+        )?;
+        next_custom = end;
+
         let layer_type = match key {
-            1 => "Végétation",
-            2 => "Parcelles agricoles",
-            3 => "Topographie",
+            PRIORITY_VEGETATION => "Végétation",
+            PRIORITY_RPG => "Parcelles agricoles",
+            PRIORITY_HYDRO => "Hydrographie",
+            PRIORITY_TOPO => "Topographie",
             _ => "Inconnu",
         };
 
-        let _ = app_handle.emit(
-            "progress-update",
-            format!(
-                "Ajout des Couches|Ajout des couches {}|{}/{}",
-                layer_type, layer_index, total_layer_types
-            ),
-        );
+        progress.report_update(ProgressUpdate::step(
+            "stage.add_layers",
+            "step.add_group",
+            &[&layer_type],
+            layer_index,
+            total_layer_types,
+        ));
 
         let total_files = value.len();
         for (file_index, file) in value.iter().enumerate() {
-            let _ = app_handle.emit(
-                "progress-update",
-                format!(
-                    "Ajout des Couches|Ajout de {}|{}/{}",
-                    file,
-                    file_index + 1,
-                    total_files
-                ),
-            );
-
-            let layer_path = format!("{}/resources/{}.gpkg", project_folder, file);
+            progress.report_update(ProgressUpdate::step(
+                "stage.add_layers",
+                "step.add_layer",
+                &[&file],
+                file_index + 1,
+                total_files,
+            ));
+
+            let layer_path = resource_gpkg(Path::new(project_folder), file)
+                .to_string_lossy()
+                .to_string();
             match key {
-                1 => add_vegetation_layer(project_file_path, &layer_path),
-                2 => add_rpg_layer(project_file_path, &layer_path),
-                3 => add_topo_layer(project_file_path, &layer_path),
+                PRIORITY_VEGETATION => match vegetation_source {
+                    VegetationSource::BdForet => {
+                        add_vegetation_layer_to(work_dir, &project, &layer_path, warnings)
+                    }
+                    source => add_oso_layer(work_dir, &project, source),
+                },
+                PRIORITY_RPG => add_rpg_layer_to(work_dir, &project, &layer_path, warnings),
+                PRIORITY_HYDRO | PRIORITY_TOPO => {
+                    let Some(layer) = layer_config.layers.iter().find(|layer| layer.name == *file)
+                    else {
+                        continue;
+                    };
+                    if !Path::new(&layer_path).exists() {
+                        warnings.push(Warning::new(
+                            format!("Couche {}", file),
+                            "aucune donnée préparée pour ce projet, couche ignorée",
+                        ));
+                        continue;
+                    }
+                    if layer.name == BUILDING_LAYER {
+                        add_building_layer_to(
+                            work_dir,
+                            &project,
+                            &layer_path,
+                            &run_config.building_heights,
+                            warnings,
+                        )
+                        .map(|density| built_density = density)
+                    } else {
+                        add_configured_layer(work_dir, &project, &layer_path, layer, warnings)
+                    }
+                }
                 _ => {
-                    println!("Unknown layer type");
+                    tracing::error!(layer = layer_type, "Type de couche inconnu");
                     return Err(Box::new(std::io::Error::other("Unknown layer type")));
                 }
-            }?
+            }?;
+            // Les couches déjà appliquées sont écrites sur le disque avant la suivante.
+            project.flush()?;
         }
 
         layer_index += 1;
     }
 
-    Ok(())
+    add_custom_layers(
+        progress,
+        work_dir,
+        &mut project,
+        Path::new(project_folder),
+        &custom_layers[next_custom..],
+        warnings,
+    )?;
+
+    project.close()?;
+
+    let mut metadata = load_project_metadata(project_name);
+    metadata.built_density = built_density;
+    save_project_metadata(project_name, &metadata)
 }
 
-/// Télécharge une image satellite JPEG pour une étendue donnée avec une résolution de 10m/pixel
-/// Cette fonction utilise le service WMS de geoportail pour télécharger une image satellite
-/// et utilise ImageMagick pour traiter l'image.
+/// Côté (en pixels) des tuiles de l'orthophoto demandées séparément lorsque l'emprise
+/// comprend de la mer (voir `assemble_land_ortho`).
+pub const ORTHO_TILE_PIXELS: usize = 2500;
+
+/// Télécharge une image satellite JPEG pour une étendue donnée à la résolution des paramètres
+/// Cette fonction essaie les sources WMS des paramètres (`ortho_sources`) par ordre de priorité
+/// pour télécharger une image satellite, puis la convertit en JPEG avec `ortho_tiff_to_jpeg`.
+/// Avec le GeoPackage régional du projet et `clip_ortho_to_land` activé, les tuiles entièrement
+/// hors des départements (mer) ne sont pas demandées mais remplies de `ortho_sea_color`.
 ///
 /// # Arguments
 ///
+/// * `work_dir` - dossier de travail de l'opération (configuration WMS, cache et image intermédiaire)
 /// * `output_jpg_path` - chemin de sortie pour l'image JPEG
 /// * `project_bb` - BoundingBox de l'étendue du projet
+/// * `epsg` - code EPSG du système de coordonnées du projet, dans lequel l'image est demandée
+/// * `regional_gpkg` - GeoPackage régional fusionné du projet, qui délimite la terre ferme
+/// * `progress` - destination des messages de progression, qui reçoit les nouvelles tentatives
+/// * `run_config` - paramètres de l'opération : résolution, sources WMS, découpe sur la terre
+///   ferme et qualité du JPEG
 ///
 /// # Returns
 ///
 /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si le téléchargement a réussi ou échoué
 pub fn download_satellite_jpeg(
+    work_dir: &WorkDir,
     output_jpg_path: &str,
     project_bb: &BoundingBox,
+    epsg: u32,
+    regional_gpkg: Option<&str>,
+    progress: &dyn ProgressSink,
+    run_config: &RunConfig,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let temp_dir = temp_dir().to_string_lossy().to_string();
-    create_directory_if_not_exists(&temp_dir)?;
+    let resolution = run_config.resolution;
+    let (width, height) = project_bb.raster_size(resolution)?;
+
+    tracing::debug!(width, height, "Dimensions de l'orthophoto calculées");
+
+    // Le masque n'est qu'une optimisation : en cas d'échec, toute l'emprise est téléchargée.
+    let mask = match regional_gpkg.filter(|_| run_config.clip_ortho_to_land) {
+        Some(gpkg) => land_mask(work_dir, gpkg, project_bb, resolution)
+            .inspect_err(|e| {
+                tracing::warn!(
+                    file = gpkg,
+                    error = %e,
+                    "Masque de la terre ferme indisponible, orthophoto complète"
+                )
+            })
+            .ok(),
+        None => None,
+    };
 
-    let wms_cache_dir = format!("{}/wms_cache", temp_dir);
-    create_directory_if_not_exists(&wms_cache_dir)?;
+    let temp_satellite = work_dir.path_for("satellite_temp.tif");
+    match mask.filter(|mask| mask.contains(&0)) {
+        Some(mask) => {
+            let skipped = assemble_land_ortho(
+                work_dir,
+                project_bb,
+                resolution,
+                &mask,
+                ORTHO_TILE_PIXELS,
+                run_config.ortho_sea_color,
+                &temp_satellite,
+                &mut |tile_bb, size, output| {
+                    fetch_wms_tiff(work_dir, tile_bb, size, epsg, output, progress, run_config)
+                },
+            )?;
+            tracing::info!(skipped, "Tuiles de l'orthophoto en mer non téléchargées");
+        }
+        None => fetch_wms_tiff(
+            work_dir,
+            project_bb,
+            (width, height),
+            epsg,
+            &temp_satellite,
+            progress,
+            run_config,
+        )?,
+    }
 
-    let resolution = resolution();
-    let width = ((project_bb.xmax - project_bb.xmin) / resolution).ceil() as usize;
-    let height = ((project_bb.ymax - project_bb.ymin) / resolution).ceil() as usize;
+    ortho_tiff_to_jpeg(&temp_satellite, output_jpg_path, project_bb, run_config)?;
 
-    println!(
-        "Dimensions calculées : largeur={}, hauteur={} pixels",
-        width, height
-    );
+    std::fs::remove_file(temp_satellite)?;
 
-    let temp_satellite = format!("{}/satellite_temp.tif", temp_dir);
-    let wms_file = format!("{}/wms_config.xml", temp_dir);
-    let wms_xml = format!(
-        r#"<GDAL_WMS>
-      <Service name="WMS">
-        <Version>1.3.0</Version>
-        <ServerUrl>https://data.geopf.fr/wms-r/wms</ServerUrl>
-        <CRS>EPSG:2154</CRS>
-        <ImageFormat>image/jpeg</ImageFormat>
-        <Layers>ORTHOIMAGERY.ORTHOPHOTOS</Layers>
-        <Styles></Styles>
-      </Service>
-      <DataWindow>
-        <UpperLeftX>{}</UpperLeftX>
-        <UpperLeftY>{}</UpperLeftY>
-        <LowerRightX>{}</LowerRightX>
-        <LowerRightY>{}</LowerRightY>
-        <SizeX>{}</SizeX>
-        <SizeY>{}</SizeY>
-      </DataWindow>
-      <BandsCount>3</BandsCount>
-      <BlockSizeX>2048</BlockSizeX>
-      <BlockSizeY>2048</BlockSizeY>
-      <OverviewCount>0</OverviewCount>
-      <ZeroBlockHttpCodes>204,400,404,502,503,504</ZeroBlockHttpCodes>
-      <MaxConnections>10</MaxConnections>
-      <Timeout>120</Timeout>
-      <Cache>
-        <Type>Disk</Type>
-        <Path>{}/wms_cache</Path>
-        <MaxSize>500000000</MaxSize>
-      </Cache>
-      <UserAgent>GDAL WMS driver (https://gdal.org/drivers/raster/wms.html)</UserAgent>
-      <UnsafeSSL>true</UnsafeSSL>
-      <Retry>
-        <Count>5</Count>
-        <Delay>1</Delay>
-      </Retry>
-    </GDAL_WMS>"#,
-        project_bb.xmin, project_bb.ymax, project_bb.xmax, project_bb.ymin, width, height, temp_dir
-    );
+    Ok(())
+}
 
-    std::fs::write(wms_file.clone(), wms_xml)?;
+/// Télécharge l'image WMS d'une emprise dans un GeoTIFF, en essayant les sources des
+/// paramètres par ordre de priorité, chacune jusqu'à trois fois.
+fn fetch_wms_tiff(
+    work_dir: &WorkDir,
+    project_bb: &BoundingBox,
+    size: (usize, usize),
+    epsg: u32,
+    output_tif: &Path,
+    progress: &dyn ProgressSink,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let wms_cache_dir = work_dir.path_for(WMS_CACHE_DIR);
+    fs::create_dir_all(&wms_cache_dir)?;
 
+    let wms_file = work_dir.path_for("wms_config.xml");
+    let sources = &run_config.ortho_sources;
+    let mut failures = Vec::new();
     let mut success = false;
-    let mut attempts = 0;
-    let max_attempts = 3;
-
-    while !success && attempts < max_attempts {
-        attempts += 1;
-        println!("Tentative de téléchargement {}/{}", attempts, max_attempts);
-
-        let status = Command::new("gdal_translate")
-            .args([
-                "-of",
-                "GTiff",
-                "-co",
-                "COMPRESS=JPEG",
-                "-co",
-                "JPEG_QUALITY=95",
-                "-co",
-                "PHOTOMETRIC=RGB",
-                "-co",
-                "BIGTIFF=YES",
-                &wms_file,
-                &temp_satellite,
-            ])
-            .status()?;
-
-        if status.success() {
-            success = true;
-        } else if attempts < max_attempts {
-            println!("Échec, nouvelle tentative dans 5 secondes...");
-            std::thread::sleep(std::time::Duration::from_secs(5));
+
+    for source in sources_by_priority(sources) {
+        let source = &source.in_project_crs(epsg);
+        std::fs::write(
+            &wms_file,
+            wms_xml(source, project_bb, size, &wms_cache_dir.to_string_lossy()),
+        )?;
+
+        let max_attempts = 3;
+        for attempt in 1..=max_attempts {
+            tracing::info!(
+                source = %source.name,
+                attempt,
+                max_attempts,
+                "Téléchargement de l'orthophoto"
+            );
+
+            let status = Command::new("gdal_translate")
+                .envs(gdal_proxy_env(proxy_config().as_ref()))
+                .envs(gdal_http_env(source))
+                .args([
+                    "-of",
+                    "GTiff",
+                    "-co",
+                    "COMPRESS=JPEG",
+                    "-co",
+                    "PHOTOMETRIC=RGB",
+                    "-co",
+                    "BIGTIFF=YES",
+                    "-co",
+                ])
+                .arg(format!("JPEG_QUALITY={}", run_config.ortho_jpeg_quality()))
+                .arg(&wms_file)
+                .arg(output_tif)
+                .status()?;
+
+            if status.success() {
+                success = true;
+                break;
+            } else if attempt < max_attempts {
+                tracing::warn!(
+                    source = %source.name,
+                    attempt,
+                    "Échec, nouvelle tentative dans 5 secondes"
+                );
+                progress.report_update(ProgressUpdate::step(
+                    "stage.finalize",
+                    "step.ortho_retry",
+                    &[&source.name, &(attempt + 1).to_string()],
+                    attempt + 1,
+                    max_attempts,
+                ));
+                std::thread::sleep(std::time::Duration::from_secs(5));
+            }
+        }
+
+        if success {
+            break;
         }
+        tracing::warn!(source = %source.name, "Source d'orthophotos indisponible");
+        failures.push(source.name.clone());
     }
 
     if !success {
-        return Err(
-            "Échec du téléchargement de l'image satellite après plusieurs tentatives".into(),
-        );
+        return Err(format!(
+            "Échec du téléchargement de l'image satellite après plusieurs tentatives (sources: {})",
+            failures.join(", ")
+        )
+        .into());
     }
 
-    let metadata = fs::metadata(&temp_satellite)?;
+    let metadata = fs::metadata(output_tif)?;
     if metadata.len() == 0 {
         return Err("Le fichier téléchargé est vide".into());
     }
+    std::fs::remove_file(wms_file)?;
 
-    let temp_jpg = format!("{}/satellite_temp.jpg", temp_dir);
+    Ok(())
+}
 
-    let magick_status = Command::new("magick")
-        .args([
-            &temp_satellite,
-            "-resize",
-            &format!("{}x{}", width, height),
-            "-colorspace",
-            "sRGB",
-            "-type",
-            "TrueColor",
-            &temp_jpg,
-        ])
-        .status()?;
-
-    if !magick_status.success() {
-        return Err("Échec de la conversion en JPEG avec ImageMagick".into());
-    }
-
-    if Path::new(&temp_jpg).exists() {
-        std::fs::rename(temp_jpg, output_jpg_path)?;
+/// Masque de la terre ferme sur la grille d'une emprise : 255 à l'intérieur des départements
+/// du GeoPackage régional, 0 ailleurs (mer, territoire étranger).
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `regional_gpkg` - GeoPackage régional fusionné du projet
+/// * `project_bb` - BoundingBox de l'étendue du projet
+/// * `resolution` - résolution de la grille en mètres par pixel
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Box<dyn std::error::Error>>` - le masque, ligne par ligne
+pub fn land_mask(
+    work_dir: &WorkDir,
+    regional_gpkg: &str,
+    project_bb: &BoundingBox,
+    resolution: f64,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let size = project_bb.raster_size(resolution)?;
+    let geo_transform = [
+        project_bb.xmin,
+        resolution,
+        0.0,
+        project_bb.ymax,
+        0.0,
+        -resolution,
+    ];
+    let regional_layers = layer_names(&open_vector(
+        regional_gpkg,
+        "couche régionale de l'orthophoto",
+    )?);
+    let temp_mask = work_dir.path_str("ortho_land_mask.tif");
+    rasterize_layer_on_grid(
+        &geo_transform,
+        size,
+        regional_gpkg,
+        &regional_layers,
+        &temp_mask,
+        [255, 255, 255],
+        None,
+        None,
+    )?;
+    let mask = read_band(&open_raster(&temp_mask, "masque de la terre ferme")?, 1)?;
+    fs::remove_file(&temp_mask)?;
+    Ok(mask)
+}
+
+/// Découpe une image en tuiles de `tile` pixels de côté (plus petites au bord droit et en bas)
+/// et les répartit entre celles qui touchent la terre ferme du masque et celles en mer.
+///
+/// # Arguments
+///
+/// * `mask` - masque de la terre ferme (voir `land_mask`), non nul sur la terre
+/// * `size` - dimensions de l'image en pixels
+/// * `tile` - côté des tuiles en pixels
+///
+/// # Returns
+///
+/// * `(Vec<RasterWindow>, Vec<RasterWindow>)` - les tuiles touchant la terre, puis celles en mer
+pub fn split_land_tiles(
+    mask: &[u8],
+    (width, height): (usize, usize),
+    tile: usize,
+) -> (Vec<RasterWindow>, Vec<RasterWindow>) {
+    let mut land = Vec::new();
+    let mut sea = Vec::new();
+    for y in (0..height).step_by(tile) {
+        for x in (0..width).step_by(tile) {
+            let window = RasterWindow {
+                x,
+                y,
+                width: tile.min(width - x),
+                height: tile.min(height - y),
+            };
+            let touches_land = (window.y..window.y + window.height).any(|row| {
+                mask[row * width + window.x..row * width + window.x + window.width]
+                    .iter()
+                    .any(|&value| value != 0)
+            });
+            if touches_land {
+                land.push(window);
+            } else {
+                sea.push(window);
+            }
+        }
+    }
+    (land, sea)
+}
+
+/// Compose l'orthophoto d'une emprise côtière : seules les tuiles touchant la terre ferme
+/// sont téléchargées, les tuiles en mer sont remplies de `sea_color`.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project_bb` - BoundingBox de l'étendue du projet
+/// * `resolution` - résolution de l'image en mètres par pixel
+/// * `mask` - masque de la terre ferme (voir `land_mask`)
+/// * `tile` - côté des tuiles en pixels (`ORTHO_TILE_PIXELS`)
+/// * `sea_color` - couleur des tuiles en mer
+/// * `output_tif` - GeoTIFF RGB de sortie, aux dimensions de l'emprise
+/// * `fetch_tile` - télécharge l'emprise d'une tuile, à la taille donnée, dans un GeoTIFF
+///
+/// # Returns
+///
+/// * `Result<usize, Box<dyn std::error::Error>>` - le nombre de tuiles en mer non téléchargées
+#[allow(clippy::too_many_arguments)]
+pub fn assemble_land_ortho(
+    work_dir: &WorkDir,
+    project_bb: &BoundingBox,
+    resolution: f64,
+    mask: &[u8],
+    tile: usize,
+    sea_color: Rgb,
+    output_tif: &Path,
+    fetch_tile: &mut dyn FnMut(
+        &BoundingBox,
+        (usize, usize),
+        &Path,
+    ) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let size = project_bb.raster_size(resolution)?;
+    let (land, sea) = split_land_tiles(mask, size, tile);
+
+    let driver = DriverManager::get_driver_by_name("GTiff")?;
+    let mut ortho = driver.create_with_band_type::<u8, _>(output_tif, size.0, size.1, 3)?;
+    ortho.set_geo_transform(&[
+        project_bb.xmin,
+        resolution,
+        0.0,
+        project_bb.ymax,
+        0.0,
+        -resolution,
+    ])?;
+
+    for window in &sea {
+        let window_size = (window.width, window.height);
+        for (band_idx, value) in sea_color.iter().enumerate() {
+            ortho.rasterband(band_idx + 1)?.write(
+                (window.x as isize, window.y as isize),
+                window_size,
+                &mut gdal::raster::Buffer::new(
+                    window_size,
+                    vec![*value; window.width * window.height],
+                ),
+            )?;
+        }
+    }
+
+    let tile_tif = work_dir.path_for("ortho_tile.tif");
+    for window in &land {
+        let window_size = (window.width, window.height);
+        let tile_bb = BoundingBox::new(
+            project_bb.xmin + window.x as f64 * resolution,
+            project_bb.ymax - (window.y + window.height) as f64 * resolution,
+            project_bb.xmin + (window.x + window.width) as f64 * resolution,
+            project_bb.ymax - window.y as f64 * resolution,
+        );
+        fetch_tile(&tile_bb, window_size, &tile_tif)?;
+
+        let tile_dataset = open_raster(&tile_tif, "tuile de l'orthophoto")?;
+        if tile_dataset.raster_size() != window_size {
+            return Err(format!(
+                "Tuile de l'orthophoto de {}x{} pixels au lieu des {}x{} pixels attendus",
+                tile_dataset.raster_size().0,
+                tile_dataset.raster_size().1,
+                window.width,
+                window.height
+            )
+            .into());
+        }
+        // Une tuile en niveaux de gris est répétée sur les trois canaux, comme dans
+        // `ortho_tiff_to_jpeg`.
+        let band_indices = if tile_dataset.raster_count() >= 3 {
+            [1, 2, 3]
+        } else {
+            [1, 1, 1]
+        };
+        for (band_idx, source_idx) in band_indices.into_iter().enumerate() {
+            let data = read_band_as_u8(&tile_dataset.rasterband(source_idx)?, window_size)?;
+            ortho.rasterband(band_idx + 1)?.write(
+                (window.x as isize, window.y as isize),
+                window_size,
+                &mut gdal::raster::Buffer::new(window_size, data),
+            )?;
+        }
+        tile_dataset.close()?;
+        fs::remove_file(&tile_tif)?;
+    }
+    ortho.close()?;
+
+    Ok(sea.len())
+}
+
+/// Convertit l'orthophoto téléchargée (GeoTIFF) en JPEG géoréférencé par un world file,
+/// sans outil externe : les bandes sont lues avec GDAL, ramenées sur 8 bits si besoin,
+/// puis l'image est encodée avec la crate `image`. L'image doit avoir exactement la taille
+/// du projet ; elle n'est jamais redimensionnée.
+///
+/// # Arguments
+///
+/// * `satellite_tiff` - GeoTIFF produit par le téléchargement WMS
+/// * `output_jpg_path` - chemin de sortie pour l'image JPEG
+/// * `project_bb` - BoundingBox de l'étendue du projet
+/// * `run_config` - paramètres de l'opération : résolution et qualité du JPEG
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - une erreur indiquant la taille obtenue et la taille attendue si elles diffèrent
+pub fn ortho_tiff_to_jpeg(
+    satellite_tiff: &Path,
+    output_jpg_path: &str,
+    project_bb: &BoundingBox,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let resolution = run_config.resolution;
+    let (width, height) = project_bb.raster_size(resolution)?;
+
+    let satellite = open_raster(satellite_tiff, "orthophoto téléchargée")?;
+    let (source_width, source_height) = satellite.raster_size();
+    if (source_width, source_height) != (width, height) {
+        return Err(format!(
+            "Orthophoto de {}x{} pixels au lieu des {}x{} pixels attendus",
+            source_width, source_height, width, height
+        )
+        .into());
+    }
+
+    // Une image en niveaux de gris (avec ou sans alpha) est répétée sur les trois canaux.
+    let band_indices = if satellite.raster_count() >= 3 {
+        [1, 2, 3]
     } else {
-        return Err("Le fichier JPEG temporaire n'a pas été créé".into());
+        [1, 1, 1]
+    };
+    let mut bands = Vec::with_capacity(3);
+    for band_idx in band_indices {
+        bands.push(read_band_as_u8(
+            &satellite.rasterband(band_idx)?,
+            (width, height),
+        )?);
     }
+    satellite.close()?;
 
-    std::fs::remove_file(temp_satellite)?;
-    std::fs::remove_file(wms_file)?;
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for i in 0..width * height {
+        rgb.extend_from_slice(&[bands[0][i], bands[1][i], bands[2][i]]);
+    }
+    let satellite_image = RgbImage::from_raw(width as u32, height as u32, rgb)
+        .ok_or("Données satellite incohérentes avec la taille de l'image")?;
+
+    let temp_jpg = format!("{}.tmp", output_jpg_path);
+    save_rgb_jpeg(&satellite_image, &temp_jpg, run_config.ortho_jpeg_quality())?;
+    fs::rename(&temp_jpg, output_jpg_path)?;
+
+    let geo_transform = [
+        project_bb.xmin,
+        resolution,
+        0.0,
+        project_bb.ymax,
+        0.0,
+        -resolution,
+    ];
+    write_world_file(output_jpg_path, &geo_transform)?;
 
     Ok(())
 }
+
+/// Lit une bande entière sur 8 bits. Une bande d'un autre type (16 bits, flottants)
+/// est ramenée linéairement de son intervalle de valeurs vers 0-255.
+fn read_band_as_u8(
+    band: &RasterBand,
+    size: (usize, usize),
+) -> Result<Vec<u8>, gdal::errors::GdalError> {
+    if band.band_type() == GdalDataType::UInt8 {
+        return Ok(band
+            .read_as::<u8>((0, 0), size, size, None)?
+            .data()
+            .to_vec());
+    }
+
+    let values = band
+        .read_as::<f64>((0, 0), size, size, None)?
+        .data()
+        .to_vec();
+    let (min, max) = values
+        .iter()
+        .filter(|value| value.is_finite())
+        .fold((f64::MAX, f64::MIN), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    let scale = if max > min { 255.0 / (max - min) } else { 0.0 };
+    Ok(values
+        .iter()
+        .map(|&value| {
+            if value.is_finite() {
+                ((value - min) * scale).round().clamp(0.0, 255.0) as u8
+            } else {
+                0
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use super::colormap::{CLASSES, ClassEntry, Rgb, RpgClassTable};
+use super::layer_config::{BUILDING_LAYER, LayerConfig, LayerGroup};
+
+use crate::i18n::{self, Language};
+
+/// Priorité du fond départemental, appliqué en premier par `add_layers`.
+pub const PRIORITY_REGIONAL: u8 = 0;
+/// Priorité de la végétation (BD Forêt ou OSO).
+pub const PRIORITY_VEGETATION: u8 = 1;
+/// Priorité des parcelles agricoles, qui recouvrent la végétation.
+pub const PRIORITY_RPG: u8 = 2;
+/// Priorité de l'hydrographie, qui recouvre la végétation et le RPG.
+pub const PRIORITY_HYDRO: u8 = 3;
+/// Priorité des couches topographiques, appliquées en dernier.
+pub const PRIORITY_TOPO: u8 = 4;
+
+/// Couche source du fond départemental.
+const REGIONAL_SOURCE: &str = "regions.geojson";
+/// Couche source de la végétation BD Forêt.
+const VEGETATION_SOURCE: &str = "FORMATION_VEGETALE";
+/// Couche source des parcelles agricoles.
+const RPG_SOURCE: &str = "PARCELLES_GRAPHIQUES";
+
+/// Séparateur des libellés et des couches des classes regroupées sous une même couleur.
+const MERGED_SEPARATOR: &str = " / ";
+
+/// Entrée de la légende de l'image de végétation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LegendEntry {
+    pub class_key: String,
+    pub label_fr: String,
+    pub label_en: String,
+    pub rgb: Rgb,
+    /// Couche(s) dont la classe est rastérisée.
+    pub source_layer: String,
+    /// Ordre d'application par `add_layers` : une priorité plus élevée recouvre les autres.
+    pub priority: u8,
+}
+
+impl LegendEntry {
+    fn from_class(entry: &ClassEntry, source_layer: String, priority: u8) -> Self {
+        LegendEntry {
+            class_key: entry.key.to_string(),
+            label_fr: entry.label.to_string(),
+            label_en: i18n::translate(&format!("class.{}", entry.key), Language::En, &[]),
+            rgb: entry.color,
+            source_layer,
+            priority,
+        }
+    }
+
+    /// Ajoute à l'entrée une classe de même couleur appliquée avant elle.
+    fn absorb(&mut self, covered: LegendEntry) {
+        self.label_fr = format!("{}{}{}", self.label_fr, MERGED_SEPARATOR, covered.label_fr);
+        self.label_en = format!("{}{}{}", self.label_en, MERGED_SEPARATOR, covered.label_en);
+        if !self
+            .source_layer
+            .split(MERGED_SEPARATOR)
+            .any(|source| source == covered.source_layer)
+        {
+            self.source_layer = format!(
+                "{}{}{}",
+                self.source_layer, MERGED_SEPARATOR, covered.source_layer
+            );
+        }
+    }
+}
+
+fn class_entry(key: &str) -> &'static ClassEntry {
+    CLASSES
+        .iter()
+        .find(|entry| entry.key == key)
+        .expect("Classe absente de colormap::CLASSES")
+}
+
+/// Construit la légende de l'image de végétation à partir des classes appliquées par
+/// `add_layers` : fond départemental, végétation, classes du RPG, puis les classes des couches
+/// hydrographiques et topographiques activées.
+///
+/// Les classes de même couleur ne se distinguent pas sur l'image : elles sont regroupées en
+/// une seule entrée, qui reprend la clé et la priorité de la classe appliquée en dernier et
+/// énumère les libellés de toutes les classes, de la plus prioritaire à la moins prioritaire.
+///
+/// # Arguments
+///
+/// * `layer_config` - la configuration des couches BDTOPO
+/// * `rpg_classes` - la table des classes de combustible du RPG
+/// * `land_color` - couleur du fond départemental du projet (voir `ProjectMetadata::land_color`)
+///
+/// # Returns
+///
+/// * `Vec<LegendEntry>` - une entrée par couleur, de la plus prioritaire à la moins prioritaire
+pub fn build_legend(
+    layer_config: &LayerConfig,
+    rpg_classes: &RpgClassTable,
+    land_color: Rgb,
+) -> Vec<LegendEntry> {
+    let mut applied = vec![LegendEntry {
+        rgb: land_color,
+        ..LegendEntry::from_class(
+            class_entry("land"),
+            REGIONAL_SOURCE.to_string(),
+            PRIORITY_REGIONAL,
+        )
+    }];
+
+    // Ordre d'application de `add_vegetation_layer` : les autres formations, les formations
+    // non renseignées puis les feuillus.
+    for key in ["other_vegetation", "undefined_vegetation", "feuillus"] {
+        applied.push(LegendEntry::from_class(
+            class_entry(key),
+            VEGETATION_SOURCE.to_string(),
+            PRIORITY_VEGETATION,
+        ));
+    }
+
+    // Les parcelles non classées gardent la couleur générique du RPG.
+    applied.push(LegendEntry::from_class(
+        class_entry("rpg"),
+        RPG_SOURCE.to_string(),
+        PRIORITY_RPG,
+    ));
+    for class in &rpg_classes.classes {
+        applied.push(LegendEntry {
+            class_key: format!("rpg.{}", class.key),
+            label_fr: class.label.clone(),
+            label_en: class
+                .label_en
+                .clone()
+                .unwrap_or_else(|| class.label.clone()),
+            rgb: class.color,
+            source_layer: RPG_SOURCE.to_string(),
+            priority: PRIORITY_RPG,
+        });
+    }
+
+    for (group, priority) in [
+        (LayerGroup::Hydro, PRIORITY_HYDRO),
+        (LayerGroup::Topo, PRIORITY_TOPO),
+    ] {
+        let layers = layer_config.enabled_layers(group);
+        // Une entrée par classe, avec les couches qui l'appliquent.
+        let mut classes: Vec<(&'static ClassEntry, Vec<&str>)> = Vec::new();
+        for layer in layers {
+            let Some(entry) = layer.class_entry() else {
+                continue;
+            };
+            // Les bâtiments hauts sont appliqués par la même couche (voir `add_building_layer`).
+            let entries = if layer.name == BUILDING_LAYER {
+                vec![entry, class_entry("building_high")]
+            } else {
+                vec![entry]
+            };
+            for entry in entries {
+                match classes.iter_mut().find(|(class, _)| class.key == entry.key) {
+                    Some((_, names)) => names.push(layer.name.as_str()),
+                    None => classes.push((entry, vec![layer.name.as_str()])),
+                }
+            }
+        }
+        for (entry, names) in classes {
+            applied.push(LegendEntry::from_class(entry, names.join(", "), priority));
+        }
+    }
+
+    // Les classes appliquées en dernier recouvrent les précédentes de même couleur.
+    let mut legend: Vec<LegendEntry> = Vec::new();
+    for entry in applied.into_iter().rev() {
+        match legend.iter_mut().find(|existing| existing.rgb == entry.rgb) {
+            Some(existing) => existing.absorb(entry),
+            None => legend.push(entry),
+        }
+    }
+    legend
+}
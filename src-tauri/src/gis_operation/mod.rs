@@ -1,13 +1,32 @@
 use std::process::Command;
 
-use gdal::{DriverManager, spatial_ref::SpatialRef};
+use gdal::vector::LayerAccess;
+use gdal::vector::sql::Dialect;
+use gdal::{Dataset, DatasetOptions, DriverManager, GdalOpenFlags, spatial_ref::SpatialRef};
 
-use crate::utils::{BoundingBox, resolution};
+use crate::app_setup::RunConfig;
+use crate::gdal_version::{GdalVersion, MAKEVALID_GDAL};
+use crate::utils::{BoundingBox, DEFAULT_PROJECT_EPSG, PROJECT_GRID_PIXELS};
 
+use ortho::xml_escape;
+
+pub mod colormap;
+pub mod creation;
+pub mod custom_layers;
+pub mod derive;
+pub mod identify;
+pub mod layer_config;
 pub mod layers;
+pub mod legend;
+pub mod ortho;
+pub mod oso;
 pub mod processing;
+pub mod qgis;
 pub mod regions;
+pub mod report;
 pub mod slicing;
+pub mod sql;
+pub mod tiles;
 
 /// Crée un projet de carte avec une résolution donnée (10m/pixel)
 /// et calcule la taille de l'image en fonction de la boîte englobante
@@ -48,15 +67,51 @@ pub fn create_project(
     project_file_path: &str,
     project_bb: &BoundingBox,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let resolution = resolution();
-    let width = (project_bb.width() / resolution).ceil() as usize;
-    let height = (project_bb.height() / resolution).ceil() as usize;
-    if !(width % 500 == 0 && height % 500 == 0) {
-        return Err("Width and height must be multiples of 500".into());
+    create_project_in_crs(
+        project_file_path,
+        project_bb,
+        DEFAULT_PROJECT_EPSG,
+        &RunConfig::snapshot(),
+    )
+}
+
+/// Crée un projet de carte comme `create_project`, dans un système de coordonnées donné :
+/// celui des départements du projet (voir `utils::regions_epsg`).
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+/// * `project_bb` - coordonnées de la boîte englobante du projet, dans ce système
+/// * `epsg` - code EPSG du système de coordonnées du projet
+/// * `run_config` - paramètres de la création : résolution en mètres par pixel et compression
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si la création a réussi ou échoué
+pub fn create_project_in_crs(
+    project_file_path: &str,
+    project_bb: &BoundingBox,
+    epsg: u32,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let resolution = run_config.resolution;
+    let (width, height) = project_bb.raster_size(resolution)?;
+    if !(width % PROJECT_GRID_PIXELS == 0 && height % PROJECT_GRID_PIXELS == 0) {
+        return Err(format!(
+            "Width and height must be multiples of {}",
+            PROJECT_GRID_PIXELS
+        )
+        .into());
     }
 
     let driver = DriverManager::get_driver_by_name("GTiff")?;
-    let mut dataset = driver.create(project_file_path, width, height, 4)?;
+    let mut dataset = driver.create_with_band_type_with_options::<u8, _>(
+        project_file_path,
+        width,
+        height,
+        4,
+        &processing::project_creation_options(run_config)?,
+    )?;
     let geotransform = [
         project_bb.xmin,
         resolution,
@@ -66,7 +121,7 @@ pub fn create_project(
         -resolution,
     ];
     dataset.set_geo_transform(&geotransform)?;
-    let srs = SpatialRef::from_epsg(2154)?;
+    let srs = SpatialRef::from_epsg(epsg)?;
     dataset.set_projection(&srs.to_wkt()?)?;
 
     for band_idx in 1..=3 {
@@ -75,10 +130,101 @@ pub fn create_project(
     }
     let mut band = dataset.rasterband(4)?;
     band.fill(255.0, None)?;
+    processing::mark_rgba_bands(&dataset)?;
 
     Ok(())
 }
 
+/// Options ogr2ogr de correction des anneaux non fermés. Avant GDAL 3.10, elles passent par
+/// les options de configuration `OGR_GEOMETRY_*` ; à partir de 3.10, qui ne les applique plus
+/// aux conversions, par `-makevalid`.
+///
+/// # Arguments
+///
+/// * `version` - la version de GDAL
+///
+/// # Returns
+///
+/// * `Vec<&'static str>` - les arguments à ajouter à la commande ogr2ogr
+pub fn ring_repair_args(version: GdalVersion) -> Vec<&'static str> {
+    if version >= MAKEVALID_GDAL {
+        vec!["-makevalid"]
+    } else {
+        vec![
+            "--config",
+            "OGR_GEOMETRY_ACCEPT_UNCLOSED_RING",
+            "NO",
+            "--config",
+            "OGR_GEOMETRY_CORRECT_UNCLOSED_RINGS",
+            "YES",
+        ]
+    }
+}
+
+/// Options ogr2ogr de `convert_to_gpkg_in_crs`, hors chemins d'entrée et de sortie.
+///
+/// # Arguments
+///
+/// * `epsg` - code EPSG du système de coordonnées du projet
+/// * `version` - la version de GDAL
+///
+/// # Returns
+///
+/// * `Vec<String>` - les arguments de la commande ogr2ogr
+pub fn convert_to_gpkg_args(epsg: u32, version: GdalVersion) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-f",
+        "GPKG",
+        "-t_srs",
+        &format!("EPSG:{}", epsg),
+        "-nlt",
+        "PROMOTE_TO_MULTI",
+        "-dim",
+        "XY",
+        "--config",
+        "OGR_ARC_STEPSIZE",
+        "0.1",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    args.extend(ring_repair_args(version).iter().map(|arg| arg.to_string()));
+    args
+}
+
+/// Options ogr2ogr de `clip_to_bb`, hors chemins d'entrée et de sortie.
+///
+/// # Arguments
+///
+/// * `project_bb` - coordonnées de la boîte englobante du projet
+/// * `version` - la version de GDAL
+///
+/// # Returns
+///
+/// * `Vec<String>` - les arguments de la commande ogr2ogr
+pub fn clip_to_bb_args(project_bb: &BoundingBox, version: GdalVersion) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-f",
+        "GPKG",
+        "-clipsrc",
+        &project_bb.xmin.to_string(),
+        &project_bb.ymin.to_string(),
+        &project_bb.xmax.to_string(),
+        &project_bb.ymax.to_string(),
+        "-nlt",
+        "PROMOTE_TO_MULTI",
+        "-skipfailures",
+        "--config",
+        "OGR_ENABLE_PARTIAL_REPROJECTION",
+        "YES",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    args.extend(ring_repair_args(version).iter().map(|arg| arg.to_string()));
+    args
+}
+
 /// Convertit un fichier en format GeoPackage (GPKG) en utilisant ogr2ogr
 ///
 /// # Arguments
@@ -92,33 +238,36 @@ pub fn create_project(
 pub fn convert_to_gpkg(
     input_file: &str,
     output_gpkg: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    convert_to_gpkg_in_crs(input_file, output_gpkg, DEFAULT_PROJECT_EPSG)
+}
+
+/// Convertit un fichier en GeoPackage comme `convert_to_gpkg`, en le reprojetant dans
+/// le système de coordonnées du projet (`-t_srs`). Le découpage par `clip_to_bb` se fait
+/// ensuite dans ce même système.
+///
+/// # Arguments
+///
+/// * `input_file` - chemin du fichier d'entrée
+/// * `output_gpkg` - chemin du fichier GeoPackage de sortie
+/// * `epsg` - code EPSG du système de coordonnées du projet
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si la conversion a réussi ou échoué
+pub fn convert_to_gpkg_in_crs(
+    input_file: &str,
+    output_gpkg: &str,
+    epsg: u32,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let current_dir = std::env::current_dir()?;
     let input_file_path = current_dir.join(input_file);
     let output_gpkg_path = current_dir.join(output_gpkg);
 
     let status = Command::new("ogr2ogr")
-        .args([
-            "-f",
-            "GPKG",
-            output_gpkg_path.to_str().unwrap(),
-            input_file_path.to_str().unwrap(),
-            "-t_srs",
-            "EPSG:2154",
-            "-nlt",
-            "PROMOTE_TO_MULTI",
-            "--config",
-            "OGR_GEOMETRY_ACCEPT_UNCLOSED_RING",
-            "NO",
-            "-dim",
-            "XY",
-            "--config",
-            "OGR_ARC_STEPSIZE",
-            "0.1",
-            "--config",
-            "OGR_GEOMETRY_CORRECT_UNCLOSED_RINGS",
-            "YES",
-        ])
+        .arg(&output_gpkg_path)
+        .arg(&input_file_path)
+        .args(convert_to_gpkg_args(epsg, GdalVersion::current()))
         .status()?;
 
     if !status.success() {
@@ -128,107 +277,351 @@ pub fn convert_to_gpkg(
     Ok(())
 }
 
+/// Champs identifiants stables utilisés pour repérer les entités dupliquées,
+/// par ordre de préférence (BDTOPO, BDFORET, RPG).
+const DEDUP_KEY_FIELDS: [&str; 3] = ["cleabs", "ID", "ID_PARCEL"];
+
+/// Noms des couches d'un jeu de données vectoriel, dans leur ordre.
+///
+/// # Arguments
+///
+/// * `dataset` - le jeu de données ouvert
+///
+/// # Returns
+///
+/// * `Vec<String>` - les noms des couches
+pub fn layer_names(dataset: &Dataset) -> Vec<String> {
+    dataset.layers().map(|layer| layer.name()).collect()
+}
+
+/// Nombre total d'entités d'un jeu de données vectoriel, toutes couches confondues.
+fn total_feature_count(path: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let dataset = Dataset::open(path)?;
+    Ok(dataset.layers().map(|layer| layer.feature_count()).sum())
+}
+
+/// Source OGR VRT réunissant toutes les couches des fichiers en une seule couche
+/// `layer_name` (`OGRVRTUnionLayer`), pour que ogr2ogr les fusionne en un seul appel.
+fn union_vrt(datasets: &[String], layer_name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let current_dir = std::env::current_dir()?;
+    let mut sources = String::new();
+    for (dataset_index, dataset) in datasets.iter().enumerate() {
+        let path = current_dir.join(dataset);
+        for (layer_index, source_layer) in layer_names(&Dataset::open(&path)?).iter().enumerate() {
+            sources.push_str(&format!(
+                "<OGRVRTLayer name=\"source_{}_{}\">\
+                 <SrcDataSource relativeToVRT=\"0\">{}</SrcDataSource>\
+                 <SrcLayer>{}</SrcLayer>\
+                 </OGRVRTLayer>",
+                dataset_index,
+                layer_index,
+                xml_escape(&path.to_string_lossy()),
+                xml_escape(source_layer)
+            ));
+        }
+    }
+    Ok(format!(
+        "<OGRVRTDataSource><OGRVRTUnionLayer name=\"{}\">{}</OGRVRTUnionLayer></OGRVRTDataSource>",
+        xml_escape(layer_name),
+        sources
+    ))
+}
+
 /// Fusionne plusieurs fichiers GeoPackage en un seul
 ///
+/// Toutes les entités sont écrites dans une seule couche, nommée d'après le fichier de sortie
+/// (`-nln`), même lorsque le nom des couches sources diffère d'une région à l'autre.
+/// Les sources sont réunies dans une couche VRT et copiées par un seul appel à ogr2ogr,
+/// par transactions de 65536 entités et sans synchronisation SQLite. Le résultat est écrit
+/// dans un fichier temporaire qui ne remplace le fichier de sortie existant qu'une fois
+/// la fusion réussie.
+/// Si le résultat ne compte pas exactement une couche avec la somme des entités sources,
+/// un avertissement est journalisé : les fonctions d'ajout des couches traitent de toute
+/// façon chacune des couches du fichier.
+///
+/// Les entités situées à cheval sur une limite départementale sont présentes dans les extraits
+/// de chaque région : lorsque `deduplicate` est vrai, elles sont supprimées après la fusion
+/// (voir [`remove_duplicate_features`]).
+///
 /// # Arguments
 ///
 /// * `datasets` - une liste de chemins vers les fichiers GeoPackage à fusionner
 /// * `output_gpkg` - chemin du fichier GeoPackage de sortie fusionné
+/// * `deduplicate` - supprime les entités dupliquées après la fusion
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si la fusion a réussi ou échoué
+/// * `Result<u64, Box<dyn std::error::Error>>` - le nombre d'entités dupliquées supprimées
 pub fn fusion_datasets(
     datasets: &[String],
     output_gpkg: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+    deduplicate: bool,
+) -> Result<u64, Box<dyn std::error::Error>> {
     if datasets.is_empty() {
         return Err("No datasets provided for fusion".into());
     }
 
-    if std::path::Path::new(output_gpkg).exists() {
-        std::fs::remove_file(output_gpkg)?;
+    let output_path = std::path::Path::new(output_gpkg);
+    let layer_name = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .ok_or("Invalid output path for fusion")?;
+    let partial_gpkg = output_path.with_file_name(format!("{}.partial.gpkg", layer_name));
+    if partial_gpkg.exists() {
+        std::fs::remove_file(&partial_gpkg)?;
     }
 
-    let first_dataset = &datasets[0];
-    let mut status = Command::new("ogr2ogr")
-        .arg("-f")
-        .arg("GPKG")
-        .arg(output_gpkg)
-        .arg(first_dataset)
-        .status()?;
+    let output = Command::new("ogr2ogr")
+        .args(["-f", "GPKG", "-nln", &layer_name, "-gt", "65536"])
+        .args(["--config", "OGR_SQLITE_SYNCHRONOUS", "OFF"])
+        .arg(&partial_gpkg)
+        .arg(union_vrt(datasets, &layer_name)?)
+        .output()?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial_gpkg);
+        return Err(format!(
+            "Failed to merge datasets {:?}: {}",
+            datasets,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    std::fs::rename(&partial_gpkg, output_path)?;
 
-    if !status.success() {
-        return Err(format!("Failed to process first dataset: {}", first_dataset).into());
+    let mut expected_features = 0;
+    for dataset in datasets {
+        expected_features += total_feature_count(dataset)?;
+    }
+    let output = Dataset::open(output_gpkg)?;
+    let output_layers = layer_names(&output);
+    let output_features: u64 = output.layers().map(|layer| layer.feature_count()).sum();
+    output.close()?;
+    if output_layers.len() != 1 || output_features != expected_features {
+        tracing::warn!(
+            output = output_gpkg,
+            layers = ?output_layers,
+            features = output_features,
+            expected_features,
+            "Fusion incomplète dans une seule couche, toutes les couches du fichier seront utilisées"
+        );
     }
 
-    for dataset in datasets.iter().skip(1) {
-        status = Command::new("ogr2ogr")
-            .arg("-f")
-            .arg("GPKG")
-            .arg("-append")
-            .arg("-update")
-            .arg(output_gpkg)
-            .arg(dataset)
-            .status()?;
+    if deduplicate && datasets.len() > 1 {
+        remove_duplicate_features(output_gpkg)
+    } else {
+        Ok(0)
+    }
+}
 
-        if !status.success() {
-            return Err(format!("Failed to append dataset: {}", dataset).into());
-        }
+/// Supprime les entités dupliquées de chaque couche d'un GeoPackage.
+///
+/// La clé de déduplication est le premier champ identifiant présent parmi
+/// `cleabs`, `ID` et `ID_PARCEL` ; à défaut, la géométrie elle-même est comparée.
+/// Seule la première occurrence (plus petit `fid`) de chaque clé est conservée ; les
+/// entités sans clé (valeur NULL) ne sont jamais considérées comme des doublons.
+///
+/// # Arguments
+///
+/// * `gpkg_path` - chemin du fichier GeoPackage à dédupliquer
+///
+/// # Returns
+///
+/// * `Result<u64, Box<dyn std::error::Error>>` - le nombre d'entités supprimées
+pub fn remove_duplicate_features(gpkg_path: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let dataset = Dataset::open_ex(
+        gpkg_path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_VECTOR,
+            ..Default::default()
+        },
+    )?;
+
+    let mut removed = 0;
+
+    for layer_name in layer_names(&dataset) {
+        let layer = dataset.layer_by_name(&layer_name)?;
+        let before = layer.feature_count();
+
+        let field_names: Vec<String> = layer.defn().fields().map(|field| field.name()).collect();
+        let key = DEDUP_KEY_FIELDS
+            .iter()
+            .find_map(|candidate| {
+                field_names
+                    .iter()
+                    .find(|name| name.eq_ignore_ascii_case(candidate))
+                    .cloned()
+            })
+            .or_else(|| {
+                layer
+                    .defn()
+                    .geom_fields()
+                    .next()
+                    .map(|geom_field| geom_field.name())
+            });
+
+        let Some(key) = key else {
+            continue;
+        };
+        drop(layer);
+
+        dataset.execute_sql(
+            format!(
+                "DELETE FROM \"{layer}\" WHERE \"{key}\" IS NOT NULL AND fid NOT IN \
+                 (SELECT MIN(fid) FROM \"{layer}\" WHERE \"{key}\" IS NOT NULL GROUP BY \"{key}\")",
+                layer = layer_name,
+                key = key
+            ),
+            None,
+            Dialect::SQLITE,
+        )?;
+
+        let after = dataset.layer_by_name(&layer_name)?.feature_count();
+        removed += before.saturating_sub(after);
     }
 
-    Ok(())
+    dataset.close()?;
+
+    Ok(removed)
 }
 
-/// Découpe un GeoPackage en fonction d'une boîte englobante, afin de le réduire à la zone d'intérêt
+/// Bilan du découpage d'un GeoPackage par `clip_to_bb`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClipReport {
+    /// Nombre d'entités du GeoPackage d'entrée, toutes couches confondues.
+    pub input_features: u64,
+    /// Nombre d'entités du GeoPackage découpé.
+    pub output_features: u64,
+    /// Estimation du nombre d'entités de l'emprise perdues lors du découpage,
+    /// le plus souvent des géométries invalides ignorées par `-skipfailures`.
+    pub skipped: u64,
+}
+
+impl ClipReport {
+    /// Part des entités de l'emprise perdues lors du découpage.
+    pub fn skipped_ratio(&self) -> f64 {
+        let expected = self.output_features + self.skipped;
+        if expected == 0 {
+            0.0
+        } else {
+            self.skipped as f64 / expected as f64
+        }
+    }
+}
+
+/// Part d'entités perdues au découpage au-delà de laquelle un avertissement est ajouté à la création.
+pub const CLIP_SKIPPED_WARNING_RATIO: f64 = 0.005;
+
+/// Découpe un GeoPackage en fonction d'une boîte englobante, afin de le réduire à la zone d'intérêt.
+/// Les entités que ogr2ogr ne parvient pas à découper sont ignorées ; leur nombre est estimé
+/// d'après les erreurs d'ogr2ogr et l'écart entre les entités de sortie et les entités
+/// d'entrée dont l'emprise recoupe la boîte englobante.
 ///
 /// # Arguments
 ///
-/// * `input_gpkg` - chemin du fichier GeoPackage d'entrée
+/// * `input_gpkg` - chemin du fichier GeoPackage d'entrée, dans le système de coordonnées
+///   du projet (voir `convert_to_gpkg_in_crs`)
 /// * `output_gpkg` - chemin du fichier GeoPackage de sortie
 /// * `project_bb` - coordonnées de la boîte englobante du projet
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si le découpage a réussi ou échoué
+/// * `Result<ClipReport, Box<dyn std::error::Error>>` - le nombre d'entités avant et après découpage et le nombre d'entités ignorées, ou une erreur
 pub fn clip_to_bb(
     input_gpkg: &str,
     output_gpkg: &str,
     project_bb: &BoundingBox,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<ClipReport, Box<dyn std::error::Error>> {
     let current_dir = std::env::current_dir()?;
     let input_gpkg = current_dir.join(input_gpkg);
     let output_gpkg = current_dir.join(output_gpkg);
 
-    let status = Command::new("ogr2ogr")
-        .args([
-            "-f",
-            "GPKG",
-            output_gpkg.to_str().unwrap(),
-            input_gpkg.to_str().unwrap(),
-            "-clipsrc",
-            &project_bb.xmin.to_string(),
-            &project_bb.ymin.to_string(),
-            &project_bb.xmax.to_string(),
-            &project_bb.ymax.to_string(),
-            "-nlt",
-            "PROMOTE_TO_MULTI",
-            "--config",
-            "OGR_GEOMETRY_ACCEPT_UNCLOSED_RING",
-            "NO",
-            "-skipfailures",
-            "--config",
-            "OGR_ENABLE_PARTIAL_REPROJECTION",
-            "YES",
-            "--config",
-            "OGR_GEOMETRY_CORRECT_UNCLOSED_RINGS",
-            "YES",
-        ])
-        .status()?;
+    let output = Command::new("ogr2ogr")
+        .arg(&output_gpkg)
+        .arg(&input_gpkg)
+        .args(clip_to_bb_args(project_bb, GdalVersion::current()))
+        .output()?;
 
-    if !status.success() {
+    if !output.status.success() {
         return Err("Failed to clip GeoPackage".into());
     }
 
-    Ok(())
+    let failures = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| line.starts_with("ERROR"))
+        .count() as u64;
+    let input_features = count_features(&input_gpkg, None)?;
+    let intersecting_features = count_features(&input_gpkg, Some(project_bb))?;
+    let output_features = count_features(&output_gpkg, None)?;
+
+    Ok(ClipReport {
+        input_features,
+        output_features,
+        skipped: failures.max(intersecting_features.saturating_sub(output_features)),
+    })
+}
+
+/// Compte les entités de toutes les couches d'un jeu de données vectoriel,
+/// limitées à celles dont l'emprise recoupe `bb` si elle est donnée.
+fn count_features(
+    path: &std::path::Path,
+    bb: Option<&BoundingBox>,
+) -> Result<u64, gdal::errors::GdalError> {
+    let dataset = Dataset::open(path)?;
+    let mut total = 0;
+    for mut layer in dataset.layers() {
+        if let Some(bb) = bb {
+            layer.set_spatial_filter_rect(bb.xmin, bb.ymin, bb.xmax, bb.ymax);
+        }
+        total += layer.feature_count();
+    }
+    Ok(total)
+}
+
+/// Calcule l'emprise d'un GeoPackage comme l'union des emprises de ses couches.
+///
+/// # Arguments
+///
+/// * `gpkg_path` - chemin du fichier GeoPackage
+///
+/// # Returns
+///
+/// * `Result<Option<BoundingBox>, Box<dyn std::error::Error>>` - l'emprise, ou `None` si aucune couche n'a d'entité
+pub fn gpkg_extent(gpkg_path: &str) -> Result<Option<BoundingBox>, Box<dyn std::error::Error>> {
+    let dataset = Dataset::open(gpkg_path)?;
+    let mut extent: Option<BoundingBox> = None;
+
+    for layer in dataset.layers() {
+        if layer.feature_count() == 0 {
+            continue;
+        }
+        let envelope = layer.get_extent()?;
+        extent = Some(match extent {
+            Some(bb) => BoundingBox::new(
+                bb.xmin.min(envelope.MinX),
+                bb.ymin.min(envelope.MinY),
+                bb.xmax.max(envelope.MaxX),
+                bb.ymax.max(envelope.MaxY),
+            ),
+            None => BoundingBox::new(envelope.MinX, envelope.MinY, envelope.MaxX, envelope.MaxY),
+        });
+    }
+
+    Ok(extent)
+}
+
+/// Vérifie qu'un GeoPackage couvre entièrement une emprise, d'après l'emprise de ses couches.
+///
+/// # Arguments
+///
+/// * `gpkg_path` - chemin du fichier GeoPackage
+/// * `bb` - emprise à couvrir
+///
+/// # Returns
+///
+/// * `Result<bool, Box<dyn std::error::Error>>` - vrai si l'emprise est couverte
+pub fn gpkg_covers_bb(
+    gpkg_path: &str,
+    bb: &BoundingBox,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(gpkg_extent(gpkg_path)?.is_some_and(|extent| extent.contains(bb)))
 }
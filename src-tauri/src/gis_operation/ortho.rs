@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{BoundingBox, DEFAULT_PROJECT_EPSG};
+
+/// Service WMS fournissant les orthophotos du projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrthoSource {
+    /// Nom affiché dans les paramètres.
+    pub name: String,
+    pub server_url: String,
+    pub layer: String,
+    #[serde(default = "default_crs")]
+    pub crs: String,
+    #[serde(default = "default_image_format")]
+    pub image_format: String,
+    /// Clé d'API, ajoutée à l'URL du service (`apikey=`).
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// En-tête HTTP transmis à chaque requête, par exemple `Authorization: Bearer ...`.
+    #[serde(default)]
+    pub http_header: Option<String>,
+    /// Ordre d'essai des sources : la plus petite valeur est essayée en premier.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl OrthoSource {
+    /// Source interrogée dans le système de coordonnées du projet : les projets en Lambert-93
+    /// gardent le système de la configuration, les projets d'outre-mer demandent le leur.
+    pub fn in_project_crs(&self, epsg: u32) -> OrthoSource {
+        let mut source = self.clone();
+        if epsg != DEFAULT_PROJECT_EPSG {
+            source.crs = format!("EPSG:{}", epsg);
+        }
+        source
+    }
+}
+
+fn default_crs() -> String {
+    "EPSG:2154".to_string()
+}
+
+fn default_image_format() -> String {
+    "image/jpeg".to_string()
+}
+
+/// Sources d'orthophotos de la Géoplateforme de l'IGN, écrites dans la configuration
+/// au premier lancement.
+pub fn default_ortho_sources() -> Vec<OrthoSource> {
+    vec![
+        OrthoSource {
+            name: "IGN - Orthophotos".to_string(),
+            server_url: "https://data.geopf.fr/wms-r/wms".to_string(),
+            layer: "ORTHOIMAGERY.ORTHOPHOTOS".to_string(),
+            crs: default_crs(),
+            image_format: default_image_format(),
+            api_key: None,
+            http_header: None,
+            priority: 0,
+        },
+        OrthoSource {
+            name: "IGN - BD ORTHO".to_string(),
+            server_url: "https://data.geopf.fr/wms-r/wms".to_string(),
+            layer: "ORTHOIMAGERY.ORTHOPHOTOS.BDORTHO".to_string(),
+            crs: default_crs(),
+            image_format: default_image_format(),
+            api_key: None,
+            http_header: None,
+            priority: 1,
+        },
+    ]
+}
+
+/// Trie les sources par priorité croissante, en conservant l'ordre de la configuration
+/// pour les priorités égales.
+pub fn sources_by_priority(sources: &[OrthoSource]) -> Vec<&OrthoSource> {
+    let mut sorted: Vec<&OrthoSource> = sources.iter().collect();
+    sorted.sort_by_key(|source| source.priority);
+    sorted
+}
+
+/// Place une source en tête de l'ordre d'essai ; les autres conservent leur ordre relatif.
+///
+/// # Arguments
+///
+/// * `sources` - Les sources configurées.
+/// * `name` - Le nom de la source à privilégier.
+///
+/// # Returns
+///
+/// * `bool` - `false` si aucune source ne porte ce nom (les priorités sont alors inchangées).
+pub fn prefer_source(sources: &mut [OrthoSource], name: &str) -> bool {
+    if !sources.iter().any(|source| source.name == name) {
+        return false;
+    }
+
+    let order: Vec<String> = sources_by_priority(sources)
+        .into_iter()
+        .map(|source| source.name.clone())
+        .filter(|source_name| source_name != name)
+        .collect();
+    for source in sources.iter_mut() {
+        source.priority = match order.iter().position(|other| *other == source.name) {
+            Some(position) => position as i32 + 1,
+            None => 0,
+        };
+    }
+    true
+}
+
+pub(crate) fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// URL du service, complétée par la clé d'API le cas échéant.
+pub fn server_url_with_key(source: &OrthoSource) -> String {
+    match source.api_key.as_deref().filter(|key| !key.is_empty()) {
+        Some(key) => {
+            let separator = if source.server_url.contains('?') {
+                '&'
+            } else {
+                '?'
+            };
+            format!("{}{}apikey={}", source.server_url, separator, key)
+        }
+        None => source.server_url.clone(),
+    }
+}
+
+/// Taille maximale (en octets) du cache WMS de GDAL d'un téléchargement d'orthophoto.
+pub const WMS_CACHE_MAX_SIZE: u64 = 500_000_000;
+
+/// Nom du dossier du cache WMS de GDAL, dans le dossier de travail d'un téléchargement.
+pub const WMS_CACHE_DIR: &str = "wms_cache";
+
+/// Construit la description GDAL_WMS d'une source pour l'emprise du projet.
+///
+/// # Arguments
+///
+/// * `source` - La source d'orthophotos.
+/// * `bb` - L'emprise du projet.
+/// * `size` - La taille de l'image en pixels.
+/// * `cache_dir` - Le dossier du cache WMS de GDAL.
+///
+/// # Returns
+///
+/// * `String` - Le contenu XML à passer à `gdal_translate`.
+pub fn wms_xml(
+    source: &OrthoSource,
+    bb: &BoundingBox,
+    size: (usize, usize),
+    cache_dir: &str,
+) -> String {
+    format!(
+        r#"<GDAL_WMS>
+      <Service name="WMS">
+        <Version>1.3.0</Version>
+        <ServerUrl>{}</ServerUrl>
+        <CRS>{}</CRS>
+        <ImageFormat>{}</ImageFormat>
+        <Layers>{}</Layers>
+        <Styles></Styles>
+      </Service>
+      <DataWindow>
+        <UpperLeftX>{}</UpperLeftX>
+        <UpperLeftY>{}</UpperLeftY>
+        <LowerRightX>{}</LowerRightX>
+        <LowerRightY>{}</LowerRightY>
+        <SizeX>{}</SizeX>
+        <SizeY>{}</SizeY>
+      </DataWindow>
+      <BandsCount>3</BandsCount>
+      <BlockSizeX>2048</BlockSizeX>
+      <BlockSizeY>2048</BlockSizeY>
+      <OverviewCount>0</OverviewCount>
+      <ZeroBlockHttpCodes>204,400,404,502,503,504</ZeroBlockHttpCodes>
+      <MaxConnections>10</MaxConnections>
+      <Timeout>120</Timeout>
+      <Cache>
+        <Type>Disk</Type>
+        <Path>{}</Path>
+        <MaxSize>{}</MaxSize>
+      </Cache>
+      <UserAgent>GDAL WMS driver (https://gdal.org/drivers/raster/wms.html)</UserAgent>
+      <UnsafeSSL>true</UnsafeSSL>
+      <Retry>
+        <Count>5</Count>
+        <Delay>1</Delay>
+      </Retry>
+    </GDAL_WMS>"#,
+        xml_escape(&server_url_with_key(source)),
+        xml_escape(&source.crs),
+        xml_escape(&source.image_format),
+        xml_escape(&source.layer),
+        bb.xmin,
+        bb.ymax,
+        bb.xmax,
+        bb.ymin,
+        size.0,
+        size.1,
+        xml_escape(cache_dir),
+        WMS_CACHE_MAX_SIZE
+    )
+}
+
+/// Options HTTP de GDAL propres à une source (en-tête d'authentification).
+///
+/// # Arguments
+///
+/// * `source` - La source d'orthophotos.
+///
+/// # Returns
+///
+/// * `Vec<(String, String)>` - Les variables d'environnement à définir pour `gdal_translate`.
+pub fn gdal_http_env(source: &OrthoSource) -> Vec<(String, String)> {
+    source
+        .http_header
+        .as_deref()
+        .filter(|header| !header.trim().is_empty())
+        .map(|header| vec![("GDAL_HTTP_HEADERS".to_string(), header.trim().to_string())])
+        .unwrap_or_default()
+}
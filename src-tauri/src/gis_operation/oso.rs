@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use gdal::{Dataset, DriverManager};
+use serde::Deserialize;
+
+use super::colormap::{self, CLASSES, Rgb};
+use super::processing::{ProjectRaster, overlay_in_place, read_band, write_band};
+
+use crate::utils::{WorkDir, in_resource_dir, oso_url, proxy_config};
+use crate::web_request::gdal_proxy_env;
+
+/// Fichier de correspondance entre les codes OSO et les classes de végétation.
+pub const OSO_RECLASS_FILE: &str = "oso_reclass.json";
+
+/// Source des données de végétation d'un projet.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum VegetationSource {
+    /// BD Forêt de l'IGN (par défaut).
+    #[default]
+    BdForet,
+    /// Occupation des sols OSO (Theia), téléchargée sur l'emprise du projet.
+    Oso,
+    /// Raster OSO local fourni par l'utilisateur.
+    File(PathBuf),
+}
+
+impl VegetationSource {
+    /// Interprète la source de végétation transmise par l'interface :
+    /// `"bdforet"`, `"oso"` ou `"file:<chemin>"`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - la valeur à interpréter
+    ///
+    /// # Returns
+    ///
+    /// * `Result<VegetationSource, String>` - la source, ou un message d'erreur si la valeur est inconnue
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "" | "bdforet" => Ok(VegetationSource::BdForet),
+            "oso" => Ok(VegetationSource::Oso),
+            value => match value.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(VegetationSource::File(PathBuf::from(path))),
+                _ => Err(format!("Source de végétation inconnue: {}", value)),
+            },
+        }
+    }
+
+    /// Représentation textuelle enregistrée dans les métadonnées du projet.
+    pub fn as_metadata_string(&self) -> String {
+        match self {
+            VegetationSource::BdForet => "bdforet".to_string(),
+            VegetationSource::Oso => "oso".to_string(),
+            VegetationSource::File(path) => format!("file:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReclassFile {
+    classes: HashMap<String, String>,
+}
+
+/// Charge la table de reclassification OSO et la convertit en couleurs de végétation.
+///
+/// # Arguments
+///
+/// * `table_path` - chemin du fichier JSON de reclassification
+///
+/// # Returns
+///
+/// * `Result<HashMap<u8, Rgb>, Box<dyn std::error::Error>>` - la couleur associée à chaque code OSO retenu
+pub fn load_reclass_table(
+    table_path: &Path,
+) -> Result<HashMap<u8, Rgb>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(table_path)?;
+    let reclass: ReclassFile = serde_json::from_str(&content)?;
+
+    let mut table = HashMap::new();
+    for (code, class_key) in reclass.classes {
+        let code: u8 = code.parse()?;
+        let entry = CLASSES
+            .iter()
+            .find(|entry| entry.key == class_key)
+            .ok_or(format!("Classe inconnue dans la table OSO: {}", class_key))?;
+        table.insert(code, entry.color);
+    }
+
+    Ok(table)
+}
+
+/// Reclassifie des codes OSO en bandes RGB de végétation.
+/// Les codes absents de la table sont laissés à 0 (non végétation).
+///
+/// # Arguments
+///
+/// * `codes` - codes OSO de chaque pixel
+/// * `table` - table de reclassification
+///
+/// # Returns
+///
+/// * `[Vec<u8>; 3]` - les bandes rouge, verte et bleue
+pub fn reclassify(codes: &[u8], table: &HashMap<u8, Rgb>) -> [Vec<u8>; 3] {
+    let mut bands = [
+        vec![0u8; codes.len()],
+        vec![0u8; codes.len()],
+        vec![0u8; codes.len()],
+    ];
+    for (i, code) in codes.iter().enumerate() {
+        if let Some(color) = table.get(code) {
+            for (band, value) in bands.iter_mut().zip(color.iter()) {
+                band[i] = *value;
+            }
+        }
+    }
+    bands
+}
+
+/// Rééchantillonne un raster OSO sur la grille du projet (emprise, taille et projection).
+/// L'emprise est exprimée dans le système du projet (Lambert-93 en métropole, UTM outre-mer).
+/// Le rééchantillonnage utilise la classe majoritaire (`mode`) car les valeurs sont catégorielles.
+///
+/// # Arguments
+///
+/// * `project` - dataset du projet
+/// * `oso_source` - chemin (ou chemin virtuel GDAL) du raster OSO
+/// * `output_raster` - chemin du raster rééchantillonné
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si le rééchantillonnage a réussi ou échoué
+pub fn resample_to_project(
+    project: &Dataset,
+    oso_source: &str,
+    output_raster: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let geo_transform = project.geo_transform()?;
+    let (width, height) = project.raster_size();
+
+    let xmin = geo_transform[0].to_string();
+    let ymin = (geo_transform[3] + geo_transform[5] * height as f64).to_string();
+    let xmax = (geo_transform[0] + geo_transform[1] * width as f64).to_string();
+    let ymax = geo_transform[3].to_string();
+    let projection = project.projection();
+
+    if Path::new(output_raster).exists() {
+        fs::remove_file(output_raster)?;
+    }
+
+    let status = Command::new("gdalwarp")
+        .envs(gdal_proxy_env(proxy_config().as_ref()))
+        .args([
+            "-t_srs",
+            &projection,
+            "-te",
+            &xmin,
+            &ymin,
+            &xmax,
+            &ymax,
+            "-ts",
+            &width.to_string(),
+            &height.to_string(),
+            "-r",
+            "mode",
+            "-ot",
+            "Byte",
+            oso_source,
+            output_raster,
+        ])
+        .status()?;
+
+    if !status.success() {
+        return Err("gdalwarp failed".into());
+    }
+
+    Ok(())
+}
+
+/// Chemin GDAL du raster OSO distant, lu via `/vsicurl/` pour ne récupérer que l'emprise utile.
+///
+/// # Returns
+///
+/// * `Result<String, Box<dyn std::error::Error>>` - le chemin virtuel, ou une erreur si aucune URL n'est configurée
+pub fn remote_oso_source() -> Result<String, Box<dyn std::error::Error>> {
+    let url = oso_url().ok_or(
+        "Aucune URL OSO configurée (oso_url) : utilisez une source \"file:<chemin>\" ou renseignez l'URL d'un GeoTIFF OSO",
+    )?;
+    Ok(format!("/vsicurl/{}", url))
+}
+
+/// Ajoute une couche de végétation issue de l'occupation des sols OSO à un projet,
+/// en remplacement de `add_vegetation_layer`.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project` - projet ouvert en écriture
+/// * `source` - source OSO (`Oso` ou `File`)
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_oso_layer(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    source: &VegetationSource,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let oso_source = match source {
+        VegetationSource::Oso => remote_oso_source()?,
+        VegetationSource::File(path) => path.to_string_lossy().to_string(),
+        VegetationSource::BdForet => {
+            return Err("La source BD Forêt n'est pas une source OSO".into());
+        }
+    };
+    let table = load_reclass_table(&in_resource_dir(OSO_RECLASS_FILE))?;
+
+    add_oso_raster_to(work_dir, project, &oso_source, &table)
+}
+
+/// Rééchantillonne, reclassifie et applique un raster OSO sur le projet.
+///
+/// # Arguments
+///
+/// * `work_dir` - dossier de travail de l'opération
+/// * `project_file_path` - chemin du fichier projet
+/// * `oso_source` - chemin (ou chemin virtuel GDAL) du raster OSO
+/// * `table` - table de reclassification
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'ajout a réussi ou échoué
+pub fn add_oso_raster(
+    work_dir: &WorkDir,
+    project_file_path: &str,
+    oso_source: &str,
+    table: &HashMap<u8, Rgb>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project = ProjectRaster::open(project_file_path)?;
+    add_oso_raster_to(work_dir, &project, oso_source, table)?;
+    project.close()
+}
+
+/// Variante de `add_oso_raster` écrivant dans un projet déjà ouvert.
+pub fn add_oso_raster_to(
+    work_dir: &WorkDir,
+    project: &ProjectRaster,
+    oso_source: &str,
+    table: &HashMap<u8, Rgb>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dataset = project.dataset();
+    let (width, height) = dataset.raster_size();
+    let temp_resampled = work_dir.path_str("temp_oso_resampled.tif");
+    let temp_vegetation = work_dir.path_str("temp_oso_vegetation.tif");
+
+    resample_to_project(dataset, oso_source, &temp_resampled)?;
+
+    let resampled = Dataset::open(&temp_resampled)?;
+    let codes = read_band(&resampled, 1)?;
+    resampled.close()?;
+
+    let driver = DriverManager::get_driver_by_name("GTiff")?;
+    let mut vegetation_raster = driver.create(&temp_vegetation, width, height, 3)?;
+    vegetation_raster.set_geo_transform(&dataset.geo_transform()?)?;
+    vegetation_raster.set_projection(&dataset.projection())?;
+
+    for (i, data) in reclassify(&codes, table).into_iter().enumerate() {
+        write_band(&vegetation_raster, i + 1, data)?;
+    }
+
+    vegetation_raster.close()?;
+
+    overlay_in_place(
+        project,
+        &temp_vegetation,
+        |&value| value > 0,
+        Some(&colormap::vegetation_class),
+    )?;
+
+    fs::remove_file(&temp_resampled)?;
+    fs::remove_file(&temp_vegetation)?;
+
+    Ok(())
+}
@@ -1,6 +1,502 @@
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use gdal::{Dataset, DriverManager};
+use gdal::raster::{ColorInterpretation, GdalDataType, RasterBand, RasterCreationOptions};
+use gdal::spatial_ref::SpatialRef;
+use gdal::{Dataset, DatasetOptions, DriverManager, GdalOpenFlags};
+
+use super::colormap::{CLASS_UNKNOWN, Rgb};
+
+use crate::app_setup::RunConfig;
+use crate::timings::processing_timer;
+use crate::utils::{BoundingBox, WorkDir, is_project_epsg};
+
+/// Nombre de bandes d'un raster de projet : rouge, vert et bleu (1 à 3) puis alpha (4),
+/// toutes en octets. Les indices des bandes RGB et alpha sont lus d'après leur
+/// interprétation des couleurs (voir `rgba_band_indices`).
+pub const PROJECT_BAND_COUNT: usize = 4;
+
+/// Écart toléré, en mètres, entre la taille des pixels d'un projet et la résolution attendue,
+/// entre ses deux dimensions, et entre son origine et la grille de ses pixels.
+pub const RESOLUTION_TOLERANCE_M: f64 = 0.001;
+
+/// Fenêtre d'un raster de projet, en pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RasterWindow {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl RasterWindow {
+    /// Fenêtre couvrant tout un raster.
+    pub fn full((width, height): (usize, usize)) -> Self {
+        RasterWindow {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// Bande de `rows` lignes de toute la largeur, à partir de la ligne `y`.
+    pub fn rows(width: usize, y: usize, rows: usize) -> Self {
+        RasterWindow {
+            x: 0,
+            y,
+            width,
+            height: rows,
+        }
+    }
+
+    fn offset(&self) -> (isize, isize) {
+        (self.x as isize, self.y as isize)
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+}
+
+/// Raster refusé par `ProjectRaster` : ce n'est pas un raster de projet Firefront.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectRasterError {
+    BandCount(usize),
+    BandType {
+        band: usize,
+        data_type: String,
+    },
+    Crs(Option<i32>),
+    Resolution {
+        pixel_size: (f64, f64),
+        expected: f64,
+    },
+    /// Pixels non carrés, grille tournée ou origine hors de la grille des pixels.
+    PixelGrid {
+        pixel_size: (f64, f64),
+        origin: (f64, f64),
+    },
+}
+
+impl std::fmt::Display for ProjectRasterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not a Firefront project raster: ")?;
+        match self {
+            ProjectRasterError::BandCount(count) => write!(
+                f,
+                "{} bandes au lieu de {} (RGBA)",
+                count, PROJECT_BAND_COUNT
+            ),
+            ProjectRasterError::BandType { band, data_type } => {
+                write!(f, "bande {} de type {} au lieu d'octets", band, data_type)
+            }
+            ProjectRasterError::Crs(Some(epsg)) => {
+                write!(f, "système de coordonnées EPSG:{} non pris en charge", epsg)
+            }
+            ProjectRasterError::Crs(None) => write!(f, "système de coordonnées inconnu"),
+            ProjectRasterError::Resolution {
+                pixel_size,
+                expected,
+            } => write!(
+                f,
+                "pixels de {} x {} m au lieu de {} m",
+                pixel_size.0, pixel_size.1, expected
+            ),
+            ProjectRasterError::PixelGrid { pixel_size, origin } => write!(
+                f,
+                "pixels de {} x {} m d'origine ({}, {}) hors d'une grille de pixels carrés",
+                pixel_size.0, pixel_size.1, origin.0, origin.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProjectRasterError {}
+
+/// Erreur d'ouverture d'un jeu de données (voir `open_vector` et `open_raster`), qui nomme
+/// le fichier et l'usage prévu, par exemple « couche RPG du projet X ».
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatasetError {
+    /// GDAL n'a pas pu ouvrir le fichier.
+    Open {
+        path: String,
+        purpose: String,
+        message: String,
+    },
+    /// Le fichier vectoriel ne contient aucune couche.
+    EmptyDataset { path: String, purpose: String },
+}
+
+impl std::fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatasetError::Open {
+                path,
+                purpose,
+                message,
+            } => write!(f, "Impossible d'ouvrir {} ({}): {}", path, purpose, message),
+            DatasetError::EmptyDataset { path, purpose } => {
+                write!(f, "{} ({}) ne contient aucune couche", path, purpose)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+fn open_dataset<P: AsRef<Path>>(
+    path: P,
+    purpose: &str,
+    open_flags: GdalOpenFlags,
+) -> Result<Dataset, DatasetError> {
+    let path = path.as_ref();
+    Dataset::open_ex(
+        path,
+        DatasetOptions {
+            open_flags,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| DatasetError::Open {
+        path: path.display().to_string(),
+        purpose: purpose.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Ouvre un fichier vectoriel et vérifie qu'il contient au moins une couche.
+///
+/// # Arguments
+///
+/// * `path` - chemin du fichier (GeoPackage, shapefile...)
+/// * `purpose` - usage du fichier, repris dans le message d'erreur
+///
+/// # Returns
+///
+/// * `Result<Dataset, DatasetError>` - le jeu de données, ou une erreur nommant le fichier et son usage
+pub fn open_vector<P: AsRef<Path>>(path: P, purpose: &str) -> Result<Dataset, DatasetError> {
+    let dataset = open_dataset(&path, purpose, GdalOpenFlags::GDAL_OF_VECTOR)?;
+    if dataset.layer_count() == 0 {
+        return Err(DatasetError::EmptyDataset {
+            path: path.as_ref().display().to_string(),
+            purpose: purpose.to_string(),
+        });
+    }
+    Ok(dataset)
+}
+
+/// Ouvre un raster en lecture seule.
+///
+/// # Arguments
+///
+/// * `path` - chemin du raster
+/// * `purpose` - usage du raster, repris dans le message d'erreur
+///
+/// # Returns
+///
+/// * `Result<Dataset, DatasetError>` - le jeu de données, ou une erreur nommant le fichier et son usage
+pub fn open_raster<P: AsRef<Path>>(path: P, purpose: &str) -> Result<Dataset, DatasetError> {
+    open_dataset(path, purpose, GdalOpenFlags::GDAL_OF_RASTER)
+}
+
+/// Ouvre un raster en écriture, comme `open_raster`.
+pub fn open_raster_for_update<P: AsRef<Path>>(
+    path: P,
+    purpose: &str,
+) -> Result<Dataset, DatasetError> {
+    open_dataset(
+        path,
+        purpose,
+        GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+    )
+}
+
+/// Vérifie qu'un raster a la structure d'un projet : 4 bandes en octets, un système de
+/// coordonnées de projet (voir `utils::is_project_epsg`) et des pixels carrés, sans rotation,
+/// dont l'origine est sur la grille des pixels. La résolution est celle du fichier : un projet
+/// créé avec une autre résolution que celle des paramètres actuels reste lisible.
+///
+/// # Arguments
+///
+/// * `dataset` - raster à vérifier
+///
+/// # Returns
+///
+/// * `Result<f64, ProjectRasterError>` - la résolution du raster en mètres par pixel, ou
+///   l'écart constaté si ce n'est pas un raster de projet
+pub fn validate_project_raster(dataset: &Dataset) -> Result<f64, ProjectRasterError> {
+    let count = dataset.raster_count();
+    if count != PROJECT_BAND_COUNT {
+        return Err(ProjectRasterError::BandCount(count));
+    }
+    for band in 1..=count {
+        let band_type = dataset
+            .rasterband(band)
+            .map(|raster_band| raster_band.band_type())
+            .map_err(|_| ProjectRasterError::BandCount(band - 1))?;
+        if band_type != GdalDataType::UInt8 {
+            return Err(ProjectRasterError::BandType {
+                band,
+                data_type: band_type.name(),
+            });
+        }
+    }
+
+    let epsg = SpatialRef::from_wkt(&dataset.projection())
+        .and_then(|srs| srs.auth_code())
+        .ok();
+    if !epsg.is_some_and(|code| u32::try_from(code).is_ok_and(is_project_epsg)) {
+        return Err(ProjectRasterError::Crs(epsg));
+    }
+
+    let geo_transform = dataset
+        .geo_transform()
+        .map_err(|_| ProjectRasterError::Crs(epsg))?;
+    let pixel_size = (geo_transform[1], -geo_transform[5]);
+    let origin = (geo_transform[0], geo_transform[3]);
+    let off_grid = |coordinate: f64| {
+        let cells = coordinate / pixel_size.0;
+        (cells - cells.round()).abs() * pixel_size.0 > RESOLUTION_TOLERANCE_M
+    };
+    if pixel_size.0 <= 0.0
+        || (pixel_size.0 - pixel_size.1).abs() > RESOLUTION_TOLERANCE_M
+        || geo_transform[2] != 0.0
+        || geo_transform[4] != 0.0
+        || off_grid(origin.0)
+        || off_grid(origin.1)
+    {
+        return Err(ProjectRasterError::PixelGrid { pixel_size, origin });
+    }
+    Ok(pixel_size.0)
+}
+
+/// Vérifie qu'un raster a la structure d'un projet, comme `validate_project_raster`,
+/// et que ses pixels ont la résolution donnée (celle du `RunConfig` d'une création en cours).
+///
+/// # Arguments
+///
+/// * `dataset` - raster à vérifier
+/// * `expected` - résolution attendue, en mètres par pixel
+///
+/// # Returns
+///
+/// * `Result<(), ProjectRasterError>` - l'écart constaté si ce n'est pas un raster de projet
+pub fn validate_project_raster_at(
+    dataset: &Dataset,
+    expected: f64,
+) -> Result<(), ProjectRasterError> {
+    let resolution = validate_project_raster(dataset)?;
+    if (resolution - expected).abs() > RESOLUTION_TOLERANCE_M {
+        return Err(ProjectRasterError::Resolution {
+            pixel_size: (resolution, resolution),
+            expected,
+        });
+    }
+    Ok(())
+}
+
+/// Raster d'un projet (`<nom>.tiff`), vérifié à l'ouverture (voir `validate_project_raster`).
+/// Les bandes ne sont lues et écrites qu'au travers de ses accesseurs, qui résolvent les
+/// bandes RGB et alpha d'après leur interprétation.
+///
+/// Pendant l'ajout des couches, `add_layers` n'ouvre le projet qu'une fois : chaque couche
+/// écrit ses bandes en place, les écritures sont vidées sur le disque après chaque couche
+/// (`flush`) et le fichier n'est fermé qu'à la fin. Un échec sur une couche laisse ainsi
+/// un projet lisible, avec les couches déjà appliquées.
+pub struct ProjectRaster {
+    path: String,
+    dataset: Dataset,
+    rgb: [usize; 3],
+    alpha: usize,
+}
+
+impl ProjectRaster {
+    /// Ouvre un fichier projet en écriture, à la résolution de ses propres pixels.
+    pub fn open(project_file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::open_with(
+            project_file_path,
+            GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+            None,
+        )
+    }
+
+    /// Ouvre en écriture un fichier projet dont les pixels doivent avoir la résolution
+    /// donnée (celle du `RunConfig` d'une création en cours).
+    pub fn open_at(
+        project_file_path: &str,
+        resolution: f64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::open_with(
+            project_file_path,
+            GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+            Some(resolution),
+        )
+    }
+
+    /// Ouvre un fichier projet en lecture seule (export, tuiles, copie), à la résolution de
+    /// ses propres pixels.
+    pub fn open_read_only(project_file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::open_with(project_file_path, GdalOpenFlags::GDAL_OF_RASTER, None)
+    }
+
+    fn open_with(
+        project_file_path: &str,
+        open_flags: GdalOpenFlags,
+        resolution: Option<f64>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let dataset = open_dataset(project_file_path, "fichier projet", open_flags)?;
+        match resolution {
+            Some(expected) => validate_project_raster_at(&dataset, expected),
+            None => validate_project_raster(&dataset).map(|_| ()),
+        }
+        .map_err(|e| format!("{}: {}", project_file_path, e))?;
+        let (rgb, alpha) = rgba_band_indices(&dataset)?;
+        Ok(ProjectRaster {
+            path: project_file_path.to_string(),
+            dataset,
+            rgb,
+            alpha: alpha
+                .ok_or_else(|| format!("{}: bande alpha introuvable", project_file_path))?,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn dataset(&self) -> &Dataset {
+        &self.dataset
+    }
+
+    /// Nom du projet, tiré du nom du fichier, repris dans les messages d'erreur.
+    pub fn name(&self) -> String {
+        Path::new(&self.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.clone())
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        self.dataset.raster_size()
+    }
+
+    /// Bandes rouge, verte et bleue.
+    pub fn rgb_bands(&self) -> Result<[RasterBand<'_>; 3], gdal::errors::GdalError> {
+        Ok([
+            self.dataset.rasterband(self.rgb[0])?,
+            self.dataset.rasterband(self.rgb[1])?,
+            self.dataset.rasterband(self.rgb[2])?,
+        ])
+    }
+
+    /// Bande alpha : 255 sur le territoire, 0 pour les pixels sans données.
+    pub fn alpha_band(&self) -> Result<RasterBand<'_>, gdal::errors::GdalError> {
+        self.dataset.rasterband(self.alpha)
+    }
+
+    /// Lit les bandes rouge, verte et bleue d'une fenêtre.
+    pub fn read_rgb_stripe(
+        &self,
+        window: RasterWindow,
+    ) -> Result<[Vec<u8>; 3], gdal::errors::GdalError> {
+        let [red, green, blue] = self.rgb_bands()?;
+        Ok([
+            read_window(&red, window)?,
+            read_window(&green, window)?,
+            read_window(&blue, window)?,
+        ])
+    }
+
+    /// Écrit les bandes rouge, verte et bleue d'une fenêtre.
+    pub fn write_rgb_stripe(
+        &self,
+        window: RasterWindow,
+        data: [Vec<u8>; 3],
+    ) -> Result<(), gdal::errors::GdalError> {
+        for (mut band, data) in self.rgb_bands()?.into_iter().zip(data) {
+            band.write(
+                window.offset(),
+                window.size(),
+                &mut gdal::raster::Buffer::new(window.size(), data),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Lit la bande alpha d'une fenêtre.
+    pub fn read_alpha_stripe(
+        &self,
+        window: RasterWindow,
+    ) -> Result<Vec<u8>, gdal::errors::GdalError> {
+        read_window(&self.alpha_band()?, window)
+    }
+
+    /// Écrit la bande alpha d'une fenêtre.
+    pub fn write_alpha_stripe(
+        &self,
+        window: RasterWindow,
+        data: Vec<u8>,
+    ) -> Result<(), gdal::errors::GdalError> {
+        self.alpha_band()?.write(
+            window.offset(),
+            window.size(),
+            &mut gdal::raster::Buffer::new(window.size(), data),
+        )
+    }
+
+    /// Vide sur le disque les écritures en attente.
+    pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.dataset.flush_cache()?;
+        Ok(())
+    }
+
+    /// Ferme le projet, en écrivant les données en attente.
+    pub fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        self.dataset.close()?;
+        Ok(())
+    }
+}
+
+fn read_window(
+    band: &RasterBand,
+    window: RasterWindow,
+) -> Result<Vec<u8>, gdal::errors::GdalError> {
+    Ok(band
+        .read_as::<u8>(window.offset(), window.size(), window.size(), None)?
+        .data()
+        .to_vec())
+}
+
+/// Lit une bande entière d'un raster en octets.
+pub fn read_band(
+    dataset: &Dataset,
+    band_index: usize,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (width, height) = dataset.raster_size();
+    Ok(dataset
+        .rasterband(band_index)?
+        .read_as::<u8>((0, 0), (width, height), (width, height), None)?
+        .data()
+        .to_vec())
+}
+
+/// Écrit une bande entière d'un raster.
+pub fn write_band(
+    dataset: &Dataset,
+    band_index: usize,
+    data: Vec<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = dataset.raster_size();
+    dataset.rasterband(band_index)?.write(
+        (0, 0),
+        (width, height),
+        &mut gdal::raster::Buffer::new((width, height), data),
+    )?;
+    Ok(())
+}
 
 /// Convertit une couche vectorielle en raster en utilisant gdal_rasterize
 ///
@@ -8,7 +504,7 @@ use gdal::{Dataset, DriverManager};
 ///
 /// * `project` - dataset du projet
 /// * `vector_gpkg` - chemin du fichier GeoPackage contenant la couche vectorielle
-/// * `layer_name` - nom de la couche à rasteriser
+/// * `layer_names` - noms des couches à rastériser, brûlées avec les mêmes paramètres
 /// * `output_raster` - chemin du fichier raster de sortie
 /// * `burn_values` - valeurs à appliquer pour chaque bande (RGB)
 /// * `where_clause` - clause WHERE SQL optionnelle pour filtrer les entités
@@ -20,173 +516,670 @@ use gdal::{Dataset, DriverManager};
 pub fn rasterize_layer(
     project: &Dataset,
     vector_gpkg: &str,
-    layer_name: &str,
+    layer_names: &[String],
     output_raster: &str,
-    burn_values: [&str; 3],
+    burn_values: Rgb,
     where_clause: Option<&str>,
     additional_args: Option<Vec<&str>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let geo_transform = project.geo_transform()?;
-    let (width, height) = project.raster_size();
-
-    let xmin = geo_transform[0].to_string();
-    let ymin = (geo_transform[3] + geo_transform[5] * height as f64).to_string();
-    let xmax = (geo_transform[0] + geo_transform[1] * width as f64).to_string();
-    let ymax = geo_transform[3].to_string();
-
-    let (arg_width, arg_height) = (&width.to_string(), &height.to_string());
-    let mut args = vec![
-        "-burn",
-        burn_values[0],
-        "-burn",
-        burn_values[1],
-        "-burn",
-        burn_values[2],
-        "-l",
-        layer_name,
-        "-ts",
-        arg_width,
-        arg_height,
-        "-te",
-        &xmin,
-        &ymin,
-        &xmax,
-        &ymax,
-    ];
+    rasterize_layer_on_grid(
+        &project.geo_transform()?,
+        project.raster_size(),
+        vector_gpkg,
+        layer_names,
+        output_raster,
+        burn_values,
+        where_clause,
+        additional_args,
+    )
+}
 
+/// Options gdal_rasterize de `rasterize_layer_on_grid`, hors arguments supplémentaires et
+/// chemins. Aucune option de configuration n'est passée : ces options se comportent de la
+/// même façon pour toutes les versions de GDAL prises en charge (voir `gdal_version`).
+///
+/// # Arguments
+///
+/// * `extent` - emprise de la sortie
+/// * `size` - dimensions de la sortie en pixels
+/// * `burn_values` - valeurs à appliquer pour chaque bande (RGB)
+/// * `layer_names` - noms des couches à rastériser
+/// * `where_clause` - clause WHERE SQL optionnelle pour filtrer les entités
+///
+/// # Returns
+///
+/// * `Vec<String>` - les arguments de la commande gdal_rasterize
+pub fn rasterize_args(
+    extent: &BoundingBox,
+    (width, height): (usize, usize),
+    burn_values: Rgb,
+    layer_names: &[String],
+    where_clause: Option<&str>,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    for value in burn_values {
+        args.push("-burn".to_string());
+        args.push(value.to_string());
+    }
+    args.extend([
+        "-ts".to_string(),
+        width.to_string(),
+        height.to_string(),
+        "-te".to_string(),
+        extent.xmin.to_string(),
+        extent.ymin.to_string(),
+        extent.xmax.to_string(),
+        extent.ymax.to_string(),
+    ]);
+    // gdal_rasterize accepte plusieurs `-l` : toutes les couches sont brûlées dans la même sortie.
+    for layer_name in layer_names {
+        args.push("-l".to_string());
+        args.push(layer_name.clone());
+    }
     if let Some(clause) = where_clause {
-        args.push("-where");
-        args.push(clause);
+        args.push("-where".to_string());
+        args.push(clause.to_string());
     }
+    args
+}
+
+/// Variante de `rasterize_layer` alignée sur une grille donnée plutôt que sur un dataset :
+/// un `Dataset` ne pouvant être partagé entre fils, elle permet de lancer plusieurs
+/// rastérisations d'un même projet en parallèle.
+///
+/// # Arguments
+///
+/// * `geo_transform` - géotransformation du projet
+/// * `raster_size` - dimensions du projet en pixels
+/// * autres arguments - voir `rasterize_layer`
+#[allow(clippy::too_many_arguments)]
+pub fn rasterize_layer_on_grid(
+    geo_transform: &[f64; 6],
+    raster_size: (usize, usize),
+    vector_gpkg: &str,
+    layer_names: &[String],
+    output_raster: &str,
+    burn_values: Rgb,
+    where_clause: Option<&str>,
+    additional_args: Option<Vec<&str>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _timer = processing_timer("rasterize");
+    let extent = BoundingBox::from_geo_transform(geo_transform, raster_size);
+    let size = extent.raster_size(geo_transform[1])?;
 
+    let mut args = rasterize_args(&extent, size, burn_values, layer_names, where_clause);
     if let Some(extra_args) = additional_args {
-        args.extend(extra_args);
+        args.extend(extra_args.iter().map(|arg| arg.to_string()));
     }
+    args.push(vector_gpkg.to_string());
+    args.push(output_raster.to_string());
 
-    args.push(vector_gpkg);
-    args.push(output_raster);
-
-    let status = Command::new("gdal_rasterize").args(args).status()?;
+    let status = Command::new("gdal_rasterize")
+        .args(args)
+        .status()
+        .inspect_err(|e| {
+            tracing::error!(
+                layers = ?layer_names,
+                file = vector_gpkg,
+                error = %e,
+                "Lancement de gdal_rasterize impossible"
+            );
+        })?;
 
     if !status.success() {
+        tracing::error!(
+            layers = ?layer_names,
+            file = vector_gpkg,
+            status = %status,
+            "gdal_rasterize a échoué"
+        );
         return Err("gdal_rasterize failed".into());
     }
 
     Ok(())
 }
 
+/// Options de création GTiff du raster d'un projet, d'après la compression des paramètres
+/// (`image_quality.tiff_compression`).
+///
+/// # Arguments
+///
+/// * `run_config` - les paramètres de l'opération
+///
+/// # Returns
+///
+/// * `Result<RasterCreationOptions, Box<dyn std::error::Error>>` - les options à passer à
+///   `create_with_band_type_with_options`
+pub fn project_creation_options(
+    run_config: &RunConfig,
+) -> Result<RasterCreationOptions, Box<dyn std::error::Error>> {
+    let mut options = RasterCreationOptions::new();
+    for (key, value) in run_config
+        .image_quality
+        .tiff_compression
+        .creation_options(run_config.veget_jpeg_quality())
+    {
+        options.set_name_value(key, &value)?;
+    }
+    Ok(options)
+}
+
+/// Déclare l'interprétation des couleurs des bandes d'un raster RGBA (rouge, vert, bleu, alpha),
+/// pour que les outils d'export (ImageMagick, visionneuses) ne confondent pas la quatrième
+/// bande avec une composante CMJN et la traitent comme de la transparence.
+///
+/// # Arguments
+///
+/// * `dataset` - raster à 4 bandes
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si les bandes ont été marquées
+pub fn mark_rgba_bands(dataset: &Dataset) -> Result<(), Box<dyn std::error::Error>> {
+    let interpretations = [
+        ColorInterpretation::RedBand,
+        ColorInterpretation::GreenBand,
+        ColorInterpretation::BlueBand,
+        ColorInterpretation::AlphaBand,
+    ];
+    for (band_idx, interpretation) in interpretations.into_iter().enumerate() {
+        dataset
+            .rasterband(band_idx + 1)?
+            .set_color_interpretation(interpretation)?;
+    }
+    Ok(())
+}
+
+/// Bandes rouge, verte, bleue et alpha d'un raster, d'après l'interprétation des couleurs
+/// déclarée. À défaut d'interprétation RGB complète, les bandes 1 à 3 sont utilisées ; à défaut
+/// de bande alpha déclarée, la première autre bande d'un raster d'au moins 4 bandes.
+///
+/// # Arguments
+///
+/// * `dataset` - raster à lire
+///
+/// # Returns
+///
+/// * `Result<([usize; 3], Option<usize>), gdal::errors::GdalError>` - les indices des bandes RGB et de l'alpha
+pub fn rgba_band_indices(
+    dataset: &Dataset,
+) -> Result<([usize; 3], Option<usize>), gdal::errors::GdalError> {
+    let count = dataset.raster_count();
+    let mut rgb = [None; 3];
+    let mut alpha = None;
+    for band_idx in 1..=count {
+        match dataset.rasterband(band_idx)?.color_interpretation() {
+            ColorInterpretation::RedBand => rgb[0] = rgb[0].or(Some(band_idx)),
+            ColorInterpretation::GreenBand => rgb[1] = rgb[1].or(Some(band_idx)),
+            ColorInterpretation::BlueBand => rgb[2] = rgb[2].or(Some(band_idx)),
+            ColorInterpretation::AlphaBand => alpha = alpha.or(Some(band_idx)),
+            _ => {}
+        }
+    }
+
+    let rgb = match rgb {
+        [Some(red), Some(green), Some(blue)] => [red, green, blue],
+        _ => [1, 2, 3],
+    };
+    let alpha = alpha
+        .filter(|band_idx| !rgb.contains(band_idx))
+        .or_else(|| {
+            if count >= 4 {
+                (1..=count).find(|band_idx| !rgb.contains(band_idx))
+            } else {
+                None
+            }
+        });
+    Ok((rgb, alpha))
+}
+
+/// Écrit la bande alpha du projet à partir d'un raster de masque : les pixels où la
+/// première bande du masque est non nulle restent opaques (255), les autres, sans
+/// données (mer, territoire étranger), deviennent transparents (0).
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+/// * `mask_raster_path` - chemin du raster de masque, aligné sur le projet
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'écriture a réussi ou échoué
+pub fn apply_alpha_mask(
+    project_file_path: &str,
+    mask_raster_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project = ProjectRaster::open(project_file_path)?;
+    write_alpha_mask(&project, mask_raster_path)?;
+    project.close()
+}
+
+/// Variante de `apply_alpha_mask` écrivant dans un projet déjà ouvert.
+///
+/// # Arguments
+///
+/// * `project` - projet ouvert en écriture
+/// * `mask_raster_path` - chemin du raster de masque, aligné sur le projet
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'écriture a réussi ou échoué
+pub fn write_alpha_mask(
+    project: &ProjectRaster,
+    mask_raster_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mask_raster = open_raster(
+        mask_raster_path,
+        &format!("masque du projet {}", project.name()),
+    )?;
+    if mask_raster.raster_size() != project.size() {
+        return Err("Le masque ne correspond pas à la taille du projet".into());
+    }
+
+    let alpha: Vec<u8> = read_band(&mask_raster, 1)?
+        .iter()
+        .map(|&value| if value > 0 { 255 } else { 0 })
+        .collect();
+
+    project.write_alpha_stripe(RasterWindow::full(project.size()), alpha)?;
+    mark_rgba_bands(project.dataset())?;
+
+    Ok(())
+}
+
+/// Chemin du raster de classes associé à un fichier projet (`<nom>_CLASSES.tif`).
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+///
+/// # Returns
+///
+/// * `PathBuf` - le chemin du raster de classes, à côté du fichier projet
+pub fn class_raster_path(project_file_path: &str) -> PathBuf {
+    let path = Path::new(project_file_path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_CLASSES.tif", stem))
+}
+
+/// Crée le raster de classes mono-bande aligné sur le projet, initialisé à `CLASS_UNKNOWN`.
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn std::error::Error>>` - le chemin du raster de classes créé
+pub fn create_class_raster(project_file_path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let project = open_raster(project_file_path, "fichier projet")?;
+    let class_path = class_raster_path(project_file_path);
+    let (width, height) = project.raster_size();
+
+    let driver = DriverManager::get_driver_by_name("GTiff")?;
+    let mut dataset = driver.create_with_band_type::<u8, _>(&class_path, width, height, 1)?;
+    dataset.set_geo_transform(&project.geo_transform()?)?;
+    dataset.set_projection(&project.projection())?;
+    dataset.rasterband(1)?.fill(CLASS_UNKNOWN as f64, None)?;
+
+    Ok(class_path)
+}
+
+/// Écrit des codes de classe dans le raster de classes du projet.
+/// Les pixels dont le code vaut `CLASS_UNKNOWN` sont laissés inchangés, ce qui permet
+/// aux couches successives de se superposer comme sur le raster RGB.
+/// Si le raster de classes n'existe pas, la fonction ne fait rien.
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet
+/// * `codes` - code de classe de chaque pixel
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'écriture a réussi ou échoué
+pub fn burn_classes(
+    project_file_path: &str,
+    codes: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let class_path = class_raster_path(project_file_path);
+    if !class_path.exists() {
+        return Ok(());
+    }
+
+    let dataset = open_raster_for_update(&class_path, "raster de classes")?;
+    let (width, height) = dataset.raster_size();
+    let mut band = dataset.rasterband(1)?;
+    let data: Vec<u8> = band
+        .read_as::<u8>((0, 0), (width, height), (width, height), None)?
+        .data()
+        .iter()
+        .zip(codes.iter())
+        .map(|(&current, &code)| if code == CLASS_UNKNOWN { current } else { code })
+        .collect();
+
+    band.write(
+        (0, 0),
+        (width, height),
+        &mut gdal::raster::Buffer::new((width, height), data),
+    )?;
+
+    Ok(())
+}
+
+/// Raster de superposition lu sur la grille du projet, avec le masque des pixels à appliquer.
+struct Overlay {
+    mask: Vec<bool>,
+    bands: Vec<Vec<u8>>,
+}
+
+impl Overlay {
+    fn read<F>(
+        project: &Dataset,
+        overlay_raster_path: &str,
+        mask_condition: F,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        F: Fn(&u8) -> bool,
+    {
+        let overlay_raster = open_raster(overlay_raster_path, "superposition")?;
+        if overlay_raster.raster_size() != project.raster_size() {
+            return Err("La superposition ne correspond pas à la taille du projet".into());
+        }
+
+        let (width, height) = project.raster_size();
+        let mut mask = vec![false; width * height];
+        let mut bands: Vec<Vec<u8>> = Vec::with_capacity(3);
+        for band_index in 1..=3 {
+            let band_data = read_band(&overlay_raster, band_index)?;
+            for (i, value) in band_data.iter().enumerate() {
+                if mask_condition(value) {
+                    mask[i] = true;
+                }
+            }
+            bands.push(band_data);
+        }
+        overlay_raster.close()?;
+
+        Ok(Overlay { mask, bands })
+    }
+
+    /// Bande RGB du projet après superposition : la valeur de la superposition
+    /// là où le masque est vrai, la valeur d'origine ailleurs.
+    fn merge(&self, band_index: usize, base: Vec<u8>) -> Vec<u8> {
+        base.into_iter()
+            .zip(self.bands[band_index - 1].iter())
+            .zip(self.mask.iter())
+            .map(
+                |((base_value, &overlay_value), &masked)| {
+                    if masked { overlay_value } else { base_value }
+                },
+            )
+            .collect()
+    }
+
+    fn class_codes(&self, class_of: &dyn Fn(Rgb) -> u8) -> Vec<u8> {
+        (0..self.mask.len())
+            .map(|i| {
+                if self.mask[i] {
+                    class_of([self.bands[0][i], self.bands[1][i], self.bands[2][i]])
+                } else {
+                    CLASS_UNKNOWN
+                }
+            })
+            .collect()
+    }
+}
+
+/// Préfixe des GeoTIFF intermédiaires de `apply_overlay`, suivi du nom du projet.
+const OVERLAY_OUTPUT_PREFIX: &str = "overlay_output";
+
+/// Numéro des GeoTIFF intermédiaires de `apply_overlay`, pour des noms uniques.
+static OVERLAY_OUTPUT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Nom du projet, tiré du nom de son fichier.
+fn project_stem(project_file_path: &str) -> String {
+    Path::new(project_file_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// GeoTIFF intermédiaire unique d'une superposition : deux superpositions partageant
+/// le même dossier de travail n'écrivent jamais dans le même fichier.
+fn overlay_output_path(work_dir: &WorkDir, project_file_path: &str) -> PathBuf {
+    work_dir.path_for(format!(
+        "{}_{}_{}_{}.tif",
+        OVERLAY_OUTPUT_PREFIX,
+        project_stem(project_file_path),
+        std::process::id(),
+        OVERLAY_OUTPUT_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
+/// Supprime du dossier de travail les GeoTIFF intermédiaires laissés pour ce projet par une
+/// superposition interrompue (et l'ancien `output.tif`), qui ne doivent jamais être renommés
+/// sur un projet. Les fichiers des autres projets sont conservés.
+fn remove_stale_overlay_outputs(
+    work_dir: &WorkDir,
+    project_file_path: &str,
+) -> std::io::Result<()> {
+    let prefix = format!(
+        "{}_{}_",
+        OVERLAY_OUTPUT_PREFIX,
+        project_stem(project_file_path)
+    );
+    // `<préfixe><pid>_<numéro>.tif` : le projet `a` ne reprend pas les fichiers du projet `a_b`.
+    let is_stale = |name: &str| {
+        name == "output.tif"
+            || name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".tif"))
+                .is_some_and(|rest| {
+                    rest.split('_')
+                        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+                })
+    };
+    for entry in fs::read_dir(work_dir.path())? {
+        let path = entry?.path();
+        if !path
+            .file_name()
+            .is_some_and(|name| is_stale(&name.to_string_lossy()))
+        {
+            continue;
+        }
+        // Une superposition concurrente a pu supprimer le même fichier.
+        let removed = fs::remove_file(&path);
+        if removed
+            .as_ref()
+            .is_err_and(|e| e.kind() != std::io::ErrorKind::NotFound)
+        {
+            return removed;
+        }
+    }
+    Ok(())
+}
+
+/// Écrit sur le disque le contenu d'un fichier, pour qu'un arrêt brutal après son
+/// renommage ne laisse pas un projet incomplet.
+fn sync_file(path: &Path) -> std::io::Result<()> {
+    // Ouvert en écriture : Windows refuse de vider les tampons d'un fichier en lecture seule.
+    fs::OpenOptions::new().write(true).open(path)?.sync_all()
+}
+
 /// Applique une superposition de couches raster sur un projet
 /// Cette fonction est le cœur de la logique de combinaison des données:
 /// - Lecture des données du projet de base et de la couche de superposition
 /// - Création d'un masque pour déterminer où la superposition doit être appliquée
 /// - Pour chaque pixel, si le masque est vrai, utilisation de la valeur de superposition,
 ///   sinon conservation de la valeur originale
-/// - Écriture du résultat dans un nouveau fichier qui remplacera le projet original
+/// - Écriture du résultat dans un fichier intermédiaire unique du dossier de travail,
+///   écrit sur le disque et vérifié (dimensions, bandes) avant de remplacer le projet original
+/// - Si `class_of` est fourni, écriture du code de classe des pixels masqués dans le raster de classes
+///
+/// L'ajout des couches d'un projet utilise `overlay_in_place`, qui écrit dans le projet
+/// déjà ouvert sans recopier le fichier.
 ///
 /// # Arguments
 ///
+/// * `work_dir` - dossier de travail de l'opération, qui reçoit le raster intermédiaire
 /// * `project_file_path` - chemin du fichier projet
 /// * `overlay_raster_path` - chemin du fichier raster de superposition
 /// * `mask_condition` - fonction pour déterminer si un pixel doit être inclus dans le masque
+/// * `class_of` - fonction optionnelle donnant le code de classe d'un pixel à partir de sa couleur
+/// * `run_config` - paramètres de l'opération, dont la compression du raster intermédiaire
 ///
 /// # Returns
 ///
 /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si la superposition a réussi ou échoué
 pub fn apply_overlay<F>(
+    work_dir: &WorkDir,
     project_file_path: &str,
     overlay_raster_path: &str,
     mask_condition: F,
+    class_of: Option<&dyn Fn(Rgb) -> u8>,
+    run_config: &RunConfig,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     F: Fn(&u8) -> bool,
 {
-    let project = Dataset::open(project_file_path)?;
-    let overlay_raster = Dataset::open(overlay_raster_path)?;
+    let _timer = processing_timer("overlay");
+    remove_stale_overlay_outputs(work_dir, project_file_path)?;
+    let project = ProjectRaster::open_read_only(project_file_path)?;
+    let overlay = Overlay::read(project.dataset(), overlay_raster_path, mask_condition)?;
 
-    let output_file = "tmp/output.tif";
+    let output_file = overlay_output_path(work_dir, project_file_path);
     let driver_manager = DriverManager::get_driver_by_name("GTiff")?;
-
-    let mut output_dataset = driver_manager.create(
-        output_file,
-        project.raster_size().0,
-        project.raster_size().1,
+    let (width, height) = project.size();
+    let mut output_dataset = driver_manager.create_with_band_type_with_options::<u8, _>(
+        &output_file,
+        width,
+        height,
         4,
+        &project_creation_options(run_config)?,
     )?;
 
-    output_dataset.set_geo_transform(&project.geo_transform()?)?;
-    output_dataset.set_projection(&project.projection())?;
+    output_dataset.set_geo_transform(&project.dataset().geo_transform()?)?;
+    output_dataset.set_projection(&project.dataset().projection())?;
+    mark_rgba_bands(&output_dataset)?;
 
-    let base_data = [
-        project.rasterband(1)?,
-        project.rasterband(2)?,
-        project.rasterband(3)?,
-        project.rasterband(4)?,
-    ];
+    let window = RasterWindow::full(project.size());
+    let rgb = project.read_rgb_stripe(window)?;
+    for (band_index, base_band_data) in (1..=3).zip(rgb) {
+        let data = overlay.merge(band_index, base_band_data);
+        write_band(&output_dataset, band_index, data)?;
+    }
+    write_band(&output_dataset, 4, project.read_alpha_stripe(window)?)?;
 
-    let overlay_bands = [
-        overlay_raster.rasterband(1)?,
-        overlay_raster.rasterband(2)?,
-        overlay_raster.rasterband(3)?,
-    ];
+    output_dataset.close()?;
+    project.close()?;
 
-    let (width, height) = project.raster_size();
-    let size = width * height;
-    let mut mask = vec![false; size];
-
-    for band in &overlay_bands {
-        let band_data: Vec<u8> = band
-            .read_as::<u8>((0, 0), (width, height), (width, height), None)?
-            .data()
-            .to_vec();
-
-        for (i, value) in band_data.iter().enumerate() {
-            if mask_condition(value) {
-                mask[i] = true;
-            }
-        }
+    sync_file(&output_file)?;
+    let written = Dataset::open(&output_file)?;
+    let written_shape = (written.raster_size(), written.raster_count());
+    drop(written);
+    if written_shape != ((width, height), PROJECT_BAND_COUNT) {
+        let _ = fs::remove_file(&output_file);
+        return Err(format!(
+            "Raster intermédiaire incorrect pour {} : {:?} au lieu de {:?}",
+            project_file_path,
+            written_shape,
+            ((width, height), PROJECT_BAND_COUNT)
+        )
+        .into());
     }
+    fs::rename(&output_file, project_file_path)?;
 
-    for (i, base_band) in base_data.iter().enumerate() {
-        let mut out_band = output_dataset.rasterband(i + 1)?;
-        let base_band_data: Vec<u8> = base_band
-            .read_as::<u8>((0, 0), (width, height), (width, height), None)?
-            .data()
-            .to_vec();
-
-        let data = if i < overlay_bands.len() {
-            let overlay_band_data: Vec<u8> = overlay_bands[i]
-                .read_as::<u8>((0, 0), (width, height), (width, height), None)?
-                .data()
-                .to_vec();
-
-            base_band_data
-                .iter()
-                .zip(overlay_band_data.iter())
-                .zip(mask.iter())
-                .map(|((&base_value, &overlay_value), &mask_value)| {
-                    if mask_value {
-                        overlay_value
-                    } else {
-                        base_value
-                    }
-                })
-                .collect::<Vec<u8>>()
-        } else {
-            base_band_data
-        };
+    if let Some(class_of) = class_of {
+        burn_classes(project_file_path, &overlay.class_codes(class_of))?;
+    }
+
+    Ok(())
+}
+
+/// Applique une superposition de couches raster sur un projet ouvert, comme `apply_overlay`,
+/// mais en écrivant directement les bandes RGB du projet : ni fichier intermédiaire ni
+/// remplacement du fichier projet. La bande alpha n'est pas modifiée.
+///
+/// # Arguments
+///
+/// * `project` - projet ouvert en écriture
+/// * `overlay_raster_path` - chemin du fichier raster de superposition
+/// * `mask_condition` - fonction pour déterminer si un pixel doit être inclus dans le masque
+/// * `class_of` - fonction optionnelle donnant le code de classe d'un pixel à partir de sa couleur
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si la superposition a réussi ou échoué
+pub fn overlay_in_place<F>(
+    project: &ProjectRaster,
+    overlay_raster_path: &str,
+    mask_condition: F,
+    class_of: Option<&dyn Fn(Rgb) -> u8>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn(&u8) -> bool,
+{
+    let _timer = processing_timer("overlay");
+    let overlay = Overlay::read(project.dataset(), overlay_raster_path, mask_condition)?;
 
-        out_band.write(
-            (0, 0),
-            (width, height),
-            &mut gdal::raster::Buffer::new((width, height), data),
-        )?;
+    let window = RasterWindow::full(project.size());
+    let [red, green, blue] = project.read_rgb_stripe(window)?;
+    project.write_rgb_stripe(
+        window,
+        [
+            overlay.merge(1, red),
+            overlay.merge(2, green),
+            overlay.merge(3, blue),
+        ],
+    )?;
+
+    if let Some(class_of) = class_of {
+        burn_classes(project.path(), &overlay.class_codes(class_of))?;
     }
 
-    output_dataset.close().unwrap();
-    overlay_raster.close().unwrap();
-    project.close().unwrap();
+    Ok(())
+}
+
+/// Remplit d'une couleur unie les pixels d'un projet ouvert couverts par un raster de masque
+/// (valeur non nulle sur l'une de ses bandes), comme `overlay_in_place`. La couverture ne
+/// dépend pas de la couleur : le noir est appliqué comme toute autre couleur.
+///
+/// # Arguments
+///
+/// * `project` - projet ouvert en écriture
+/// * `mask_raster_path` - chemin du raster de masque, aligné sur le projet
+/// * `color` - couleur des pixels couverts
+/// * `class` - code de classe des pixels couverts, écrit dans le raster de classes
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si le remplissage a réussi ou échoué
+pub fn fill_in_place(
+    project: &ProjectRaster,
+    mask_raster_path: &str,
+    color: Rgb,
+    class: u8,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _timer = processing_timer("overlay");
+    let mut overlay = Overlay::read(project.dataset(), mask_raster_path, |&value| value > 0)?;
+    let pixels = overlay.mask.len();
+    overlay.bands = color.iter().map(|&value| vec![value; pixels]).collect();
 
-    std::fs::rename(output_file, project_file_path)?;
+    let window = RasterWindow::full(project.size());
+    let [red, green, blue] = project.read_rgb_stripe(window)?;
+    project.write_rgb_stripe(
+        window,
+        [
+            overlay.merge(1, red),
+            overlay.merge(2, green),
+            overlay.merge(3, blue),
+        ],
+    )?;
+    burn_classes(project.path(), &overlay.class_codes(&|_| class))?;
 
     Ok(())
 }
@@ -0,0 +1,671 @@
+//! Projet QGIS (`<nom>.qgs`) référençant les fichiers produits pour un projet : GeoTIFF,
+//! raster de classes, GeoPackages de `resources/` et orthophotographie, stylés avec les
+//! couleurs de `colormap`. Le format `.qgs` est un XML documenté : aucune dépendance à QGIS
+//! n'est nécessaire. Les chemins sont relatifs au fichier, le dossier reste déplaçable.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{LayerAccess, OGRwkbGeometryType};
+
+use super::colormap::{self, CLASS_LAND, CLASS_UNKNOWN, CLASSES, Rgb, load_rpg_classes};
+use super::custom_layers::CUSTOM_LAYERS_DIR;
+use super::layer_config::{LayerConfig, RPG_LAYER, VEGETATION_LAYER, load_layer_config};
+use super::layer_names;
+use super::layers::classify_essences;
+use super::ortho::xml_escape;
+use super::processing::{class_raster_path, open_vector};
+
+use crate::utils::{
+    BoundingBox, ProjectMetadata, get_project_bounding_box, load_project_metadata, project_dir,
+    project_ortho_jpeg, project_tiff,
+};
+
+/// Extension du projet QGIS écrit dans le dossier du projet.
+pub const QGIS_PROJECT_EXTENSION: &str = "qgs";
+
+/// Version de QGIS inscrite dans le projet : les versions 3.x ouvrent le fichier.
+const QGIS_VERSION: &str = "3.34.0-Prizren";
+
+/// Couleur des GeoPackages de `resources/` qui ne correspondent à aucune couche connue.
+const UNSTYLED_COLOR: Rgb = [128, 128, 128];
+
+/// Projet QGIS d'un projet (`<projet>/<projet>.qgs`).
+pub fn qgis_project_path(project_name: &str) -> PathBuf {
+    project_dir(project_name).join(format!("{}.{}", project_name, QGIS_PROJECT_EXTENSION))
+}
+
+/// Rendu d'une couche raster.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RasterStyle {
+    /// Bandes rouge, verte et bleue, avec la bande alpha éventuelle.
+    Rgb { alpha_band: Option<u8> },
+    /// Une couleur et un libellé par valeur de la bande.
+    Paletted(Vec<(u8, Rgb, String)>),
+}
+
+/// Rendu d'une couche vectorielle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorStyle {
+    Single(Rgb),
+    /// Une couleur par valeur de l'attribut (valeur, couleur, libellé), et celle des autres valeurs.
+    Categorized {
+        attribute: String,
+        categories: Vec<(String, Rgb, String)>,
+        other: Rgb,
+    },
+}
+
+/// Type de symbole QGIS d'une couche vectorielle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Fill,
+    Line,
+    Marker,
+}
+
+impl SymbolKind {
+    /// Déduit le symbole du type de géométrie de la première entité de la couche.
+    fn from_geometry_type(geometry_type: Option<OGRwkbGeometryType::Type>) -> Self {
+        match geometry_type {
+            Some(OGRwkbGeometryType::wkbLineString | OGRwkbGeometryType::wkbMultiLineString) => {
+                SymbolKind::Line
+            }
+            Some(OGRwkbGeometryType::wkbPoint | OGRwkbGeometryType::wkbMultiPoint) => {
+                SymbolKind::Marker
+            }
+            _ => SymbolKind::Fill,
+        }
+    }
+
+    fn symbol_type(self) -> &'static str {
+        match self {
+            SymbolKind::Fill => "fill",
+            SymbolKind::Line => "line",
+            SymbolKind::Marker => "marker",
+        }
+    }
+
+    fn geometry(self) -> &'static str {
+        match self {
+            SymbolKind::Fill => "Polygon",
+            SymbolKind::Line => "Line",
+            SymbolKind::Marker => "Point",
+        }
+    }
+}
+
+/// Source et rendu d'une couche du projet QGIS.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QgisLayerKind {
+    Raster(RasterStyle),
+    Vector {
+        /// Couche du GeoPackage.
+        layer_name: String,
+        symbol: SymbolKind,
+        style: VectorStyle,
+    },
+}
+
+/// Couche du projet QGIS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QgisLayer {
+    pub name: String,
+    /// Fichier de la couche, relatif au dossier du projet.
+    pub path: PathBuf,
+    pub kind: QgisLayerKind,
+    /// Couche affichée à l'ouverture du projet.
+    pub visible: bool,
+}
+
+impl QgisLayer {
+    /// Source QGIS de la couche, relative au fichier `.qgs` (`./resources/BATIMENT.gpkg|layername=BATIMENT`).
+    pub fn datasource(&self) -> String {
+        let path = self
+            .path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        match &self.kind {
+            QgisLayerKind::Raster(_) => format!("./{}", path),
+            QgisLayerKind::Vector { layer_name, .. } => {
+                format!("./{}|layername={}", path, layer_name)
+            }
+        }
+    }
+}
+
+/// Relève les couches du projet QGIS, de la plus basse à la plus haute : orthophotographie,
+/// GeoTIFF, raster de classes, puis les GeoPackages de `resources/` dans l'ordre d'application
+/// de `add_layers`. Les fichiers absents sont ignorés.
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet
+///
+/// # Returns
+///
+/// * `Result<Vec<QgisLayer>, Box<dyn Error>>` - les couches, ou une erreur si un GeoPackage est illisible
+pub fn qgis_layers(project_name: &str) -> Result<Vec<QgisLayer>, Box<dyn Error>> {
+    let folder = project_dir(project_name);
+    let metadata = load_project_metadata(project_name);
+    let relative = |path: &Path| path.strip_prefix(&folder).unwrap_or(path).to_path_buf();
+    let mut layers = Vec::new();
+
+    let ortho = project_ortho_jpeg(project_name);
+    if ortho.exists() {
+        layers.push(QgisLayer {
+            name: "Orthophotographie".to_string(),
+            path: relative(&ortho),
+            kind: QgisLayerKind::Raster(RasterStyle::Rgb { alpha_band: None }),
+            visible: false,
+        });
+    }
+    let tiff = project_tiff(project_name);
+    if tiff.exists() {
+        layers.push(QgisLayer {
+            name: project_name.to_string(),
+            path: relative(&tiff),
+            kind: QgisLayerKind::Raster(RasterStyle::Rgb {
+                alpha_band: Some(4),
+            }),
+            visible: true,
+        });
+    }
+    let classes = class_raster_path(&tiff.to_string_lossy());
+    if classes.exists() {
+        layers.push(QgisLayer {
+            name: "Classes".to_string(),
+            path: relative(&classes),
+            kind: QgisLayerKind::Raster(RasterStyle::Paletted(class_palette(&metadata))),
+            visible: false,
+        });
+    }
+
+    let layer_config = load_layer_config()
+        .inspect_err(|e| tracing::warn!(error = %e, "Configuration des couches illisible"))
+        .unwrap_or_default();
+    let mut gpkgs = gpkg_files(&folder.join("resources"))?;
+    gpkgs.sort_by_key(|path| resource_order(project_name, &layer_config, path));
+    gpkgs.extend(gpkg_files(
+        &folder.join("resources").join(CUSTOM_LAYERS_DIR),
+    )?);
+    for gpkg in gpkgs {
+        let stem = gpkg
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let custom = gpkg
+            .parent()
+            .is_some_and(|parent| parent.ends_with(CUSTOM_LAYERS_DIR));
+        let style = if custom {
+            VectorStyle::Single(
+                metadata
+                    .custom_layers
+                    .iter()
+                    .find(|layer| layer.name == stem)
+                    .map_or(UNSTYLED_COLOR, |layer| layer.color),
+            )
+        } else {
+            resource_style(project_name, &metadata, &layer_config, &stem, &gpkg)
+        };
+
+        let dataset = open_vector(&gpkg, &format!("ressource {} du projet QGIS", stem))?;
+        for layer_name in layer_names(&dataset) {
+            let mut layer = dataset.layer_by_name(&layer_name)?;
+            let geometry_type = layer
+                .features()
+                .next()
+                .and_then(|feature| feature.geometry().map(|geometry| geometry.geometry_type()));
+            layers.push(QgisLayer {
+                name: layer_name.clone(),
+                path: relative(&gpkg),
+                kind: QgisLayerKind::Vector {
+                    layer_name,
+                    symbol: SymbolKind::from_geometry_type(geometry_type),
+                    style: style.clone(),
+                },
+                visible: true,
+            });
+        }
+    }
+    Ok(layers)
+}
+
+/// GeoPackages d'un dossier, triés par nom ; aucun si le dossier n'existe pas.
+fn gpkg_files(folder: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !folder.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(folder)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "gpkg"));
+    files.sort();
+    Ok(files)
+}
+
+/// Rang d'une ressource dans l'ordre d'application de `add_layers` : fond départemental,
+/// végétation, RPG, puis les couches BDTOPO par priorité.
+fn resource_order(project_name: &str, layer_config: &LayerConfig, path: &Path) -> (u8, u8) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if stem == project_name {
+        return (0, 0);
+    }
+    if stem == VEGETATION_LAYER {
+        return (1, 0);
+    }
+    if stem == RPG_LAYER {
+        return (2, 0);
+    }
+    let priority = layer_config
+        .layers
+        .iter()
+        .find(|layer| layer.name == stem)
+        .map_or(u8::MAX, |layer| layer.priority);
+    (3, priority)
+}
+
+/// Style d'un GeoPackage de `resources/`, d'après les couleurs appliquées par `add_layers`.
+fn resource_style(
+    project_name: &str,
+    metadata: &ProjectMetadata,
+    layer_config: &LayerConfig,
+    stem: &str,
+    gpkg: &Path,
+) -> VectorStyle {
+    if stem == project_name {
+        return VectorStyle::Single(metadata.project_land_color());
+    }
+    if stem == VEGETATION_LAYER {
+        return match classify_essences(&gpkg.to_string_lossy()) {
+            Ok(classification) => {
+                let mut categories = Vec::new();
+                for (values, color) in [
+                    (&classification.feuillus, colormap::FEUILLUS),
+                    (&classification.undefined, colormap::UNDEFINED_VEGETATION),
+                    (&classification.other, colormap::OTHER_VEGETATION),
+                ] {
+                    categories.extend(
+                        values
+                            .iter()
+                            .map(|value| (value.clone(), color, value.clone())),
+                    );
+                }
+                VectorStyle::Categorized {
+                    attribute: classification.attribute,
+                    categories,
+                    other: colormap::OTHER_VEGETATION,
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Essences illisibles, végétation d'une seule couleur");
+                VectorStyle::Single(colormap::OTHER_VEGETATION)
+            }
+        };
+    }
+    if stem == RPG_LAYER {
+        return match load_rpg_classes() {
+            Ok(table) => VectorStyle::Categorized {
+                categories: table
+                    .classes
+                    .iter()
+                    .flat_map(|class| {
+                        class
+                            .codes
+                            .iter()
+                            .map(|code| (code.clone(), class.color, class.label.clone()))
+                    })
+                    .collect(),
+                attribute: table.attribute,
+                other: colormap::RPG,
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, "Classes RPG illisibles, parcelles d'une seule couleur");
+                VectorStyle::Single(colormap::RPG)
+            }
+        };
+    }
+    let color = layer_config
+        .layers
+        .iter()
+        .find(|layer| layer.name == stem)
+        .map_or(UNSTYLED_COLOR, |layer| layer.color());
+    VectorStyle::Single(color)
+}
+
+/// Palette du raster de classes, avec la couleur du fond départemental du projet.
+fn class_palette(metadata: &ProjectMetadata) -> Vec<(u8, Rgb, String)> {
+    CLASSES
+        .iter()
+        .filter(|entry| entry.code != CLASS_UNKNOWN)
+        .map(|entry| {
+            let color = if entry.code == CLASS_LAND {
+                metadata.project_land_color()
+            } else {
+                entry.color
+            };
+            (entry.code, color, entry.label.to_string())
+        })
+        .collect()
+}
+
+/// Système de coordonnées au format QGIS (`<spatialrefsys>`).
+fn spatialrefsys_xml(epsg: u32) -> String {
+    let spatial_ref = SpatialRef::from_epsg(epsg).ok();
+    let wkt = spatial_ref
+        .as_ref()
+        .and_then(|spatial_ref| spatial_ref.to_wkt().ok())
+        .unwrap_or_default();
+    let proj4 = spatial_ref
+        .as_ref()
+        .and_then(|spatial_ref| spatial_ref.to_proj4().ok())
+        .unwrap_or_default();
+    format!(
+        r#"<spatialrefsys nativeFormat="Wkt">
+        <wkt>{}</wkt>
+        <proj4>{}</proj4>
+        <authid>EPSG:{}</authid>
+        <srid>{}</srid>
+      </spatialrefsys>"#,
+        xml_escape(&wkt),
+        xml_escape(proj4.trim()),
+        epsg,
+        epsg
+    )
+}
+
+fn extent_xml(bb: &BoundingBox) -> String {
+    format!(
+        "<xmin>{}</xmin><ymin>{}</ymin><xmax>{}</xmax><ymax>{}</ymax>",
+        bb.xmin, bb.ymin, bb.xmax, bb.ymax
+    )
+}
+
+fn qgis_color(color: Rgb) -> String {
+    format!("{},{},{},255", color[0], color[1], color[2])
+}
+
+/// Symbole QGIS d'une couleur.
+fn symbol_xml(name: &str, kind: SymbolKind, color: Rgb) -> String {
+    let (class, options) = match kind {
+        SymbolKind::Fill => (
+            "SimpleFill",
+            format!(
+                r#"<Option type="QString" name="color" value="{}"/><Option type="QString" name="outline_style" value="no"/>"#,
+                qgis_color(color)
+            ),
+        ),
+        SymbolKind::Line => (
+            "SimpleLine",
+            format!(
+                r#"<Option type="QString" name="line_color" value="{}"/><Option type="QString" name="line_width" value="0.4"/>"#,
+                qgis_color(color)
+            ),
+        ),
+        SymbolKind::Marker => (
+            "SimpleMarker",
+            format!(
+                r#"<Option type="QString" name="color" value="{}"/><Option type="QString" name="size" value="2"/>"#,
+                qgis_color(color)
+            ),
+        ),
+    };
+    format!(
+        r#"<symbol type="{}" name="{}" alpha="1" clip_to_extent="1" force_rhr="0"><layer class="{}" enabled="1" locked="0" pass="0"><Option type="Map">{}</Option></layer></symbol>"#,
+        kind.symbol_type(),
+        xml_escape(name),
+        class,
+        options
+    )
+}
+
+/// Rendu QGIS (`<renderer-v2>`) d'une couche vectorielle.
+fn renderer_xml(kind: SymbolKind, style: &VectorStyle) -> String {
+    match style {
+        VectorStyle::Single(color) => format!(
+            r#"<renderer-v2 type="singleSymbol" enableorderby="0" forceraster="0" symbollevels="0"><symbols>{}</symbols></renderer-v2>"#,
+            symbol_xml("0", kind, *color)
+        ),
+        VectorStyle::Categorized {
+            attribute,
+            categories,
+            other,
+        } => {
+            let mut category_xml = String::new();
+            let mut symbols_xml = String::new();
+            for (index, (value, color, label)) in categories.iter().enumerate() {
+                category_xml.push_str(&format!(
+                    r#"<category value="{}" symbol="{}" label="{}" render="true"/>"#,
+                    xml_escape(value),
+                    index,
+                    xml_escape(label)
+                ));
+                symbols_xml.push_str(&symbol_xml(&index.to_string(), kind, *color));
+            }
+            // Une catégorie de valeur vide regroupe toutes les autres valeurs.
+            let other_index = categories.len();
+            category_xml.push_str(&format!(
+                r#"<category value="" symbol="{}" label="" render="true"/>"#,
+                other_index
+            ));
+            symbols_xml.push_str(&symbol_xml(&other_index.to_string(), kind, *other));
+            format!(
+                r#"<renderer-v2 type="categorizedSymbol" attr="{}" enableorderby="0" forceraster="0" symbollevels="0"><categories>{}</categories><symbols>{}</symbols></renderer-v2>"#,
+                xml_escape(attribute),
+                category_xml,
+                symbols_xml
+            )
+        }
+    }
+}
+
+/// Rendu QGIS (`<pipe>`) d'une couche raster.
+fn raster_pipe_xml(style: &RasterStyle) -> String {
+    let renderer = match style {
+        RasterStyle::Rgb { alpha_band } => format!(
+            r#"<rasterrenderer type="multibandcolor" redBand="1" greenBand="2" blueBand="3" alphaBand="{}" opacity="1" nodataColor=""/>"#,
+            alpha_band.map_or(-1, i32::from)
+        ),
+        RasterStyle::Paletted(entries) => {
+            let palette: String = entries
+                .iter()
+                .map(|(value, [r, g, b], label)| {
+                    format!(
+                        r##"<paletteEntry value="{}" color="#{:02x}{:02x}{:02x}" alpha="255" label="{}"/>"##,
+                        value,
+                        r,
+                        g,
+                        b,
+                        xml_escape(label)
+                    )
+                })
+                .collect();
+            format!(
+                r#"<rasterrenderer type="paletted" band="1" alphaBand="-1" opacity="1" nodataColor=""><colorPalette>{}</colorPalette></rasterrenderer>"#,
+                palette
+            )
+        }
+    };
+    format!("<pipe>{}</pipe>", renderer)
+}
+
+/// Identifiant QGIS d'une couche, unique dans le projet.
+fn layer_id(index: usize, layer: &QgisLayer) -> String {
+    let name: String = layer
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", name, index)
+}
+
+/// Construit le XML d'un projet QGIS.
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet, titre du projet QGIS
+/// * `layers` - les couches, de la plus basse à la plus haute (voir `qgis_layers`)
+/// * `epsg` - code EPSG du système de coordonnées du projet
+/// * `extent` - emprise du projet, affichée à l'ouverture
+///
+/// # Returns
+///
+/// * `String` - le contenu du fichier `.qgs`
+pub fn qgis_project_xml(
+    project_name: &str,
+    layers: &[QgisLayer],
+    epsg: u32,
+    extent: &BoundingBox,
+) -> String {
+    let crs = spatialrefsys_xml(epsg);
+    let extent = extent_xml(extent);
+    let ids: Vec<String> = layers
+        .iter()
+        .enumerate()
+        .map(|(index, layer)| layer_id(index, layer))
+        .collect();
+
+    // L'arbre des couches liste la couche du dessus en premier.
+    let tree: String = layers
+        .iter()
+        .zip(&ids)
+        .rev()
+        .map(|(layer, id)| {
+            format!(
+                r#"
+    <layer-tree-layer id="{}" name="{}" source="{}" providerKey="{}" checked="{}" expanded="0"/>"#,
+                id,
+                xml_escape(&layer.name),
+                xml_escape(&layer.datasource()),
+                provider(layer),
+                if layer.visible {
+                    "Qt::Checked"
+                } else {
+                    "Qt::Unchecked"
+                }
+            )
+        })
+        .collect();
+
+    let map_layers: String = layers
+        .iter()
+        .zip(&ids)
+        .map(|(layer, id)| {
+            let (layer_type, geometry, style) = match &layer.kind {
+                QgisLayerKind::Raster(style) => ("raster", String::new(), raster_pipe_xml(style)),
+                QgisLayerKind::Vector { symbol, style, .. } => (
+                    "vector",
+                    format!(r#" geometry="{}""#, symbol.geometry()),
+                    renderer_xml(*symbol, style),
+                ),
+            };
+            format!(
+                r#"
+    <maplayer type="{}"{}>
+      <id>{}</id>
+      <datasource>{}</datasource>
+      <layername>{}</layername>
+      <srs>
+      {}
+      </srs>
+      <provider encoding="UTF-8">{}</provider>
+      {}
+    </maplayer>"#,
+                layer_type,
+                geometry,
+                id,
+                xml_escape(&layer.datasource()),
+                xml_escape(&layer.name),
+                crs,
+                provider(layer),
+                style
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE qgis PUBLIC 'http://mrcc.com/qgis.dtd' 'SYSTEM'>
+<qgis projectname="{name}" version="{version}">
+  <homePath path=""/>
+  <title>{name}</title>
+  <projectCrs>
+      {crs}
+  </projectCrs>
+  <layer-tree-group>{tree}
+  </layer-tree-group>
+  <mapcanvas name="theMapCanvas" annotationsVisible="1">
+    <units>meters</units>
+    <extent>{extent}</extent>
+    <rotation>0</rotation>
+    <destinationsrs>
+      {crs}
+    </destinationsrs>
+  </mapcanvas>
+  <projectlayers>{map_layers}
+  </projectlayers>
+  <properties>
+    <Paths>
+      <Absolute type="bool">false</Absolute>
+    </Paths>
+  </properties>
+</qgis>
+"#,
+        name = xml_escape(project_name),
+        version = QGIS_VERSION,
+        crs = crs,
+        tree = tree,
+        extent = extent,
+        map_layers = map_layers,
+    )
+}
+
+fn provider(layer: &QgisLayer) -> &'static str {
+    match layer.kind {
+        QgisLayerKind::Raster(_) => "gdal",
+        QgisLayerKind::Vector { .. } => "ogr",
+    }
+}
+
+/// Écrit le projet QGIS d'un projet (`<projet>/<projet>.qgs`), qui référence ses fichiers
+/// par des chemins relatifs, dans le système de coordonnées du projet et centré sur son emprise.
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - le chemin du projet QGIS écrit
+pub fn generate_qgis_project(project_name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let extent = get_project_bounding_box(project_name)?;
+    let layers = qgis_layers(project_name)?;
+    let epsg = load_project_metadata(project_name).project_epsg();
+    let output_path = qgis_project_path(project_name);
+    fs::write(
+        &output_path,
+        qgis_project_xml(project_name, &layers, epsg, &extent),
+    )?;
+    tracing::info!(
+        project = project_name,
+        layers = layers.len(),
+        path = %output_path.display(),
+        "Projet QGIS écrit"
+    );
+    Ok(output_path)
+}
+
+/// Réécrit le projet QGIS d'un projet renommé ou copié, qui pointe encore vers les fichiers
+/// portant l'ancien nom. Sans projet QGIS, rien n'est écrit ; un échec est seulement journalisé.
+pub fn refresh_qgis_project(project_name: &str) {
+    if !qgis_project_path(project_name).exists() {
+        return;
+    }
+    if let Err(e) = generate_qgis_project(project_name) {
+        tracing::warn!(project = project_name, error = %e, "Projet QGIS non mis à jour");
+    }
+}
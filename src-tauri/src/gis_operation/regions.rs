@@ -1,19 +1,29 @@
 use std::{
-    collections::HashMap,
-    env::current_dir,
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs::{self, File},
     io::Write,
     path::Path,
+    sync::Mutex,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use gdal::vector::Geometry;
 use geojson::GeoJson;
+use lazy_static::lazy_static;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 
-use crate::utils::BoundingBox;
+use crate::utils::{BoundingBox, DEFAULT_PROJECT_EPSG, OVERSEAS_CRS, regions_epsg};
+
+lazy_static! {
+    /// Index des régions partagé, fourni par `setup_check` ou chargé au premier accès.
+    static ref REGIONS_INDEX: Mutex<Option<RegionsIndex>> = Mutex::new(None);
+}
+
+/// Nombre de lectures du fichier du graphe des régions.
+static GRAPH_READS: AtomicUsize = AtomicUsize::new(0);
 
 struct GeometryDef {
     wkt: String,
@@ -118,36 +128,115 @@ impl Region {
     }
 }
 
+/// Fichier GeoJSON des départements dont le graphe des régions est construit.
+pub const REGIONS_GEOJSON_FILE: &str = "resources/regions.geojson";
+
+/// Fichier du graphe des régions généré au démarrage.
+pub const REGIONS_GRAPH_FILE: &str = "resources/regions_graph.json";
+
+/// Contenu du fichier du graphe des régions : les régions et l'empreinte du GeoJSON
+/// dont elles ont été construites.
+#[derive(Serialize, Deserialize)]
+struct RegionsGraphFile {
+    source_hash: String,
+    regions: HashMap<String, Region>,
+}
+
+/// En-tête du fichier du graphe, lu pour vérifier qu'il est à jour sans convertir
+/// les géométries des régions.
+#[derive(Deserialize)]
+struct RegionsGraphHeader {
+    source_hash: String,
+    regions: HashMap<String, RegionNeighbors>,
+}
+
+#[derive(Deserialize)]
+struct RegionNeighbors {
+    neighbors: Vec<String>,
+}
+
+/// Bilan de la construction du graphe des régions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionsGraphReport {
+    /// Nombre de départements du graphe.
+    pub regions: usize,
+    /// Nombre de paires de départements voisins.
+    pub edges: usize,
+    /// Empreinte du GeoJSON dont le graphe est issu.
+    pub source_hash: String,
+    /// Vrai si le graphe a été reconstruit, faux s'il était déjà à jour.
+    pub rebuilt: bool,
+}
+
+/// Empreinte stable (FNV-1a 64 bits) du contenu d'un fichier GeoJSON des départements,
+/// enregistrée avec le graphe pour détecter une modification du GeoJSON.
+pub fn regions_source_hash(content: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in content {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Nombre de paires de départements voisins d'un graphe, chaque paire étant listée
+/// dans les voisins de ses deux départements.
+fn count_edges<'a>(neighbors: impl Iterator<Item = &'a Vec<String>>) -> usize {
+    neighbors.map(Vec::len).sum::<usize>() / 2
+}
+
+/// Bilan du graphe enregistré s'il a été construit à partir d'un GeoJSON d'empreinte
+/// `source_hash`, ou `None` s'il est absent, illisible, d'un ancien format ou périmé.
+fn current_graph_report(output_file: &Path, source_hash: &str) -> Option<RegionsGraphReport> {
+    let json_str = fs::read_to_string(output_file).ok()?;
+    let header: RegionsGraphHeader = serde_json::from_str(&json_str).ok()?;
+    if header.source_hash != source_hash {
+        return None;
+    }
+    Some(RegionsGraphReport {
+        regions: header.regions.len(),
+        edges: count_edges(header.regions.values().map(|region| &region.neighbors)),
+        source_hash: header.source_hash,
+        rebuilt: false,
+    })
+}
+
 /// Construit un graphe de dépendances entre les régions à partir d'un fichier GeoJSON.
-/// Le graphe est sauvegardé dans un fichier JSON pour une utilisation ultérieure.
-/// Si le fichier de sortie existe déjà, il est chargé à partir de ce fichier.
+/// Le graphe est sauvegardé dans un fichier JSON avec l'empreinte du GeoJSON : il n'est
+/// reconstruit que si le GeoJSON a changé depuis, ou si `force` est vrai.
 ///
 /// # Arguments
 ///
+/// * `source` - Le chemin du GeoJSON des départements.
 /// * `output_file` - Le chemin vers le fichier de sortie où le graphe sera sauvegardé.
+/// * `force` - Reconstruire le graphe même s'il est à jour.
 ///
 /// # Returns
 ///
-/// * `Result<bool, Box<dyn Error>>` - Retourne `true` si le graphe a été construit ou chargé avec succès.
-pub fn build_regions_graph(output_file: Option<&str>) -> Result<bool, Box<dyn Error>> {
-    if let Some(path) = &output_file {
-        if Path::new(path).exists() {
-            println!("Loading regions graph from cache file: {}", path);
-            let json_str = fs::read_to_string(path)?;
-            let _: HashMap<String, Region> = serde_json::from_str(&json_str)?;
-            return Ok(true);
-        }
+/// * `Result<RegionsGraphReport, Box<dyn Error>>` - Le nombre de départements et de voisinages
+///   du graphe, et s'il a été reconstruit.
+pub fn build_regions_graph(
+    source: &Path,
+    output_file: &Path,
+    force: bool,
+) -> Result<RegionsGraphReport, Box<dyn Error>> {
+    if !source.exists() {
+        return Err(format!("Input file not found: {}", source.display()).into());
     }
 
-    let binding = current_dir()?.join("resources/regions.geojson");
-    let regional_geojson_path = binding.to_str().unwrap();
-    if !Path::new(regional_geojson_path).exists() {
-        return Err(format!("Input file not found: {}", regional_geojson_path).into());
+    let geojson_str = fs::read_to_string(source)?;
+    let source_hash = regions_source_hash(geojson_str.as_bytes());
+    if !force {
+        if let Some(report) = current_graph_report(output_file, &source_hash) {
+            tracing::debug!(
+                file = %output_file.display(),
+                "Graphe des départements à jour"
+            );
+            return Ok(report);
+        }
     }
 
-    let geojson_str = fs::read_to_string(regional_geojson_path)?;
     let geojson: GeoJson = geojson_str.parse()?;
-
     let feature_collection = match geojson {
         GeoJson::FeatureCollection(fc) => fc,
         _ => return Err("GeoJSON is not a FeatureCollection".into()),
@@ -177,7 +266,11 @@ pub fn build_regions_graph(output_file: Option<&str>) -> Result<bool, Box<dyn Er
         let gdal_geom = match Geometry::from_geojson(&geojson_str) {
             Ok(g) => g,
             Err(e) => {
-                eprintln!("Failed to convert geometry for region {}: {}", code, e);
+                tracing::warn!(
+                    region = %code,
+                    error = %e,
+                    "Conversion de la géométrie du département impossible"
+                );
                 continue;
             }
         };
@@ -217,31 +310,143 @@ pub fn build_regions_graph(output_file: Option<&str>) -> Result<bool, Box<dyn Er
         }
     }
 
-    if let Some(path) = output_file {
-        let json_str = serde_json::to_string_pretty(&regions_info)?;
-        let mut file = File::create(path)?;
-        file.write_all(json_str.as_bytes())?;
-        println!("Regions graph saved to: {}", path);
+    let report = RegionsGraphReport {
+        regions: regions_info.len(),
+        edges: count_edges(regions_info.values().map(|region| &region.neighbors)),
+        source_hash: source_hash.clone(),
+        rebuilt: true,
+    };
+    let graph_file = RegionsGraphFile {
+        source_hash,
+        regions: regions_info,
+    };
+    let json_str = serde_json::to_string_pretty(&graph_file)?;
+    let mut file = File::create(output_file)?;
+    file.write_all(json_str.as_bytes())?;
+    tracing::info!(
+        file = %output_file.display(),
+        regions = report.regions,
+        edges = report.edges,
+        "Graphe des départements enregistré"
+    );
+
+    Ok(report)
+}
+
+/// Charge le graphe des régions depuis le fichier JSON généré au démarrage.
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, Region>, Box<dyn Error>>` - Les régions indexées par code départemental.
+pub fn load_regions_graph() -> Result<HashMap<String, Region>, Box<dyn Error>> {
+    if !Path::new(REGIONS_GRAPH_FILE).exists() {
+        return Err("Regions graph file not found".into());
     }
 
-    Ok(true)
+    GRAPH_READS.fetch_add(1, Ordering::SeqCst);
+    let json_str = fs::read_to_string(REGIONS_GRAPH_FILE)?;
+    let graph: RegionsGraphFile = serde_json::from_str(&json_str)?;
+
+    Ok(graph.regions)
 }
 
-fn load_regions_graph() -> Result<HashMap<String, Region>, Box<dyn Error>> {
-    let graph_path = "resources/regions_graph.json";
+/// Nombre de lectures du fichier du graphe des régions depuis le lancement de l'application.
+pub fn regions_graph_reads() -> usize {
+    GRAPH_READS.load(Ordering::SeqCst)
+}
 
-    if !Path::new(graph_path).exists() {
-        return Err("Regions graph file not found".into());
+/// Graphe des régions chargé en mémoire, avec l'emprise rectangulaire de chaque région
+/// pour écarter rapidement les régions éloignées avant le test d'intersection exact.
+pub struct RegionsIndex {
+    regions: HashMap<String, Region>,
+    envelopes: HashMap<String, BoundingBox>,
+}
+
+// Les géométries OGR ne sont manipulées qu'à travers le verrou de `REGIONS_INDEX` :
+// l'index peut changer de thread mais n'est jamais utilisé par deux threads à la fois.
+unsafe impl Send for RegionsIndex {}
+
+impl RegionsIndex {
+    /// Construit l'index à partir d'un graphe des régions déjà chargé.
+    pub fn from_graph(regions: HashMap<String, Region>) -> Self {
+        let envelopes = regions
+            .iter()
+            .map(|(code, region)| {
+                let envelope = region.extent.envelope();
+                (
+                    code.clone(),
+                    BoundingBox::new(envelope.MinX, envelope.MinY, envelope.MaxX, envelope.MaxY),
+                )
+            })
+            .collect();
+        RegionsIndex { regions, envelopes }
+    }
+
+    /// Charge l'index depuis le fichier du graphe des régions.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_graph(load_regions_graph()?))
+    }
+
+    /// Les régions indexées par code départemental.
+    pub fn regions(&self) -> &HashMap<String, Region> {
+        &self.regions
+    }
+
+    pub fn get(&self, region_id: &str) -> Option<&Region> {
+        self.regions.get(region_id)
+    }
+
+    /// Les régions voisines d'une région, ou `None` si le code est inconnu.
+    pub fn neighbors(&self, region_id: &str) -> Option<Vec<&Region>> {
+        let region = self.regions.get(region_id)?;
+        Some(
+            region
+                .neighbors
+                .iter()
+                .filter_map(|neighbor_code| self.regions.get(neighbor_code))
+                .collect(),
+        )
+    }
+
+    /// Les régions dont la géométrie intersecte la boîte englobante.
+    pub fn find_intersecting(&self, bounding_box: &BoundingBox) -> Vec<&Region> {
+        self.regions
+            .iter()
+            .filter(|(code, _)| {
+                self.envelopes
+                    .get(*code)
+                    .is_none_or(|envelope| envelope.intersects(bounding_box))
+            })
+            .map(|(_, region)| region)
+            .filter(|region| region.intersects(bounding_box))
+            .collect()
     }
+}
 
-    let json_str = fs::read_to_string(graph_path)?;
-    let graph: HashMap<String, Region> = serde_json::from_str(&json_str)?;
+/// Remplace l'index partagé par le graphe construit au démarrage.
+pub fn set_regions_index(graph: HashMap<String, Region>) {
+    *REGIONS_INDEX.lock().unwrap() = Some(RegionsIndex::from_graph(graph));
+}
 
-    Ok(graph)
+/// Exécute une requête sur l'index partagé, chargé depuis le fichier au premier accès.
+///
+/// # Arguments
+///
+/// * `query` - La requête, appelée avec l'index verrouillé.
+///
+/// # Returns
+///
+/// * `Result<T, Box<dyn Error>>` - Le résultat de la requête, ou une erreur si le graphe n'a pas pu être chargé.
+pub fn with_regions_index<T>(query: impl FnOnce(&RegionsIndex) -> T) -> Result<T, Box<dyn Error>> {
+    let mut index = REGIONS_INDEX.lock().unwrap();
+    if index.is_none() {
+        *index = Some(RegionsIndex::load()?);
+    }
+    Ok(query(index.as_ref().unwrap()))
 }
 
 /// Renvoie la liste des régions voisines pour une région donnée
-/// en utilisant le fichier JSON du graphe des régions.
+/// en utilisant l'index partagé du graphe des régions.
 ///
 /// # Arguments
 ///
@@ -251,29 +456,60 @@ fn load_regions_graph() -> Result<HashMap<String, Region>, Box<dyn Error>> {
 ///
 /// * `Result<Vec<Region>, Box<dyn Error>>` - Une liste de `Region` représentant les voisins de la région.
 pub fn get_neighbors(region_id: &str) -> Result<Vec<Region>, Box<dyn Error>> {
-    let graph = load_regions_graph()?;
-
-    if let Some(region_info) = graph.get(region_id) {
-        let neighbors: Vec<Region> = region_info
-            .neighbors
-            .iter()
-            .filter_map(|neighbor_code| graph.get(neighbor_code).cloned())
-            .collect();
-        Ok(neighbors)
-    } else {
-        Err(format!("Region code '{}' not found in the graph", region_id).into())
-    }
+    with_regions_index(|index| {
+        index
+            .neighbors(region_id)
+            .map(|neighbors| neighbors.into_iter().cloned().collect())
+    })?
+    .ok_or_else(|| format!("Region code '{}' not found in the graph", region_id).into())
 }
 
 pub fn get_region(region_id: &str) -> Result<Region, Box<dyn Error>> {
-    let graph = load_regions_graph()?;
-
-    graph
-        .get(region_id)
-        .cloned()
+    with_regions_index(|index| index.get(region_id).cloned())?
         .ok_or_else(|| format!("Region code '{}' not found in the graph", region_id).into())
 }
 
+/// Noms et voisins des départements, extraits une seule fois du graphe des régions
+/// pour être servis au frontend sans relire le fichier JSON.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegionDirectory {
+    names: BTreeMap<String, String>,
+    neighbors: HashMap<String, Vec<String>>,
+}
+
+impl RegionDirectory {
+    /// Construit l'annuaire à partir d'un graphe des régions déjà chargé.
+    pub fn from_graph(graph: &HashMap<String, Region>) -> Self {
+        let mut directory = RegionDirectory::default();
+        for (code, region) in graph {
+            directory.names.insert(code.clone(), region.name.clone());
+            let mut neighbors = region.neighbors.clone();
+            neighbors.sort();
+            directory.neighbors.insert(code.clone(), neighbors);
+        }
+        directory
+    }
+
+    /// Charge l'annuaire depuis l'index partagé du graphe des régions.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RegionDirectory, Box<dyn Error>>` - L'annuaire, ou une erreur si le graphe est absent ou illisible.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        with_regions_index(|index| Self::from_graph(index.regions()))
+    }
+
+    /// Noms des départements, indexés et triés par code.
+    pub fn names(&self) -> &BTreeMap<String, String> {
+        &self.names
+    }
+
+    /// Codes des départements voisins, triés, ou `None` si le code est inconnu.
+    pub fn neighbors(&self, code: &str) -> Option<&[String]> {
+        self.neighbors.get(code).map(Vec::as_slice)
+    }
+}
+
 /// Détermine quelles régions intersectent avec une boîte englobante donnée
 ///
 /// # Arguments
@@ -286,17 +522,54 @@ pub fn get_region(region_id: &str) -> Result<Region, Box<dyn Error>> {
 pub fn find_intersecting_regions(
     bounding_box: &BoundingBox,
 ) -> Result<Vec<Region>, Box<dyn Error>> {
-    let graph = load_regions_graph()?;
-
-    let mut intersecting_regions: Vec<Region> = Vec::new();
+    with_regions_index(|index| {
+        index
+            .find_intersecting(bounding_box)
+            .into_iter()
+            .cloned()
+            .collect()
+    })
+}
 
-    for region in graph.values() {
-        if region.intersects(bounding_box) {
-            intersecting_regions.push(region.clone());
+/// Régions intersectant l'emprise d'un projet et système de coordonnées de ce projet.
+///
+/// Le graphe des régions est en Lambert-93 ; l'emprise, elle, est exprimée dans le système
+/// de ses départements (Lambert-93 en métropole, UTM outre-mer, voir `utils::OVERSEAS_CRS`).
+/// Chaque système est essayé tour à tour : l'emprise y est reprojetée en Lambert-93 et
+/// retenue si ses départements sont bien dans ce système.
+///
+/// # Arguments
+///
+/// * `bounding_box` - L'emprise du projet
+///
+/// # Returns
+///
+/// * `Result<(Vec<Region>, u32), Box<dyn Error>>` - Les régions et le code EPSG du projet,
+///   aucune région si l'emprise ne recoupe aucun département, ou une erreur si les
+///   départements recoupés n'ont pas le même système de coordonnées
+pub fn find_project_regions(
+    bounding_box: &BoundingBox,
+) -> Result<(Vec<Region>, u32), Box<dyn Error>> {
+    let mut candidates = vec![DEFAULT_PROJECT_EPSG];
+    for (_, epsg, _) in OVERSEAS_CRS {
+        if !candidates.contains(&epsg) {
+            candidates.push(epsg);
         }
     }
-
-    Ok(intersecting_regions)
+    for epsg in candidates {
+        let Ok(graph_bb) = bounding_box.reproject(epsg, DEFAULT_PROJECT_EPSG) else {
+            continue;
+        };
+        let regions = find_intersecting_regions(&graph_bb)?;
+        if regions.is_empty() {
+            continue;
+        }
+        let codes: Vec<String> = regions.iter().map(|region| region.code.clone()).collect();
+        if regions_epsg(&codes)? == epsg {
+            return Ok((regions, epsg));
+        }
+    }
+    Ok((Vec::new(), DEFAULT_PROJECT_EPSG))
 }
 
 /// Crée un fichier GeoJSON pour une région donnée
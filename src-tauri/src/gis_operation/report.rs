@@ -0,0 +1,426 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use printpdf::image_crate::codecs::jpeg::JpegDecoder;
+use printpdf::{
+    BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PaintMode, PdfDocument,
+    PdfLayerReference, Point, Polygon, Rgb as PdfRgb, WindingOrder,
+};
+use serde::{Deserialize, Serialize};
+
+use super::colormap::{CLASS_RPG, CLASS_UNKNOWN, CLASSES, Rgb, load_rpg_classes};
+
+use crate::utils::{
+    BoundingBox, crs_label, get_project_bounding_box, load_project_metadata, output_location,
+    project_dir,
+};
+
+/// Dimensions d'une page A4 en millimètres (largeur, hauteur en portrait).
+const A4_MM: (f64, f64) = (210.0, 297.0);
+const MARGIN_MM: f64 = 10.0;
+const TITLE_HEIGHT_MM: f64 = 22.0;
+const FOOTER_HEIGHT_MM: f64 = 38.0;
+const LEGEND_ROW_MM: f64 = 5.0;
+
+/// Image du projet affichée (et exportée) : végétation ou orthophoto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViewMode {
+    Vegetation,
+    Satellite,
+}
+
+impl ViewMode {
+    /// Suffixe de l'image du projet (`<nom>_VEGET.jpeg`, `<nom>_ORTHO.jpeg`).
+    pub fn image_suffix(&self) -> &'static str {
+        match self {
+            ViewMode::Vegetation => "VEGET",
+            ViewMode::Satellite => "ORTHO",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ViewMode::Vegetation => "Carte de végétation",
+            ViewMode::Satellite => "Orthophotographie",
+        }
+    }
+}
+
+/// Orientation de la page, choisie selon les proportions du projet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+impl Orientation {
+    /// Dimensions de la page (largeur, hauteur) en millimètres.
+    pub fn page_size_mm(&self) -> (f64, f64) {
+        match self {
+            Orientation::Portrait => A4_MM,
+            Orientation::Landscape => (A4_MM.1, A4_MM.0),
+        }
+    }
+}
+
+/// Orientation adaptée à une emprise : paysage si elle est plus large que haute.
+pub fn page_orientation(project_bb: &BoundingBox) -> Orientation {
+    if project_bb.width() >= project_bb.height() {
+        Orientation::Landscape
+    } else {
+        Orientation::Portrait
+    }
+}
+
+/// Longueur « ronde » (1, 2 ou 5 × 10^n mètres) de la barre d'échelle,
+/// la plus grande ne dépassant pas `max_length_m`.
+///
+/// # Arguments
+///
+/// * `max_length_m` - longueur maximale au sol, en mètres
+///
+/// # Returns
+///
+/// * `f64` - la longueur de la barre, en mètres
+pub fn scale_bar_length(max_length_m: f64) -> f64 {
+    if max_length_m < 1.0 {
+        return max_length_m;
+    }
+    let magnitude = 10f64.powf(max_length_m.log10().floor());
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|length| *length <= max_length_m)
+        .unwrap_or(magnitude)
+}
+
+fn format_distance(length_m: f64) -> String {
+    if length_m >= 1000.0 {
+        format!("{} km", length_m / 1000.0)
+    } else {
+        format!("{} m", length_m)
+    }
+}
+
+/// Chemin de l'image d'un projet pour une vue donnée.
+pub fn project_image_path(project_name: &str, view: ViewMode) -> PathBuf {
+    project_dir(project_name).join(format!("{}_{}.jpeg", project_name, view.image_suffix()))
+}
+
+/// Exporte une carte imprimable (PDF A4) d'un projet dans le dossier d'export.
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet
+/// * `view` - image du projet à mettre en page
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn std::error::Error>>` - le chemin du PDF créé
+pub fn export_map_pdf(
+    project_name: &str,
+    view: ViewMode,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let project_bb = get_project_bounding_box(project_name)?;
+    let date = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let output_path = output_location().join(format!(
+        "carte_{}_{}_{}.pdf",
+        project_name,
+        view.image_suffix(),
+        date
+    ));
+
+    write_map_pdf(
+        &project_image_path(project_name, view),
+        project_name,
+        &project_bb,
+        view,
+        &output_path,
+    )?;
+    Ok(output_path)
+}
+
+/// Met en page une image de projet dans un PDF : cartouche (nom, date, système de coordonnées),
+/// barre d'échelle, flèche du nord, coordonnées de l'emprise et légende des classes.
+///
+/// # Arguments
+///
+/// * `image_path` - image JPEG du projet
+/// * `project_name` - nom du projet, repris dans le cartouche
+/// * `project_bb` - emprise couverte par l'image
+/// * `view` - nature de l'image (la légende n'accompagne que la carte de végétation)
+/// * `output_path` - chemin du PDF à écrire
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'écriture a réussi ou échoué
+pub fn write_map_pdf(
+    image_path: &Path,
+    project_name: &str,
+    project_bb: &BoundingBox,
+    view: ViewMode,
+    output_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let orientation = page_orientation(project_bb);
+    let (page_width, page_height) = orientation.page_size_mm();
+
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Carte {}", project_name),
+        Mm(page_width as f32),
+        Mm(page_height as f32),
+        "Carte",
+    );
+    let layer = doc.get_page(page).get_layer(layer);
+    let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+
+    // Emprise de la carte, centrée entre le cartouche et le pied de page.
+    let available_width = page_width - 2.0 * MARGIN_MM;
+    let available_height = page_height - 2.0 * MARGIN_MM - TITLE_HEIGHT_MM - FOOTER_HEIGHT_MM;
+    let aspect_ratio = project_bb.width() / project_bb.height();
+    let map_width = available_width.min(available_height * aspect_ratio);
+    let map_height = map_width / aspect_ratio;
+    let map_x = MARGIN_MM + (available_width - map_width) / 2.0;
+    let map_y = MARGIN_MM + FOOTER_HEIGHT_MM + (available_height - map_height) / 2.0;
+
+    let mut image_file = File::open(image_path)?;
+    let image = Image::try_from(JpegDecoder::new(&mut image_file)?)?;
+    let dpi = image.image.width.0 as f64 * 25.4 / map_width;
+    image.add_to_layer(
+        layer.clone(),
+        ImageTransform {
+            translate_x: Some(Mm(map_x as f32)),
+            translate_y: Some(Mm(map_y as f32)),
+            dpi: Some(dpi as f32),
+            ..Default::default()
+        },
+    );
+    draw_frame(&layer, map_x, map_y, map_width, map_height);
+
+    let title_y = page_height - MARGIN_MM;
+    layer.use_text(
+        project_name,
+        18.0,
+        Mm(MARGIN_MM as f32),
+        Mm((title_y - 7.0) as f32),
+        &bold,
+    );
+    layer.use_text(
+        format!(
+            "{} - éditée le {}",
+            view.label(),
+            chrono::Local::now().format("%d/%m/%Y")
+        ),
+        10.0,
+        Mm(MARGIN_MM as f32),
+        Mm((title_y - 13.0) as f32),
+        &font,
+    );
+    layer.use_text(
+        format!(
+            "Système de coordonnées : {}",
+            crs_label(load_project_metadata(project_name).project_epsg())
+        ),
+        10.0,
+        Mm(MARGIN_MM as f32),
+        Mm((title_y - 18.0) as f32),
+        &font,
+    );
+
+    let footer_top = MARGIN_MM + FOOTER_HEIGHT_MM - 4.0;
+    draw_scale_bar(&layer, &font, project_bb, map_width, MARGIN_MM, footer_top);
+    draw_bounding_box(&layer, &font, project_bb, MARGIN_MM, footer_top - 14.0);
+    draw_north_arrow(
+        &layer,
+        &bold,
+        page_width - MARGIN_MM - 8.0,
+        footer_top - 14.0,
+    );
+    if view == ViewMode::Vegetation {
+        draw_legend(
+            &layer,
+            &font,
+            MARGIN_MM + 75.0,
+            footer_top,
+            page_width - MARGIN_MM - 20.0,
+        );
+    }
+
+    doc.save(&mut BufWriter::new(File::create(output_path)?))?;
+    Ok(())
+}
+
+fn pdf_color(color: Rgb) -> Color {
+    Color::Rgb(PdfRgb::new(
+        color[0] as f32 / 255.0,
+        color[1] as f32 / 255.0,
+        color[2] as f32 / 255.0,
+        None,
+    ))
+}
+
+fn point(x: f64, y: f64) -> (Point, bool) {
+    (Point::new(Mm(x as f32), Mm(y as f32)), false)
+}
+
+fn rectangle(x: f64, y: f64, width: f64, height: f64, mode: PaintMode) -> Polygon {
+    Polygon {
+        rings: vec![vec![
+            point(x, y),
+            point(x + width, y),
+            point(x + width, y + height),
+            point(x, y + height),
+        ]],
+        mode,
+        winding_order: WindingOrder::NonZero,
+    }
+}
+
+fn draw_frame(layer: &PdfLayerReference, x: f64, y: f64, width: f64, height: f64) {
+    layer.set_outline_color(pdf_color([0, 0, 0]));
+    layer.set_outline_thickness(0.5);
+    layer.add_polygon(rectangle(x, y, width, height, PaintMode::Stroke));
+}
+
+/// Barre d'échelle en deux segments alternés, d'un quart de la largeur de la carte au plus.
+fn draw_scale_bar(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    project_bb: &BoundingBox,
+    map_width_mm: f64,
+    x: f64,
+    top: f64,
+) {
+    let metres_per_mm = project_bb.width() / map_width_mm;
+    let length_m = scale_bar_length(project_bb.width() / 4.0);
+    let length_mm = length_m / metres_per_mm;
+    let bar_y = top - 8.0;
+
+    layer.use_text(
+        format!("Échelle 1:{:.0}", metres_per_mm * 1000.0),
+        9.0,
+        Mm(x as f32),
+        Mm((top - 3.0) as f32),
+        font,
+    );
+
+    layer.set_outline_color(pdf_color([0, 0, 0]));
+    layer.set_outline_thickness(0.3);
+    for (segment, color) in [[0, 0, 0], [255, 255, 255]].into_iter().enumerate() {
+        layer.set_fill_color(pdf_color(color));
+        layer.add_polygon(rectangle(
+            x + segment as f64 * length_mm / 2.0,
+            bar_y,
+            length_mm / 2.0,
+            2.0,
+            PaintMode::FillStroke,
+        ));
+    }
+
+    layer.set_fill_color(pdf_color([0, 0, 0]));
+    layer.use_text("0", 8.0, Mm(x as f32), Mm((bar_y - 4.0) as f32), font);
+    layer.use_text(
+        format_distance(length_m),
+        8.0,
+        Mm((x + length_mm - 4.0) as f32),
+        Mm((bar_y - 4.0) as f32),
+        font,
+    );
+}
+
+fn draw_bounding_box(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    project_bb: &BoundingBox,
+    x: f64,
+    top: f64,
+) {
+    layer.set_fill_color(pdf_color([0, 0, 0]));
+    for (row, line) in [
+        format!("X : {:.0} - {:.0}", project_bb.xmin, project_bb.xmax),
+        format!("Y : {:.0} - {:.0}", project_bb.ymin, project_bb.ymax),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        layer.use_text(
+            line,
+            8.0,
+            Mm(x as f32),
+            Mm((top - 4.0 * row as f64) as f32),
+            font,
+        );
+    }
+}
+
+fn draw_north_arrow(layer: &PdfLayerReference, font: &IndirectFontRef, center_x: f64, base: f64) {
+    layer.set_fill_color(pdf_color([0, 0, 0]));
+    layer.add_polygon(Polygon {
+        rings: vec![vec![
+            point(center_x, base + 14.0),
+            point(center_x + 4.0, base),
+            point(center_x, base + 3.0),
+            point(center_x - 4.0, base),
+        ]],
+        mode: PaintMode::Fill,
+        winding_order: WindingOrder::NonZero,
+    });
+    layer.add_line(Line {
+        points: vec![point(center_x, base + 3.0), point(center_x, base + 14.0)],
+        is_closed: false,
+    });
+    layer.use_text(
+        "N",
+        10.0,
+        Mm((center_x - 1.8) as f32),
+        Mm((base + 16.0) as f32),
+        font,
+    );
+}
+
+/// Légende des classes de végétation, répartie en colonnes entre `x` et `max_x`.
+fn draw_legend(layer: &PdfLayerReference, font: &IndirectFontRef, x: f64, top: f64, max_x: f64) {
+    // Les parcelles agricoles sont détaillées par classe de combustible lorsque la table est lisible.
+    let rpg_classes = load_rpg_classes()
+        .map(|table| table.classes)
+        .unwrap_or_default();
+    let entries: Vec<(String, Rgb)> = CLASSES
+        .iter()
+        .filter(|entry| entry.code != CLASS_UNKNOWN)
+        .flat_map(|entry| {
+            if entry.code == CLASS_RPG && !rpg_classes.is_empty() {
+                rpg_classes
+                    .iter()
+                    .map(|class| (class.label.clone(), class.color))
+                    .chain([("Autres parcelles agricoles".to_string(), entry.color)])
+                    .collect()
+            } else {
+                vec![(entry.label.to_string(), entry.color)]
+            }
+        })
+        .collect();
+    let rows_per_column = ((FOOTER_HEIGHT_MM - 8.0) / LEGEND_ROW_MM) as usize;
+    let columns = entries.len().div_ceil(rows_per_column);
+    let column_width = (max_x - x) / columns as f64;
+
+    layer.set_outline_color(pdf_color([0, 0, 0]));
+    layer.set_outline_thickness(0.2);
+    for (index, (label, color)) in entries.into_iter().enumerate() {
+        let entry_x = x + (index / rows_per_column) as f64 * column_width;
+        let entry_y = top - 4.0 - (index % rows_per_column) as f64 * LEGEND_ROW_MM;
+
+        layer.set_fill_color(pdf_color(color));
+        layer.add_polygon(rectangle(entry_x, entry_y, 4.0, 3.0, PaintMode::FillStroke));
+        layer.set_fill_color(pdf_color([0, 0, 0]));
+        layer.use_text(
+            label,
+            8.0,
+            Mm((entry_x + 6.0) as f32),
+            Mm((entry_y + 0.5) as f32),
+            font,
+        );
+    }
+}
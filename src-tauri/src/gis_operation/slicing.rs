@@ -1,135 +1,1065 @@
-use crate::utils::{create_directory_if_not_exists, get_project_bounding_box, projects_dir};
-use image::{DynamicImage, GenericImageView};
+use crate::app_setup::RunConfig;
+use crate::utils::{
+    BoundingBox, get_project_bounding_box, project_dir, project_ortho_jpeg, project_veget_jpeg,
+};
+use ab_glyph::{FontRef, PxScale};
+use gdal::Dataset;
+use gdal::raster::ResampleAlg;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageResult, Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fs;
-use std::process::Command;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
 
-pub fn slice_images(project_name: &str, slice_factor: u32) -> Result<(), String> {
-    let projects_dir_path = projects_dir();
-    let project_folder = projects_dir_path.to_str().unwrap();
-    let project_path = format!("{}/{}/", project_folder, project_name);
-    let slice_path = format!("{}/{}/slices/", project_folder, project_name);
+/// Écart-type du filtre de débruitage appliqué aux découpes ORTHO,
+/// équivalent au `-enhance` d'ImageMagick utilisé auparavant.
+const ENHANCE_SIGMA: f32 = 0.6;
 
-    prepare_directories(&slice_path)?;
+/// Nom du fichier d'état du découpage, stocké dans le dossier `slices/`.
+pub const SLICE_STATE_FILE: &str = ".state.json";
 
-    let veget_image_path = format!("{}{}_VEGET.jpeg", project_path, project_name);
-    let ortho_image_path = format!("{}{}_ORTHO.jpeg", project_path, project_name);
+/// Nom du fichier d'index des tuiles, stocké dans le dossier `slices/`.
+pub const SLICE_INDEX_FILE: &str = "index.json";
 
-    let veget_image = load_image(&veget_image_path, "VEGET")?;
-    let ortho_image = load_image(&ortho_image_path, "ORTHO")?;
+/// Modèle par défaut des noms de tuiles : coordonnées kilométriques (dans le système du projet,
+/// Lambert 93 en métropole) du coin sud-ouest sur 5 chiffres, type de tuile et facteur de découpe,
+/// par exemple `01210_06070_veget_500.jpg`.
+pub const DEFAULT_SLICE_NAME_TEMPLATE: &str = "{x:05}_{y:05}_{kind}_{factor}.jpg";
 
-    let project_coordinates = get_project_bounding_box(project_name)?;
-    let (base_x, base_y) =
-        calculate_base_coordinates(project_coordinates.xmin, project_coordinates.ymin);
+/// Plus grand côté, en pixels, de l'aperçu de la grille des tuiles.
+pub const OVERVIEW_MAX_SIZE: u32 = 2000;
 
-    slice_and_process_images(
-        &veget_image,
+/// Couleur et épaisseur (en pixels) des lignes de la grille dans l'aperçu.
+pub const OVERVIEW_GRID_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+pub const OVERVIEW_LINE_WIDTH: u32 = 2;
+
+/// Qualité JPEG de l'aperçu, plus élevée que celle des découpes pour garder les lignes
+/// et les noms lisibles.
+const OVERVIEW_JPEG_QUALITY: u8 = 90;
+
+/// Police des noms de tuiles de l'aperçu (DejaVu Sans Mono, voir `resources/fonts`).
+const OVERVIEW_FONT: &[u8] = include_bytes!("../../resources/fonts/DejaVuSansMono-Bold.ttf");
+
+lazy_static! {
+    /// Champ d'un modèle de nom de tuile : `{nom}` ou `{nom:0N}` pour un nombre sur N chiffres.
+    static ref TEMPLATE_FIELD_REGEX: Regex = Regex::new(r"\{(\w+)(?::0(\d+))?\}").unwrap();
+}
+
+/// Type d'une tuile découpée.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SliceKind {
+    Ortho,
+    Veget,
+}
+
+impl SliceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SliceKind::Ortho => "ortho",
+            SliceKind::Veget => "veget",
+        }
+    }
+}
+
+/// Construit le nom d'une tuile à partir d'un modèle.
+/// Les champs reconnus sont `{x}` et `{y}` (coordonnées en km), `{kind}` (`ortho` ou `veget`)
+/// et `{factor}` ; les champs numériques acceptent un remplissage par des zéros (`{x:05}`).
+///
+/// # Arguments
+///
+/// * `template` - Le modèle de nom, voir `DEFAULT_SLICE_NAME_TEMPLATE`.
+/// * `x` - La coordonnée kilométrique ouest de la tuile.
+/// * `y` - La coordonnée kilométrique sud de la tuile.
+/// * `kind` - Le type de tuile.
+/// * `slice_factor` - La taille des tuiles en pixels.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - Le nom de la tuile, ou une erreur si le modèle contient un
+///   champ inconnu ou ne distingue pas les tuiles (`{x}`, `{y}` et `{kind}` sont requis).
+pub fn slice_file_name(
+    template: &str,
+    x: u32,
+    y: u32,
+    kind: SliceKind,
+    slice_factor: u32,
+) -> Result<String, String> {
+    let mut name = String::new();
+    let mut fields = Vec::new();
+    let mut last = 0;
+    for caps in TEMPLATE_FIELD_REGEX.captures_iter(template) {
+        let field = caps.get(0).unwrap();
+        let key = &caps[1];
+        let width = caps
+            .get(2)
+            .map(|width| width.as_str().parse::<usize>().unwrap_or(0))
+            .unwrap_or(0);
+        let value = match key {
+            "x" => format!("{:0width$}", x, width = width),
+            "y" => format!("{:0width$}", y, width = width),
+            "factor" => format!("{:0width$}", slice_factor, width = width),
+            "kind" if width == 0 => kind.as_str().to_string(),
+            _ => {
+                return Err(format!(
+                    "Unknown field {} in slice name template '{}'",
+                    field.as_str(),
+                    template
+                ));
+            }
+        };
+        name.push_str(&template[last..field.start()]);
+        name.push_str(&value);
+        last = field.end();
+        fields.push(key.to_string());
+    }
+    name.push_str(&template[last..]);
+
+    for required in ["x", "y", "kind"] {
+        if !fields.iter().any(|field| field == required) {
+            return Err(format!(
+                "Slice name template '{}' must contain {{{}}}",
+                template, required
+            ));
+        }
+    }
+    Ok(name)
+}
+
+/// Position et taille d'une tuile dans l'image découpée, en pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PixelWindow {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Tuile de l'index du découpage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SliceIndexEntry {
+    /// Coordonnée kilométrique ouest de la tuile.
+    pub x: u32,
+    /// Coordonnée kilométrique sud de la tuile.
+    pub y: u32,
+    /// Position de la tuile dans les images VEGET et ORTHO ; la fenêtre peut dépasser
+    /// l'image pour les tuiles de bord, complétées en noir.
+    pub window: PixelWindow,
+    /// Emprise de la tuile, dans le système de coordonnées du projet.
+    pub extent: BoundingBox,
+    pub ortho: String,
+    pub veget: String,
+}
+
+/// Index des tuiles d'un projet (`slices/index.json`), écrit à chaque découpage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SliceIndex {
+    pub slice_factor: u32,
+    /// Résolution des images découpées, en mètres par pixel.
+    pub resolution: f64,
+    pub name_template: String,
+    pub tiles: Vec<SliceIndexEntry>,
+}
+
+/// Lit l'index des tuiles d'un projet.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Option<SliceIndex>` - L'index, ou `None` si le projet n'a pas été découpé depuis son introduction.
+pub fn read_slice_index(project_name: &str) -> Option<SliceIndex> {
+    let index_path = project_dir(project_name)
+        .join("slices")
+        .join(SLICE_INDEX_FILE);
+    let content = fs::read_to_string(index_path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Empreinte d'une image source (taille et date de modification).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceFingerprint {
+    pub size: u64,
+    pub modified_ms: u64,
+}
+
+/// État du dernier découpage d'un projet, permettant de ne pas le refaire inutilement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SliceState {
+    pub veget: SourceFingerprint,
+    pub ortho: SourceFingerprint,
+    pub slice_factor: u32,
+    /// Les découpes ORTHO ont été débruitées (voir `enhance_ortho_slices`).
+    pub enhance: bool,
+    /// Modèle des noms de tuiles ; vide pour les découpages antérieurs à son introduction,
+    /// dont les tuiles sont alors toutes refaites.
+    #[serde(default)]
+    pub name_template: String,
+    /// Qualité des découpes JPEG ; 0 pour les découpages antérieurs à son introduction.
+    #[serde(default)]
+    pub jpeg_quality: u8,
+    /// Les aperçus de la grille ont été écrits (voir `slice_overview`).
+    #[serde(default)]
+    pub overview: bool,
+}
+
+/// Découpe les images VEGET et ORTHO d'un projet en tuiles carrées.
+/// Le découpage est incrémental : si les images sources, le facteur de découpe et
+/// l'option d'amélioration sont identiques à ceux du dernier découpage
+/// (voir `slices/.state.json`), les découpes existantes sont conservées.
+/// Si seule l'orthophotographie ou l'option d'amélioration a changé, seules les tuiles
+/// ORTHO sont refaites.
+/// Les tuiles sont nommées selon le modèle `slice_name_template` et listées dans
+/// `slices/index.json`. Avec l'option `slice_overview`, un aperçu de la grille est écrit
+/// pour chaque type de tuile (voir `render_overview`) ; son activation ou sa désactivation
+/// seule ne refait aucune tuile : les aperçus sont écrits d'après l'index existant, ou supprimés.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `run_config` - Les paramètres du découpage : taille des tuiles en pixels, modèle des noms,
+///   qualité, débruitage et aperçus.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Un résultat indiquant si le découpage a réussi ou échoué.
+pub fn slice_images(project_name: &str, run_config: &RunConfig) -> Result<(), String> {
+    let slice_factor = run_config.slice_factor;
+    let slice_path = project_dir(project_name).join("slices");
+    let veget_image_path = project_veget_jpeg(project_name);
+    let ortho_image_path = project_ortho_jpeg(project_name);
+
+    let state = SliceState {
+        veget: fingerprint(&veget_image_path)?,
+        ortho: fingerprint(&ortho_image_path)?,
+        slice_factor,
+        enhance: run_config.enhance_ortho_slices,
+        name_template: run_config.slice_name_template.clone(),
+        jpeg_quality: run_config.slice_jpeg_quality(),
+        overview: run_config.slice_overview,
+    };
+    let state_path = slice_path.join(SLICE_STATE_FILE);
+    let previous_state = read_slice_state(&state_path);
+
+    if previous_state.as_ref() == Some(&state) {
+        return Ok(());
+    }
+
+    let memory_limit_mb = run_config.slice_memory_limit_mb;
+    let overview_only = previous_state.as_ref().is_some_and(|previous| {
+        SliceState {
+            overview: state.overview,
+            ..previous.clone()
+        } == state
+    });
+    if overview_only {
+        if let Some(index) = read_slice_index(project_name) {
+            let (veget_image, ortho_image) = if state.overview {
+                (
+                    Some(TileSource::open(
+                        &veget_image_path,
+                        "VEGET",
+                        memory_limit_mb,
+                    )?),
+                    Some(TileSource::open(
+                        &ortho_image_path,
+                        "ORTHO",
+                        memory_limit_mb,
+                    )?),
+                )
+            } else {
+                (None, None)
+            };
+            update_overviews(
+                &slice_path,
+                &index,
+                veget_image.as_ref(),
+                ortho_image.as_ref(),
+                state.overview,
+            )?;
+            return write_slice_state(&state_path, &state);
+        }
+    }
+
+    let ortho_only = previous_state.is_some_and(|previous| {
+        previous.veget == state.veget
+            && previous.slice_factor == state.slice_factor
+            && previous.name_template == state.name_template
+            && previous.jpeg_quality == state.jpeg_quality
+    });
+
+    // Sans nouvelles tuiles VEGET, l'image VEGET n'est ouverte que pour un aperçu manquant.
+    let veget_overview_missing = state.overview
+        && !slice_path
+            .join(overview_file_name(SliceKind::Veget))
+            .exists();
+    if !ortho_only {
+        prepare_directories(&slice_path)?;
+    }
+    let veget_image = if ortho_only && !veget_overview_missing {
+        None
+    } else {
+        Some(TileSource::open(
+            &veget_image_path,
+            "VEGET",
+            memory_limit_mb,
+        )?)
+    };
+    let ortho_image = TileSource::open(&ortho_image_path, "ORTHO", memory_limit_mb)?;
+
+    let project_bb = get_project_bounding_box(project_name)?;
+
+    let index = slice_and_process_images(
+        veget_image.as_ref().filter(|_| !ortho_only),
         &ortho_image,
         &slice_path,
         slice_factor,
-        base_x,
-        base_y,
+        &project_bb,
+        state.enhance,
+        &state.name_template,
+        state.jpeg_quality,
     )?;
 
+    let index_json = serde_json::to_string_pretty(&index)
+        .map_err(|e| format!("Failed to serialize slice index: {}", e))?;
+    fs::write(slice_path.join(SLICE_INDEX_FILE), index_json)
+        .map_err(|e| format!("Failed to write slice index: {}", e))?;
+
+    update_overviews(
+        &slice_path,
+        &index,
+        veget_image.as_ref(),
+        Some(&ortho_image),
+        state.overview,
+    )?;
+
+    write_slice_state(&state_path, &state)
+}
+
+/// Écrit ou supprime les aperçus de la grille (`overview_veget.jpg`, `overview_ortho.jpg`)
+/// d'un découpage, d'après son index.
+///
+/// # Arguments
+///
+/// * `slice_path` - Le dossier `slices/` du projet.
+/// * `index` - L'index des tuiles du découpage.
+/// * `veget_image` - L'image VEGET ; sans elle, l'aperçu VEGET existant est conservé.
+/// * `ortho_image` - L'image ORTHO ; sans elle, l'aperçu ORTHO existant est conservé.
+/// * `overview` - Les aperçus sont demandés ; sinon ils sont supprimés.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Une erreur si un aperçu ne peut être écrit ou supprimé.
+fn update_overviews(
+    slice_path: &Path,
+    index: &SliceIndex,
+    veget_image: Option<&TileSource>,
+    ortho_image: Option<&TileSource>,
+    overview: bool,
+) -> Result<(), String> {
+    for (kind, image) in [
+        (SliceKind::Veget, veget_image),
+        (SliceKind::Ortho, ortho_image),
+    ] {
+        let overview_path = slice_path.join(overview_file_name(kind));
+        match image {
+            Some(image) if overview => save_slice(
+                &DynamicImage::ImageRgb8(render_overview(image, kind, index)?),
+                &overview_path,
+                OVERVIEW_JPEG_QUALITY,
+            )
+            .map_err(|e| format!("Failed to save {} overview: {}", kind.as_str(), e))?,
+            // Tuiles inchangées : leur aperçu reste valable.
+            None if overview => {}
+            _ => {
+                if overview_path.exists() {
+                    fs::remove_file(&overview_path).map_err(|e| {
+                        format!("Failed to remove {}: {}", overview_path.display(), e)
+                    })?;
+                }
+            }
+        }
+    }
     Ok(())
 }
 
-fn prepare_directories(slice_path: &str) -> Result<(), String> {
-    fs::remove_dir_all(slice_path).map_err(|e| format!("Failed to remove directory: {}", e))?;
-    create_directory_if_not_exists(slice_path)
-        .map_err(|e| format!("Failed to create directory: {}", e))?;
+/// Nom de l'aperçu de la grille des tuiles d'un type, dans le dossier `slices/`.
+pub fn overview_file_name(kind: SliceKind) -> String {
+    format!("overview_{}.jpg", kind.as_str())
+}
+
+/// Dessine l'aperçu de la grille des tuiles : l'image découpée, réduite pour que la grille
+/// tienne dans `OVERVIEW_MAX_SIZE` pixels, avec les lignes de la grille et le nom `<x>_<y>`
+/// de chaque tuile. La grille couvre les tuiles de bord entières, complétées en noir comme
+/// les découpes ; les noms qui ne tiennent pas dans leur tuile ne sont pas écrits.
+///
+/// # Arguments
+///
+/// * `source` - L'image découpée.
+/// * `kind` - Le type de tuile : les images VEGET sont réduites au plus proche voisin pour
+///   garder les couleurs des classes.
+/// * `index` - L'index du découpage, qui donne la taille et les coordonnées des tuiles.
+///
+/// # Returns
+///
+/// * `Result<RgbImage, String>` - L'aperçu, ou une erreur de lecture de l'image.
+pub fn render_overview(
+    source: &TileSource,
+    kind: SliceKind,
+    index: &SliceIndex,
+) -> Result<RgbImage, String> {
+    let (width, height) = source.dimensions();
+    let slice_factor = index.slice_factor;
+    if width == 0 || height == 0 || slice_factor == 0 {
+        return Err("Cannot render the overview of an empty image".to_string());
+    }
+    let grid_width = width.div_ceil(slice_factor) * slice_factor;
+    let grid_height = height.div_ceil(slice_factor) * slice_factor;
+    let scale = (OVERVIEW_MAX_SIZE as f64 / grid_width.max(grid_height) as f64).min(1.0);
+    let scaled = |pixels: u32| (pixels as f64 * scale).round() as u32;
+
+    let mut overview = RgbImage::new(
+        scaled(grid_width).max(OVERVIEW_LINE_WIDTH),
+        scaled(grid_height).max(OVERVIEW_LINE_WIDTH),
+    );
+    let image = source.downscaled(scaled(width).max(1), scaled(height).max(1), kind)?;
+    imageops::replace(&mut overview, &image, 0, 0);
+
+    let (overview_width, overview_height) = overview.dimensions();
+    let line_start = |position: u32, size: u32| position.min(size - OVERVIEW_LINE_WIDTH) as i32;
+    for column in (0..=grid_width).step_by(slice_factor as usize) {
+        let x = line_start(scaled(column), overview_width);
+        let line = Rect::at(x, 0).of_size(OVERVIEW_LINE_WIDTH, overview_height);
+        draw_filled_rect_mut(&mut overview, line, OVERVIEW_GRID_COLOR);
+    }
+    for row in (0..=grid_height).step_by(slice_factor as usize) {
+        let y = line_start(scaled(row), overview_height);
+        let line = Rect::at(0, y).of_size(overview_width, OVERVIEW_LINE_WIDTH);
+        draw_filled_rect_mut(&mut overview, line, OVERVIEW_GRID_COLOR);
+    }
+
+    let font = FontRef::try_from_slice(OVERVIEW_FONT)
+        .map_err(|e| format!("Failed to load the overview font: {}", e))?;
+    let tile_size = scaled(slice_factor);
+    let font_scale = PxScale::from((tile_size as f32 / 10.0).clamp(10.0, 32.0));
+    let padding = 3;
+    for tile in &index.tiles {
+        let label = format!("{}_{}", tile.x, tile.y);
+        let (text_width, text_height) = text_size(font_scale, &font, &label);
+        let label_width = text_width + 2 * padding;
+        let label_height = text_height + 2 * padding;
+        if label_width + 2 * OVERVIEW_LINE_WIDTH > tile_size
+            || label_height + 2 * OVERVIEW_LINE_WIDTH > tile_size
+        {
+            continue;
+        }
+        let x = (scaled(tile.window.x) + 2 * OVERVIEW_LINE_WIDTH) as i32;
+        let y = (scaled(tile.window.y) + 2 * OVERVIEW_LINE_WIDTH) as i32;
+        let background = Rect::at(x, y).of_size(label_width, label_height);
+        draw_filled_rect_mut(&mut overview, background, Rgb([0, 0, 0]));
+        draw_text_mut(
+            &mut overview,
+            Rgb([255, 255, 255]),
+            x + padding as i32,
+            y + padding as i32,
+            font_scale,
+            &font,
+            &label,
+        );
+    }
+    Ok(overview)
+}
+
+/// Marque les tuiles ORTHO d'un projet comme obsolètes, après le remplacement de
+/// son orthophotographie : le prochain découpage ne refera que ces tuiles.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Un résultat indiquant si l'état a pu être mis à jour.
+pub fn invalidate_ortho_slices(project_name: &str) -> Result<(), String> {
+    let state_path = project_dir(project_name)
+        .join("slices")
+        .join(SLICE_STATE_FILE);
+    let Some(mut state) = read_slice_state(&state_path) else {
+        return Ok(());
+    };
+    state.ortho = SourceFingerprint {
+        size: 0,
+        modified_ms: 0,
+    };
+    write_slice_state(&state_path, &state)
+}
+
+fn write_slice_state(state_path: &Path, state: &SliceState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize slice state: {}", e))?;
+    fs::write(state_path, json).map_err(|e| format!("Failed to write slice state: {}", e))
+}
+
+fn fingerprint(image_path: &Path) -> Result<SourceFingerprint, String> {
+    let metadata = fs::metadata(image_path)
+        .map_err(|e| format!("Failed to read {}: {}", image_path.display(), e))?;
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default();
+
+    Ok(SourceFingerprint {
+        size: metadata.len(),
+        modified_ms,
+    })
+}
+
+fn read_slice_state(state_path: &Path) -> Option<SliceState> {
+    let content = fs::read_to_string(state_path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn prepare_directories(slice_path: &Path) -> Result<(), String> {
+    if slice_path.exists() {
+        fs::remove_dir_all(slice_path).map_err(|e| format!("Failed to remove directory: {}", e))?;
+    }
+    fs::create_dir_all(slice_path).map_err(|e| format!("Failed to create directory: {}", e))?;
     Ok(())
 }
 
-fn load_image(image_path: &str, image_type: &str) -> Result<DynamicImage, String> {
-    image::ImageReader::open(image_path)
-        .map_err(|e| format!("Failed to open {} image: {}", image_type, e))?
-        .decode()
-        .map_err(|e| format!("Failed to decode {} image: {}", image_type, e))
+/// Taille en octets d'une image RGB décodée.
+pub fn decoded_size(width: u32, height: u32) -> u64 {
+    width as u64 * height as u64 * 3
 }
 
-fn calculate_base_coordinates(xmin: f64, ymin: f64) -> (u32, u32) {
-    let base_x = (xmin / 1000.0) as u32;
-    let base_y = (ymin / 1000.0) as u32;
-    (base_x, base_y)
+/// Image découpée en tuiles : décodée en mémoire, ou lue par fenêtres avec GDAL
+/// lorsque son décodage dépasserait la limite mémoire du découpage.
+pub enum TileSource {
+    Decoded(DynamicImage),
+    Windowed(Dataset),
 }
 
-fn slice_and_process_images(
-    veget_image: &DynamicImage,
-    ortho_image: &DynamicImage,
-    slice_path: &str,
+impl TileSource {
+    /// Ouvre une image à découper. Seul son en-tête est lu pour estimer la mémoire
+    /// nécessaire à son décodage ; au-delà de `memory_limit_mb`, elle est lue par fenêtres.
+    ///
+    /// # Arguments
+    ///
+    /// * `image_path` - Le chemin de l'image, lisible par GDAL (JPEG avec world file, GeoTIFF...).
+    /// * `image_type` - Le type d'image (`VEGET`, `ORTHO`), repris dans les erreurs.
+    /// * `memory_limit_mb` - La mémoire maximale de l'image décodée, en Mo.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TileSource, String>` - L'image, ou une erreur indiquant sa taille et la limite
+    ///   si elle ne peut pas être lue par fenêtres.
+    pub fn open(image_path: &Path, image_type: &str, memory_limit_mb: u64) -> Result<Self, String> {
+        let (width, height) = image::image_dimensions(image_path)
+            .map_err(|e| format!("Failed to read {} image header: {}", image_type, e))?;
+        let size_mb = decoded_size(width, height).div_ceil(1024 * 1024);
+        if size_mb <= memory_limit_mb {
+            return image::ImageReader::open(image_path)
+                .map_err(|e| format!("Failed to open {} image: {}", image_type, e))?
+                .decode()
+                .map(TileSource::Decoded)
+                .map_err(|e| format!("Failed to decode {} image: {}", image_type, e));
+        }
+
+        tracing::info!(
+            image = %image_path.display(),
+            width,
+            height,
+            size_mb,
+            memory_limit_mb,
+            "Image lue par fenêtres pour le découpage"
+        );
+        let too_large = |e: String| {
+            format!(
+                "{} image of {}x{} px needs {} MB once decoded, above the {} MB slicing limit, \
+                 and cannot be read by windows: {}",
+                image_type, width, height, size_mb, memory_limit_mb, e
+            )
+        };
+        let dataset = Dataset::open(image_path).map_err(|e| too_large(e.to_string()))?;
+        if dataset.raster_count() < 3 {
+            return Err(too_large(format!(
+                "{} band(s) instead of 3",
+                dataset.raster_count()
+            )));
+        }
+        Ok(TileSource::Windowed(dataset))
+    }
+
+    /// Dimensions de l'image en pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            TileSource::Decoded(image) => image.dimensions(),
+            TileSource::Windowed(dataset) => {
+                let (width, height) = dataset.raster_size();
+                (width as u32, height as u32)
+            }
+        }
+    }
+
+    /// Extrait une tuile de `slice_factor` pixels de côté, complétée en noir
+    /// lorsqu'elle dépasse le bord de l'image.
+    ///
+    /// # Arguments
+    ///
+    /// * `img_x` - La colonne du coin supérieur gauche de la tuile.
+    /// * `img_y` - La ligne du coin supérieur gauche de la tuile.
+    /// * `slice_factor` - La taille des tuiles en pixels.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DynamicImage, String>` - La tuile, ou une erreur de lecture.
+    pub fn crop_tile(
+        &self,
+        img_x: u32,
+        img_y: u32,
+        slice_factor: u32,
+    ) -> Result<DynamicImage, String> {
+        let cropped = match self {
+            TileSource::Decoded(image) => image.crop_imm(img_x, img_y, slice_factor, slice_factor),
+            TileSource::Windowed(dataset) => read_window(dataset, img_x, img_y, slice_factor)
+                .map_err(|e| format!("Failed to read tile ({}, {}): {}", img_x, img_y, e))?,
+        };
+        if cropped.dimensions() == (slice_factor, slice_factor) {
+            return Ok(cropped);
+        }
+
+        let mut padded = DynamicImage::new_rgb8(slice_factor, slice_factor);
+        imageops::replace(&mut padded, &cropped, 0, 0);
+        Ok(padded)
+    }
+
+    /// Réduit l'image entière à `width` x `height` pixels. Les images VEGET sont réduites
+    /// au plus proche voisin, qui garde les couleurs des classes ; une image lue par fenêtres
+    /// est réduite par GDAL à la lecture.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - La largeur de l'image réduite.
+    /// * `height` - La hauteur de l'image réduite.
+    /// * `kind` - Le type de l'image.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RgbImage, String>` - L'image réduite, ou une erreur de lecture.
+    pub fn downscaled(&self, width: u32, height: u32, kind: SliceKind) -> Result<RgbImage, String> {
+        match self {
+            TileSource::Decoded(image) => {
+                let filter = match kind {
+                    SliceKind::Veget => FilterType::Nearest,
+                    SliceKind::Ortho => FilterType::Triangle,
+                };
+                Ok(image.resize_exact(width, height, filter).to_rgb8())
+            }
+            TileSource::Windowed(dataset) => {
+                let resample = match kind {
+                    SliceKind::Veget => ResampleAlg::NearestNeighbour,
+                    SliceKind::Ortho => ResampleAlg::Average,
+                };
+                read_downscaled(dataset, width, height, resample)
+                    .map_err(|e| format!("Failed to read the downscaled image: {}", e))
+            }
+        }
+    }
+}
+
+/// Lit les trois premières bandes d'une fenêtre de l'image, limitée à ses bords.
+fn read_window(
+    dataset: &Dataset,
+    img_x: u32,
+    img_y: u32,
     slice_factor: u32,
-    base_x: u32,
-    base_y: u32,
-) -> Result<(), String> {
-    let (width, height) = veget_image.dimensions();
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let (width, height) = dataset.raster_size();
+    let window_width = (slice_factor as usize).min(width.saturating_sub(img_x as usize));
+    let window_height = (slice_factor as usize).min(height.saturating_sub(img_y as usize));
+    let window = (window_width, window_height);
+
+    let mut bands = Vec::with_capacity(3);
+    for band_idx in 1..=3 {
+        bands.push(
+            dataset
+                .rasterband(band_idx)?
+                .read_as::<u8>((img_x as isize, img_y as isize), window, window, None)?
+                .data()
+                .to_vec(),
+        );
+    }
+    let image = rgb_from_bands(&bands, window_width as u32, window_height as u32)?;
+    Ok(DynamicImage::ImageRgb8(image))
+}
 
-    for img_y in (0..height).step_by(slice_factor as usize).rev() {
+/// Lit les trois premières bandes de l'image entière, rééchantillonnées à `width` x `height`.
+fn read_downscaled(
+    dataset: &Dataset,
+    width: u32,
+    height: u32,
+    resample: ResampleAlg,
+) -> Result<RgbImage, Box<dyn std::error::Error>> {
+    let size = (width as usize, height as usize);
+    let mut bands = Vec::with_capacity(3);
+    for band_idx in 1..=3 {
+        bands.push(
+            dataset
+                .rasterband(band_idx)?
+                .read_as::<u8>((0, 0), dataset.raster_size(), size, Some(resample))?
+                .data()
+                .to_vec(),
+        );
+    }
+    rgb_from_bands(&bands, width, height)
+}
+
+/// Entrelace trois bandes lues par GDAL en une image RGB.
+fn rgb_from_bands(
+    bands: &[Vec<u8>],
+    width: u32,
+    height: u32,
+) -> Result<RgbImage, Box<dyn std::error::Error>> {
+    let pixels = width as usize * height as usize;
+    let mut rgb = Vec::with_capacity(pixels * 3);
+    for i in 0..pixels {
+        rgb.extend_from_slice(&[bands[0][i], bands[1][i], bands[2][i]]);
+    }
+    RgbImage::from_raw(width, height, rgb)
+        .ok_or_else(|| "Window data does not match its size".into())
+}
+
+/// Calcule les coordonnées kilométriques (Lambert 93 en métropole, UTM outre-mer) du coin
+/// inférieur gauche d'une tuile,
+/// utilisées pour nommer les découpes (voir `slice_file_name`).
+/// Les tuiles de la dernière ligne ou colonne peuvent dépasser l'image lorsque ses
+/// dimensions ne sont pas un multiple du facteur de découpe : elles sont alors complétées
+/// au sud et à l'est, et leurs coordonnées restent alignées sur la grille des tuiles.
+///
+/// # Arguments
+///
+/// * `img_x` - La colonne du coin supérieur gauche de la tuile dans l'image, en pixels.
+/// * `img_y` - La ligne du coin supérieur gauche de la tuile dans l'image, en pixels.
+/// * `width` - La largeur de l'image en pixels.
+/// * `height` - La hauteur de l'image en pixels.
+/// * `slice_factor` - La taille des tuiles en pixels.
+/// * `resolution` - La résolution de l'image en mètres par pixel.
+/// * `bb` - L'emprise de l'image.
+///
+/// # Returns
+///
+/// * `Result<(u32, u32), String>` - Les coordonnées en kilomètres, ou une erreur si la tuile
+///   n'est pas sur la grille ou si deux tuiles voisines porteraient le même nom.
+pub fn slice_coords(
+    img_x: u32,
+    img_y: u32,
+    width: u32,
+    height: u32,
+    slice_factor: u32,
+    resolution: f64,
+    bb: &BoundingBox,
+) -> Result<(u32, u32), String> {
+    if slice_factor == 0 || resolution <= 0.0 {
+        return Err(format!(
+            "Invalid slicing parameters: factor {}, resolution {}",
+            slice_factor, resolution
+        ));
+    }
+    let tile_size_m = slice_factor as f64 * resolution;
+    if tile_size_m < 1000.0 {
+        return Err(format!(
+            "Tiles of {} m are smaller than the 1 km naming grid",
+            tile_size_m
+        ));
+    }
+    if img_x >= width || img_y >= height {
+        return Err(format!(
+            "Tile ({}, {}) is outside the {}x{} image",
+            img_x, img_y, width, height
+        ));
+    }
+    if img_x % slice_factor != 0 || img_y % slice_factor != 0 {
+        return Err(format!(
+            "Tile ({}, {}) is not aligned on a {} px grid",
+            img_x, img_y, slice_factor
+        ));
+    }
+
+    let west_m = bb.xmin + img_x as f64 * resolution;
+    let south_m = bb.ymax - (img_y + slice_factor) as f64 * resolution;
+    if west_m < 0.0 || south_m < 0.0 {
+        return Err(format!(
+            "Tile ({}, {}) has negative coordinates",
+            img_x, img_y
+        ));
+    }
+
+    Ok(((west_m / 1000.0) as u32, (south_m / 1000.0) as u32))
+}
+
+/// Associe les coordonnées (en km) de chaque tuile à la position de son coin
+/// supérieur gauche dans l'image, avec la même grille que le découpage.
+///
+/// # Arguments
+///
+/// * `width` - La largeur de l'image en pixels.
+/// * `height` - La hauteur de l'image en pixels.
+/// * `slice_factor` - La taille des tuiles en pixels.
+/// * `bb` - L'emprise du projet.
+///
+/// # Returns
+///
+/// * `Result<HashMap<(u32, u32), (u32, u32)>, String>` - La position en pixels de chaque tuile.
+pub fn tile_origins(
+    width: u32,
+    height: u32,
+    slice_factor: u32,
+    bb: &BoundingBox,
+) -> Result<HashMap<(u32, u32), (u32, u32)>, String> {
+    if width == 0 || slice_factor == 0 {
+        return Err("Cannot slice an empty image".to_string());
+    }
+    let resolution = bb.width() / width as f64;
+
+    let mut origins = HashMap::new();
+    for img_y in (0..height).step_by(slice_factor as usize) {
         for img_x in (0..width).step_by(slice_factor as usize) {
-            if img_x + slice_factor > width || img_y + slice_factor > height {
-                continue;
-            }
+            let coords = slice_coords(img_x, img_y, width, height, slice_factor, resolution, bb)?;
+            origins.insert(coords, (img_x, img_y));
+        }
+    }
+    Ok(origins)
+}
+
+/// Liste les découpes que doit contenir le dossier `slices/` d'un projet, d'après
+/// les dimensions du projet et le facteur de découpe : une tuile VEGET et une tuile
+/// ORTHO par case de la grille, nommées avec le modèle des paramètres.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `run_config` - Les paramètres de l'export : taille des tuiles en pixels, résolution et
+///   modèle des noms.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, String>` - Les noms des découpes, triés.
+pub fn expected_slice_files(
+    project_name: &str,
+    run_config: &RunConfig,
+) -> Result<Vec<String>, String> {
+    let slice_factor = run_config.slice_factor;
+    let project_bb = get_project_bounding_box(project_name).map_err(|e| e.to_string())?;
+    let (width, height) = project_bb
+        .raster_size(run_config.resolution)
+        .map_err(|e| e.to_string())?;
+    let name_template = &run_config.slice_name_template;
+
+    let mut names = Vec::new();
+    for (coord_x, coord_y) in
+        tile_origins(width as u32, height as u32, slice_factor, &project_bb)?.into_keys()
+    {
+        for kind in [SliceKind::Veget, SliceKind::Ortho] {
+            names.push(slice_file_name(
+                name_template,
+                coord_x,
+                coord_y,
+                kind,
+                slice_factor,
+            )?);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn slice_and_process_images(
+    veget_image: Option<&TileSource>,
+    ortho_image: &TileSource,
+    slice_path: &Path,
+    slice_factor: u32,
+    project_bb: &BoundingBox,
+    enhance_ortho: bool,
+    name_template: &str,
+    jpeg_quality: u8,
+) -> Result<SliceIndex, String> {
+    let (width, height) = ortho_image.dimensions();
+    if width == 0 || slice_factor == 0 {
+        return Err("Cannot slice an empty image".to_string());
+    }
+    if let Some(veget_image) = veget_image {
+        if veget_image.dimensions() != (width, height) {
+            let (veget_width, veget_height) = veget_image.dimensions();
+            return Err(format!(
+                "VEGET ({}x{}) and ORTHO ({}x{}) images differ in size",
+                veget_width, veget_height, width, height
+            ));
+        }
+    }
+    let resolution = project_bb.width() / width as f64;
+    let expected = project_bb
+        .raster_size(resolution)
+        .map_err(|e| e.to_string())?;
+    if expected != (width as usize, height as usize) {
+        return Err(format!(
+            "Images of {}x{} do not match the project grid ({}x{})",
+            width, height, expected.0, expected.1
+        ));
+    }
 
-            let cropped_veget = veget_image.crop_imm(img_x, img_y, slice_factor, slice_factor);
-            let cropped_ortho = ortho_image.crop_imm(img_x, img_y, slice_factor, slice_factor);
+    // Les lignes sont parcourues de haut en bas : une image lue par fenêtres
+    // est ainsi décodée dans l'ordre de ses lignes.
+    let mut tiles = Vec::new();
+    for img_y in (0..height).step_by(slice_factor as usize) {
+        for img_x in (0..width).step_by(slice_factor as usize) {
+            let (coord_x, coord_y) = slice_coords(
+                img_x,
+                img_y,
+                width,
+                height,
+                slice_factor,
+                resolution,
+                project_bb,
+            )?;
 
-            let coord_x = base_x + img_x / 100;
-            let coord_y = base_y + (height - img_y - slice_factor) / 100;
+            let cropped_veget = veget_image
+                .map(|image| image.crop_tile(img_x, img_y, slice_factor))
+                .transpose()?;
+            let cropped_ortho = ortho_image.crop_tile(img_x, img_y, slice_factor)?;
 
-            save_and_process_slice(
-                &cropped_veget,
-                &cropped_ortho,
-                slice_path,
+            let veget_name = slice_file_name(
+                name_template,
                 coord_x,
                 coord_y,
+                SliceKind::Veget,
                 slice_factor,
             )?;
+            let ortho_name = slice_file_name(
+                name_template,
+                coord_x,
+                coord_y,
+                SliceKind::Ortho,
+                slice_factor,
+            )?;
+            save_and_process_slice(
+                cropped_veget.as_ref(),
+                &cropped_ortho,
+                &slice_path.join(&veget_name),
+                &slice_path.join(&ortho_name),
+                enhance_ortho,
+                jpeg_quality,
+            )?;
+
+            let tile_size_m = slice_factor as f64 * resolution;
+            let xmin = project_bb.xmin + img_x as f64 * resolution;
+            let ymax = project_bb.ymax - img_y as f64 * resolution;
+            tiles.push(SliceIndexEntry {
+                x: coord_x,
+                y: coord_y,
+                window: PixelWindow {
+                    x: img_x,
+                    y: img_y,
+                    width: slice_factor,
+                    height: slice_factor,
+                },
+                extent: BoundingBox::new(xmin, ymax - tile_size_m, xmin + tile_size_m, ymax),
+                ortho: ortho_name,
+                veget: veget_name,
+            });
         }
     }
 
-    Ok(())
+    // L'index liste les tuiles du sud au nord, puis d'ouest en est.
+    tiles.sort_by_key(|tile| (Reverse(tile.window.y), tile.window.x));
+
+    Ok(SliceIndex {
+        slice_factor,
+        resolution,
+        name_template: name_template.to_string(),
+        tiles,
+    })
 }
 
+/// Enregistre les découpes d'une tuile ; sans découpe VEGET, seule la tuile ORTHO est écrite.
 fn save_and_process_slice(
-    cropped_veget: &DynamicImage,
+    cropped_veget: Option<&DynamicImage>,
     cropped_ortho: &DynamicImage,
-    slice_path: &str,
-    coord_x: u32,
-    coord_y: u32,
-    slice_factor: u32,
+    veget_path: &Path,
+    ortho_path: &Path,
+    enhance_ortho: bool,
+    jpeg_quality: u8,
 ) -> Result<(), String> {
-    let veget_path = format!(
-        "{}/{}_{}_veget_{}.jpg",
-        slice_path, coord_x, coord_y, slice_factor
-    );
-
-    let ortho_path = format!(
-        "{}/{}_{}_{}.jpg",
-        slice_path, coord_x, coord_y, slice_factor
-    );
-
-    cropped_veget
-        .save(&veget_path)
+    if let Some(cropped_veget) = cropped_veget {
+        save_slice(
+            &process_slice(cropped_veget, SliceKind::Veget, enhance_ortho),
+            veget_path,
+            jpeg_quality,
+        )
         .map_err(|e| format!("Failed to save VEGET slice: {}", e))?;
+    }
 
-    cropped_ortho
-        .save(&ortho_path)
-        .map_err(|e| format!("Failed to save ORTHO slice: {}", e))?;
-
-    process_with_imagemagick(&veget_path, "VEGET")?;
-    process_with_imagemagick(&ortho_path, "ORTHO")?;
+    save_slice(
+        &process_slice(cropped_ortho, SliceKind::Ortho, enhance_ortho),
+        ortho_path,
+        jpeg_quality,
+    )
+    .map_err(|e| format!("Failed to save ORTHO slice: {}", e))?;
 
     Ok(())
 }
 
-fn process_with_imagemagick(image_path: &str, image_type: &str) -> Result<(), String> {
-    Command::new("magick")
-        .args(["convert", image_path, "-enhance", image_path])
-        .output()
-        .map_err(|e| {
-            format!(
-                "Failed to process {} slice with ImageMagick: {}",
-                image_type, e
-            )
-        })?;
+/// Enregistre une découpe au format donné par son extension ; les découpes JPEG
+/// sont encodées avec la qualité `slice_jpeg_quality`.
+fn save_slice(image: &DynamicImage, path: &Path, jpeg_quality: u8) -> ImageResult<()> {
+    if ImageFormat::from_path(path)? != ImageFormat::Jpeg {
+        return image.save(path);
+    }
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    image.write_with_encoder(JpegEncoder::new_with_quality(&mut writer, jpeg_quality))?;
+    writer.flush()?;
     Ok(())
 }
+
+/// Traitement d'une découpe avant son enregistrement. Les découpes VEGET sont des rasters
+/// de classes : elles sont gardées telles quelles, un filtre mélangerait leurs couleurs.
+/// Les découpes ORTHO ne sont débruitées que si `enhance_ortho` est vrai.
+///
+/// # Arguments
+///
+/// * `image` - La découpe.
+/// * `kind` - Le type de la découpe.
+/// * `enhance_ortho` - L'option `enhance_ortho_slices`.
+///
+/// # Returns
+///
+/// * `Cow<DynamicImage>` - La découpe à enregistrer, empruntée si elle n'est pas modifiée.
+pub fn process_slice(
+    image: &DynamicImage,
+    kind: SliceKind,
+    enhance_ortho: bool,
+) -> Cow<'_, DynamicImage> {
+    match kind {
+        SliceKind::Ortho if enhance_ortho => Cow::Owned(enhance_slice(image)),
+        _ => Cow::Borrowed(image),
+    }
+}
+
+/// Applique un léger filtre de débruitage à une découpe ORTHO.
+/// Remplace l'appel à `magick convert -enhance` pour ne plus dépendre d'ImageMagick.
+pub fn enhance_slice(image: &DynamicImage) -> DynamicImage {
+    image.blur(ENHANCE_SIGMA)
+}
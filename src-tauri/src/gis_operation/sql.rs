@@ -0,0 +1,92 @@
+/// Nombre maximal de valeurs par liste `IN (...)` : au-delà, la liste est découpée en
+/// plusieurs listes reliées par `OR` (ou `AND` pour `NOT IN`), pour rester sous les
+/// limites d'expression de SQLite utilisé par les GeoPackages.
+pub const IN_CLAUSE_CHUNK: usize = 500;
+
+/// Littéral SQL d'une valeur d'attribut : les apostrophes sont doublées
+/// (`Chêne d'Amérique` devient `'Chêne d''Amérique'`).
+///
+/// # Arguments
+///
+/// * `value` - La valeur à insérer dans une clause WHERE.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - Le littéral entre apostrophes, ou une erreur si la valeur
+///   contient un caractère de contrôle.
+pub fn sql_literal(value: &str) -> Result<String, String> {
+    if let Some(c) = value.chars().find(|c| c.is_control()) {
+        return Err(format!(
+            "Caractère de contrôle {:?} interdit dans la valeur '{}'",
+            c,
+            value.escape_debug()
+        ));
+    }
+    Ok(format!("'{}'", value.replace('\'', "''")))
+}
+
+fn literal_chunks(values: &[String]) -> Result<Vec<String>, String> {
+    let literals = values
+        .iter()
+        .map(|value| sql_literal(value))
+        .collect::<Result<Vec<String>, String>>()?;
+    Ok(literals
+        .chunks(IN_CLAUSE_CHUNK)
+        .map(|chunk| chunk.join(", "))
+        .collect())
+}
+
+/// Filtre sélectionnant les entités dont l'attribut `field` vaut l'une des valeurs.
+/// Une liste vide donne un filtre qui ne sélectionne rien (`0 = 1`).
+///
+/// # Arguments
+///
+/// * `field` - Le nom de l'attribut.
+/// * `values` - Les valeurs recherchées, échappées par `sql_literal`.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - La clause WHERE, ou l'erreur de `sql_literal`.
+pub fn build_in_clause(field: &str, values: &[String]) -> Result<String, String> {
+    let chunks = literal_chunks(values)?;
+    Ok(match chunks.as_slice() {
+        [] => "0 = 1".to_string(),
+        [chunk] => format!("{} IN ({})", field, chunk),
+        chunks => format!(
+            "({})",
+            chunks
+                .iter()
+                .map(|chunk| format!("{} IN ({})", field, chunk))
+                .collect::<Vec<String>>()
+                .join(" OR ")
+        ),
+    })
+}
+
+/// Filtre sélectionnant les entités dont l'attribut `field` ne vaut aucune des valeurs.
+/// Comme en SQL, les entités dont l'attribut est NULL ne sont pas sélectionnées ;
+/// une liste vide donne un filtre qui sélectionne tout (`1 = 1`).
+///
+/// # Arguments
+///
+/// * `field` - Le nom de l'attribut.
+/// * `values` - Les valeurs exclues, échappées par `sql_literal`.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - La clause WHERE, ou l'erreur de `sql_literal`.
+pub fn build_not_in_clause(field: &str, values: &[String]) -> Result<String, String> {
+    let chunks = literal_chunks(values)?;
+    Ok(match chunks.as_slice() {
+        [] => "1 = 1".to_string(),
+        [chunk] => format!("{} NOT IN ({})", field, chunk),
+        chunks => format!(
+            "({})",
+            chunks
+                .iter()
+                .map(|chunk| format!("{} NOT IN ({})", field, chunk))
+                .collect::<Vec<String>>()
+                .join(" AND ")
+        ),
+    })
+}
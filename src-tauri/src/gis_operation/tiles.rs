@@ -0,0 +1,295 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gdal::raster::{RasterBand, ResampleAlg};
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use image::{Rgb, RgbImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::processing::ProjectRaster;
+use super::report::ViewMode;
+
+use crate::utils::{
+    nodata_color, ortho_jpeg_quality, project_dir, project_ortho_jpeg, project_tiff, save_rgb_jpeg,
+};
+
+/// Côté des tuiles servies à l'interface, en pixels.
+pub const TILE_SIZE: usize = 256;
+
+/// Dossier du cache des tuiles, dans le dossier du projet.
+pub const TILES_DIR: &str = ".tiles";
+
+/// Pyramide de tuiles d'une image de projet.
+///
+/// Le niveau `max_zoom` correspond à la pleine résolution de l'image ; chaque niveau inférieur
+/// divise la résolution par deux, jusqu'au niveau 0 où l'image tient dans une seule tuile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileGrid {
+    /// Largeur de l'image en pixels.
+    pub width: usize,
+    /// Hauteur de l'image en pixels.
+    pub height: usize,
+    /// Côté des tuiles en pixels.
+    pub tile_size: usize,
+    /// Niveau de zoom de la pleine résolution.
+    pub max_zoom: u32,
+}
+
+impl TileGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut max_zoom = 0;
+        while TILE_SIZE << max_zoom < width.max(height) {
+            max_zoom += 1;
+        }
+        TileGrid {
+            width,
+            height,
+            tile_size: TILE_SIZE,
+            max_zoom,
+        }
+    }
+
+    /// Nombre de pixels de l'image par pixel de tuile au niveau `z`.
+    pub fn scale(&self, z: u32) -> usize {
+        1 << self.max_zoom.saturating_sub(z)
+    }
+
+    /// Nombre de tuiles (colonnes, lignes) au niveau `z`.
+    pub fn tile_count(&self, z: u32) -> (usize, usize) {
+        let span = self.tile_size * self.scale(z);
+        (self.width.div_ceil(span), self.height.div_ceil(span))
+    }
+
+    /// Tuile (colonne, ligne) contenant le pixel `(px, py)` de l'image au niveau `z`.
+    pub fn tile_at(&self, z: u32, px: usize, py: usize) -> (usize, usize) {
+        let span = self.tile_size * self.scale(z);
+        (px / span, py / span)
+    }
+}
+
+/// Tuile d'une image de projet, écrite dans le cache du projet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectTile {
+    /// Chemin de la tuile (PNG pour la végétation, JPEG pour l'orthophoto).
+    pub path: PathBuf,
+    /// La tuile était déjà dans le cache et n'a pas été recalculée.
+    pub cached: bool,
+}
+
+/// Raster dont sont découpées les tuiles : le GeoTIFF du projet (avec sa transparence)
+/// pour la végétation, l'orthophoto JPEG pour la vue satellite.
+fn tile_source(project_name: &str, view: ViewMode) -> PathBuf {
+    match view {
+        ViewMode::Vegetation => project_tiff(project_name),
+        ViewMode::Satellite => project_ortho_jpeg(project_name),
+    }
+}
+
+fn tile_extension(view: ViewMode) -> &'static str {
+    match view {
+        ViewMode::Vegetation => "png",
+        ViewMode::Satellite => "jpg",
+    }
+}
+
+/// Chemin d'une tuile dans le cache du projet :
+/// `projects/<nom>/.tiles/<VEGET|ORTHO>/<z>/<x>/<y>.<png|jpg>`.
+pub fn tile_path(project_name: &str, view: ViewMode, z: u32, x: usize, y: usize) -> PathBuf {
+    project_dir(project_name)
+        .join(TILES_DIR)
+        .join(view.image_suffix())
+        .join(z.to_string())
+        .join(x.to_string())
+        .join(format!("{}.{}", y, tile_extension(view)))
+}
+
+/// Pyramide de tuiles d'une image de projet.
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet
+/// * `view` - image du projet (végétation ou orthophoto)
+///
+/// # Returns
+///
+/// * `Result<TileGrid, Box<dyn Error>>` - les dimensions de l'image et son niveau de zoom maximal
+pub fn project_tile_grid(project_name: &str, view: ViewMode) -> Result<TileGrid, Box<dyn Error>> {
+    let source = Dataset::open(tile_source(project_name, view))?;
+    let (width, height) = source.raster_size();
+    Ok(TileGrid::new(width, height))
+}
+
+/// La tuile en cache est au moins aussi récente que le raster dont elle est découpée.
+fn is_fresh(tile: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(tile), modified(source)) {
+        (Ok(tile), Ok(source)) => tile >= source,
+        _ => false,
+    }
+}
+
+/// Renvoie une tuile de `TILE_SIZE` pixels d'une image de projet, en la découpant du raster
+/// du projet si elle n'est pas déjà dans le cache (`.tiles` du dossier du projet).
+///
+/// La fenêtre de l'image est lue directement à la résolution de la tuile : GDAL utilise
+/// alors les aperçus (overviews) du raster, voir [`build_project_overviews`].
+/// Les tuiles du bord de l'image sont complétées par de la transparence (végétation)
+/// ou la couleur de fond (orthophoto).
+///
+/// # Arguments
+///
+/// * `project_name` - nom du projet
+/// * `view` - image du projet (végétation ou orthophoto)
+/// * `z` - niveau de zoom, de 0 à `TileGrid::max_zoom`
+/// * `x` - colonne de la tuile
+/// * `y` - ligne de la tuile
+///
+/// # Returns
+///
+/// * `Result<ProjectTile, Box<dyn Error>>` - le chemin de la tuile, ou une erreur si elle est hors de l'image
+pub fn get_project_tile(
+    project_name: &str,
+    view: ViewMode,
+    z: u32,
+    x: usize,
+    y: usize,
+) -> Result<ProjectTile, Box<dyn Error>> {
+    let source_path = tile_source(project_name, view);
+    let path = tile_path(project_name, view, z, x, y);
+    if is_fresh(&path, &source_path) {
+        return Ok(ProjectTile { path, cached: true });
+    }
+
+    // L'image de végétation est le raster du projet, vérifié à l'ouverture ; ses bandes
+    // sont lues dans l'ordre rouge, vert, bleu, alpha.
+    let project;
+    let satellite;
+    let (size, source_bands) = match view {
+        ViewMode::Vegetation => {
+            project = ProjectRaster::open_read_only(&source_path.to_string_lossy())?;
+            let [red, green, blue] = project.rgb_bands()?;
+            (
+                project.size(),
+                vec![red, green, blue, project.alpha_band()?],
+            )
+        }
+        ViewMode::Satellite => {
+            satellite = Dataset::open(&source_path)?;
+            let bands = (1..=satellite.raster_count().min(4))
+                .map(|band| satellite.rasterband(band))
+                .collect::<Result<Vec<_>, _>>()?;
+            (satellite.raster_size(), bands)
+        }
+    };
+    let (width, height) = size;
+    let grid = TileGrid::new(width, height);
+    let (columns, rows) = grid.tile_count(z);
+    if z > grid.max_zoom || x >= columns || y >= rows {
+        return Err(format!(
+            "Tuile {}/{}/{} hors de l'image du projet '{}'",
+            z, x, y, project_name
+        )
+        .into());
+    }
+
+    let scale = grid.scale(z);
+    let span = TILE_SIZE * scale;
+    let (x0, y0) = (x * span, y * span);
+    let window = (span.min(width - x0), span.min(height - y0));
+    let buffer = (window.0.div_ceil(scale), window.1.div_ceil(scale));
+    let read_band = |band: &RasterBand| -> Result<Vec<u8>, gdal::errors::GdalError> {
+        Ok(band
+            .read_as::<u8>(
+                (x0 as isize, y0 as isize),
+                window,
+                buffer,
+                Some(ResampleAlg::NearestNeighbour),
+            )?
+            .data()
+            .to_vec())
+    };
+    let bands: Vec<Vec<u8>> = source_bands
+        .iter()
+        .map(read_band)
+        .collect::<Result<_, _>>()?;
+    drop(source_bands);
+
+    let pixel =
+        |band: usize, px: usize, py: usize| bands[band.min(bands.len() - 1)][py * buffer.0 + px];
+
+    fs::create_dir_all(path.parent().ok_or("Invalid tile path")?)?;
+    let temp_path = path.with_extension(format!("{}.tmp", tile_extension(view)));
+    match view {
+        ViewMode::Vegetation => {
+            let has_alpha = bands.len() >= 4;
+            let mut image = RgbaImage::new(TILE_SIZE as u32, TILE_SIZE as u32);
+            for py in 0..buffer.1 {
+                for px in 0..buffer.0 {
+                    let alpha = if has_alpha { pixel(3, px, py) } else { 255 };
+                    image.put_pixel(
+                        px as u32,
+                        py as u32,
+                        Rgba([pixel(0, px, py), pixel(1, px, py), pixel(2, px, py), alpha]),
+                    );
+                }
+            }
+            image.save_with_format(&temp_path, image::ImageFormat::Png)?;
+        }
+        ViewMode::Satellite => {
+            let mut image =
+                RgbImage::from_pixel(TILE_SIZE as u32, TILE_SIZE as u32, Rgb(nodata_color()));
+            for py in 0..buffer.1 {
+                for px in 0..buffer.0 {
+                    image.put_pixel(
+                        px as u32,
+                        py as u32,
+                        Rgb([pixel(0, px, py), pixel(1, px, py), pixel(2, px, py)]),
+                    );
+                }
+            }
+            save_rgb_jpeg(&image, &temp_path.to_string_lossy(), ortho_jpeg_quality())?;
+        }
+    }
+    fs::rename(&temp_path, &path)?;
+
+    Ok(ProjectTile {
+        path,
+        cached: false,
+    })
+}
+
+/// Facteurs de réduction des aperçus d'un raster de `width` × `height` pixels :
+/// 2, 4, 8... tant que l'aperçu dépasse une tuile.
+pub fn overview_levels(width: usize, height: usize) -> Vec<i32> {
+    let grid = TileGrid::new(width, height);
+    (1..=grid.max_zoom).map(|level| 1 << level).collect()
+}
+
+/// Calcule les aperçus (overviews) internes du GeoTIFF d'un projet, utilisés pour
+/// découper les tuiles des niveaux de zoom réduits sans relire la pleine résolution.
+/// Le rééchantillonnage au plus proche voisin conserve les couleurs des classes.
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du GeoTIFF du projet
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - un résultat indiquant si le calcul a réussi ou échoué
+pub fn build_project_overviews<P: AsRef<Path>>(project_file_path: P) -> Result<(), Box<dyn Error>> {
+    let mut dataset = Dataset::open_ex(
+        project_file_path.as_ref(),
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+            ..Default::default()
+        },
+    )?;
+    let (width, height) = dataset.raster_size();
+    let levels = overview_levels(width, height);
+    if !levels.is_empty() {
+        dataset.build_overviews("NEAREST", &levels, &[])?;
+    }
+    dataset.close()?;
+    Ok(())
+}
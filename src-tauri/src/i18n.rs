@@ -0,0 +1,620 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Langue des messages émis par le backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Fr,
+    En,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Fr, Language::En];
+
+    /// Code de la langue dans la configuration (`fr`, `en`).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::Fr => "fr",
+            Language::En => "en",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::Fr => "Français",
+            Language::En => "English",
+        }
+    }
+
+    /// Lit un code de langue de la configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Le code, par exemple `en`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Language, String>` - La langue, ou une erreur si elle n'est pas traduite.
+    pub fn from_code(code: &str) -> Result<Language, String> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.code() == code.trim().to_lowercase())
+            .ok_or_else(|| format!("Langue non prise en charge: {}", code))
+    }
+
+    /// Langue correspondant à une locale système (`en_US.UTF-8`, `fr-FR`...).
+    /// Les locales françaises et neutres (`C`, `POSIX`) donnent le français,
+    /// les autres l'anglais.
+    pub fn from_locale(locale: &str) -> Language {
+        let locale = locale.trim().to_lowercase();
+        if locale.is_empty() || locale.starts_with("fr") || locale == "c" || locale == "posix" {
+            Language::Fr
+        } else {
+            Language::En
+        }
+    }
+
+    /// Langue de la locale système, lue dans `LC_ALL`, `LC_MESSAGES` puis `LANG`.
+    pub fn system() -> Language {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.trim().is_empty())
+            .map(|locale| Language::from_locale(&locale))
+            .unwrap_or(Language::Fr)
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Catalogue des messages : clé, texte français, texte anglais.
+/// Les paramètres sont notés `{0}`, `{1}`...
+pub const CATALOG: &[(&str, &str, &str)] = &[
+    // Étapes de la création d'un projet
+    (
+        "stage.search_files",
+        "Recherche des fichiers",
+        "Searching for files",
+    ),
+    (
+        "stage.init",
+        "Initialisation du projet",
+        "Initializing the project",
+    ),
+    (
+        "stage.download",
+        "Téléchargement des données",
+        "Downloading data",
+    ),
+    (
+        "stage.prepare",
+        "Préparation des Couches",
+        "Preparing layers",
+    ),
+    ("stage.fuse", "Fusion des données", "Merging data"),
+    ("stage.add_layers", "Ajout des Couches", "Adding layers"),
+    ("stage.finalize", "Finalisation", "Finalizing"),
+    ("stage.cleanup", "Nettoyage", "Cleaning up"),
+    (
+        "stage.created",
+        "Projet créé avec succès",
+        "Project created successfully",
+    ),
+    (
+        "stage.refresh_ortho",
+        "Mise à jour de l'orthophoto",
+        "Updating the orthophoto",
+    ),
+    (
+        "stage.ortho_refreshed",
+        "Orthophoto mise à jour",
+        "Orthophoto updated",
+    ),
+    ("stage.export", "Exportation", "Exporting"),
+    // Sous-tâches
+    (
+        "step.create_folders",
+        "Création des dossiers",
+        "Creating folders",
+    ),
+    (
+        "step.configure_project",
+        "Configuration du projet",
+        "Configuring the project",
+    ),
+    (
+        "step.reuse_source",
+        "Réutilisation des données du projet source",
+        "Reusing the source project data",
+    ),
+    (
+        "step.crop_raster",
+        "Découpage du raster du projet source",
+        "Cropping the source project raster",
+    ),
+    ("step.download_file", "{0}", "{0}"),
+    ("step.received_mb", "{0} Mo reçus", "{0} MB received"),
+    (
+        "step.check_archives",
+        "Vérification du contenu des archives",
+        "Checking archive contents",
+    ),
+    (
+        "step.process_region",
+        "Traitement de la région {0}",
+        "Processing region {0}",
+    ),
+    (
+        "step.regional_extent",
+        "Préparation de l'étendue régionale",
+        "Preparing the regional extent",
+    ),
+    (
+        "step.prepare_group",
+        "Préparation des couches {0}",
+        "Preparing {0} layers",
+    ),
+    ("step.extract", "Extraction de {0}", "Extracting {0}"),
+    ("step.convert", "Conversion de {0}", "Converting {0}"),
+    ("step.clip", "Découpage de {0}", "Clipping {0}"),
+    (
+        "step.merge_regions",
+        "Fusion des régions",
+        "Merging regions",
+    ),
+    (
+        "step.merge_regional",
+        "Fusion des couches régionales",
+        "Merging regional layers",
+    ),
+    (
+        "step.merge_vegetation",
+        "Fusion des couches de végétation",
+        "Merging vegetation layers",
+    ),
+    (
+        "step.merge_rpg",
+        "Fusion des couches RPG",
+        "Merging RPG layers",
+    ),
+    (
+        "step.merge_topo",
+        "Fusion des couches topographiques",
+        "Merging topographic layers",
+    ),
+    ("step.merge_layer", "Fusion de {0}", "Merging {0}"),
+    (
+        "step.copy_single_region",
+        "Copie des fichiers (une seule région)",
+        "Copying files (single region)",
+    ),
+    (
+        "step.add_regional",
+        "Ajout de la couche régionale",
+        "Adding the regional layer",
+    ),
+    (
+        "step.add_group",
+        "Ajout des couches {0}",
+        "Adding {0} layers",
+    ),
+    ("step.add_layer", "Ajout de {0}", "Adding {0}"),
+    ("step.export_jpeg", "Export en JPEG", "Exporting to JPEG"),
+    (
+        "step.crop_ortho",
+        "Découpage de l'orthophoto du projet source",
+        "Cropping the source project orthophoto",
+    ),
+    (
+        "step.download_ortho",
+        "Téléchargement d'orthophoto",
+        "Downloading the orthophoto",
+    ),
+    (
+        "step.ortho_retry",
+        "Nouvelle tentative de téléchargement de l'orthophoto ({0}, essai {1})",
+        "Retrying the orthophoto download ({0}, attempt {1})",
+    ),
+    (
+        "step.replace_ortho",
+        "Remplacement de l'orthophoto",
+        "Replacing the orthophoto",
+    ),
+    (
+        "step.update_preview",
+        "Mise à jour de l'aperçu",
+        "Updating the preview",
+    ),
+    ("step.compression", "Compression", "Compressing"),
+    // Tâches en cours sur un projet
+    ("busy.creating", "création en cours", "being created"),
+    ("busy.exporting", "export en cours", "being exported"),
+    (
+        "busy.refreshing_ortho",
+        "mise à jour de l'orthophoto en cours",
+        "orthophoto being updated",
+    ),
+    (
+        "busy.adding_layer",
+        "ajout d'une couche en cours",
+        "layer being added",
+    ),
+    ("busy.cloning", "duplication en cours", "being cloned"),
+    ("busy.renaming", "renommage en cours", "being renamed"),
+    ("busy.deleting", "suppression en cours", "being deleted"),
+    (
+        "settings.saved",
+        "Paramètres sauvegardés avec succès",
+        "Settings saved successfully",
+    ),
+    (
+        "settings.partially_saved",
+        "Paramètres sauvegardés, sauf {0} : un projet est en cours de traitement",
+        "Settings saved except {0}: a project is being processed",
+    ),
+    // Erreurs des commandes
+    (
+        "error.nothing_to_resume",
+        "Aucune création à reprendre pour le projet {0}",
+        "No creation to resume for project {0}",
+    ),
+    (
+        "error.creation_cancelled",
+        "Création du projet annulée",
+        "Project creation cancelled",
+    ),
+    (
+        "error.read_projects",
+        "Erreur lors de la lecture des projets",
+        "Could not read projects",
+    ),
+    (
+        "error.create_project",
+        "Erreur lors de la création du projet",
+        "Could not create the project",
+    ),
+    (
+        "error.creation_template",
+        "Erreur lors de la lecture des paramètres du projet modèle",
+        "Could not read the template project settings",
+    ),
+    (
+        "error.batch_definitions",
+        "Erreur lors de la lecture du fichier de lot",
+        "Could not read the batch file",
+    ),
+    (
+        "error.save_metadata",
+        "Erreur lors de l'enregistrement des métadonnées",
+        "Could not save the project metadata",
+    ),
+    (
+        "error.save_state",
+        "Erreur lors de l'enregistrement de l'état de la création",
+        "Could not save the creation state",
+    ),
+    (
+        "error.layer_selection",
+        "Sélection de couches invalide",
+        "Invalid layer selection",
+    ),
+    (
+        "error.project_size",
+        "Projet trop grand",
+        "Project too large",
+    ),
+    (
+        "error.project_grid",
+        "Emprise non alignée sur la grille du projet",
+        "Extent not aligned on the project grid",
+    ),
+    (
+        "error.load_layer_config",
+        "Erreur lors du chargement de la configuration des couches",
+        "Could not load the layer configuration",
+    ),
+    (
+        "error.save_layer_config",
+        "Erreur lors de l'enregistrement de la configuration des couches",
+        "Could not save the layer configuration",
+    ),
+    (
+        "error.work_dir",
+        "Erreur lors de la création du dossier de travail temporaire",
+        "Could not create the temporary work folder",
+    ),
+    (
+        "error.read_project_info",
+        "Erreur lors de la lecture des informations du projet",
+        "Could not read the project information",
+    ),
+    (
+        "error.copy_project",
+        "Erreur lors de la copie du projet",
+        "Could not copy the project",
+    ),
+    (
+        "error.invalid_project_name",
+        "Nom de projet refusé",
+        "Project name rejected",
+    ),
+    (
+        "error.trash_project",
+        "Impossible de mettre de côté le projet existant",
+        "Could not set the existing project aside",
+    ),
+    (
+        "error.project_exists",
+        "Un projet nommé '{0}' existe déjà",
+        "A project named '{0}' already exists",
+    ),
+    (
+        "error.reuse_available",
+        "Le projet '{0}' contient déjà cette emprise : autorisez sa réutilisation ou refusez-la",
+        "Project '{0}' already covers this extent: allow or decline its reuse",
+    ),
+    (
+        "error.derive_project",
+        "Erreur lors de la dérivation du projet",
+        "Could not derive the project",
+    ),
+    (
+        "error.prepare_project",
+        "Erreur lors de la préparation du projet",
+        "Could not prepare the project",
+    ),
+    (
+        "error.add_layers",
+        "Erreur lors de l'ajout des couches",
+        "Could not add the layers",
+    ),
+    (
+        "error.export_image",
+        "Erreur lors de l'exportation de l'image",
+        "Could not export the image",
+    ),
+    (
+        "error.download_satellite",
+        "Erreur lors du téléchargement de l'image satellite",
+        "Could not download the satellite image",
+    ),
+    (
+        "error.mark_complete",
+        "Erreur lors de l'enregistrement de la fin de la création",
+        "Could not record the end of the creation",
+    ),
+    (
+        "error.refresh_ortho",
+        "Erreur lors de la mise à jour de l'orthophoto",
+        "Could not update the orthophoto",
+    ),
+    (
+        "error.export",
+        "Erreur lors de l'exportation",
+        "Export failed",
+    ),
+    (
+        "error.project_not_found",
+        "Le projet '{0}' n'existe pas",
+        "Project '{0}' does not exist",
+    ),
+    (
+        "error.queue_export",
+        "Erreur lors de l'ajout à la file d'exports",
+        "Could not add to the export queue",
+    ),
+    (
+        "error.cancel_export",
+        "Erreur lors de l'annulation de l'export",
+        "Could not cancel the export",
+    ),
+    (
+        "error.export_pdf",
+        "Erreur lors de l'export de la carte PDF",
+        "Could not export the PDF map",
+    ),
+    (
+        "error.qgis_project",
+        "Erreur lors de l'écriture du projet QGIS",
+        "Could not write the QGIS project",
+    ),
+    (
+        "error.add_custom_layer",
+        "Erreur lors de l'ajout de la couche",
+        "Could not add the layer",
+    ),
+    (
+        "error.project_tile",
+        "Erreur lors de la lecture de la carte du projet",
+        "Could not read the project map",
+    ),
+    (
+        "error.open_folder",
+        "Erreur lors de l'ouverture du dossier du projet",
+        "Could not open the project folder",
+    ),
+    (
+        "error.reveal_export",
+        "Erreur lors de l'affichage de l'export",
+        "Could not show the export",
+    ),
+    (
+        "error.delete_project",
+        "Erreur lors de la suppression du projet",
+        "Could not delete the project",
+    ),
+    (
+        "error.import_project",
+        "Erreur lors de l'import du projet",
+        "Could not import the project",
+    ),
+    (
+        "error.rename_project",
+        "Erreur lors du renommage du projet",
+        "Could not rename the project",
+    ),
+    (
+        "error.save_settings",
+        "Échec de sauvegarde des paramètres",
+        "Could not save the settings",
+    ),
+    (
+        "error.read_logs",
+        "Erreur lors de la lecture du journal",
+        "Could not read the log",
+    ),
+    (
+        "error.cache_in_use",
+        "Le cache ({0}) est utilisé par le projet {1} ({2}) : réessayez à la fin de la tâche",
+        "The cache ({0}) is in use by project {1} ({2}): try again once the task is over",
+    ),
+    (
+        "error.clear_cache",
+        "Échec du vidage du cache",
+        "Could not clear the cache",
+    ),
+    (
+        "error.unknown_department",
+        "Département inconnu: {0}",
+        "Unknown department: {0}",
+    ),
+    (
+        "error.cache_status",
+        "Erreur lors de la lecture de l'état du cache",
+        "Could not read the cache status",
+    ),
+    (
+        "error.snap_bbox",
+        "Impossible de corriger l'emprise",
+        "Could not adjust the extent",
+    ),
+    (
+        "error.estimate",
+        "Erreur lors de l'estimation du projet",
+        "Could not estimate the project",
+    ),
+    (
+        "error.project_busy",
+        "Le projet '{0}' est occupé ({1})",
+        "Project '{0}' is busy ({1})",
+    ),
+    (
+        "error.verify_cache",
+        "Erreur lors de la vérification du cache",
+        "Could not verify the cache",
+    ),
+    (
+        "error.rebuild_regions_graph",
+        "Erreur lors de la reconstruction du graphe des départements",
+        "Could not rebuild the departments graph",
+    ),
+    (
+        "error.setup_incomplete",
+        "Configuration incomplète ({0}) : voir l'écran « Configuration requise »",
+        "Incomplete setup ({0}): see the \"Setup required\" screen",
+    ),
+    ("class.unknown", "Hors zone", "Outside the area"),
+    ("class.feuillus", "Feuillus", "Broadleaved forest"),
+    (
+        "class.other_vegetation",
+        "Conifères et autres formations",
+        "Conifers and other formations",
+    ),
+    ("class.rpg", "Parcelles agricoles", "Agricultural parcels"),
+    ("class.building", "Bâtiments", "Buildings"),
+    ("class.building_high", "Bâtiments hauts", "Tall buildings"),
+    (
+        "class.road",
+        "Routes et voies ferrées",
+        "Roads and railways",
+    ),
+    ("class.water", "Eau", "Water"),
+    (
+        "class.undefined_vegetation",
+        "Végétation non renseignée",
+        "Unspecified vegetation",
+    ),
+    (
+        "class.other_topo",
+        "Autres infrastructures",
+        "Other infrastructure",
+    ),
+    (
+        "class.land",
+        "Terres sans couverture connue",
+        "Land without known cover",
+    ),
+    ("identify.outside", "Hors zone", "Outside the area"),
+    ("identify.unknown", "Classe inconnue", "Unknown class"),
+    (
+        "error.identify_pixel",
+        "Erreur lors de l'identification du pixel",
+        "Could not identify the pixel",
+    ),
+    (
+        "error.legend",
+        "Erreur lors de la construction de la légende",
+        "Could not build the legend",
+    ),
+    (
+        "error.check_data_updates",
+        "Erreur lors de la recherche des nouveaux millésimes",
+        "Could not check for new data releases",
+    ),
+    (
+        "error.refresh_cache",
+        "Erreur lors de la mise à jour des archives du cache",
+        "Could not update the cached archives",
+    ),
+];
+
+/// Traduit un message du catalogue.
+///
+/// # Arguments
+///
+/// * `key` - La clé du message, par exemple `stage.download`.
+/// * `language` - La langue du texte.
+/// * `params` - Les valeurs des paramètres `{0}`, `{1}`...
+///
+/// # Returns
+///
+/// * `String` - Le texte traduit, ou la clé elle-même si elle est absente du catalogue.
+pub fn translate(key: &str, language: Language, params: &[&str]) -> String {
+    let Some((_, fr, en)) = CATALOG.iter().find(|(entry, _, _)| *entry == key) else {
+        return key.to_string();
+    };
+    let template = match language {
+        Language::Fr => fr,
+        Language::En => en,
+    };
+    params
+        .iter()
+        .enumerate()
+        .fold(template.to_string(), |text, (index, value)| {
+            text.replace(&format!("{{{}}}", index), value)
+        })
+}
+
+/// Traduit un message dans la langue de la configuration.
+pub fn text(key: &str, params: &[&str]) -> String {
+    translate(key, crate::utils::language(), params)
+}
+
+/// Message d'erreur d'une commande : le contexte traduit suivi du détail de l'erreur.
+///
+/// # Arguments
+///
+/// * `key` - La clé du contexte, par exemple `error.export`.
+/// * `detail` - L'erreur d'origine.
+///
+/// # Returns
+///
+/// * `String` - Le message, par exemple `Export failed: disque plein`.
+pub fn error(key: &str, detail: impl fmt::Display) -> String {
+    format!("{}: {}", text(key, &[]), detail)
+}
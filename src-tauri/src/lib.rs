@@ -1,32 +1,121 @@
 use app_setup::setup_check;
+use busy::BusyProjects;
 use commands::{
-    clear_cache, create_project_com, delete_project, export, get_os, get_projects, get_settings,
-    save_settings,
+    add_custom_layer, cancel_export, check_data_updates, check_project_conflict, clear_cache,
+    clone_project, create_project_com, create_projects_batch, delete_project, estimate_project,
+    export, export_map_pdf, generate_qgis_project, get_busy_projects, get_cache_status,
+    get_creation_params, get_data_update_status, get_dpts_list, get_export_queue, get_layer_config,
+    get_legend, get_os, get_project_details, get_project_tile, get_projects, get_recent_logs,
+    get_region_neighbors, get_settings, get_setup_report, get_tile_grid, get_timings,
+    identify_pixel, identify_point, import_project, list_export_targets, list_projects,
+    open_project_folder, queue_export, rebuild_regions_graph, recheck_setup, refresh_cache,
+    refresh_ortho, rename_project, resume_project_creation, reveal_export, run_diagnostics,
+    save_layer_config, save_settings, suggest_valid_bbox, verify_cache,
 };
+use data_updates::DataUpdates;
+use export_queue::ExportQueue;
+use gis_operation::regions::RegionDirectory;
+use std::sync::RwLock;
 
 pub mod app_setup;
+pub mod batch;
+pub mod busy;
 pub mod commands;
+pub mod data_updates;
 pub mod dependency;
+pub mod diagnostics;
+pub mod estimate;
+pub mod events;
+pub mod export_queue;
+pub mod fetcher;
+pub mod gdal_version;
 pub mod gis_operation;
+pub mod i18n;
+pub mod logging;
+#[path = "../../shared/payloads.rs"]
+pub mod payloads;
+pub mod pipeline;
+pub mod progress;
+pub mod timings;
 pub mod utils;
 pub mod web_request;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    setup_check().expect("Setup check failed");
+    if let Err(e) = logging::init_logging(&utils::log_level()) {
+        eprintln!("Journalisation indisponible: {}", e);
+    }
+    // La fenêtre s'ouvre même si la configuration est incomplète : le frontend affiche
+    // alors l'écran « Configuration requise » (voir `get_setup_report`).
+    let setup = setup_check();
+    let regions = RegionDirectory::load().unwrap_or_else(|e| {
+        tracing::error!(error = %e, "Liste des départements indisponible");
+        RegionDirectory::default()
+    });
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(RwLock::new(regions))
+        .manage(RwLock::new(setup))
+        .manage(ExportQueue::default())
+        .manage(BusyProjects::default())
+        .manage(DataUpdates::default())
+        .setup(|app| {
+            commands::start_data_update_check(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             create_project_com,
             get_projects,
+            list_projects,
             get_os,
             export,
+            list_export_targets,
             delete_project,
+            import_project,
             get_settings,
             save_settings,
-            clear_cache
+            clear_cache,
+            run_diagnostics,
+            open_project_folder,
+            reveal_export,
+            rename_project,
+            clone_project,
+            verify_cache,
+            get_layer_config,
+            save_layer_config,
+            resume_project_creation,
+            export_map_pdf,
+            generate_qgis_project,
+            add_custom_layer,
+            get_tile_grid,
+            get_project_tile,
+            get_project_details,
+            get_creation_params,
+            get_cache_status,
+            estimate_project,
+            get_dpts_list,
+            get_region_neighbors,
+            refresh_ortho,
+            get_recent_logs,
+            queue_export,
+            get_export_queue,
+            cancel_export,
+            get_busy_projects,
+            suggest_valid_bbox,
+            get_timings,
+            check_project_conflict,
+            rebuild_regions_graph,
+            get_setup_report,
+            recheck_setup,
+            get_legend,
+            get_data_update_status,
+            check_data_updates,
+            refresh_cache,
+            identify_pixel,
+            identify_point,
+            create_projects_batch
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
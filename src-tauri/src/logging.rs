@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Identifiant de l'application, qui nomme son dossier de données (comme `app_data_dir` de Tauri).
+pub const APP_IDENTIFIER: &str = "com.firefront-gis.app";
+
+/// Préfixe des fichiers journaux : `firefront.<date>.log`.
+pub const LOG_FILE_PREFIX: &str = "firefront";
+
+/// Nombre de fichiers journaux quotidiens conservés.
+pub const MAX_LOG_FILES: usize = 7;
+
+/// Niveaux de journalisation acceptés dans la configuration.
+pub const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Garde de l'écriture en tâche de fond : tant qu'elle existe, les journaux sont écrits.
+static LOG_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Dossier des journaux : `<données de l'application>/logs`.
+pub fn logs_dir() -> PathBuf {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.data_dir().join(APP_IDENTIFIER))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("logs")
+}
+
+/// Vérifie qu'un niveau de journalisation est reconnu.
+///
+/// # Arguments
+///
+/// * `level` - Le niveau, par exemple `info`.
+///
+/// # Returns
+///
+/// * `Result<String, String>` - Le niveau en minuscules, ou une erreur s'il est inconnu.
+pub fn parse_log_level(level: &str) -> Result<String, String> {
+    let level = level.trim().to_lowercase();
+    if LOG_LEVELS.contains(&level.as_str()) {
+        Ok(level)
+    } else {
+        Err(format!("Niveau de journalisation inconnu: {}", level))
+    }
+}
+
+/// Initialise la journalisation dans un fichier quotidien de `logs_dir()`.
+/// Les durées des spans (étapes de la création) sont enregistrées à leur fermeture.
+/// Sans effet si la journalisation est déjà initialisée.
+///
+/// # Arguments
+///
+/// * `level` - Le niveau minimal des enregistrements ; `RUST_LOG` est prioritaire s'il est défini.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si le dossier des journaux ne peut pas être créé.
+pub fn init_logging(level: &str) -> Result<(), Box<dyn Error>> {
+    if LOG_GUARD.get().is_some() {
+        return Ok(());
+    }
+
+    let dir = logs_dir();
+    fs::create_dir_all(&dir)?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(parse_log_level(level).unwrap_or_else(|_| "info".to_string()))
+    });
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .try_init()?;
+
+    let _ = LOG_GUARD.set(guard);
+    Ok(())
+}
+
+/// Fichier journal le plus récent d'un dossier.
+fn latest_log_file(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX) && name.ends_with(".log"))
+        })
+        .max()
+}
+
+/// Dernières lignes du journal le plus récent d'un dossier.
+///
+/// # Arguments
+///
+/// * `dir` - Le dossier des journaux.
+/// * `lines` - Le nombre de lignes à renvoyer.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, Box<dyn Error>>` - Les lignes, des plus anciennes aux plus récentes ;
+///   aucune si le dossier ne contient pas de journal.
+pub fn recent_log_lines(dir: &Path, lines: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let Some(path) = latest_log_file(dir) else {
+        return Ok(Vec::new());
+    };
+    let content = fs::read_to_string(path)?;
+    let all: Vec<&str> = content.lines().collect();
+    let start = all.len().saturating_sub(lines);
+    Ok(all[start..].iter().map(|line| line.to_string()).collect())
+}
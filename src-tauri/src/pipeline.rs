@@ -0,0 +1,1526 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tracing::Instrument;
+
+use crate::app_setup::RunConfig;
+use crate::busy::CancelToken;
+use crate::events::{ProgressUpdate, Warning};
+use crate::fetcher::DataFetcher;
+use crate::gis_operation::{
+    clip_to_bb, create_project_in_crs, fusion_datasets,
+    layer_config::{LayerConfig, RPG_LAYER, VEGETATION_LAYER, load_layer_config},
+    layers::{add_layers, check_archive_layers, prepare_layers, prepare_regional_layer},
+    oso::VegetationSource,
+    regions::find_project_regions,
+    tiles::build_project_overviews,
+};
+use crate::i18n;
+use crate::progress::ProgressSink;
+use crate::timings::{StageTimings, stage_timer, take_processing_timings};
+use crate::utils::{
+    BoundingBox, DataVintage, ProjectMetadata, WorkDir, clean_tmp_except_gpkg, default_land_color,
+    export_to_jpg, generate_project_preview, get_incomplete_projects, get_project_bounding_box,
+    load_project_metadata, mark_project_complete, pin_cache_entries, project_dir, project_regions,
+    project_tiff, read_cache_vintages, regions_epsg, resource_gpkg, save_project_metadata,
+    update_project_size,
+};
+use crate::web_request::{
+    ArchiveUrl, DepartmentArchives, DownloadJob, DownloadProgress, department_cache_paths,
+};
+
+/// État de la création enregistré dans le dossier du projet après chaque étape.
+pub const PIPELINE_STATE_FILE: &str = "pipeline_state.json";
+
+/// Dossier du projet conservant les GPKG préparés jusqu'à leur fusion.
+pub const STAGING_DIR: &str = "staging";
+
+/// Étapes de la création d'un projet, dans leur ordre d'exécution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stage {
+    Download,
+    Prepare,
+    Fuse,
+    AddLayers,
+    ExportJpeg,
+    Ortho,
+    Cleanup,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Download,
+        Stage::Prepare,
+        Stage::Fuse,
+        Stage::AddLayers,
+        Stage::ExportJpeg,
+        Stage::Ortho,
+        Stage::Cleanup,
+    ];
+
+    /// Clé de l'étape dans les durées de la création (`StageTimings`).
+    pub fn key(&self) -> &'static str {
+        match self {
+            Stage::Download => "download",
+            Stage::Prepare => "prepare",
+            Stage::Fuse => "fuse",
+            Stage::AddLayers => "add_layers",
+            Stage::ExportJpeg => "export_jpeg",
+            Stage::Ortho => "ortho",
+            Stage::Cleanup => "cleanup",
+        }
+    }
+}
+
+/// GPKG découpés d'un département, produits par l'étape `Prepare`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PreparedRegion {
+    pub code: String,
+    pub regional: String,
+    pub vegetation: String,
+    pub rpg: String,
+    /// Couches BDTOPO, par nom de couche.
+    pub topo: HashMap<String, Vec<String>>,
+    /// Couches sans entité dans l'emprise du projet, écartées de la fusion.
+    #[serde(default)]
+    pub empty_layers: Vec<String>,
+}
+
+impl PreparedRegion {
+    /// Le GPKG de la couche n'a aucune entité dans l'emprise du projet.
+    pub fn is_layer_empty(&self, layer_name: &str) -> bool {
+        self.empty_layers.iter().any(|name| name == layer_name)
+    }
+}
+
+/// Avancement d'une création, relu par `resume_project_creation`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineState {
+    pub name: String,
+    pub project_bb: BoundingBox,
+    /// Source de végétation au format des métadonnées (`VegetationSource::as_metadata_string`).
+    pub vegetation_source: String,
+    pub regions: Vec<String>,
+    pub completed: Vec<Stage>,
+    #[serde(default)]
+    pub prepared: Vec<PreparedRegion>,
+    /// Durées des étapes terminées, conservées pour une reprise.
+    #[serde(default)]
+    pub timings: StageTimings,
+    /// Couches retenues (voir `LayerConfig::with_selection`), toutes si `None`.
+    #[serde(default)]
+    pub layers: Option<Vec<String>>,
+    /// Création hors ligne, décidée par l'étape de téléchargement : l'orthophoto n'est
+    /// pas téléchargée.
+    #[serde(default)]
+    pub offline: bool,
+    /// Projet terminé dont les couches sont reprises pour `reused_regions`
+    /// (voir `create_project_reusing`).
+    #[serde(default)]
+    pub reuse_source: Option<String>,
+    /// Départements dont la partie de l'emprise est couverte par `reuse_source` :
+    /// leurs archives ne sont ni téléchargées ni extraites.
+    #[serde(default)]
+    pub reused_regions: Vec<String>,
+}
+
+impl PipelineState {
+    pub fn new(
+        name: &str,
+        project_bb: &BoundingBox,
+        vegetation_source: &VegetationSource,
+        regions: Vec<String>,
+    ) -> Self {
+        PipelineState {
+            name: name.to_string(),
+            project_bb: *project_bb,
+            vegetation_source: vegetation_source.as_metadata_string(),
+            regions,
+            completed: Vec::new(),
+            prepared: Vec::new(),
+            timings: StageTimings::default(),
+            layers: None,
+            offline: false,
+            reuse_source: None,
+            reused_regions: Vec::new(),
+        }
+    }
+
+    /// Départements dont les archives sont téléchargées et extraites : ceux du projet,
+    /// hors départements repris d'un projet source.
+    pub fn archive_regions(&self) -> Vec<String> {
+        self.regions
+            .iter()
+            .filter(|code| !self.reused_regions.contains(code))
+            .cloned()
+            .collect()
+    }
+
+    /// Première étape non terminée, ou `None` si la création est achevée.
+    pub fn next_stage(&self) -> Option<Stage> {
+        Stage::ALL
+            .into_iter()
+            .find(|stage| !self.completed.contains(stage))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_stage().is_none()
+    }
+
+    pub fn mark_completed(&mut self, stage: Stage) {
+        if !self.completed.contains(&stage) {
+            self.completed.push(stage);
+        }
+    }
+
+    /// Lit l'état enregistré dans le dossier d'un projet.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_folder` - dossier du projet
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PipelineState, Box<dyn std::error::Error>>` - l'état lu
+    pub fn load(project_folder: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(project_folder.join(PIPELINE_STATE_FILE))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Enregistre l'état dans le dossier du projet.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_folder` - dossier du projet
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'enregistrement a réussi
+    pub fn save(&self, project_folder: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(
+            project_folder.join(PIPELINE_STATE_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Données partagées par les étapes d'une création.
+pub struct PipelineContext {
+    pub project_folder: PathBuf,
+    pub state: PipelineState,
+    pub layer_config: LayerConfig,
+    pub warnings: Vec<Warning>,
+    /// Demande d'annulation, consultée avant chaque étape.
+    pub cancel: CancelToken,
+    /// Paramètres figés au lancement, utilisés par toutes les étapes.
+    pub run_config: RunConfig,
+}
+
+impl PipelineContext {
+    pub fn new(project_folder: PathBuf, state: PipelineState, layer_config: LayerConfig) -> Self {
+        PipelineContext {
+            project_folder,
+            state,
+            layer_config,
+            warnings: Vec::new(),
+            cancel: CancelToken::default(),
+            run_config: RunConfig::snapshot(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    pub fn folder_str(&self) -> String {
+        self.project_folder.to_string_lossy().to_string()
+    }
+
+    pub fn project_file_path(&self) -> String {
+        self.project_folder
+            .join(format!("{}.tiff", self.state.name))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Image de végétation du projet (`<nom>_VEGET.jpeg`).
+    pub fn veget_jpeg_path(&self) -> PathBuf {
+        self.project_folder
+            .join(format!("{}_VEGET.jpeg", self.state.name))
+    }
+
+    /// Orthophotographie du projet (`<nom>_ORTHO.jpeg`).
+    pub fn ortho_jpeg_path(&self) -> PathBuf {
+        self.project_folder
+            .join(format!("{}_ORTHO.jpeg", self.state.name))
+    }
+
+    pub fn resource_path(&self, layer_name: &str) -> String {
+        resource_gpkg(&self.project_folder, layer_name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn staging_dir(&self) -> PathBuf {
+        self.project_folder.join(STAGING_DIR)
+    }
+}
+
+/// Exécute une étape de la création. Permet de substituer les étapes réelles,
+/// par exemple pour simuler une interruption.
+pub trait StageRunner {
+    fn run_stage(
+        &mut self,
+        stage: Stage,
+        ctx: &mut PipelineContext,
+    ) -> impl Future<Output = Result<(), String>>;
+}
+
+/// Exécute les étapes restantes d'une création à partir de la première étape non terminée,
+/// en enregistrant l'état après chacune d'elles. Le marqueur de fin de création est écrit
+/// une fois la dernière étape terminée. La durée de chaque étape est ajoutée à
+/// `ctx.state.timings`. Si l'annulation est demandée, la création s'arrête avant l'étape suivante.
+///
+/// # Arguments
+///
+/// * `runner` - exécution des étapes
+/// * `ctx` - contexte de la création
+///
+/// # Returns
+///
+/// * `Result<(), String>` - l'erreur de la première étape en échec, ou de l'annulation
+pub async fn run_pipeline<R: StageRunner>(
+    runner: &mut R,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    while let Some(stage) = ctx.state.next_stage() {
+        if ctx.cancel.is_cancelled() {
+            tracing::info!(project = %ctx.name(), stage = ?stage, "Création annulée");
+            return Err(i18n::text("error.creation_cancelled", &[]));
+        }
+        // La durée de chaque étape est enregistrée dans le journal à la fermeture du span.
+        let span = tracing::info_span!("stage", project = %ctx.name(), stage = ?stage);
+        let mut stage_timings = StageTimings::default();
+        let result = {
+            let _timer = stage_timer(&mut stage_timings, stage.key());
+            runner.run_stage(stage, ctx).instrument(span).await
+        };
+        ctx.state.timings.extend(stage_timings);
+        if let Err(e) = result {
+            tracing::error!(
+                project = %ctx.name(),
+                stage = ?stage,
+                error = %e,
+                "Échec de l'étape"
+            );
+            return Err(e);
+        }
+        ctx.state.mark_completed(stage);
+        ctx.state.save(&ctx.project_folder).map_err(|e| {
+            format!(
+                "Erreur lors de l'enregistrement de l'état de la création: {:?}",
+                e
+            )
+        })?;
+    }
+    mark_project_complete(&ctx.project_folder).map_err(|e| {
+        format!(
+            "Erreur lors de l'enregistrement de la fin de la création: {:?}",
+            e
+        )
+    })
+}
+
+/// Étapes réelles de la création. La progression est transmise à `progress`
+/// et les accès réseau passent par `fetcher`.
+pub struct CreationStages<'a> {
+    pub progress: &'a dyn ProgressSink,
+    pub fetcher: &'a dyn DataFetcher,
+    pub work_dir: WorkDir,
+}
+
+impl StageRunner for CreationStages<'_> {
+    async fn run_stage(&mut self, stage: Stage, ctx: &mut PipelineContext) -> Result<(), String> {
+        match stage {
+            Stage::Download => download_stage(self.progress, self.fetcher, ctx).await,
+            Stage::Prepare => prepare_stage(self.progress, &self.work_dir, ctx).await,
+            Stage::Fuse => fuse_stage(self.progress, ctx).await,
+            Stage::AddLayers => add_layers_stage(self.progress, &self.work_dir, ctx).await,
+            Stage::ExportJpeg => export_jpeg_stage(self.progress, ctx).await,
+            Stage::Ortho => ortho_stage(self.progress, self.fetcher, ctx).await,
+            Stage::Cleanup => cleanup_stage(self.progress, ctx).await,
+        }
+    }
+}
+
+/// Télécharge (ou vérifie dans le cache) les archives des départements du projet.
+/// Hors ligne (paramètre `offline_mode`, ou serveurs injoignables lors de la recherche
+/// des URLs), les archives sont lues dans le cache sans téléchargement.
+async fn download_stage(
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    let required_archives = ctx.layer_config.required_archives();
+    let archive_regions = ctx.state.archive_regions();
+    let mut offline = ctx.run_config.offline_mode;
+    let mut department_archives = Vec::new();
+    if !offline {
+        let mut url_errors = Vec::new();
+        let mut network_errors = 0;
+        for department in fetcher.resolve_urls(&archive_regions).await {
+            match department.archives {
+                Ok(archives) => department_archives.push((department.code, archives)),
+                Err(e) => {
+                    if department.network_error {
+                        network_errors += 1;
+                    }
+                    url_errors.push(e);
+                }
+            }
+        }
+        if !url_errors.is_empty() && network_errors < url_errors.len() {
+            return Err(format!(
+                "Erreur lors de la recherche des fichiers: {}",
+                url_errors.join("; ")
+            ));
+        }
+        if !url_errors.is_empty() {
+            tracing::warn!(
+                errors = %url_errors.join("; "),
+                "Serveurs injoignables, création hors ligne à partir du cache"
+            );
+            ctx.warnings.push(Warning::new(
+                "Hors ligne",
+                "serveurs injoignables : projet créé à partir des archives du cache, sans orthophoto",
+            ));
+            offline = true;
+        }
+    }
+    if offline {
+        department_archives = cached_department_archives(
+            &ctx.run_config.cache_dir,
+            &archive_regions,
+            &required_archives,
+        )?;
+        ctx.state.offline = true;
+    }
+
+    progress.report_update(ProgressUpdate::stage("stage.download"));
+
+    // Les archives sans couche sélectionnée ne sont pas téléchargées.
+    let jobs: Vec<DownloadJob> = department_archives
+        .iter()
+        .flat_map(|(code, archives)| {
+            department_archive_list(archives)
+                .into_iter()
+                .filter(|(file_type, _)| required_archives.contains(file_type))
+                .map(|(file_type, archive)| DownloadJob {
+                    file_type: file_type.to_string(),
+                    code: code.clone(),
+                    url: archive.url.clone(),
+                })
+        })
+        .collect();
+    let data_warnings = if required_archives.contains("BDFORET") {
+        bdforet_fallback_warnings(&department_archives)
+    } else {
+        Vec::new()
+    };
+    record_vintages(
+        ctx.name(),
+        &department_archives,
+        &required_archives,
+        &data_warnings,
+        offline,
+        &ctx.state.reused_regions,
+    )?;
+    ctx.warnings.extend(data_warnings);
+    if offline {
+        return Ok(());
+    }
+
+    let archive_timings = Mutex::new(StageTimings::default());
+    let on_progress = |download: DownloadProgress| {
+        if let Some(seconds) = download.archive_seconds {
+            archive_timings
+                .lock()
+                .unwrap()
+                .add(&format!("download/{}", download.label), seconds);
+        }
+        let update = if download.label.is_empty() {
+            let received_mb = (download.downloaded_bytes / (1024 * 1024)).to_string();
+            ProgressUpdate::step(
+                "stage.download",
+                "step.received_mb",
+                &[&received_mb],
+                download.completed,
+                download.total,
+            )
+        } else {
+            ProgressUpdate::step(
+                "stage.download",
+                "step.download_file",
+                &[&download.label],
+                download.completed,
+                download.total,
+            )
+        };
+        progress.report_update(update);
+    };
+    let result = fetcher.download_archives(jobs, &on_progress).await;
+    ctx.state
+        .timings
+        .extend(archive_timings.into_inner().unwrap());
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Erreur lors du téléchargement des fichiers SHP: {}", e))
+}
+
+/// Archives des départements lues dans le cache, pour une création hors ligne. L'URL d'origine
+/// d'une archive, enregistrée avec son millésime, en donne la date et la version ; à défaut,
+/// le chemin de l'archive en tient lieu.
+///
+/// # Arguments
+///
+/// * `cache_dir` - Dossier du cache des archives.
+/// * `regions` - Codes des départements du projet.
+/// * `required_archives` - Types d'archives nécessaires aux couches sélectionnées.
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, DepartmentArchives)>, String>` - Les archives de chaque département, ou la liste des archives absentes du cache.
+fn cached_department_archives(
+    cache_dir: &Path,
+    regions: &[String],
+    required_archives: &BTreeSet<&str>,
+) -> Result<Vec<(String, DepartmentArchives)>, String> {
+    let vintages = read_cache_vintages(cache_dir);
+    let mut missing = Vec::new();
+    let mut department_archives = Vec::new();
+    for code in regions {
+        let [topo, foret, rpg] = ["BDTOPO", "BDFORET", "RPG"].map(|file_type| {
+            let file_name = format!("{}_{}.7z", file_type, code);
+            let path = cache_dir.join(&file_name);
+            if required_archives.contains(file_type) && !path.exists() {
+                missing.push(file_name.clone());
+            }
+            let url = vintages
+                .get(&file_name)
+                .map(|vintage| vintage.url.clone())
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| format!("file://{}", path.display()));
+            ArchiveUrl::new(&url)
+        });
+        department_archives.push((code.clone(), DepartmentArchives { topo, foret, rpg }));
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "Création hors ligne impossible, archives absentes du cache: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(department_archives)
+}
+
+/// Avertissements des départements pour lesquels seule la BD Forêt v1 est disponible.
+fn bdforet_fallback_warnings(department_archives: &[(String, DepartmentArchives)]) -> Vec<Warning> {
+    department_archives
+        .iter()
+        .filter(|(_, archives)| archives.foret.is_bdforet_v1())
+        .map(|(code, _)| {
+            Warning::new(
+                format!("BD Forêt {}", code),
+                "aucune archive BD Forêt v2, la v1 est utilisée (végétation classée par type de formation)",
+            )
+        })
+        .collect()
+}
+
+/// Ajoute aux avertissements sur les données des métadonnées du projet ceux de la préparation
+/// des couches (couches absentes, entités perdues au découpage), sans doublon en cas de reprise.
+fn record_data_warnings(project_name: &str, warnings: &[Warning]) -> Result<(), String> {
+    if warnings.is_empty() {
+        return Ok(());
+    }
+    let mut metadata = load_project_metadata(project_name);
+    for warning in warnings {
+        if !metadata.data_warnings.contains(warning) {
+            metadata.data_warnings.push(warning.clone());
+        }
+    }
+    save_project_metadata(project_name, &metadata).map_err(|e| {
+        format!(
+            "Erreur lors de l'enregistrement des avertissements: {:?}",
+            e
+        )
+    })
+}
+
+/// Archives d'un département, par type.
+fn department_archive_list(archives: &DepartmentArchives) -> [(&'static str, &ArchiveUrl); 3] {
+    [
+        ("BDTOPO", &archives.topo),
+        ("BDFORET", &archives.foret),
+        ("RPG", &archives.rpg),
+    ]
+}
+
+/// Enregistre dans les métadonnées du projet les millésimes des archives téléchargées,
+/// les avertissements sur ces archives et la création hors ligne. Les millésimes des
+/// départements repris d'un projet source, copiés à l'initialisation, sont conservés.
+fn record_vintages(
+    project_name: &str,
+    department_archives: &[(String, DepartmentArchives)],
+    required_archives: &BTreeSet<&str>,
+    data_warnings: &[Warning],
+    offline: bool,
+    reused_regions: &[String],
+) -> Result<(), String> {
+    let mut metadata = load_project_metadata(project_name);
+    metadata.data_warnings = data_warnings.to_vec();
+    metadata.offline = offline;
+    metadata
+        .vintages
+        .retain(|vintage| reused_regions.contains(&vintage.region));
+    metadata
+        .vintages
+        .extend(department_archives.iter().flat_map(|(code, archives)| {
+            department_archive_list(archives)
+                .into_iter()
+                .filter(|(data_type, _)| required_archives.contains(data_type))
+                .map(|(data_type, archive)| DataVintage {
+                    data_type: data_type.to_string(),
+                    region: code.clone(),
+                    date: archive.date.map(|date| date.format("%Y-%m-%d").to_string()),
+                    url: archive.url.clone(),
+                })
+        }));
+    save_project_metadata(project_name, &metadata)
+        .map_err(|e| format!("Erreur lors de l'enregistrement des millésimes: {:?}", e))
+}
+
+/// Déplace un fichier, y compris entre deux systèmes de fichiers.
+fn move_file(source: &str, destination: &Path) -> std::io::Result<()> {
+    if fs::rename(source, destination).is_err() {
+        fs::copy(source, destination)?;
+        fs::remove_file(source)?;
+    }
+    Ok(())
+}
+
+/// Déplace un GPKG préparé dans le dossier `staging` du projet, pour qu'il survive à une interruption.
+/// Le chemin vide d'une couche non sélectionnée est conservé tel quel.
+fn stage_gpkg(path: &str, staging_dir: &Path) -> Result<String, String> {
+    if path.is_empty() {
+        return Ok(String::new());
+    }
+    let file_name = Path::new(path)
+        .file_name()
+        .ok_or(format!("Chemin de GPKG invalide: {}", path))?;
+    let destination = staging_dir.join(file_name);
+    move_file(path, &destination)
+        .map_err(|e| format!("Erreur lors de la conservation de {}: {:?}", path, e))?;
+    Ok(destination.to_string_lossy().to_string())
+}
+
+/// Vérifie le contenu des archives, puis extrait, convertit et découpe les couches de chaque
+/// département. Les départements repris d'un projet source sont préparés en dernier
+/// (voir `prepare_reused_regions`).
+async fn prepare_stage(
+    progress: &dyn ProgressSink,
+    work_dir: &WorkDir,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    progress.report_update(ProgressUpdate::step(
+        "stage.prepare",
+        "step.check_archives",
+        &[],
+        0,
+        1,
+    ));
+    let archive_regions = ctx.state.archive_regions();
+    let archive_warnings = check_archive_layers(
+        &ctx.run_config.cache_dir,
+        &archive_regions,
+        &ctx.layer_config,
+    )?;
+    ctx.warnings.extend(archive_warnings);
+
+    progress.report_update(ProgressUpdate::stage("stage.prepare"));
+
+    let staging_dir = ctx.staging_dir();
+    fs::create_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+    ctx.state.prepared.clear();
+
+    let total_regions = ctx.state.regions.len();
+    for (idx, code) in archive_regions.iter().enumerate() {
+        progress.report_update(ProgressUpdate::step(
+            "stage.prepare",
+            "step.process_region",
+            &[&code],
+            idx + 1,
+            total_regions,
+        ));
+
+        let project_bb = ctx.state.project_bb;
+        let first_warning = ctx.warnings.len();
+        let (regional, vegetation, rpg, topo, empty_layers) = prepare_layers(
+            progress,
+            work_dir,
+            &project_bb,
+            code,
+            &ctx.layer_config,
+            &mut ctx.warnings,
+            &mut ctx.state.timings,
+            &ctx.run_config,
+        )
+        .await?;
+        record_data_warnings(ctx.name(), &ctx.warnings[first_warning..])?;
+
+        let mut staged_topo: HashMap<String, Vec<String>> = HashMap::new();
+        for (layer_name, paths) in topo {
+            for path in paths {
+                staged_topo
+                    .entry(layer_name.clone())
+                    .or_default()
+                    .push(stage_gpkg(&path, &staging_dir)?);
+            }
+        }
+        ctx.state.prepared.push(PreparedRegion {
+            code: code.clone(),
+            regional: stage_gpkg(&regional, &staging_dir)?,
+            vegetation: stage_gpkg(&vegetation, &staging_dir)?,
+            rpg: stage_gpkg(&rpg, &staging_dir)?,
+            topo: staged_topo,
+            empty_layers,
+        });
+
+        if let Err(e) = clean_tmp_except_gpkg(work_dir) {
+            return Err(format!(
+                "Erreur lors du nettoyage des fichiers temporaires: {:?}",
+                e
+            ));
+        }
+    }
+    prepare_reused_regions(progress, work_dir, ctx)?;
+
+    record_empty_layers(ctx.name(), &ctx.state.prepared)
+}
+
+/// Prépare les départements repris de `PipelineState::reuse_source` : l'étendue de chaque
+/// département, et les couches du projet source découpées sur l'emprise, rattachées au premier
+/// d'entre eux. Découpées sur l'emprise du projet source, les entités à cheval sur sa limite
+/// y sont tronquées : elles passent après celles des départements téléchargés, dont la fusion
+/// conserve la première occurrence.
+fn prepare_reused_regions(
+    progress: &dyn ProgressSink,
+    work_dir: &WorkDir,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    let Some(source) = ctx.state.reuse_source.clone() else {
+        return Ok(());
+    };
+    let staging_dir = ctx.staging_dir();
+    let project_bb = ctx.state.project_bb;
+    let total_regions = ctx.state.regions.len();
+    let first_index = total_regions - ctx.state.reused_regions.len();
+
+    for (idx, code) in ctx.state.reused_regions.clone().iter().enumerate() {
+        progress.report_update(ProgressUpdate::step(
+            "stage.prepare",
+            "step.process_region",
+            &[code],
+            first_index + idx + 1,
+            total_regions,
+        ));
+        let regional = prepare_regional_layer(work_dir, &project_bb, code)?;
+        let mut region = PreparedRegion {
+            code: code.clone(),
+            regional: stage_gpkg(&regional, &staging_dir)?,
+            ..PreparedRegion::default()
+        };
+
+        if idx == 0 {
+            let mut layers: Vec<&str> = ctx.layer_config.processing_set();
+            if ctx.layer_config.includes(RPG_LAYER) {
+                layers.insert(0, RPG_LAYER);
+            }
+            if ctx.layer_config.includes(VEGETATION_LAYER) {
+                layers.insert(0, VEGETATION_LAYER);
+            }
+            for layer_name in layers {
+                let source_gpkg = resource_gpkg(&project_dir(&source), layer_name);
+                if !source_gpkg.exists() {
+                    // Une couche BDTOPO absente des archives du projet source est ignorée,
+                    // comme à sa création ; la végétation et le RPG sont obligatoires.
+                    if layer_name == VEGETATION_LAYER || layer_name == RPG_LAYER {
+                        return Err(format!(
+                            "Couche {} absente du projet source {}",
+                            layer_name, source
+                        ));
+                    }
+                    continue;
+                }
+                let output_gpkg = staging_dir.join(format!("reuse_{}.gpkg", layer_name));
+                if output_gpkg.exists() {
+                    fs::remove_file(&output_gpkg).map_err(|e| e.to_string())?;
+                }
+                let output = output_gpkg.to_string_lossy().to_string();
+                let clip_report = clip_to_bb(&source_gpkg.to_string_lossy(), &output, &project_bb)
+                    .map_err(|e| {
+                        format!(
+                            "Erreur lors du découpage de la couche {} du projet {}: {:?}",
+                            layer_name, source, e
+                        )
+                    })?;
+                if clip_report.output_features == 0 {
+                    region.empty_layers.push(layer_name.to_string());
+                }
+                match layer_name {
+                    VEGETATION_LAYER => region.vegetation = output,
+                    RPG_LAYER => region.rpg = output,
+                    _ => {
+                        region.topo.insert(layer_name.to_string(), vec![output]);
+                    }
+                }
+            }
+        }
+        ctx.state.prepared.push(region);
+
+        clean_tmp_except_gpkg(work_dir)
+            .map_err(|e| format!("Erreur lors du nettoyage des fichiers temporaires: {:?}", e))?;
+    }
+    Ok(())
+}
+
+/// Enregistre dans les métadonnées du projet les couches sans entité dans l'emprise,
+/// par département. Les listes sont remplacées, pour qu'une reprise ne les cumule pas.
+fn record_empty_layers(project_name: &str, prepared: &[PreparedRegion]) -> Result<(), String> {
+    let mut metadata = load_project_metadata(project_name);
+    metadata.empty_layers = prepared
+        .iter()
+        .filter(|region| !region.empty_layers.is_empty())
+        .map(|region| (region.code.clone(), region.empty_layers.clone()))
+        .collect();
+    save_project_metadata(project_name, &metadata)
+        .map_err(|e| format!("Erreur lors de l'enregistrement des couches vides: {:?}", e))
+}
+
+/// GPKG à fusionner pour une couche : ceux des départements qui ont des entités dans
+/// l'emprise du projet, `None` si aucun n'en a.
+fn fusion_inputs<'a>(
+    layer_name: &str,
+    candidates: impl IntoIterator<Item = (&'a PreparedRegion, &'a String)>,
+) -> Option<Vec<String>> {
+    let inputs: Vec<String> = candidates
+        .into_iter()
+        .filter(|(region, path)| !path.is_empty() && !region.is_layer_empty(layer_name))
+        .map(|(_, path)| path.clone())
+        .collect();
+    (!inputs.is_empty()).then_some(inputs)
+}
+
+/// Fusionne les GPKG préparés de chaque département dans les ressources du projet.
+/// La végétation et le RPG ne sont fusionnés que s'ils font partie de la sélection.
+/// Les GPKG sans entité dans l'emprise sont écartés ; une couche vide dans tous les
+/// départements est copiée telle quelle, sans fusion ni étape de progression.
+async fn fuse_stage(progress: &dyn ProgressSink, ctx: &mut PipelineContext) -> Result<(), String> {
+    let prepared = &ctx.state.prepared;
+    if prepared.is_empty() {
+        return Err("Aucune couche préparée à fusionner".to_string());
+    }
+
+    let layer_inputs = |layer_name: &str, select: fn(&PreparedRegion) -> &String| {
+        fusion_inputs(
+            layer_name,
+            prepared.iter().map(|region| (region, select(region))),
+        )
+    };
+    let vegetation_inputs = ctx
+        .layer_config
+        .includes(VEGETATION_LAYER)
+        .then(|| layer_inputs(VEGETATION_LAYER, |region| &region.vegetation));
+    let rpg_inputs = ctx
+        .layer_config
+        .includes(RPG_LAYER)
+        .then(|| layer_inputs(RPG_LAYER, |region| &region.rpg));
+    let total_steps = 2
+        + vegetation_inputs.as_ref().is_some_and(Option::is_some) as usize
+        + rpg_inputs.as_ref().is_some_and(Option::is_some) as usize;
+    progress.report_update(ProgressUpdate::step(
+        "stage.fuse",
+        "step.merge_regions",
+        &[],
+        1,
+        total_steps,
+    ));
+
+    let regional_merged_gpkg = ctx.resource_path(ctx.name());
+    let vegetation_merged_gpkg = ctx.resource_path(VEGETATION_LAYER);
+    let rpg_merged_gpkg = ctx.resource_path(RPG_LAYER);
+
+    let mut topo_candidates: HashMap<&String, Vec<(&PreparedRegion, &String)>> = HashMap::new();
+    for region in prepared {
+        for (layer_name, paths) in &region.topo {
+            topo_candidates
+                .entry(layer_name)
+                .or_default()
+                .extend(paths.iter().map(|path| (region, path)));
+        }
+    }
+
+    if prepared.len() > 1 {
+        let mut step = 1;
+        progress.report_update(ProgressUpdate::step(
+            "stage.fuse",
+            "step.merge_regional",
+            &[],
+            step,
+            total_steps,
+        ));
+        let regional: Vec<String> = prepared
+            .iter()
+            .map(|region| region.regional.clone())
+            .collect();
+        if let Err(e) = fusion_datasets(&regional, &regional_merged_gpkg, false) {
+            return Err(format!(
+                "Erreur lors de la fusion des couches régionales: {:?}",
+                e
+            ));
+        }
+
+        // Couches vides dans tous les départements : le GPKG vide du premier en tient lieu.
+        let mut empty_layers: Vec<(&str, &String)> = Vec::new();
+
+        let mut duplicates_removed = 0;
+        match vegetation_inputs {
+            Some(Some(inputs)) => {
+                step += 1;
+                progress.report_update(ProgressUpdate::step(
+                    "stage.fuse",
+                    "step.merge_vegetation",
+                    &[],
+                    step,
+                    total_steps,
+                ));
+                duplicates_removed += fusion_datasets(&inputs, &vegetation_merged_gpkg, true)
+                    .map_err(|e| {
+                        format!(
+                            "Erreur lors de la fusion des couches de végétation: {:?}",
+                            e
+                        )
+                    })?;
+            }
+            Some(None) => empty_layers.push((VEGETATION_LAYER, &prepared[0].vegetation)),
+            None => {}
+        }
+
+        match rpg_inputs {
+            Some(Some(inputs)) => {
+                step += 1;
+                progress.report_update(ProgressUpdate::step(
+                    "stage.fuse",
+                    "step.merge_rpg",
+                    &[],
+                    step,
+                    total_steps,
+                ));
+                duplicates_removed += fusion_datasets(&inputs, &rpg_merged_gpkg, true)
+                    .map_err(|e| format!("Erreur lors de la fusion des couches RPG: {:?}", e))?;
+            }
+            Some(None) => empty_layers.push((RPG_LAYER, &prepared[0].rpg)),
+            None => {}
+        }
+
+        progress.report_update(ProgressUpdate::step(
+            "stage.fuse",
+            "step.merge_topo",
+            &[],
+            step + 1,
+            total_steps,
+        ));
+
+        let mut topo_inputs = Vec::new();
+        for (layer_name, candidates) in &topo_candidates {
+            match fusion_inputs(layer_name, candidates.iter().copied()) {
+                Some(inputs) => topo_inputs.push((layer_name.as_str(), inputs)),
+                None => empty_layers.push((layer_name.as_str(), candidates[0].1)),
+            }
+        }
+        let total_topo_layers = topo_inputs.len();
+        for (topo_count, (layer_name, paths)) in topo_inputs.iter().enumerate() {
+            progress.report_update(ProgressUpdate::step(
+                "stage.fuse",
+                "step.merge_layer",
+                &[layer_name],
+                topo_count + 1,
+                total_topo_layers,
+            ));
+            duplicates_removed += fusion_datasets(paths, &ctx.resource_path(layer_name), true)
+                .map_err(|e| {
+                    format!(
+                        "Erreur lors de la fusion des couches topo {}: {:?}",
+                        layer_name, e
+                    )
+                })?;
+        }
+
+        for (layer_name, path) in empty_layers {
+            tracing::info!(
+                project = ctx.name(),
+                layer = layer_name,
+                "Aucune entité dans l'emprise du projet, couche copiée sans fusion"
+            );
+            if let Err(e) = fs::copy(path, ctx.resource_path(layer_name)) {
+                return Err(format!(
+                    "Erreur lors de la copie de la couche {}: {:?}",
+                    layer_name, e
+                ));
+            }
+        }
+
+        tracing::info!(
+            project = ctx.name(),
+            duplicates_removed,
+            "Entités dupliquées supprimées lors de la fusion"
+        );
+    } else {
+        progress.report_update(ProgressUpdate::step(
+            "stage.fuse",
+            "step.copy_single_region",
+            &[],
+            1,
+            1,
+        ));
+
+        let region = &prepared[0];
+        for (source, destination, label) in [
+            (&region.regional, &regional_merged_gpkg, "régionale"),
+            (&region.vegetation, &vegetation_merged_gpkg, "de végétation"),
+            (&region.rpg, &rpg_merged_gpkg, "RPG"),
+        ] {
+            // Couche hors de la sélection : rien n'a été préparé.
+            if source.is_empty() {
+                continue;
+            }
+            // Copie plutôt que déplacement : une reprise de l'étape retrouve les fichiers préparés.
+            if let Err(e) = fs::copy(source, destination) {
+                return Err(format!(
+                    "Erreur lors de la copie de la couche {}: {:?}",
+                    label, e
+                ));
+            }
+        }
+
+        for (layer_name, candidates) in &topo_candidates {
+            if let Some((_, path)) = candidates.first() {
+                if let Err(e) = fs::copy(path, ctx.resource_path(layer_name)) {
+                    return Err(format!(
+                        "Erreur lors de la copie de la couche topo {}: {:?}",
+                        layer_name, e
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Applique les couches sur un raster de projet vierge.
+/// Le raster est recréé afin qu'une reprise ne cumule pas les couches d'une tentative interrompue.
+async fn add_layers_stage(
+    progress: &dyn ProgressSink,
+    work_dir: &WorkDir,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    progress.report_update(ProgressUpdate::stage("stage.add_layers"));
+
+    let project_file_path = ctx.project_file_path();
+    let epsg = regions_epsg(&ctx.state.regions)?;
+    if let Err(e) = create_project_in_crs(
+        &project_file_path,
+        &ctx.state.project_bb,
+        epsg,
+        &ctx.run_config,
+    ) {
+        return Err(format!("Erreur lors de la création du projet: {:?}", e));
+    }
+
+    let vegetation_source = VegetationSource::parse(&ctx.state.vegetation_source)?;
+    // Les durées de rastérisation et de superposition sont cumulées sur le fil courant.
+    take_processing_timings();
+    let result = add_layers(
+        progress,
+        work_dir,
+        &ctx.folder_str(),
+        &project_file_path,
+        &ctx.state.name,
+        &vegetation_source,
+        &ctx.layer_config,
+        &mut ctx.warnings,
+        &ctx.run_config,
+    );
+    ctx.state
+        .timings
+        .extend(take_processing_timings().with_prefix(Stage::AddLayers.key()));
+    if let Err(e) = result {
+        return Err(format!("Erreur lors de l'ajout des couches: {:?}", e));
+    }
+
+    Ok(())
+}
+
+/// Exporte le raster du projet en JPEG (`<nom>_VEGET.jpeg`).
+async fn export_jpeg_stage(
+    progress: &dyn ProgressSink,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    progress.report_update(ProgressUpdate::stage("stage.finalize"));
+    progress.report_update(ProgressUpdate::step(
+        "stage.finalize",
+        "step.export_jpeg",
+        &[],
+        1,
+        2,
+    ));
+    if let Err(e) = export_to_jpg(
+        ctx.project_file_path(),
+        ctx.veget_jpeg_path(),
+        &ctx.run_config,
+    ) {
+        return Err(format!("Erreur lors de l'exportation de l'image: {:?}", e));
+    }
+    if let Err(e) = build_project_overviews(ctx.project_file_path()) {
+        ctx.warnings
+            .push(Warning::new("Aperçus du raster", e.to_string()));
+    }
+    Ok(())
+}
+
+/// Télécharge l'orthophoto du projet (`<nom>_ORTHO.jpeg`). Une création hors ligne s'en
+/// passe : l'orthophoto est marquée comme manquante, pour que `refresh_ortho` la produise.
+async fn ortho_stage(
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    if ctx.state.offline {
+        tracing::info!(project = %ctx.name(), "Orthophoto non téléchargée hors ligne");
+        let mut metadata = load_project_metadata(ctx.name());
+        metadata.ortho_skipped = true;
+        return save_project_metadata(ctx.name(), &metadata)
+            .map_err(|e| i18n::error("error.save_metadata", e));
+    }
+    progress.report_update(ProgressUpdate::step(
+        "stage.finalize",
+        "step.download_ortho",
+        &[],
+        2,
+        2,
+    ));
+    if let Err(e) = fetcher.download_ortho(
+        &ctx.run_config,
+        &ctx.ortho_jpeg_path().to_string_lossy(),
+        &ctx.state.project_bb,
+        regions_epsg(&ctx.state.regions)?,
+        Some(ctx.resource_path(ctx.name()).as_str()),
+        progress,
+    ) {
+        return Err(format!(
+            "Erreur lors du téléchargement de l'image satellite: {}",
+            e
+        ));
+    }
+    Ok(())
+}
+
+/// Génère l'aperçu du projet et supprime les GPKG préparés, devenus inutiles.
+async fn cleanup_stage(
+    progress: &dyn ProgressSink,
+    ctx: &mut PipelineContext,
+) -> Result<(), String> {
+    if let Err(e) = generate_project_preview(ctx.name()) {
+        ctx.warnings.push(Warning::new("Aperçu", e.to_string()));
+    }
+
+    progress.report_update(ProgressUpdate::stage("stage.cleanup"));
+    let staging_dir = ctx.staging_dir();
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).map_err(|e| {
+            format!(
+                "Erreur lors de la suppression des couches préparées: {:?}",
+                e
+            )
+        })?;
+    }
+    ctx.state.prepared.clear();
+    Ok(())
+}
+
+/// Issue d'une création de projet menée à son terme ou annulée
+/// (suppression forcée du projet).
+#[derive(Debug)]
+pub enum CreationOutcome {
+    Created {
+        folder: String,
+        regions: Vec<String>,
+        timings: StageTimings,
+    },
+    Cancelled,
+}
+
+/// Configuration des couches d'une création : la configuration active, restreinte à la
+/// sélection de couches et associée à la source de végétation demandées.
+///
+/// # Arguments
+///
+/// * `vegetation_source` - Source des données de végétation.
+/// * `layers` - Couches retenues (voir `LayerConfig::with_selection`), toutes si `None`.
+///
+/// # Returns
+///
+/// * `Result<LayerConfig, String>` - La configuration de la création, ou un message d'erreur
+///   si la configuration ne peut pas être chargée ou si la sélection nomme une couche inconnue.
+pub fn creation_layer_config(
+    vegetation_source: VegetationSource,
+    layers: Option<&[String]>,
+) -> Result<LayerConfig, String> {
+    Ok(load_layer_config()
+        .map_err(|e| i18n::error("error.load_layer_config", e))?
+        .with_selection(layers)
+        .map_err(|e| i18n::error("error.layer_selection", e))?
+        .with_vegetation_source(vegetation_source))
+}
+
+/// Crée un projet : initialise le projet (dossiers, raster vierge, métadonnées et état de
+/// la création) puis exécute les étapes de la création. Cœur de `create_project_com`,
+/// utilisable sans application Tauri. Les problèmes non bloquants sont ajoutés à `warnings`.
+///
+/// # Arguments
+///
+/// * `name` - Nom du projet.
+/// * `project_bb` - Boîte englobante du projet.
+/// * `layer_config` - Configuration des couches de la création, avec sa source de végétation
+///   et sa sélection de couches (voir `creation_layer_config`).
+/// * `cancel` - Demande d'annulation de la création.
+/// * `warnings` - Avertissements collectés pendant la création.
+/// * `progress` - Destination des messages de progression.
+/// * `fetcher` - Accès aux archives et à l'orthophoto.
+/// * `run_config` - Paramètres figés au lancement de la création, utilisés par toutes ses étapes.
+///
+/// # Returns
+///
+/// * `Result<CreationOutcome, String>` - Le dossier du projet et les départements traités, l'annulation, ou un message d'erreur.
+#[allow(clippy::too_many_arguments)]
+pub async fn create_project_pipeline(
+    name: &str,
+    project_bb: &BoundingBox,
+    layer_config: &LayerConfig,
+    cancel: CancelToken,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+    run_config: RunConfig,
+) -> Result<CreationOutcome, String> {
+    create_project_from(
+        name,
+        project_bb,
+        layer_config,
+        None,
+        cancel,
+        warnings,
+        progress,
+        fetcher,
+        run_config,
+    )
+    .await
+}
+
+/// Crée un projet comme `create_project_pipeline`, en reprenant les couches d'un projet terminé
+/// pour les départements couverts par son emprise (voir `covered_regions`) : seules les archives
+/// des autres départements sont téléchargées. Les millésimes des départements repris sont ceux
+/// du projet source.
+///
+/// # Arguments
+///
+/// * `source_name` - Projet terminé dont les couches sont reprises, créé avec la même source
+///   de végétation et la même sélection de couches que `layer_config`.
+/// * `name` - Nom du projet.
+/// * `project_bb` - Boîte englobante du projet.
+/// * `layer_config` - Configuration des couches de la création (voir `creation_layer_config`).
+/// * `cancel` - Demande d'annulation de la création.
+/// * `warnings` - Avertissements collectés pendant la création.
+/// * `progress` - Destination des messages de progression.
+/// * `fetcher` - Accès aux archives et à l'orthophoto.
+/// * `run_config` - Paramètres figés au lancement de la création, utilisés par toutes ses étapes.
+///
+/// # Returns
+///
+/// * `Result<CreationOutcome, String>` - Le dossier du projet et les départements traités, l'annulation, ou un message d'erreur.
+#[allow(clippy::too_many_arguments)]
+pub async fn create_project_reusing(
+    source_name: &str,
+    name: &str,
+    project_bb: &BoundingBox,
+    layer_config: &LayerConfig,
+    cancel: CancelToken,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+    run_config: RunConfig,
+) -> Result<CreationOutcome, String> {
+    create_project_from(
+        name,
+        project_bb,
+        layer_config,
+        Some(source_name),
+        cancel,
+        warnings,
+        progress,
+        fetcher,
+        run_config,
+    )
+    .await
+}
+
+/// Départements d'un projet dont la partie de l'emprise est entièrement couverte par
+/// l'emprise d'un projet source : ceux qui ne recoupent pas l'emprise hors du projet source.
+///
+/// # Arguments
+///
+/// * `source_bb` - L'emprise du projet source.
+/// * `project_bb` - L'emprise du nouveau projet.
+/// * `regions` - Les départements du nouveau projet.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, String>` - Les départements couverts, dans l'ordre de `regions`.
+pub fn covered_regions(
+    source_bb: &BoundingBox,
+    project_bb: &BoundingBox,
+    regions: &[String],
+) -> Result<Vec<String>, String> {
+    let mut uncovered = BTreeSet::new();
+    for part in project_bb.difference(source_bb) {
+        let (part_regions, _) = find_project_regions(&part)
+            .map_err(|e| format!("La surface de travail est incorrecte: {}", e))?;
+        uncovered.extend(part_regions.into_iter().map(|region| region.code));
+    }
+    Ok(regions
+        .iter()
+        .filter(|code| !uncovered.contains(*code))
+        .cloned()
+        .collect())
+}
+
+/// Création commune à `create_project_pipeline` et `create_project_reusing`.
+#[allow(clippy::too_many_arguments)]
+async fn create_project_from(
+    name: &str,
+    project_bb: &BoundingBox,
+    layer_config: &LayerConfig,
+    reuse_source: Option<&str>,
+    cancel: CancelToken,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+    run_config: RunConfig,
+) -> Result<CreationOutcome, String> {
+    let vegetation_source = &layer_config.vegetation_source;
+    let selected_layers = layer_config.selected_layers();
+
+    progress.report_update(ProgressUpdate::stage("stage.search_files"));
+
+    // L'emprise est exprimée dans le système de ses départements (Lambert-93 en métropole,
+    // UTM outre-mer) ; un projet à cheval sur deux systèmes est refusé.
+    let mut region_codes: Vec<String> = Vec::new();
+    let epsg = match find_project_regions(project_bb) {
+        Ok((result, epsg)) => {
+            if result.is_empty() {
+                return Err("La surface de travail est incorrecte".to_string());
+            } else {
+                for region in result {
+                    region_codes.push(region.code);
+                }
+            }
+            epsg
+        }
+        Err(e) => return Err(format!("La surface de travail est incorrecte: {}", e)),
+    };
+
+    // Départements couverts par le projet source : ses couches tiennent lieu de leurs archives.
+    let source_metadata = reuse_source.map(load_project_metadata);
+    let reused_regions: Vec<String> = match (reuse_source, &source_metadata) {
+        (Some(source), Some(source_metadata)) => {
+            let source_regions = project_regions(source_metadata);
+            covered_regions(
+                &get_project_bounding_box(source)?,
+                project_bb,
+                &region_codes,
+            )?
+            .into_iter()
+            .filter(|code| source_regions.contains(code))
+            .collect()
+        }
+        _ => Vec::new(),
+    };
+    let reuse_source = reuse_source.filter(|_| !reused_regions.is_empty());
+    if let Some(source) = reuse_source {
+        tracing::info!(
+            project = %name,
+            source = %source,
+            regions = ?reused_regions,
+            "Départements repris du projet source, sans téléchargement"
+        );
+    }
+
+    progress.report_update(ProgressUpdate::stage("stage.init"));
+    let project_folder = project_dir(name).to_string_lossy().to_string();
+    let project_file_path = project_tiff(name).to_string_lossy().to_string();
+
+    let incomplete =
+        get_incomplete_projects().map_err(|e| i18n::error("error.read_projects", e))?;
+    if incomplete.iter().any(|project| project == name) {
+        // Reste d'une création interrompue : il est supprimé sans confirmation.
+        tracing::info!(project = %name, "Suppression de la création interrompue du projet");
+        fs::remove_dir_all(&project_folder).map_err(|e| e.to_string())?;
+    } else if Path::new(&project_folder).exists() {
+        // Le conflit est résolu par `create_project_com` : un projet apparu entre-temps est conservé.
+        return Err(i18n::text("error.project_exists", &[name]));
+    }
+
+    progress.report_update(ProgressUpdate::step(
+        "stage.init",
+        "step.create_folders",
+        &[],
+        1,
+        2,
+    ));
+    fs::create_dir_all(&project_folder).map_err(|e| e.to_string())?;
+    fs::create_dir_all(project_dir(name).join("resources")).map_err(|e| e.to_string())?;
+    fs::create_dir_all(project_dir(name).join("slices")).map_err(|e| e.to_string())?;
+
+    // L'état est écrit avant le raster : un dossier sans état serait pris pour un ancien projet terminé.
+    let mut state = PipelineState::new(name, project_bb, vegetation_source, region_codes.clone());
+    state.layers = selected_layers.clone();
+    state.reuse_source = reuse_source.map(str::to_string);
+    state.reused_regions = reused_regions.clone();
+    state
+        .save(Path::new(&project_folder))
+        .map_err(|e| i18n::error("error.save_state", e))?;
+
+    progress.report_update(ProgressUpdate::step(
+        "stage.init",
+        "step.configure_project",
+        &[],
+        2,
+        2,
+    ));
+    if let Err(e) = create_project_in_crs(&project_file_path, project_bb, epsg, &run_config) {
+        return Err(i18n::error("error.create_project", e));
+    }
+
+    let metadata = ProjectMetadata {
+        vegetation_source: vegetation_source.as_metadata_string(),
+        created_at: Some(chrono::Local::now().to_rfc3339()),
+        layers: selected_layers,
+        epsg: Some(epsg),
+        regions: region_codes,
+        land_color: Some(default_land_color()),
+        vintages: source_metadata
+            .map(|source_metadata| {
+                source_metadata
+                    .vintages
+                    .into_iter()
+                    .filter(|vintage| reused_regions.contains(&vintage.region))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        ..ProjectMetadata::default()
+    };
+    save_project_metadata(name, &metadata).map_err(|e| i18n::error("error.save_metadata", e))?;
+
+    run_creation_stages(
+        project_folder.into(),
+        state,
+        cancel,
+        warnings,
+        progress,
+        fetcher,
+        run_config,
+    )
+    .await
+}
+
+/// Exécute les étapes restantes d'une création à partir de son état enregistré.
+///
+/// # Arguments
+///
+/// * `project_folder` - Dossier du projet.
+/// * `state` - État de la création.
+/// * `cancel` - Demande d'annulation, consultée avant chaque étape.
+/// * `warnings` - Avertissements collectés pendant la création.
+/// * `progress` - Destination des messages de progression.
+/// * `fetcher` - Accès aux archives et à l'orthophoto.
+/// * `run_config` - Paramètres figés de la création, utilisés par toutes ses étapes.
+///
+/// # Returns
+///
+/// * `Result<CreationOutcome, String>` - Le dossier du projet et les départements traités, l'annulation, ou un message d'erreur.
+pub async fn run_creation_stages(
+    project_folder: PathBuf,
+    state: PipelineState,
+    cancel: CancelToken,
+    warnings: &mut Vec<Warning>,
+    progress: &dyn ProgressSink,
+    fetcher: &dyn DataFetcher,
+    run_config: RunConfig,
+) -> Result<CreationOutcome, String> {
+    let layer_config = load_layer_config()
+        .map_err(|e| i18n::error("error.load_layer_config", e))?
+        .with_selection(state.layers.as_deref())
+        .map_err(|e| i18n::error("error.layer_selection", e))?;
+    let work_dir = WorkDir::new(&run_config, &format!("create_{}", state.name))
+        .map_err(|e| i18n::error("error.work_dir", e))?;
+
+    // Les archives des départements du projet ne doivent pas être évincées du cache
+    // tant que la création n'est pas terminée.
+    let _cache_pin = pin_cache_entries(&department_cache_paths(&state.archive_regions()));
+    let mut ctx = PipelineContext::new(project_folder, state, layer_config);
+    ctx.cancel = cancel;
+    ctx.run_config = run_config;
+    let mut stages = CreationStages {
+        progress,
+        fetcher,
+        work_dir,
+    };
+    let result = run_pipeline(&mut stages, &mut ctx).await;
+    warnings.append(&mut ctx.warnings);
+    if result.is_err() && ctx.cancel.is_cancelled() {
+        return Ok(CreationOutcome::Cancelled);
+    }
+    result?;
+
+    let mut metadata = load_project_metadata(ctx.name());
+    metadata.timings = ctx.state.timings.clone();
+    if let Err(e) = save_project_metadata(ctx.name(), &metadata) {
+        warnings.push(Warning::new("Durées de la création", e.to_string()));
+    }
+    if let Err(e) = update_project_size(ctx.name()) {
+        warnings.push(Warning::new("Taille du projet", e.to_string()));
+    }
+
+    Ok(CreationOutcome::Created {
+        folder: ctx.folder_str(),
+        regions: ctx.state.regions,
+        timings: ctx.state.timings,
+    })
+}
@@ -0,0 +1,95 @@
+use std::sync::Mutex;
+
+use tauri::Emitter;
+
+use crate::events::{PROGRESS_EVENT, ProgressUpdate};
+
+/// Destination des messages de progression d'une création de projet.
+/// Permet d'exécuter les traitements sans application Tauri, par exemple dans les tests.
+pub trait ProgressSink: Sync {
+    /// Signale l'avancement d'une tâche.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - La clé de l'étape (`stage.prepare`...).
+    /// * `detail` - La clé de la sous-tâche (`step.extract`...), `None` au début d'une étape.
+    /// * `current` - Le numéro de la sous-tâche.
+    /// * `total` - Le nombre de sous-tâches.
+    fn report(&self, stage: &str, detail: Option<&str>, current: u64, total: u64);
+
+    /// Signale un message de progression complet, avec ses textes traduits et ses paramètres.
+    /// Par défaut, seules les clés et l'avancement sont transmis à `report`.
+    fn report_update(&self, update: ProgressUpdate) {
+        let (current, total) = update.count.unwrap_or((0, 0));
+        self.report(
+            &update.stage,
+            update.step.as_deref(),
+            current as u64,
+            total as u64,
+        );
+    }
+}
+
+/// Émet la progression vers l'interface avec l'événement `progress-update`.
+impl ProgressSink for tauri::AppHandle {
+    fn report(&self, stage: &str, detail: Option<&str>, current: u64, total: u64) {
+        let update = match detail {
+            Some(step) => ProgressUpdate::step(stage, step, &[], current as usize, total as usize),
+            None => ProgressUpdate::stage(stage),
+        };
+        self.report_update(update);
+    }
+
+    fn report_update(&self, update: ProgressUpdate) {
+        let _ = self.emit(PROGRESS_EVENT, update);
+    }
+}
+
+/// Ignore la progression.
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _stage: &str, _detail: Option<&str>, _current: u64, _total: u64) {}
+}
+
+/// Message de progression conservé par `ProgressCollector`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressReport {
+    pub stage: String,
+    pub detail: Option<String>,
+    pub current: u64,
+    pub total: u64,
+}
+
+impl ProgressReport {
+    pub fn new(stage: &str, detail: Option<&str>, current: u64, total: u64) -> Self {
+        ProgressReport {
+            stage: stage.to_string(),
+            detail: detail.map(str::to_string),
+            current,
+            total,
+        }
+    }
+}
+
+/// Conserve les messages de progression dans leur ordre d'émission.
+#[derive(Debug, Default)]
+pub struct ProgressCollector {
+    reports: Mutex<Vec<ProgressReport>>,
+}
+
+impl ProgressCollector {
+    /// Les messages reçus, du plus ancien au plus récent.
+    pub fn reports(&self) -> Vec<ProgressReport> {
+        self.reports.lock().unwrap().clone()
+    }
+}
+
+impl ProgressSink for ProgressCollector {
+    fn report(&self, stage: &str, detail: Option<&str>, current: u64, total: u64) {
+        self.reports
+            .lock()
+            .unwrap()
+            .push(ProgressReport::new(stage, detail, current, total));
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Durées, en secondes, mesurées pendant la création d'un projet.
+/// Les clés sans `/` sont les étapes de la création (`download`, `prepare`...) : leur somme
+/// correspond à la durée de la création. Les clés `étape/détail` (`download/BDTOPO 2A`,
+/// `add_layers/rasterize`...) détaillent une étape et ne s'ajoutent pas au total.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StageTimings(BTreeMap<String, f64>);
+
+impl StageTimings {
+    /// Ajoute une durée à une clé, les mesures d'une même clé se cumulant.
+    pub fn add(&mut self, key: &str, seconds: f64) {
+        *self.0.entry(key.to_string()).or_default() += seconds;
+    }
+
+    pub fn get(&self, key: &str) -> Option<f64> {
+        self.0.get(key).copied()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Cumule les durées d'autres mesures.
+    pub fn extend(&mut self, other: StageTimings) {
+        for (key, seconds) in other.0 {
+            self.add(&key, seconds);
+        }
+    }
+
+    /// Les mêmes durées, avec les clés préfixées par `prefix/`.
+    pub fn with_prefix(self, prefix: &str) -> StageTimings {
+        StageTimings(
+            self.0
+                .into_iter()
+                .map(|(key, seconds)| (format!("{}/{}", prefix, key), seconds))
+                .collect(),
+        )
+    }
+
+    /// Somme des durées des étapes, sans leurs détails.
+    pub fn stages_total(&self) -> f64 {
+        self.0
+            .iter()
+            .filter(|(key, _)| !key.contains('/'))
+            .map(|(_, seconds)| seconds)
+            .sum()
+    }
+
+    /// Résumé d'une ligne des durées des étapes, pour le journal de création.
+    pub fn summary(&self) -> String {
+        self.0
+            .iter()
+            .filter(|(key, _)| !key.contains('/'))
+            .map(|(key, seconds)| format!("{} {:.1} s", key, seconds))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Mesure en cours, ajoutée aux durées quand elle est détruite.
+pub struct StageTimer<'a> {
+    timings: &'a mut StageTimings,
+    key: String,
+    start: Instant,
+}
+
+impl Drop for StageTimer<'_> {
+    fn drop(&mut self) {
+        self.timings
+            .add(&self.key, self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// Démarre la mesure d'une étape ou d'un détail d'étape, jusqu'à la fin de la portée
+/// de la valeur retournée.
+///
+/// # Arguments
+///
+/// * `timings` - Les durées auxquelles ajouter la mesure.
+/// * `key` - La clé de la mesure, par exemple `prepare/2A/TRONCON_DE_ROUTE`.
+///
+/// # Returns
+///
+/// * `StageTimer` - La mesure, à conserver dans une variable (`_timer`).
+pub fn stage_timer(timings: &mut StageTimings, key: impl Into<String>) -> StageTimer<'_> {
+    StageTimer {
+        timings,
+        key: key.into(),
+        start: Instant::now(),
+    }
+}
+
+thread_local! {
+    /// Durées des traitements raster du fil courant, relevées par `take_processing_timings`.
+    static PROCESSING_TIMINGS: RefCell<StageTimings> = RefCell::new(StageTimings::default());
+}
+
+/// Mesure d'un traitement raster, ajoutée aux durées du fil courant quand elle est détruite.
+pub struct ProcessingTimer {
+    key: &'static str,
+    start: Instant,
+}
+
+impl Drop for ProcessingTimer {
+    fn drop(&mut self) {
+        let seconds = self.start.elapsed().as_secs_f64();
+        PROCESSING_TIMINGS.with(|timings| timings.borrow_mut().add(self.key, seconds));
+    }
+}
+
+/// Démarre la mesure d'un traitement raster (`rasterize`, `overlay`). Les traitements sont
+/// appelés en profondeur par l'ajout des couches : leurs durées sont cumulées sur le fil
+/// courant plutôt que transmises à chaque fonction.
+pub fn processing_timer(key: &'static str) -> ProcessingTimer {
+    ProcessingTimer {
+        key,
+        start: Instant::now(),
+    }
+}
+
+/// Relève et remet à zéro les durées des traitements raster du fil courant.
+pub fn take_processing_timings() -> StageTimings {
+    PROCESSING_TIMINGS.with(|timings| std::mem::take(&mut *timings.borrow_mut()))
+}
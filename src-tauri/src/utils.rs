@@ -1,25 +1,47 @@
-use crate::app_setup::{CONFIG, Config};
+use crate::app_setup::{CONFIG, Config, ProxyConfig, RunConfig, TiffCompression};
+use crate::busy::BusyKind;
+use crate::estimate::Throughput;
+use crate::events::Warning;
+use crate::gis_operation::colormap::{self, BuildingHeights, Rgb};
+use crate::gis_operation::custom_layers::CustomLayer;
+use crate::gis_operation::layers::BuiltDensity;
+use crate::gis_operation::ortho::{OrthoSource, WMS_CACHE_DIR, WMS_CACHE_MAX_SIZE};
+use crate::gis_operation::processing::{ProjectRaster, RasterWindow};
+use crate::gis_operation::qgis::{
+    QGIS_PROJECT_EXTENSION, generate_qgis_project, refresh_qgis_project,
+};
+use crate::i18n::Language;
+pub use crate::payloads::{BoundingBox, ConflictMode, ExportOptions, ExportTarget, ProjectSummary};
+use crate::pipeline::{PIPELINE_STATE_FILE, PipelineState, STAGING_DIR, Stage};
+use crate::timings::StageTimings;
+use fs2::FileExt;
+use gdal::raster::ColorInterpretation;
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
 use gdal::vector::Geometry;
+use gdal::{Dataset, DriverManager};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops;
+use image::{DynamicImage, GrayImage, RgbImage};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs::{self};
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::MutexGuard;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use xdg_user;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
 
-use crate::gis_operation::slicing::slice_images;
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Copy)]
-pub struct BoundingBox {
-    pub xmin: f64,
-    pub ymin: f64,
-    pub xmax: f64,
-    pub ymax: f64,
-}
+use crate::gis_operation::slicing::{
+    expected_slice_files, invalidate_ortho_slices, read_slice_index, slice_images, tile_origins,
+};
 
 impl BoundingBox {
     pub fn new(xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Self {
@@ -39,6 +61,45 @@ impl BoundingBox {
         self.ymax - self.ymin
     }
 
+    /// Surface de l'emprise en km², nulle pour une emprise vide ou inversée.
+    pub fn area_km2(&self) -> f64 {
+        self.width().max(0.0) * self.height().max(0.0) / 1_000_000.0
+    }
+
+    /// Indique si deux emprises se recouvrent ou se touchent.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.xmin <= other.xmax
+            && other.xmin <= self.xmax
+            && self.ymin <= other.ymax
+            && other.ymin <= self.ymax
+    }
+
+    /// Indique si l'emprise contient entièrement une autre emprise.
+    pub fn contains(&self, other: &BoundingBox) -> bool {
+        self.xmin <= other.xmin
+            && self.ymin <= other.ymin
+            && self.xmax >= other.xmax
+            && self.ymax >= other.ymax
+    }
+
+    /// Parties de l'emprise hors d'une autre emprise, en au plus quatre rectangles disjoints :
+    /// bandes gauche et droite sur toute la hauteur, puis bandes basse et haute entre les deux.
+    pub fn difference(&self, other: &BoundingBox) -> Vec<BoundingBox> {
+        if !self.intersects(other) {
+            return vec![*self];
+        }
+        let xmin = self.xmin.max(other.xmin);
+        let xmax = self.xmax.min(other.xmax);
+        let mut parts = vec![
+            BoundingBox::new(self.xmin, self.ymin, xmin, self.ymax),
+            BoundingBox::new(xmax, self.ymin, self.xmax, self.ymax),
+            BoundingBox::new(xmin, self.ymin, xmax, other.ymin),
+            BoundingBox::new(xmin, other.ymax, xmax, self.ymax),
+        ];
+        parts.retain(|part| part.width() > 0.0 && part.height() > 0.0);
+        parts
+    }
+
     pub fn to_wkt(&self) -> String {
         format!(
             "POLYGON(({} {}, {} {}, {} {}, {} {}, {} {}))",
@@ -58,8 +119,257 @@ impl BoundingBox {
     pub fn to_geometry(&self) -> Result<Geometry, gdal::errors::GdalError> {
         Geometry::from_wkt(&self.to_wkt())
     }
+
+    /// Emprise englobant la boîte reprojetée d'un système de coordonnées à un autre,
+    /// les bords étant densifiés pour suivre leur courbure.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_epsg` - Le code EPSG du système de la boîte.
+    /// * `to_epsg` - Le code EPSG du système cible.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BoundingBox, gdal::errors::GdalError>` - La boîte dans le système cible.
+    pub fn reproject(
+        &self,
+        from_epsg: u32,
+        to_epsg: u32,
+    ) -> Result<BoundingBox, gdal::errors::GdalError> {
+        if from_epsg == to_epsg {
+            return Ok(*self);
+        }
+        let transform = CoordTransform::new(
+            &SpatialRef::from_epsg(from_epsg)?,
+            &SpatialRef::from_epsg(to_epsg)?,
+        )?;
+        let [xmin, ymin, xmax, ymax] =
+            transform.transform_bounds(&[self.xmin, self.ymin, self.xmax, self.ymax], 21)?;
+        Ok(BoundingBox::new(xmin, ymin, xmax, ymax))
+    }
+
+    /// Emprise d'un raster à partir de sa géotransformation et de sa taille en pixels.
+    pub fn from_geo_transform(geo_transform: &[f64; 6], size: (usize, usize)) -> Self {
+        let (width, height) = size;
+        BoundingBox {
+            xmin: geo_transform[0],
+            ymin: geo_transform[3] + geo_transform[5] * height as f64,
+            xmax: geo_transform[0] + geo_transform[1] * width as f64,
+            ymax: geo_transform[3],
+        }
+    }
+
+    /// Calcule la taille en pixels du raster couvrant l'emprise.
+    /// Toutes les images du projet (VEGET, ORTHO, tuiles) dérivent de ce calcul :
+    /// l'emprise doit donc être alignée sur la grille de la résolution.
+    ///
+    /// # Arguments
+    ///
+    /// * `resolution` - La résolution en mètres par pixel.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(usize, usize), GridError>` - La largeur et la hauteur, ou une erreur si
+    ///   l'emprise est vide ou n'est pas un multiple entier de la résolution.
+    pub fn raster_size(&self, resolution: f64) -> Result<(usize, usize), GridError> {
+        if !resolution.is_finite() || resolution <= 0.0 {
+            return Err(GridError::InvalidResolution(resolution));
+        }
+        if self.width() <= 0.0 || self.height() <= 0.0 {
+            return Err(GridError::EmptyExtent {
+                width: self.width(),
+                height: self.height(),
+            });
+        }
+
+        let columns = self.width() / resolution;
+        let rows = self.height() / resolution;
+        if (columns - columns.round()).abs() > GRID_TOLERANCE
+            || (rows - rows.round()).abs() > GRID_TOLERANCE
+        {
+            return Err(GridError::InvalidBoundingBox {
+                bb: *self,
+                resolution,
+                suggested: self.snapped(resolution),
+            });
+        }
+
+        Ok((columns.round() as usize, rows.round() as usize))
+    }
+
+    /// Agrandit l'emprise au multiple de la résolution supérieur, en conservant
+    /// le coin supérieur gauche (l'origine de la géotransformation).
+    pub fn snapped(&self, resolution: f64) -> Self {
+        let columns = (self.width() / resolution - GRID_TOLERANCE).ceil().max(1.0);
+        let rows = (self.height() / resolution - GRID_TOLERANCE)
+            .ceil()
+            .max(1.0);
+        BoundingBox {
+            xmin: self.xmin,
+            ymin: self.ymax - rows * resolution,
+            xmax: self.xmin + columns * resolution,
+            ymax: self.ymax,
+        }
+    }
+
+    /// Ajuste l'emprise pour que sa largeur et sa hauteur soient des multiples de `cell_m`.
+    /// Une emprise plus petite qu'une cellule est portée à exactement une cellule.
+    ///
+    /// # Arguments
+    ///
+    /// * `cell_m` - La taille de la cellule en mètres (`PROJECT_GRID_PIXELS` × résolution).
+    /// * `mode` - L'ajustement : agrandir ou réduire en conservant le coin supérieur gauche,
+    ///   ou arrondir au nombre de cellules le plus proche en conservant le centre.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BoundingBox, GridError>` - L'emprise ajustée, ou une erreur si l'emprise est
+    ///   vide ou inversée ou si la taille de la cellule est invalide.
+    pub fn snap_to_grid(&self, cell_m: f64, mode: SnapMode) -> Result<BoundingBox, GridError> {
+        if !cell_m.is_finite() || cell_m <= 0.0 {
+            return Err(GridError::InvalidResolution(cell_m));
+        }
+        if !(self.width() > 0.0 && self.height() > 0.0) {
+            return Err(GridError::EmptyExtent {
+                width: self.width(),
+                height: self.height(),
+            });
+        }
+
+        let cells = |length: f64| {
+            let count = length / cell_m;
+            let snapped = match mode {
+                SnapMode::Expand => (count - GRID_TOLERANCE).ceil(),
+                SnapMode::Shrink => (count + GRID_TOLERANCE).floor(),
+                SnapMode::Center => count.round(),
+            };
+            snapped.max(1.0) * cell_m
+        };
+        let width = cells(self.width());
+        let height = cells(self.height());
+
+        Ok(match mode {
+            SnapMode::Expand | SnapMode::Shrink => BoundingBox {
+                xmin: self.xmin,
+                ymin: self.ymax - height,
+                xmax: self.xmin + width,
+                ymax: self.ymax,
+            },
+            SnapMode::Center => {
+                let xmin = self.xmin - (width - self.width()) / 2.0;
+                let ymin = self.ymin - (height - self.height()) / 2.0;
+                BoundingBox {
+                    xmin,
+                    ymin,
+                    xmax: xmin + width,
+                    ymax: ymin + height,
+                }
+            }
+        })
+    }
+}
+
+/// Taille, en pixels, dont la largeur et la hauteur d'un projet doivent être des multiples.
+pub const PROJECT_GRID_PIXELS: usize = 500;
+
+/// Écart toléré, en fraction de pixel, entre la taille de l'emprise et la grille.
+const GRID_TOLERANCE: f64 = 1e-6;
+
+/// Ajustement d'une emprise sur la grille du projet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapMode {
+    /// Agrandit l'emprise vers la droite et le bas.
+    Expand,
+    /// Réduit l'emprise depuis la droite et le bas.
+    Shrink,
+    /// Arrondit au nombre de cellules le plus proche autour du centre.
+    Center,
+}
+
+/// Emprise ajustée sur la grille et déplacement appliqué à chaque bord, en mètres
+/// (positif vers l'est et le nord).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GridSnap {
+    pub bbox: BoundingBox,
+    pub delta: BoundingBox,
+}
+
+impl GridSnap {
+    /// Ajuste une emprise sur une grille de `cell_m` mètres.
+    ///
+    /// # Arguments
+    ///
+    /// * `bb` - L'emprise saisie.
+    /// * `cell_m` - La taille de la cellule en mètres.
+    /// * `mode` - L'ajustement à appliquer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GridSnap, GridError>` - L'emprise ajustée et le déplacement de chaque bord.
+    pub fn new(bb: &BoundingBox, cell_m: f64, mode: SnapMode) -> Result<GridSnap, GridError> {
+        let bbox = bb.snap_to_grid(cell_m, mode)?;
+        Ok(GridSnap {
+            bbox,
+            delta: BoundingBox {
+                xmin: bbox.xmin - bb.xmin,
+                ymin: bbox.ymin - bb.ymin,
+                xmax: bbox.xmax - bb.xmax,
+                ymax: bbox.ymax - bb.ymax,
+            },
+        })
+    }
 }
 
+/// Erreur de correspondance entre une emprise et la grille de pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridError {
+    /// La résolution n'est pas strictement positive.
+    InvalidResolution(f64),
+    /// L'emprise a une largeur ou une hauteur nulle ou négative.
+    EmptyExtent { width: f64, height: f64 },
+    /// L'emprise n'est pas alignée sur la grille de la résolution.
+    InvalidBoundingBox {
+        bb: BoundingBox,
+        resolution: f64,
+        suggested: BoundingBox,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::InvalidResolution(resolution) => {
+                write!(f, "Résolution invalide: {}", resolution)
+            }
+            GridError::EmptyExtent { width, height } => write!(
+                f,
+                "Emprise vide ou inversée: largeur {} m, hauteur {} m",
+                width, height
+            ),
+            GridError::InvalidBoundingBox {
+                bb,
+                resolution,
+                suggested,
+            } => write!(
+                f,
+                "Emprise non alignée sur la grille de {} m: {} {} {} {} (emprise suggérée: {} {} {} {})",
+                resolution,
+                bb.xmin,
+                bb.ymin,
+                bb.xmax,
+                bb.ymax,
+                suggested.xmin,
+                suggested.ymin,
+                suggested.xmax,
+                suggested.ymax
+            ),
+        }
+    }
+}
+
+impl Error for GridError {}
+
 lazy_static! {
     pub static ref RPG_DEP: HashMap<&'static str, Vec<&'static str>> = HashMap::from([
         (
@@ -116,17 +426,130 @@ lazy_static! {
     };
 }
 
-pub fn get_rpg_for_dep_code(code: &str) -> Option<&str> {
+/// Système de coordonnées des projets de métropole : RGF93 / Lambert-93.
+/// C'est aussi celui du graphe des régions.
+pub const DEFAULT_PROJECT_EPSG: u32 = 2154;
+
+/// Systèmes de coordonnées des départements d'outre-mer, dans lesquels l'IGN diffuse
+/// leurs données : département, code EPSG et nom du système.
+pub const OVERSEAS_CRS: [(&str, u32, &str); 5] = [
+    ("971", 5490, "RGAF09 / UTM zone 20N"),
+    ("972", 5490, "RGAF09 / UTM zone 20N"),
+    ("973", 2972, "RGFG95 / UTM zone 22N"),
+    ("974", 2975, "RGR92 / UTM zone 40S"),
+    ("976", 4471, "RGM04 / UTM zone 38S"),
+];
+
+/// Code EPSG du système de coordonnées d'un département : celui de `OVERSEAS_CRS`
+/// pour l'outre-mer, Lambert-93 pour la métropole.
+pub fn department_epsg(code: &str) -> u32 {
+    let normalized = normalize_department_code(code);
+    OVERSEAS_CRS
+        .iter()
+        .find(|(department, _, _)| *department == normalized)
+        .map_or(DEFAULT_PROJECT_EPSG, |(_, epsg, _)| *epsg)
+}
+
+/// Indique si un code EPSG est celui d'un système de coordonnées de projet.
+pub fn is_project_epsg(epsg: u32) -> bool {
+    epsg == DEFAULT_PROJECT_EPSG || OVERSEAS_CRS.iter().any(|(_, code, _)| *code == epsg)
+}
+
+/// Libellé d'un système de coordonnées de projet, par exemple `RGF93 / Lambert-93 (EPSG:2154)`.
+pub fn crs_label(epsg: u32) -> String {
+    let name = if epsg == DEFAULT_PROJECT_EPSG {
+        Some("RGF93 / Lambert-93")
+    } else {
+        OVERSEAS_CRS
+            .iter()
+            .find(|(_, code, _)| *code == epsg)
+            .map(|(_, _, name)| *name)
+    };
+    match name {
+        Some(name) => format!("{} (EPSG:{})", name, epsg),
+        None => format!("EPSG:{}", epsg),
+    }
+}
+
+/// Système de coordonnées d'un projet, déduit de ses départements.
+///
+/// # Arguments
+///
+/// * `codes` - Les codes des départements du projet.
+///
+/// # Returns
+///
+/// * `Result<u32, String>` - Le code EPSG commun aux départements, ou une erreur si
+///   les départements n'ont pas le même système (métropole et outre-mer, ou deux
+///   départements d'outre-mer éloignés).
+pub fn regions_epsg(codes: &[String]) -> Result<u32, String> {
+    let mut epsgs: Vec<(u32, &str)> = codes
+        .iter()
+        .map(|code| (department_epsg(code), code.as_str()))
+        .collect();
+    epsgs.sort();
+    epsgs.dedup_by_key(|(epsg, _)| *epsg);
+    match epsgs.as_slice() {
+        [] => Ok(DEFAULT_PROJECT_EPSG),
+        [(epsg, _)] => Ok(*epsg),
+        _ => Err(format!(
+            "Les départements du projet n'ont pas le même système de coordonnées : {}",
+            epsgs
+                .iter()
+                .map(|(epsg, code)| format!("{} en {}", code, crs_label(*epsg)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Normalise un code de département : majuscules, sans espaces,
+/// et sans zéros en tête pour les codes numériques (`"01"` devient `"1"`, `"2a"` devient `"2A"`).
+/// C'est la forme utilisée comme clé dans `RPG_DEP`.
+pub fn normalize_department_code(code: &str) -> String {
+    let code = code.trim().to_uppercase();
+    if !code.is_empty() && code.chars().all(|c| c.is_ascii_digit()) {
+        let stripped = code.trim_start_matches('0');
+        if stripped.is_empty() {
+            "0".to_string()
+        } else {
+            stripped.to_string()
+        }
+    } else {
+        code
+    }
+}
+
+/// Code de département tel qu'il apparaît dans les noms d'archives de l'IGN :
+/// `D` suivi du code sur trois caractères (`D001`, `D02A`, `D971`).
+pub fn ign_department_code(code: &str) -> String {
+    format!("D{:0>3}", normalize_department_code(code))
+}
+
+/// Retrouve la région RPG associée à un département.
+///
+/// # Arguments
+///
+/// * `code` - Le code du département, sous n'importe quelle forme (`"01"`, `"1"`, `"2a"`...).
+///
+/// # Returns
+///
+/// * `Result<&'static str, String>` - Le code de la région RPG, ou une erreur nommant le département.
+pub fn get_rpg_for_dep_code(code: &str) -> Result<&'static str, String> {
+    let normalized = normalize_department_code(code);
     RPG_DEP
         .iter()
         .find_map(|(rpg, deps)| {
-            if deps.contains(&code) {
-                Some(rpg)
+            if deps.contains(&normalized.as_str()) {
+                Some(*rpg)
             } else {
                 None
             }
         })
-        .map(|v| &**v)
+        .ok_or(format!(
+            "Aucune région RPG n'est associée au département '{}'",
+            code
+        ))
 }
 
 pub fn create_directory_if_not_exists(path: &str) -> Result<(), Box<dyn Error>> {
@@ -136,236 +559,2986 @@ pub fn create_directory_if_not_exists(path: &str) -> Result<(), Box<dyn Error>>
     Ok(())
 }
 
+/// Liste les fichiers d'un dossier et de ses sous-dossiers, relativement à ce dossier.
+///
+/// # Arguments
+///
+/// * `root` - Le dossier à parcourir.
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>, Box<dyn Error>>` - Les chemins relatifs des fichiers, triés.
+pub fn relative_files(root: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(root, &path, files)?;
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(root, root, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Nom d'un fichier dans une archive zip : composants du chemin relatif séparés par `/`,
+/// quel que soit le système d'exploitation.
+pub fn zip_entry_name(relative_path: &Path) -> String {
+    relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Compresse dans une archive zip (deflate) les fichiers d'un dossier retenus par `include`.
+/// Les chemins relatifs sont conservés et les noms non ASCII sont encodés en UTF-8.
+///
+/// # Arguments
+///
+/// * `source_folder_path` - Le dossier à compresser.
+/// * `output_zip_name` - Le nom de l'archive, sans extension.
+/// * `destination_directory` - Le dossier où écrire l'archive.
+/// * `include` - Filtre appliqué au chemin de chaque fichier, relatif à `source_folder_path`.
+/// * `on_progress` - Appelé après chaque fichier ajouté, avec le nombre de fichiers ajoutés et le total.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le chemin de l'archive créée.
 pub fn compress_folder(
     source_folder_path: &str,
     output_zip_name: &str,
     destination_directory: &str,
-) -> Result<(), Box<dyn Error>> {
-    let output_zip_path = format!("{}/{}.zip", destination_directory, output_zip_name);
-
-    let mut command = Command::new("7z");
-    command.args(["a", &output_zip_path]);
-    command.current_dir(source_folder_path);
-    command.arg(".");
-    let output = command.output()?;
+    include: &dyn Fn(&Path) -> bool,
+    on_progress: Option<&dyn Fn(usize, usize)>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let output_zip_path = Path::new(destination_directory).join(format!("{}.zip", output_zip_name));
+    let source = Path::new(source_folder_path);
 
-    if !output.status.success() {
-        return Err(format!("Failed to execute 7z command: {:?}", output).into());
+    let files: Vec<PathBuf> = relative_files(source)?
+        .into_iter()
+        .filter(|path| include(path))
+        .collect();
+    if files.is_empty() {
+        return Err(format!("Aucun fichier à compresser dans {}", source_folder_path).into());
     }
 
-    Ok(())
+    compress_files(source, &files, &output_zip_path, on_progress)?;
+    Ok(output_zip_path)
 }
 
-pub fn extract_files_by_name(
-    archive_path: &str,
-    target_filename: &str,
-    output_dir: &str,
+/// Compresse dans une archive zip (deflate) une liste de fichiers d'un dossier,
+/// dans l'ordre de la liste.
+///
+/// # Arguments
+///
+/// * `source` - Le dossier des fichiers.
+/// * `files` - Les chemins des fichiers, relatifs à `source`.
+/// * `output_zip_path` - Le chemin de l'archive à écrire.
+/// * `on_progress` - Appelé après chaque fichier ajouté, avec le nombre de fichiers ajoutés et le total.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Un résultat indiquant si la compression a réussi ou échoué.
+pub fn compress_files(
+    source: &Path,
+    files: &[PathBuf],
+    output_zip_path: &Path,
+    on_progress: Option<&dyn Fn(usize, usize)>,
 ) -> Result<(), Box<dyn Error>> {
-    create_directory_if_not_exists(output_dir)?;
-    let temp_extract_dir = Path::new(output_dir).join("temp_extract");
-    create_directory_if_not_exists(temp_extract_dir.to_str().unwrap())?;
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    let mut writer = ZipWriter::new(BufWriter::new(fs::File::create(output_zip_path)?));
+    for (index, relative_path) in files.iter().enumerate() {
+        writer.start_file(zip_entry_name(relative_path), options)?;
+        let mut file = fs::File::open(source.join(relative_path))?;
+        std::io::copy(&mut file, &mut writer)?;
+        if let Some(on_progress) = on_progress {
+            on_progress(index + 1, files.len());
+        }
+    }
+    writer.finish()?.flush()?;
+    Ok(())
+}
 
-    let extract_output = Command::new("7z")
-        .args([
-            "x",
-            archive_path,
-            &format!("-o{}", temp_extract_dir.to_str().unwrap()),
-        ])
-        .output()?;
+/// Fichiers d'un export zip, relevés entre le découpage et la compression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportInventory {
+    /// Les fichiers à compresser, relatifs au dossier du projet.
+    pub files: Vec<PathBuf>,
+    /// La taille totale des fichiers, avant compression.
+    pub total_bytes: u64,
+}
 
-    if !extract_output.status.success() {
-        return Err("Archive extraction failed".into());
+/// Relève les fichiers d'un projet à inclure dans son archive zip, après avoir vérifié que
+/// chaque découpe attendue (voir `expected_slice_files`) existe et n'est pas vide.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `options` - Le contenu optionnel de l'archive.
+/// * `run_config` - Les paramètres de l'export, dont la taille des tuiles.
+///
+/// # Returns
+///
+/// * `Result<ExportInventory, Box<dyn Error>>` - Les fichiers à compresser, ou une erreur
+///   listant les découpes manquantes.
+pub fn export_inventory(
+    project_name: &str,
+    options: &ExportOptions,
+    run_config: &RunConfig,
+) -> Result<ExportInventory, Box<dyn Error>> {
+    let project_path = project_dir(project_name);
+    let slices_path = project_path.join("slices");
+    let missing: Vec<String> = expected_slice_files(project_name, run_config)?
+        .into_iter()
+        .filter(|name| !fs::metadata(slices_path.join(name)).is_ok_and(|m| m.len() > 0))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Découpes manquantes ou vides pour {}: {}",
+            project_name,
+            missing.join(", ")
+        )
+        .into());
     }
 
-    let destination = Path::new(output_dir).join(target_filename);
-    create_directory_if_not_exists(destination.to_str().unwrap())?;
-
-    let mut found_files = Vec::new();
-    find_files_by_basename(&temp_extract_dir, target_filename, &mut found_files)?;
+    let files: Vec<PathBuf> = relative_files(&project_path)?
+        .into_iter()
+        .filter(|path| options.includes(project_name, path))
+        .collect();
+    let total_bytes = files
+        .iter()
+        .map(|path| fs::metadata(project_path.join(path)).map(|metadata| metadata.len()))
+        .sum::<Result<u64, _>>()?;
+    Ok(ExportInventory { files, total_bytes })
+}
 
-    if found_files.is_empty() {
-        return Err(format!("No files matching '{}' found in archive", target_filename).into());
+/// Vérifie qu'une archive zip contient tous les fichiers d'un relevé : même nombre
+/// d'entrées et même taille totale une fois décompressées.
+///
+/// # Arguments
+///
+/// * `zip_path` - Le chemin de l'archive.
+/// * `inventory` - Les fichiers compressés.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si l'archive est illisible ou incomplète.
+pub fn verify_zip(zip_path: &Path, inventory: &ExportInventory) -> Result<(), Box<dyn Error>> {
+    let mut archive = ZipArchive::new(fs::File::open(zip_path)?)?;
+    if archive.len() != inventory.files.len() {
+        return Err(format!(
+            "Archive incomplète {}: {} fichiers au lieu de {}",
+            zip_path.display(),
+            archive.len(),
+            inventory.files.len()
+        )
+        .into());
     }
-
-    for file_path in &found_files {
-        let file_name = file_path.file_name().unwrap();
-        let dest_path = destination.join(file_name);
-        fs::copy(file_path, dest_path)?;
+    let mut total_bytes = 0;
+    for index in 0..archive.len() {
+        total_bytes += archive.by_index(index)?.size();
+    }
+    if total_bytes != inventory.total_bytes {
+        return Err(format!(
+            "Archive incomplète {}: {} octets décompressés au lieu de {}",
+            zip_path.display(),
+            total_bytes,
+            inventory.total_bytes
+        )
+        .into());
     }
-
-    fs::remove_dir_all(temp_extract_dir)?;
-
     Ok(())
 }
 
-fn find_files_by_basename(
-    dir: &Path,
-    target_basename: &str,
-    result: &mut Vec<PathBuf>,
-) -> Result<(), Box<dyn Error>> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let path = entry?.path();
+/// Erreur détectée lors de la vérification d'une archive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveError {
+    /// L'archive n'existe pas.
+    Missing(PathBuf),
+    /// Le test d'intégrité de 7z a échoué.
+    Corrupt { path: PathBuf, details: String },
+    /// 7z n'a pas pu être lancé.
+    Io(String),
+}
 
-            if path.is_file() {
-                if let Some(file_stem) = path.file_stem() {
-                    if file_stem.to_string_lossy() == target_basename {
-                        result.push(path);
-                    }
-                }
-            } else if path.is_dir() {
-                find_files_by_basename(&path, target_basename, result)?;
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Missing(path) => write!(f, "Archive introuvable: {}", path.display()),
+            ArchiveError::Corrupt { path, details } => {
+                write!(f, "Archive corrompue: {} ({})", path.display(), details)
             }
+            ArchiveError::Io(e) => write!(f, "Impossible de lancer 7z: {}", e),
         }
     }
+}
+
+impl Error for ArchiveError {}
+
+/// Vérifie l'intégrité d'une archive avec la commande `7z t`.
+///
+/// # Arguments
+///
+/// * `path` - Le chemin de l'archive.
+///
+/// # Returns
+///
+/// * `Result<(), ArchiveError>` - Une erreur si l'archive est absente ou corrompue.
+pub fn verify_archive(path: &Path) -> Result<(), ArchiveError> {
+    if !path.exists() {
+        return Err(ArchiveError::Missing(path.to_path_buf()));
+    }
+
+    let output = Command::new("7z")
+        .arg("t")
+        .arg(path)
+        .output()
+        .map_err(|e| ArchiveError::Io(e.to_string()))?;
+
+    if !output.status.success() {
+        let details = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(ArchiveError::Corrupt {
+            path: path.to_path_buf(),
+            details,
+        });
+    }
 
     Ok(())
 }
 
-pub fn get_previous_projects() -> Result<HashMap<String, Vec<String>>, Box<dyn Error>> {
-    #[cfg(target_os = "windows")]
-    let output = Command::new("cmd")
-        .args(&["/C", "dir", &projects_dir().to_string_lossy(), "/b", "/a:d"])
-        .output()?;
-    #[cfg(not(target_os = "windows"))]
-    let output = Command::new("ls")
-        .args([projects_dir().to_string_lossy().as_ref()])
-        .output()?;
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let mut projects = HashMap::new();
-    for line in output_str.lines() {
-        let project_name = line.trim();
-        if project_name != "cache" {
-            let project_path = project_dir(project_name);
-            let preview_image_path = project_path.join(format!("{}_ORTHO.jpeg", project_name));
-            projects.insert(
-                project_name.to_string(),
-                vec![
-                    preview_image_path.to_string_lossy().to_string(),
-                    project_path.to_string_lossy().to_string(),
-                ],
-            );
-        }
+/// Taille et date de modification d'une archive dont le test d'intégrité a réussi.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct VerifiedArchive {
+    size_bytes: u64,
+    /// Date de modification, en millisecondes depuis l'époque Unix.
+    modified_ms: u64,
+}
+
+impl VerifiedArchive {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified_ms = metadata
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_millis() as u64;
+        Some(VerifiedArchive {
+            size_bytes: metadata.len(),
+            modified_ms,
+        })
     }
-    Ok(projects)
 }
 
-pub fn get_operating_system() -> &'static str {
-    std::env::consts::OS
+fn read_verified_archives(cache: &Path) -> HashMap<String, VerifiedArchive> {
+    fs::read_to_string(cache.join(CACHE_VERIFIED_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
 }
 
-/// Exporte un projet ainsi que l'ensemble de ses ressources
-/// (images, fichiers de configuration, etc.) dans un format compressé.
+/// Comme `verify_archive`, pour une archive du cache : le test de 7z, long pour les
+/// archives de plusieurs gigaoctets, n'est refait que si la taille ou la date de
+/// modification de l'archive ont changé depuis le dernier test réussi, enregistré
+/// dans `CACHE_VERIFIED_FILE`.
 ///
 /// # Arguments
 ///
-/// * `project_name` - Le nom du projet à exporter.
+/// * `path` - Le chemin de l'archive.
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn Error>>` - Un résultat indiquant si l'exportation a réussi ou échoué.
-pub fn export_project(project_name: &str) -> Result<(), Box<dyn Error>> {
-    let project_path = format!("{}/{}", projects_dir().to_string_lossy(), project_name);
-    let slice_factor_value = slice_factor();
-    let output_dir = output_location().to_string_lossy().to_string();
+/// * `Result<(), ArchiveError>` - Une erreur si l'archive est absente ou corrompue.
+pub fn verify_cached_archive(path: &Path) -> Result<(), ArchiveError> {
+    let (Some(cache), Some(name)) = (path.parent(), path.file_name()) else {
+        return verify_archive(path);
+    };
+    let name = name.to_string_lossy().to_string();
+    let signature = VerifiedArchive::of(path);
+    if signature.is_some() && read_verified_archives(cache).get(&name) == signature.as_ref() {
+        return Ok(());
+    }
 
-    let date = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    verify_archive(path)?;
 
-    match slice_images(project_name, slice_factor_value) {
-        Ok(_) => {
-            compress_folder(
-                &project_path,
-                &format!("export_{}_{}", project_name, date),
-                &output_dir,
-            )?;
-            Ok(())
+    if let Some(signature) = signature {
+        let _guard = CACHE_USAGE_LOCK.lock().unwrap();
+        let mut verified = read_verified_archives(cache);
+        verified.insert(name, signature);
+        let written = serde_json::to_string_pretty(&verified)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                fs::write(cache.join(CACHE_VERIFIED_FILE), content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            tracing::warn!(
+                file = %path.display(),
+                error = %e,
+                "Impossible d'enregistrer la vérification de l'archive"
+            );
         }
-        Err(e) => Err(format!("Echec découpage: {}: {}", project_name, e).into()),
     }
+    Ok(())
 }
 
-/// Exporte un projet en format JPEG
-/// Cette fonction est utilisée pour créer une image JPEG à partir d'un projet GDAL.
-/// Utilise ImageMagick pour exporter un projet en JPEG. (Compatibilité avec le simulateur)
+/// Vérifie toutes les archives du cache.
 ///
 /// # Arguments
 ///
-/// * `project_file_path` - chemin du fichier projet à exporter
-/// * `output_jpg_path` - chemin du fichier JPEG de sortie
+/// * `repair` - Si vrai, supprime les archives corrompues pour qu'elles soient retéléchargées.
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'exportation a réussi ou échoué
-pub fn export_to_jpg(
-    project_file_path: &str,
-    output_jpg_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let magick_status = Command::new("magick")
-        .args([project_file_path, output_jpg_path])
-        .status()?;
+/// * `Result<Vec<String>, Box<dyn Error>>` - Les noms des archives corrompues.
+pub fn verify_cache_archives(repair: bool) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut corrupt = Vec::new();
+    if !cache_dir().exists() {
+        return Ok(corrupt);
+    }
 
-    if !magick_status.success() {
-        return Err("Failed to export to JPEG using ImageMagick".into());
+    for entry in fs::read_dir(cache_dir())? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "7z") {
+            continue;
+        }
+        match verify_archive(&path) {
+            Ok(()) => {}
+            Err(ArchiveError::Corrupt { .. }) => {
+                corrupt.push(path.file_name().unwrap().to_string_lossy().to_string());
+                if repair {
+                    fs::remove_file(&path)?;
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
     }
 
-    Ok(())
+    Ok(corrupt)
 }
 
-pub fn get_project_bounding_box(project_name: &str) -> Result<BoundingBox, String> {
-    let project_path = format!("{}/{}/", projects_dir().to_string_lossy(), project_name);
-    let output = Command::new("gdalinfo")
-        .args([
-            format!("{}{}.tiff", project_path, project_name),
-            "-json".to_owned(),
-        ])
-        .output();
+/// Fichier du cache enregistrant la date de dernière utilisation de chaque archive.
+pub const CACHE_USAGE_FILE: &str = ".usage.json";
 
-    let json_output: Value = serde_json::from_slice(&output.unwrap().stdout)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+/// Fichier du cache enregistrant le millésime de chaque archive téléchargée.
+pub const CACHE_VINTAGES_FILE: &str = ".vintages.json";
 
-    let corner_coordinates = json_output["cornerCoordinates"].as_object().unwrap();
+/// Fichier du cache enregistrant la taille et la date de modification des archives vérifiées.
+pub const CACHE_VERIFIED_FILE: &str = ".verified.json";
 
-    Ok(BoundingBox {
-        xmin: corner_coordinates["lowerLeft"][0].as_f64().unwrap(),
-        ymin: corner_coordinates["lowerLeft"][1].as_f64().unwrap(),
-        xmax: corner_coordinates["upperRight"][0].as_f64().unwrap(),
-        ymax: corner_coordinates["upperRight"][1].as_f64().unwrap(),
-    })
+lazy_static! {
+    /// Archives utilisées par une création en cours, avec leur nombre d'utilisateurs.
+    static ref PINNED_ARCHIVES: std::sync::Mutex<HashMap<PathBuf, usize>> =
+        std::sync::Mutex::new(HashMap::new());
+    /// Sérialise les lectures-écritures de `CACHE_USAGE_FILE`, `CACHE_VINTAGES_FILE`
+    /// et `CACHE_VERIFIED_FILE`.
+    static ref CACHE_USAGE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 }
 
-pub fn get_geojson_bounding_box(
-    file_path: &str,
-) -> Result<BoundingBox, Box<dyn std::error::Error>> {
-    let output = Command::new("ogrinfo")
-        .args(["-so", "-al", file_path])
-        .output()?;
-    let info_str = String::from_utf8(output.stdout)?;
-
-    let extent_pattern = r"Extent:\s*\(([\d.-]+),\s*([\d.-]+)\)\s*-\s*\(([\d.-]+),\s*([\d.-]+)\)";
-    let caps = regex::Regex::new(extent_pattern)?
-        .captures(&info_str)
-        .ok_or("Could not find extent in ogrinfo output")?;
+/// Protège des archives du cache contre l'éviction tant qu'il est conservé.
+pub struct CachePin {
+    paths: Vec<PathBuf>,
+}
 
-    Ok(BoundingBox {
-        xmin: caps[1].parse()?,
+impl Drop for CachePin {
+    fn drop(&mut self) {
+        let mut pinned = PINNED_ARCHIVES.lock().unwrap();
+        for path in &self.paths {
+            if let Some(count) = pinned.get_mut(path) {
+                *count -= 1;
+                if *count == 0 {
+                    pinned.remove(path);
+                }
+            }
+        }
+    }
+}
+
+/// Épingle des archives du cache : elles ne peuvent pas être évincées
+/// tant que le `CachePin` retourné n'est pas libéré.
+///
+/// # Arguments
+///
+/// * `paths` - Les chemins des archives utilisées.
+///
+/// # Returns
+///
+/// * `CachePin` - Le verrou à conserver pendant l'utilisation des archives.
+pub fn pin_cache_entries(paths: &[PathBuf]) -> CachePin {
+    let mut pinned = PINNED_ARCHIVES.lock().unwrap();
+    for path in paths {
+        *pinned.entry(path.clone()).or_insert(0) += 1;
+    }
+    CachePin {
+        paths: paths.to_vec(),
+    }
+}
+
+/// Indique si une archive est épinglée par une création en cours.
+pub fn is_cache_entry_pinned(path: &Path) -> bool {
+    PINNED_ARCHIVES.lock().unwrap().contains_key(path)
+}
+
+fn read_cache_usage(cache: &Path) -> HashMap<String, u64> {
+    fs::read_to_string(cache.join(CACHE_USAGE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_cache_usage(cache: &Path, usage: &HashMap<String, u64>) -> Result<(), Box<dyn Error>> {
+    fs::write(
+        cache.join(CACHE_USAGE_FILE),
+        serde_json::to_string_pretty(usage)?,
+    )?;
+    Ok(())
+}
+
+/// Enregistre l'utilisation d'une archive du cache (téléchargement ou réutilisation).
+///
+/// # Arguments
+///
+/// * `path` - Le chemin de l'archive.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Un résultat indiquant si l'enregistrement a réussi.
+pub fn touch_cache_entry(path: &Path) -> Result<(), Box<dyn Error>> {
+    let (Some(cache), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(format!("Chemin d'archive invalide: {}", path.display()).into());
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as u64;
+
+    let _guard = CACHE_USAGE_LOCK.lock().unwrap();
+    let mut usage = read_cache_usage(cache);
+    usage.insert(name.to_string_lossy().to_string(), now);
+    write_cache_usage(cache, &usage)
+}
+
+/// Lit les millésimes des archives d'un dossier de cache, par nom d'archive.
+/// Les archives téléchargées avant l'enregistrement des millésimes n'y figurent pas.
+pub fn read_cache_vintages(cache: &Path) -> HashMap<String, DataVintage> {
+    fs::read_to_string(cache.join(CACHE_VINTAGES_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_cache_vintages(
+    cache: &Path,
+    vintages: &HashMap<String, DataVintage>,
+) -> Result<(), Box<dyn Error>> {
+    fs::write(
+        cache.join(CACHE_VINTAGES_FILE),
+        serde_json::to_string_pretty(vintages)?,
+    )?;
+    Ok(())
+}
+
+/// Enregistre le millésime d'une archive téléchargée dans le cache.
+///
+/// # Arguments
+///
+/// * `path` - Le chemin de l'archive.
+/// * `vintage` - Le millésime de l'archive et son URL.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Un résultat indiquant si l'enregistrement a réussi.
+pub fn record_cache_vintage(path: &Path, vintage: DataVintage) -> Result<(), Box<dyn Error>> {
+    let (Some(cache), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(format!("Chemin d'archive invalide: {}", path.display()).into());
+    };
+
+    let _guard = CACHE_USAGE_LOCK.lock().unwrap();
+    let mut vintages = read_cache_vintages(cache);
+    vintages.insert(name.to_string_lossy().to_string(), vintage);
+    write_cache_vintages(cache, &vintages)
+}
+
+/// Archive présente dans le cache.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub name: String,
+    pub size_bytes: u64,
+    /// Dernière utilisation, en millisecondes depuis l'époque Unix.
+    pub last_used_ms: u64,
+    /// Archive utilisée par une création en cours.
+    pub pinned: bool,
+}
+
+/// État du cache des archives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheStatus {
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    /// Archives, de la moins récemment utilisée à la plus récente.
+    pub entries: Vec<CacheEntry>,
+    /// Archives évincées lors de la dernière application de la limite.
+    pub evicted: Vec<String>,
+}
+
+/// Liste les archives d'un dossier de cache, de la moins récemment utilisée à la plus récente.
+/// La date de dernière utilisation provient de `CACHE_USAGE_FILE`, ou à défaut
+/// de la date d'accès (puis de modification) du fichier.
+///
+/// # Arguments
+///
+/// * `cache` - Le dossier du cache.
+///
+/// # Returns
+///
+/// * `Result<Vec<CacheEntry>, Box<dyn Error>>` - Les archives du cache.
+pub fn list_cache_entries(cache: &Path) -> Result<Vec<CacheEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    if !cache.exists() {
+        return Ok(entries);
+    }
+
+    let usage = read_cache_usage(cache);
+    for entry in fs::read_dir(cache)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "7z") {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let metadata = fs::metadata(&path)?;
+        let last_used_ms = usage.get(&name).copied().unwrap_or_else(|| {
+            metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default()
+        });
+        entries.push(CacheEntry {
+            pinned: is_cache_entry_pinned(&path),
+            name,
+            size_bytes: metadata.len(),
+            last_used_ms,
+        });
+    }
+
+    entries.sort_by(|a, b| {
+        a.last_used_ms
+            .cmp(&b.last_used_ms)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(entries)
+}
+
+/// Évince les archives les moins récemment utilisées d'un dossier de cache jusqu'à ce que
+/// sa taille totale ne dépasse plus `max_bytes`. Les archives épinglées ne sont jamais évincées,
+/// quitte à laisser le cache au-dessus de la limite.
+///
+/// # Arguments
+///
+/// * `cache` - Le dossier du cache.
+/// * `max_bytes` - La taille maximale du cache en octets.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, Box<dyn Error>>` - Les noms des archives évincées, dans l'ordre d'éviction.
+pub fn enforce_cache_limit_in(cache: &Path, max_bytes: u64) -> Result<Vec<String>, Box<dyn Error>> {
+    let entries = list_cache_entries(cache)?;
+    let mut total: u64 = entries.iter().map(|entry| entry.size_bytes).sum();
+    let mut evicted = Vec::new();
+
+    for entry in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if entry.pinned {
+            continue;
+        }
+        fs::remove_file(cache.join(&entry.name))?;
+        total -= entry.size_bytes;
+        evicted.push(entry.name);
+    }
+
+    if !evicted.is_empty() {
+        let _guard = CACHE_USAGE_LOCK.lock().unwrap();
+        let mut usage = read_cache_usage(cache);
+        usage.retain(|name, _| !evicted.contains(name));
+        write_cache_usage(cache, &usage)?;
+        let mut vintages = read_cache_vintages(cache);
+        if vintages.keys().any(|name| evicted.contains(name)) {
+            vintages.retain(|name, _| !evicted.contains(name));
+            write_cache_vintages(cache, &vintages)?;
+        }
+    }
+    Ok(evicted)
+}
+
+/// Applique la limite de taille du cache définie par `max_cache_size_gb`.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, Box<dyn Error>>` - Les noms des archives évincées.
+pub fn enforce_cache_limit() -> Result<Vec<String>, Box<dyn Error>> {
+    enforce_cache_limit_in(&cache_dir(), max_cache_size_bytes())
+}
+
+/// Applique la limite de taille du cache puis décrit son contenu.
+///
+/// # Returns
+///
+/// * `Result<CacheStatus, Box<dyn Error>>` - L'état du cache.
+pub fn get_cache_status() -> Result<CacheStatus, Box<dyn Error>> {
+    let evicted = enforce_cache_limit()?;
+    let entries = list_cache_entries(&cache_dir())?;
+    Ok(CacheStatus {
+        total_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+        max_bytes: max_cache_size_bytes(),
+        entries,
+        evicted,
+    })
+}
+
+/// Erreur renvoyée par `extract_files_by_name` lorsqu'aucun fichier ne porte le nom recherché.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileNotInArchive(pub String);
+
+impl fmt::Display for FileNotInArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No files matching '{}' found in archive", self.0)
+    }
+}
+
+impl Error for FileNotInArchive {}
+
+pub fn extract_files_by_name(
+    archive_path: &str,
+    target_filename: &str,
+    output_dir: &str,
+) -> Result<(), Box<dyn Error>> {
+    let temp_extract_dir = Path::new(output_dir).join("temp_extract");
+    fs::create_dir_all(&temp_extract_dir)?;
+
+    let mut output_arg = OsString::from("-o");
+    output_arg.push(&temp_extract_dir);
+    let extract_output = Command::new("7z")
+        .arg("x")
+        .arg(archive_path)
+        .arg(output_arg)
+        .output()?;
+
+    if !extract_output.status.success() {
+        return Err("Archive extraction failed".into());
+    }
+
+    let destination = Path::new(output_dir).join(target_filename);
+    fs::create_dir_all(&destination)?;
+
+    let mut found_files = Vec::new();
+    find_files_by_basename(&temp_extract_dir, target_filename, &mut found_files)?;
+
+    if found_files.is_empty() {
+        fs::remove_dir_all(&temp_extract_dir)?;
+        fs::remove_dir_all(&destination)?;
+        return Err(FileNotInArchive(target_filename.to_string()).into());
+    }
+
+    for file_path in &found_files {
+        let file_name = file_path.file_name().unwrap();
+        let dest_path = destination.join(file_name);
+        fs::copy(file_path, dest_path)?;
+    }
+
+    fs::remove_dir_all(temp_extract_dir)?;
+
+    Ok(())
+}
+
+fn find_files_by_basename(
+    dir: &Path,
+    target_basename: &str,
+    result: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_file() {
+                if let Some(file_stem) = path.file_stem() {
+                    if file_stem.to_string_lossy() == target_basename {
+                        result.push(path);
+                    }
+                }
+            } else if path.is_dir() {
+                find_files_by_basename(&path, target_basename, result)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Liste les fichiers d'une archive sans l'extraire (`7z l -slt`).
+///
+/// # Arguments
+///
+/// * `archive_path` - Le chemin de l'archive.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, Box<dyn Error>>` - Les chemins des entrées de l'archive.
+pub fn probe_archive_contents(archive_path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let output = Command::new("7z")
+        .arg("l")
+        .arg("-slt")
+        .arg(archive_path)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Impossible de lire le contenu de l'archive {}: {}",
+            archive_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    Ok(parse_7z_listing(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Extrait les chemins des entrées d'une liste technique `7z l -slt`.
+/// L'en-tête, qui précède la ligne `----------`, décrit l'archive elle-même et est ignoré.
+///
+/// # Arguments
+///
+/// * `listing` - La sortie de `7z l -slt`.
+///
+/// # Returns
+///
+/// * `Vec<String>` - Les chemins des entrées de l'archive.
+pub fn parse_7z_listing(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .skip_while(|line| line.trim() != "----------")
+        .filter_map(|line| line.strip_prefix("Path = "))
+        .map(|path| path.trim().to_string())
+        .collect()
+}
+
+/// Noms de fichiers recherchés absents d'une liste d'entrées d'archive, comparés comme
+/// `extract_files_by_name` au nom du fichier sans extension.
+///
+/// # Arguments
+///
+/// * `entries` - Les entrées de l'archive.
+/// * `basenames` - Les noms recherchés.
+///
+/// # Returns
+///
+/// * `Vec<String>` - Les noms absents, dans l'ordre de `basenames`.
+pub fn missing_basenames(entries: &[String], basenames: &[&str]) -> Vec<String> {
+    let present: HashSet<String> = entries
+        .iter()
+        .filter_map(|entry| {
+            Path::new(&entry.replace('\\', "/"))
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .collect();
+    basenames
+        .iter()
+        .filter(|basename| !present.contains(**basename))
+        .map(|basename| basename.to_string())
+        .collect()
+}
+
+/// Noms des projets du dossier des projets et du registre des projets externes.
+fn project_names(registry: &BTreeMap<String, PathBuf>) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut project_names = Vec::new();
+    if projects_dir().exists() {
+        for entry in fs::read_dir(projects_dir())? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if let Some(name) = path.file_name() {
+                    project_names.push(name.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+    for (name, folder) in registry {
+        if folder.is_dir() && !project_names.contains(name) {
+            project_names.push(name.clone());
+        } else if !folder.is_dir() {
+            tracing::warn!(project = %name, folder = %folder.display(), "Projet externe introuvable");
+        }
+    }
+    project_names.retain(|name| name != "cache" && !name.starts_with('.'));
+    Ok(project_names)
+}
+
+/// Aperçu d'un projet, généré si besoin ; l'orthophoto s'il ne peut pas l'être.
+fn project_preview_path(project_name: &str) -> PathBuf {
+    match generate_project_preview(project_name) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!(project = %project_name, error = %e, "Aperçu indisponible");
+            project_ortho_jpeg(project_name)
+        }
+    }
+}
+
+pub fn get_previous_projects() -> Result<HashMap<String, Vec<String>>, Box<dyn Error>> {
+    let registry = load_projects_registry();
+    let mut projects = HashMap::new();
+    for project_name in project_names(&registry)? {
+        let project_path = project_dir(&project_name);
+        let preview_image_path = project_preview_path(&project_name);
+        let status = if !is_project_complete(&project_path) {
+            "incomplete"
+        } else {
+            "complete"
+        };
+        let location = if registry.contains_key(&project_name) {
+            "external"
+        } else {
+            "local"
+        };
+        let vintages = format_vintages(&load_project_metadata(&project_name).vintages);
+        projects.insert(
+            project_name,
+            vec![
+                preview_image_path.to_string_lossy().to_string(),
+                project_path.to_string_lossy().to_string(),
+                vintages,
+                status.to_string(),
+                location.to_string(),
+            ],
+        );
+    }
+    Ok(projects)
+}
+
+/// Critère de tri de la liste des projets (voir `list_projects`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectSort {
+    /// Par nom.
+    #[default]
+    Name,
+    /// Du plus récent au plus ancien ; les projets sans date de création sont à la fin.
+    CreatedAt,
+    /// Du plus volumineux au plus petit.
+    Size,
+    /// Par premier code de département ; les projets sans département sont à la fin.
+    Department,
+}
+
+impl ProjectSummary {
+    /// Indique si le projet correspond à une recherche : nom contenant le texte recherché,
+    /// ou code de département égal à celui-ci, sans tenir compte de la casse.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        filter.is_empty()
+            || self.name.to_lowercase().contains(&filter)
+            || self
+                .regions
+                .iter()
+                .any(|code| code.to_lowercase() == filter)
+    }
+}
+
+/// Trie des projets selon un critère ; les projets à égalité restent triés par nom.
+///
+/// # Arguments
+///
+/// * `projects` - Les projets à trier.
+/// * `sort_by` - Le critère de tri.
+pub fn sort_projects(projects: &mut [ProjectSummary], sort_by: ProjectSort) {
+    projects.sort_by(|a, b| {
+        let order = match sort_by {
+            ProjectSort::Name => std::cmp::Ordering::Equal,
+            ProjectSort::CreatedAt => {
+                let created_at = |project: &ProjectSummary| {
+                    project
+                        .created_at
+                        .as_deref()
+                        .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
+                };
+                match (created_at(a), created_at(b)) {
+                    (Some(a), Some(b)) => b.cmp(&a),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                }
+            }
+            ProjectSort::Size => b.size_bytes.cmp(&a.size_bytes),
+            ProjectSort::Department => match (a.regions.first(), b.regions.first()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            },
+        };
+        order.then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Codes des départements d'un projet. Les projets antérieurs à leur enregistrement
+/// dans les métadonnées les retrouvent dans leurs millésimes.
+pub fn project_regions(metadata: &ProjectMetadata) -> Vec<String> {
+    let mut regions = if metadata.regions.is_empty() {
+        metadata
+            .vintages
+            .iter()
+            .map(|vintage| vintage.region.clone())
+            .collect()
+    } else {
+        metadata.regions.clone()
+    };
+    regions.sort();
+    regions.dedup();
+    regions
+}
+
+/// Taille du dossier d'un projet : la valeur enregistrée dans ses métadonnées, ou à défaut
+/// la somme de la taille de ses fichiers.
+pub fn project_size_bytes(project_name: &str, metadata: &ProjectMetadata) -> u64 {
+    metadata
+        .size_bytes
+        .unwrap_or_else(|| directory_size(&project_dir(project_name)))
+}
+
+/// Mesure la taille du dossier d'un projet et l'enregistre dans ses métadonnées.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<u64, Box<dyn Error>>` - La taille mesurée, en octets.
+pub fn update_project_size(project_name: &str) -> Result<u64, Box<dyn Error>> {
+    let size_bytes = directory_size(&project_dir(project_name));
+    let mut metadata = load_project_metadata(project_name);
+    metadata.size_bytes = Some(size_bytes);
+    save_project_metadata(project_name, &metadata)?;
+    Ok(size_bytes)
+}
+
+/// Noms de tous les projets : ceux du dossier des projets et les projets importés par référence.
+pub fn all_project_names() -> Result<Vec<String>, Box<dyn Error>> {
+    project_names(&load_projects_registry())
+}
+
+/// Liste les projets pour l'accueil, filtrés puis triés.
+///
+/// # Arguments
+///
+/// * `sort_by` - Le critère de tri.
+/// * `filter` - Texte recherché dans le nom ou code de département (voir `ProjectSummary::matches`).
+///
+/// # Returns
+///
+/// * `Result<Vec<ProjectSummary>, Box<dyn Error>>` - Les projets retenus, dans l'ordre demandé.
+pub fn list_projects(
+    sort_by: ProjectSort,
+    filter: Option<&str>,
+) -> Result<Vec<ProjectSummary>, Box<dyn Error>> {
+    let registry = load_projects_registry();
+    let mut projects = Vec::new();
+    for project_name in project_names(&registry)? {
+        let summary = summarize_project(project_name, &registry);
+        if filter.is_none_or(|filter| summary.matches(filter)) {
+            projects.push(summary);
+        }
+    }
+    // Les aperçus ne sont générés que pour les projets retenus.
+    for summary in &mut projects {
+        summary.preview_path = project_preview_path(&summary.name)
+            .to_string_lossy()
+            .to_string();
+    }
+    sort_projects(&mut projects, sort_by);
+    Ok(projects)
+}
+
+/// Résumé d'un projet pour l'accueil, sans son aperçu.
+fn summarize_project(project_name: String, registry: &BTreeMap<String, PathBuf>) -> ProjectSummary {
+    let metadata = load_project_metadata(&project_name);
+    ProjectSummary {
+        preview_path: String::new(),
+        folder: project_dir(&project_name).to_string_lossy().to_string(),
+        vintages: format_vintages(&metadata.vintages),
+        created_at: metadata.created_at.clone(),
+        size_bytes: project_size_bytes(&project_name, &metadata),
+        regions: project_regions(&metadata),
+        center: get_project_bounding_box(&project_name)
+            .ok()
+            .map(|bb| ((bb.xmin + bb.xmax) / 2.0, (bb.ymin + bb.ymax) / 2.0)),
+        complete: is_project_complete(&project_dir(&project_name)),
+        external: registry.contains_key(&project_name),
+        name: project_name,
+    }
+}
+
+/// Résumé d'un projet, tel que listé par `list_projects`, avec son aperçu.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `ProjectSummary` - Le résumé du projet.
+pub fn project_summary(project_name: &str) -> ProjectSummary {
+    let mut summary = summarize_project(project_name.to_string(), &load_projects_registry());
+    summary.preview_path = project_preview_path(project_name)
+        .to_string_lossy()
+        .to_string();
+    summary
+}
+
+/// Registre des projets externes : projets ouverts depuis un dossier hors du dossier
+/// des projets (un lecteur réseau partagé par exemple), par nom de projet.
+pub const PROJECTS_REGISTRY_FILE: &str = "projects_registry.json";
+
+fn projects_registry_path() -> PathBuf {
+    projects_dir().join(PROJECTS_REGISTRY_FILE)
+}
+
+/// Lit le registre des projets externes ; un registre absent ou illisible est vide.
+///
+/// # Returns
+///
+/// * `BTreeMap<String, PathBuf>` - Le dossier de chaque projet externe, par nom de projet.
+pub fn load_projects_registry() -> BTreeMap<String, PathBuf> {
+    fs::read_to_string(projects_registry_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_projects_registry(registry: &BTreeMap<String, PathBuf>) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(projects_dir())?;
+    fs::write(
+        projects_registry_path(),
+        serde_json::to_string_pretty(registry)?,
+    )?;
+    Ok(())
+}
+
+/// Dossier d'un projet externe, `None` pour un projet du dossier des projets.
+pub fn external_project_dir(project_name: &str) -> Option<PathBuf> {
+    load_projects_registry().remove(project_name)
+}
+
+/// Retire un projet du registre des projets externes, sans toucher à son dossier.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<bool, Box<dyn Error>>` - `false` si le projet n'était pas enregistré.
+pub fn unregister_external_project(project_name: &str) -> Result<bool, Box<dyn Error>> {
+    let mut registry = load_projects_registry();
+    if registry.remove(project_name).is_none() {
+        return Ok(false);
+    }
+    save_projects_registry(&registry)?;
+    Ok(true)
+}
+
+/// Manière d'importer un projet existant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Le dossier reste en place et est inscrit au registre des projets externes.
+    #[default]
+    Reference,
+    /// Le dossier est copié dans le dossier des projets.
+    Copy,
+    /// Le dossier est déplacé dans le dossier des projets.
+    Move,
+}
+
+/// Vérifie qu'un dossier contient un projet : un GeoTIFF `<nom du dossier>.tiff` en Lambert-93
+/// (EPSG:2154) ou dans le système d'un département d'outre-mer (voir `OVERSEAS_CRS`).
+/// Les images de végétation et d'orthophoto sont facultatives.
+///
+/// # Arguments
+///
+/// * `folder` - Le dossier du projet.
+///
+/// # Returns
+///
+/// * `Result<String, Box<dyn Error>>` - Le nom du projet, tiré du nom du dossier.
+pub fn validate_project_folder(folder: &Path) -> Result<String, Box<dyn Error>> {
+    if !folder.is_dir() {
+        return Err(format!("{} n'est pas un dossier", folder.display()).into());
+    }
+    let name = folder
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("{} n'a pas de nom de dossier", folder.display()))?;
+    validate_project_name(&name)?;
+
+    let tiff = folder.join(format!("{}.tiff", name));
+    if !tiff.is_file() {
+        return Err(format!(
+            "Le dossier ne contient pas de raster de projet {}",
+            tiff.display()
+        )
+        .into());
+    }
+    let epsg = Dataset::open(&tiff)?
+        .spatial_ref()
+        .ok()
+        .and_then(|srs| srs.auth_code().ok());
+    if !epsg.is_some_and(|epsg| epsg > 0 && is_project_epsg(epsg as u32)) {
+        return Err(format!(
+            "Le raster {} n'est ni en Lambert-93 (EPSG:2154) ni dans le système d'un département d'outre-mer",
+            tiff.display()
+        )
+        .into());
+    }
+    Ok(name)
+}
+
+/// Importe un projet existant depuis un dossier quelconque, après validation
+/// (voir `validate_project_folder`). Le nom du projet ne doit pas être déjà utilisé.
+///
+/// # Arguments
+///
+/// * `folder` - Le dossier du projet.
+/// * `mode` - Inscription au registre des projets externes, copie ou déplacement.
+///
+/// # Returns
+///
+/// * `Result<(String, PathBuf), Box<dyn Error>>` - Le nom du projet et son dossier.
+pub fn import_project(
+    folder: &Path,
+    mode: ImportMode,
+) -> Result<(String, PathBuf), Box<dyn Error>> {
+    let name = validate_project_folder(folder)?;
+    let folder = folder.canonicalize()?;
+    if folder.parent() == projects_dir().canonicalize().ok().as_deref() {
+        return Err(format!("Le projet '{}' est déjà dans le dossier des projets", name).into());
+    }
+    if project_dir(&name).exists() {
+        return Err(format!("Un projet nommé '{}' existe déjà", name).into());
+    }
+
+    let destination = in_projects_dir(&name);
+    match mode {
+        ImportMode::Reference => {
+            let mut registry = load_projects_registry();
+            registry.insert(name.clone(), folder.clone());
+            save_projects_registry(&registry)?;
+            return Ok((name, folder));
+        }
+        ImportMode::Copy => {
+            if let Err(e) = copy_dir_recursive(&folder, &destination) {
+                let _ = fs::remove_dir_all(&destination);
+                return Err(e);
+            }
+        }
+        ImportMode::Move => {
+            if fs::rename(&folder, &destination).is_err() {
+                // Le dossier est sur un autre système de fichiers : copie puis suppression.
+                if let Err(e) = copy_dir_recursive(&folder, &destination) {
+                    let _ = fs::remove_dir_all(&destination);
+                    return Err(e);
+                }
+                fs::remove_dir_all(&folder)?;
+            }
+        }
+    }
+    Ok((name, destination))
+}
+
+/// Marqueur écrit dans le dossier d'un projet une fois sa création terminée.
+pub const PROJECT_COMPLETE_MARKER: &str = ".complete";
+
+/// Écrit le marqueur de fin de création dans le dossier d'un projet.
+///
+/// # Arguments
+///
+/// * `project_folder` - Le dossier du projet.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si le marqueur n'a pas pu être écrit.
+pub fn mark_project_complete(project_folder: &Path) -> Result<(), Box<dyn Error>> {
+    fs::write(
+        project_folder.join(PROJECT_COMPLETE_MARKER),
+        chrono::Local::now().to_rfc3339(),
+    )?;
+    Ok(())
+}
+
+/// Indique si la création d'un projet est allée à son terme.
+/// Les projets antérieurs au marqueur sont reconnus à leur état de création achevé ; ceux
+/// des versions sans état de création, à leur raster `<nom>.tiff` lisible. Seul un état de
+/// création inachevé désigne une création interrompue.
+///
+/// # Arguments
+///
+/// * `project_folder` - Le dossier du projet.
+///
+/// # Returns
+///
+/// * `bool` - `true` si le marqueur est présent, si toutes les étapes de la création sont
+///   terminées, ou si le dossier est un projet sans état de création dont le raster est lisible.
+pub fn is_project_complete(project_folder: &Path) -> bool {
+    if project_folder.join(PROJECT_COMPLETE_MARKER).exists() {
+        return true;
+    }
+    if project_folder.join(PIPELINE_STATE_FILE).exists() {
+        return PipelineState::load(project_folder).is_ok_and(|state| state.is_finished());
+    }
+    project_folder.file_name().is_some_and(|name| {
+        let tiff = project_folder.join(format!("{}.tiff", name.to_string_lossy()));
+        tiff.is_file() && Dataset::open(&tiff).is_ok()
+    })
+}
+
+/// Liste les projets dont la création a été interrompue, par exemple par un arrêt
+/// de l'application.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, Box<dyn Error>>` - Les noms des projets incomplets, triés.
+pub fn get_incomplete_projects() -> Result<Vec<String>, Box<dyn Error>> {
+    let projects_dir = projects_dir();
+    if !projects_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut incomplete = Vec::new();
+    for entry in fs::read_dir(&projects_dir)? {
+        let path = entry?.path();
+        if !path.is_dir() || is_project_complete(&path) {
+            continue;
+        }
+        if let Some(name) = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+        {
+            if name != "cache" && !name.starts_with('.') {
+                incomplete.push(name);
+            }
+        }
+    }
+    incomplete.sort();
+    Ok(incomplete)
+}
+
+/// Nom du fichier de métadonnées d'un projet.
+pub const PROJECT_METADATA_FILE: &str = "project.json";
+
+/// Métadonnées enregistrées dans le dossier d'un projet.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectMetadata {
+    /// Source des données de végétation (`bdforet`, `oso` ou `file:<chemin>`).
+    pub vegetation_source: String,
+    /// Date de création du projet (RFC 3339).
+    pub created_at: Option<String>,
+    /// Millésimes des archives IGN utilisées, par type de données et département.
+    pub vintages: Vec<DataVintage>,
+    /// Durées des étapes de la création.
+    pub timings: StageTimings,
+    /// Avertissements sur les données retenues, par exemple une BD Forêt v1 faute de v2.
+    pub data_warnings: Vec<Warning>,
+    /// Projet dont celui-ci a été découpé sans téléchargement (voir `derive_project`).
+    pub derived_from: Option<String>,
+    /// Couches retenues à la création (voir `LayerConfig::with_selection`), toutes si `None`.
+    pub layers: Option<Vec<String>>,
+    /// Limite de taille dépassée sur dérogation lors de la création (voir `check_project_size`).
+    pub size_limit_override: Option<String>,
+    /// Couches de l'utilisateur ajoutées au projet (voir `add_custom_layer`),
+    /// réappliquées par `add_layers`.
+    pub custom_layers: Vec<CustomLayer>,
+    /// Code EPSG du système de coordonnées du projet, déduit de ses départements
+    /// (voir `regions_epsg`). Absent des projets antérieurs, tous en Lambert-93.
+    pub epsg: Option<u32>,
+    /// Codes des départements couverts par le projet.
+    pub regions: Vec<String>,
+    /// Taille du dossier du projet en octets, mise à jour à la création et à l'export
+    /// (voir `update_project_size`).
+    pub size_bytes: Option<u64>,
+    /// Densité du bâti par case d'un kilomètre (voir `add_building_layer`), absente
+    /// si le projet n'a pas de bâtiments.
+    pub built_density: Option<BuiltDensity>,
+    /// Paramètres de la création, repris par « Dupliquer les paramètres de... »
+    /// (voir `project_creation_params`).
+    pub creation_params: Option<CreationParams>,
+    /// Projet créé hors ligne, à partir des seules archives du cache (voir `download_stage`).
+    pub offline: bool,
+    /// Orthophoto non téléchargée à la création hors ligne : `refresh_ortho` la produit
+    /// une fois le réseau revenu.
+    pub ortho_skipped: bool,
+    /// Couleur du fond départemental, fixée à la création (`default_land_color`).
+    /// Absente des projets antérieurs, dont le fond est noir comme les bâtiments.
+    pub land_color: Option<Rgb>,
+    /// Couches sans entité dans l'emprise du projet, par code de département : leur GPKG
+    /// découpé n'a pas été fusionné (voir `prepare_layers`).
+    pub empty_layers: BTreeMap<String, Vec<String>>,
+}
+
+impl ProjectMetadata {
+    /// Code EPSG du système de coordonnées du projet.
+    pub fn project_epsg(&self) -> u32 {
+        self.epsg.unwrap_or(DEFAULT_PROJECT_EPSG)
+    }
+
+    /// Couleur du fond départemental du projet.
+    pub fn project_land_color(&self) -> Rgb {
+        self.land_color.unwrap_or(colormap::LEGACY_REGIONAL)
+    }
+}
+
+/// Millésime d'une archive utilisée pour construire un projet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataVintage {
+    /// Type de données (`BDTOPO`, `BDFORET` ou `RPG`).
+    pub data_type: String,
+    /// Code du département.
+    pub region: String,
+    /// Date du millésime (`AAAA-MM-JJ`), si le nom de l'archive en contient une.
+    pub date: Option<String>,
+    /// URL de l'archive téléchargée.
+    pub url: String,
+}
+
+/// Résume les millésimes d'un projet pour l'affichage, par exemple
+/// `BDTOPO 2025-03, BDFORET 2017-05, RPG 2023`. Le RPG étant annuel, seule l'année est gardée ;
+/// lorsque les départements d'un projet ont des millésimes différents, ils sont tous listés.
+///
+/// # Arguments
+///
+/// * `vintages` - Les millésimes enregistrés dans les métadonnées.
+///
+/// # Returns
+///
+/// * `String` - Le résumé, vide si aucun millésime n'est connu.
+pub fn format_vintages(vintages: &[DataVintage]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for data_type in ["BDTOPO", "BDFORET", "RPG"] {
+        let mut dates: Vec<&str> = vintages
+            .iter()
+            .filter(|vintage| vintage.data_type == data_type)
+            .filter_map(|vintage| vintage.date.as_deref())
+            .map(|date| {
+                let len = if data_type == "RPG" { 4 } else { 7 };
+                date.get(..len).unwrap_or(date)
+            })
+            .collect();
+        dates.sort_unstable_by(|a, b| b.cmp(a));
+        dates.dedup();
+        if !dates.is_empty() {
+            parts.push(format!("{} {}", data_type, dates.join("/")));
+        }
+    }
+    parts.join(", ")
+}
+
+/// Paramètres choisis à la création d'un projet, enregistrés dans ses métadonnées. Un projet
+/// peut servir de modèle à une nouvelle création (`template_project` de `create_project_com`) :
+/// ses paramètres deviennent les valeurs par défaut des arguments non renseignés.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CreationParams {
+    /// Source de végétation (`bdforet`, `oso` ou `file:<chemin>`).
+    pub vegetation_source: Option<String>,
+    /// Couches retenues (voir `LayerConfig::with_selection`), toutes si `None`.
+    pub layers: Option<Vec<String>>,
+    /// Résolution du raster en mètres par pixel.
+    pub resolution: Option<f64>,
+    /// Source d'orthophoto essayée en premier.
+    pub ortho_source: Option<String>,
+}
+
+impl CreationParams {
+    /// Paramètres d'un projet créé avant leur enregistrement, reconstitués à partir
+    /// de ses métadonnées.
+    pub fn from_metadata(metadata: &ProjectMetadata) -> Self {
+        CreationParams {
+            vegetation_source: Some(metadata.vegetation_source.clone())
+                .filter(|source| !source.is_empty()),
+            layers: metadata.layers.clone(),
+            ..CreationParams::default()
+        }
+    }
+
+    /// Complète les paramètres renseignés avec ceux d'un projet modèle.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Les paramètres du projet modèle.
+    ///
+    /// # Returns
+    ///
+    /// * `CreationParams` - Les paramètres renseignés, ceux du modèle à défaut.
+    pub fn or_template(self, template: &CreationParams) -> Self {
+        CreationParams {
+            vegetation_source: self
+                .vegetation_source
+                .or_else(|| template.vegetation_source.clone()),
+            layers: self.layers.or_else(|| template.layers.clone()),
+            resolution: self.resolution.or(template.resolution),
+            ortho_source: self.ortho_source.or_else(|| template.ortho_source.clone()),
+        }
+    }
+}
+
+/// Paramètres de création d'un projet existant.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<CreationParams, Box<dyn Error>>` - Les paramètres enregistrés ou reconstitués
+///   depuis les métadonnées, ou une erreur si le projet n'existe pas.
+pub fn project_creation_params(project_name: &str) -> Result<CreationParams, Box<dyn Error>> {
+    if !project_dir(project_name).is_dir() {
+        return Err(format!("Le projet '{}' n'existe pas", project_name).into());
+    }
+    let metadata = load_project_metadata(project_name);
+    Ok(metadata
+        .creation_params
+        .clone()
+        .unwrap_or_else(|| CreationParams::from_metadata(&metadata)))
+}
+
+/// Enregistre les métadonnées d'un projet dans `project.json`.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `metadata` - Les métadonnées à enregistrer.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Un résultat indiquant si l'écriture a réussi ou échoué.
+pub fn save_project_metadata(
+    project_name: &str,
+    metadata: &ProjectMetadata,
+) -> Result<(), Box<dyn Error>> {
+    let json = serde_json::to_string_pretty(metadata)?;
+    fs::write(project_dir(project_name).join(PROJECT_METADATA_FILE), json)?;
+    Ok(())
+}
+
+/// Lit les métadonnées d'un projet ; les projets plus anciens sans `project.json`
+/// reçoivent les valeurs par défaut.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `ProjectMetadata` - Les métadonnées du projet.
+pub fn load_project_metadata(project_name: &str) -> ProjectMetadata {
+    fs::read_to_string(project_dir(project_name).join(PROJECT_METADATA_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Informations d'un projet affichées dans sa barre latérale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectDetails {
+    pub name: String,
+    #[serde(flatten)]
+    pub metadata: ProjectMetadata,
+    /// Résumé des millésimes (voir `format_vintages`).
+    pub vintage_summary: String,
+}
+
+/// Rassemble les informations d'un projet à partir de ses métadonnées.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<ProjectDetails, Box<dyn Error>>` - Les informations du projet, ou une erreur s'il n'existe pas.
+pub fn get_project_details(project_name: &str) -> Result<ProjectDetails, Box<dyn Error>> {
+    if !project_dir(project_name).is_dir() {
+        return Err(format!("Le projet '{}' n'existe pas", project_name).into());
+    }
+    let metadata = load_project_metadata(project_name);
+    Ok(ProjectDetails {
+        name: project_name.to_string(),
+        vintage_summary: format_vintages(&metadata.vintages),
+        metadata,
+    })
+}
+
+/// Nom du fichier d'aperçu affiché sur la page d'accueil.
+pub const PREVIEW_FILE: &str = "preview.jpg";
+
+/// Taille maximale (en pixels) du plus grand côté de l'aperçu.
+pub const PREVIEW_MAX_SIZE: u32 = 400;
+
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Génère l'aperçu `preview.jpg` d'un projet à partir de son orthophoto,
+/// ou de l'image de végétation si l'orthophoto est absente, ou à défaut un aplat gris.
+/// L'aperçu n'est régénéré que s'il est absent ou plus ancien que l'image source.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le chemin de l'aperçu.
+pub fn generate_project_preview(project_name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let project_path = project_dir(project_name);
+    let preview_path = project_path.join(PREVIEW_FILE);
+
+    let source = [
+        project_ortho_jpeg(project_name),
+        project_veget_jpeg(project_name),
+    ]
+    .into_iter()
+    .find(|path| path.exists());
+
+    if let Some(preview_time) = modified_time(&preview_path) {
+        let source_time = source.as_deref().and_then(modified_time);
+        if source_time.is_none_or(|time| time <= preview_time) {
+            return Ok(preview_path);
+        }
+    }
+
+    let preview = match &source {
+        Some(path) => image::open(path)?
+            .thumbnail(PREVIEW_MAX_SIZE, PREVIEW_MAX_SIZE)
+            .to_rgb8(),
+        None => RgbImage::from_pixel(
+            PREVIEW_MAX_SIZE,
+            PREVIEW_MAX_SIZE,
+            image::Rgb([128, 128, 128]),
+        ),
+    };
+    save_rgb_jpeg(&preview, &preview_path.to_string_lossy(), 85)?;
+
+    Ok(preview_path)
+}
+
+/// Caractères interdits dans un nom de projet (séparateurs de chemin et caractères réservés).
+const INVALID_PROJECT_NAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Longueur maximale d'un nom de projet, en caractères.
+pub const MAX_PROJECT_NAME_LEN: usize = 100;
+
+/// Noms de fichiers réservés par Windows, quelle que soit leur extension.
+const RESERVED_PROJECT_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Nom de projet validé par `validate_project_name`, utilisable sans risque dans un chemin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizedName(String);
+
+impl SanitizedName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SanitizedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Raison du refus d'un nom de projet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// Le nom est vide ou ne contient que des espaces.
+    Empty,
+    /// Le nom contient un séparateur de chemin, un caractère réservé ou de contrôle.
+    InvalidCharacter { name: String, character: char },
+    /// Le nom est réservé par Windows (`CON`, `NUL`, `COM1`...) ou par l'application
+    /// (`cache`, dossiers cachés comme `.trash`).
+    Reserved { name: String },
+    /// Le nom se termine par un point ou une espace (dont `.` et `..`).
+    TrailingDotOrSpace { name: String },
+    /// Le nom dépasse `MAX_PROJECT_NAME_LEN` caractères.
+    TooLong { name: String, length: usize },
+}
+
+impl NameError {
+    /// Nom corrigé proposé à la place du nom refusé.
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            NameError::Empty => None,
+            NameError::InvalidCharacter { name, .. }
+            | NameError::Reserved { name }
+            | NameError::TrailingDotOrSpace { name }
+            | NameError::TooLong { name, .. } => Some(sanitize_project_name(name)),
+        }
+    }
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::Empty => return write!(f, "Le nom du projet est vide"),
+            NameError::InvalidCharacter { name, character } => write!(
+                f,
+                "Le nom de projet '{}' contient un caractère interdit: '{}'",
+                name,
+                character.escape_default()
+            )?,
+            NameError::Reserved { name } => {
+                write!(f, "Le nom de projet '{}' est réservé par le système", name)?
+            }
+            NameError::TrailingDotOrSpace { name } => write!(
+                f,
+                "Le nom de projet '{}' ne peut pas se terminer par un point ou une espace",
+                name
+            )?,
+            NameError::TooLong { length, .. } => write!(
+                f,
+                "Le nom de projet est trop long: {} caractères (maximum {})",
+                length, MAX_PROJECT_NAME_LEN
+            )?,
+        }
+        match self.suggestion() {
+            Some(suggestion) => write!(f, " (nom suggéré: '{}')", suggestion),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for NameError {}
+
+fn is_reserved_project_name(name: &str) -> bool {
+    // Les dossiers cachés et le cache ne sont pas listés comme projets.
+    if name.starts_with('.') || name == "cache" {
+        return true;
+    }
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_PROJECT_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+}
+
+/// Vérifie qu'un nom de projet peut servir de nom de dossier sur toutes les plateformes,
+/// sans sortir du dossier des projets (`..`, séparateurs de chemin).
+///
+/// # Arguments
+///
+/// * `name` - Le nom de projet saisi.
+///
+/// # Returns
+///
+/// * `Result<SanitizedName, NameError>` - Le nom validé, ou la raison du refus
+///   avec un nom corrigé suggéré.
+pub fn validate_project_name(name: &str) -> Result<SanitizedName, NameError> {
+    if name.trim().is_empty() {
+        return Err(NameError::Empty);
+    }
+    if let Some(character) = name
+        .chars()
+        .find(|c| INVALID_PROJECT_NAME_CHARS.contains(c) || c.is_control())
+    {
+        return Err(NameError::InvalidCharacter {
+            name: name.to_string(),
+            character,
+        });
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(NameError::TrailingDotOrSpace {
+            name: name.to_string(),
+        });
+    }
+    if is_reserved_project_name(name) {
+        return Err(NameError::Reserved {
+            name: name.to_string(),
+        });
+    }
+    let length = name.chars().count();
+    if length > MAX_PROJECT_NAME_LEN {
+        return Err(NameError::TooLong {
+            name: name.to_string(),
+            length,
+        });
+    }
+    Ok(SanitizedName(name.to_string()))
+}
+
+/// Corrige un nom de projet : caractères interdits remplacés par `-`, points et espaces
+/// de fin retirés, longueur limitée et noms réservés complétés par `_`.
+/// Le résultat est toujours accepté par `validate_project_name`.
+pub fn sanitize_project_name(name: &str) -> String {
+    let replaced: String = name
+        .trim_start()
+        .chars()
+        .map(|c| {
+            if INVALID_PROJECT_NAME_CHARS.contains(&c) || c.is_control() {
+                '-'
+            } else {
+                c
+            }
+        })
+        .take(MAX_PROJECT_NAME_LEN)
+        .collect();
+    let mut sanitized = replaced.trim_end_matches(['.', ' ']).to_string();
+    if sanitized.is_empty() {
+        sanitized = "projet".to_string();
+    }
+    if is_reserved_project_name(&sanitized) {
+        let stem_end = sanitized.find('.').unwrap_or(sanitized.len());
+        sanitized.insert(stem_end, '_');
+        sanitized = sanitized
+            .chars()
+            .take(MAX_PROJECT_NAME_LEN)
+            .collect::<String>()
+            .trim_end_matches(['.', ' '])
+            .to_string();
+    }
+    sanitized
+}
+
+/// Indique si un fichier du dossier projet porte le nom du projet (`<nom>.tiff`, `<nom>_VEGET.jpeg`...).
+fn is_name_embedded_artifact(file_name: &str, project_name: &str) -> bool {
+    file_name
+        .strip_prefix(project_name)
+        .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('_'))
+}
+
+/// Établit le plan de renommage des fichiers portant le nom du projet,
+/// à la racine du dossier et dans `resources/`.
+fn name_embedded_renames(
+    project_folder: &Path,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Vec<(PathBuf, PathBuf)>, Box<dyn Error>> {
+    let mut plan: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for folder in [
+        project_folder.to_path_buf(),
+        project_folder.join("resources"),
+    ] {
+        if !folder.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&folder)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            if is_name_embedded_artifact(&file_name, old_name) {
+                let renamed = format!("{}{}", new_name, &file_name[old_name.len()..]);
+                plan.push((folder.join(&file_name), folder.join(renamed)));
+            }
+        }
+    }
+    Ok(plan)
+}
+
+fn copy_dir_recursive(source: &Path, destination: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let target = destination.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            copy_dir_recursive(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Renomme une liste de chemins dans l'ordre, en annulant les renommages déjà appliqués en cas d'échec.
+fn apply_rename_plan(plan: &[(PathBuf, PathBuf)]) -> Result<(), Box<dyn Error>> {
+    for (index, (from, to)) in plan.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            for (applied_from, applied_to) in plan[..index].iter().rev() {
+                let _ = fs::rename(applied_to, applied_from);
+            }
+            return Err(format!(
+                "Échec du renommage de {} en {}: {}",
+                from.display(),
+                to.display(),
+                e
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Renomme un projet : son dossier et tous les fichiers portant son nom
+/// (`<nom>.tiff`, `<nom>_VEGET.jpeg`, `<nom>_ORTHO.jpeg`, `<nom>_CLASSES.*`, `resources/<nom>.gpkg`...).
+/// L'opération est transactionnelle : le plan de renommage est établi avant toute modification
+/// et les renommages déjà appliqués sont annulés en cas d'échec.
+///
+/// # Arguments
+///
+/// * `old_name` - Le nom actuel du projet.
+/// * `new_name` - Le nouveau nom du projet.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le chemin du dossier renommé.
+pub fn rename_project(old_name: &str, new_name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    validate_project_name(old_name)?;
+    validate_project_name(new_name)?;
+
+    let old_dir = project_dir(old_name);
+    let new_dir = project_dir(new_name);
+    if !old_dir.is_dir() {
+        return Err(format!("Le projet '{}' n'existe pas", old_name).into());
+    }
+    if external_project_dir(old_name).is_some() {
+        return Err(format!(
+            "Le projet '{}' est un projet externe et ne peut pas être renommé",
+            old_name
+        )
+        .into());
+    }
+    if new_dir.exists() {
+        return Err(format!("Un projet nommé '{}' existe déjà", new_name).into());
+    }
+
+    let mut plan = name_embedded_renames(&old_dir, old_name, new_name)?;
+    plan.push((old_dir, new_dir.clone()));
+
+    apply_rename_plan(&plan)?;
+    refresh_qgis_project(new_name);
+
+    Ok(new_dir)
+}
+
+/// Copie un projet sous un nouveau nom, en renommant les fichiers portant son nom.
+/// En cas d'échec, la copie partielle est supprimée.
+///
+/// # Arguments
+///
+/// * `source_name` - Le nom du projet à copier.
+/// * `new_name` - Le nom de la copie.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le chemin du dossier de la copie.
+pub fn copy_project(source_name: &str, new_name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    validate_project_name(source_name)?;
+    validate_project_name(new_name)?;
+
+    let source_dir = project_dir(source_name);
+    let new_dir = project_dir(new_name);
+    if !source_dir.is_dir() {
+        return Err(format!("Le projet '{}' n'existe pas", source_name).into());
+    }
+    if new_dir.exists() {
+        return Err(format!("Un projet nommé '{}' existe déjà", new_name).into());
+    }
+
+    let result = copy_dir_recursive(&source_dir, &new_dir).and_then(|_| {
+        let plan = name_embedded_renames(&new_dir, source_name, new_name)?;
+        apply_rename_plan(&plan)
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&new_dir);
+        return Err(e);
+    }
+    refresh_qgis_project(new_name);
+
+    Ok(new_dir)
+}
+
+/// Projet existant portant le nom demandé pour une création.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectConflict {
+    pub name: String,
+    /// Date de création du projet existant (RFC 3339), si elle est connue.
+    pub created_at: Option<String>,
+    /// Taille du dossier du projet existant, en octets.
+    pub size_bytes: u64,
+    /// Emprise du projet existant, si son raster est lisible.
+    pub bbox: Option<BoundingBox>,
+    /// Nom libre proposé pour la nouvelle création.
+    pub suggested_name: String,
+}
+
+/// Décrit le projet existant qui empêche la création d'un projet du même nom.
+/// Les créations interrompues ne sont pas des conflits : elles sont remplacées sans confirmation.
+///
+/// # Arguments
+///
+/// * `name` - Le nom du projet à créer.
+///
+/// # Returns
+///
+/// * `Option<ProjectConflict>` - Le projet existant, ou `None` si le nom est libre.
+pub fn project_conflict(name: &str) -> Option<ProjectConflict> {
+    let folder = project_dir(name);
+    if !folder.is_dir() || !is_project_complete(&folder) {
+        return None;
+    }
+
+    let size_bytes = relative_files(&folder)
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|path| fs::metadata(folder.join(path)).ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0);
+    Some(ProjectConflict {
+        name: name.to_string(),
+        created_at: load_project_metadata(name).created_at,
+        size_bytes,
+        bbox: get_project_bounding_box(name).ok(),
+        suggested_name: free_project_name(name),
+    })
+}
+
+/// Premier nom libre de la forme `<nom>-2`, `<nom>-3`..., raccourci si nécessaire
+/// pour rester dans la longueur maximale d'un nom de projet.
+pub fn free_project_name(name: &str) -> String {
+    (2..)
+        .map(|index| {
+            let suffix = format!("-{}", index);
+            let base: String = name
+                .chars()
+                .take(MAX_PROJECT_NAME_LEN - suffix.len())
+                .collect();
+            format!("{}{}", base.trim_end_matches(['.', ' ']), suffix)
+        })
+        .find(|candidate| !project_dir(candidate).exists())
+        .unwrap_or_default()
+}
+
+/// Dossier des projets écartés le temps de leur recréation, ignoré par la liste des projets.
+pub const TRASH_DIR: &str = ".trash";
+
+/// Écarte un projet dans `.trash` avant sa recréation, pour pouvoir le restaurer
+/// si la nouvelle création échoue.
+///
+/// # Arguments
+///
+/// * `name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le dossier écarté.
+pub fn move_project_to_trash(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let trash = trash_dir();
+    fs::create_dir_all(&trash)?;
+    let trashed = trash.join(format!(
+        "{}-{}",
+        name,
+        chrono::Local::now().format("%Y%m%d%H%M%S%3f")
+    ));
+    fs::rename(project_dir(name), &trashed)?;
+    Ok(trashed)
+}
+
+/// Remet en place un projet écarté par `move_project_to_trash`, en supprimant
+/// la création inachevée qui occupe son dossier.
+///
+/// # Arguments
+///
+/// * `name` - Le nom du projet.
+/// * `trashed` - Le dossier écarté.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si le projet n'a pas pu être restauré.
+pub fn restore_project_from_trash(name: &str, trashed: &Path) -> Result<(), Box<dyn Error>> {
+    let folder = project_dir(name);
+    if folder.exists() {
+        fs::remove_dir_all(&folder)?;
+    }
+    fs::rename(trashed, &folder)?;
+    Ok(())
+}
+
+/// Remplace l'orthophotographie d'un projet existant sans reconstruire le raster de végétation.
+/// La nouvelle image est téléchargée dans un dossier de travail, copiée à côté de l'ancienne
+/// puis substituée par renommage : en cas d'échec, l'orthophotographie existante est conservée.
+/// L'aperçu est ensuite régénéré et les tuiles ORTHO sont marquées à refaire.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `run_config` - Les paramètres du remplacement, dont le dossier temporaire.
+/// * `download` - Téléchargement de l'image (`download_satellite_jpeg`), qui écrit le JPEG
+///   et son world file au chemin donné.
+/// * `on_progress` - Appelée avec la clé de chaque étape (voir `i18n`), son rang et le nombre d'étapes.
+///
+/// # Returns
+///
+/// * `Result<PathBuf, Box<dyn Error>>` - Le chemin de la nouvelle orthophotographie.
+pub fn refresh_project_ortho<F>(
+    project_name: &str,
+    run_config: &RunConfig,
+    download: F,
+    on_progress: &dyn Fn(&str, usize, usize),
+) -> Result<PathBuf, Box<dyn Error>>
+where
+    F: FnOnce(&WorkDir, &str, &BoundingBox) -> Result<(), Box<dyn Error>>,
+{
+    let project_path = project_dir(project_name);
+    if !project_path.is_dir() {
+        return Err(format!("Le projet '{}' n'existe pas", project_name).into());
+    }
+    let project_bb = get_project_bounding_box(project_name)?;
+    let ortho_path = project_ortho_jpeg(project_name);
+
+    on_progress("step.download_ortho", 1, 3);
+    let work_dir = WorkDir::new(run_config, &format!("refresh_ortho_{}", project_name))?;
+    let downloaded = work_dir.path_for("ortho.jpeg");
+    download(&work_dir, &downloaded.to_string_lossy(), &project_bb)?;
+
+    on_progress("step.replace_ortho", 2, 3);
+    // Copie sur le même système de fichiers que le projet, pour un remplacement atomique.
+    let staged = project_path.join(format!(".{}_ORTHO.jpeg.part", project_name));
+    let staged_world_file = project_path.join(format!(".{}_ORTHO.wld.part", project_name));
+    let copied = fs::copy(&downloaded, &staged).and_then(|_| {
+        let downloaded_world_file = downloaded.with_extension("wld");
+        if downloaded_world_file.exists() {
+            fs::copy(&downloaded_world_file, &staged_world_file)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&staged);
+        let _ = fs::remove_file(&staged_world_file);
+        return Err(e.into());
+    }
+    fs::rename(&staged, &ortho_path)?;
+    if staged_world_file.exists() {
+        fs::rename(&staged_world_file, ortho_path.with_extension("wld"))?;
+    }
+
+    let mut metadata = load_project_metadata(project_name);
+    if metadata.ortho_skipped {
+        metadata.ortho_skipped = false;
+        save_project_metadata(project_name, &metadata)?;
+    }
+
+    on_progress("step.update_preview", 3, 3);
+    generate_project_preview(project_name)?;
+    invalidate_ortho_slices(project_name)?;
+
+    Ok(ortho_path)
+}
+
+pub fn get_operating_system() -> &'static str {
+    std::env::consts::OS
+}
+
+impl ExportTarget {
+    pub const ALL: [ExportTarget; 2] = [ExportTarget::Zip, ExportTarget::Simulator];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportTarget::Zip => "Archive zip",
+            ExportTarget::Simulator => "Simulateur",
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Indique si un fichier du dossier d'un projet fait partie de l'export zip.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_name` - Le nom du projet.
+    /// * `relative_path` - Le chemin du fichier, relatif au dossier du projet.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` si le fichier doit être exporté.
+    pub fn includes(&self, project_name: &str, relative_path: &Path) -> bool {
+        if !self.include_resources && relative_path.starts_with("resources") {
+            return false;
+        }
+        if !self.include_qgis_project
+            && relative_path == Path::new(&format!("{}.{}", project_name, QGIS_PROJECT_EXTENSION))
+        {
+            return false;
+        }
+        if !self.include_full_images && relative_path.parent() == Some(Path::new("")) {
+            // Le GeoTIFF et ses fichiers annexes (`.aux.xml`, `.ovr`).
+            let project_tiff = format!("{}.tiff", project_name);
+            if relative_path
+                .to_string_lossy()
+                .starts_with(project_tiff.as_str())
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Rapport d'un export, transmis à l'interface.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportReport {
+    /// Le chemin de l'archive, ou du dossier créé pour le simulateur.
+    pub zip_path: PathBuf,
+    /// Les fichiers exportés, relatifs à l'archive ou au dossier.
+    pub files: Vec<String>,
+    /// La taille totale des fichiers exportés, avant compression.
+    pub total_bytes: u64,
+    /// La durée de l'export en secondes, découpage compris.
+    pub duration: f64,
+}
+
+/// Description d'un format d'export proposé à l'interface.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportTargetInfo {
+    pub target: ExportTarget,
+    pub label: String,
+}
+
+/// Manifeste décrivant la grille de tuiles exportée pour le simulateur.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatorManifest {
+    pub project_name: String,
+    pub crs: String,
+    pub resolution: f64,
+    pub origin_x: f64,
+    pub origin_y: f64,
+    pub tile_size_px: u32,
+    pub tile_size_m: f64,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// Calcule le manifeste du simulateur à partir de l'emprise du projet.
+/// L'origine de la grille est le coin inférieur gauche de l'emprise, dans le système
+/// de coordonnées du projet enregistré dans ses métadonnées.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `project_bb` - L'emprise du projet.
+/// * `slice_factor` - La taille des tuiles en pixels.
+/// * `resolution` - La résolution en mètres par pixel.
+///
+/// # Returns
+///
+/// * `SimulatorManifest` - Le manifeste de la grille.
+pub fn simulator_manifest(
+    project_name: &str,
+    project_bb: &BoundingBox,
+    slice_factor: u32,
+    resolution: f64,
+) -> SimulatorManifest {
+    let tile_size_m = slice_factor as f64 * resolution;
+    SimulatorManifest {
+        project_name: project_name.to_string(),
+        crs: format!(
+            "EPSG:{}",
+            load_project_metadata(project_name).project_epsg()
+        ),
+        resolution,
+        origin_x: project_bb.xmin,
+        origin_y: project_bb.ymin,
+        tile_size_px: slice_factor,
+        tile_size_m,
+        columns: (project_bb.width() / tile_size_m).floor() as u32,
+        rows: (project_bb.height() / tile_size_m).floor() as u32,
+    }
+}
+
+/// Copie les découpes d'un projet dans la disposition attendue par le simulateur :
+/// `tiles/<x>_<y>.png` pour l'orthophoto, `tiles/<x>_<y>_fuel.png` pour la végétation,
+/// et écrit `manifest.json` à la racine du dossier d'export.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet (les découpes doivent déjà exister).
+/// * `export_dir` - Le dossier d'export à créer.
+/// * `run_config` - Les paramètres de l'export : taille des tuiles et résolution du manifeste.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Un résultat indiquant si l'export a réussi ou échoué.
+pub fn export_simulator_layout(
+    project_name: &str,
+    export_dir: &Path,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn Error>> {
+    let slice_dir = project_dir(project_name).join("slices");
+    let tiles_dir = export_dir.join("tiles");
+    fs::create_dir_all(&tiles_dir)?;
+
+    let project_bb = get_project_bounding_box(project_name)?;
+    let slice_factor_value = run_config.slice_factor;
+    let alpha = read_project_alpha(project_name)?;
+    let origins = tile_origins(
+        alpha.width(),
+        alpha.height(),
+        slice_factor_value,
+        &project_bb,
+    )?;
+
+    for (path, x, y, tile_name) in simulator_tiles(project_name, &slice_dir)? {
+        let tile = image::open(&path)?;
+        match origins.get(&(x, y)) {
+            Some(&(img_x, img_y)) => {
+                with_tile_alpha(&tile, &alpha, img_x, img_y).save(tiles_dir.join(tile_name))?
+            }
+            None => tile.save(tiles_dir.join(tile_name))?,
+        }
+    }
+
+    let manifest = simulator_manifest(
+        project_name,
+        &project_bb,
+        slice_factor_value,
+        run_config.resolution,
+    );
+    fs::write(
+        export_dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    Ok(())
+}
+
+/// Liste les découpes à exporter pour le simulateur, avec leurs coordonnées en km et le nom
+/// de la tuile exportée (`<x>_<y>.png` ou `<x>_<y>_fuel.png`).
+/// Les découpes sont lues dans `slices/index.json` ; sans index, les noms des découpes
+/// sont analysés, y compris ceux des découpes antérieures au modèle de nom
+/// (`<x>_<y>_<facteur>.jpg`, `<x>_<y>_veget_<facteur>.jpg`).
+fn simulator_tiles(
+    project_name: &str,
+    slice_dir: &Path,
+) -> Result<Vec<(PathBuf, u32, u32, String)>, Box<dyn Error>> {
+    if let Some(index) = read_slice_index(project_name) {
+        return Ok(index
+            .tiles
+            .iter()
+            .flat_map(|tile| {
+                [
+                    (
+                        slice_dir.join(&tile.ortho),
+                        tile.x,
+                        tile.y,
+                        format!("{}_{}.png", tile.x, tile.y),
+                    ),
+                    (
+                        slice_dir.join(&tile.veget),
+                        tile.x,
+                        tile.y,
+                        format!("{}_{}_fuel.png", tile.x, tile.y),
+                    ),
+                ]
+            })
+            .collect());
+    }
+
+    let mut tiles = Vec::new();
+    for entry in fs::read_dir(slice_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "jpg") {
+            continue;
+        }
+        let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let parts: Vec<&str> = stem.split('_').collect();
+        let (x, y, fuel) = match parts.as_slice() {
+            [x, y, "veget", _] => (*x, *y, true),
+            [x, y, "ortho", _] | [x, y, _] => (*x, *y, false),
+            _ => continue,
+        };
+        let (Ok(x), Ok(y)) = (x.parse::<u32>(), y.parse::<u32>()) else {
+            continue;
+        };
+        let tile_name = if fuel {
+            format!("{}_{}_fuel.png", x, y)
+        } else {
+            format!("{}_{}.png", x, y)
+        };
+        tiles.push((path, x, y, tile_name));
+    }
+    Ok(tiles)
+}
+
+/// Lit la bande alpha du projet, qui marque les pixels sans données.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+///
+/// # Returns
+///
+/// * `Result<GrayImage, Box<dyn Error>>` - La bande alpha du projet.
+fn read_project_alpha(project_name: &str) -> Result<GrayImage, Box<dyn Error>> {
+    let project = ProjectRaster::open_read_only(&project_tiff(project_name).to_string_lossy())?;
+    let (width, height) = project.size();
+    let data = project.read_alpha_stripe(RasterWindow::full((width, height)))?;
+    GrayImage::from_raw(width as u32, height as u32, data)
+        .ok_or_else(|| "Bande alpha du projet invalide".into())
+}
+
+/// Ajoute à une tuile la transparence de la zone correspondante du projet.
+/// Les tuiles entièrement opaques sont conservées en RGB.
+fn with_tile_alpha(tile: &DynamicImage, alpha: &GrayImage, img_x: u32, img_y: u32) -> DynamicImage {
+    let (tile_width, tile_height) = (tile.width(), tile.height());
+    // La partie de la tuile qui dépasse le bord du projet est transparente.
+    let mut tile_alpha = GrayImage::new(tile_width, tile_height);
+    imageops::replace(
+        &mut tile_alpha,
+        &imageops::crop_imm(alpha, img_x, img_y, tile_width, tile_height).to_image(),
+        0,
+        0,
+    );
+    if tile_alpha.pixels().all(|pixel| pixel.0[0] == 255) {
+        return tile.clone();
+    }
+
+    let mut rgba = tile.to_rgba8();
+    for (pixel, alpha) in rgba.pixels_mut().zip(tile_alpha.pixels()) {
+        pixel.0[3] = alpha.0[0];
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Erreur détectée lors de la vérification du dossier d'export.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputLocationError {
+    /// Le dossier n'existe pas et ne peut pas être créé (disque débranché, chemin invalide...).
+    NotCreatable { path: PathBuf, details: String },
+    /// Le dossier existe mais aucun fichier ne peut y être écrit.
+    NotWritable { path: PathBuf, details: String },
+    /// L'espace libre est inférieur à la taille estimée de l'export.
+    InsufficientSpace {
+        path: PathBuf,
+        required_bytes: u64,
+        available_bytes: u64,
+    },
+}
+
+impl fmt::Display for OutputLocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputLocationError::NotCreatable { path, details } => write!(
+                f,
+                "Dossier d'export inaccessible: {} ({})",
+                path.display(),
+                details
+            ),
+            OutputLocationError::NotWritable { path, details } => write!(
+                f,
+                "Dossier d'export en lecture seule: {} ({})",
+                path.display(),
+                details
+            ),
+            OutputLocationError::InsufficientSpace {
+                path,
+                required_bytes,
+                available_bytes,
+            } => write!(
+                f,
+                "Dossier d'export sans espace suffisant: {} ({} Mo nécessaires, {} Mo disponibles)",
+                path.display(),
+                required_bytes / (1024 * 1024),
+                available_bytes / (1024 * 1024)
+            ),
+        }
+    }
+}
+
+impl Error for OutputLocationError {}
+
+/// Vérifie qu'un dossier d'export est utilisable : il est créé s'il n'existe pas,
+/// un fichier témoin y est écrit puis supprimé, et l'espace libre doit couvrir `required_bytes`.
+///
+/// # Arguments
+///
+/// * `path` - Le dossier d'export.
+/// * `required_bytes` - La taille estimée de l'export.
+///
+/// # Returns
+///
+/// * `Result<(), OutputLocationError>` - Une erreur nommant le dossier s'il est inutilisable.
+pub fn validate_output_location(
+    path: &Path,
+    required_bytes: u64,
+) -> Result<(), OutputLocationError> {
+    fs::create_dir_all(path).map_err(|e| OutputLocationError::NotCreatable {
+        path: path.to_path_buf(),
+        details: e.to_string(),
+    })?;
+
+    let probe = path.join(".firefront_probe");
+    fs::write(&probe, b"probe")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| OutputLocationError::NotWritable {
+            path: path.to_path_buf(),
+            details: e.to_string(),
+        })?;
+
+    if let Ok(available_bytes) = fs2::available_space(path) {
+        if available_bytes < required_bytes {
+            return Err(OutputLocationError::InsufficientSpace {
+                path: path.to_path_buf(),
+                required_bytes,
+                available_bytes,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Octets estimés par pixel d'une découpe JPEG (végétation ou orthophoto).
+const JPEG_SLICE_BYTES_PER_PIXEL: u64 = 1;
+
+/// Octets estimés par pixel d'une tuile PNG du simulateur.
+const PNG_TILE_BYTES_PER_PIXEL: u64 = 3;
+
+fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                directory_size(&path)
+            } else {
+                fs::metadata(&path).map(|m| m.len()).unwrap_or_default()
+            }
+        })
+        .sum()
+}
+
+/// Estime la taille d'un export à partir du nombre de découpes du projet :
+/// pour une archive zip, les fichiers exportés du dossier du projet (hors découpes existantes)
+/// et les découpes JPEG ; pour le simulateur, les tuiles PNG.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet.
+/// * `target` - Le format d'export.
+/// * `options` - Le contenu optionnel de l'archive zip.
+/// * `run_config` - Les paramètres de l'export : taille des tuiles en pixels et résolution.
+///
+/// # Returns
+///
+/// * `Result<u64, Box<dyn Error>>` - La taille estimée en octets.
+pub fn estimate_export_size(
+    project_name: &str,
+    target: ExportTarget,
+    options: &ExportOptions,
+    run_config: &RunConfig,
+) -> Result<u64, Box<dyn Error>> {
+    let project_bb = get_project_bounding_box(project_name)?;
+    let slice_factor = run_config.slice_factor;
+    let tile_size_m = slice_factor.max(1) as f64 * run_config.resolution;
+    let slice_count = ((project_bb.width() / tile_size_m).ceil()
+        * (project_bb.height() / tile_size_m).ceil()) as u64;
+    // Chaque tuile existe en deux versions : végétation et orthophoto.
+    let tile_pixels = 2 * slice_factor as u64 * slice_factor as u64;
+
+    Ok(match target {
+        ExportTarget::Zip => {
+            let project_path = project_dir(project_name);
+            let files_size: u64 = relative_files(&project_path)?
+                .into_iter()
+                .filter(|path| !path.starts_with("slices") && options.includes(project_name, path))
+                .filter_map(|path| fs::metadata(project_path.join(path)).ok())
+                .map(|metadata| metadata.len())
+                .sum();
+            files_size + slice_count * tile_pixels * JPEG_SLICE_BYTES_PER_PIXEL
+        }
+        ExportTarget::Simulator => slice_count * tile_pixels * PNG_TILE_BYTES_PER_PIXEL,
+    })
+}
+
+/// Exporte un projet (images découpées, fichiers de configuration, etc.) dans un format compressé,
+/// ou dans un dossier au format du simulateur, dans le dossier d'export des paramètres.
+/// Les ressources et le GeoTIFF complet ne sont pas inclus dans l'archive zip.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet à exporter.
+/// * `target` - Le format d'export.
+///
+/// # Returns
+///
+/// * `Result<ExportReport, Box<dyn Error>>` - Le rapport de l'export.
+pub fn export_project(
+    project_name: &str,
+    target: ExportTarget,
+) -> Result<ExportReport, Box<dyn Error>> {
+    export_project_to(
+        project_name,
+        target,
+        &ExportOptions::default(),
+        &output_location(),
+        None,
+    )
+}
+
+/// Exporte un projet dans le dossier donné.
+/// Le dossier est vérifié (voir `validate_output_location`) avant le découpage :
+/// un dossier inutilisable est signalé par une `OutputLocationError` sans attendre la fin du découpage.
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet à exporter.
+/// * `target` - Le format d'export.
+/// * `options` - Le contenu optionnel de l'archive zip, ignoré pour le simulateur.
+/// * `output_dir` - Le dossier où écrire l'export.
+/// * `on_progress` - Progression de la compression (fichiers ajoutés, total), voir `compress_folder`.
+///
+/// # Returns
+///
+/// * `Result<ExportReport, Box<dyn Error>>` - Le rapport de l'export.
+pub fn export_project_to(
+    project_name: &str,
+    target: ExportTarget,
+    options: &ExportOptions,
+    output_dir: &Path,
+    on_progress: Option<&dyn Fn(usize, usize)>,
+) -> Result<ExportReport, Box<dyn Error>> {
+    export_project_with_hook(
+        project_name,
+        target,
+        options,
+        output_dir,
+        on_progress,
+        &|| {},
+    )
+}
+
+/// Exporte un projet dans le dossier donné, comme `export_project_to`, en appelant
+/// `after_slicing` entre le découpage et le relevé des fichiers de l'archive.
+/// L'archive zip n'est écrite que si toutes les découpes attendues sont présentes
+/// (voir `export_inventory`), puis relue pour vérifier qu'elle est complète (voir `verify_zip`).
+///
+/// # Arguments
+///
+/// * `project_name` - Le nom du projet à exporter.
+/// * `target` - Le format d'export.
+/// * `options` - Le contenu optionnel de l'archive zip, ignoré pour le simulateur.
+/// * `output_dir` - Le dossier où écrire l'export.
+/// * `on_progress` - Progression de la compression (fichiers ajoutés, total), voir `compress_files`.
+/// * `after_slicing` - Appelé une fois le découpage terminé.
+///
+/// # Returns
+///
+/// * `Result<ExportReport, Box<dyn Error>>` - Le rapport de l'export, ou une erreur listant
+///   les découpes manquantes.
+pub fn export_project_with_hook(
+    project_name: &str,
+    target: ExportTarget,
+    options: &ExportOptions,
+    output_dir: &Path,
+    on_progress: Option<&dyn Fn(usize, usize)>,
+    after_slicing: &dyn Fn(),
+) -> Result<ExportReport, Box<dyn Error>> {
+    let start = std::time::Instant::now();
+    let project_path = project_dir(project_name);
+    // Un changement des paramètres pendant l'export ne s'applique qu'aux exports suivants.
+    let run_config = RunConfig::snapshot();
+
+    let required_bytes = estimate_export_size(project_name, target, options, &run_config)?;
+    validate_output_location(output_dir, required_bytes)?;
+
+    let date = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    if let Err(e) = slice_images(project_name, &run_config) {
+        return Err(format!("Echec découpage: {}: {}", project_name, e).into());
+    }
+    // Les découpes font partie du dossier du projet.
+    if let Err(e) = update_project_size(project_name) {
+        tracing::warn!(project = project_name, error = %e, "Taille du projet non enregistrée");
+    }
+    after_slicing();
+
+    let (zip_path, files, total_bytes) = match target {
+        ExportTarget::Zip => {
+            if options.include_qgis_project {
+                generate_qgis_project(project_name)?;
+            }
+            let inventory = export_inventory(project_name, options, &run_config)?;
+            let zip_path = output_dir.join(format!("export_{}_{}.zip", project_name, date));
+            compress_files(&project_path, &inventory.files, &zip_path, on_progress)?;
+            verify_zip(&zip_path, &inventory)?;
+            (zip_path, inventory.files, inventory.total_bytes)
+        }
+        ExportTarget::Simulator => {
+            let export_dir = output_dir.join(format!("simulator_{}_{}", project_name, date));
+            export_simulator_layout(project_name, &export_dir, &run_config)?;
+            let files = relative_files(&export_dir)?;
+            let total_bytes = directory_size(&export_dir);
+            (export_dir, files, total_bytes)
+        }
+    };
+
+    Ok(ExportReport {
+        zip_path,
+        files: files.iter().map(|path| zip_entry_name(path)).collect(),
+        total_bytes,
+        duration: start.elapsed().as_secs_f64(),
+    })
+}
+
+/// Construit la commande ouvrant le gestionnaire de fichiers du système d'exploitation.
+///
+/// # Arguments
+///
+/// * `os` - Le système d'exploitation (`windows`, `macos`, `linux`...).
+/// * `path` - Le dossier à ouvrir ou le fichier à sélectionner.
+/// * `reveal` - Si vrai, sélectionne le fichier dans son dossier au lieu de l'ouvrir.
+///
+/// # Returns
+///
+/// * `(String, Vec<String>)` - Le programme à lancer et ses arguments.
+pub fn file_manager_command(os: &str, path: &Path, reveal: bool) -> (String, Vec<String>) {
+    let path_str = path.to_string_lossy().to_string();
+    match (os, reveal) {
+        ("windows", true) => (
+            "explorer".to_string(),
+            vec![format!("/select,{}", path_str)],
+        ),
+        ("windows", false) => ("explorer".to_string(), vec![path_str]),
+        ("macos", true) => ("open".to_string(), vec!["-R".to_string(), path_str]),
+        ("macos", false) => ("open".to_string(), vec![path_str]),
+        // xdg-open ne sait pas sélectionner un fichier : on ouvre son dossier parent.
+        (_, true) => (
+            "xdg-open".to_string(),
+            vec![path.parent().unwrap_or(path).to_string_lossy().to_string()],
+        ),
+        (_, false) => ("xdg-open".to_string(), vec![path_str]),
+    }
+}
+
+/// Ouvre le gestionnaire de fichiers du système sur un dossier ou un fichier.
+///
+/// # Arguments
+///
+/// * `path` - Le dossier à ouvrir ou le fichier à sélectionner.
+/// * `reveal` - Si vrai, sélectionne le fichier dans son dossier au lieu de l'ouvrir.
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - Une erreur si le chemin n'existe plus ou si le lancement a échoué.
+pub fn open_in_file_manager(path: &Path, reveal: bool) -> Result<(), Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("Le chemin {} n'existe plus", path.display()).into());
+    }
+
+    let (program, args) = file_manager_command(get_operating_system(), path, reveal);
+    Command::new(program).args(args).spawn()?;
+
+    Ok(())
+}
+
+/// Nombre de lignes lues à la fois lors de l'export JPEG d'un projet.
+pub const JPEG_EXPORT_STRIPE_ROWS: usize = 256;
+
+/// Compose une valeur de canal sur la couleur de fond selon son alpha.
+///
+/// # Arguments
+///
+/// * `value` - valeur du canal
+/// * `alpha` - opacité du pixel (0 : transparent, 255 : opaque)
+/// * `background` - valeur du canal de la couleur de fond
+///
+/// # Returns
+///
+/// * `u8` - la valeur composée, arrondie
+pub fn composite_over_background(value: u8, alpha: u8, background: u8) -> u8 {
+    let value = value as u32 * alpha as u32 + background as u32 * (255 - alpha as u32);
+    ((value + 127) / 255) as u8
+}
+
+/// Exporte un projet en format JPEG
+/// Cette fonction est utilisée pour créer une image JPEG à partir d'un projet GDAL.
+/// Le projet est lu par bandes de `JPEG_EXPORT_STRIPE_ROWS` lignes : les bandes RGB,
+/// composées sur la couleur `nodata_color` des paramètres selon l'alpha (le JPEG
+/// n'ayant pas de transparence), sont écrites dans un GeoTIFF temporaire que
+/// `gdal_translate` encode ensuite ligne par ligne. La mémoire utilisée reste ainsi
+/// de l'ordre d'une bande, quelle que soit la taille du projet.
+/// Le projet est ouvert avec `ProjectRaster` : un raster qui n'est pas un projet est refusé,
+/// et les bandes de couleur et d'alpha sont choisies d'après leur interprétation. Le GeoTIFF
+/// temporaire est déclaré RGB : la quatrième bande n'est jamais encodée, ni prise pour une
+/// composante CMJN.
+/// Un world file (`.wld`) porte le géoréférencement du projet, comme pour l'orthophotographie.
+///
+/// # Arguments
+///
+/// * `project_file_path` - chemin du fichier projet à exporter
+/// * `output_jpg_path` - chemin du fichier JPEG de sortie
+/// * `run_config` - paramètres de l'export : couleur sans données, qualité et dossier temporaire
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn std::error::Error>>` - un résultat indiquant si l'exportation a réussi ou échoué
+pub fn export_to_jpg<P: AsRef<Path>, Q: AsRef<Path>>(
+    project_file_path: P,
+    output_jpg_path: Q,
+    run_config: &RunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let project = ProjectRaster::open_read_only(&project_file_path.as_ref().to_string_lossy())?;
+    let (width, height) = project.size();
+    let geo_transform = project.dataset().geo_transform()?;
+    let background = run_config.nodata_color;
+
+    // Le GeoTIFF intermédiaire n'est pas géoréférencé : le JPEG ne reçoit pas de .aux.xml.
+    let work_dir = WorkDir::new(run_config, "jpeg_export")?;
+    let rgb_path = work_dir.path_for("rgb.tif");
+    let driver = DriverManager::get_driver_by_name("GTiff")?;
+    let rgb = driver.create_with_band_type::<u8, _>(&rgb_path, width, height, 3)?;
+    for (band_idx, interpretation) in [
+        ColorInterpretation::RedBand,
+        ColorInterpretation::GreenBand,
+        ColorInterpretation::BlueBand,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        rgb.rasterband(band_idx + 1)?
+            .set_color_interpretation(interpretation)?;
+    }
+
+    for y in (0..height).step_by(JPEG_EXPORT_STRIPE_ROWS) {
+        let rows = JPEG_EXPORT_STRIPE_ROWS.min(height - y);
+        let window = RasterWindow::rows(width, y, rows);
+        let alpha = project.read_alpha_stripe(window)?;
+        let stripe = project.read_rgb_stripe(window)?;
+
+        for (band_idx, (mut data, &background_value)) in
+            stripe.into_iter().zip(background.iter()).enumerate()
+        {
+            for (value, &opacity) in data.iter_mut().zip(alpha.iter()) {
+                *value = composite_over_background(*value, opacity, background_value);
+            }
+            rgb.rasterband(band_idx + 1)?.write(
+                (0, y as isize),
+                (width, rows),
+                &mut gdal::raster::Buffer::new((width, rows), data),
+            )?;
+        }
+    }
+    rgb.close()?;
+    project.close()?;
+
+    let status = Command::new("gdal_translate")
+        .args(["-q", "-of", "JPEG", "-co"])
+        .arg(format!("QUALITY={}", run_config.veget_jpeg_quality()))
+        .arg(&rgb_path)
+        .arg(output_jpg_path.as_ref())
+        .status()?;
+
+    if !status.success() {
+        return Err("Failed to export to JPEG using gdal_translate".into());
+    }
+
+    write_world_file(&output_jpg_path.as_ref().to_string_lossy(), &geo_transform)?;
+
+    Ok(())
+}
+
+pub fn get_project_bounding_box(project_name: &str) -> Result<BoundingBox, String> {
+    let project_file = project_tiff(project_name);
+    let dataset = Dataset::open(&project_file)
+        .map_err(|e| format!("Failed to open {}: {}", project_file.display(), e))?;
+
+    let geo_transform = dataset
+        .geo_transform()
+        .map_err(|e| format!("Failed to read geotransform: {}", e))?;
+    let (width, height) = dataset.raster_size();
+
+    Ok(BoundingBox {
+        xmin: geo_transform[0],
+        ymin: geo_transform[3] + geo_transform[5] * height as f64,
+        xmax: geo_transform[0] + geo_transform[1] * width as f64,
+        ymax: geo_transform[3],
+    })
+}
+
+/// Enregistre une image RGB au format JPEG avec la qualité demandée.
+///
+/// # Arguments
+///
+/// * `image` - image à enregistrer
+/// * `output_path` - chemin du fichier JPEG de sortie
+/// * `quality` - qualité JPEG (1-100)
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - un résultat indiquant si l'enregistrement a réussi ou échoué
+pub fn save_rgb_jpeg(
+    image: &RgbImage,
+    output_path: &str,
+    quality: u8,
+) -> Result<(), Box<dyn Error>> {
+    let file = fs::File::create(output_path)?;
+    let mut writer = BufWriter::new(file);
+    let encoder = JpegEncoder::new_with_quality(&mut writer, quality);
+    image.write_with_encoder(encoder)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Écrit un fichier de géoréférencement (world file) à côté d'une image JPEG,
+/// afin que GDAL retrouve l'emprise et la résolution de l'image.
+///
+/// # Arguments
+///
+/// * `jpeg_path` - chemin de l'image JPEG
+/// * `geo_transform` - géotransformation GDAL de l'image
+///
+/// # Returns
+///
+/// * `Result<(), Box<dyn Error>>` - un résultat indiquant si l'écriture a réussi ou échoué
+pub fn write_world_file(jpeg_path: &str, geo_transform: &[f64; 6]) -> Result<(), Box<dyn Error>> {
+    let world_file = Path::new(jpeg_path).with_extension("wld");
+    let content = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n",
+        geo_transform[1],
+        geo_transform[4],
+        geo_transform[2],
+        geo_transform[5],
+        geo_transform[0] + geo_transform[1] / 2.0,
+        geo_transform[3] + geo_transform[5] / 2.0,
+    );
+    fs::write(world_file, content)?;
+    Ok(())
+}
+
+pub fn get_geojson_bounding_box(
+    file_path: &str,
+) -> Result<BoundingBox, Box<dyn std::error::Error>> {
+    let output = Command::new("ogrinfo")
+        .args(["-so", "-al", file_path])
+        .output()?;
+    let info_str = String::from_utf8(output.stdout)?;
+
+    let extent_pattern = r"Extent:\s*\(([\d.-]+),\s*([\d.-]+)\)\s*-\s*\(([\d.-]+),\s*([\d.-]+)\)";
+    let caps = regex::Regex::new(extent_pattern)?
+        .captures(&info_str)
+        .ok_or("Could not find extent in ogrinfo output")?;
+
+    Ok(BoundingBox {
+        xmin: caps[1].parse()?,
         ymin: caps[2].parse()?,
         xmax: caps[3].parse()?,
         ymax: caps[4].parse()?,
     })
 }
 
-/// Nettoie le dossier tmp en conservant uniquement les fichiers GPKG
+/// Compteur garantissant l'unicité des dossiers de travail créés dans un même processus.
+static WORK_DIR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Dossier de travail temporaire propre à une opération (création, export...).
+/// Chaque opération écrit ses fichiers intermédiaires dans son propre sous-dossier
+/// du dossier temporaire, ce qui permet d'exécuter plusieurs commandes en même temps.
+/// Le dossier est supprimé lorsque la valeur est détruite.
+#[derive(Debug)]
+pub struct WorkDir {
+    path: PathBuf,
+}
+
+impl WorkDir {
+    /// Crée un dossier de travail unique sous le dossier temporaire de l'opération.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_config` - Les paramètres de l'opération, dont le dossier temporaire.
+    /// * `prefix` - Préfixe du nom du dossier, pour identifier l'opération.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<WorkDir, Box<dyn Error>>` - Le dossier de travail créé.
+    pub fn new(run_config: &RunConfig, prefix: &str) -> Result<Self, Box<dyn Error>> {
+        Self::new_in(&run_config.temp_dir, prefix)
+    }
+
+    /// Crée un dossier de travail unique dans le dossier `parent`.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - Dossier dans lequel créer le dossier de travail.
+    /// * `prefix` - Préfixe du nom du dossier, pour identifier l'opération.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<WorkDir, Box<dyn Error>>` - Le dossier de travail créé.
+    pub fn new_in(parent: &Path, prefix: &str) -> Result<Self, Box<dyn Error>> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_millis();
+        let counter = WORK_DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = parent.join(format!(
+            "{}_{}_{}_{}",
+            prefix,
+            timestamp,
+            std::process::id(),
+            counter
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(WorkDir { path })
+    }
+
+    /// Chemin du dossier de travail.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Chemin d'un fichier du dossier de travail.
+    pub fn path_for<P: AsRef<Path>>(&self, name: P) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Chemin d'un fichier du dossier de travail, sous forme de chaîne
+    /// pour les fonctions GDAL et les commandes externes.
+    pub fn path_str<P: AsRef<Path>>(&self, name: P) -> String {
+        self.path_for(name).to_string_lossy().to_string()
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Nettoie un dossier de travail en conservant uniquement les fichiers GPKG
 /// Cette fonction est utilisée pour nettoyer les fichiers entre les traitements
 /// de différentes régions dans le processus de création de projet
 ///
+/// # Arguments
+///
+/// * `work_dir` - Le dossier de travail de l'opération en cours.
+///
 /// # Returns
 ///
 /// * `Result<(), Box<dyn std::error::Error>>` - Un résultat indiquant le succès ou l'échec
-pub fn clean_tmp_except_gpkg() -> Result<(), Box<dyn std::error::Error>> {
-    let tmp_dir = temp_dir();
+pub fn clean_tmp_except_gpkg(work_dir: &WorkDir) -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = work_dir.path();
 
     if !tmp_dir.exists() {
         return Ok(());
@@ -392,6 +3565,310 @@ pub fn clean_tmp_except_gpkg() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Verrou tenu par l'instance de l'application pendant toute son exécution,
+/// dans le dossier temporaire.
+pub const INSTANCE_LOCK_FILE: &str = ".instance.lock";
+
+/// Âge au-delà duquel un téléchargement interrompu (`.part`) du cache est supprimé.
+const STALE_PART_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Verrou exclusif d'une instance de l'application, libéré à la destruction de la valeur
+/// ou à l'arrêt du processus, y compris en cas de plantage.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: fs::File,
+}
+
+/// Prend le verrou d'instance du dossier temporaire.
+///
+/// # Arguments
+///
+/// * `temp_dir` - Le dossier temporaire.
+///
+/// # Returns
+///
+/// * `std::io::Result<Option<InstanceLock>>` - Le verrou, ou `None` s'il est tenu par une autre instance.
+pub fn try_lock_instance(temp_dir: &Path) -> std::io::Result<Option<InstanceLock>> {
+    fs::create_dir_all(temp_dir)?;
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(temp_dir.join(INSTANCE_LOCK_FILE))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(Some(InstanceLock { _file: file })),
+        Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Bilan d'un nettoyage (démarrage, `clear_cache`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CleanupReport {
+    /// Nombre de fichiers et dossiers supprimés.
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+impl CleanupReport {
+    fn remove(&mut self, path: &Path) {
+        let size = if path.is_dir() {
+            directory_size(path)
+        } else {
+            fs::metadata(path).map(|m| m.len()).unwrap_or_default()
+        };
+        let result = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        match result {
+            Ok(()) => {
+                self.removed += 1;
+                self.freed_bytes += size;
+            }
+            Err(e) => tracing::warn!(
+                path = %path.display(),
+                error = %e,
+                "Impossible de supprimer un fichier temporaire"
+            ),
+        }
+    }
+}
+
+/// Âge d'un fichier ou dossier d'après sa date de modification.
+fn entry_age(path: &Path) -> Duration {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .unwrap_or_default()
+}
+
+/// Supprime les données laissées par les exécutions précédentes (après un plantage notamment) :
+/// le contenu du dossier temporaire plus ancien que `max_tmp_age`, les fichiers les plus
+/// anciens des caches WMS restants au-delà de `WMS_CACHE_MAX_SIZE`, et les téléchargements
+/// interrompus (`.part`) du cache vieux de plus d'un jour. Les archives du cache et les projets
+/// ne sont jamais supprimés. Le verrou d'instance doit être tenu par l'appelant.
+///
+/// # Arguments
+///
+/// * `temp_dir` - Le dossier temporaire.
+/// * `cache_dir` - Le dossier du cache des archives.
+/// * `max_tmp_age` - L'âge au-delà duquel le contenu du dossier temporaire est supprimé.
+///
+/// # Returns
+///
+/// * `CleanupReport` - Le nombre d'éléments supprimés et les octets libérés.
+pub fn startup_cleanup(temp_dir: &Path, cache_dir: &Path, max_tmp_age: Duration) -> CleanupReport {
+    let mut report = CleanupReport::default();
+
+    for path in tmp_entries(temp_dir) {
+        if entry_age(&path) >= max_tmp_age {
+            report.remove(&path);
+        }
+    }
+
+    for wms_cache in wms_cache_dirs(temp_dir) {
+        cap_directory_size(&wms_cache, WMS_CACHE_MAX_SIZE, &mut report);
+    }
+
+    for entry in fs::read_dir(cache_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let is_part = path
+            .extension()
+            .is_some_and(|extension| extension == "part");
+        if is_part && path.is_file() && entry_age(&path) >= STALE_PART_AGE {
+            report.remove(&path);
+        }
+    }
+
+    report
+}
+
+/// Supprime les fichiers les plus anciens d'un dossier jusqu'à ce que sa taille
+/// ne dépasse plus `max_size` octets.
+fn cap_directory_size(dir: &Path, max_size: u64, report: &mut CleanupReport) {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files);
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_size {
+        return;
+    }
+    files.sort_by_key(|(_, _, age)| std::cmp::Reverse(*age));
+    for (path, size, _) in files {
+        if total <= max_size {
+            break;
+        }
+        report.remove(&path);
+        total = total.saturating_sub(size);
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<(PathBuf, u64, Duration)>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+            let age = entry_age(&path);
+            files.push((path, size, age));
+        }
+    }
+}
+
+/// Contenu du dossier temporaire, hors verrou d'instance.
+fn tmp_entries(temp_dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(temp_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_none_or(|name| name != INSTANCE_LOCK_FILE)
+        })
+        .collect()
+}
+
+/// Caches WMS du dossier temporaire : `tmp/wms_cache` et ceux des dossiers de travail
+/// (`tmp/<opération>/wms_cache`).
+fn wms_cache_dirs(temp_dir: &Path) -> Vec<PathBuf> {
+    tmp_entries(temp_dir)
+        .into_iter()
+        .map(|path| {
+            if path.file_name().is_some_and(|name| name == WMS_CACHE_DIR) {
+                path
+            } else {
+                path.join(WMS_CACHE_DIR)
+            }
+        })
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// Données supprimables par `clear_cache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheScope {
+    /// Archives IGN téléchargées (dossier du cache).
+    Archives,
+    /// GPKG préparés conservés pour la reprise des créations interrompues (`<projet>/staging`).
+    Prepared,
+    /// Tuiles WMS des orthophotos.
+    Wms,
+    /// Tout le dossier temporaire, caches WMS compris.
+    Tmp,
+}
+
+impl CacheScope {
+    pub const ALL: [CacheScope; 4] = [
+        CacheScope::Archives,
+        CacheScope::Prepared,
+        CacheScope::Wms,
+        CacheScope::Tmp,
+    ];
+
+    /// Nom du périmètre, tel qu'accepté par `clear_cache`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            CacheScope::Archives => "archives",
+            CacheScope::Prepared => "prepared",
+            CacheScope::Wms => "wms",
+            CacheScope::Tmp => "tmp",
+        }
+    }
+
+    /// Indique si une tâche en cours sur un projet utilise les données de ce périmètre.
+    pub fn used_by(&self, kind: BusyKind) -> bool {
+        match self {
+            CacheScope::Archives | CacheScope::Prepared => kind == BusyKind::Creating,
+            CacheScope::Wms => matches!(kind, BusyKind::Creating | BusyKind::RefreshingOrtho),
+            CacheScope::Tmp => true,
+        }
+    }
+}
+
+/// Supprime les données d'un périmètre du cache, avec les dossiers de la configuration.
+///
+/// # Arguments
+///
+/// * `scope` - Le périmètre à vider.
+///
+/// # Returns
+///
+/// * `CleanupReport` - Le nombre d'éléments supprimés et les octets libérés.
+pub fn clear_cache_scope(scope: CacheScope) -> CleanupReport {
+    clear_cache_scope_in(scope, &cache_dir(), &temp_dir(), &projects_dir())
+}
+
+/// Supprime les données d'un périmètre du cache. Les archives et le dossier temporaire
+/// sont vidés sans être supprimés, le verrou d'instance est conservé. Les GPKG préparés
+/// d'une création interrompue étant supprimés, sa reprise recommence à l'étape de préparation.
+///
+/// # Arguments
+///
+/// * `scope` - Le périmètre à vider.
+/// * `cache_dir` - Le dossier du cache des archives.
+/// * `temp_dir` - Le dossier temporaire.
+/// * `projects_dir` - Le dossier des projets.
+///
+/// # Returns
+///
+/// * `CleanupReport` - Le nombre d'éléments supprimés et les octets libérés.
+pub fn clear_cache_scope_in(
+    scope: CacheScope,
+    cache_dir: &Path,
+    temp_dir: &Path,
+    projects_dir: &Path,
+) -> CleanupReport {
+    let mut report = CleanupReport::default();
+    match scope {
+        CacheScope::Archives => {
+            for entry in fs::read_dir(cache_dir).into_iter().flatten().flatten() {
+                report.remove(&entry.path());
+            }
+        }
+        CacheScope::Prepared => {
+            for entry in fs::read_dir(projects_dir).into_iter().flatten().flatten() {
+                let project_folder = entry.path();
+                let staging = project_folder.join(STAGING_DIR);
+                if !staging.is_dir() {
+                    continue;
+                }
+                report.remove(&staging);
+                if let Ok(mut state) = PipelineState::load(&project_folder) {
+                    if state.completed.contains(&Stage::Prepare)
+                        && !state.completed.contains(&Stage::Fuse)
+                    {
+                        state.completed.retain(|stage| *stage != Stage::Prepare);
+                        state.prepared.clear();
+                        if let Err(e) = state.save(&project_folder) {
+                            tracing::warn!(
+                                project = %project_folder.display(),
+                                error = %e,
+                                "État de création non mis à jour"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        CacheScope::Wms => {
+            for wms_cache in wms_cache_dirs(temp_dir) {
+                report.remove(&wms_cache);
+            }
+        }
+        CacheScope::Tmp => {
+            for path in tmp_entries(temp_dir) {
+                report.remove(&path);
+            }
+        }
+    }
+    report
+}
+
 pub fn get_config() -> MutexGuard<'static, Config> {
     CONFIG.lock().unwrap()
 }
@@ -428,6 +3905,124 @@ pub fn slice_factor() -> u32 {
     get_config().slice_factor
 }
 
+pub fn enhance_ortho_slices() -> bool {
+    get_config().enhance_ortho_slices
+}
+
+pub fn slice_name_template() -> String {
+    get_config().slice_name_template.clone()
+}
+
+pub fn slice_overview() -> bool {
+    get_config().slice_overview
+}
+
+pub fn tmp_max_age() -> Duration {
+    Duration::from_secs(get_config().tmp_max_age_hours * 60 * 60)
+}
+
+pub fn slice_memory_limit_mb() -> u64 {
+    get_config().slice_memory_limit_mb
+}
+
+pub fn default_land_color() -> Rgb {
+    get_config().default_land_color
+}
+
+pub fn offline_mode() -> bool {
+    get_config().offline_mode
+}
+
+pub fn offline_fixture_dir() -> Option<PathBuf> {
+    get_config().offline_fixture_dir.clone()
+}
+
+pub fn geopf_download_api() -> String {
+    get_config().geopf_download_api.clone()
+}
+
+/// URL d'archive saisie par l'utilisateur pour une base et un département, si elle existe.
+pub fn dataset_url_override(data_type: &str, code: &str) -> Option<String> {
+    get_config()
+        .dataset_url_overrides
+        .get(data_type)
+        .and_then(|urls| urls.get(code))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+pub fn oso_url() -> Option<String> {
+    get_config().oso_url.clone()
+}
+
+pub fn proxy_config() -> Option<ProxyConfig> {
+    get_config().proxy.clone()
+}
+
+pub fn download_parallelism() -> usize {
+    get_config().download_parallelism.max(1)
+}
+
+pub fn throughput() -> Throughput {
+    get_config().throughput
+}
+
+pub fn nodata_color() -> [u8; 3] {
+    get_config().nodata_color
+}
+
+pub fn clip_ortho_to_land() -> bool {
+    get_config().clip_ortho_to_land
+}
+
+pub fn ortho_sea_color() -> [u8; 3] {
+    get_config().ortho_sea_color
+}
+
+pub fn language() -> Language {
+    get_config().language
+}
+
+pub fn log_level() -> String {
+    get_config().log_level.clone()
+}
+
+pub fn ortho_jpeg_quality() -> u8 {
+    get_config().image_quality.ortho_jpeg_quality.clamp(1, 100)
+}
+
+pub fn veget_jpeg_quality() -> u8 {
+    get_config().image_quality.veget_jpeg_quality.clamp(1, 100)
+}
+
+pub fn slice_jpeg_quality() -> u8 {
+    get_config().image_quality.slice_jpeg_quality.clamp(1, 100)
+}
+
+pub fn tiff_compression() -> TiffCompression {
+    get_config().image_quality.tiff_compression
+}
+
+pub fn building_heights() -> BuildingHeights {
+    get_config().building_heights
+}
+
+pub fn ortho_sources() -> Vec<OrthoSource> {
+    get_config().ortho_sources.clone()
+}
+
+pub fn max_project_area_km2() -> f64 {
+    get_config().max_project_area_km2
+}
+
+pub fn max_project_regions() -> usize {
+    get_config().max_project_regions
+}
+
+pub fn max_cache_size_bytes() -> u64 {
+    (get_config().max_cache_size_gb.max(0.0) * 1024.0 * 1024.0 * 1024.0) as u64
+}
+
 pub fn in_cache_dir<P: AsRef<Path>>(path: P) -> PathBuf {
     cache_dir().join(path)
 }
@@ -444,14 +4039,42 @@ pub fn in_resource_dir<P: AsRef<Path>>(path: P) -> PathBuf {
     resource_dir().join(path)
 }
 
+pub fn trash_dir() -> PathBuf {
+    projects_dir().join(TRASH_DIR)
+}
+
+/// Dossier d'un projet : son dossier externe s'il est inscrit au registre des projets externes,
+/// `<dossier des projets>/<projet>` sinon.
 pub fn project_dir(project_name: &str) -> PathBuf {
-    in_projects_dir(project_name)
+    external_project_dir(project_name).unwrap_or_else(|| in_projects_dir(project_name))
 }
 
 pub fn in_project_dir(project_name: &str, path: &str) -> PathBuf {
     project_dir(project_name).join(path)
 }
 
+/// GeoTIFF d'un projet (`<projet>/<projet>.tiff`).
+pub fn project_tiff(project_name: &str) -> PathBuf {
+    project_dir(project_name).join(format!("{}.tiff", project_name))
+}
+
+/// Image de végétation d'un projet (`<projet>/<projet>_VEGET.jpeg`).
+pub fn project_veget_jpeg(project_name: &str) -> PathBuf {
+    project_dir(project_name).join(format!("{}_VEGET.jpeg", project_name))
+}
+
+/// Orthophotographie d'un projet (`<projet>/<projet>_ORTHO.jpeg`).
+pub fn project_ortho_jpeg(project_name: &str) -> PathBuf {
+    project_dir(project_name).join(format!("{}_ORTHO.jpeg", project_name))
+}
+
+/// GeoPackage d'une couche dans le dossier `resources` d'un projet.
+pub fn resource_gpkg(project_folder: &Path, layer_name: &str) -> PathBuf {
+    project_folder
+        .join("resources")
+        .join(format!("{}.gpkg", layer_name))
+}
+
 pub fn save_config() -> Result<(), Box<dyn std::error::Error>> {
     get_config().save()
 }
@@ -1,84 +1,451 @@
 use chrono::NaiveDate;
-use futures_util::StreamExt;
+use futures_util::{StreamExt, stream};
+use lazy_static::lazy_static;
 use regex::Regex;
-use reqwest;
+use reqwest::{self, NoProxy, Proxy};
 use scraper::{Html, Selector};
-use std::{error::Error, fs, path::Path};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Instant,
+};
 use tokio::{fs::File, io::AsyncWriteExt};
 
-use crate::utils::{cache_dir, get_rpg_for_dep_code};
+use crate::app_setup::ProxyConfig;
+use crate::utils::{
+    self, ArchiveError, DataVintage, cache_dir, dataset_url_override, enforce_cache_limit,
+    geopf_download_api, get_rpg_for_dep_code, ign_department_code, proxy_config, touch_cache_entry,
+    verify_cached_archive,
+};
 
+lazy_static! {
+    /// Client HTTP partagé par toutes les requêtes, reconstruit après un changement de proxy.
+    static ref HTTP_CLIENT: Mutex<Option<reqwest::Client>> = Mutex::new(None);
+    /// Date du millésime dans le nom des archives IGN.
+    static ref DATE_REGEX: Regex = Regex::new(r"(\d{4}-\d{2}-\d{2})").unwrap();
+}
+
+/// Adresse par défaut de l'API de téléchargement de la Géoplateforme.
+pub const GEOPF_DOWNLOAD_API: &str = "https://data.geopf.fr/telechargement";
+
+/// Taille de l'extrait de page journalisé lorsque la mise en page du site a changé.
+const HTML_EXCERPT_BYTES: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DBType {
     FORET,
     TOPO,
     RPG,
 }
 
-/// Obtient l'URL d'un fichier SHP depuis la base de données IGN.
-/// Cherche l'url le plus récent pour le département spécifié.
+impl DBType {
+    /// Nom de la base dans l'API de téléchargement et les métadonnées des projets.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DBType::FORET => "BDFORET",
+            DBType::TOPO => "BDTOPO",
+            DBType::RPG => "RPG",
+        }
+    }
+
+    /// Page de téléchargement de la base sur geoservices.ign.fr, lue en repli de l'API.
+    pub fn page_url(&self) -> &'static str {
+        match self {
+            DBType::FORET => "https://geoservices.ign.fr/bdforet#",
+            DBType::TOPO => "https://geoservices.ign.fr/bdtopo#",
+            DBType::RPG => "https://geoservices.ign.fr/rpg#",
+        }
+    }
+
+    fn from_page_url(url: &str) -> Option<Self> {
+        match true {
+            _ if url.contains("bdforet#") => Some(DBType::FORET),
+            _ if url.contains("bdtopo#") => Some(DBType::TOPO),
+            _ if url.contains("rpg#") => Some(DBType::RPG),
+            _ => None,
+        }
+    }
+
+    /// Code de la zone dans le nom des archives : département (`D02A`), ou région pour le RPG (`R94`).
+    fn archive_code(&self, code: &str) -> String {
+        match self {
+            DBType::RPG => format!("R{}", code),
+            _ => ign_department_code(code),
+        }
+    }
+}
+
+/// Échec de la recherche de l'archive d'un département.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveLookupError {
+    /// La page a été lue mais ne contient aucun lien vers une archive SHP :
+    /// la mise en page du site a changé et la lecture des liens est à revoir.
+    ScrapeFormatChanged { url: String },
+    /// Des archives SHP sont listées, mais aucune pour le département demandé.
+    NoDepartmentArchive,
+}
+
+impl fmt::Display for ArchiveLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveLookupError::ScrapeFormatChanged { url } => write!(
+                f,
+                "No SHP archive link on {}: the page layout has changed",
+                url
+            ),
+            ArchiveLookupError::NoDepartmentArchive => write!(f, "No file found"),
+        }
+    }
+}
+
+impl Error for ArchiveLookupError {}
+
+/// Prépare un client HTTP passant par le proxy configuré, le cas échéant.
+///
+/// # Arguments
+/// - `proxy`: La configuration du proxy.
+///
+/// # Retourne
+/// - Result<reqwest::ClientBuilder, reqwest::Error> - Le constructeur du client, à compléter (délais...).
+pub fn client_builder(
+    proxy: Option<&ProxyConfig>,
+) -> Result<reqwest::ClientBuilder, reqwest::Error> {
+    let builder = reqwest::Client::builder();
+    let Some(proxy) = proxy else {
+        return Ok(builder);
+    };
+
+    let mut reqwest_proxy = Proxy::all(&proxy.url)?;
+    if let Some(username) = &proxy.username {
+        reqwest_proxy =
+            reqwest_proxy.basic_auth(username, proxy.password.as_deref().unwrap_or_default());
+    }
+    if !proxy.no_proxy.is_empty() {
+        reqwest_proxy = reqwest_proxy.no_proxy(NoProxy::from_string(&proxy.no_proxy.join(",")));
+    }
+    Ok(builder.proxy(reqwest_proxy))
+}
+
+/// Construit un client HTTP passant par le proxy configuré, le cas échéant.
+///
+/// # Arguments
+/// - `proxy`: La configuration du proxy.
+///
+/// # Retourne
+/// - Result<reqwest::Client, reqwest::Error> - Le client.
+pub fn build_client(proxy: Option<&ProxyConfig>) -> Result<reqwest::Client, reqwest::Error> {
+    client_builder(proxy)?.build()
+}
+
+/// Retourne le client HTTP partagé, construit au premier appel à partir de la configuration.
+///
+/// # Retourne
+/// - Result<reqwest::Client, reqwest::Error> - Le client (une copie partageant le même pool de connexions).
+pub fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    // La configuration est lue avant de verrouiller le client, pour ne jamais
+    // prendre les deux verrous dans l'ordre inverse de `save_settings`.
+    let proxy = proxy_config();
+    let mut client = HTTP_CLIENT.lock().unwrap();
+    if let Some(client) = client.as_ref() {
+        return Ok(client.clone());
+    }
+    let built = build_client(proxy.as_ref())?;
+    *client = Some(built.clone());
+    Ok(built)
+}
+
+/// Oublie le client HTTP partagé : le prochain appel à `http_client` prendra en compte
+/// la nouvelle configuration du proxy.
+pub fn reset_http_client() {
+    *HTTP_CLIENT.lock().unwrap() = None;
+}
+
+/// Variables d'environnement transmises aux outils GDAL (`gdal_translate`, `gdalwarp`)
+/// pour que leurs requêtes HTTP (WMS, `/vsicurl/`) passent par le proxy.
+///
+/// # Arguments
+/// - `proxy`: La configuration du proxy.
+///
+/// # Retourne
+/// - Vec<(String, String)> - Les variables à définir, vide sans proxy.
+pub fn gdal_proxy_env(proxy: Option<&ProxyConfig>) -> Vec<(String, String)> {
+    let Some(proxy) = proxy else {
+        return Vec::new();
+    };
+
+    let mut env = vec![("GDAL_HTTP_PROXY".to_string(), proxy.url.clone())];
+    if let Some(username) = &proxy.username {
+        env.push((
+            "GDAL_HTTP_PROXYUSERPWD".to_string(),
+            format!(
+                "{}:{}",
+                username,
+                proxy.password.as_deref().unwrap_or_default()
+            ),
+        ));
+    }
+    if !proxy.no_proxy.is_empty() {
+        // GDAL délègue les requêtes à curl, qui lit la liste d'exclusion dans NO_PROXY.
+        env.push(("NO_PROXY".to_string(), proxy.no_proxy.join(",")));
+    }
+    env
+}
+
+/// Version de la BD Forêt d'une archive : la v2 (attribut `ESSENCE`) est préférée,
+/// la v1 (attributs `CODE_TFV` et `TFV`) n'est retenue que pour les départements sans v2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BdForetVersion {
+    V1,
+    V2,
+}
+
+impl BdForetVersion {
+    /// Version d'une archive d'après son nom (`BDFORET_2-0__SHP_...`), ou `None` si ce n'est
+    /// pas une archive de la BD Forêt.
+    pub fn from_url(url: &str) -> Option<Self> {
+        if url.contains("BDFORET_2-") {
+            Some(BdForetVersion::V2)
+        } else if url.contains("BDFORET_1-") {
+            Some(BdForetVersion::V1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Archive IGN retenue pour un département, avec la date de son millésime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveUrl {
+    pub url: String,
+    /// Date extraite du nom de l'archive (`..._2025-03-15.7z`), si présente.
+    pub date: Option<NaiveDate>,
+    /// Version de la BD Forêt retenue, `None` pour les autres bases.
+    pub bdforet_version: Option<BdForetVersion>,
+}
+
+impl ArchiveUrl {
+    pub fn new(url: &str) -> Self {
+        ArchiveUrl {
+            url: url.to_string(),
+            date: archive_date(url),
+            bdforet_version: BdForetVersion::from_url(url),
+        }
+    }
+
+    /// L'archive est une BD Forêt v1, retenue faute de v2 pour le département.
+    pub fn is_bdforet_v1(&self) -> bool {
+        self.bdforet_version == Some(BdForetVersion::V1)
+    }
+}
+
+/// Extrait la date du millésime du nom d'une archive IGN.
+///
+/// # Arguments
+/// - `url`: L'URL ou le nom de l'archive.
+///
+/// # Retourne
+/// - Option<NaiveDate> - La date, ou `None` si le nom n'en contient pas.
+pub fn archive_date(url: &str) -> Option<NaiveDate> {
+    DATE_REGEX
+        .captures(url)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| NaiveDate::parse_from_str(m.as_str(), "%Y-%m-%d").ok())
+}
+
+/// Retient l'archive la plus récente d'un département parmi les liens d'une source.
+/// Pour la BD Forêt, l'archive v2 est retenue ; à défaut, la v1 la plus récente.
+fn select_archive(
+    dbtype: DBType,
+    code: &str,
+    links: Vec<String>,
+) -> Result<ArchiveUrl, Box<dyn Error>> {
+    let archive_code = dbtype.archive_code(code);
+    let mut shp_files: Vec<String> = links
+        .into_iter()
+        .filter(|href| href.contains(&archive_code) && href.contains("SHP"))
+        .collect();
+
+    if shp_files.is_empty() {
+        return Err(ArchiveLookupError::NoDepartmentArchive.into());
+    }
+
+    if dbtype == DBType::FORET {
+        // La v1 n'est utilisée que pour les départements sans archive v2.
+        let version = if shp_files
+            .iter()
+            .any(|file| BdForetVersion::from_url(file) == Some(BdForetVersion::V2))
+        {
+            BdForetVersion::V2
+        } else {
+            BdForetVersion::V1
+        };
+        shp_files.retain(|file| BdForetVersion::from_url(file) == Some(version));
+
+        if shp_files.is_empty() {
+            return Err("No BDFORET V2 or V1 file found".into());
+        }
+        if version == BdForetVersion::V1 {
+            tracing::warn!(
+                department = code,
+                "Aucune archive BD Forêt v2, repli sur la BD Forêt v1"
+            );
+        }
+    }
+
+    let mut archives: Vec<ArchiveUrl> =
+        shp_files.iter().map(|file| ArchiveUrl::new(file)).collect();
+    // Les archives sans date sont classées en dernier (`None` < `Some`).
+    archives.sort_by(|a, b| b.date.cmp(&a.date));
+
+    archives
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No valid file URL found after filtering".into())
+}
+
+/// Début d'une page, coupé sur une limite de caractère, pour les journaux.
+fn html_excerpt(body: &str) -> &str {
+    let end = body
+        .char_indices()
+        .map(|(idx, c)| idx + c.len_utf8())
+        .take_while(|&end| end <= HTML_EXCERPT_BYTES)
+        .last()
+        .unwrap_or(0);
+    &body[..end]
+}
+
+/// Obtient l'URL d'un fichier SHP depuis la page de téléchargement IGN d'une base.
+/// Cherche l'url le plus récent pour le département spécifié. Pour la BD Forêt, l'archive v2
+/// est retenue ; à défaut, la v1 la plus récente, signalée par `ArchiveUrl::bdforet_version`.
 ///
 /// # Arguments
 /// - `code`: Le code du département.
 /// - `url`: L'URL de la base de données.
 ///
 /// # Retourne
-/// - Result<String, Box<dyn Error>> - L'URL du fichier SHP.
-pub async fn get_departement_shp_file_url(code: &str, url: &str) -> Result<String, Box<dyn Error>> {
-    let body = reqwest::get(url).await?.text().await?;
+/// - Result<ArchiveUrl, Box<dyn Error>> - L'URL du fichier SHP, la date de son millésime et la version
+///   de la BD Forêt. Une page sans aucun lien SHP donne `ArchiveLookupError::ScrapeFormatChanged`,
+///   une page sans lien pour ce département `ArchiveLookupError::NoDepartmentArchive`.
+pub async fn get_departement_shp_file_url(
+    code: &str,
+    url: &str,
+) -> Result<ArchiveUrl, Box<dyn Error>> {
+    let dbtype = DBType::from_page_url(url).ok_or("Unsupported database type")?;
+    let body = http_client()?.get(url).send().await?.text().await?;
     let document = Html::parse_document(&body);
     let selector = Selector::parse("a")?;
 
-    let dbtype = match true {
-        _ if url.contains("bdforet#") => DBType::FORET,
-        _ if url.contains("bdtopo#") => DBType::TOPO,
-        _ if url.contains("rpg#") => DBType::RPG,
-        _ => return Err("Unsupported database type".into()),
-    };
-
-    let code_prefix = match dbtype {
-        DBType::RPG => "R",
-        _ => "D0",
-    };
-
-    let mut shp_files: Vec<String> = document
+    let shp_links: Vec<String> = document
         .select(&selector)
         .filter_map(|element| element.value().attr("href"))
-        .filter(|href| href.contains(&format!("{}{}", code_prefix, code)) && href.contains("SHP"))
+        .filter(|href| href.contains("SHP"))
         .map(|s| s.to_string())
         .collect();
 
-    if shp_files.is_empty() {
-        return Err("No file found".into());
+    if shp_links.is_empty() {
+        tracing::error!(
+            url,
+            html = html_excerpt(&body),
+            "Aucun lien d'archive SHP sur la page de téléchargement : mise en page modifiée"
+        );
+        return Err(ArchiveLookupError::ScrapeFormatChanged {
+            url: url.to_string(),
+        }
+        .into());
     }
 
-    if matches!(dbtype, DBType::FORET) {
-        shp_files.retain(|file| file.contains("BDFORET_2-0"));
+    select_archive(dbtype, code, shp_links)
+}
 
-        if shp_files.is_empty() {
-            return Err("No BDFORET V2 file found".into());
+/// Chaînes d'un document JSON désignant une archive (`.7z`), où qu'elles se trouvent.
+fn json_archive_links(value: &serde_json::Value, links: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(text) if text.ends_with(".7z") => links.push(text.clone()),
+        serde_json::Value::Array(values) => {
+            for value in values {
+                json_archive_links(value, links);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for value in fields.values() {
+                json_archive_links(value, links);
+            }
         }
+        _ => {}
     }
+}
 
-    let date_regex = Regex::new(r"(\d{4}-\d{2}-\d{2})").unwrap();
-
-    shp_files.sort_by(|a, b| {
-        let date_a = date_regex
-            .captures(a)
-            .and_then(|cap| cap.get(1))
-            .and_then(|m| NaiveDate::parse_from_str(m.as_str(), "%Y-%m-%d").ok())
-            .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap());
+/// Obtient l'URL d'un fichier SHP depuis l'API de téléchargement de la Géoplateforme :
+/// les archives sont lues dans la liste JSON des ressources de la base
+/// (`<api>/resource/<BASE>?zone=<code>`), puis retenues comme sur la page IGN.
+///
+/// # Arguments
+/// - `dbtype`: La base recherchée.
+/// - `code`: Le code du département, ou de la région pour le RPG.
+/// - `api_url`: L'adresse de l'API (`GEOPF_DOWNLOAD_API` par défaut).
+///
+/// # Retourne
+/// - Result<ArchiveUrl, Box<dyn Error>> - L'archive retenue.
+pub async fn get_api_shp_file_url(
+    dbtype: DBType,
+    code: &str,
+    api_url: &str,
+) -> Result<ArchiveUrl, Box<dyn Error>> {
+    let url = format!(
+        "{}/resource/{}?zone={}",
+        api_url.trim_end_matches('/'),
+        dbtype.name(),
+        dbtype.archive_code(code)
+    );
+    let listing: serde_json::Value = http_client()?
+        .get(&url)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
 
-        let date_b = date_regex
-            .captures(b)
-            .and_then(|cap| cap.get(1))
-            .and_then(|m| NaiveDate::parse_from_str(m.as_str(), "%Y-%m-%d").ok())
-            .unwrap_or_else(|| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap());
-        date_b.cmp(&date_a)
-    });
+    let mut links = Vec::new();
+    json_archive_links(&listing, &mut links);
+    if !links.iter().any(|link| link.contains("SHP")) {
+        return Err(format!("No SHP archive listed by {}", url).into());
+    }
+    select_archive(dbtype, code, links)
+}
 
-    match shp_files.first() {
-        Some(url) => Ok(url.clone()),
-        None => Err("No valid file URL found after filtering".into()),
+/// Recherche l'archive d'un département : par l'API de la Géoplateforme, puis en repli
+/// sur la page de téléchargement IGN de la base.
+///
+/// # Arguments
+/// - `dbtype`: La base recherchée.
+/// - `code`: Le code du département, ou de la région pour le RPG.
+/// - `api_url`: L'adresse de l'API de téléchargement.
+/// - `page_url`: La page de téléchargement lue en repli.
+///
+/// # Retourne
+/// - Result<ArchiveUrl, Box<dyn Error>> - L'archive retenue, ou l'erreur de la page de repli.
+pub async fn find_archive_url(
+    dbtype: DBType,
+    code: &str,
+    api_url: &str,
+    page_url: &str,
+) -> Result<ArchiveUrl, Box<dyn Error>> {
+    match get_api_shp_file_url(dbtype, code, api_url).await {
+        Ok(archive) => Ok(archive),
+        Err(e) => {
+            tracing::warn!(
+                base = dbtype.name(),
+                code,
+                error = %e,
+                "API de téléchargement indisponible, repli sur la page IGN"
+            );
+            get_departement_shp_file_url(code, page_url).await
+        }
     }
 }
 
@@ -91,11 +458,30 @@ pub async fn get_departement_shp_file_url(code: &str, url: &str) -> Result<Strin
 /// # Retourne
 /// - Result<(), Box<dyn Error>> - Un résultat vide indiquant le succès ou une erreur.
 pub async fn download_file(url: &str, path: &str) -> Result<(), Box<dyn Error>> {
+    download_file_with_progress(url, path, &|_| {}).await
+}
+
+/// Télécharge un fichier en signalant la taille de chaque bloc reçu.
+///
+/// # Arguments
+/// - `url`: L'URL du fichier à télécharger.
+/// - `path`: Le chemin où le fichier sera enregistré.
+/// - `on_chunk`: Appelée avec la taille (en octets) de chaque bloc écrit.
+///
+/// # Retourne
+/// - Result<(), Box<dyn Error>> - Un résultat vide indiquant le succès ou une erreur.
+pub async fn download_file_with_progress(
+    url: &str,
+    path: &str,
+    on_chunk: &(dyn Fn(u64) + Sync),
+) -> Result<(), Box<dyn Error>> {
+    let response = http_client()?.get(url).send().await?.error_for_status()?;
     let mut file = File::create(path).await?;
-    let mut stream = reqwest::get(url).await?.bytes_stream();
+    let mut stream = response.bytes_stream();
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result?;
         file.write_all(&chunk).await?;
+        on_chunk(chunk.len() as u64);
     }
     file.flush().await?;
     Ok(())
@@ -121,42 +507,489 @@ pub async fn download_shp_file(url: &str, code: &str) -> Result<(), Box<dyn Erro
         url if url.contains("RPG") => "RPG",
         _ => "unknown",
     };
-    let cache_dir_path = cache_dir();
-    let cache_folder_path = cache_dir_path.to_str().unwrap_or_default();
-    let archive_path = format!("{}/{}_{}.7z", cache_folder_path, name, code);
+    let archive_path = archive_cache_path(name, code);
 
-    if Path::new(&archive_path).exists() {
+    if archive_path.exists() {
         fs::remove_file(&archive_path)?;
     }
 
-    download_file(url, &archive_path).await
+    download_file(url, &archive_path.to_string_lossy()).await
 }
 
-/// Obtients les URLs des fichiers SHP pour les départements spécifiés.
+/// Chemin de l'archive en cache pour un type de données et un département.
+pub fn archive_cache_path(file_type: &str, code: &str) -> PathBuf {
+    cache_dir().join(format!("{}_{}.7z", file_type, code))
+}
+
+/// Chemins des archives en cache (BDTOPO, BDFORET, RPG) nécessaires aux départements donnés.
+pub fn department_cache_paths(codes: &[String]) -> Vec<PathBuf> {
+    codes
+        .iter()
+        .flat_map(|code| {
+            ["BDTOPO", "BDFORET", "RPG"].map(|file_type| archive_cache_path(file_type, code))
+        })
+        .collect()
+}
+
+/// Obtient la taille d'un fichier distant par une requête HEAD, sans le télécharger.
 ///
 /// # Arguments
-/// - `codes`: Une liste de chaînes contenant les codes des départements.
+/// - `url`: L'URL du fichier.
+/// - `timeout`: Le délai maximal de la requête.
 ///
 /// # Retourne
-/// - Result<Vec<String>, Box<dyn Error>> - Une liste de chaînes contenant les URLs des fichiers SHP.
-pub async fn get_shp_file_urls(codes: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
-    let url_dl_topo = "https://geoservices.ign.fr/bdtopo#";
-    let url_dl_foret = "https://geoservices.ign.fr/bdforet#";
-    let url_dl_rpg = "https://geoservices.ign.fr/rpg#";
+/// - Option<u64> - La valeur de l'en-tête `Content-Length`, `None` si elle n'a pas pu être obtenue.
+pub async fn remote_file_size(url: &str, timeout: std::time::Duration) -> Option<u64> {
+    let client = client_builder(proxy_config().as_ref())
+        .and_then(|builder| builder.timeout(timeout).build())
+        .ok()?;
+    let response = client.head(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+
+    // `content_length()` reflète le corps (vide) de la réponse HEAD, pas l'en-tête.
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
 
-    let mut urls = Vec::new();
+/// Archive à télécharger dans le cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadJob {
+    pub file_type: String,
+    pub code: String,
+    pub url: String,
+}
 
-    for code in codes {
-        let url_topo = get_departement_shp_file_url(code, url_dl_topo).await?;
-        urls.push(url_topo);
+impl DownloadJob {
+    pub fn cache_path(&self) -> PathBuf {
+        archive_cache_path(&self.file_type, &self.code)
+    }
+}
+
+/// Progression agrégée des téléchargements concurrents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadProgress {
+    /// Nombre d'archives entièrement téléchargées.
+    pub completed: usize,
+    /// Nombre d'archives à télécharger (hors cache).
+    pub total: usize,
+    /// Octets reçus, toutes archives confondues.
+    pub downloaded_bytes: u64,
+    /// Dernier événement : archive du cache en cours de vérification, archive terminée
+    /// (`BDTOPO 2A`...) ou archive corrompue remplacée ; vide pendant les téléchargements.
+    pub label: String,
+    /// Durée du téléchargement de l'archive terminée, en secondes.
+    pub archive_seconds: Option<f64>,
+}
+
+/// Intervalle (en octets reçus) entre deux signalements de progression pendant les téléchargements.
+const PROGRESS_STEP_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Télécharge des archives dans le cache avec au plus `parallelism` téléchargements simultanés.
+/// Les archives déjà présentes dans le cache sont vérifiées avec `verify_cached_archive`, hors
+/// de l'exécuteur asynchrone : les archives valides sont ignorées, les archives corrompues
+/// supprimées et téléchargées à nouveau.
+/// Les tâches pointant vers le même fichier de cache ne sont téléchargées qu'une fois.
+/// Chaque archive est écrite dans un fichier `.part` au nom unique, renommé à la fin du
+/// téléchargement : au premier échec, les téléchargements en cours sont abandonnés et les
+/// fichiers `.part` de la série supprimés, sans laisser d'archive tronquée.
+/// Après chaque téléchargement, la limite de taille du cache est appliquée : les archives
+/// à protéger doivent avoir été épinglées avec `pin_cache_entries`.
+///
+/// # Arguments
+/// - `jobs`: Les archives à télécharger.
+/// - `parallelism`: Le nombre maximal de téléchargements simultanés.
+/// - `on_progress`: Appelée avec la progression agrégée.
+///
+/// # Retourne
+/// - Result<usize, Box<dyn Error>> - Le nombre d'archives téléchargées.
+pub async fn download_archives<F>(
+    jobs: Vec<DownloadJob>,
+    parallelism: usize,
+    on_progress: F,
+) -> Result<usize, Box<dyn Error>>
+where
+    F: Fn(DownloadProgress) + Sync,
+{
+    let mut seen = HashSet::new();
+    let mut pending: Vec<DownloadJob> = Vec::new();
+    for job in jobs {
+        let archive_path = job.cache_path();
+        if !seen.insert(archive_path.clone()) {
+            continue;
+        }
+        if archive_path.exists() {
+            on_progress(DownloadProgress {
+                completed: 0,
+                total: 0,
+                downloaded_bytes: 0,
+                label: format!("Vérification de l'archive {} {}", job.file_type, job.code),
+                archive_seconds: None,
+            });
+            // Le test de 7z peut durer plusieurs minutes : il ne bloque pas l'exécuteur.
+            let path = archive_path.clone();
+            let verified =
+                tokio::task::spawn_blocking(move || verify_cached_archive(&path)).await?;
+            match verified {
+                Ok(()) => {
+                    record_cache_use(&archive_path);
+                    continue;
+                }
+                Err(ArchiveError::Io(e)) => return Err(e.into()),
+                Err(e) => {
+                    tracing::warn!(
+                        file = %archive_path.display(),
+                        error = %e,
+                        "Archive invalide, nouveau téléchargement"
+                    );
+                    fs::remove_file(&archive_path)?;
+                    on_progress(DownloadProgress {
+                        completed: 0,
+                        total: 0,
+                        downloaded_bytes: 0,
+                        label: format!(
+                            "Archive {} {} corrompue, nouveau téléchargement",
+                            job.file_type, job.code
+                        ),
+                        archive_seconds: None,
+                    });
+                }
+            }
+        }
+        pending.push(job);
+    }
+
+    let total = pending.len();
+    let completed_count = AtomicUsize::new(0);
+    let downloaded_bytes = AtomicU64::new(0);
+    let last_reported = AtomicU64::new(0);
+    let on_chunk = |size: u64| {
+        let bytes = downloaded_bytes.fetch_add(size, Ordering::SeqCst) + size;
+        let reported = last_reported.load(Ordering::SeqCst);
+        if bytes - reported >= PROGRESS_STEP_BYTES
+            && last_reported
+                .compare_exchange(reported, bytes, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        {
+            on_progress(DownloadProgress {
+                completed: completed_count.load(Ordering::SeqCst),
+                total,
+                downloaded_bytes: bytes,
+                label: String::new(),
+                archive_seconds: None,
+            });
+        }
+    };
+    let on_chunk = &on_chunk;
+
+    let part_paths: Vec<PathBuf> = pending
+        .iter()
+        .map(|job| unique_part_path(&job.cache_path()))
+        .collect();
+    let mut downloads = stream::iter(pending.iter().zip(part_paths.iter()))
+        .map(|(job, part_path)| async move {
+            let archive_path = job.cache_path();
+            let start = Instant::now();
+            download_file_with_progress(&job.url, &part_path.to_string_lossy(), on_chunk)
+                .await
+                .map_err(|e| format!("Échec du téléchargement de {}: {}", job.url, e))?;
+            fs::rename(part_path, &archive_path).map_err(|e| e.to_string())?;
+            record_cache_vintage(job);
+            Ok::<(&DownloadJob, f64), String>((job, start.elapsed().as_secs_f64()))
+        })
+        .buffer_unordered(parallelism.max(1));
+
+    while let Some(result) = downloads.next().await {
+        let (job, seconds) = match result {
+            Ok(done) => done,
+            Err(e) => {
+                // Les téléchargements en cours sont abandonnés avant la suppression de leurs
+                // fichiers ; ceux qui n'ont pas commencé n'en ont pas créé.
+                drop(downloads);
+                remove_part_files(&part_paths);
+                return Err(e.into());
+            }
+        };
+        record_cache_use(&job.cache_path());
+        match enforce_cache_limit() {
+            Ok(evicted) if !evicted.is_empty() => {
+                tracing::info!(evicted = %evicted.join(", "), "Archives évincées du cache")
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(error = %e, "Impossible d'appliquer la limite du cache"),
+        }
+        let completed = completed_count.fetch_add(1, Ordering::SeqCst) + 1;
+        on_progress(DownloadProgress {
+            completed,
+            total,
+            downloaded_bytes: downloaded_bytes.load(Ordering::SeqCst),
+            label: format!("{} {}", job.file_type, job.code),
+            archive_seconds: Some(seconds),
+        });
+    }
+
+    Ok(completed_count.load(Ordering::SeqCst))
+}
+
+/// Télécharge une nouvelle version d'archives du cache, avec au plus `parallelism`
+/// téléchargements simultanés. Chaque archive est écrite dans un fichier `.part` au nom
+/// unique, à côté de l'archive en place, qui reste utilisable : le remplacement est laissé
+/// à l'appelant (voir `data_updates::refresh_archives`). Au premier échec, les
+/// téléchargements en cours sont abandonnés et tous les fichiers `.part` supprimés.
+///
+/// # Arguments
+/// - `jobs`: Les archives à télécharger.
+/// - `parallelism`: Le nombre maximal de téléchargements simultanés.
+///
+/// # Retourne
+/// - Result<Vec<(DownloadJob, PathBuf)>, Box<dyn Error>> - Le fichier téléchargé de chaque archive.
+pub async fn download_replacements(
+    jobs: Vec<DownloadJob>,
+    parallelism: usize,
+) -> Result<Vec<(DownloadJob, PathBuf)>, Box<dyn Error>> {
+    let mut seen = HashSet::new();
+    let pending: Vec<(DownloadJob, PathBuf)> = jobs
+        .into_iter()
+        .filter(|job| seen.insert(job.cache_path()))
+        .map(|job| {
+            let part_path = unique_part_path(&job.cache_path());
+            (job, part_path)
+        })
+        .collect();
+    let part_paths: Vec<PathBuf> = pending.iter().map(|(_, part)| part.clone()).collect();
+
+    let mut downloads = stream::iter(pending.iter())
+        .map(|(job, part_path)| async move {
+            download_file(&job.url, &part_path.to_string_lossy())
+                .await
+                .map_err(|e| format!("Échec du téléchargement de {}: {}", job.url, e))
+        })
+        .buffer_unordered(parallelism.max(1));
+    while let Some(result) = downloads.next().await {
+        if let Err(e) = result {
+            drop(downloads);
+            remove_part_files(&part_paths);
+            return Err(e.into());
+        }
+    }
+    Ok(pending)
+}
+
+/// Numéro des fichiers `.part` de `download_archives`, pour des noms uniques.
+static PART_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Fichier temporaire unique d'un téléchargement, à côté de l'archive : deux téléchargements
+/// de la même archive (deux processus, ou une tâche abandonnée encore en cours) n'écrivent
+/// jamais dans le même fichier. L'extension `.part` permet au nettoyage du cache de
+/// supprimer les fichiers laissés par un processus interrompu.
+pub fn unique_part_path(archive_path: &Path) -> PathBuf {
+    let file_name = archive_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    archive_path.with_file_name(format!(
+        "{}.{}_{}.part",
+        file_name,
+        std::process::id(),
+        PART_FILE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
+/// Supprime les fichiers `.part` d'une série de téléchargements abandonnée ; les fichiers
+/// déjà renommés en archive ou jamais créés sont ignorés.
+pub fn remove_part_files(part_paths: &[PathBuf]) {
+    for part_path in part_paths {
+        if let Err(e) = fs::remove_file(part_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(
+                    file = %part_path.display(),
+                    error = %e,
+                    "Impossible de supprimer le téléchargement interrompu"
+                );
+            }
+        }
+    }
+}
+
+/// Enregistre le millésime de l'archive téléchargée d'une tâche dans le cache.
+pub fn record_cache_vintage(job: &DownloadJob) {
+    let vintage = DataVintage {
+        data_type: job.file_type.clone(),
+        region: job.code.clone(),
+        date: archive_date(&job.url).map(|date| date.format("%Y-%m-%d").to_string()),
+        url: job.url.clone(),
+    };
+    if let Err(e) = utils::record_cache_vintage(&job.cache_path(), vintage) {
+        tracing::warn!(
+            url = %job.url,
+            error = %e,
+            "Impossible d'enregistrer le millésime de l'archive"
+        );
+    }
+}
+
+fn record_cache_use(archive_path: &Path) {
+    if let Err(e) = touch_cache_entry(archive_path) {
+        tracing::warn!(
+            file = %archive_path.display(),
+            error = %e,
+            "Impossible d'enregistrer l'utilisation de l'archive"
+        );
+    }
+}
+
+/// Archives à télécharger pour un département.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepartmentArchives {
+    pub topo: ArchiveUrl,
+    pub foret: ArchiveUrl,
+    pub rpg: ArchiveUrl,
+}
+
+/// Résultat de la recherche des URLs pour un département.
+#[derive(Debug)]
+pub struct DepartmentUrls {
+    pub code: String,
+    pub archives: Result<DepartmentArchives, String>,
+    /// La recherche a échoué faute de pouvoir joindre les serveurs (connexion impossible
+    /// ou délai dépassé), et non parce qu'une archive est absente.
+    pub network_error: bool,
+}
+
+/// Échec de la recherche des archives d'un département.
+struct DepartmentLookupError {
+    message: String,
+    network: bool,
+}
 
-        let url_foret = get_departement_shp_file_url(code, url_dl_foret).await?;
-        urls.push(url_foret);
+impl DepartmentLookupError {
+    fn new(message: String, error: &(dyn Error + 'static)) -> Self {
+        DepartmentLookupError {
+            message,
+            network: is_network_error(error),
+        }
+    }
+}
 
-        let rpg_code = get_rpg_for_dep_code(code).unwrap();
-        let url_rpg = get_departement_shp_file_url(rpg_code, url_dl_rpg).await?;
-        urls.push(url_rpg);
+impl From<String> for DepartmentLookupError {
+    fn from(message: String) -> Self {
+        DepartmentLookupError {
+            message,
+            network: false,
+        }
+    }
+}
+
+/// Indique si une erreur, ou l'une de ses causes, est un échec de connexion ou un
+/// dépassement de délai d'une requête HTTP : les serveurs sont alors injoignables.
+///
+/// # Arguments
+/// - `error`: L'erreur à examiner.
+///
+/// # Retourne
+/// - bool - Vrai pour une erreur réseau.
+pub fn is_network_error(error: &(dyn Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+        {
+            return true;
+        }
+        current = error.source();
+    }
+    false
+}
+
+/// Archive saisie par l'utilisateur pour une base et un département (`dataset_url_overrides`).
+fn archive_override(dbtype: DBType, code: &str) -> Option<ArchiveUrl> {
+    let url = dataset_url_override(dbtype.name(), code)?;
+    tracing::info!(
+        base = dbtype.name(),
+        department = code,
+        url,
+        "URL d'archive saisie dans la configuration"
+    );
+    Some(ArchiveUrl::new(&url))
+}
+
+async fn get_department_archives(code: &str) -> Result<DepartmentArchives, DepartmentLookupError> {
+    let api_url = geopf_download_api();
+
+    let topo = match archive_override(DBType::TOPO, code) {
+        Some(archive) => archive,
+        None => find_archive_url(DBType::TOPO, code, &api_url, DBType::TOPO.page_url())
+            .await
+            .map_err(|e| {
+                DepartmentLookupError::new(
+                    format!("BDTOPO introuvable pour le département {}: {}", code, e),
+                    &*e,
+                )
+            })?,
+    };
+    let foret = match archive_override(DBType::FORET, code) {
+        Some(archive) => archive,
+        None => find_archive_url(DBType::FORET, code, &api_url, DBType::FORET.page_url())
+            .await
+            .map_err(|e| {
+                DepartmentLookupError::new(
+                    format!("BDFORET introuvable pour le département {}: {}", code, e),
+                    &*e,
+                )
+            })?,
+    };
+    let rpg = match archive_override(DBType::RPG, code) {
+        Some(archive) => archive,
+        None => {
+            let rpg_code = get_rpg_for_dep_code(code)?;
+            find_archive_url(DBType::RPG, rpg_code, &api_url, DBType::RPG.page_url())
+                .await
+                .map_err(|e| {
+                    DepartmentLookupError::new(
+                        format!(
+                            "RPG introuvable pour le département {} (région {}): {}",
+                            code, rpg_code, e
+                        ),
+                        &*e,
+                    )
+                })?
+        }
+    };
+
+    Ok(DepartmentArchives { topo, foret, rpg })
+}
+
+/// Obtients les URLs des fichiers SHP pour les départements spécifiés.
+/// Chaque département est résolu indépendamment : l'échec de l'un n'annule pas
+/// les URLs déjà obtenues pour les autres. Les URLs saisies dans `dataset_url_overrides`
+/// sont retenues sans recherche.
+///
+/// # Arguments
+/// - `codes`: Une liste de chaînes contenant les codes des départements.
+///
+/// # Retourne
+/// - Vec<DepartmentUrls> - Le résultat de la recherche pour chaque département, dans l'ordre des codes.
+pub async fn get_shp_file_urls(codes: &[String]) -> Vec<DepartmentUrls> {
+    let mut results = Vec::new();
+
+    for code in codes {
+        let (archives, network_error) = match get_department_archives(code).await {
+            Ok(archives) => (Ok(archives), false),
+            Err(e) => (Err(e.message), e.network),
+        };
+        results.push(DepartmentUrls {
+            code: code.clone(),
+            archives,
+            network_error,
+        });
     }
 
-    Ok(urls)
+    results
 }
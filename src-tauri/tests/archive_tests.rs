@@ -0,0 +1,157 @@
+use firefront_gis_lib::gis_operation::layer_config::LayerConfig;
+use firefront_gis_lib::gis_operation::layers::{archive_layers, check_archive_layers};
+use firefront_gis_lib::utils::{missing_basenames, parse_7z_listing, probe_archive_contents};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod common;
+
+use common::default_layer_config;
+
+/// Dossier d'archives contenant les archives 2A de `tests/res`, sauf l'archive RPG,
+/// remplacée par une archive dont le shapefile des parcelles porte un autre nom.
+fn doctored_archive_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    let content_dir = dir.join("content").join("RPG");
+    fs::create_dir_all(&content_dir).unwrap();
+
+    fs::copy("tests/res/BDFORET_2A.7z", dir.join("BDFORET_2A.7z")).unwrap();
+    for extension in ["shp", "shx", "dbf", "prj"] {
+        fs::write(
+            content_dir.join(format!("PARCELLES_GRAPHIQUES_2024.{}", extension)),
+            b"",
+        )
+        .unwrap();
+    }
+    let status = Command::new("7z")
+        .arg("a")
+        .arg(dir.join("RPG_2A.7z"))
+        .arg(&content_dir)
+        .output()
+        .expect("7z doit être installé");
+    assert!(status.status.success());
+    dir
+}
+
+#[test]
+fn test_parse_7z_listing() {
+    let listing = "\
+7-Zip [64] 16.02 : Copyright (c) 1999-2016 Igor Pavlov : 2016-05-21
+
+Listing archive: RPG_2A.7z
+
+--
+Path = RPG_2A.7z
+Type = 7z
+Physical Size = 17894162
+
+----------
+Path = RPG/PARCELLES_GRAPHIQUES.shp
+Size = 1024
+Attributes = A
+
+Path = RPG
+Size = 0
+Attributes = D
+";
+
+    assert_eq!(
+        parse_7z_listing(listing),
+        vec!["RPG/PARCELLES_GRAPHIQUES.shp", "RPG"]
+    );
+    assert!(parse_7z_listing("").is_empty());
+}
+
+#[test]
+fn test_missing_basenames() {
+    let entries = vec![
+        "BDTOPO/TRANSPORT/TRONCON_DE_ROUTE.shp".to_string(),
+        "BDTOPO\\BATI\\BATIMENT.dbf".to_string(),
+        "BDTOPO/HYDROGRAPHIE".to_string(),
+    ];
+
+    assert_eq!(
+        missing_basenames(
+            &entries,
+            &["TRONCON_DE_ROUTE", "BATIMENT", "COURS_D_EAU", "HYDRO"]
+        ),
+        vec!["COURS_D_EAU", "HYDRO"]
+    );
+}
+
+#[test]
+fn test_archive_layers_groups_topo_files_by_archive() {
+    let config = default_layer_config();
+    let layers = archive_layers("2A", &config);
+
+    assert_eq!(layers[0].archive, "BDFORET_2A.7z");
+    assert!(layers[0].required);
+    assert_eq!(layers[1].archive, "RPG_2A.7z");
+    assert!(layers[1].required);
+    assert!(
+        layers[2..]
+            .iter()
+            .all(|layer| layer.archive == "BDTOPO_2A.7z" && !layer.required)
+    );
+    assert!(layers[2].files.contains(&"BATIMENT".to_string()));
+}
+
+#[test]
+fn test_probe_fixture_archive() {
+    let entries = probe_archive_contents("tests/res/RPG_2A.7z").unwrap();
+
+    assert!(missing_basenames(&entries, &["PARCELLES_GRAPHIQUES"]).is_empty());
+    assert!(probe_archive_contents("tests/res/ABSENTE.7z").is_err());
+}
+
+#[test]
+fn test_check_archive_layers_fixtures() {
+    let result = check_archive_layers(
+        Path::new("tests/res"),
+        &["2A".to_string()],
+        &default_layer_config(),
+    );
+
+    assert!(result.is_ok(), "{:?}", result);
+}
+
+#[test]
+fn test_check_archive_layers_reports_missing_layer() {
+    let dir = doctored_archive_dir("firefront_doctored_archives_2a");
+
+    let error = check_archive_layers(&dir, &["2A".to_string()], &LayerConfig::default())
+        .expect_err("L'archive RPG modifiée ne contient pas les parcelles");
+
+    assert!(
+        error.contains("RPG_2A.7z : PARCELLES_GRAPHIQUES"),
+        "{}",
+        error
+    );
+    assert!(!error.contains("BDFORET_2A.7z"), "{}", error);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_check_archive_layers_collects_all_regions() {
+    let dir = doctored_archive_dir("firefront_doctored_archives_2a_2b");
+
+    let error = check_archive_layers(
+        &dir,
+        &["2A".to_string(), "2B".to_string()],
+        &LayerConfig::default(),
+    )
+    .unwrap_err();
+
+    assert!(
+        error.contains("RPG_2A.7z : PARCELLES_GRAPHIQUES"),
+        "{}",
+        error
+    );
+    assert!(error.contains("BDFORET_2B.7z"), "{}", error);
+    assert!(error.contains("RPG_2B.7z"), "{}", error);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::batch::{
+    BatchStatus, parse_batch_csv, parse_batch_file, parse_batch_geojson, run_batch, validate_batch,
+};
+use firefront_gis_lib::busy::BusyProjects;
+use firefront_gis_lib::events::BatchProgress;
+use firefront_gis_lib::fetcher::FixtureFetcher;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::utils::{BoundingBox, PROJECT_COMPLETE_MARKER, WorkDir, project_dir};
+use std::fs;
+use std::sync::Mutex;
+
+#[test]
+fn test_parse_batch_csv() {
+    let text = "name;xmin;ymin;xmax;ymax\n\
+                # commentaire\n\
+                sud; 1210000; 6070000; 1235000; 6095000\n\
+                \n\
+                nord;1210000;6095000;1235000\n\
+                est;1235000;abc;1260000;6095000\n";
+    let definitions = parse_batch_csv(text);
+    assert_eq!(definitions.len(), 3);
+
+    assert_eq!(definitions[0].index, 1);
+    assert_eq!(definitions[0].name, "sud");
+    assert_eq!(
+        definitions[0].project_bb,
+        Ok(BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6095000.0))
+    );
+    assert!(definitions[1].project_bb.is_err());
+    assert_eq!(definitions[2].name, "est");
+    assert!(definitions[2].project_bb.is_err());
+
+    let definitions = parse_batch_csv("a,0,0,5000,5000");
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(
+        definitions[0].project_bb,
+        Ok(BoundingBox::new(0.0, 0.0, 5000.0, 5000.0))
+    );
+}
+
+#[test]
+fn test_parse_batch_geojson_uses_bbox_or_geometry_extent() {
+    let text = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "bbox": [1210000, 6070000, 1235000, 6095000],
+                "properties": { "name": "avec-bbox" },
+                "geometry": null
+            },
+            {
+                "type": "Feature",
+                "properties": { "name": "polygone" },
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[1210000, 6070000], [1215000, 6072000],
+                                     [1212000, 6075000], [1210000, 6070000]]]
+                }
+            },
+            {
+                "type": "Feature",
+                "properties": {},
+                "geometry": null
+            }
+        ]
+    }"#;
+    let definitions = parse_batch_geojson(text).unwrap();
+    assert_eq!(definitions.len(), 3);
+    assert_eq!(definitions[0].name, "avec-bbox");
+    assert_eq!(
+        definitions[0].project_bb,
+        Ok(BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6095000.0))
+    );
+    assert_eq!(
+        definitions[1].project_bb,
+        Ok(BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0))
+    );
+    assert_eq!(definitions[2].name, "");
+    assert!(definitions[2].project_bb.is_err());
+
+    assert!(parse_batch_geojson(r#"{"type": "Point", "coordinates": [0, 0]}"#).is_err());
+}
+
+#[test]
+fn test_parse_batch_file_rejects_unknown_format() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_batch_format").unwrap();
+    let path = work_dir.path().join("lot.xlsx");
+    fs::write(&path, "a,0,0,5000,5000").unwrap();
+    assert!(parse_batch_file(&path).is_err());
+    assert!(parse_batch_file(&work_dir.path().join("absent.csv")).is_err());
+}
+
+#[test]
+fn test_validate_batch() {
+    let text = "valide,1210000,6070000,1235000,6095000\n\
+                bad/name,1210000,6070000,1235000,6095000\n\
+                VALIDE,1235000,6070000,1260000,6095000\n\
+                inversee,1235000,6070000,1210000,6095000\n\
+                decalee,1210000,6070000,1217000,6095000\n\
+                colonnes,1210000\n";
+    let (entries, rejected) = validate_batch(&parse_batch_csv(text), 10.0);
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "valide");
+    assert_eq!(
+        rejected
+            .iter()
+            .map(|result| result.index)
+            .collect::<Vec<_>>(),
+        [2, 3, 4, 5, 6]
+    );
+    assert!(
+        rejected
+            .iter()
+            .all(|result| result.status == BatchStatus::Invalid && result.message.is_some())
+    );
+    assert!(rejected[1].message.as_ref().unwrap().contains("entrée 1"));
+}
+
+#[tokio::test]
+async fn test_run_batch_with_invalid_entry() {
+    let name = "batch-porto-vecchio";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_batch_run").unwrap();
+    let path = work_dir.path().join("lot.csv");
+    let bb = get_test_bounding_box();
+    fs::write(
+        &path,
+        format!(
+            "name,xmin,ymin,xmax,ymax\n{},{},{},{},{}\nbatch-inverse,{},{},{},{}\n",
+            name, bb.xmin, bb.ymin, bb.xmax, bb.ymax, bb.xmax, bb.ymin, bb.xmin, bb.ymax
+        ),
+    )
+    .unwrap();
+
+    let definitions = parse_batch_file(&path).unwrap();
+    let (entries, mut results) = validate_batch(&definitions, 10.0);
+    assert_eq!(entries.len(), 1);
+
+    let progress: Mutex<Vec<BatchProgress>> = Mutex::new(Vec::new());
+    let on_progress = |update: BatchProgress| progress.lock().unwrap().push(update);
+    results.extend(
+        run_batch(
+            &entries,
+            definitions.len(),
+            &VegetationSource::BdForet,
+            &BusyProjects::default(),
+            &FixtureFetcher::new("tests/res"),
+            &on_progress,
+        )
+        .await,
+    );
+    results.sort_by_key(|result| result.index);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].status, BatchStatus::Created, "{:?}", results[0]);
+    assert!(project_dir(name).join(PROJECT_COMPLETE_MARKER).exists());
+    assert_eq!(results[1].name, "batch-inverse");
+    assert_eq!(results[1].status, BatchStatus::Invalid);
+    assert!(results[1].message.is_some());
+
+    let progress = progress.into_inner().unwrap();
+    assert!(!progress.is_empty());
+    assert!(
+        progress
+            .iter()
+            .all(|update| update.index == 1 && update.total == 2 && update.name == name)
+    );
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
@@ -0,0 +1,277 @@
+use firefront_gis_lib::app_setup::{CONFIG, Config, SettingsUpdate};
+use firefront_gis_lib::busy::{BusyKind, BusyProjects};
+use firefront_gis_lib::gis_operation::layer_config::LayerConfig;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::pipeline::{
+    PipelineContext, PipelineState, Stage, StageRunner, run_pipeline,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+
+/// Étapes simulées qui durent chacune un moment, comme une création réelle.
+struct SlowStages {
+    executed: Vec<Stage>,
+}
+
+impl StageRunner for SlowStages {
+    async fn run_stage(&mut self, stage: Stage, _ctx: &mut PipelineContext) -> Result<(), String> {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        self.executed.push(stage);
+        Ok(())
+    }
+}
+
+/// Étapes simulées qui relèvent la résolution utilisée par chacune d'elles.
+struct RecordingStages {
+    resolutions: Vec<f64>,
+}
+
+impl StageRunner for RecordingStages {
+    async fn run_stage(&mut self, _stage: Stage, ctx: &mut PipelineContext) -> Result<(), String> {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        self.resolutions.push(ctx.run_config.resolution);
+        Ok(())
+    }
+}
+
+fn create_busy_folder(name: &str) -> PathBuf {
+    let folder = PathBuf::from(format!("tests/tmp_busy_{}", name));
+    if folder.exists() {
+        fs::remove_dir_all(&folder).unwrap();
+    }
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+#[tokio::test]
+async fn test_busy_project_deletion_refused_until_job_finishes() {
+    let folder = create_busy_folder("export");
+    let busy = BusyProjects::default();
+
+    // Export simulé : le projet reste occupé tant que la marque n'est pas rendue.
+    let guard = busy.acquire("busy-export", BusyKind::Exporting).unwrap();
+    let job = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        drop(guard);
+    });
+
+    assert_eq!(busy.kind("busy-export"), Some(BusyKind::Exporting));
+    let refused = busy
+        .reserve_for_deletion("busy-export", false, Duration::from_secs(5))
+        .await;
+    let message = refused.expect_err("La suppression d'un projet occupé doit être refusée");
+    assert!(message.contains("busy-export"), "{}", message);
+    assert!(folder.exists());
+
+    job.join().unwrap();
+    assert!(busy.busy_projects().is_empty());
+    busy.reserve_for_deletion("busy-export", false, Duration::from_secs(5))
+        .await
+        .unwrap();
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[tokio::test]
+async fn test_forced_deletion_cancels_creation() {
+    let folder = create_busy_folder("creation");
+    let state = PipelineState::new(
+        "busy-creation",
+        &common::get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.save(&folder).unwrap();
+
+    let busy = BusyProjects::default();
+    let guard = busy.acquire("busy-creation", BusyKind::Creating).unwrap();
+    let mut ctx = PipelineContext::new(folder.clone(), state, LayerConfig::default());
+    ctx.cancel = guard.cancel_token();
+    let mut stages = SlowStages {
+        executed: Vec::new(),
+    };
+
+    let creation = async {
+        let result = run_pipeline(&mut stages, &mut ctx).await;
+        drop(guard);
+        result
+    };
+    let deletion = async {
+        tokio::time::sleep(Duration::from_millis(75)).await;
+        busy.reserve_for_deletion("busy-creation", true, Duration::from_secs(5))
+            .await
+    };
+    let (created, released) = tokio::join!(creation, deletion);
+
+    assert!(released.is_ok(), "{:?}", released);
+    assert!(created.is_err(), "La création doit être annulée");
+    assert!(
+        stages.executed.len() < Stage::ALL.len(),
+        "La création doit s'arrêter avant sa dernière étape"
+    );
+    assert!(!PipelineState::load(&folder).unwrap().is_finished());
+    // Le nom reste réservé pendant la suppression : aucune nouvelle création ne peut démarrer.
+    assert_eq!(busy.kind("busy-creation"), Some(BusyKind::Deleting));
+    assert!(busy.acquire("busy-creation", BusyKind::Creating).is_err());
+    drop(released);
+    assert!(busy.kind("busy-creation").is_none());
+
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn test_project_cannot_be_acquired_twice() {
+    let busy = BusyProjects::default();
+    let guard = busy.acquire("projet-a", BusyKind::Creating).unwrap();
+    assert!(busy.acquire("projet-a", BusyKind::Exporting).is_err());
+    let _other = busy.acquire("projet-b", BusyKind::RefreshingOrtho).unwrap();
+
+    let projects = busy.busy_projects();
+    assert_eq!(projects.get("projet-a"), Some(&BusyKind::Creating));
+    assert_eq!(projects.get("projet-b"), Some(&BusyKind::RefreshingOrtho));
+    assert!(!busy.request_cancel("inconnu"));
+
+    drop(guard);
+    assert!(busy.acquire("projet-a", BusyKind::Exporting).is_ok());
+}
+
+/// Création simulée comme dans `create_project_com` : le projet est réservé avant toute
+/// écriture, puis les étapes s'exécutent tant que la marque est tenue.
+async fn guarded_creation(
+    busy: &BusyProjects,
+    name: &str,
+    folder: &PathBuf,
+) -> Result<Vec<Stage>, BusyKind> {
+    let guard = busy.try_acquire(name, BusyKind::Creating)?;
+    let state = PipelineState::new(
+        name,
+        &common::get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.save(folder).unwrap();
+    let mut ctx = PipelineContext::new(folder.clone(), state, LayerConfig::default());
+    ctx.cancel = guard.cancel_token();
+    let mut stages = SlowStages {
+        executed: Vec::new(),
+    };
+    run_pipeline(&mut stages, &mut ctx).await.unwrap();
+    Ok(stages.executed)
+}
+
+#[tokio::test]
+async fn test_duplicate_creation_request_is_rejected() {
+    let folder = create_busy_folder("duplicate");
+    let busy = BusyProjects::default();
+
+    let first = guarded_creation(&busy, "busy-duplicate", &folder);
+    let second = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        guarded_creation(&busy, "busy-duplicate", &folder).await
+    };
+    let (first, second) = tokio::join!(first, second);
+
+    assert_eq!(first.unwrap(), Stage::ALL.to_vec());
+    assert_eq!(second, Err(BusyKind::Creating));
+    assert!(PipelineState::load(&folder).unwrap().is_finished());
+    assert!(busy.kind("busy-duplicate").is_none());
+
+    // La marque rendue, une nouvelle demande est acceptée.
+    assert!(
+        busy.try_acquire("busy-duplicate", BusyKind::Creating)
+            .is_ok()
+    );
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn test_busy_guard_released_after_panic() {
+    let busy = BusyProjects::default();
+    let guard = busy.try_acquire("busy-panic", BusyKind::Creating).unwrap();
+    let job = thread::spawn(move || {
+        let _guard = guard;
+        panic!("étape interrompue");
+    });
+
+    assert!(job.join().is_err());
+    assert!(busy.kind("busy-panic").is_none());
+}
+
+#[tokio::test]
+async fn test_settings_change_does_not_affect_running_creation() {
+    let folder = create_busy_folder("settings");
+    let state = PipelineState::new(
+        "busy-settings",
+        &common::get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.save(&folder).unwrap();
+
+    let busy = BusyProjects::default();
+    let guard = busy.acquire("busy-settings", BusyKind::Creating).unwrap();
+    let original_resolution = CONFIG.lock().unwrap().resolution;
+    let mut ctx = PipelineContext::new(folder.clone(), state, LayerConfig::default());
+    let mut stages = RecordingStages {
+        resolutions: Vec::new(),
+    };
+
+    let creation = async {
+        let result = run_pipeline(&mut stages, &mut ctx).await;
+        drop(guard);
+        result
+    };
+    let settings = async {
+        tokio::time::sleep(Duration::from_millis(75)).await;
+        // Un projet étant occupé, la résolution est refusée et les autres paramètres appliqués.
+        let mut config = Config::default();
+        let blocked = config
+            .apply_settings(
+                SettingsUpdate {
+                    resolution: Some(config.resolution + 5.0),
+                    log_level: Some("debug".to_string()),
+                    ..SettingsUpdate::default()
+                },
+                !busy.busy_projects().is_empty(),
+            )
+            .unwrap();
+        assert_eq!(blocked, vec!["resolution".to_string()]);
+        assert_eq!(config.resolution, Config::default().resolution);
+        assert_eq!(config.log_level, "debug");
+
+        // Même modifiée directement, la configuration n'atteint pas la création en cours.
+        CONFIG.lock().unwrap().resolution = original_resolution + 5.0;
+    };
+    let (created, ()) = tokio::join!(creation, settings);
+    CONFIG.lock().unwrap().resolution = original_resolution;
+
+    assert!(created.is_ok(), "{:?}", created);
+    assert_eq!(stages.resolutions.len(), Stage::ALL.len());
+    assert!(
+        stages
+            .resolutions
+            .iter()
+            .all(|resolution| *resolution == original_resolution),
+        "{:?}",
+        stages.resolutions
+    );
+
+    // Sans projet occupé, la résolution est appliquée.
+    let mut config = Config::default();
+    let blocked = config
+        .apply_settings(
+            SettingsUpdate {
+                resolution: Some(5.0),
+                ..SettingsUpdate::default()
+            },
+            !busy.busy_projects().is_empty(),
+        )
+        .unwrap();
+    assert!(blocked.is_empty());
+    assert_eq!(config.resolution, 5.0);
+
+    fs::remove_dir_all(&folder).unwrap();
+}
@@ -0,0 +1,111 @@
+use firefront_gis_lib::utils::{
+    CACHE_USAGE_FILE, enforce_cache_limit_in, list_cache_entries, pin_cache_entries,
+    touch_cache_entry,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+mod common;
+
+/// Crée un cache de test contenant des archives factices de 100 octets,
+/// avec leur date de dernière utilisation (en millisecondes).
+fn create_test_cache(name: &str, archives: &[(&str, u64)]) -> PathBuf {
+    let cache = PathBuf::from(format!("tests/tmp_cache_{}", name));
+    if cache.exists() {
+        fs::remove_dir_all(&cache).unwrap();
+    }
+    fs::create_dir_all(&cache).unwrap();
+
+    let mut usage = HashMap::new();
+    for (archive, last_used) in archives {
+        fs::write(cache.join(archive), vec![0u8; 100]).unwrap();
+        usage.insert(archive.to_string(), *last_used);
+    }
+    fs::write(
+        cache.join(CACHE_USAGE_FILE),
+        serde_json::to_string(&usage).unwrap(),
+    )
+    .unwrap();
+    cache
+}
+
+#[test]
+fn test_cache_eviction_order() {
+    let cache = create_test_cache(
+        "lru",
+        &[
+            ("BDTOPO_2A.7z", 1_000),
+            ("BDFORET_2A.7z", 3_000),
+            ("RPG_2A.7z", 2_000),
+        ],
+    );
+    fs::write(cache.join("BDTOPO_2B.7z.part"), vec![0u8; 500]).unwrap();
+
+    let entries = list_cache_entries(&cache).unwrap();
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, vec!["BDTOPO_2A.7z", "RPG_2A.7z", "BDFORET_2A.7z"]);
+
+    assert!(enforce_cache_limit_in(&cache, 300).unwrap().is_empty());
+
+    let evicted = enforce_cache_limit_in(&cache, 150).unwrap();
+    assert_eq!(evicted, vec!["BDTOPO_2A.7z", "RPG_2A.7z"]);
+    assert!(cache.join("BDFORET_2A.7z").exists());
+    assert!(!cache.join("BDTOPO_2A.7z").exists());
+    assert!(
+        cache.join("BDTOPO_2B.7z.part").exists(),
+        "Seules les archives complètes sont évincées"
+    );
+
+    let usage: HashMap<String, u64> =
+        serde_json::from_str(&fs::read_to_string(cache.join(CACHE_USAGE_FILE)).unwrap()).unwrap();
+    assert_eq!(usage.keys().collect::<Vec<_>>(), vec!["BDFORET_2A.7z"]);
+
+    fs::remove_dir_all(&cache).unwrap();
+}
+
+#[test]
+fn test_pinned_archives_are_never_evicted() {
+    let cache = create_test_cache(
+        "pinned",
+        &[
+            ("BDTOPO_2A.7z", 1_000),
+            ("BDFORET_2A.7z", 3_000),
+            ("RPG_2A.7z", 2_000),
+        ],
+    );
+
+    let pin = pin_cache_entries(&[cache.join("BDTOPO_2A.7z")]);
+    assert!(list_cache_entries(&cache).unwrap()[0].pinned);
+
+    let evicted = enforce_cache_limit_in(&cache, 150).unwrap();
+    assert_eq!(evicted, vec!["RPG_2A.7z", "BDFORET_2A.7z"]);
+    assert!(cache.join("BDTOPO_2A.7z").exists());
+
+    // Même une limite nulle ne peut pas évincer une archive épinglée.
+    assert!(enforce_cache_limit_in(&cache, 0).unwrap().is_empty());
+
+    drop(pin);
+    assert_eq!(
+        enforce_cache_limit_in(&cache, 0).unwrap(),
+        vec!["BDTOPO_2A.7z"]
+    );
+
+    fs::remove_dir_all(&cache).unwrap();
+}
+
+#[test]
+fn test_touch_marks_archive_as_most_recent() {
+    let cache = create_test_cache("touch", &[("BDTOPO_2A.7z", 1_000), ("RPG_2A.7z", 2_000)]);
+
+    touch_cache_entry(&cache.join("BDTOPO_2A.7z")).unwrap();
+
+    let evicted = enforce_cache_limit_in(&cache, 100).unwrap();
+    assert_eq!(evicted, vec!["RPG_2A.7z"]);
+    common::assert_file_exists(
+        cache.join("BDTOPO_2A.7z").to_str().unwrap(),
+        "L'archive utilisée récemment doit être conservée",
+    );
+
+    fs::remove_dir_all(&cache).unwrap();
+}
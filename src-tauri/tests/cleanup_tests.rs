@@ -0,0 +1,265 @@
+use firefront_gis_lib::busy::BusyKind;
+use firefront_gis_lib::gis_operation::ortho::{WMS_CACHE_DIR, WMS_CACHE_MAX_SIZE};
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::pipeline::{PipelineState, PreparedRegion, STAGING_DIR, Stage};
+use firefront_gis_lib::utils::{
+    BoundingBox, CacheScope, CleanupReport, INSTANCE_LOCK_FILE, clear_cache_scope_in,
+    startup_cleanup, try_lock_instance,
+};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+mod common;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Crée un dossier de test vide contenant `tmp`, `cache` et `projects`.
+fn create_test_tree(name: &str) -> PathBuf {
+    let root = PathBuf::from(format!("tests/tmp_cleanup_{}", name));
+    if root.exists() {
+        fs::remove_dir_all(&root).unwrap();
+    }
+    for dir in ["tmp", "cache", "projects"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    root
+}
+
+/// Crée un fichier de `size` octets (creux) modifié il y a `age`.
+fn create_file(path: &Path, size: u64, age: Duration) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let file = File::create(path).unwrap();
+    file.set_len(size).unwrap();
+    file.set_modified(SystemTime::now() - age).unwrap();
+}
+
+/// Change la date de modification d'un dossier.
+fn set_dir_age(path: &Path, age: Duration) {
+    File::open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() - age)
+        .unwrap();
+}
+
+#[test]
+fn test_startup_cleanup_removes_only_stale_data() {
+    let root = create_test_tree("selective");
+    let (tmp, cache, projects) = (root.join("tmp"), root.join("cache"), root.join("projects"));
+
+    create_file(&tmp.join(INSTANCE_LOCK_FILE), 0, 48 * HOUR);
+    create_file(&tmp.join("old_export/raster.tif"), 100, 48 * HOUR);
+    set_dir_age(&tmp.join("old_export"), 48 * HOUR);
+    create_file(&tmp.join("recent_export/raster.tif"), 100, Duration::ZERO);
+    create_file(&cache.join("BDTOPO_2A.7z"), 100, 48 * HOUR);
+    create_file(&cache.join("BDFORET_2A.7z.part"), 100, 48 * HOUR);
+    create_file(&cache.join("RPG_2A.7z.part"), 100, Duration::ZERO);
+    create_file(&projects.join("corse/corse.tif"), 100, 48 * HOUR);
+
+    let report = startup_cleanup(&tmp, &cache, HOUR);
+
+    assert_eq!(report.removed, 2);
+    assert_eq!(report.freed_bytes, 200);
+    assert!(!tmp.join("old_export").exists());
+    assert!(!cache.join("BDFORET_2A.7z.part").exists());
+    for kept in [
+        tmp.join(INSTANCE_LOCK_FILE),
+        tmp.join("recent_export/raster.tif"),
+        cache.join("BDTOPO_2A.7z"),
+        cache.join("RPG_2A.7z.part"),
+        projects.join("corse/corse.tif"),
+    ] {
+        assert!(kept.exists(), "{} should be kept", kept.display());
+    }
+
+    let report = startup_cleanup(&tmp, &cache, Duration::ZERO);
+    assert!(!tmp.join("recent_export").exists());
+    assert!(tmp.join(INSTANCE_LOCK_FILE).exists());
+    assert_eq!(report.removed, 1);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_startup_cleanup_caps_wms_cache_oldest_first() {
+    let root = create_test_tree("wms_cache");
+    let (tmp, cache) = (root.join("tmp"), root.join("cache"));
+    let wms_cache = tmp.join("ortho_export").join(WMS_CACHE_DIR);
+    let tile_size = WMS_CACHE_MAX_SIZE / 2;
+
+    create_file(&wms_cache.join("0/oldest.jpg"), tile_size, 3 * HOUR);
+    create_file(&wms_cache.join("0/older.jpg"), tile_size, 2 * HOUR);
+    create_file(&wms_cache.join("1/newest.jpg"), tile_size, Duration::ZERO);
+
+    let report = startup_cleanup(&tmp, &cache, 24 * HOUR);
+
+    assert_eq!(report.removed, 1);
+    assert_eq!(report.freed_bytes, tile_size);
+    assert!(!wms_cache.join("0/oldest.jpg").exists());
+    assert!(wms_cache.join("0/older.jpg").exists());
+    assert!(wms_cache.join("1/newest.jpg").exists());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_instance_lock_is_exclusive() {
+    let root = create_test_tree("lock");
+    let tmp = root.join("tmp");
+
+    let lock = try_lock_instance(&tmp).unwrap();
+    assert!(lock.is_some(), "First instance should take the lock");
+    assert!(tmp.join(INSTANCE_LOCK_FILE).exists());
+    assert!(
+        try_lock_instance(&tmp).unwrap().is_none(),
+        "Second instance should not take a held lock"
+    );
+
+    drop(lock);
+    assert!(
+        try_lock_instance(&tmp).unwrap().is_some(),
+        "Lock should be available once released"
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+/// Crée une arborescence couvrant chaque périmètre de `clear_cache` (fichiers de 100 octets).
+fn create_cache_tree(name: &str) -> PathBuf {
+    let root = create_test_tree(name);
+    let (tmp, cache, projects) = (root.join("tmp"), root.join("cache"), root.join("projects"));
+
+    create_file(&cache.join("BDTOPO_2A.7z"), 100, Duration::ZERO);
+    create_file(&cache.join("RPG_2A.7z.part"), 100, Duration::ZERO);
+    create_file(&tmp.join(INSTANCE_LOCK_FILE), 0, Duration::ZERO);
+    create_file(
+        &tmp.join(WMS_CACHE_DIR).join("0/tile.jpg"),
+        100,
+        Duration::ZERO,
+    );
+    create_file(
+        &tmp.join("refresh_ortho")
+            .join(WMS_CACHE_DIR)
+            .join("0/tile.jpg"),
+        100,
+        Duration::ZERO,
+    );
+    create_file(
+        &tmp.join("refresh_ortho/satellite_temp.tif"),
+        100,
+        Duration::ZERO,
+    );
+
+    let interrupted = projects.join("interrupted");
+    create_file(
+        &interrupted.join(STAGING_DIR).join("2A_region.gpkg"),
+        100,
+        Duration::ZERO,
+    );
+    create_file(&interrupted.join("interrupted.tiff"), 100, Duration::ZERO);
+    let mut state = PipelineState::new(
+        "interrupted",
+        &BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.mark_completed(Stage::Download);
+    state.mark_completed(Stage::Prepare);
+    state.prepared.push(PreparedRegion::default());
+    state.save(&interrupted).unwrap();
+    create_file(
+        &projects.join("complete/resources/BATIMENT.gpkg"),
+        100,
+        Duration::ZERO,
+    );
+    root
+}
+
+fn clear_scope(root: &Path, scope: CacheScope) -> CleanupReport {
+    clear_cache_scope_in(
+        scope,
+        &root.join("cache"),
+        &root.join("tmp"),
+        &root.join("projects"),
+    )
+}
+
+#[test]
+fn test_clear_archives_scope() {
+    let root = create_cache_tree("scope_archives");
+
+    let report = clear_scope(&root, CacheScope::Archives);
+
+    assert_eq!(report.removed, 2);
+    assert_eq!(report.freed_bytes, 200);
+    assert!(root.join("cache").is_dir());
+    assert_eq!(fs::read_dir(root.join("cache")).unwrap().count(), 0);
+    assert!(root.join("tmp").join(WMS_CACHE_DIR).exists());
+    assert!(root.join("projects/interrupted").join(STAGING_DIR).exists());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_clear_prepared_scope_rewinds_interrupted_creation() {
+    let root = create_cache_tree("scope_prepared");
+    let interrupted = root.join("projects/interrupted");
+
+    let report = clear_scope(&root, CacheScope::Prepared);
+
+    assert_eq!(report.removed, 1);
+    assert_eq!(report.freed_bytes, 100);
+    assert!(!interrupted.join(STAGING_DIR).exists());
+    assert!(interrupted.join("interrupted.tiff").exists());
+    assert!(
+        root.join("projects/complete/resources/BATIMENT.gpkg")
+            .exists()
+    );
+    let state = PipelineState::load(&interrupted).unwrap();
+    assert_eq!(state.next_stage(), Some(Stage::Prepare));
+    assert!(state.prepared.is_empty());
+    assert!(root.join("cache/BDTOPO_2A.7z").exists());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_clear_wms_scope() {
+    let root = create_cache_tree("scope_wms");
+    let tmp = root.join("tmp");
+
+    let report = clear_scope(&root, CacheScope::Wms);
+
+    assert_eq!(report.removed, 2);
+    assert_eq!(report.freed_bytes, 200);
+    assert!(!tmp.join(WMS_CACHE_DIR).exists());
+    assert!(!tmp.join("refresh_ortho").join(WMS_CACHE_DIR).exists());
+    assert!(tmp.join("refresh_ortho/satellite_temp.tif").exists());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_clear_tmp_scope_keeps_instance_lock() {
+    let root = create_cache_tree("scope_tmp");
+    let tmp = root.join("tmp");
+
+    let report = clear_scope(&root, CacheScope::Tmp);
+
+    assert_eq!(report.removed, 2);
+    assert_eq!(report.freed_bytes, 300);
+    assert!(tmp.join(INSTANCE_LOCK_FILE).exists());
+    assert_eq!(fs::read_dir(&tmp).unwrap().count(), 1);
+    assert!(root.join("cache/BDTOPO_2A.7z").exists());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_cache_scopes_used_by_busy_tasks() {
+    assert!(CacheScope::Archives.used_by(BusyKind::Creating));
+    assert!(!CacheScope::Archives.used_by(BusyKind::Exporting));
+    assert!(CacheScope::Wms.used_by(BusyKind::RefreshingOrtho));
+    assert!(!CacheScope::Prepared.used_by(BusyKind::RefreshingOrtho));
+    assert!(CacheScope::Tmp.used_by(BusyKind::Exporting));
+}
@@ -1,8 +1,16 @@
+use firefront_gis_lib::gis_operation::layer_config::LayerConfig;
 use firefront_gis_lib::utils::BoundingBox;
 use gdal::Dataset;
 use std::fs;
 use std::path::Path;
 
+/// Configuration de couches fournie avec l'application (`resources/layer_sets.json`).
+#[allow(unused)]
+pub fn default_layer_config() -> LayerConfig {
+    LayerConfig::load_from(Path::new("resources/layer_sets.json"))
+        .expect("La configuration de couches fournie doit être valide")
+}
+
 #[allow(unused)]
 pub fn remove_file_if_exists(file_path: &str) {
     if Path::new(file_path).exists() {
@@ -84,3 +92,17 @@ pub fn get_test_bounding_box() -> BoundingBox {
         ymax: 6095000.0,
     }
 }
+
+#[allow(unused)]
+pub fn copy_dir_recursive(source: &Path, destination: &Path) {
+    fs::create_dir_all(destination).unwrap();
+    for entry in fs::read_dir(source).unwrap() {
+        let path = entry.unwrap().path();
+        let target = destination.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir_recursive(&path, &target);
+        } else {
+            fs::copy(&path, &target).unwrap();
+        }
+    }
+}
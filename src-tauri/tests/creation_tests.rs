@@ -0,0 +1,601 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::busy::CancelToken;
+use firefront_gis_lib::fetcher::{DataFetcher, FixtureFetcher};
+use firefront_gis_lib::gis_operation::colormap::{CLASS_RPG, load_rpg_classes};
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::gis_operation::processing::class_raster_path;
+use firefront_gis_lib::gis_operation::{clip_to_bb, create_project_in_crs, gpkg_extent};
+use firefront_gis_lib::pipeline::{
+    CreationOutcome, PIPELINE_STATE_FILE, PipelineState, create_project_pipeline,
+    create_project_reusing, creation_layer_config,
+};
+use firefront_gis_lib::progress::{NoProgress, ProgressCollector, ProgressSink};
+use firefront_gis_lib::utils::{
+    BoundingBox, CreationParams, DEFAULT_PROJECT_EPSG, DataVintage, PROJECT_COMPLETE_MARKER,
+    ProjectMetadata, WorkDir, copy_project, extract_files_by_name, generate_project_preview,
+    get_incomplete_projects, is_project_complete, load_project_metadata, mark_project_complete,
+    project_conflict, project_creation_params, project_dir, project_ortho_jpeg, project_tiff,
+    project_veget_jpeg, resource_gpkg, save_project_metadata,
+};
+use firefront_gis_lib::web_request::{
+    DepartmentUrls, DownloadJob, DownloadProgress, archive_cache_path,
+};
+use futures_util::future::BoxFuture;
+use gdal::Dataset;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[tokio::test]
+async fn test_create_project_pipeline_offline_from_fixtures() {
+    let name = "porto-vecchio-offline";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let fetcher = FixtureFetcher::new("tests/res");
+    let collector = ProgressCollector::default();
+    let mut warnings = Vec::new();
+
+    let outcome = create_project_pipeline(
+        name,
+        &get_test_bounding_box(),
+        &creation_layer_config(VegetationSource::BdForet, None).unwrap(),
+        CancelToken::default(),
+        &mut warnings,
+        &collector,
+        &fetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&outcome, "Offline project creation failed");
+    match outcome.unwrap() {
+        CreationOutcome::Created { regions, .. } => assert_eq!(regions, vec!["2A".to_string()]),
+        CreationOutcome::Cancelled => panic!("Creation should not be cancelled"),
+    }
+
+    let mut stages: Vec<String> = Vec::new();
+    for report in collector.reports() {
+        if stages.last() != Some(&report.stage) {
+            stages.push(report.stage);
+        }
+    }
+    assert_eq!(
+        stages,
+        [
+            "stage.search_files",
+            "stage.init",
+            "stage.download",
+            "stage.prepare",
+            "stage.fuse",
+            "stage.add_layers",
+            "stage.finalize",
+            "stage.cleanup",
+        ]
+    );
+
+    let folder = project_dir(name);
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+    let tiff = project_tiff(name);
+    assert_file_exists(&tiff.to_string_lossy(), "Project raster was not created");
+    assert_eq!(Dataset::open(&tiff).unwrap().raster_count(), 4);
+    for jpeg in [project_veget_jpeg(name), project_ortho_jpeg(name)] {
+        let jpeg = jpeg.to_string_lossy().to_string();
+        assert_file_exists(&jpeg, &format!("{} was not created", jpeg));
+        check_jpeg_properties(&jpeg, 10.0, &jpeg);
+    }
+    for layer_name in [name, "FORMATION_VEGETALE", "PARCELLES_GRAPHIQUES"] {
+        assert_file_exists(
+            &resource_gpkg(&folder, layer_name).to_string_lossy(),
+            &format!("Resource {} was not created", layer_name),
+        );
+    }
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[tokio::test]
+async fn test_fixture_fetcher_reports_missing_archives() {
+    let fetcher = FixtureFetcher::new("tests/res");
+    let codes = vec!["2A".to_string(), "2B".to_string()];
+
+    let departments = fetcher.resolve_urls(&codes).await;
+
+    assert!(departments[0].archives.is_ok());
+    let error = departments[1].archives.as_ref().unwrap_err();
+    assert!(error.contains("BDTOPO"), "{}", error);
+}
+
+/// Archives locales, en conservant les départements recherchés et les types d'archives
+/// demandés au téléchargement.
+struct RecordingFetcher {
+    fixtures: FixtureFetcher,
+    resolved: Mutex<Vec<String>>,
+    requested: Mutex<Vec<String>>,
+}
+
+impl RecordingFetcher {
+    fn new() -> Self {
+        RecordingFetcher {
+            fixtures: FixtureFetcher::new("tests/res"),
+            resolved: Mutex::new(Vec::new()),
+            requested: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl DataFetcher for RecordingFetcher {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        self.resolved.lock().unwrap().extend(codes.iter().cloned());
+        self.fixtures.resolve_urls(codes)
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+        on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        self.requested
+            .lock()
+            .unwrap()
+            .extend(jobs.iter().map(|job| job.file_type.clone()));
+        self.fixtures.download_archives(jobs, on_progress)
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        self.fixtures.download_replacements(jobs)
+    }
+
+    fn download_ortho(
+        &self,
+        run_config: &RunConfig,
+        output_jpg_path: &str,
+        project_bb: &BoundingBox,
+        epsg: u32,
+        regional_gpkg: Option<&str>,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        self.fixtures.download_ortho(
+            run_config,
+            output_jpg_path,
+            project_bb,
+            epsg,
+            regional_gpkg,
+            progress,
+        )
+    }
+}
+
+#[tokio::test]
+async fn test_create_project_with_selected_layers_skips_rpg() {
+    let name = "porto-vecchio-selected-layers";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let fetcher = RecordingFetcher::new();
+    let layers = vec![
+        "TRONCON_DE_ROUTE".to_string(),
+        "FORMATION_VEGETALE".to_string(),
+    ];
+    let mut warnings = Vec::new();
+
+    let outcome = create_project_pipeline(
+        name,
+        &get_test_bounding_box(),
+        &creation_layer_config(VegetationSource::BdForet, Some(&layers)).unwrap(),
+        CancelToken::default(),
+        &mut warnings,
+        &NoProgress,
+        &fetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&outcome, "Creation with selected layers failed");
+
+    let requested = fetcher.requested.lock().unwrap().clone();
+    assert!(!requested.contains(&"RPG".to_string()), "{:?}", requested);
+    assert!(
+        requested.contains(&"BDFORET".to_string()),
+        "{:?}",
+        requested
+    );
+    assert!(requested.contains(&"BDTOPO".to_string()), "{:?}", requested);
+
+    let folder = project_dir(name);
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+    assert_eq!(
+        load_project_metadata(name).layers,
+        Some(vec![
+            "FORMATION_VEGETALE".to_string(),
+            "TRONCON_DE_ROUTE".to_string(),
+        ])
+    );
+    assert!(resource_gpkg(&folder, "TRONCON_DE_ROUTE").exists());
+    assert!(!resource_gpkg(&folder, "PARCELLES_GRAPHIQUES").exists());
+    assert!(!resource_gpkg(&folder, "BATIMENT").exists());
+
+    let tiff = project_tiff(name);
+    let classes = Dataset::open(class_raster_path(&tiff.to_string_lossy())).unwrap();
+    let size = classes.raster_size();
+    let codes = classes
+        .rasterband(1)
+        .unwrap()
+        .read_as::<u8>((0, 0), size, size, None)
+        .unwrap();
+    assert!(!codes.data().contains(&CLASS_RPG));
+
+    let rpg_colors: Vec<[u8; 3]> = load_rpg_classes()
+        .unwrap()
+        .classes
+        .iter()
+        .map(|class| class.color)
+        .collect();
+    let project = Dataset::open(&tiff).unwrap();
+    let bands: Vec<Vec<u8>> = (1..=3)
+        .map(|band_idx| {
+            project
+                .rasterband(band_idx)
+                .unwrap()
+                .read_as::<u8>((0, 0), size, size, None)
+                .unwrap()
+                .data()
+                .to_vec()
+        })
+        .collect();
+    for pixel in 0..size.0 * size.1 {
+        let color = [bands[0][pixel], bands[1][pixel], bands[2][pixel]];
+        assert!(
+            !rpg_colors.contains(&color),
+            "RPG color {:?} found at pixel {}",
+            color,
+            pixel
+        );
+    }
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[tokio::test]
+async fn test_templated_creation_uses_source_layer_selection() {
+    let source = "porto-vecchio-template-source";
+    let name = "porto-vecchio-templated";
+    for project in [source, name] {
+        let _ = fs::remove_dir_all(project_dir(project));
+    }
+    let fetcher = FixtureFetcher::new("tests/res");
+    let layers = vec!["FORMATION_VEGETALE".to_string()];
+
+    let outcome = create_project_pipeline(
+        source,
+        &get_test_bounding_box(),
+        &creation_layer_config(VegetationSource::BdForet, Some(&layers)).unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &NoProgress,
+        &fetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&outcome, "Template source creation failed");
+    // Paramètres enregistrés par `create_project_com` après la création.
+    let mut metadata = load_project_metadata(source);
+    metadata.creation_params = Some(CreationParams {
+        vegetation_source: Some("bdforet".to_string()),
+        layers: Some(layers.clone()),
+        resolution: Some(10.0),
+        ortho_source: None,
+    });
+    save_project_metadata(source, &metadata).unwrap();
+
+    let params = CreationParams::default().or_template(&project_creation_params(source).unwrap());
+    assert_eq!(params.layers, Some(layers.clone()));
+    let vegetation_source =
+        VegetationSource::parse(params.vegetation_source.as_deref().unwrap_or_default()).unwrap();
+    let outcome = create_project_pipeline(
+        name,
+        &get_test_bounding_box(),
+        &creation_layer_config(vegetation_source, params.layers.as_deref()).unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &NoProgress,
+        &fetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&outcome, "Templated creation failed");
+
+    let folder = project_dir(name);
+    assert_eq!(load_project_metadata(name).layers, Some(layers));
+    assert!(resource_gpkg(&folder, "FORMATION_VEGETALE").exists());
+    assert!(!resource_gpkg(&folder, "PARCELLES_GRAPHIQUES").exists());
+
+    for project in [source, name] {
+        fs::remove_dir_all(project_dir(project)).unwrap();
+    }
+}
+
+/// Serveurs injoignables : la recherche des URLs échoue sur une erreur réseau,
+/// et tout téléchargement échoue.
+struct NetworkDownFetcher;
+
+impl DataFetcher for NetworkDownFetcher {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        Box::pin(async move {
+            codes
+                .iter()
+                .map(|code| DepartmentUrls {
+                    code: code.clone(),
+                    archives: Err(format!(
+                        "BDTOPO introuvable pour le département {}: connexion refusée",
+                        code
+                    )),
+                    network_error: true,
+                })
+                .collect()
+        })
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        _jobs: Vec<DownloadJob>,
+        _on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        Box::pin(async { Err("connexion refusée".to_string()) })
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        _jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        Box::pin(async { Err("connexion refusée".to_string()) })
+    }
+
+    fn download_ortho(
+        &self,
+        _run_config: &RunConfig,
+        _output_jpg_path: &str,
+        _project_bb: &BoundingBox,
+        _epsg: u32,
+        _regional_gpkg: Option<&str>,
+        _progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        Err("connexion refusée".to_string())
+    }
+}
+
+/// Copie dans le cache les archives de test de la Corse-du-Sud.
+#[tokio::test]
+async fn test_reusing_creation_downloads_only_uncovered_regions() {
+    // Projet source sur Cozzano (2A et 2B), étendu vers l'ouest, en Corse-du-Sud seulement :
+    // seules les archives de 2A sont téléchargées, la végétation de 2B vient du projet source.
+    let source = "cozzano-reuse-source";
+    let name = "cozzano-reuse-extended";
+    let source_bb = BoundingBox::new(1199000.0, 6105000.0, 1219000.0, 6120000.0);
+    let project_bb = BoundingBox::new(1189000.0, 6105000.0, 1219000.0, 6120000.0);
+    let layers = vec!["FORMATION_VEGETALE".to_string()];
+    for project in [source, name] {
+        let _ = fs::remove_dir_all(project_dir(project));
+    }
+
+    let run_config = RunConfig::snapshot();
+    let source_folder = project_dir(source);
+    fs::create_dir_all(source_folder.join("resources")).unwrap();
+    create_project_in_crs(
+        &project_tiff(source).to_string_lossy(),
+        &source_bb,
+        DEFAULT_PROJECT_EPSG,
+        &run_config,
+    )
+    .unwrap();
+    let work_dir = WorkDir::new(&run_config, "reuse_source").unwrap();
+    extract_files_by_name(
+        "tests/res/BDFORET_2B.7z",
+        "FORMATION_VEGETALE",
+        &work_dir.path().to_string_lossy(),
+    )
+    .unwrap();
+    clip_to_bb(
+        &work_dir.path_str("FORMATION_VEGETALE/FORMATION_VEGETALE.shp"),
+        &resource_gpkg(&source_folder, "FORMATION_VEGETALE").to_string_lossy(),
+        &source_bb,
+    )
+    .unwrap();
+    let source_vintage = DataVintage {
+        data_type: "BDFORET".to_string(),
+        region: "2B".to_string(),
+        date: Some("2024-06-15".to_string()),
+        url: "https://data.geopf.fr/BDFORET_2B.7z".to_string(),
+    };
+    save_project_metadata(
+        source,
+        &ProjectMetadata {
+            vegetation_source: "bdforet".to_string(),
+            layers: Some(layers.clone()),
+            regions: vec!["2A".to_string(), "2B".to_string()],
+            vintages: vec![source_vintage.clone()],
+            ..ProjectMetadata::default()
+        },
+    )
+    .unwrap();
+    mark_project_complete(&source_folder).unwrap();
+
+    let fetcher = RecordingFetcher::new();
+    let outcome = create_project_reusing(
+        source,
+        name,
+        &project_bb,
+        &creation_layer_config(VegetationSource::BdForet, Some(&layers)).unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &NoProgress,
+        &fetcher,
+        run_config,
+    )
+    .await;
+    assert_result_ok(&outcome, "Creation reusing the source project failed");
+    match outcome.unwrap() {
+        CreationOutcome::Created { regions, .. } => {
+            assert_eq!(regions, vec!["2A".to_string(), "2B".to_string()])
+        }
+        CreationOutcome::Cancelled => panic!("Creation should not be cancelled"),
+    }
+    assert_eq!(*fetcher.resolved.lock().unwrap(), vec!["2A".to_string()]);
+    assert_eq!(
+        *fetcher.requested.lock().unwrap(),
+        vec!["BDFORET".to_string()]
+    );
+
+    let folder = project_dir(name);
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+    let vegetation = gpkg_extent(&resource_gpkg(&folder, "FORMATION_VEGETALE").to_string_lossy())
+        .unwrap()
+        .expect("The merged vegetation layer has features");
+    assert!(vegetation.xmin < source_bb.xmin, "{:?}", vegetation);
+    let vintages = load_project_metadata(name).vintages;
+    assert!(vintages.contains(&source_vintage), "{:?}", vintages);
+    assert!(
+        vintages
+            .iter()
+            .any(|vintage| vintage.data_type == "BDFORET" && vintage.region == "2A"),
+        "{:?}",
+        vintages
+    );
+
+    for project in [source, name] {
+        fs::remove_dir_all(project_dir(project)).unwrap();
+    }
+}
+
+fn populate_cache_2a() {
+    for file_type in ["BDTOPO", "BDFORET", "RPG"] {
+        let cached = archive_cache_path(file_type, "2A");
+        if !cached.exists() {
+            fs::create_dir_all(cached.parent().unwrap()).unwrap();
+            fs::copy(format!("tests/res/{}_2A.7z", file_type), &cached).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_create_project_offline_from_cache_when_network_is_down() {
+    let name = "porto-vecchio-network-down";
+    let _ = fs::remove_dir_all(project_dir(name));
+    populate_cache_2a();
+    let mut warnings = Vec::new();
+
+    let outcome = create_project_pipeline(
+        name,
+        &get_test_bounding_box(),
+        &creation_layer_config(VegetationSource::BdForet, None).unwrap(),
+        CancelToken::default(),
+        &mut warnings,
+        &NoProgress,
+        &NetworkDownFetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&outcome, "Offline creation from the cache failed");
+    assert!(
+        warnings
+            .iter()
+            .any(|warning| warning.context == "Hors ligne"),
+        "{:?}",
+        warnings
+    );
+
+    let folder = project_dir(name);
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+    assert!(project_veget_jpeg(name).exists());
+    assert!(!project_ortho_jpeg(name).exists());
+    assert!(resource_gpkg(&folder, "FORMATION_VEGETALE").exists());
+
+    let metadata = load_project_metadata(name);
+    assert!(metadata.offline);
+    assert!(metadata.ortho_skipped);
+    assert_eq!(
+        metadata
+            .vintages
+            .iter()
+            .map(|vintage| vintage.data_type.as_str())
+            .collect::<Vec<_>>(),
+        ["BDTOPO", "BDFORET", "RPG"]
+    );
+
+    // L'aperçu se replie sur l'image de végétation.
+    assert!(generate_project_preview(name).unwrap().exists());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[tokio::test]
+async fn test_offline_creation_lists_archives_missing_from_cache() {
+    let name = "cozzano-network-down";
+    let _ = fs::remove_dir_all(project_dir(name));
+    populate_cache_2a();
+
+    // Emprise à cheval sur la Corse-du-Sud et la Haute-Corse, dont les archives
+    // ne sont pas dans le cache.
+    let outcome = create_project_pipeline(
+        name,
+        &BoundingBox::new(1199000.0, 6104000.0, 1219000.0, 6120000.0),
+        &creation_layer_config(VegetationSource::BdForet, None).unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &NoProgress,
+        &NetworkDownFetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    let error = outcome.unwrap_err();
+    assert!(error.contains("BDTOPO_2B.7z"), "{}", error);
+    assert!(error.contains("RPG_2B.7z"), "{}", error);
+    assert!(!error.contains("BDTOPO_2A.7z"), "{}", error);
+
+    let _ = fs::remove_dir_all(project_dir(name));
+}
+
+#[tokio::test]
+async fn test_legacy_project_without_state_is_kept() {
+    // Dossier d'une version sans marqueur ni état de création : seul le raster est présent.
+    let name = "test-legacy-layout";
+    let _ = fs::remove_dir_all(project_dir(name));
+    copy_project("porto-vecchio", name).unwrap();
+    let _ = fs::remove_file(project_dir(name).join(PROJECT_COMPLETE_MARKER));
+    let _ = fs::remove_file(project_dir(name).join(PIPELINE_STATE_FILE));
+
+    assert!(is_project_complete(&project_dir(name)));
+    assert!(
+        !get_incomplete_projects()
+            .unwrap()
+            .contains(&name.to_string())
+    );
+    assert!(project_conflict(name).is_some());
+
+    let outcome = create_project_pipeline(
+        name,
+        &get_test_bounding_box(),
+        &creation_layer_config(VegetationSource::BdForet, None).unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &NoProgress,
+        &FixtureFetcher::new("tests/res"),
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert!(outcome.is_err(), "The legacy project must not be replaced");
+    assert!(Dataset::open(project_tiff(name)).is_ok());
+
+    // Un état de création inachevé désigne, lui, une création interrompue.
+    let state = PipelineState::new(
+        name,
+        &get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.save(&project_dir(name)).unwrap();
+    assert!(!is_project_complete(&project_dir(name)));
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
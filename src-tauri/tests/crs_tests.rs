@@ -0,0 +1,156 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gis_operation::create_project_in_crs;
+use firefront_gis_lib::gis_operation::oso::resample_to_project;
+use firefront_gis_lib::gis_operation::regions::{
+    Region, find_project_regions, load_regions_graph, set_regions_index,
+};
+use firefront_gis_lib::utils::{
+    BoundingBox, DEFAULT_PROJECT_EPSG, WorkDir, create_directory_if_not_exists, crs_label,
+    department_epsg, is_project_epsg, regions_epsg,
+};
+use gdal::raster::Buffer;
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::Geometry;
+use gdal::{Dataset, DriverManager};
+
+/// Emprise de 5 km en Guadeloupe, en RGAF09 / UTM zone 20N (EPSG:5490).
+fn guadeloupe_bounding_box() -> BoundingBox {
+    BoundingBox::new(640000.0, 1770000.0, 645000.0, 1775000.0)
+}
+
+#[test]
+fn test_department_crs() {
+    assert_eq!(department_epsg("2A"), DEFAULT_PROJECT_EPSG);
+    assert_eq!(department_epsg("971"), 5490);
+    assert_eq!(department_epsg("974"), 2975);
+    assert!(is_project_epsg(5490));
+    assert!(!is_project_epsg(4326));
+    assert_eq!(crs_label(2154), "RGF93 / Lambert-93 (EPSG:2154)");
+    assert_eq!(crs_label(5490), "RGAF09 / UTM zone 20N (EPSG:5490)");
+
+    let codes = |codes: &[&str]| codes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    assert_eq!(regions_epsg(&codes(&["2A", "2B"])), Ok(2154));
+    assert_eq!(regions_epsg(&codes(&["971", "972"])), Ok(5490));
+    let mixed = regions_epsg(&codes(&["2A", "971"])).unwrap_err();
+    assert!(
+        mixed.contains("même système"),
+        "Unexpected error: {}",
+        mixed
+    );
+}
+
+#[test]
+fn test_create_project_in_overseas_crs() {
+    create_directory_if_not_exists("tmp").unwrap();
+    let project_file_path = "tmp/test_overseas_project.tiff";
+    remove_file_if_exists(project_file_path);
+
+    let result = create_project_in_crs(
+        project_file_path,
+        &guadeloupe_bounding_box(),
+        5490,
+        &RunConfig::snapshot(),
+    );
+    assert_result_ok(&result, "Creating the overseas project failed");
+
+    let dataset = Dataset::open(project_file_path).unwrap();
+    let srs = SpatialRef::from_wkt(&dataset.projection()).unwrap();
+    assert_eq!(srs.auth_code().unwrap(), 5490);
+    assert_eq!(dataset.geo_transform().unwrap()[0], 640000.0);
+    drop(dataset);
+    remove_file_if_exists(project_file_path);
+}
+
+#[test]
+fn test_find_project_regions_detects_overseas_crs() {
+    // Le graphe des régions est en Lambert-93 : l'étendue du département est l'emprise
+    // guadeloupéenne reprojetée.
+    let extent = guadeloupe_bounding_box()
+        .reproject(5490, DEFAULT_PROJECT_EPSG)
+        .unwrap();
+    let wkt = format!(
+        "POLYGON (({xmin} {ymin}, {xmax} {ymin}, {xmax} {ymax}, {xmin} {ymax}, {xmin} {ymin}))",
+        xmin = extent.xmin - 1000.0,
+        ymin = extent.ymin - 1000.0,
+        xmax = extent.xmax + 1000.0,
+        ymax = extent.ymax + 1000.0,
+    );
+    let mut graph = load_regions_graph().unwrap();
+    graph.insert(
+        "971".to_string(),
+        Region::new(
+            "971".to_string(),
+            "Guadeloupe".to_string(),
+            Geometry::from_wkt(&wkt).unwrap(),
+        ),
+    );
+    set_regions_index(graph);
+
+    let (regions, epsg) = find_project_regions(&guadeloupe_bounding_box()).unwrap();
+    assert_eq!(epsg, 5490);
+    let codes: Vec<&str> = regions.iter().map(|r| r.get_code().as_str()).collect();
+    assert_eq!(codes, vec!["971"]);
+
+    let (regions, epsg) = find_project_regions(&get_test_bounding_box()).unwrap();
+    assert_eq!(epsg, DEFAULT_PROJECT_EPSG);
+    assert!(regions.iter().any(|r| r.get_code() == "2A"));
+    assert!(regions.iter().all(|r| r.get_code() != "971"));
+}
+
+#[test]
+fn test_oso_resample_in_overseas_crs() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "oso_overseas").unwrap();
+    let project_file_path = work_dir.path_str("overseas.tiff");
+    let bb = guadeloupe_bounding_box();
+    create_project_in_crs(&project_file_path, &bb, 5490, &RunConfig::snapshot()).unwrap();
+
+    // Raster OSO uniforme (feuillus) couvrant l'emprise, dans le système du projet.
+    let oso_path = work_dir.path_str("oso.tiff");
+    let size = 100;
+    let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+    let mut oso = driver
+        .create_with_band_type::<u8, _>(&oso_path, size, size, 1)
+        .unwrap();
+    oso.set_geo_transform(&[bb.xmin, 50.0, 0.0, bb.ymax, 0.0, -50.0])
+        .unwrap();
+    oso.set_spatial_ref(&SpatialRef::from_epsg(5490).unwrap())
+        .unwrap();
+    oso.rasterband(1)
+        .unwrap()
+        .write(
+            (0, 0),
+            (size, size),
+            &mut Buffer::new((size, size), vec![16u8; size * size]),
+        )
+        .unwrap();
+    drop(oso);
+
+    let resampled_path = work_dir.path_str("oso_resampled.tiff");
+    let project = Dataset::open(&project_file_path).unwrap();
+    resample_to_project(&project, &oso_path, &resampled_path).unwrap();
+
+    let resampled = Dataset::open(&resampled_path).unwrap();
+    let srs = SpatialRef::from_wkt(&resampled.projection()).unwrap();
+    assert_eq!(srs.auth_code().unwrap(), 5490);
+    assert_eq!(resampled.raster_size(), project.raster_size());
+    let (width, height) = resampled.raster_size();
+    let center = resampled
+        .rasterband(1)
+        .unwrap()
+        .read_as::<u8>(
+            ((width / 2) as isize, (height / 2) as isize),
+            (1, 1),
+            (1, 1),
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        center.data()[0],
+        16,
+        "OSO classes must land on the project extent"
+    );
+}
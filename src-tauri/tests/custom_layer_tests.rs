@@ -0,0 +1,131 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::gis_operation::custom_layers::{add_custom_layer, custom_layer_name};
+use firefront_gis_lib::utils::{
+    copy_project, create_directory_if_not_exists, load_project_metadata, project_dir, project_tiff,
+};
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{Geometry, LayerAccess, LayerOptions, OGRwkbGeometryType};
+use gdal::{Dataset, DriverManager};
+use std::fs;
+use std::path::Path;
+
+/// Crée un GeoPackage Lambert-93 contenant un seul polygone.
+fn create_polygon_gpkg(path: &str, layer_name: &str, wkt: &str) {
+    remove_file_if_exists(path);
+    let srs = SpatialRef::from_epsg(2154).unwrap();
+    let mut dataset = DriverManager::get_driver_by_name("GPKG")
+        .unwrap()
+        .create_vector_only(path)
+        .unwrap();
+    let mut layer = dataset
+        .create_layer(LayerOptions {
+            name: layer_name,
+            srs: Some(&srs),
+            ty: OGRwkbGeometryType::wkbPolygon,
+            ..Default::default()
+        })
+        .unwrap();
+    layer
+        .create_feature(Geometry::from_wkt(wkt).unwrap())
+        .unwrap();
+    drop(layer);
+    dataset.close().unwrap();
+}
+
+fn pixel_color(dataset: &Dataset, x: usize, y: usize) -> [u8; 3] {
+    let mut color = [0; 3];
+    for (band_idx, value) in color.iter_mut().enumerate() {
+        *value = dataset
+            .rasterband(band_idx + 1)
+            .unwrap()
+            .read_as::<u8>((x as isize, y as isize), (1, 1), (1, 1), None)
+            .unwrap()
+            .data()[0];
+    }
+    color
+}
+
+#[test]
+fn test_custom_layer_name_is_sanitized() {
+    assert_eq!(
+        custom_layer_name(Path::new("/data/Coupures feu (2A).shp")).unwrap(),
+        "Coupures_feu__2A_"
+    );
+    assert!(custom_layer_name(Path::new("/")).is_err());
+}
+
+#[test]
+fn test_add_custom_firebreak_layer() {
+    let name = "porto-vecchio-custom-layer";
+    let _ = fs::remove_dir_all(project_dir(name));
+    copy_project("porto-vecchio", name).unwrap();
+    create_directory_if_not_exists("tmp/custom_layer").unwrap();
+    let firebreak = "tmp/custom_layer/firebreak.gpkg";
+    let outside = "tmp/custom_layer/outside.gpkg";
+    // Coupure de 200 m de large sur 2 km, dans l'emprise du projet.
+    create_polygon_gpkg(
+        firebreak,
+        "firebreak",
+        "POLYGON ((1217000 6076000, 1217200 6076000, 1217200 6078000, 1217000 6078000, 1217000 6076000))",
+    );
+    create_polygon_gpkg(
+        outside,
+        "outside",
+        "POLYGON ((1100000 6076000, 1100200 6076000, 1100200 6078000, 1100000 6078000, 1100000 6076000))",
+    );
+    let color = [255, 140, 0];
+
+    // Le filtre est vérifié sur la couche : l'erreur d'OGR est renvoyée telle quelle.
+    let invalid_filter = add_custom_layer(
+        name,
+        Path::new(firebreak),
+        color,
+        4,
+        Some("NATURE = 'Coupe-feu'"),
+    );
+    assert!(invalid_filter.unwrap_err().to_string().contains("NATURE"));
+    let no_match = add_custom_layer(name, Path::new(firebreak), color, 4, Some("fid < 0"));
+    assert!(
+        no_match
+            .unwrap_err()
+            .to_string()
+            .contains("ne correspond au filtre")
+    );
+    assert!(load_project_metadata(name).custom_layers.is_empty());
+
+    let result = add_custom_layer(name, Path::new(firebreak), color, 4, None);
+    assert_result_ok(&result, "Adding the custom layer failed");
+    let layer = result.unwrap();
+    assert_eq!(layer.name, "firebreak");
+    assert!(layer.gpkg_path(&project_dir(name)).exists());
+    assert_eq!(load_project_metadata(name).custom_layers, vec![layer]);
+
+    let project = Dataset::open(project_tiff(name)).unwrap();
+    let geo_transform = project.geo_transform().unwrap();
+    let to_pixel = |x: f64, y: f64| {
+        (
+            ((x - geo_transform[0]) / geo_transform[1]) as usize,
+            ((y - geo_transform[3]) / geo_transform[5]) as usize,
+        )
+    };
+    for (x, y) in [(1217100.0, 6077000.0), (1217010.0, 6076010.0)] {
+        let (px, py) = to_pixel(x, y);
+        assert_eq!(pixel_color(&project, px, py), color, "({}, {})", x, y);
+    }
+    let (px, py) = to_pixel(1216500.0, 6077000.0);
+    assert_ne!(pixel_color(&project, px, py), color);
+    drop(project);
+
+    let duplicate = add_custom_layer(name, Path::new(firebreak), color, 4, None);
+    assert!(duplicate.unwrap_err().to_string().contains("existe déjà"));
+    assert!(add_custom_layer(name, Path::new(outside), [0, 0, 0], 4, None).is_err());
+    let empty = add_custom_layer(name, Path::new(outside), color, 4, None);
+    assert!(empty.unwrap_err().to_string().contains("aucune entité"));
+    assert_eq!(load_project_metadata(name).custom_layers.len(), 1);
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+    fs::remove_dir_all("tmp/custom_layer").unwrap();
+}
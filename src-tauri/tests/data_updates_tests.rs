@@ -0,0 +1,352 @@
+use chrono::{Duration, Local, NaiveDate};
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::data_updates::{
+    DataUpdate, DataUpdates, check_data_updates, compare_vintages, is_check_due, known_vintages,
+    refresh_archives,
+};
+use firefront_gis_lib::events::Warning;
+use firefront_gis_lib::fetcher::{DataFetcher, FixtureFetcher};
+use firefront_gis_lib::progress::ProgressSink;
+use firefront_gis_lib::utils::{
+    BoundingBox, CachePin, DataVintage, ProjectMetadata, get_config_mut, pin_cache_entries,
+    record_cache_vintage,
+};
+use firefront_gis_lib::web_request::{
+    ArchiveUrl, DepartmentArchives, DepartmentUrls, DownloadJob, DownloadProgress,
+    archive_cache_path,
+};
+use futures_util::future::BoxFuture;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn vintage(data_type: &str, region: &str, date: Option<&str>) -> DataVintage {
+    DataVintage {
+        data_type: data_type.to_string(),
+        region: region.to_string(),
+        date: date.map(str::to_string),
+        url: String::new(),
+    }
+}
+
+fn published(code: &str, topo: &str, foret: &str, rpg: &str) -> DepartmentUrls {
+    DepartmentUrls {
+        code: code.to_string(),
+        archives: Ok(DepartmentArchives {
+            topo: ArchiveUrl::new(topo),
+            foret: ArchiveUrl::new(foret),
+            rpg: ArchiveUrl::new(rpg),
+        }),
+        network_error: false,
+    }
+}
+
+fn date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+}
+
+/// Recherche des URLs simulée : chaque département a des archives datées du 2030-01-01.
+struct MockResolver {
+    calls: AtomicUsize,
+}
+
+impl DataFetcher for MockResolver {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move {
+            codes
+                .iter()
+                .map(|code| {
+                    published(
+                        code,
+                        "BDTOPO_SHP_2030-01-01.7z",
+                        "BDFORET_SHP_2030-01-01.7z",
+                        "RPG_SHP_2030-01-01.7z",
+                    )
+                })
+                .collect()
+        })
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        _jobs: Vec<DownloadJob>,
+        _on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        Box::pin(async { Err("Téléchargement indisponible".to_string()) })
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        _jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        Box::pin(async { Err("Téléchargement indisponible".to_string()) })
+    }
+
+    fn download_ortho(
+        &self,
+        _run_config: &RunConfig,
+        _output_jpg_path: &str,
+        _project_bb: &BoundingBox,
+        _epsg: u32,
+        _regional_gpkg: Option<&str>,
+        _progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        Err("Orthophoto indisponible".to_string())
+    }
+}
+
+#[test]
+fn test_check_is_due_once_a_day() {
+    let now = Local::now();
+    assert!(is_check_due(None, now));
+    assert!(is_check_due(Some("pas une date"), now));
+    assert!(!is_check_due(
+        Some(&(now - Duration::hours(23)).to_rfc3339()),
+        now
+    ));
+    assert!(is_check_due(
+        Some(&(now - Duration::hours(25)).to_rfc3339()),
+        now
+    ));
+}
+
+#[test]
+fn test_known_vintages_keep_most_recent_of_cache_and_projects() {
+    let cache = Path::new("tmp/data_updates_cache");
+    let _ = fs::remove_dir_all(cache);
+    fs::create_dir_all(cache).unwrap();
+    for name in ["BDTOPO_2A.7z", "BDFORET_2A.7z", "RPG_2B.7z"] {
+        fs::write(cache.join(name), b"archive").unwrap();
+    }
+    record_cache_vintage(
+        &cache.join("BDTOPO_2A.7z"),
+        vintage("BDTOPO", "2A", Some("2025-03-15")),
+    )
+    .unwrap();
+    record_cache_vintage(
+        &cache.join("BDFORET_2A.7z"),
+        vintage("BDFORET", "2A", Some("2017-05-01")),
+    )
+    .unwrap();
+
+    let project = ProjectMetadata {
+        vintages: vec![
+            vintage("BDTOPO", "2A", Some("2024-12-15")),
+            vintage("BDFORET", "2A", Some("2019-01-01")),
+            vintage("RPG", "04", Some("2023-01-01")),
+        ],
+        ..ProjectMetadata::default()
+    };
+    let known = known_vintages(cache, &[project]);
+
+    let key = |data_type: &str, code: &str| (data_type.to_string(), code.to_string());
+    assert_eq!(known[&key("BDTOPO", "2A")], date("2025-03-15"));
+    assert_eq!(known[&key("BDFORET", "2A")], date("2019-01-01"));
+    // Archive du cache téléchargée avant l'enregistrement des millésimes.
+    assert_eq!(known[&key("RPG", "2B")], None);
+    assert_eq!(known[&key("RPG", "04")], date("2023-01-01"));
+    assert_eq!(known.len(), 4);
+
+    fs::remove_dir_all(cache).unwrap();
+}
+
+#[test]
+fn test_compare_vintages_reports_newer_releases_only() {
+    let mut known = std::collections::BTreeMap::new();
+    known.insert(("BDTOPO".to_string(), "2A".to_string()), date("2025-03-15"));
+    known.insert(
+        ("BDFORET".to_string(), "2A".to_string()),
+        date("2017-05-01"),
+    );
+    known.insert(("RPG".to_string(), "2A".to_string()), None);
+    known.insert(("BDTOPO".to_string(), "2B".to_string()), date("2025-06-15"));
+
+    let resolved = vec![
+        published(
+            "2A",
+            "BDTOPO_3-4_TOUSTHEMES_SHP_LAMB93_D02A_2025-06-15.7z",
+            "BDFORET_2-0__SHP_LAMB93_D02A_2017-05-01.7z",
+            "RPG_2-2__SHP_LAMB93_R94_2024-01-01.7z",
+        ),
+        published(
+            "2B",
+            "BDTOPO_3-4_TOUSTHEMES_SHP_LAMB93_D02B_2025-06-15.7z",
+            "BDFORET_2-0__SHP_LAMB93_D02B_2017-05-01.7z",
+            "RPG_2-2__SHP_LAMB93_R94_2024-01-01.7z",
+        ),
+        DepartmentUrls {
+            code: "04".to_string(),
+            archives: Err("BDTOPO introuvable pour le département 04".to_string()),
+            network_error: false,
+        },
+    ];
+    let (updates, errors) = compare_vintages(&known, &resolved);
+
+    // Seule la BDTOPO 2A est plus récente ; le RPG 2A sans millésime connu n'est pas signalé.
+    assert_eq!(updates.len(), 1, "{:?}", updates);
+    assert_eq!(updates[0].data_type, "BDTOPO");
+    assert_eq!(updates[0].region, "2A");
+    assert_eq!(updates[0].current, "2025-03-15");
+    assert_eq!(updates[0].latest, "2025-06-15");
+    assert!(updates[0].url.ends_with("D02A_2025-06-15.7z"));
+    assert_eq!(
+        errors,
+        vec![Warning::new(
+            "Département 04",
+            "BDTOPO introuvable pour le département 04"
+        )]
+    );
+}
+
+#[test]
+fn test_data_updates_state_keeps_previous_result_when_skipped() {
+    let updates = DataUpdates::default();
+    assert!(updates.start());
+    assert!(!updates.start(), "Une seule recherche à la fois");
+    let status = updates.finish(None);
+    assert!(!status.checking);
+    assert_eq!(status.checked_at, None);
+    assert!(updates.start());
+}
+
+#[tokio::test]
+async fn test_automatic_check_is_rate_limited() {
+    let resolver = MockResolver {
+        calls: AtomicUsize::new(0),
+    };
+    get_config_mut().last_data_update_check = None;
+
+    let first = check_data_updates(&resolver, false).await.unwrap();
+    assert!(first.is_some_and(|status| status.checked_at.is_some()));
+    let calls = resolver.calls.load(Ordering::SeqCst);
+    assert!(get_config_mut().last_data_update_check.is_some());
+
+    // Moins de 24 h après : la recherche automatique n'est pas refaite.
+    let second = check_data_updates(&resolver, false).await.unwrap();
+    assert_eq!(second, None);
+    assert_eq!(resolver.calls.load(Ordering::SeqCst), calls);
+
+    // Une recherche demandée par l'utilisateur ignore l'intervalle.
+    let forced = check_data_updates(&resolver, true).await.unwrap();
+    assert!(forced.is_some());
+}
+
+/// Archives locales ; l'archive remplacée est épinglée pendant son téléchargement,
+/// comme par une création lancée entre-temps.
+struct PinningFetcher {
+    fixtures: FixtureFetcher,
+    pin: Mutex<Option<CachePin>>,
+}
+
+impl DataFetcher for PinningFetcher {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        self.fixtures.resolve_urls(codes)
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+        on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        self.fixtures.download_archives(jobs, on_progress)
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        let paths: Vec<PathBuf> = jobs.iter().map(|job| job.cache_path()).collect();
+        *self.pin.lock().unwrap() = Some(pin_cache_entries(&paths));
+        self.fixtures.download_replacements(jobs)
+    }
+
+    fn download_ortho(
+        &self,
+        run_config: &RunConfig,
+        output_jpg_path: &str,
+        project_bb: &BoundingBox,
+        epsg: u32,
+        regional_gpkg: Option<&str>,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        self.fixtures.download_ortho(
+            run_config,
+            output_jpg_path,
+            project_bb,
+            epsg,
+            regional_gpkg,
+            progress,
+        )
+    }
+}
+
+/// Fichiers `.part` laissés à côté d'une archive du cache.
+fn part_files(archive_path: &Path) -> Vec<PathBuf> {
+    let prefix = archive_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    fs::read_dir(archive_path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with(&prefix) && name.ends_with(".part")
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_refresh_keeps_cached_archive_until_replaced() {
+    let fixture_dir = std::env::temp_dir().join("firefront_refresh_fixtures");
+    fs::create_dir_all(&fixture_dir).unwrap();
+    fs::write(fixture_dir.join("BDTOPO_99.7z"), b"nouveau").unwrap();
+    let archive_path = archive_cache_path("BDTOPO", "99");
+    fs::create_dir_all(archive_path.parent().unwrap()).unwrap();
+    fs::write(&archive_path, b"ancien").unwrap();
+    let update = DataUpdate {
+        data_type: "BDTOPO".to_string(),
+        region: "99".to_string(),
+        current: "2024-01-01".to_string(),
+        latest: "2030-01-01".to_string(),
+        url: "https://example.invalid/BDTOPO_SHP_2030-01-01.7z".to_string(),
+    };
+
+    // Téléchargement en échec : l'archive du cache reste utilisable.
+    let resolver = MockResolver {
+        calls: AtomicUsize::new(0),
+    };
+    assert!(
+        refresh_archives(&resolver, std::slice::from_ref(&update))
+            .await
+            .is_err()
+    );
+    assert_eq!(fs::read(&archive_path).unwrap(), b"ancien");
+
+    // Archive épinglée pendant le téléchargement : elle n'est pas remplacée.
+    let pinning = PinningFetcher {
+        fixtures: FixtureFetcher::new(&fixture_dir),
+        pin: Mutex::new(None),
+    };
+    let refreshed = refresh_archives(&pinning, std::slice::from_ref(&update))
+        .await
+        .unwrap();
+    assert!(refreshed.is_empty());
+    assert_eq!(fs::read(&archive_path).unwrap(), b"ancien");
+    assert!(part_files(&archive_path).is_empty());
+    drop(pinning);
+
+    let fixtures = FixtureFetcher::new(&fixture_dir);
+    let refreshed = refresh_archives(&fixtures, std::slice::from_ref(&update))
+        .await
+        .unwrap();
+    assert_eq!(refreshed, vec![("BDTOPO".to_string(), "99".to_string())]);
+    assert_eq!(fs::read(&archive_path).unwrap(), b"nouveau");
+    assert!(part_files(&archive_path).is_empty());
+
+    fs::remove_file(&archive_path).unwrap();
+    fs::remove_dir_all(&fixture_dir).unwrap();
+}
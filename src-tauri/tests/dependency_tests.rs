@@ -4,8 +4,9 @@ use firefront_gis_lib::{app_setup, dependency};
 
 #[test]
 fn test_setup_check() {
-    let result = app_setup::setup_check();
-    common::assert_result_ok(&result, "Setup check failed");
+    let report = app_setup::setup_check();
+    assert!(report.ready, "Setup check failed: {:?}", report.missing());
+    assert!(report.ensure_ready().is_ok());
 }
 
 #[test]
@@ -0,0 +1,158 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gis_operation::derive::{
+    RasterWindow, derive_project, find_containing_project, project_window,
+};
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::progress::NoProgress;
+use firefront_gis_lib::utils::{
+    BoundingBox, PROJECT_COMPLETE_MARKER, load_project_metadata, project_dir, project_ortho_jpeg,
+    project_tiff, resource_gpkg,
+};
+use gdal::Dataset;
+use std::fs;
+
+/// Emprise de 5 × 5 km contenue dans le projet de test de 25 × 25 km.
+fn derived_bounding_box() -> BoundingBox {
+    BoundingBox::new(1215000.0, 6075000.0, 1220000.0, 6080000.0)
+}
+
+#[test]
+fn test_project_window_of_contained_extent() {
+    let window = project_window("porto-vecchio", &derived_bounding_box(), 10.0).unwrap();
+    assert_eq!(
+        window,
+        Some(RasterWindow {
+            x: 500,
+            y: 1500,
+            width: 500,
+            height: 500,
+        })
+    );
+
+    let overflowing = BoundingBox::new(1205000.0, 6075000.0, 1210000.0, 6080000.0);
+    assert_eq!(
+        project_window("porto-vecchio", &overflowing, 10.0).unwrap(),
+        None
+    );
+    let misaligned = BoundingBox::new(1215005.0, 6075000.0, 1220005.0, 6080000.0);
+    assert_eq!(
+        project_window("porto-vecchio", &misaligned, 10.0).unwrap(),
+        None
+    );
+    // Un projet à une autre résolution ne peut pas servir de source.
+    assert_eq!(
+        project_window("porto-vecchio", &derived_bounding_box(), 5.0).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_find_containing_project() {
+    let source = find_containing_project(
+        &derived_bounding_box(),
+        &VegetationSource::BdForet,
+        None,
+        "",
+        10.0,
+    )
+    .expect("The test project should contain the extent");
+    assert!(
+        project_window(&source, &derived_bounding_box(), 10.0)
+            .unwrap()
+            .is_some()
+    );
+
+    let outside = BoundingBox::new(1100000.0, 6075000.0, 1105000.0, 6080000.0);
+    assert_eq!(
+        find_containing_project(&outside, &VegetationSource::BdForet, None, "", 10.0),
+        None
+    );
+}
+
+#[test]
+fn test_derive_project_matches_source_window() {
+    let name = "porto-vecchio-derived";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let bb = derived_bounding_box();
+    let mut warnings = Vec::new();
+
+    let result = derive_project(
+        "porto-vecchio",
+        name,
+        &bb,
+        &mut warnings,
+        &NoProgress,
+        &RunConfig::snapshot(),
+    );
+    assert_result_ok(&result, "Deriving the project failed");
+
+    let folder = project_dir(name);
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+    let metadata = load_project_metadata(name);
+    assert_eq!(metadata.derived_from.as_deref(), Some("porto-vecchio"));
+    assert_eq!(
+        metadata.vegetation_source,
+        load_project_metadata("porto-vecchio").vegetation_source
+    );
+    assert!(metadata.timings.get("derive").is_some());
+
+    let source = Dataset::open(project_tiff("porto-vecchio")).unwrap();
+    let derived = Dataset::open(project_tiff(name)).unwrap();
+    assert_eq!(derived.raster_size(), (500, 500));
+    assert_eq!(
+        derived.geo_transform().unwrap(),
+        [1215000.0, 10.0, 0.0, 6080000.0, 0.0, -10.0]
+    );
+    assert_eq!(derived.raster_count(), source.raster_count());
+    for band_idx in 1..=source.raster_count() {
+        let expected = source
+            .rasterband(band_idx)
+            .unwrap()
+            .read_as::<u8>((500, 1500), (500, 500), (500, 500), None)
+            .unwrap();
+        let actual = derived
+            .rasterband(band_idx)
+            .unwrap()
+            .read_as::<u8>((0, 0), (500, 500), (500, 500), None)
+            .unwrap();
+        assert!(
+            expected.data() == actual.data(),
+            "Band {} differs from the source window",
+            band_idx
+        );
+    }
+
+    let ortho = project_ortho_jpeg(name).to_string_lossy().to_string();
+    assert_file_exists(&ortho, "Orthophoto was not cropped");
+    check_jpeg_properties(&ortho, 10.0, "Derived orthophoto");
+    assert_eq!(Dataset::open(&ortho).unwrap().raster_size(), (500, 500));
+    assert_file_exists(
+        &resource_gpkg(&folder, name).to_string_lossy(),
+        "Regional layer was not clipped",
+    );
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_derive_project_rejects_uncontained_extent() {
+    let name = "porto-vecchio-not-derived";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let bb = BoundingBox::new(1230000.0, 6090000.0, 1240000.0, 6100000.0);
+
+    let result = derive_project(
+        "porto-vecchio",
+        name,
+        &bb,
+        &mut Vec::new(),
+        &NoProgress,
+        &RunConfig::snapshot(),
+    );
+
+    assert!(result.is_err());
+    assert!(!project_dir(name).exists());
+}
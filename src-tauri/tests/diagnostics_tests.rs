@@ -0,0 +1,63 @@
+mod common;
+
+use firefront_gis_lib::diagnostics::{
+    BinaryStatus, DiagnosticsReport, DirectoryStatus, NetworkProbe, check_binary, check_directory,
+};
+use std::path::Path;
+
+#[test]
+fn test_missing_binary_is_reported_not_fatal() {
+    let status = check_binary("firefront-binaire-inexistant");
+    assert!(!status.found, "Missing binary should not be found");
+    assert!(status.path.is_none(), "Missing binary should have no path");
+}
+
+#[test]
+fn test_directory_check_writable() {
+    let status = check_directory(Path::new("tests/res"));
+    assert!(status.writable, "tests/res should be writable");
+    assert!(
+        !Path::new("tests/res/.firefront_probe").exists(),
+        "Probe file was not removed"
+    );
+}
+
+#[test]
+fn test_report_serialization() {
+    let report = DiagnosticsReport {
+        gdal_version: "3100300".to_string(),
+        gdal_warning: None,
+        binaries: vec![BinaryStatus {
+            name: "7z".to_string(),
+            found: false,
+            path: None,
+        }],
+        projects_dir: DirectoryStatus {
+            path: "projects".to_string(),
+            writable: true,
+            free_space_bytes: Some(1024),
+        },
+        output_location: DirectoryStatus {
+            path: "/tmp".to_string(),
+            writable: false,
+            free_space_bytes: None,
+        },
+        regions_geojson_loaded: true,
+        regions_graph_loaded: true,
+        network: Some(NetworkProbe {
+            url: "https://geoservices.ign.fr/bdtopo".to_string(),
+            reachable: true,
+            latency_ms: Some(120),
+            error: None,
+        }),
+    };
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["binaries"][0]["name"], "7z");
+    assert_eq!(json["binaries"][0]["found"], false);
+    assert_eq!(json["projects_dir"]["free_space_bytes"], 1024);
+    assert_eq!(json["network"]["latency_ms"], 120);
+
+    let round_trip: DiagnosticsReport = serde_json::from_value(json).unwrap();
+    assert_eq!(round_trip, report);
+}
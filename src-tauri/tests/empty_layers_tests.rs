@@ -0,0 +1,144 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::busy::CancelToken;
+use firefront_gis_lib::fetcher::DataFetcher;
+use firefront_gis_lib::gis_operation::layer_config::VEGETATION_LAYER;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::gis_operation::regions::{Region, load_regions_graph, set_regions_index};
+use firefront_gis_lib::pipeline::{
+    CreationOutcome, create_project_pipeline, creation_layer_config,
+};
+use firefront_gis_lib::progress::{ProgressCollector, ProgressSink};
+use firefront_gis_lib::utils::{
+    BoundingBox, PROJECT_COMPLETE_MARKER, load_project_metadata, project_dir, resource_gpkg,
+};
+use firefront_gis_lib::web_request::{
+    DepartmentUrls, DownloadJob, DownloadProgress, archive_cache_path,
+};
+use futures_util::future::BoxFuture;
+use gdal::Dataset;
+use gdal::vector::{Geometry, LayerAccess};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Serveurs injoignables : la création se fait à partir des archives du cache.
+struct OfflineFetcher;
+
+impl DataFetcher for OfflineFetcher {
+    fn resolve_urls<'a>(&'a self, codes: &'a [String]) -> BoxFuture<'a, Vec<DepartmentUrls>> {
+        Box::pin(async move {
+            codes
+                .iter()
+                .map(|code| DepartmentUrls {
+                    code: code.clone(),
+                    archives: Err(format!("Département {}: connexion refusée", code)),
+                    network_error: true,
+                })
+                .collect()
+        })
+    }
+
+    fn download_archives<'a>(
+        &'a self,
+        _jobs: Vec<DownloadJob>,
+        _on_progress: &'a (dyn Fn(DownloadProgress) + Sync),
+    ) -> BoxFuture<'a, Result<usize, String>> {
+        Box::pin(async { Err("connexion refusée".to_string()) })
+    }
+
+    fn download_replacements<'a>(
+        &'a self,
+        _jobs: Vec<DownloadJob>,
+    ) -> BoxFuture<'a, Result<Vec<(DownloadJob, PathBuf)>, String>> {
+        Box::pin(async { Err("connexion refusée".to_string()) })
+    }
+
+    fn download_ortho(
+        &self,
+        _run_config: &RunConfig,
+        _output_jpg_path: &str,
+        _project_bb: &BoundingBox,
+        _epsg: u32,
+        _regional_gpkg: Option<&str>,
+        _progress: &dyn ProgressSink,
+    ) -> Result<(), String> {
+        Err("connexion refusée".to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_sliver_region_vegetation_is_not_merged() {
+    // La Haute-Corse est réduite à une bande de 10 m sur le bord est de l'emprise :
+    // l'emprise la recoupe, mais la BD Forêt de la Haute-Corse n'y a aucune entité.
+    let bb = get_test_bounding_box();
+    let mut graph = load_regions_graph().unwrap();
+    let sliver = Geometry::from_wkt(&format!(
+        "POLYGON (({x0} {y0}, {x1} {y0}, {x1} {y1}, {x0} {y1}, {x0} {y0}))",
+        x0 = bb.xmax - 10.0,
+        x1 = bb.xmax + 1000.0,
+        y0 = bb.ymin + 1000.0,
+        y1 = bb.ymin + 2000.0,
+    ))
+    .unwrap();
+    graph.insert(
+        "2B".to_string(),
+        Region::new("2B".to_string(), "Haute-Corse".to_string(), sliver),
+    );
+    set_regions_index(graph);
+
+    for code in ["2A", "2B"] {
+        let cached = archive_cache_path("BDFORET", code);
+        if !cached.exists() {
+            fs::create_dir_all(cached.parent().unwrap()).unwrap();
+            fs::copy(format!("tests/res/BDFORET_{}.7z", code), &cached).unwrap();
+        }
+    }
+
+    let name = "porto-vecchio-sliver";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let collector = ProgressCollector::default();
+    let outcome = create_project_pipeline(
+        name,
+        &bb,
+        &creation_layer_config(
+            VegetationSource::BdForet,
+            Some(&[VEGETATION_LAYER.to_string()]),
+        )
+        .unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &collector,
+        &OfflineFetcher,
+        RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&outcome, "Creation over a sliver of 2B failed");
+    match outcome.unwrap() {
+        CreationOutcome::Created { regions, .. } => assert_eq!(regions, ["2A", "2B"]),
+        CreationOutcome::Cancelled => panic!("Creation should not be cancelled"),
+    }
+
+    let folder = project_dir(name);
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+    assert_eq!(
+        load_project_metadata(name).empty_layers,
+        BTreeMap::from([("2B".to_string(), vec![VEGETATION_LAYER.to_string()])])
+    );
+
+    // La végétation de la Corse-du-Sud est fusionnée seule, sans étape pour le RPG.
+    let vegetation = Dataset::open(resource_gpkg(&folder, VEGETATION_LAYER)).unwrap();
+    let features: u64 = vegetation.layers().map(|layer| layer.feature_count()).sum();
+    assert!(features > 0);
+    let merge_vegetation = collector
+        .reports()
+        .into_iter()
+        .find(|report| report.detail.as_deref() == Some("step.merge_vegetation"))
+        .expect("The vegetation was not merged");
+    assert_eq!((merge_vegetation.current, merge_vegetation.total), (2, 3));
+
+    fs::remove_dir_all(folder).unwrap();
+}
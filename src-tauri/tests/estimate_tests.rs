@@ -0,0 +1,181 @@
+mod common;
+
+use firefront_gis_lib::estimate::{
+    ArchiveEstimate, ProjectLimits, ProjectSizeError, Throughput, UNKNOWN_ARCHIVE_BYTES,
+    build_estimate, check_project_size, estimate_duration_secs, raster_dimensions, slice_count,
+    tiff_size_bytes,
+};
+use firefront_gis_lib::utils::BoundingBox;
+
+fn archive(file_type: &str, cached: bool, size_bytes: Option<u64>) -> ArchiveEstimate {
+    ArchiveEstimate {
+        file_type: file_type.to_string(),
+        code: "2A".to_string(),
+        cached,
+        size_bytes,
+    }
+}
+
+#[test]
+fn test_raster_dimensions_and_tiff_size() {
+    let bb = common::get_test_bounding_box();
+    assert_eq!(raster_dimensions(&bb, 10.0), (2500, 2500));
+    assert_eq!(tiff_size_bytes(2500, 2500), 25_000_000);
+
+    let uneven = BoundingBox::new(0.0, 0.0, 1005.0, 1000.0);
+    assert_eq!(raster_dimensions(&uneven, 10.0), (101, 100));
+    assert_eq!(raster_dimensions(&uneven, 0.0), (0, 0));
+}
+
+#[test]
+fn test_slice_count_includes_partial_tiles() {
+    assert_eq!(slice_count(2500, 2500, 500), 25);
+    assert_eq!(slice_count(2501, 2500, 500), 30);
+    assert_eq!(slice_count(2500, 2500, 0), 0);
+}
+
+#[test]
+fn test_estimate_duration_from_throughput() {
+    let throughput = Throughput {
+        download_mb_per_sec: 1.0,
+        seconds_per_department: 10.0,
+        megapixels_per_sec: 1.0,
+        seconds_per_slice: 0.5,
+    };
+
+    let secs = estimate_duration_secs(20 * 1024 * 1024, 2, 5_000_000, 10, &throughput);
+    assert_eq!(secs, 20 + 20 + 5 + 5);
+
+    let stalled = Throughput {
+        download_mb_per_sec: 0.0,
+        ..throughput
+    };
+    assert_eq!(estimate_duration_secs(1024, 0, 0, 0, &stalled), 0);
+}
+
+#[test]
+fn test_build_estimate_counts_only_archives_to_download() {
+    let bb = common::get_test_bounding_box();
+    let throughput = Throughput::default();
+    let archives = vec![
+        archive("BDTOPO", true, Some(500)),
+        archive("BDFORET", false, Some(1000)),
+        archive("RPG", false, None),
+    ];
+
+    let estimate = build_estimate(
+        &bb,
+        vec!["2A".to_string()],
+        archives.clone(),
+        10.0,
+        500,
+        &throughput,
+        &ProjectLimits {
+            max_area_km2: 2500.0,
+            max_regions: 4,
+        },
+    );
+
+    assert_eq!(estimate.archives, archives);
+    assert_eq!(estimate.download_bytes, 1000);
+    assert_eq!(estimate.unknown_sizes, 1);
+    assert_eq!((estimate.width, estimate.height), (2500, 2500));
+    assert_eq!(estimate.slice_count, 25);
+    assert_eq!(
+        estimate.duration_secs,
+        estimate_duration_secs(
+            1000 + UNKNOWN_ARCHIVE_BYTES,
+            1,
+            2500 * 2500,
+            25,
+            &throughput
+        )
+    );
+    assert_eq!(estimate.area_km2, 625.0);
+    assert_eq!(estimate.size_error, None);
+}
+
+/// Emprise de Cozzano, à cheval sur la limite 2A/2B.
+fn cozzano_bounding_box() -> BoundingBox {
+    BoundingBox::new(1199000.0, 6104000.0, 1219000.0, 6120000.0)
+}
+
+#[test]
+fn test_project_area_limit_at_boundary() {
+    let bb = common::get_test_bounding_box();
+    assert_eq!(bb.area_km2(), 625.0);
+    assert_eq!(BoundingBox::new(10.0, 10.0, 0.0, 0.0).area_km2(), 0.0);
+
+    let limits = ProjectLimits {
+        max_area_km2: 625.0,
+        max_regions: 4,
+    };
+    assert_eq!(limits.check(&bb, 1), Ok(()));
+
+    let larger = BoundingBox::new(bb.xmin, bb.ymin, bb.xmax + 5000.0, bb.ymax);
+    let error = limits.check(&larger, 1).unwrap_err();
+    assert_eq!(
+        error,
+        ProjectSizeError::AreaTooLarge {
+            requested_km2: 750.0,
+            allowed_km2: 625.0,
+        }
+    );
+    let message = error.to_string();
+    assert!(
+        message.contains("750") && message.contains("625"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_project_region_limit_with_multi_region_extent() {
+    let bb = cozzano_bounding_box();
+    let limits = ProjectLimits {
+        max_area_km2: 2500.0,
+        max_regions: 1,
+    };
+
+    let error = check_project_size(&bb, &limits, false).unwrap_err();
+    assert!(error.to_string().contains("1 autorisés"), "{}", error);
+    match limits.check(&bb, 2) {
+        Err(ProjectSizeError::TooManyRegions { requested, allowed }) => {
+            assert_eq!((requested, allowed), (2, 1))
+        }
+        other => panic!("Unexpected check result: {:?}", other),
+    }
+
+    let relaxed = ProjectLimits {
+        max_regions: 2,
+        ..limits
+    };
+    assert_eq!(check_project_size(&bb, &relaxed, false).unwrap(), None);
+}
+
+#[test]
+fn test_project_size_override() {
+    let limits = ProjectLimits {
+        max_area_km2: 100.0,
+        max_regions: 1,
+    };
+
+    let bb = common::get_test_bounding_box();
+    assert!(check_project_size(&bb, &limits, false).is_err());
+    let exceeded = check_project_size(&bb, &limits, true)
+        .unwrap()
+        .expect("The exceeded limit should be reported");
+    assert!(matches!(exceeded, ProjectSizeError::AreaTooLarge { .. }));
+
+    let estimate = build_estimate(
+        &bb,
+        vec!["2A".to_string()],
+        Vec::new(),
+        10.0,
+        500,
+        &Throughput::default(),
+        &limits,
+    );
+    assert_eq!(estimate.size_error, Some(exceeded.to_string()));
+    assert_eq!(estimate.limits, limits);
+}
@@ -0,0 +1,56 @@
+use firefront_gis_lib::events::{ProjectCreated, ProjectCreationFailed, Warning};
+use firefront_gis_lib::timings::StageTimings;
+use serde_json::json;
+
+#[test]
+fn test_project_created_payload() {
+    let mut timings = StageTimings::default();
+    timings.add("download", 4.5);
+    timings.add("download/BDTOPO 2A", 4.0);
+    let created = ProjectCreated::new(
+        "porto-vecchio",
+        "projects/porto-vecchio",
+        12.5,
+        vec!["2A".to_string()],
+        &[Warning::new("Aperçu", "non généré")],
+        timings,
+    );
+
+    let payload = serde_json::to_value(&created).unwrap();
+    assert_eq!(
+        payload,
+        json!({
+            "name": "porto-vecchio",
+            "folder": "projects/porto-vecchio",
+            "veget_path": "projects/porto-vecchio/porto-vecchio_VEGET.jpeg",
+            "ortho_path": "projects/porto-vecchio/porto-vecchio_ORTHO.jpeg",
+            "duration_seconds": 12.5,
+            "regions": ["2A"],
+            "warnings": ["Aperçu: non généré"],
+            "timings": {"download": 4.5, "download/BDTOPO 2A": 4.0},
+        })
+    );
+
+    let parsed: ProjectCreated = serde_json::from_value(payload).unwrap();
+    assert_eq!(parsed, created);
+}
+
+#[test]
+fn test_project_creation_failed_payload() {
+    let failed = ProjectCreationFailed {
+        name: "porto-vecchio".to_string(),
+        message: "La surface de travail est incorrecte".to_string(),
+        cancelled: false,
+        resumable: true,
+        warnings: Vec::new(),
+    };
+
+    let payload = serde_json::to_value(&failed).unwrap();
+    let object = payload.as_object().unwrap();
+    assert_eq!(object.len(), 5);
+    assert_eq!(object["name"], "porto-vecchio");
+    assert_eq!(object["message"], "La surface de travail est incorrecte");
+    assert_eq!(object["cancelled"], false);
+    assert_eq!(object["resumable"], true);
+    assert!(object["warnings"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::events::ExportProgress;
+use firefront_gis_lib::export_queue::{ExportJobState, ExportQueue};
+use firefront_gis_lib::utils::{
+    ExportOptions, ExportTarget, WorkDir, copy_project, export_project_to, project_dir,
+};
+use std::fs;
+
+fn names(projects: &[&str]) -> Vec<String> {
+    projects.iter().map(|name| name.to_string()).collect()
+}
+
+fn states(progress: &[ExportProgress]) -> Vec<(String, usize, ExportJobState)> {
+    progress
+        .iter()
+        .map(|event| (event.project.clone(), event.position, event.state))
+        .collect()
+}
+
+#[test]
+fn test_export_queue_processes_jobs_in_order() {
+    let projects = ["porto-vecchio-queue-a", "porto-vecchio-queue-b"];
+    for name in projects {
+        let _ = fs::remove_dir_all(project_dir(name));
+        assert_result_ok(
+            &copy_project("porto-vecchio", name),
+            "Copying project failed",
+        );
+    }
+    let output = WorkDir::new(&RunConfig::snapshot(), "export_queue_output").unwrap();
+
+    let queue = ExportQueue::default();
+    let queued = queue.enqueue(
+        &names(&[projects[0], projects[1], "porto-vecchio"]),
+        ExportTarget::Zip,
+        ExportOptions::default(),
+    );
+    assert_eq!(queued.len(), 3);
+    assert!(queue.cancel("porto-vecchio").is_ok());
+
+    assert!(queue.claim_worker());
+    assert!(
+        !queue.claim_worker(),
+        "Une seule tâche doit traiter la file"
+    );
+
+    let mut events = Vec::new();
+    let mut exported = Vec::new();
+    queue.run_worker(
+        |job| {
+            exported.push(job.project.clone());
+            export_project_to(&job.project, job.target, &job.options, output.path(), None)
+                .map(|report| report.zip_path)
+                .map_err(|e| e.to_string())
+        },
+        |progress| events.push(progress),
+    );
+
+    assert_eq!(exported, names(&projects));
+    assert_eq!(
+        states(&events),
+        vec![
+            (projects[0].to_string(), 1, ExportJobState::Running),
+            (projects[0].to_string(), 1, ExportJobState::Done),
+            (projects[1].to_string(), 2, ExportJobState::Running),
+            (projects[1].to_string(), 2, ExportJobState::Done),
+        ]
+    );
+    for event in events.iter().filter(|e| e.state == ExportJobState::Done) {
+        let zip_path = event.zip_path.as_ref().expect("Chemin de l'archive absent");
+        assert_file_exists(zip_path, "Archive exportée introuvable");
+    }
+
+    let jobs = queue.jobs();
+    assert_eq!(jobs[2].project, "porto-vecchio");
+    assert_eq!(jobs[2].state, ExportJobState::Cancelled);
+    assert!(jobs[2].zip_path.is_none());
+    assert!(!queue.claim_worker(), "La file doit être vide");
+
+    for name in projects {
+        fs::remove_dir_all(project_dir(name)).unwrap();
+    }
+}
+
+#[test]
+fn test_export_queue_reports_failures_and_continues() {
+    let queue = ExportQueue::default();
+    queue.enqueue(
+        &names(&["projet-a", "projet-b"]),
+        ExportTarget::Simulator,
+        ExportOptions::default(),
+    );
+
+    assert!(queue.claim_worker());
+    let mut events = Vec::new();
+    queue.run_worker(
+        |job| {
+            if job.project == "projet-a" {
+                Err("disque plein".to_string())
+            } else {
+                Ok(std::path::PathBuf::from("export_projet-b"))
+            }
+        },
+        |progress| events.push(progress),
+    );
+
+    assert_eq!(events[1].state, ExportJobState::Failed);
+    assert_eq!(events[1].error.as_deref(), Some("disque plein"));
+    assert_eq!(events[3].state, ExportJobState::Done);
+    assert_eq!(events[3].zip_path.as_deref(), Some("export_projet-b"));
+}
+
+#[test]
+fn test_export_queue_enqueue_and_cancel_rules() {
+    let queue = ExportQueue::default();
+    queue.enqueue(
+        &names(&["projet-a", "projet-b"]),
+        ExportTarget::Zip,
+        ExportOptions::default(),
+    );
+
+    let again = queue.enqueue(
+        &names(&["projet-b", "projet-c"]),
+        ExportTarget::Zip,
+        ExportOptions::default(),
+    );
+    assert_eq!(
+        states(&again),
+        vec![("projet-c".to_string(), 3, ExportJobState::Queued)]
+    );
+
+    assert!(queue.cancel("inconnu").is_err());
+    let cancelled = queue.cancel("projet-b").unwrap();
+    assert_eq!(cancelled.position, 2);
+    assert_eq!(cancelled.state, ExportJobState::Cancelled);
+    assert!(queue.cancel("projet-b").is_err());
+
+    assert!(queue.claim_worker());
+    queue.run_worker(
+        |job| {
+            assert!(
+                queue.cancel(&job.project).is_err(),
+                "Un export en cours ne peut pas être annulé"
+            );
+            Ok(std::path::PathBuf::from(&job.project))
+        },
+        |_| {},
+    );
+
+    let finished = queue.enqueue(
+        &names(&["projet-a"]),
+        ExportTarget::Zip,
+        ExportOptions::default(),
+    );
+    assert_eq!(
+        finished[0].position, 1,
+        "Les exports terminés sont retirés de la file"
+    );
+    assert_eq!(queue.jobs().len(), 1);
+}
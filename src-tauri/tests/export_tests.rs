@@ -0,0 +1,377 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::utils::{
+    ExportOptions, ExportTarget, OutputLocationError, SimulatorManifest, WorkDir, compress_folder,
+    copy_project, export_project, export_project_to, export_project_with_hook,
+    file_manager_command, get_project_bounding_box, open_in_file_manager, output_location,
+    project_dir, resolution, simulator_manifest, slice_factor, validate_output_location,
+};
+use gdal::Dataset;
+use std::cell::RefCell;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+#[test]
+fn test_export_returns_zip_path() {
+    let project_name = "porto-vecchio";
+    let output = WorkDir::new(&RunConfig::snapshot(), "export_zip_path").unwrap();
+
+    let result = export_project_to(
+        project_name,
+        ExportTarget::Zip,
+        &ExportOptions::default(),
+        output.path(),
+        None,
+    );
+    assert_result_ok(&result, "Export failed");
+
+    let report = result.unwrap();
+    let zip_path = report.zip_path;
+    assert_file_exists(&zip_path.to_string_lossy(), "Export archive not found");
+    assert_eq!(zip_path.parent().unwrap(), output.path());
+    assert_eq!(zip_path.extension().unwrap(), "zip");
+    assert!(
+        zip_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with(&format!("export_{}_", project_name))
+    );
+    let mut entries = zip_entries(&zip_path);
+    entries.sort();
+    let mut files = report.files;
+    files.sort();
+    assert_eq!(entries, files);
+    assert!(report.total_bytes > 0);
+}
+
+/// Liste les fichiers d'une archive zip.
+fn zip_entries(zip_path: &Path) -> Vec<String> {
+    let mut archive = ZipArchive::new(fs::File::open(zip_path).unwrap()).unwrap();
+    (0..archive.len())
+        .map(|index| archive.by_index(index).unwrap().name().to_string())
+        .collect()
+}
+
+#[test]
+fn test_compress_folder_roundtrip() {
+    let source = Path::new("tests/tmp_compress_source");
+    let output_dir = Path::new("tests/tmp_compress_output");
+    let _ = fs::remove_dir_all(source);
+    let _ = fs::remove_dir_all(output_dir);
+    fs::create_dir_all(source.join("découpes/forêt")).unwrap();
+    fs::create_dir_all(output_dir).unwrap();
+    fs::write(source.join("manifeste.json"), b"{}").unwrap();
+    fs::write(source.join("découpes/forêt/tuile_é.txt"), "végétation").unwrap();
+    fs::write(source.join("découpes/ignoré.tmp"), b"tmp").unwrap();
+
+    let progress = RefCell::new(Vec::new());
+    let on_progress = |done: usize, total: usize| progress.borrow_mut().push((done, total));
+    let zip_path = compress_folder(
+        &source.to_string_lossy(),
+        "archive_accentuée",
+        &output_dir.to_string_lossy(),
+        &|path| path.extension().is_none_or(|ext| ext != "tmp"),
+        Some(&on_progress),
+    )
+    .unwrap();
+
+    assert_eq!(zip_path, output_dir.join("archive_accentuée.zip"));
+    let mut entries = zip_entries(&zip_path);
+    entries.sort();
+    assert_eq!(
+        entries,
+        vec!["découpes/forêt/tuile_é.txt", "manifeste.json"]
+    );
+    assert_eq!(progress.into_inner(), vec![(1, 2), (2, 2)]);
+
+    let mut archive = ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+    let mut content = String::new();
+    archive
+        .by_name("découpes/forêt/tuile_é.txt")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "végétation");
+
+    fs::remove_dir_all(source).unwrap();
+    fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn test_export_options_filter_zip_contents() {
+    let project_name = "porto-vecchio";
+    let output_dir = Path::new("tests/tmp_export_options");
+    let _ = fs::remove_dir_all(output_dir);
+    fs::create_dir_all(output_dir).unwrap();
+
+    let resources = project_dir(project_name).join("resources");
+    fs::create_dir_all(&resources).unwrap();
+    let marker_gpkg = resources.join("export_options_test.gpkg");
+    fs::write(&marker_gpkg, b"gpkg").unwrap();
+
+    let light = export_project_to(
+        project_name,
+        ExportTarget::Zip,
+        &ExportOptions::default(),
+        output_dir,
+        None,
+    )
+    .unwrap();
+    let light_entries = zip_entries(&light.zip_path);
+
+    let full = export_project_to(
+        project_name,
+        ExportTarget::Zip,
+        &ExportOptions {
+            include_resources: true,
+            include_full_images: true,
+            include_qgis_project: false,
+        },
+        output_dir,
+        None,
+    )
+    .unwrap();
+    let full_entries = zip_entries(&full.zip_path);
+
+    let tiff = format!("{}.tiff", project_name);
+    let gpkg = "resources/export_options_test.gpkg".to_string();
+    assert!(
+        light_entries
+            .iter()
+            .any(|entry| entry.starts_with("slices/"))
+    );
+    assert!(light_entries.contains(&"slices/index.json".to_string()));
+    assert!(!light_entries.contains(&tiff));
+    assert!(!light_entries.contains(&gpkg));
+    assert!(full_entries.contains(&tiff));
+    assert!(full_entries.contains(&gpkg));
+    for entry in &light_entries {
+        assert!(
+            full_entries.contains(entry),
+            "{} absent de l'export complet",
+            entry
+        );
+    }
+    assert!(light.total_bytes < full.total_bytes);
+
+    fs::remove_file(marker_gpkg).unwrap();
+    fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn test_export_fails_on_missing_slice() {
+    let project_name = "porto-vecchio-missing-slice";
+    let _ = fs::remove_dir_all(project_dir(project_name));
+    assert_result_ok(
+        &copy_project("porto-vecchio", project_name),
+        "Copying project failed",
+    );
+    let output = WorkDir::new(&RunConfig::snapshot(), "export_missing_slice").unwrap();
+
+    let removed = RefCell::new(None);
+    let remove_slice = || {
+        let slices = project_dir(project_name).join("slices");
+        let slice = fs::read_dir(&slices)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .find(|name| name.ends_with(".jpg"))
+            .expect("No slice to remove");
+        fs::remove_file(slices.join(&slice)).unwrap();
+        removed.replace(Some(slice));
+    };
+    let error = export_project_with_hook(
+        project_name,
+        ExportTarget::Zip,
+        &ExportOptions::default(),
+        output.path(),
+        None,
+        &remove_slice,
+    )
+    .unwrap_err()
+    .to_string();
+
+    let removed = removed.into_inner().expect("The hook was not called");
+    assert!(
+        error.contains(&removed),
+        "The error should name {}: {}",
+        removed,
+        error
+    );
+    assert!(
+        fs::read_dir(output.path()).unwrap().next().is_none(),
+        "No archive should be written"
+    );
+
+    fs::remove_dir_all(project_dir(project_name)).unwrap();
+}
+
+#[test]
+fn test_file_manager_command_per_os() {
+    let archive = Path::new("/exports/export_test_1.zip");
+
+    assert_eq!(
+        file_manager_command("windows", archive, true),
+        (
+            "explorer".to_string(),
+            vec!["/select,/exports/export_test_1.zip".to_string()]
+        )
+    );
+    assert_eq!(
+        file_manager_command("macos", archive, true),
+        (
+            "open".to_string(),
+            vec!["-R".to_string(), "/exports/export_test_1.zip".to_string()]
+        )
+    );
+    assert_eq!(
+        file_manager_command("linux", archive, true),
+        ("xdg-open".to_string(), vec!["/exports".to_string()])
+    );
+    assert_eq!(
+        file_manager_command("linux", Path::new("/projects/test"), false),
+        ("xdg-open".to_string(), vec!["/projects/test".to_string()])
+    );
+}
+
+#[test]
+fn test_open_deleted_folder_fails() {
+    let missing = Path::new("tmp/deleted_project_folder");
+    let _ = fs::remove_dir_all(missing);
+
+    assert!(open_in_file_manager(missing, false).is_err());
+}
+
+#[test]
+fn test_simulator_manifest_matches_geotransform() {
+    let project_name = "porto-vecchio";
+    let project_bb = get_project_bounding_box(project_name).unwrap();
+    let manifest = simulator_manifest(project_name, &project_bb, 500, 10.0);
+
+    let dataset = Dataset::open(project_dir(project_name).join("porto-vecchio.tiff")).unwrap();
+    let geo_transform = dataset.geo_transform().unwrap();
+    let (_, height) = dataset.raster_size();
+
+    assert_eq!(manifest.origin_x, geo_transform[0]);
+    assert_eq!(
+        manifest.origin_y,
+        geo_transform[3] + geo_transform[5] * height as f64
+    );
+    assert_eq!(manifest.origin_x, 1210000.0);
+    assert_eq!(manifest.origin_y, 6070000.0);
+    assert_eq!(manifest.crs, "EPSG:2154");
+    assert_eq!(manifest.tile_size_px, 500);
+    assert_eq!(manifest.tile_size_m, 5000.0);
+    assert_eq!((manifest.columns, manifest.rows), (5, 5));
+}
+
+#[test]
+fn test_export_simulator_layout() {
+    let project_name = "porto-vecchio";
+
+    let result = export_project(project_name, ExportTarget::Simulator);
+    assert_result_ok(&result, "Simulator export failed");
+
+    let export_dir = result.unwrap().zip_path;
+    assert!(export_dir.is_dir());
+    assert_eq!(export_dir.parent().unwrap(), output_location().as_path());
+
+    let manifest: SimulatorManifest =
+        serde_json::from_str(&fs::read_to_string(export_dir.join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest.tile_size_px, slice_factor());
+    assert_eq!(manifest.resolution, resolution());
+
+    let tiles: Vec<String> = fs::read_dir(export_dir.join("tiles"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert!(!tiles.is_empty(), "No tiles exported");
+    for tile in tiles.iter().filter(|tile| !tile.ends_with("_fuel.png")) {
+        let fuel_tile = tile.replace(".png", "_fuel.png");
+        assert!(tiles.contains(&fuel_tile), "Missing fuel tile for {}", tile);
+    }
+
+    fs::remove_dir_all(export_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_to_read_only_directory_fails_before_slicing() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let project_name = "porto-vecchio";
+    let output_dir = Path::new("tests/tmp_readonly_export");
+    let _ = fs::remove_dir_all(output_dir);
+    fs::create_dir_all(output_dir).unwrap();
+    fs::set_permissions(output_dir, fs::Permissions::from_mode(0o555)).unwrap();
+
+    // Les droits ne s'appliquent pas à root : le test n'a alors rien à vérifier.
+    let probe = output_dir.join("probe");
+    if fs::write(&probe, b"").is_ok() {
+        fs::remove_file(&probe).unwrap();
+        fs::set_permissions(output_dir, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(output_dir).unwrap();
+        return;
+    }
+
+    let slice_state = project_dir(project_name).join("slices/.state.json");
+    let state_before = fs::metadata(&slice_state).and_then(|m| m.modified()).ok();
+
+    let error = export_project_to(
+        project_name,
+        ExportTarget::Zip,
+        &ExportOptions::default(),
+        output_dir,
+        None,
+    )
+    .unwrap_err();
+    let output_error = error
+        .downcast_ref::<OutputLocationError>()
+        .expect("L'export doit échouer sur la vérification du dossier");
+    assert!(matches!(
+        output_error,
+        OutputLocationError::NotWritable { .. }
+    ));
+    assert!(error.to_string().contains("tests/tmp_readonly_export"));
+    assert_eq!(
+        fs::metadata(&slice_state).and_then(|m| m.modified()).ok(),
+        state_before,
+        "Le découpage ne doit pas commencer"
+    );
+
+    fs::set_permissions(output_dir, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn test_output_location_validation() {
+    let output_dir = Path::new("tests/tmp_output_location");
+    let _ = fs::remove_dir_all(output_dir);
+
+    validate_output_location(output_dir, 0).expect("Le dossier doit être créé");
+    assert!(output_dir.is_dir());
+    assert!(
+        fs::read_dir(output_dir).unwrap().next().is_none(),
+        "Le fichier témoin doit être supprimé"
+    );
+
+    let error = validate_output_location(output_dir, u64::MAX).unwrap_err();
+    assert!(matches!(
+        error,
+        OutputLocationError::InsufficientSpace { .. }
+    ));
+
+    let file_path = output_dir.join("not_a_folder");
+    fs::write(&file_path, b"").unwrap();
+    let error = validate_output_location(&file_path, 0).unwrap_err();
+    assert!(matches!(error, OutputLocationError::NotCreatable { .. }));
+    assert!(error.to_string().contains("not_a_folder"));
+
+    fs::remove_dir_all(output_dir).unwrap();
+}
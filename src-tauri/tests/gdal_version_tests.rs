@@ -0,0 +1,194 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gdal_version::{GdalVersion, MAKEVALID_GDAL, MIN_SUPPORTED_GDAL};
+use firefront_gis_lib::gis_operation::processing::rasterize_args;
+use firefront_gis_lib::gis_operation::{
+    clip_to_bb_args, convert_to_gpkg_args, convert_to_gpkg_in_crs, ring_repair_args,
+};
+use firefront_gis_lib::utils::{BoundingBox, WorkDir};
+use gdal::Dataset;
+use gdal::vector::LayerAccess;
+
+const LEGACY: GdalVersion = GdalVersion::new(3, 8, 4);
+const RECENT: GdalVersion = GdalVersion::new(3, 11, 0);
+
+#[test]
+fn test_parse_release_names() {
+    assert_eq!(GdalVersion::parse("3.8.4"), Some(LEGACY));
+    assert_eq!(GdalVersion::parse("3.11.0dev"), Some(RECENT));
+    assert_eq!(
+        GdalVersion::parse("3.10.0rc1"),
+        Some(GdalVersion::new(3, 10, 0))
+    );
+    assert_eq!(GdalVersion::parse("3.4"), Some(MIN_SUPPORTED_GDAL));
+    assert_eq!(GdalVersion::parse("inconnue"), None);
+    assert_eq!(GdalVersion::parse("3"), None);
+
+    assert!(RECENT > GdalVersion::new(3, 9, 12));
+    assert_eq!(RECENT.to_string(), "3.11.0");
+    assert!(GdalVersion::detect().is_some());
+}
+
+#[test]
+fn test_minimum_supported_version_warning() {
+    assert!(LEGACY.is_supported());
+    assert!(MIN_SUPPORTED_GDAL.support_warning().is_none());
+
+    let warning = GdalVersion::new(3, 3, 2).support_warning().unwrap();
+    assert!(warning.contains("3.3.2"), "{}", warning);
+    assert!(warning.contains("3.4.0"), "{}", warning);
+}
+
+#[test]
+fn test_ring_repair_args_by_version() {
+    assert_eq!(
+        ring_repair_args(LEGACY),
+        [
+            "--config",
+            "OGR_GEOMETRY_ACCEPT_UNCLOSED_RING",
+            "NO",
+            "--config",
+            "OGR_GEOMETRY_CORRECT_UNCLOSED_RINGS",
+            "YES",
+        ]
+    );
+    assert_eq!(ring_repair_args(MAKEVALID_GDAL), ["-makevalid"]);
+    assert_eq!(ring_repair_args(RECENT), ["-makevalid"]);
+}
+
+#[test]
+fn test_convert_and_clip_args_by_version() {
+    let legacy = convert_to_gpkg_args(2154, LEGACY);
+    assert_eq!(
+        legacy,
+        [
+            "-f",
+            "GPKG",
+            "-t_srs",
+            "EPSG:2154",
+            "-nlt",
+            "PROMOTE_TO_MULTI",
+            "-dim",
+            "XY",
+            "--config",
+            "OGR_ARC_STEPSIZE",
+            "0.1",
+            "--config",
+            "OGR_GEOMETRY_ACCEPT_UNCLOSED_RING",
+            "NO",
+            "--config",
+            "OGR_GEOMETRY_CORRECT_UNCLOSED_RINGS",
+            "YES",
+        ]
+    );
+    let recent = convert_to_gpkg_args(2154, RECENT);
+    assert_eq!(recent[..11], legacy[..11]);
+    assert_eq!(recent[11..], ["-makevalid"]);
+
+    let bb = BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6095000.0);
+    let legacy = clip_to_bb_args(&bb, LEGACY);
+    assert_eq!(
+        legacy[..13],
+        [
+            "-f",
+            "GPKG",
+            "-clipsrc",
+            "1210000",
+            "6070000",
+            "1235000",
+            "6095000",
+            "-nlt",
+            "PROMOTE_TO_MULTI",
+            "-skipfailures",
+            "--config",
+            "OGR_ENABLE_PARTIAL_REPROJECTION",
+            "YES",
+        ]
+    );
+    assert!(
+        legacy
+            .iter()
+            .any(|arg| arg == "OGR_GEOMETRY_CORRECT_UNCLOSED_RINGS")
+    );
+    let recent = clip_to_bb_args(&bb, RECENT);
+    assert_eq!(recent[..13], legacy[..13]);
+    assert_eq!(recent[13..], ["-makevalid"]);
+    assert!(!recent.iter().any(|arg| arg.starts_with("OGR_GEOMETRY_")));
+}
+
+#[test]
+fn test_rasterize_args() {
+    let extent = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let layers = vec!["BATIMENT".to_string(), "RESERVOIR".to_string()];
+    assert_eq!(
+        rasterize_args(
+            &extent,
+            (500, 500),
+            [10, 20, 30],
+            &layers,
+            Some("HAUTEUR > 3")
+        ),
+        [
+            "-burn",
+            "10",
+            "-burn",
+            "20",
+            "-burn",
+            "30",
+            "-ts",
+            "500",
+            "500",
+            "-te",
+            "1210000",
+            "6070000",
+            "1215000",
+            "6075000",
+            "-l",
+            "BATIMENT",
+            "-l",
+            "RESERVOIR",
+            "-where",
+            "HAUTEUR > 3",
+        ]
+    );
+    assert!(
+        !rasterize_args(&extent, (500, 500), [0, 0, 0], &layers, None)
+            .iter()
+            .any(|arg| arg == "-where")
+    );
+}
+
+#[test]
+fn test_conversion_with_installed_gdal() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_gdal_version_conversion").unwrap();
+    let input = work_dir.path_str("zone.geojson");
+    std::fs::write(
+        &input,
+        r#"{
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": { "nom": "zone" },
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[9.2, 41.6], [9.3, 41.6], [9.3, 41.7], [9.2, 41.6]]]
+                }
+            }]
+        }"#,
+    )
+    .unwrap();
+    let output = work_dir.path_str("zone.gpkg");
+
+    let result = convert_to_gpkg_in_crs(&input, &output, 2154);
+    assert_result_ok(
+        &result,
+        &format!("Conversion with GDAL {} failed", GdalVersion::current()),
+    );
+
+    let dataset = Dataset::open(&output).unwrap();
+    let features: u64 = dataset.layers().map(|layer| layer.feature_count()).sum();
+    assert_eq!(features, 1);
+}
@@ -3,14 +3,34 @@ mod common;
 use common::*;
 
 use firefront_gis_lib::{
+    app_setup::{CONFIG, RunConfig, TiffCompression},
     gis_operation::{
-        clip_to_bb, convert_to_gpkg, create_project, fusion_datasets,
-        layers::download_satellite_jpeg, regions::create_region_geojson,
+        clip_to_bb, colormap, convert_to_gpkg, create_project, fusion_datasets,
+        layer_config::{GeometryHint, LayerConfig, LayerDefinition, LayerGroup},
+        layers::{
+            add_configured_layer, assemble_land_ortho, download_satellite_jpeg, land_mask,
+            ortho_tiff_to_jpeg, split_land_tiles,
+        },
+        processing::{
+            ProjectRaster, apply_overlay, class_raster_path, create_class_raster, overlay_in_place,
+            read_band,
+        },
+        regions::create_region_geojson,
+    },
+    progress::NoProgress,
+    utils::{
+        BoundingBox, DEFAULT_PROJECT_EPSG, GridError, GridSnap, JPEG_EXPORT_STRIPE_ROWS, SnapMode,
+        WorkDir, composite_over_background, create_directory_if_not_exists, export_to_jpg,
+        extract_files_by_name, nodata_color, veget_jpeg_quality,
     },
-    utils::{create_directory_if_not_exists, export_to_jpg, extract_files_by_name},
 };
-use gdal::Dataset;
+use gdal::raster::{Buffer, ColorInterpretation};
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{Geometry, LayerAccess, LayerOptions};
+use gdal::{Dataset, DriverManager};
 use std::fs;
+use std::path::Path;
+use std::process::Command;
 
 #[test]
 fn test_project_creation() {
@@ -96,12 +116,170 @@ fn test_get_regional_gpkg() {
     assert_result_ok(&result, "Creating regional GeoPackage failed");
 }
 
+#[test]
+fn test_raster_size_aligned_extent() {
+    let bbox = get_test_bounding_box();
+    assert_eq!(bbox.raster_size(10.0), Ok((2500, 2500)));
+    assert_eq!(bbox.raster_size(5.0), Ok((5000, 5000)));
+
+    let fractional = BoundingBox::new(0.5, 0.25, 100.5, 50.25);
+    assert_eq!(fractional.raster_size(0.25), Ok((400, 200)));
+}
+
+#[test]
+fn test_raster_size_rejects_misaligned_extent() {
+    let bbox = BoundingBox::new(1210000.0, 6070000.0, 1235005.0, 6095003.0);
+
+    match bbox.raster_size(10.0) {
+        Err(GridError::InvalidBoundingBox {
+            bb,
+            resolution,
+            suggested,
+        }) => {
+            assert_eq!(bb, bbox);
+            assert_eq!(resolution, 10.0);
+            assert_eq!(
+                suggested,
+                BoundingBox::new(1210000.0, 6069993.0, 1235010.0, 6095003.0)
+            );
+            assert_eq!(suggested.raster_size(10.0), Ok((2501, 2501)));
+        }
+        other => panic!("Expected an InvalidBoundingBox error, got {:?}", other),
+    }
+
+    let project_path = "tests/res/test_misaligned_project.tiff";
+    remove_file_if_exists(project_path);
+    let error = create_project(project_path, &bbox).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<GridError>(),
+        Some(GridError::InvalidBoundingBox { .. })
+    ));
+    assert!(!std::path::Path::new(project_path).exists());
+}
+
+#[test]
+fn test_raster_size_rejects_negative_extent() {
+    let inverted = BoundingBox::new(1235000.0, 6070000.0, 1210000.0, 6095000.0);
+    assert!(matches!(
+        inverted.raster_size(10.0),
+        Err(GridError::EmptyExtent { .. })
+    ));
+
+    let flat = BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6070000.0);
+    assert!(matches!(
+        flat.raster_size(10.0),
+        Err(GridError::EmptyExtent { .. })
+    ));
+
+    assert_eq!(
+        get_test_bounding_box().raster_size(0.0),
+        Err(GridError::InvalidResolution(0.0))
+    );
+}
+
+#[test]
+fn test_snap_to_grid_expand() {
+    let bbox = BoundingBox::new(1210000.0, 6070000.0, 1234200.0, 6094600.0);
+    let snapped = bbox.snap_to_grid(5000.0, SnapMode::Expand).unwrap();
+    assert_eq!(
+        snapped,
+        BoundingBox::new(1210000.0, 6069600.0, 1235000.0, 6094600.0)
+    );
+    assert_eq!(snapped.raster_size(10.0), Ok((2500, 2500)));
+
+    let aligned = get_test_bounding_box();
+    assert_eq!(
+        aligned.snap_to_grid(5000.0, SnapMode::Expand).unwrap(),
+        aligned
+    );
+}
+
+#[test]
+fn test_snap_to_grid_shrink() {
+    let bbox = BoundingBox::new(1210000.0, 6070000.0, 1234200.0, 6094600.0);
+    let snapped = bbox.snap_to_grid(5000.0, SnapMode::Shrink).unwrap();
+    assert_eq!(
+        snapped,
+        BoundingBox::new(1210000.0, 6074600.0, 1230000.0, 6094600.0)
+    );
+    assert!(bbox.contains(&snapped));
+
+    let aligned = get_test_bounding_box();
+    assert_eq!(
+        aligned.snap_to_grid(5000.0, SnapMode::Shrink).unwrap(),
+        aligned
+    );
+}
+
+#[test]
+fn test_snap_to_grid_center() {
+    // 24 200 m arrondis à 25 000 m, 21 000 m arrondis à 20 000 m, autour du même centre.
+    let bbox = BoundingBox::new(1210000.0, 6070000.0, 1234200.0, 6091000.0);
+    let snapped = bbox.snap_to_grid(5000.0, SnapMode::Center).unwrap();
+    assert_eq!(
+        snapped,
+        BoundingBox::new(1209600.0, 6070500.0, 1234600.0, 6090500.0)
+    );
+    assert_eq!(
+        (snapped.xmin + snapped.xmax) / 2.0,
+        (bbox.xmin + bbox.xmax) / 2.0
+    );
+    assert_eq!(
+        (snapped.ymin + snapped.ymax) / 2.0,
+        (bbox.ymin + bbox.ymax) / 2.0
+    );
+}
+
+#[test]
+fn test_snap_to_grid_smaller_than_one_cell() {
+    let tiny = BoundingBox::new(1210000.0, 6070000.0, 1211000.0, 6070400.0);
+    for mode in [SnapMode::Expand, SnapMode::Shrink, SnapMode::Center] {
+        let snapped = tiny.snap_to_grid(5000.0, mode).unwrap();
+        assert_eq!(snapped.width(), 5000.0, "{:?}", mode);
+        assert_eq!(snapped.height(), 5000.0, "{:?}", mode);
+    }
+}
+
+#[test]
+fn test_snap_to_grid_rejects_invalid_extents() {
+    let inverted = BoundingBox::new(1235000.0, 6070000.0, 1210000.0, 6095000.0);
+    let flat = BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6070000.0);
+    for bbox in [inverted, flat] {
+        for mode in [SnapMode::Expand, SnapMode::Shrink, SnapMode::Center] {
+            assert!(matches!(
+                bbox.snap_to_grid(5000.0, mode),
+                Err(GridError::EmptyExtent { .. })
+            ));
+        }
+    }
+
+    assert_eq!(
+        get_test_bounding_box().snap_to_grid(-5000.0, SnapMode::Expand),
+        Err(GridError::InvalidResolution(-5000.0))
+    );
+}
+
+#[test]
+fn test_grid_snap_reports_edge_deltas() {
+    let bbox = BoundingBox::new(1210000.0, 6070000.0, 1234200.0, 6094600.0);
+    let snap = GridSnap::new(&bbox, 5000.0, SnapMode::Expand).unwrap();
+    assert_eq!(snap.delta, BoundingBox::new(0.0, -400.0, 800.0, 0.0));
+
+    let payload = serde_json::to_value(snap).unwrap();
+    assert_eq!(payload["bbox"]["xmax"], 1235000.0);
+    assert_eq!(payload["delta"]["ymin"], -400.0);
+    assert_eq!(
+        serde_json::from_str::<SnapMode>("\"center\"").unwrap(),
+        SnapMode::Center
+    );
+}
+
 #[test]
 fn test_export_to_jpeg() {
     let input_tiff = "tests/res/test1.tiff";
     let output_jpeg = "tests/res/test1.jpg";
 
-    export_to_jpg(input_tiff, output_jpeg).expect("Export to JPEG failed");
+    export_to_jpg(input_tiff, output_jpeg, &RunConfig::snapshot()).expect("Export to JPEG failed");
     assert_file_exists(output_jpeg, "JPEG file was not created");
 
     let dataset = Dataset::open(output_jpeg).unwrap();
@@ -131,17 +309,38 @@ fn test_satellite_download_and_compare() {
     let vegetation_jpg = "tests/res/test1_vegetation.jpg";
     let bounding_box = get_test_bounding_box();
 
-    let result = download_satellite_jpeg(satellite_jpg, &bounding_box);
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_satellite").unwrap();
+
+    let result = download_satellite_jpeg(
+        &work_dir,
+        satellite_jpg,
+        &bounding_box,
+        DEFAULT_PROJECT_EPSG,
+        None,
+        &NoProgress,
+        &RunConfig::snapshot(),
+    );
     assert_result_ok(&result, "Failed to download satellite JPEG");
     assert_file_exists(satellite_jpg, "Satellite JPEG not created");
     check_jpeg_properties(satellite_jpg, 10.0, "Satellite JPEG");
 
-    let result = export_to_jpg(vegetation_tiff, vegetation_jpg);
+    let result = export_to_jpg(vegetation_tiff, vegetation_jpg, &RunConfig::snapshot());
     assert_result_ok(&result, "Failed to export vegetation to JPEG");
     check_jpeg_properties(vegetation_jpg, 10.0, "Vegetation JPEG");
 
     assert_jpegs_match(satellite_jpg, vegetation_jpg);
 
+    let expected = bounding_box.raster_size(10.0).unwrap();
+    for jpg in [satellite_jpg, vegetation_jpg] {
+        let dataset = Dataset::open(jpg).unwrap();
+        assert_eq!(
+            dataset.raster_size(),
+            expected,
+            "{} does not match the project grid",
+            jpg
+        );
+    }
+
     // Cleanup
     remove_file_if_exists(satellite_jpg);
     remove_file_if_exists(vegetation_jpg);
@@ -163,6 +362,931 @@ fn test_fusion() {
         "tmp/FORMATION_VEGETALE_2B/FORMATION_VEGETALE.shp".to_string(),
     ];
 
-    let res = fusion_datasets(&dataset, "tmp/FORMATION_VEGETALE_FUSION.gpkg");
+    let output = "tmp/FORMATION_VEGETALE_FUSION.gpkg";
+    let res = fusion_datasets(&dataset, output, false);
     assert_result_ok(&res, "Fusion of datasets failed");
+
+    let feature_count = |path: &str| -> u64 {
+        Dataset::open(path)
+            .unwrap()
+            .layers()
+            .map(|layer| layer.feature_count())
+            .sum()
+    };
+    let merged = Dataset::open(output).unwrap();
+    assert_eq!(merged.layer_count(), 1);
+    drop(merged);
+    assert_eq!(
+        feature_count(output),
+        dataset.iter().map(|path| feature_count(path)).sum::<u64>(),
+        "The merged layer must hold every source feature"
+    );
+
+    // Une fusion en échec laisse le fichier précédent intact.
+    let missing = [
+        dataset[0].clone(),
+        "tmp/FORMATION_VEGETALE_MISSING.shp".to_string(),
+    ];
+    assert!(fusion_datasets(&missing, output, false).is_err());
+    assert_eq!(
+        feature_count(output),
+        dataset.iter().map(|path| feature_count(path)).sum::<u64>()
+    );
+    assert!(!Path::new("tmp/FORMATION_VEGETALE_FUSION.partial.gpkg").exists());
+}
+
+/// Clés de déduplication (`ID`) des entités d'un GeoPackage, `None` pour une clé NULL.
+fn dedup_keys(path: &str) -> Vec<Option<String>> {
+    let dataset = Dataset::open(path).unwrap();
+    let mut layer = dataset.layer(0).unwrap();
+    let index = layer
+        .defn()
+        .fields()
+        .position(|field| field.name().eq_ignore_ascii_case("ID"))
+        .expect("BD Forêt layers have an ID field");
+    layer
+        .features()
+        .map(|feature| feature.field_as_string(index).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_fusion_removes_boundary_duplicates() {
+    // Cozzano, à cheval sur la limite 2A/2B
+    let overlapping_bb = BoundingBox::new(1199000.0, 6104000.0, 1219000.0, 6120000.0);
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "dedup").unwrap();
+
+    let mut clipped = Vec::new();
+    for region in ["2A", "2B"] {
+        let extract_dir = work_dir.path_str(region);
+        extract_files_by_name(
+            &format!("tests/res/BDFORET_{}.7z", region),
+            "FORMATION_VEGETALE",
+            &extract_dir,
+        )
+        .unwrap();
+        let output = work_dir.path_str(format!("FORMATION_VEGETALE_{}.gpkg", region));
+        clip_to_bb(
+            &format!("{}/FORMATION_VEGETALE/FORMATION_VEGETALE.shp", extract_dir),
+            &output,
+            &overlapping_bb,
+        )
+        .unwrap();
+        clipped.push(output);
+    }
+
+    // Entités sans identifiant : elles ne sont pas des doublons les unes des autres.
+    {
+        let dataset = Dataset::open_ex(
+            &clipped[0],
+            gdal::DatasetOptions {
+                open_flags: gdal::GdalOpenFlags::GDAL_OF_UPDATE
+                    | gdal::GdalOpenFlags::GDAL_OF_VECTOR,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut layer = dataset.layer(0).unwrap();
+        for wkt in [
+            "POLYGON ((1205000 6110000, 1205100 6110000, 1205100 6110100, 1205000 6110000))",
+            "POLYGON ((1206000 6111000, 1206100 6111000, 1206100 6111100, 1206000 6111000))",
+        ] {
+            layer
+                .create_feature(Geometry::from_wkt(wkt).unwrap())
+                .unwrap();
+        }
+    }
+
+    let keys: Vec<Option<String>> = clipped.iter().flat_map(|path| dedup_keys(path)).collect();
+    let null_keys = keys.iter().filter(|key| key.is_none()).count();
+    assert_eq!(null_keys, 2);
+    let distinct: std::collections::HashSet<&String> = keys.iter().flatten().collect();
+    let expected = distinct.len() + null_keys;
+
+    let output = work_dir.path_str("FORMATION_VEGETALE_FUSION.gpkg");
+    let removed = fusion_datasets(&clipped, &output, true).expect("Fusion with dedup failed");
+    assert_eq!(removed as usize, keys.len() - expected);
+
+    let merged = dedup_keys(&output);
+    assert_eq!(merged.len(), expected, "Merged layer should keep one copy");
+    assert_eq!(
+        merged.iter().filter(|key| key.is_none()).count(),
+        null_keys,
+        "Features without a key must all be kept"
+    );
+}
+
+/// Crée un raster de superposition RGB uniforme calé sur le projet.
+fn create_uniform_overlay(project_file_path: &str, overlay_path: &str, color: [u8; 3]) {
+    let project = Dataset::open(project_file_path).unwrap();
+    let (width, height) = project.raster_size();
+    let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+    let mut overlay = driver.create(overlay_path, width, height, 3).unwrap();
+    overlay
+        .set_geo_transform(&project.geo_transform().unwrap())
+        .unwrap();
+    overlay.set_projection(&project.projection()).unwrap();
+    for (i, value) in color.iter().enumerate() {
+        overlay
+            .rasterband(i + 1)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), vec![*value; width * height]),
+            )
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_concurrent_overlays_use_separate_work_dirs() {
+    create_directory_if_not_exists("tmp/overlays").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let colors = [[200u8, 10, 10], [10u8, 10, 200]];
+
+    for (i, color) in colors.iter().enumerate() {
+        let project_file_path = format!("tmp/overlays/project_{}.tiff", i);
+        create_project(&project_file_path, &project_bb).unwrap();
+        create_uniform_overlay(
+            &project_file_path,
+            &format!("tmp/overlays/overlay_{}.tif", i),
+            *color,
+        );
+    }
+
+    let handles: Vec<_> = (0..colors.len())
+        .map(|i| {
+            std::thread::spawn(move || {
+                let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_overlay").unwrap();
+                let work_path = work_dir.path().to_path_buf();
+                apply_overlay(
+                    &work_dir,
+                    &format!("tmp/overlays/project_{}.tiff", i),
+                    &format!("tmp/overlays/overlay_{}.tif", i),
+                    |&value| value > 0,
+                    None,
+                    &RunConfig::snapshot(),
+                )
+                .map_err(|e| e.to_string())
+                .map(|_| work_path)
+            })
+        })
+        .collect();
+
+    let work_paths: Vec<_> = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap().expect("Overlay failed"))
+        .collect();
+
+    assert_ne!(work_paths[0], work_paths[1]);
+    for path in &work_paths {
+        assert!(!path.exists(), "Work dir not removed: {:?}", path);
+    }
+
+    for (i, color) in colors.iter().enumerate() {
+        let dataset = Dataset::open(format!("tmp/overlays/project_{}.tiff", i)).unwrap();
+        for (band_index, value) in color.iter().enumerate() {
+            let data = dataset
+                .rasterband(band_index + 1)
+                .unwrap()
+                .read_as::<u8>((50, 50), (1, 1), (1, 1), None)
+                .unwrap();
+            assert_eq!(data.data()[0], *value, "Project {} has wrong color", i);
+        }
+    }
+
+    fs::remove_dir_all("tmp/overlays").unwrap();
+}
+
+#[test]
+fn test_concurrent_overlays_in_shared_work_dir() {
+    create_directory_if_not_exists("tmp/shared_overlays").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let colors = [[200u8, 120, 10], [10u8, 120, 200]];
+    for (i, color) in colors.iter().enumerate() {
+        let project_file_path = format!("tmp/shared_overlays/project_{}.tiff", i);
+        create_project(&project_file_path, &project_bb).unwrap();
+        create_uniform_overlay(
+            &project_file_path,
+            &format!("tmp/shared_overlays/overlay_{}.tif", i),
+            *color,
+        );
+    }
+
+    // Reste d'une superposition interrompue du premier projet, et ancien nom fixe.
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_shared_overlay").unwrap();
+    let stale = work_dir.path_for("overlay_output_project_0_1_0.tif");
+    fs::copy("tmp/shared_overlays/overlay_1.tif", &stale).unwrap();
+    fs::copy(
+        "tmp/shared_overlays/overlay_1.tif",
+        work_dir.path_for("output.tif"),
+    )
+    .unwrap();
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..colors.len())
+            .map(|i| {
+                let work_dir = &work_dir;
+                scope.spawn(move || {
+                    apply_overlay(
+                        work_dir,
+                        &format!("tmp/shared_overlays/project_{}.tiff", i),
+                        &format!("tmp/shared_overlays/overlay_{}.tif", i),
+                        |&value| value > 0,
+                        None,
+                        &RunConfig::snapshot(),
+                    )
+                    .map_err(|e| e.to_string())
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().expect("Overlay failed");
+        }
+    });
+
+    for (i, color) in colors.iter().enumerate() {
+        let bands = read_bands(&format!("tmp/shared_overlays/project_{}.tiff", i));
+        for (band, value) in bands.iter().zip(color) {
+            assert!(
+                band.iter().all(|pixel| pixel == value),
+                "Project {} has wrong color",
+                i
+            );
+        }
+    }
+    assert!(!stale.exists());
+    assert_eq!(fs::read_dir(work_dir.path()).unwrap().count(), 0);
+
+    fs::remove_dir_all("tmp/shared_overlays").unwrap();
+}
+
+/// Crée un raster de superposition calé sur le projet, coloré une colonne sur deux.
+fn create_striped_overlay(project_file_path: &str, overlay_path: &str, color: [u8; 3]) {
+    create_uniform_overlay(project_file_path, overlay_path, color);
+    let overlay = Dataset::open_ex(
+        overlay_path,
+        gdal::DatasetOptions {
+            open_flags: gdal::GdalOpenFlags::GDAL_OF_UPDATE,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let (width, height) = overlay.raster_size();
+    for (i, value) in color.iter().enumerate() {
+        let data: Vec<u8> = (0..width * height)
+            .map(|pixel| if (pixel % width) % 2 == 0 { *value } else { 0 })
+            .collect();
+        overlay
+            .rasterband(i + 1)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), data),
+            )
+            .unwrap();
+    }
+}
+
+fn read_bands(project_file_path: &str) -> Vec<Vec<u8>> {
+    let dataset = Dataset::open(project_file_path).unwrap();
+    (1..=4)
+        .map(|band_index| read_band(&dataset, band_index).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_overlay_in_place_matches_rename_based_overlay() {
+    create_directory_if_not_exists("tmp/in_place").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let renamed_project = "tmp/in_place/renamed.tiff";
+    let in_place_project = "tmp/in_place/in_place.tiff";
+    let overlay_path = "tmp/in_place/overlay.tif";
+    create_project(renamed_project, &project_bb).unwrap();
+    create_project(in_place_project, &project_bb).unwrap();
+    create_striped_overlay(renamed_project, overlay_path, [200, 120, 40]);
+
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_in_place").unwrap();
+    apply_overlay(
+        &work_dir,
+        renamed_project,
+        overlay_path,
+        |&value| value > 0,
+        None,
+        &RunConfig::snapshot(),
+    )
+    .unwrap();
+
+    let project = ProjectRaster::open(in_place_project).unwrap();
+    overlay_in_place(&project, overlay_path, |&value| value > 0, None).unwrap();
+    project.close().unwrap();
+
+    assert_eq!(read_bands(in_place_project), read_bands(renamed_project));
+    let renamed = Dataset::open(renamed_project).unwrap();
+    let in_place = Dataset::open(in_place_project).unwrap();
+    assert_eq!(
+        in_place.geo_transform().unwrap(),
+        renamed.geo_transform().unwrap()
+    );
+    assert_eq!(in_place.projection(), renamed.projection());
+
+    fs::remove_dir_all("tmp/in_place").unwrap();
+}
+
+#[test]
+fn test_deflate_project_round_trips_through_overlay() {
+    create_directory_if_not_exists("tmp/deflate_overlay").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let plain_project = "tmp/deflate_overlay/plain.tiff";
+    let deflate_project = "tmp/deflate_overlay/deflate.tiff";
+    let overlay_path = "tmp/deflate_overlay/overlay.tif";
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_deflate_overlay").unwrap();
+
+    let previous = CONFIG.lock().unwrap().image_quality.tiff_compression;
+    CONFIG.lock().unwrap().image_quality.tiff_compression = TiffCompression::None;
+    create_project(plain_project, &project_bb).unwrap();
+    create_striped_overlay(plain_project, overlay_path, [200, 120, 40]);
+    apply_overlay(
+        &work_dir,
+        plain_project,
+        overlay_path,
+        |&v| v > 0,
+        None,
+        &RunConfig::snapshot(),
+    )
+    .unwrap();
+
+    CONFIG.lock().unwrap().image_quality.tiff_compression = TiffCompression::Deflate;
+    create_project(deflate_project, &project_bb).unwrap();
+    apply_overlay(
+        &work_dir,
+        deflate_project,
+        overlay_path,
+        |&v| v > 0,
+        None,
+        &RunConfig::snapshot(),
+    )
+    .unwrap();
+    CONFIG.lock().unwrap().image_quality.tiff_compression = previous;
+
+    let compression = Dataset::open(deflate_project)
+        .unwrap()
+        .metadata_item("COMPRESSION", "IMAGE_STRUCTURE");
+    assert_eq!(compression.as_deref(), Some("DEFLATE"));
+    assert!(
+        fs::metadata(deflate_project).unwrap().len() < fs::metadata(plain_project).unwrap().len()
+    );
+    assert_eq!(read_bands(deflate_project), read_bands(plain_project));
+
+    fs::remove_dir_all("tmp/deflate_overlay").unwrap();
+}
+
+#[test]
+fn test_failed_layer_keeps_project_readable() {
+    create_directory_if_not_exists("tmp/in_place_failure").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/in_place_failure/project.tiff";
+    let reference_path = "tmp/in_place_failure/reference.tiff";
+    let overlay_path = "tmp/in_place_failure/overlay.tif";
+    create_project(project_file_path, &project_bb).unwrap();
+    create_project(reference_path, &project_bb).unwrap();
+    create_striped_overlay(project_file_path, overlay_path, [10, 200, 10]);
+
+    let reference = ProjectRaster::open(reference_path).unwrap();
+    overlay_in_place(&reference, overlay_path, |&value| value > 0, None).unwrap();
+    reference.close().unwrap();
+
+    // Comme dans `add_layers` : la première couche est vidée sur le disque,
+    // puis la couche suivante échoue avant toute écriture.
+    let mut project = ProjectRaster::open(project_file_path).unwrap();
+    overlay_in_place(&project, overlay_path, |&value| value > 0, None).unwrap();
+    project.flush().unwrap();
+    let result = overlay_in_place(
+        &project,
+        "tmp/in_place_failure/missing.tif",
+        |&value| value > 0,
+        None,
+    );
+    assert!(result.is_err());
+    drop(project);
+
+    assert_eq!(read_bands(project_file_path), read_bands(reference_path));
+
+    fs::remove_dir_all("tmp/in_place_failure").unwrap();
+}
+
+#[test]
+fn test_composite_over_background() {
+    assert_eq!(composite_over_background(200, 255, 10), 200);
+    assert_eq!(composite_over_background(200, 0, 10), 10);
+    assert_eq!(composite_over_background(255, 128, 0), 128);
+    assert_eq!(composite_over_background(0, 128, 255), 127);
+}
+
+#[test]
+fn test_export_to_jpeg_matches_imagemagick() {
+    create_directory_if_not_exists("tmp/jpeg_export").unwrap();
+    let input_tiff = "tests/res/test1.tiff";
+    let output_jpeg = "tmp/jpeg_export/test1.jpeg";
+    let magick_jpeg = "tmp/jpeg_export/test1_magick.jpeg";
+
+    export_to_jpg(input_tiff, output_jpeg, &RunConfig::snapshot()).expect("Export to JPEG failed");
+    assert_file_exists(
+        "tmp/jpeg_export/test1.wld",
+        "World file was not created next to the JPEG",
+    );
+    let [red, green, blue] = nodata_color();
+    let status = std::process::Command::new("magick")
+        .arg(input_tiff)
+        .args([
+            "-background",
+            &format!("rgb({},{},{})", red, green, blue),
+            "-alpha",
+            "remove",
+            "-alpha",
+            "off",
+            "-quality",
+            &veget_jpeg_quality().to_string(),
+            magick_jpeg,
+        ])
+        .status()
+        .expect("ImageMagick is required to compare the export paths");
+    assert!(status.success());
+
+    let exported = image::open(output_jpeg).unwrap().to_rgb8();
+    let reference = image::open(magick_jpeg).unwrap().to_rgb8();
+    assert_eq!(exported.dimensions(), reference.dimensions());
+    let total_difference: u64 = exported
+        .as_raw()
+        .iter()
+        .zip(reference.as_raw())
+        .map(|(a, b)| a.abs_diff(*b) as u64)
+        .sum();
+    let mean_difference = total_difference as f64 / exported.as_raw().len() as f64;
+    assert!(
+        mean_difference < 2.0,
+        "Exported JPEG differs from the ImageMagick export: mean difference = {}",
+        mean_difference
+    );
+
+    let project = Dataset::open(input_tiff).unwrap();
+    let exported = Dataset::open(output_jpeg).unwrap();
+    assert_eq!(
+        project.geo_transform().unwrap(),
+        exported.geo_transform().unwrap()
+    );
+
+    fs::remove_dir_all("tmp/jpeg_export").unwrap();
+}
+
+#[test]
+fn test_export_to_jpeg_composites_alpha_across_stripes() {
+    create_directory_if_not_exists("tmp/jpeg_stripes").unwrap();
+    let project_path = "tmp/jpeg_stripes/project.tiff";
+    let output_jpeg = "tmp/jpeg_stripes/project.jpeg";
+    let (width, height) = (300, JPEG_EXPORT_STRIPE_ROWS * 2 + 188);
+    let transparent_rows = JPEG_EXPORT_STRIPE_ROWS + 100;
+    let color = [200u8, 120, 40];
+
+    let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+    let project = driver
+        .create_with_band_type::<u8, _>(project_path, width, height, 4)
+        .unwrap();
+    project
+        .set_geo_transform(&[1210000.0, 10.0, 0.0, 6075000.0, 0.0, -10.0])
+        .unwrap();
+    project
+        .set_projection(&SpatialRef::from_epsg(2154).unwrap().to_wkt().unwrap())
+        .unwrap();
+    for (band, value) in color.iter().enumerate() {
+        project
+            .rasterband(band + 1)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), vec![*value; width * height]),
+            )
+            .unwrap();
+    }
+    let alpha: Vec<u8> = (0..height)
+        .flat_map(|row| {
+            let value = if row < transparent_rows { 0 } else { 255 };
+            std::iter::repeat_n(value, width)
+        })
+        .collect();
+    project
+        .rasterband(4)
+        .unwrap()
+        .write(
+            (0, 0),
+            (width, height),
+            &mut Buffer::new((width, height), alpha),
+        )
+        .unwrap();
+    project.close().unwrap();
+
+    export_to_jpg(project_path, output_jpeg, &RunConfig::snapshot())
+        .expect("Export to JPEG failed");
+
+    let exported = image::open(output_jpeg).unwrap().to_rgb8();
+    assert_eq!(exported.dimensions(), (width as u32, height as u32));
+    let background = nodata_color();
+    for (row, expected) in [
+        (10, background),
+        (JPEG_EXPORT_STRIPE_ROWS + 50, background),
+        (transparent_rows + 50, color),
+        (height - 5, color),
+    ] {
+        let pixel = exported.get_pixel(150, row as u32).0;
+        for (value, expected) in pixel.iter().zip(expected.iter()) {
+            assert!(
+                value.abs_diff(*expected) <= 4,
+                "Unexpected color at row {}: {:?} instead of {:?}",
+                row,
+                pixel,
+                expected
+            );
+        }
+    }
+
+    fs::remove_dir_all("tmp/jpeg_stripes").unwrap();
+}
+
+/// Écrit des bandes verticales de couleurs connues dans les bandes `rgb_bands` d'un projet,
+/// opaques sauf sur les `transparent_rows` dernières lignes.
+fn write_color_stripes(
+    project: &Dataset,
+    colors: &[[u8; 3]],
+    rgb_bands: [usize; 3],
+    transparent_rows: usize,
+) {
+    let (width, height) = project.raster_size();
+    let stripe_width = width / colors.len();
+    for (channel, band_idx) in rgb_bands.into_iter().enumerate() {
+        let data: Vec<u8> = (0..width * height)
+            .map(|pixel| colors[(pixel % width / stripe_width).min(colors.len() - 1)][channel])
+            .collect();
+        project
+            .rasterband(band_idx)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), data),
+            )
+            .unwrap();
+    }
+    let alpha: Vec<u8> = (0..width * height)
+        .map(|pixel| {
+            if pixel / width < height - transparent_rows {
+                255
+            } else {
+                0
+            }
+        })
+        .collect();
+    project
+        .rasterband(4)
+        .unwrap()
+        .write(
+            (0, 0),
+            (width, height),
+            &mut Buffer::new((width, height), alpha),
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_export_to_jpeg_keeps_rgba_colors() {
+    create_directory_if_not_exists("tmp/jpeg_rgba").unwrap();
+    let project_path = "tmp/jpeg_rgba/project.tiff";
+    let output_jpeg = "tmp/jpeg_rgba/project.jpeg";
+    let bbox = BoundingBox::new(1215000.0, 6075000.0, 1220000.0, 6080000.0);
+    let colors = [
+        [220u8, 30, 30],
+        [30, 200, 40],
+        [40, 60, 210],
+        [240, 160, 20],
+    ];
+    let transparent_rows = 100;
+    create_project(project_path, &bbox).unwrap();
+
+    let project = Dataset::open(project_path).unwrap();
+    let interpretations: Vec<ColorInterpretation> = (1..=4)
+        .map(|band_idx| project.rasterband(band_idx).unwrap().color_interpretation())
+        .collect();
+    assert_eq!(
+        interpretations,
+        [
+            ColorInterpretation::RedBand,
+            ColorInterpretation::GreenBand,
+            ColorInterpretation::BlueBand,
+            ColorInterpretation::AlphaBand,
+        ]
+    );
+    drop(project);
+
+    // Bandes dans l'ordre RGBA, puis déclarées et écrites dans l'ordre BGRA.
+    for rgb_bands in [[1, 2, 3], [3, 2, 1]] {
+        let project = Dataset::open_ex(
+            project_path,
+            gdal::DatasetOptions {
+                open_flags: gdal::GdalOpenFlags::GDAL_OF_UPDATE,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        for (band_idx, interpretation) in rgb_bands.into_iter().zip([
+            ColorInterpretation::RedBand,
+            ColorInterpretation::GreenBand,
+            ColorInterpretation::BlueBand,
+        ]) {
+            project
+                .rasterband(band_idx)
+                .unwrap()
+                .set_color_interpretation(interpretation)
+                .unwrap();
+        }
+        write_color_stripes(&project, &colors, rgb_bands, transparent_rows);
+        project.close().unwrap();
+
+        export_to_jpg(project_path, output_jpeg, &RunConfig::snapshot())
+            .expect("Export to JPEG failed");
+
+        let exported = image::open(output_jpeg).unwrap().to_rgb8();
+        let (width, height) = exported.dimensions();
+        let stripe_width = width / colors.len() as u32;
+        let mut samples: Vec<((u32, u32), [u8; 3])> = colors
+            .iter()
+            .enumerate()
+            .map(|(idx, color)| ((idx as u32 * stripe_width + stripe_width / 2, 100), *color))
+            .collect();
+        samples.push((
+            (width / 2, height - transparent_rows as u32 / 2),
+            nodata_color(),
+        ));
+        for ((x, y), expected) in samples {
+            let pixel = exported.get_pixel(x, y).0;
+            for (value, expected_value) in pixel.iter().zip(expected.iter()) {
+                assert!(
+                    value.abs_diff(*expected_value) <= 8,
+                    "Bands {:?}: unexpected color at ({}, {}): {:?} instead of {:?}",
+                    rgb_bands,
+                    x,
+                    y,
+                    pixel,
+                    expected
+                );
+            }
+        }
+    }
+
+    fs::remove_dir_all("tmp/jpeg_rgba").unwrap();
+}
+
+/// Crée un GeoPackage Lambert-93 d'une seule couche contenant une géométrie.
+fn create_single_feature_gpkg(path: &str, layer_name: &str, wkt: &str) {
+    remove_file_if_exists(path);
+    let srs = SpatialRef::from_epsg(2154).unwrap();
+    let mut dataset = DriverManager::get_driver_by_name("GPKG")
+        .unwrap()
+        .create_vector_only(path)
+        .unwrap();
+    let mut layer = dataset
+        .create_layer(LayerOptions {
+            name: layer_name,
+            srs: Some(&srs),
+            ..Default::default()
+        })
+        .unwrap();
+    layer
+        .create_feature(Geometry::from_wkt(wkt).unwrap())
+        .unwrap();
+    drop(layer);
+    dataset.close().unwrap();
+}
+
+fn topo_layer(name: &str, class: &str, geometry: GeometryHint, priority: u8) -> LayerDefinition {
+    LayerDefinition {
+        name: name.to_string(),
+        archive: "BDTOPO".to_string(),
+        group: LayerGroup::Topo,
+        geometry,
+        class: class.to_string(),
+        enabled: true,
+        priority,
+    }
+}
+
+#[test]
+fn test_roads_cover_area_layers_they_cross() {
+    create_directory_if_not_exists("tmp/topo_priority").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/topo_priority/project.tiff";
+    create_project(project_file_path, &project_bb).unwrap();
+    create_class_raster(project_file_path).unwrap();
+    create_single_feature_gpkg(
+        "tmp/topo_priority/ROUTE.gpkg",
+        "ROUTE",
+        "LINESTRING (1210500 6072505, 1214500 6072505)",
+    );
+    create_single_feature_gpkg(
+        "tmp/topo_priority/EAU.gpkg",
+        "EAU",
+        "POLYGON ((1212000 6072000, 1213000 6072000, 1213000 6073000, 1212000 6073000, 1212000 6072000))",
+    );
+
+    // La route est listée avant la surface : seule la priorité fixe l'ordre d'application.
+    let config = LayerConfig {
+        description: None,
+        layers: vec![
+            topo_layer("ROUTE", "road", GeometryHint::Line, 30),
+            topo_layer("EAU", "water", GeometryHint::Polygon, 10),
+        ],
+        ..LayerConfig::default()
+    };
+    let ordered: Vec<&str> = config
+        .enabled_layers(LayerGroup::Topo)
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect();
+    assert_eq!(ordered, vec!["EAU", "ROUTE"]);
+
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_topo_priority").unwrap();
+    let mut warnings = Vec::new();
+    let project = ProjectRaster::open(project_file_path).unwrap();
+    for layer in config.enabled_layers(LayerGroup::Topo) {
+        let layer_gpkg = format!("tmp/topo_priority/{}.gpkg", layer.name);
+        add_configured_layer(&work_dir, &project, &layer_gpkg, layer, &mut warnings).unwrap();
+    }
+    project.close().unwrap();
+    assert!(warnings.is_empty());
+
+    let bands = read_bands(project_file_path);
+    let dataset = Dataset::open(project_file_path).unwrap();
+    let (width, _) = dataset.raster_size();
+    let geo_transform = dataset.geo_transform().unwrap();
+    let pixel_index = |x: f64, y: f64| {
+        let px = ((x - geo_transform[0]) / geo_transform[1]) as usize;
+        let py = ((y - geo_transform[3]) / geo_transform[5]) as usize;
+        py * width + px
+    };
+    let classes = read_band(
+        &Dataset::open(class_raster_path(project_file_path)).unwrap(),
+        1,
+    )
+    .unwrap();
+    let color_at = |i: usize| [bands[0][i], bands[1][i], bands[2][i]];
+
+    let crossing = pixel_index(1212500.0, 6072505.0);
+    assert_eq!(color_at(crossing), colormap::TOPO);
+    assert_eq!(classes[crossing], colormap::CLASS_ROAD);
+    let water = pixel_index(1212500.0, 6072805.0);
+    assert_eq!(color_at(water), colormap::HYDRO);
+    assert_eq!(classes[water], colormap::CLASS_WATER);
+
+    fs::remove_dir_all("tmp/topo_priority").unwrap();
+}
+
+#[test]
+fn test_split_land_tiles() {
+    // Image de 5 × 3 pixels en tuiles de 2 pixels : seul le pixel (4, 0) est sur la terre.
+    let mut mask = vec![0u8; 15];
+    mask[4] = 255;
+    let (land, sea) = split_land_tiles(&mask, (5, 3), 2);
+    assert_eq!(land.len(), 1);
+    assert_eq!(
+        (land[0].x, land[0].y, land[0].width, land[0].height),
+        (4, 0, 1, 2)
+    );
+    assert_eq!(sea.len(), 5);
+    assert!(
+        sea.iter()
+            .any(|window| (window.x, window.y, window.width, window.height) == (4, 2, 1, 1))
+    );
+}
+
+#[test]
+fn test_coastal_ortho_skips_sea_tiles() {
+    const SEA: [u8; 3] = [22, 58, 92];
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_coastal_ortho").unwrap();
+    let bb = get_test_bounding_box();
+    let (width, height) = bb.raster_size(10.0).unwrap();
+    let geo_transform = [bb.xmin, 10.0, 0.0, bb.ymax, 0.0, -10.0];
+
+    // Département sur la moitié ouest de l'emprise, la mer à l'est.
+    let regional_gpkg = work_dir.path_str("regional.gpkg");
+    create_single_feature_gpkg(
+        &regional_gpkg,
+        "departement",
+        "POLYGON ((1200000 6060000, 1222500 6060000, 1222500 6100000, 1200000 6100000, 1200000 6060000))",
+    );
+    let mask = land_mask(&work_dir, &regional_gpkg, &bb, 10.0).unwrap();
+    assert_eq!(mask.len(), width * height);
+    assert_ne!(mask[0], 0);
+    assert_eq!(mask[width - 1], 0);
+
+    // Orthophoto complète de référence : un dégradé sur chaque bande.
+    let full_path = work_dir.path_str("full.tif");
+    let full: Vec<Vec<u8>> = vec![
+        (0..width * height)
+            .map(|i| (i % width * 255 / width) as u8)
+            .collect(),
+        (0..width * height)
+            .map(|i| (i / width * 255 / height) as u8)
+            .collect(),
+        vec![160u8; width * height],
+    ];
+    let mut dataset = DriverManager::get_driver_by_name("GTiff")
+        .unwrap()
+        .create_with_band_type::<u8, _>(&full_path, width, height, 3)
+        .unwrap();
+    dataset.set_geo_transform(&geo_transform).unwrap();
+    for (band_idx, data) in full.iter().enumerate() {
+        dataset
+            .rasterband(band_idx + 1)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), data.clone()),
+            )
+            .unwrap();
+    }
+    dataset.close().unwrap();
+
+    let assembled = work_dir.path_for("assembled.tif");
+    let mut fetched = 0;
+    let skipped = assemble_land_ortho(
+        &work_dir,
+        &bb,
+        10.0,
+        &mask,
+        500,
+        SEA,
+        &assembled,
+        &mut |tile_bb, _size, output| {
+            fetched += 1;
+            let status = Command::new("gdal_translate")
+                .arg("-q")
+                .arg("-projwin")
+                .args(
+                    [tile_bb.xmin, tile_bb.ymax, tile_bb.xmax, tile_bb.ymin].map(|v| v.to_string()),
+                )
+                .arg(&full_path)
+                .arg(output)
+                .status()?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err("gdal_translate a échoué".into())
+            }
+        },
+    )
+    .unwrap();
+    // Les tuiles des colonnes 1500 à 2499 sont entièrement en mer.
+    assert_eq!(skipped, 10);
+    assert_eq!(fetched, 15);
+
+    let dataset = Dataset::open(&assembled).unwrap();
+    for (band_idx, full_band) in full.iter().enumerate() {
+        let band = read_band(&dataset, band_idx + 1).unwrap();
+        for row in (0..height).step_by(7) {
+            for col in 0..width {
+                let i = row * width + col;
+                if col >= 1500 {
+                    assert_eq!(band[i], SEA[band_idx], "sea pixel ({}, {})", col, row);
+                } else {
+                    assert_eq!(band[i], full_band[i], "land pixel ({}, {})", col, row);
+                }
+            }
+        }
+    }
+    dataset.close().unwrap();
+
+    // Après conversion en JPEG, les couleurs restent proches.
+    let jpg = work_dir.path_str("ortho.jpeg");
+    ortho_tiff_to_jpeg(&assembled, &jpg, &bb, &RunConfig::snapshot()).unwrap();
+    let image = image::open(&jpg).unwrap().to_rgb8();
+    let close = |actual: u8, expected: u8| (actual as i32 - expected as i32).abs() <= 8;
+    let sea_pixel = image.get_pixel(2000, 1200);
+    assert!(
+        (0..3).all(|c| close(sea_pixel[c], SEA[c])),
+        "{:?}",
+        sea_pixel
+    );
+    for (col, row) in [(200, 300), (1100, 2000)] {
+        let pixel = image.get_pixel(col as u32, row as u32);
+        let i = row * width + col;
+        assert!(
+            (0..3).all(|c| close(pixel[c], full[c][i])),
+            "{:?} at ({}, {})",
+            pixel,
+            col,
+            row
+        );
+    }
 }
@@ -0,0 +1,94 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::fetcher::FixtureFetcher;
+use firefront_gis_lib::gis_operation::creation::{
+    CreationRequest, FirefrontError, create_project_pipeline,
+};
+use firefront_gis_lib::progress::NoProgress;
+use firefront_gis_lib::utils::{
+    ConflictMode, PROJECT_COMPLETE_MARKER, load_project_metadata, project_dir,
+};
+use std::fs;
+use std::path::Path;
+
+#[tokio::test]
+async fn test_headless_creation() {
+    let name = "headless-porto-vecchio";
+    let _ = fs::remove_dir_all(project_dir(name));
+    let fetcher = FixtureFetcher::new("tests/res");
+
+    let summary = create_project_pipeline(
+        CreationRequest {
+            allow_reuse: Some(false),
+            ..CreationRequest::new(name, get_test_bounding_box())
+        },
+        &mut Vec::new(),
+        &NoProgress,
+        &fetcher,
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.name, name);
+    assert!(summary.complete);
+    assert_eq!(summary.regions, ["2A"]);
+    assert!(
+        Path::new(&summary.folder)
+            .join(PROJECT_COMPLETE_MARKER)
+            .exists()
+    );
+    let params = load_project_metadata(name).creation_params.unwrap();
+    assert!(params.resolution.is_some());
+
+    // Un second projet du même nom est refusé sans toucher au premier.
+    let conflict = create_project_pipeline(
+        CreationRequest::new(name, get_test_bounding_box()),
+        &mut Vec::new(),
+        &NoProgress,
+        &fetcher,
+    )
+    .await;
+    match conflict {
+        Err(FirefrontError::Conflict(conflict)) => assert_eq!(conflict.name, name),
+        other => panic!("Expected a conflict, got {:?}", other),
+    }
+    assert!(project_dir(name).join(PROJECT_COMPLETE_MARKER).exists());
+
+    // Le projet existant contient l'emprise : sa découpe est proposée sans rien créer.
+    let reuse = create_project_pipeline(
+        CreationRequest {
+            on_conflict: ConflictMode::Rename,
+            ..CreationRequest::new(name, get_test_bounding_box())
+        },
+        &mut Vec::new(),
+        &NoProgress,
+        &fetcher,
+    )
+    .await;
+    assert!(
+        matches!(reuse, Err(FirefrontError::ReuseAvailable { .. })),
+        "{:?}",
+        reuse
+    );
+    assert!(!project_dir(&format!("{}-2", name)).exists());
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
+
+#[tokio::test]
+async fn test_headless_creation_rejects_invalid_name() {
+    let result = create_project_pipeline(
+        CreationRequest::new("../headless", get_test_bounding_box()),
+        &mut Vec::new(),
+        &NoProgress,
+        &FixtureFetcher::new("tests/res"),
+    )
+    .await;
+    assert!(
+        matches!(result, Err(FirefrontError::InvalidName(_))),
+        "{:?}",
+        result
+    );
+    assert!(!Path::new("../headless").exists());
+}
@@ -0,0 +1,121 @@
+use firefront_gis_lib::app_setup::CONFIG;
+use firefront_gis_lib::events::ProgressUpdate;
+use firefront_gis_lib::i18n::{self, CATALOG, Language, translate};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+
+/// Paramètres `{0}`, `{1}`... d'un texte du catalogue.
+fn placeholders(text: &str) -> HashSet<String> {
+    Regex::new(r"\{\d+\}")
+        .unwrap()
+        .find_iter(text)
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+#[test]
+fn test_catalog_is_complete() {
+    let mut keys = HashSet::new();
+    for (key, fr, en) in CATALOG {
+        assert!(keys.insert(*key), "clé en double : {}", key);
+        assert!(
+            !fr.is_empty() && !en.is_empty(),
+            "traduction vide : {}",
+            key
+        );
+        assert_eq!(placeholders(fr), placeholders(en), "paramètres de {}", key);
+    }
+}
+
+#[test]
+fn test_keys_used_in_sources_exist() {
+    let keys: HashSet<&str> = CATALOG.iter().map(|(key, _, _)| *key).collect();
+    let used = Regex::new(r#""((?:stage|step|error|settings|busy)\.[a-z_]+)""#).unwrap();
+
+    for file in [
+        "src/commands.rs",
+        "src/busy.rs",
+        "src/app_setup.rs",
+        "src/pipeline.rs",
+        "src/utils.rs",
+        "src/gis_operation/layers.rs",
+    ] {
+        let source = fs::read_to_string(file).unwrap();
+        for capture in used.captures_iter(&source) {
+            assert!(
+                keys.contains(&capture[1]),
+                "clé absente du catalogue dans {} : {}",
+                file,
+                &capture[1]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_translate() {
+    assert_eq!(
+        translate("step.extract", Language::Fr, &["BATIMENT"]),
+        "Extraction de BATIMENT"
+    );
+    assert_eq!(
+        translate("step.extract", Language::En, &["BATIMENT"]),
+        "Extracting BATIMENT"
+    );
+    assert_eq!(
+        translate("step.inconnue", Language::En, &[]),
+        "step.inconnue"
+    );
+}
+
+#[test]
+fn test_language_codes_and_locales() {
+    assert_eq!(Language::from_code("EN").unwrap(), Language::En);
+    assert_eq!(Language::from_code("fr").unwrap(), Language::Fr);
+    assert!(Language::from_code("de").is_err());
+
+    assert_eq!(Language::from_locale("fr_FR.UTF-8"), Language::Fr);
+    assert_eq!(Language::from_locale("en_GB.UTF-8"), Language::En);
+    assert_eq!(Language::from_locale("de-DE"), Language::En);
+    assert_eq!(Language::from_locale("C"), Language::Fr);
+}
+
+#[test]
+fn test_config_language_changes_text_not_keys() {
+    let previous = CONFIG.lock().unwrap().language;
+
+    CONFIG.lock().unwrap().language = Language::En;
+    let english = ProgressUpdate::step("stage.prepare", "step.convert", &["ROUTE"], 2, 5);
+    let english_error = i18n::error("error.export", "disque plein");
+
+    CONFIG.lock().unwrap().language = Language::Fr;
+    let french = ProgressUpdate::step("stage.prepare", "step.convert", &["ROUTE"], 2, 5);
+    let french_error = i18n::error("error.export", "disque plein");
+
+    CONFIG.lock().unwrap().language = previous;
+
+    assert_eq!(english.stage, french.stage);
+    assert_eq!(english.step, french.step);
+    assert_eq!(english.params, vec!["ROUTE"]);
+    assert_eq!(english.count, Some((2, 5)));
+
+    assert_eq!(english.stage_text, "Preparing layers");
+    assert_eq!(french.stage_text, "Préparation des Couches");
+    assert_eq!(english.step_text.as_deref(), Some("Converting ROUTE"));
+    assert_eq!(french.step_text.as_deref(), Some("Conversion de ROUTE"));
+
+    assert_eq!(english_error, "Export failed: disque plein");
+    assert_eq!(french_error, "Erreur lors de l'exportation: disque plein");
+}
+
+#[test]
+fn test_progress_update_payload() {
+    let update = ProgressUpdate::stage_in(Language::En, "stage.download");
+    let payload = serde_json::to_value(&update).unwrap();
+
+    assert_eq!(payload["stage"], "stage.download");
+    assert_eq!(payload["stage_text"], "Downloading data");
+    assert!(payload["step"].is_null());
+    assert!(payload["count"].is_null());
+}
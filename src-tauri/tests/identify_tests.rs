@@ -0,0 +1,134 @@
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gis_operation::colormap::{self, Rgb, RpgClassTable};
+use firefront_gis_lib::gis_operation::create_project_in_crs;
+use firefront_gis_lib::gis_operation::identify::{
+    identify_pixel, identify_point, map_to_pixel, pixel_to_map,
+};
+use firefront_gis_lib::gis_operation::legend::{LegendEntry, build_legend};
+use firefront_gis_lib::gis_operation::processing::{
+    burn_classes, create_class_raster, open_raster_for_update,
+};
+use firefront_gis_lib::utils::{BoundingBox, WorkDir};
+use gdal::Dataset;
+use gdal::raster::Buffer;
+use std::path::Path;
+
+mod common;
+
+use common::default_layer_config;
+
+const SIZE: usize = 500;
+
+fn default_legend() -> Vec<LegendEntry> {
+    let config = default_layer_config();
+    let rpg_classes = RpgClassTable::load_from(Path::new("resources/rpg_classes.json")).unwrap();
+    build_legend(&config, &rpg_classes, colormap::REGIONAL)
+}
+
+/// Peint un carré de `side` pixels à partir de (`x`, `y`) sur les quatre bandes du projet.
+fn paint(project: &Dataset, (x, y): (usize, usize), side: usize, color: Rgb, alpha: u8) {
+    for (band_idx, value) in [color[0], color[1], color[2], alpha].iter().enumerate() {
+        project
+            .rasterband(band_idx + 1)
+            .unwrap()
+            .write(
+                (x as isize, y as isize),
+                (side, side),
+                &mut Buffer::new((side, side), vec![*value; side * side]),
+            )
+            .unwrap();
+    }
+}
+
+/// Projet de 500 × 500 pixels à 10 m : un plan d'eau, un bois de feuillus et une bande
+/// hors du département (alpha nul) en bas de l'image.
+fn create_fixture_project(work_dir: &WorkDir) -> String {
+    let path = work_dir.path_str("identify.tif");
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let run_config = RunConfig {
+        resolution: 10.0,
+        ..RunConfig::snapshot()
+    };
+    create_project_in_crs(&path, &project_bb, 2154, &run_config).unwrap();
+    create_class_raster(&path).unwrap();
+
+    let project = open_raster_for_update(&path, "projet de test").unwrap();
+    paint(&project, (100, 100), 50, colormap::HYDRO, 255);
+    paint(&project, (300, 100), 50, colormap::FEUILLUS, 255);
+    project
+        .rasterband(4)
+        .unwrap()
+        .write(
+            (0, 450),
+            (SIZE, 50),
+            &mut Buffer::new((SIZE, 50), vec![0u8; SIZE * 50]),
+        )
+        .unwrap();
+    project.close().unwrap();
+
+    let mut codes = vec![colormap::CLASS_UNKNOWN; SIZE * SIZE];
+    for row in 100..150 {
+        for col in 100..150 {
+            codes[row * SIZE + col] = colormap::CLASS_WATER;
+        }
+        for col in 300..350 {
+            codes[row * SIZE + col] = colormap::CLASS_FEUILLUS;
+        }
+    }
+    burn_classes(&path, &codes).unwrap();
+    path
+}
+
+#[test]
+fn test_pixel_and_map_coordinates_round_trip() {
+    let geo_transform = [1210000.0, 10.0, 0.0, 6075000.0, 0.0, -10.0];
+    assert_eq!(pixel_to_map(&geo_transform, 0, 0), (1210005.0, 6074995.0));
+    assert_eq!(
+        pixel_to_map(&geo_transform, 120, 40),
+        (1211205.0, 6074595.0)
+    );
+    assert_eq!(
+        map_to_pixel(&geo_transform, (SIZE, SIZE), 1211205.0, 6074595.0),
+        Some((120, 40))
+    );
+    assert_eq!(
+        map_to_pixel(&geo_transform, (SIZE, SIZE), 1209999.0, 6074595.0),
+        None
+    );
+    assert_eq!(
+        map_to_pixel(&geo_transform, (SIZE, SIZE), 1215000.0, 6074595.0),
+        None
+    );
+}
+
+#[test]
+fn test_identify_known_fixture_pixels() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_identify").unwrap();
+    let path = create_fixture_project(&work_dir);
+    let legend = default_legend();
+
+    let water = identify_pixel(&path, 2154, &legend, 120, 120).unwrap();
+    assert!(water.in_zone);
+    assert_eq!(water.rgba, [30, 80, 200, 255]);
+    assert_eq!(water.class_code, Some(colormap::CLASS_WATER));
+    assert_eq!(water.class_key.as_deref(), Some("water"));
+    assert!(water.label_fr.contains("Eau"), "{}", water.label_fr);
+    assert_eq!((water.x, water.y), (1211205.0, 6073795.0));
+    assert_eq!(water.epsg, 2154);
+
+    let feuillus = identify_point(&path, 2154, &legend, 1213205.0, 6073795.0).unwrap();
+    assert_eq!((feuillus.px, feuillus.py), (320, 120));
+    assert_eq!(feuillus.class_key.as_deref(), Some("feuillus"));
+    assert_eq!(
+        [feuillus.rgba[0], feuillus.rgba[1], feuillus.rgba[2]],
+        colormap::FEUILLUS
+    );
+
+    let outside = identify_pixel(&path, 2154, &legend, 250, 480).unwrap();
+    assert!(!outside.in_zone);
+    assert_eq!(outside.label_fr, "Hors zone");
+    assert_eq!(outside.class_key, None);
+
+    assert!(identify_pixel(&path, 2154, &legend, SIZE as u32, 0).is_err());
+    assert!(identify_point(&path, 2154, &legend, 1200000.0, 6073795.0).is_err());
+}
@@ -2,19 +2,41 @@ mod common;
 
 use common::*;
 
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gis_operation::colormap::{self, BuildingHeights};
+use firefront_gis_lib::gis_operation::layer_config::load_layer_config;
 use firefront_gis_lib::gis_operation::layers::{
-    add_regional_layer, add_rpg_layer, add_topo_layer, add_vegetation_layer,
+    BUILT_DENSITY_CELL_M, EssenceClassification, add_building_layer, add_hydro_layer,
+    add_regional_layer, add_rpg_layer, add_topo_layer, add_vegetation_layer, classify_essences,
+    count_rpg_classes, normalize_essence, prepare_layers, prepare_regional_layer,
+    vegetation_attribute,
 };
-use firefront_gis_lib::gis_operation::regions::create_region_geojson;
-use firefront_gis_lib::gis_operation::{clip_to_bb, convert_to_gpkg, create_project};
-use firefront_gis_lib::utils::{create_directory_if_not_exists, extract_files_by_name};
-use gdal::Dataset;
+use firefront_gis_lib::gis_operation::processing::{
+    DatasetError, ProjectRaster, class_raster_path, create_class_raster, overlay_in_place,
+    rasterize_layer, read_band,
+};
+use firefront_gis_lib::gis_operation::regions::{
+    Region, create_region_geojson, get_region, load_regions_graph, set_regions_index,
+};
+use firefront_gis_lib::gis_operation::{
+    CLIP_SKIPPED_WARNING_RATIO, clip_to_bb, convert_to_gpkg, create_project, fusion_datasets,
+    layer_names,
+};
+use firefront_gis_lib::progress::NoProgress;
+use firefront_gis_lib::timings::{StageTimings, take_processing_timings};
+use firefront_gis_lib::utils::{
+    BoundingBox, WorkDir, create_directory_if_not_exists, extract_files_by_name,
+};
+use gdal::vector::{Geometry, LayerAccess, LayerOptions, OGRwkbGeometryType};
+use gdal::{Dataset, DriverManager};
 use std::fs;
 use std::path::Path;
+use std::process::Command;
 
 #[test]
 fn test_end_to_end_workflow() {
     create_directory_if_not_exists("tmp").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_end_to_end").unwrap();
     let project_bb = get_test_bounding_box();
     let project_file_path = "tests/res/test1.tiff";
 
@@ -120,24 +142,48 @@ fn test_end_to_end_workflow() {
         assert_result_ok(&result, &format!("Clipping of {} failed", subfolder));
     }
 
-    type LayerAdder = fn(&str, &str) -> Result<(), Box<dyn std::error::Error>>;
-    let layers_to_add: Vec<(&str, LayerAdder)> = vec![
-        ("tests/res/test_regional_clipped.gpkg", add_regional_layer),
-        (
-            "tests/res/test_vegetation_clipped.gpkg",
-            add_vegetation_layer,
-        ),
-        ("tests/res/test_rpg_clipped.gpkg", add_rpg_layer),
-    ];
+    type LayerAdder = fn(&WorkDir, &str, &str) -> Result<(), Box<dyn std::error::Error>>;
+    let layers_to_add: Vec<(&str, LayerAdder)> = vec![(
+        "tests/res/test_regional_clipped.gpkg",
+        |work_dir, project, gpkg| add_regional_layer(work_dir, project, gpkg, colormap::REGIONAL),
+    )];
 
     for (layer, add_layer_fn) in layers_to_add {
-        let result = add_layer_fn(project_file_path, layer);
+        let result = add_layer_fn(&work_dir, project_file_path, layer);
         assert_result_ok(&result, &format!("Adding layer {} failed", layer));
     }
 
+    let mut warnings = Vec::new();
+    let result = add_vegetation_layer(
+        &work_dir,
+        project_file_path,
+        "tests/res/test_vegetation_clipped.gpkg",
+        &mut warnings,
+    );
+    assert_result_ok(
+        &result,
+        "Adding layer tests/res/test_vegetation_clipped.gpkg failed",
+    );
+
+    let result = add_rpg_layer(
+        &work_dir,
+        project_file_path,
+        "tests/res/test_rpg_clipped.gpkg",
+        &mut warnings,
+    );
+    assert_result_ok(
+        &result,
+        "Adding layer tests/res/test_rpg_clipped.gpkg failed",
+    );
+
     for subfolder in &topo_subfolders {
         let clipped_gpkg_path = format!("tests/res/test_{}_clipped.gpkg", subfolder);
-        let result = add_topo_layer(project_file_path, &clipped_gpkg_path);
+        let result = add_topo_layer(
+            &work_dir,
+            project_file_path,
+            &clipped_gpkg_path,
+            &mut warnings,
+        );
         assert_result_ok(
             &result,
             &format!("Adding topography layer {} failed", subfolder),
@@ -166,6 +212,39 @@ fn test_end_to_end_workflow() {
         pixel_size_y
     );
 
+    // Au large de la côte est (mer Tyrrhénienne) et à l'intérieur des terres.
+    let region_2a = get_region("2A").unwrap();
+    let sea = BoundingBox::new(1234900.0, 6070000.0, 1235000.0, 6070100.0);
+    let inland = BoundingBox::new(1212000.0, 6090000.0, 1212100.0, 6090100.0);
+    assert!(
+        !region_2a.intersects(&sea),
+        "Sea sample should be outside 2A"
+    );
+    assert!(
+        region_2a.contains(&inland),
+        "Inland sample should be inside 2A"
+    );
+
+    let (width, height) = dataset.raster_size();
+    let alpha = dataset
+        .rasterband(4)
+        .unwrap()
+        .read_as::<u8>((0, 0), (width, height), (width, height), None)
+        .unwrap()
+        .data()
+        .to_vec();
+    let alpha_at = |x: f64, y: f64| {
+        let col = ((x - geotransform[0]) / pixel_size_x) as usize;
+        let row = ((geotransform[3] - y) / pixel_size_y) as usize;
+        alpha[row * width + col]
+    };
+    assert_eq!(
+        alpha_at(1234950.0, 6070050.0),
+        0,
+        "Sea should be transparent"
+    );
+    assert_eq!(alpha_at(1212050.0, 6090050.0), 255, "Land should be opaque");
+
     let test_dir = Path::new("tests/res");
     for entry in fs::read_dir(test_dir).unwrap() {
         let entry = entry.unwrap();
@@ -180,3 +259,985 @@ fn test_end_to_end_workflow() {
     }
     fs::remove_dir_all("tmp").unwrap();
 }
+
+#[test]
+fn test_hydro_layer_color() {
+    // Hors de `tmp`, que `test_end_to_end_workflow` supprime en parallèle.
+    let work_dir = WorkDir::new_in(&std::env::temp_dir(), "test_hydro").unwrap();
+    let project_bb = get_test_bounding_box();
+    let project_file_path = work_dir.path_str("test_hydro.tiff");
+    let hydro_gpkg = work_dir.path_str("SURFACE_HYDROGRAPHIQUE.gpkg");
+    let clipped_gpkg = work_dir.path_str("SURFACE_HYDROGRAPHIQUE_clipped.gpkg");
+
+    extract_files_by_name(
+        "tests/res/BDTOPO_2A.7z",
+        "SURFACE_HYDROGRAPHIQUE",
+        &work_dir.path().to_string_lossy(),
+    )
+    .unwrap();
+    create_project(&project_file_path, &project_bb).unwrap();
+    convert_to_gpkg(
+        &work_dir.path_str("SURFACE_HYDROGRAPHIQUE/SURFACE_HYDROGRAPHIQUE.shp"),
+        &hydro_gpkg,
+    )
+    .unwrap();
+    clip_to_bb(&hydro_gpkg, &clipped_gpkg, &project_bb).unwrap();
+
+    let result = add_hydro_layer(
+        &work_dir,
+        &project_file_path,
+        &clipped_gpkg,
+        &mut Vec::new(),
+    );
+    assert_result_ok(&result, "Adding hydrography layer failed");
+
+    let dataset = Dataset::open(&project_file_path).unwrap();
+    let (width, height) = dataset.raster_size();
+    let bands: Vec<Vec<u8>> = (1..=3)
+        .map(|i| {
+            dataset
+                .rasterband(i)
+                .unwrap()
+                .read_as::<u8>((0, 0), (width, height), (width, height), None)
+                .unwrap()
+                .data()
+                .to_vec()
+        })
+        .collect();
+
+    let water_pixels = (0..width * height)
+        .filter(|&i| [bands[0][i], bands[1][i], bands[2][i]] == colormap::HYDRO)
+        .count();
+    assert!(water_pixels > 0, "No water-colored pixels found");
+
+    dataset.close().unwrap();
+}
+
+#[test]
+fn test_rpg_parcels_colored_by_crop_group() {
+    create_directory_if_not_exists("tmp/rpg_classes").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_rpg_classes").unwrap();
+    let project_bb = get_test_bounding_box();
+    let project_file_path = "tmp/rpg_classes/test_rpg_classes.tiff";
+    let rpg_gpkg = "tmp/rpg_classes/PARCELLES_GRAPHIQUES.gpkg";
+    let clipped_gpkg = "tmp/rpg_classes/PARCELLES_GRAPHIQUES_clipped.gpkg";
+
+    extract_files_by_name(
+        "tests/res/RPG_2A.7z",
+        "PARCELLES_GRAPHIQUES",
+        "tmp/rpg_classes",
+    )
+    .unwrap();
+    create_project(project_file_path, &project_bb).unwrap();
+    convert_to_gpkg(
+        "tmp/rpg_classes/PARCELLES_GRAPHIQUES/PARCELLES_GRAPHIQUES.shp",
+        rpg_gpkg,
+    )
+    .unwrap();
+    clip_to_bb(rpg_gpkg, clipped_gpkg, &project_bb).unwrap();
+
+    let table = colormap::load_rpg_classes().unwrap();
+    let counts = count_rpg_classes(clipped_gpkg, &table).unwrap();
+    let total = Dataset::open(clipped_gpkg)
+        .unwrap()
+        .layer(0)
+        .unwrap()
+        .feature_count();
+    assert!(total > 0, "The clipped RPG layer should not be empty");
+    assert_eq!(
+        counts.iter().map(|count| count.features).sum::<u64>(),
+        total,
+        "Feature counts per class should sum to the layer total"
+    );
+
+    let mut warnings = Vec::new();
+    let result = add_rpg_layer(&work_dir, project_file_path, clipped_gpkg, &mut warnings);
+    assert_result_ok(&result, "Adding RPG layer failed");
+    let unclassified = counts.last().unwrap();
+    assert_eq!(unclassified.key, None);
+    assert_eq!(
+        warnings
+            .iter()
+            .any(|warning| warning.context == "Couche RPG"),
+        unclassified.features > 0
+    );
+
+    let dataset = Dataset::open(project_file_path).unwrap();
+    let (width, height) = dataset.raster_size();
+    let bands: Vec<Vec<u8>> = (1..=3)
+        .map(|i| {
+            dataset
+                .rasterband(i)
+                .unwrap()
+                .read_as::<u8>((0, 0), (width, height), (width, height), None)
+                .unwrap()
+                .data()
+                .to_vec()
+        })
+        .collect();
+
+    let rpg_colors: Vec<colormap::Rgb> = table
+        .classes
+        .iter()
+        .map(|class| class.color)
+        .chain([colormap::RPG])
+        .collect();
+    let mut colors_found = std::collections::HashSet::new();
+    for i in 0..width * height {
+        let color = [bands[0][i], bands[1][i], bands[2][i]];
+        if rpg_colors.contains(&color) {
+            colors_found.insert(color);
+        }
+    }
+    assert!(
+        colors_found.len() >= 2,
+        "Expected at least two distinct RPG colors, found {:?}",
+        colors_found
+    );
+
+    dataset.close().unwrap();
+    fs::remove_dir_all("tmp/rpg_classes").unwrap();
+}
+
+#[test]
+fn test_building_layer_splits_heights_and_measures_density() {
+    create_directory_if_not_exists("tmp/buildings").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_buildings").unwrap();
+    let project_bb = get_test_bounding_box();
+    let project_file_path = "tmp/buildings/test_buildings.tiff";
+    let building_gpkg = "tmp/buildings/BATIMENT.gpkg";
+    let clipped_gpkg = "tmp/buildings/BATIMENT_clipped.gpkg";
+
+    extract_files_by_name("tests/res/BDTOPO_2A.7z", "BATIMENT", "tmp/buildings").unwrap();
+    create_project(project_file_path, &project_bb).unwrap();
+    create_class_raster(project_file_path).unwrap();
+    convert_to_gpkg("tmp/buildings/BATIMENT/BATIMENT.shp", building_gpkg).unwrap();
+    clip_to_bb(building_gpkg, clipped_gpkg, &project_bb).unwrap();
+
+    // Couleur des bâtiments bas distincte du fond noir du projet.
+    let heights = BuildingHeights {
+        low_color: [200, 100, 0],
+        ..BuildingHeights::default()
+    };
+    let mut warnings = Vec::new();
+    let density = add_building_layer(
+        &work_dir,
+        project_file_path,
+        clipped_gpkg,
+        &heights,
+        &mut warnings,
+    )
+    .unwrap()
+    .expect("La couche des bâtiments ne doit pas être vide");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+
+    let dataset = Dataset::open(project_file_path).unwrap();
+    let bands: Vec<Vec<u8>> = (1..=3).map(|i| read_band(&dataset, i).unwrap()).collect();
+    let codes = read_band(
+        &Dataset::open(class_raster_path(project_file_path)).unwrap(),
+        1,
+    )
+    .unwrap();
+    for (color, class_code) in [
+        (heights.low_color, colormap::CLASS_BUILDING),
+        (heights.high_color, colormap::CLASS_BUILDING_HIGH),
+    ] {
+        let pixels: Vec<usize> = (0..codes.len())
+            .filter(|&i| [bands[0][i], bands[1][i], bands[2][i]] == color)
+            .collect();
+        assert!(!pixels.is_empty(), "Aucun pixel de couleur {:?}", color);
+        assert!(pixels.iter().all(|&i| codes[i] == class_code));
+    }
+
+    // Une case par kilomètre de l'emprise du projet.
+    let rows = ((project_bb.ymax - project_bb.ymin) / BUILT_DENSITY_CELL_M).ceil() as usize;
+    let columns = ((project_bb.xmax - project_bb.xmin) / BUILT_DENSITY_CELL_M).ceil() as usize;
+    assert_eq!(density.cell_m, BUILT_DENSITY_CELL_M);
+    assert_eq!(density.cells.len(), rows);
+    assert!(density.cells.iter().all(|line| line.len() == columns));
+    assert!(
+        density
+            .cells
+            .iter()
+            .flatten()
+            .all(|fraction| (0.0..=1.0).contains(fraction))
+    );
+    assert!(
+        density
+            .cells
+            .iter()
+            .flatten()
+            .any(|fraction| *fraction > 0.0)
+    );
+
+    dataset.close().unwrap();
+    fs::remove_dir_all("tmp/buildings").unwrap();
+}
+
+#[test]
+fn test_class_raster_matches_rgb() {
+    create_directory_if_not_exists("tmp/classes").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_classes").unwrap();
+    let project_bb = get_test_bounding_box();
+    let project_file_path = "tmp/classes/test_classes.tiff";
+    let hydro_gpkg = "tmp/classes/SURFACE_HYDROGRAPHIQUE.gpkg";
+    let clipped_gpkg = "tmp/classes/SURFACE_HYDROGRAPHIQUE_clipped.gpkg";
+
+    extract_files_by_name(
+        "tests/res/BDTOPO_2A.7z",
+        "SURFACE_HYDROGRAPHIQUE",
+        "tmp/classes",
+    )
+    .unwrap();
+    create_project(project_file_path, &project_bb).unwrap();
+    convert_to_gpkg(
+        "tmp/classes/SURFACE_HYDROGRAPHIQUE/SURFACE_HYDROGRAPHIQUE.shp",
+        hydro_gpkg,
+    )
+    .unwrap();
+    clip_to_bb(hydro_gpkg, clipped_gpkg, &project_bb).unwrap();
+
+    let class_path = create_class_raster(project_file_path).unwrap();
+    assert_eq!(class_path, class_raster_path(project_file_path));
+    assert_file_exists(&class_path.to_string_lossy(), "Class raster not created");
+
+    let result = add_hydro_layer(&work_dir, project_file_path, clipped_gpkg, &mut Vec::new());
+    assert_result_ok(&result, "Adding hydrography layer failed");
+
+    let dataset = Dataset::open(project_file_path).unwrap();
+    let classes = Dataset::open(&class_path).unwrap();
+    assert_eq!(classes.raster_count(), 1);
+    assert_eq!(classes.raster_size(), dataset.raster_size());
+
+    let (width, height) = dataset.raster_size();
+    let bands: Vec<Vec<u8>> = (1..=3)
+        .map(|i| {
+            dataset
+                .rasterband(i)
+                .unwrap()
+                .read_as::<u8>((0, 0), (width, height), (width, height), None)
+                .unwrap()
+                .data()
+                .to_vec()
+        })
+        .collect();
+    let codes: Vec<u8> = classes
+        .rasterband(1)
+        .unwrap()
+        .read_as::<u8>((0, 0), (width, height), (width, height), None)
+        .unwrap()
+        .data()
+        .to_vec();
+
+    let water_pixels = (0..width * height)
+        .filter(|&i| [bands[0][i], bands[1][i], bands[2][i]] == colormap::HYDRO)
+        .collect::<Vec<usize>>();
+    assert!(!water_pixels.is_empty(), "No water-colored pixels found");
+    assert!(
+        water_pixels
+            .iter()
+            .all(|&i| codes[i] == colormap::CLASS_WATER)
+    );
+    assert!(
+        codes
+            .iter()
+            .all(|&code| code == colormap::CLASS_WATER || code == colormap::CLASS_UNKNOWN)
+    );
+
+    classes.close().unwrap();
+    dataset.close().unwrap();
+    fs::remove_dir_all("tmp/classes").unwrap();
+}
+
+#[test]
+fn test_empty_topo_layer_records_warning() {
+    create_directory_if_not_exists("tmp/empty_topo").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_empty_topo").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/empty_topo/test_empty_topo.tiff";
+    let empty_gpkg = "tmp/empty_topo/BATIMENT.gpkg";
+    remove_file_if_exists(empty_gpkg);
+
+    create_project(project_file_path, &project_bb).unwrap();
+    let mut dataset = DriverManager::get_driver_by_name("GPKG")
+        .unwrap()
+        .create_vector_only(empty_gpkg)
+        .unwrap();
+    dataset
+        .create_layer(LayerOptions {
+            name: "BATIMENT",
+            ty: OGRwkbGeometryType::wkbMultiPolygon,
+            ..Default::default()
+        })
+        .unwrap();
+    dataset.close().unwrap();
+
+    let mut warnings = Vec::new();
+    let result = add_topo_layer(&work_dir, project_file_path, empty_gpkg, &mut warnings);
+    assert_result_ok(&result, "An empty layer should not abort the run");
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].context.contains("BATIMENT"));
+
+    fs::remove_dir_all("tmp/empty_topo").unwrap();
+}
+
+#[test]
+fn test_clip_reports_skipped_invalid_geometry() {
+    create_directory_if_not_exists("tmp/clip_report").unwrap();
+    let geojson_path = "tmp/clip_report/invalid.geojson";
+    let input_gpkg = "tmp/clip_report/invalid.gpkg";
+    let output_gpkg = "tmp/clip_report/invalid_clipped.gpkg";
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1211000.0, 6071000.0);
+
+    // Un carré intérieur, un carré à cheval sur le bord est, un polygone en nœud papillon
+    // (auto-intersecté) à cheval sur le coin nord-est et un carré extérieur.
+    let rings = [
+        "[[1210100,6070100],[1210200,6070100],[1210200,6070200],[1210100,6070200],[1210100,6070100]]",
+        "[[1210900,6070100],[1211100,6070100],[1211100,6070200],[1210900,6070200],[1210900,6070100]]",
+        "[[1210800,6070800],[1211200,6071200],[1211200,6070800],[1210800,6071200],[1210800,6070800]]",
+        "[[1212000,6072000],[1212100,6072000],[1212100,6072100],[1212000,6072100],[1212000,6072000]]",
+    ];
+    let features: Vec<String> = rings
+        .iter()
+        .map(|ring| {
+            format!(
+                r#"{{"type":"Feature","properties":{{}},"geometry":{{"type":"Polygon","coordinates":[{ring}]}}}}"#
+            )
+        })
+        .collect();
+    fs::write(
+        geojson_path,
+        format!(
+            r#"{{"type":"FeatureCollection","crs":{{"type":"name","properties":{{"name":"urn:ogc:def:crs:EPSG::2154"}}}},"features":[{}]}}"#,
+            features.join(",")
+        ),
+    )
+    .unwrap();
+    remove_file_if_exists(input_gpkg);
+    remove_file_if_exists(output_gpkg);
+    convert_to_gpkg(geojson_path, input_gpkg).unwrap();
+
+    let report = clip_to_bb(input_gpkg, output_gpkg, &project_bb).unwrap();
+
+    assert_eq!(report.input_features, 4);
+    assert_eq!(report.output_features, 2);
+    assert_eq!(report.skipped, 1);
+    assert!(report.skipped_ratio() > CLIP_SKIPPED_WARNING_RATIO);
+
+    fs::remove_dir_all("tmp/clip_report").unwrap();
+}
+
+#[test]
+fn test_failed_regional_clip_aborts() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_regional_clip").unwrap();
+    // Un dossier à l'emplacement du GPKG de sortie fait échouer le découpage.
+    fs::create_dir_all(work_dir.path_for("2A_region.gpkg")).unwrap();
+
+    let result = prepare_regional_layer(&work_dir, &get_test_bounding_box(), "2A");
+    let error = result.expect_err("A failed regional clip must abort the run");
+    assert!(error.contains("découpage"), "Unexpected error: {}", error);
+}
+
+#[tokio::test]
+async fn test_corrupted_region_fails_prepare_layers() {
+    // Entrée du graphe dont la géométrie (auto-intersectée, loin de la Corse) ne donne
+    // aucune entité dans l'emprise du projet.
+    let mut graph = load_regions_graph().unwrap();
+    let extent = Geometry::from_wkt("POLYGON ((0 0, 10 10, 10 0, 0 10, 0 0))").unwrap();
+    graph.insert(
+        "XX".to_string(),
+        Region::new("XX".to_string(), "Corrompue".to_string(), extent),
+    );
+    set_regions_index(graph);
+
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_corrupted_region").unwrap();
+    // Un GPKG périmé d'une exécution précédente ne doit pas masquer l'échec.
+    let stale = prepare_regional_layer(&work_dir, &get_test_bounding_box(), "2A").unwrap();
+    fs::rename(stale, work_dir.path_for("XX_region.gpkg")).unwrap();
+    let mut warnings = Vec::new();
+    let mut timings = StageTimings::default();
+
+    let result = prepare_layers(
+        &NoProgress,
+        &work_dir,
+        &get_test_bounding_box(),
+        "XX",
+        &load_layer_config().unwrap(),
+        &mut warnings,
+        &mut timings,
+        &RunConfig::snapshot(),
+    )
+    .await;
+
+    let error = result.expect_err("A corrupted region must abort the preparation");
+    assert!(
+        error.contains("département XX"),
+        "Unexpected error: {}",
+        error
+    );
+    assert!(!error.contains("extraction"), "Unexpected error: {}", error);
+    assert!(warnings.is_empty());
+}
+
+/// Écrit une couche de végétation synthétique : un carré de 500 m par valeur d'ESSENCE,
+/// espacés de 800 m d'ouest en est à partir de `origin`.
+fn write_essence_geojson(path: &str, attribute: &str, origin: (f64, f64), essences: &[&str]) {
+    let features: Vec<String> = essences
+        .iter()
+        .enumerate()
+        .map(|(index, essence)| {
+            let xmin = origin.0 + 800.0 * index as f64;
+            let (ymin, xmax, ymax) = (origin.1, xmin + 500.0, origin.1 + 500.0);
+            format!(
+                r#"{{"type":"Feature","properties":{{"{attribute}":"{essence}"}},"geometry":{{"type":"Polygon","coordinates":[[[{xmin},{ymin}],[{xmax},{ymin}],[{xmax},{ymax}],[{xmin},{ymax}],[{xmin},{ymin}]]]}}}}"#
+            )
+        })
+        .collect();
+    let geojson = format!(
+        r#"{{"type":"FeatureCollection","crs":{{"type":"name","properties":{{"name":"urn:ogc:def:crs:EPSG::2154"}}}},"features":[{}]}}"#,
+        features.join(",")
+    );
+    fs::write(path, geojson).unwrap();
+}
+
+#[test]
+fn test_essence_values_are_normalized() {
+    assert_eq!(normalize_essence("Chênes  décidus "), "chenes decidus");
+    assert_eq!(normalize_essence("CHENES DECIDUS"), "chenes decidus");
+    assert_eq!(normalize_essence("Pin d’Alep"), "pin d'alep");
+
+    let counts: Vec<(String, u64)> = [
+        ("Chenes decidus", 3),
+        ("FEUILLUS", 2),
+        ("nr", 1),
+        ("PIN D'ALEP", 4),
+        ("Essence exotique", 5),
+    ]
+    .iter()
+    .map(|(value, count)| (value.to_string(), *count))
+    .collect();
+    let classification = EssenceClassification::from_counts(&counts);
+
+    assert_eq!(classification.feuillus, vec!["Chenes decidus", "FEUILLUS"]);
+    assert_eq!(classification.undefined, vec!["nr"]);
+    assert_eq!(classification.other, vec!["PIN D'ALEP"]);
+    assert_eq!(
+        classification.unmatched,
+        vec![("Essence exotique".to_string(), 5)]
+    );
+    assert_eq!(
+        classification.other_where().unwrap(),
+        "ESSENCE NOT IN ('Chenes decidus', 'FEUILLUS', 'nr')"
+    );
+    assert_eq!(
+        EssenceClassification::default().feuillus_where().unwrap(),
+        "0 = 1"
+    );
+}
+
+#[test]
+fn test_bdforet_v1_formation_types_are_classified() {
+    assert_eq!(
+        vegetation_attribute(&["ID".to_string(), "CODE_TFV".to_string(), "TFV".to_string()]),
+        Some("CODE_TFV")
+    );
+    assert_eq!(vegetation_attribute(&["tfv".to_string()]), Some("TFV"));
+    assert_eq!(vegetation_attribute(&["ID".to_string()]), None);
+
+    let counts: Vec<(String, u64)> = [
+        ("FF1-00-00", 3),
+        ("FO1-00-00", 1),
+        ("FF0-00-00", 2),
+        ("FF2-00-00", 4),
+        ("LA6-00-00", 1),
+        ("XX9-00-00", 2),
+    ]
+    .iter()
+    .map(|(value, count)| (value.to_string(), *count))
+    .collect();
+    let classification = EssenceClassification::from_tfv_counts("CODE_TFV", &counts);
+
+    assert!(classification.is_bdforet_v1());
+    assert_eq!(classification.feuillus, vec!["FF1-00-00", "FO1-00-00"]);
+    assert_eq!(classification.undefined, vec!["FF0-00-00"]);
+    assert_eq!(classification.other, vec!["FF2-00-00", "LA6-00-00"]);
+    assert_eq!(classification.unmatched, vec![("XX9-00-00".to_string(), 2)]);
+    assert_eq!(
+        classification.feuillus_where().unwrap(),
+        "CODE_TFV IN ('FF1-00-00', 'FO1-00-00')"
+    );
+}
+
+#[test]
+fn test_bdforet_v1_vegetation_layer_classified_by_tfv() {
+    create_directory_if_not_exists("tmp/tfv").unwrap();
+    let geojson = "tmp/tfv/tfv.geojson";
+    let vegetation_gpkg = "tmp/tfv/FORMATION_VEGETALE.gpkg";
+    let _ = fs::remove_file(vegetation_gpkg);
+
+    let formations = [
+        "Forêt fermée de feuillus purs en îlots",
+        "FORET OUVERTE DE FEUILLUS PURS",
+        "Forêt fermée sans couvert arboré",
+        "Forêt fermée à mélange de feuillus et conifères",
+        "Lande ligneuse",
+    ];
+    write_essence_geojson(geojson, "TFV", (1210100.0, 6072250.0), &formations);
+    convert_to_gpkg(geojson, vegetation_gpkg).unwrap();
+
+    let classification = classify_essences(vegetation_gpkg).unwrap();
+    assert_eq!(classification.attribute, "TFV");
+    assert_eq!(classification.feuillus.len(), 2);
+    assert_eq!(
+        classification.undefined,
+        vec!["Forêt fermée sans couvert arboré"]
+    );
+    assert_eq!(classification.other.len(), 2);
+    assert!(classification.unmatched.is_empty());
+    assert!(
+        classification
+            .other_where()
+            .unwrap()
+            .starts_with("TFV NOT IN (")
+    );
+
+    fs::remove_dir_all("tmp/tfv").unwrap();
+}
+
+#[test]
+fn test_vegetation_essences_matched_case_and_accent_insensitively() {
+    create_directory_if_not_exists("tmp/essences").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_essences").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/essences/test_essences.tiff";
+    let geojson = "tmp/essences/essences.geojson";
+    let vegetation_gpkg = "tmp/essences/FORMATION_VEGETALE.gpkg";
+    let _ = fs::remove_file(vegetation_gpkg);
+
+    let essences = [
+        "CHENES DECIDUS",
+        "châtaignier",
+        "Feuillus",
+        "NC",
+        "Pin maritime",
+        "Essence exotique",
+    ];
+    write_essence_geojson(geojson, "ESSENCE", (1210100.0, 6072250.0), &essences);
+    convert_to_gpkg(geojson, vegetation_gpkg).unwrap();
+    create_project(project_file_path, &project_bb).unwrap();
+
+    let classification = classify_essences(vegetation_gpkg).unwrap();
+    assert_eq!(classification.feuillus.len(), 3);
+    assert_eq!(classification.undefined, vec!["NC"]);
+    assert_eq!(classification.other, vec!["Pin maritime"]);
+    assert_eq!(
+        classification.unmatched,
+        vec![("Essence exotique".to_string(), 1)]
+    );
+
+    let mut warnings = Vec::new();
+    let result = add_vegetation_layer(&work_dir, project_file_path, vegetation_gpkg, &mut warnings);
+    assert_result_ok(&result, "Adding vegetation layer failed");
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("Essence exotique"));
+
+    let expected = [
+        colormap::FEUILLUS,
+        colormap::FEUILLUS,
+        colormap::FEUILLUS,
+        colormap::UNDEFINED_VEGETATION,
+        colormap::OTHER_VEGETATION,
+        colormap::OTHER_VEGETATION,
+    ];
+    let dataset = Dataset::open(project_file_path).unwrap();
+    for (index, expected_color) in expected.iter().enumerate() {
+        // Centre du carré de l'essence, à 10 m par pixel.
+        let x = 1210350.0 + 800.0 * index as f64;
+        let y = 6072500.0;
+        let col = ((x - project_bb.xmin) / 10.0) as usize;
+        let row = ((project_bb.ymax - y) / 10.0) as usize;
+        let color: Vec<u8> = (1..=3)
+            .map(|band| {
+                dataset
+                    .rasterband(band)
+                    .unwrap()
+                    .read_as::<u8>((col as isize, row as isize), (1, 1), (1, 1), None)
+                    .unwrap()
+                    .data()[0]
+            })
+            .collect();
+        assert_eq!(
+            color,
+            expected_color.to_vec(),
+            "Essence '{}' mal classée",
+            essences[index]
+        );
+    }
+
+    dataset.close().unwrap();
+    fs::remove_dir_all("tmp/essences").unwrap();
+}
+
+#[test]
+fn test_layers_with_differing_names_are_all_burned() {
+    create_directory_if_not_exists("tmp/multilayer").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_multilayer").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/multilayer/test_multilayer.tiff";
+    let sources = [
+        ("VEGETATION_2A", (1210100.0, 6072250.0), "Hêtre"),
+        ("vegetation_2b", (1212500.0, 6072250.0), "Pin maritime"),
+    ];
+    let mut source_gpkgs = Vec::new();
+    for (layer_name, origin, essence) in sources {
+        let geojson = format!("tmp/multilayer/{}.geojson", layer_name);
+        let gpkg = format!("tmp/multilayer/{}.gpkg", layer_name);
+        remove_file_if_exists(&gpkg);
+        write_essence_geojson(&geojson, "ESSENCE", origin, &[essence]);
+        convert_to_gpkg(&geojson, &gpkg).unwrap();
+        source_gpkgs.push(gpkg);
+    }
+
+    // Un ajout sans nom de couche commun crée une seconde couche.
+    let multi_gpkg = "tmp/multilayer/MULTI.gpkg";
+    remove_file_if_exists(multi_gpkg);
+    fs::copy(&source_gpkgs[0], multi_gpkg).unwrap();
+    let status = Command::new("ogr2ogr")
+        .args(["-f", "GPKG", "-append", "-update", multi_gpkg])
+        .arg(&source_gpkgs[1])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(layer_names(&Dataset::open(multi_gpkg).unwrap()).len(), 2);
+
+    let classification = classify_essences(multi_gpkg).unwrap();
+    assert_eq!(classification.feuillus, vec!["Hêtre"]);
+    assert_eq!(classification.other, vec!["Pin maritime"]);
+
+    create_project(project_file_path, &project_bb).unwrap();
+    let result = add_vegetation_layer(&work_dir, project_file_path, multi_gpkg, &mut Vec::new());
+    assert_result_ok(&result, "Adding a multi-layer vegetation file failed");
+
+    let dataset = Dataset::open(project_file_path).unwrap();
+    for ((_, origin, essence), expected) in sources
+        .iter()
+        .zip([colormap::FEUILLUS, colormap::OTHER_VEGETATION])
+    {
+        // Centre du carré, à 10 m par pixel.
+        let col = ((origin.0 + 250.0 - project_bb.xmin) / 10.0) as isize;
+        let row = ((project_bb.ymax - origin.1 - 250.0) / 10.0) as isize;
+        let color: Vec<u8> = (1..=3)
+            .map(|band| {
+                dataset
+                    .rasterband(band)
+                    .unwrap()
+                    .read_as::<u8>((col, row), (1, 1), (1, 1), None)
+                    .unwrap()
+                    .data()[0]
+            })
+            .collect();
+        assert_eq!(color, expected.to_vec(), "Couche de '{}' ignorée", essence);
+    }
+    dataset.close().unwrap();
+
+    // La fusion regroupe les entités dans une seule couche, nommée d'après le fichier.
+    let merged_gpkg = "tmp/multilayer/FORMATION_VEGETALE.gpkg";
+    fusion_datasets(&source_gpkgs, merged_gpkg, false).unwrap();
+    let merged = Dataset::open(merged_gpkg).unwrap();
+    assert_eq!(layer_names(&merged), vec!["FORMATION_VEGETALE"]);
+    assert_eq!(merged.layer(0).unwrap().feature_count(), 2);
+    merged.close().unwrap();
+
+    fs::remove_dir_all("tmp/multilayer").unwrap();
+}
+
+#[test]
+fn test_uncovered_land_color_is_distinct_from_buildings() {
+    create_directory_if_not_exists("tmp/land_color").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_land_color").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let regional_gpkg = "tmp/land_color/REGIONAL.gpkg";
+    let building_gpkg = "tmp/land_color/BATIMENT.gpkg";
+    // Deux carrés de terres, le second entièrement couvert par un bâtiment.
+    for (geojson, gpkg, origin, squares) in [
+        (
+            "tmp/land_color/REGIONAL.geojson",
+            regional_gpkg,
+            (1210000.0, 6070000.0),
+            &["2A", "2A"][..],
+        ),
+        (
+            "tmp/land_color/BATIMENT.geojson",
+            building_gpkg,
+            (1210800.0, 6070000.0),
+            &["Bâti"][..],
+        ),
+    ] {
+        remove_file_if_exists(gpkg);
+        write_essence_geojson(geojson, "NATURE", origin, squares);
+        convert_to_gpkg(geojson, gpkg).unwrap();
+    }
+    // Centres des deux carrés, à 10 m par pixel.
+    let inland = (25, 475);
+    let building = (105, 475);
+
+    for (land_color, name) in [
+        (colormap::REGIONAL, "default"),
+        (colormap::LEGACY_REGIONAL, "legacy"),
+    ] {
+        let project_file_path = format!("tmp/land_color/{}.tiff", name);
+        create_project(&project_file_path, &project_bb).unwrap();
+        create_class_raster(&project_file_path).unwrap();
+        add_regional_layer(&work_dir, &project_file_path, regional_gpkg, land_color).unwrap();
+        add_building_layer(
+            &work_dir,
+            &project_file_path,
+            building_gpkg,
+            &BuildingHeights::default(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let dataset = Dataset::open(&project_file_path).unwrap();
+        let bands: Vec<Vec<u8>> = (1..=3).map(|i| read_band(&dataset, i).unwrap()).collect();
+        let codes = read_band(
+            &Dataset::open(class_raster_path(&project_file_path)).unwrap(),
+            1,
+        )
+        .unwrap();
+        let (width, _) = dataset.raster_size();
+        let pixel = |(col, row): (usize, usize)| {
+            let i = row * width + col;
+            ([bands[0][i], bands[1][i], bands[2][i]], codes[i])
+        };
+
+        assert_eq!(
+            pixel(inland),
+            (land_color, colormap::CLASS_LAND),
+            "{}",
+            name
+        );
+        assert_eq!(
+            pixel(building),
+            (colormap::TOPO, colormap::CLASS_BUILDING),
+            "{}",
+            name
+        );
+        dataset.close().unwrap();
+    }
+    assert_ne!(colormap::REGIONAL, colormap::TOPO);
+
+    fs::remove_dir_all("tmp/land_color").unwrap();
+}
+
+/// Ajout séquentiel de la végétation, tel qu'avant la rastérisation parallèle :
+/// trois rastérisations successives puis fusion de bandes entières.
+fn add_vegetation_layer_sequentially(work_dir: &WorkDir, project_file_path: &str, gpkg: &str) {
+    let layers = layer_names(&Dataset::open(gpkg).unwrap());
+    let essences = classify_essences(gpkg).unwrap();
+    let project = ProjectRaster::open(project_file_path).unwrap();
+    let sub_classes = [
+        (colormap::FEUILLUS, essences.feuillus_where().unwrap()),
+        (
+            colormap::UNDEFINED_VEGETATION,
+            essences.undefined_where().unwrap(),
+        ),
+        (colormap::OTHER_VEGETATION, essences.other_where().unwrap()),
+    ];
+    let (width, height) = project.dataset().raster_size();
+    let mut rasters = Vec::new();
+    for (index, (color, where_clause)) in sub_classes.iter().enumerate() {
+        let output = work_dir.path_str(format!("sequential_{}.tif", index));
+        rasterize_layer(
+            project.dataset(),
+            gpkg,
+            &layers,
+            &output,
+            *color,
+            Some(where_clause.as_str()),
+            None,
+        )
+        .unwrap();
+        rasters.push(Dataset::open(&output).unwrap());
+    }
+
+    let merged_path = work_dir.path_str("sequential_vegetation.tif");
+    let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+    let mut merged = driver.create(&merged_path, width, height, 3).unwrap();
+    merged
+        .set_geo_transform(&project.dataset().geo_transform().unwrap())
+        .unwrap();
+    for band_idx in 1..=3 {
+        let bands: Vec<Vec<u8>> = rasters
+            .iter()
+            .map(|raster| {
+                raster
+                    .rasterband(band_idx)
+                    .unwrap()
+                    .read_as::<u8>((0, 0), (width, height), (width, height), None)
+                    .unwrap()
+                    .data()
+                    .to_vec()
+            })
+            .collect();
+        let combined: Vec<u8> = (0..width * height)
+            .map(|pixel| {
+                if bands[0][pixel] > 0 {
+                    bands[0][pixel]
+                } else if bands[1][pixel] > 0 {
+                    bands[1][pixel]
+                } else {
+                    bands[2][pixel]
+                }
+            })
+            .collect();
+        merged
+            .rasterband(band_idx)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut gdal::raster::Buffer::new((width, height), combined),
+            )
+            .unwrap();
+    }
+    merged.close().unwrap();
+
+    overlay_in_place(
+        &project,
+        &merged_path,
+        |&value| value > 0,
+        Some(&colormap::vegetation_class),
+    )
+    .unwrap();
+    project.close().unwrap();
+}
+
+#[test]
+fn test_parallel_vegetation_matches_sequential() {
+    create_directory_if_not_exists("tmp/parallel_vegetation").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_parallel_vegetation").unwrap();
+    let project_bb = BoundingBox::new(1215000.0, 6075000.0, 1220000.0, 6080000.0);
+    let vegetation_gpkg = "tmp/parallel_vegetation/FORMATION_VEGETALE.gpkg";
+    let clipped_gpkg = "tmp/parallel_vegetation/FORMATION_VEGETALE_clipped.gpkg";
+    remove_file_if_exists(vegetation_gpkg);
+    remove_file_if_exists(clipped_gpkg);
+
+    extract_files_by_name(
+        "tests/res/BDFORET_2A.7z",
+        "FORMATION_VEGETALE",
+        "tmp/parallel_vegetation",
+    )
+    .unwrap();
+    convert_to_gpkg(
+        "tmp/parallel_vegetation/FORMATION_VEGETALE/FORMATION_VEGETALE.shp",
+        vegetation_gpkg,
+    )
+    .unwrap();
+    clip_to_bb(vegetation_gpkg, clipped_gpkg, &project_bb).unwrap();
+
+    let parallel_project = "tmp/parallel_vegetation/parallel.tiff";
+    let sequential_project = "tmp/parallel_vegetation/sequential.tiff";
+    create_project(parallel_project, &project_bb).unwrap();
+    create_project(sequential_project, &project_bb).unwrap();
+
+    take_processing_timings();
+    let result = add_vegetation_layer(&work_dir, parallel_project, clipped_gpkg, &mut Vec::new());
+    assert_result_ok(&result, "Adding the vegetation layer failed");
+    let timings = take_processing_timings();
+    assert!(
+        timings.get("rasterize_vegetation_parallel").is_some(),
+        "The parallel rasterization was not used: {:?}",
+        timings.keys().collect::<Vec<_>>()
+    );
+    assert!(timings.get("merge_vegetation").is_some());
+
+    add_vegetation_layer_sequentially(&work_dir, sequential_project, clipped_gpkg);
+
+    let parallel = Dataset::open(parallel_project).unwrap();
+    let sequential = Dataset::open(sequential_project).unwrap();
+    let size = parallel.raster_size();
+    for band_idx in 1..=parallel.raster_count() {
+        let read = |dataset: &Dataset| {
+            dataset
+                .rasterband(band_idx)
+                .unwrap()
+                .read_as::<u8>((0, 0), size, size, None)
+                .unwrap()
+                .data()
+                .to_vec()
+        };
+        assert!(
+            read(&parallel) == read(&sequential),
+            "Band {} differs from the sequential implementation",
+            band_idx
+        );
+    }
+    parallel.close().unwrap();
+    sequential.close().unwrap();
+    fs::remove_dir_all("tmp/parallel_vegetation").unwrap();
+}
+
+#[test]
+fn test_add_layer_open_errors_name_path_and_purpose() {
+    create_directory_if_not_exists("tmp/open_errors").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_open_errors").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/open_errors/open_errors.tiff";
+    let missing_gpkg = "tmp/open_errors/MISSING.gpkg";
+    let empty_gpkg = "tmp/open_errors/EMPTY.gpkg";
+    create_project(project_file_path, &project_bb).unwrap();
+    // GeoPackage valide mais sans aucune couche.
+    DriverManager::get_driver_by_name("GPKG")
+        .unwrap()
+        .create_vector_only(empty_gpkg)
+        .unwrap()
+        .close()
+        .unwrap();
+
+    type AddLayer = fn(&WorkDir, &str, &str) -> Result<(), Box<dyn std::error::Error>>;
+    let add_functions: [(&str, AddLayer); 6] = [
+        ("couche régionale", |work_dir, project, gpkg| {
+            add_regional_layer(work_dir, project, gpkg, colormap::REGIONAL)
+        }),
+        ("couche RPG", |work_dir, project, gpkg| {
+            add_rpg_layer(work_dir, project, gpkg, &mut Vec::new())
+        }),
+        ("couche de végétation", |work_dir, project, gpkg| {
+            add_vegetation_layer(work_dir, project, gpkg, &mut Vec::new())
+        }),
+        ("couche BDTOPO", |work_dir, project, gpkg| {
+            add_topo_layer(work_dir, project, gpkg, &mut Vec::new())
+        }),
+        ("couche BDTOPO", |work_dir, project, gpkg| {
+            add_hydro_layer(work_dir, project, gpkg, &mut Vec::new())
+        }),
+        ("couche des bâtiments", |work_dir, project, gpkg| {
+            add_building_layer(
+                work_dir,
+                project,
+                gpkg,
+                &BuildingHeights::default(),
+                &mut Vec::new(),
+            )
+            .map(|_| ())
+        }),
+    ];
+
+    for (purpose, add_layer) in add_functions {
+        let purpose = format!("{} du projet open_errors", purpose);
+        for gpkg in [missing_gpkg, empty_gpkg] {
+            let error = add_layer(&work_dir, project_file_path, gpkg).unwrap_err();
+            let message = error.to_string();
+            assert!(message.contains(gpkg), "{}", message);
+            assert!(message.contains(&purpose), "{}", message);
+            let dataset_error = error.downcast_ref::<DatasetError>().unwrap();
+            assert_eq!(
+                matches!(dataset_error, DatasetError::EmptyDataset { .. }),
+                gpkg == empty_gpkg
+            );
+        }
+    }
+
+    fs::remove_dir_all("tmp/open_errors").unwrap();
+}
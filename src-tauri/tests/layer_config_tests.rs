@@ -0,0 +1,196 @@
+use firefront_gis_lib::gis_operation::colormap::{self, RpgClassTable};
+use firefront_gis_lib::gis_operation::layer_config::{GeometryHint, LayerConfig, LayerGroup};
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::default_layer_config;
+
+#[test]
+fn test_optional_layers_disabled_by_default() {
+    let config = default_layer_config();
+    let processing_set = config.processing_set();
+
+    for name in [
+        "LIGNE_ELECTRIQUE",
+        "CIMETIERE",
+        "ZONE_D_ACTIVITE_OU_D_INTERET",
+    ] {
+        assert!(
+            config.layers.iter().any(|layer| layer.name == name),
+            "La couche {} doit être décrite",
+            name
+        );
+        assert!(
+            !processing_set.contains(&name),
+            "La couche {} ne doit pas être traitée par défaut",
+            name
+        );
+    }
+    for name in ["BATIMENT", "TRONCON_DE_ROUTE", "COURS_D_EAU"] {
+        assert!(processing_set.contains(&name));
+    }
+}
+
+#[test]
+fn test_enabling_power_lines_adds_them_to_processing_set() {
+    let mut config = default_layer_config();
+    config.set_enabled("LIGNE_ELECTRIQUE", true).unwrap();
+
+    assert!(config.processing_set().contains(&"LIGNE_ELECTRIQUE"));
+    let topo_layer = config
+        .enabled_layers(LayerGroup::Topo)
+        .into_iter()
+        .find(|layer| layer.name == "LIGNE_ELECTRIQUE")
+        .expect("La couche doit faire partie des couches topographiques");
+    assert_eq!(topo_layer.geometry, GeometryHint::Line);
+    assert!(
+        config
+            .enabled_layers(LayerGroup::Hydro)
+            .iter()
+            .all(|layer| layer.name != "LIGNE_ELECTRIQUE")
+    );
+
+    assert!(config.set_enabled("LIGNE_INCONNUE", true).is_err());
+}
+
+#[test]
+fn test_layer_classes_match_colormap() {
+    let config = default_layer_config();
+    let class_of = |name: &str| {
+        config
+            .layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .unwrap()
+            .class_code()
+    };
+
+    assert_eq!(class_of("BATIMENT"), colormap::CLASS_BUILDING);
+    assert_eq!(class_of("TRONCON_DE_ROUTE"), colormap::CLASS_ROAD);
+    assert_eq!(class_of("PLAN_D_EAU"), colormap::CLASS_WATER);
+    assert_eq!(class_of("AERODROME"), colormap::topo_class("AERODROME"));
+}
+
+#[test]
+fn test_topo_layers_applied_areas_then_buildings_then_lines() {
+    let mut config = default_layer_config();
+    config.set_enabled("LIGNE_ELECTRIQUE", true).unwrap();
+    let topo = config.enabled_layers(LayerGroup::Topo);
+    let position = |name: &str| topo.iter().position(|layer| layer.name == name).unwrap();
+
+    for area in ["AERODROME", "TERRAIN_DE_SPORT", "ZONE_D_ESTRAN"] {
+        assert!(position(area) < position("BATIMENT"), "{}", area);
+    }
+    for line in [
+        "TRONCON_DE_ROUTE",
+        "TRONCON_DE_VOIE_FERREE",
+        "LIGNE_ELECTRIQUE",
+    ] {
+        assert!(position("BATIMENT") < position(line), "{}", line);
+    }
+    assert!(
+        topo.windows(2)
+            .all(|pair| pair[0].priority <= pair[1].priority)
+    );
+
+    let hydro = config.enabled_layers(LayerGroup::Hydro);
+    assert_eq!(hydro.last().unwrap().name, "COURS_D_EAU");
+}
+
+#[test]
+fn test_invalid_layer_config_rejected() {
+    let mut config = default_layer_config();
+    config.layers[0].class = "lave".to_string();
+    assert!(config.validate().is_err());
+
+    let mut config = default_layer_config();
+    let duplicate = config.layers[0].clone();
+    config.layers.push(duplicate);
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_layer_selection_restricts_archives() {
+    let selection = vec![
+        "TRONCON_DE_ROUTE".to_string(),
+        "FORMATION_VEGETALE".to_string(),
+    ];
+    let config = default_layer_config()
+        .with_selection(Some(&selection))
+        .unwrap();
+
+    assert_eq!(config.processing_set(), vec!["TRONCON_DE_ROUTE"]);
+    assert!(config.includes("FORMATION_VEGETALE"));
+    assert!(!config.includes("PARCELLES_GRAPHIQUES"));
+    let archives: Vec<&str> = config.required_archives().into_iter().collect();
+    assert_eq!(archives, vec!["BDFORET", "BDTOPO"]);
+    assert_eq!(
+        config.selected_layers(),
+        Some(vec![
+            "FORMATION_VEGETALE".to_string(),
+            "TRONCON_DE_ROUTE".to_string(),
+        ])
+    );
+
+    let unknown = default_layer_config().with_selection(Some(&["ROUTE".to_string()]));
+    assert!(unknown.is_err());
+}
+
+#[test]
+fn test_layer_config_save_and_load() {
+    let path_str = "tests/tmp_layer_config.json";
+    let path = Path::new(path_str);
+    common::remove_file_if_exists(path_str);
+
+    let mut config = default_layer_config();
+    config.set_enabled("CIMETIERE", true).unwrap();
+    config.save_to(path).unwrap();
+
+    let loaded = LayerConfig::load_from(path).unwrap();
+    assert_eq!(loaded, config);
+    assert!(loaded.processing_set().contains(&"CIMETIERE"));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_rpg_class_table() {
+    let table = RpgClassTable::load_from(Path::new("resources/rpg_classes.json"))
+        .expect("La table des classes RPG fournie doit être valide");
+    assert_eq!(table.attribute, "CODE_GROUP");
+
+    let keys: Vec<&str> = table
+        .classes
+        .iter()
+        .map(|class| class.key.as_str())
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["cereals", "vineyards", "orchards", "grassland", "other"]
+    );
+    let vineyards = &table.classes[1];
+    assert_eq!(
+        table.where_clause(vineyards).unwrap(),
+        "CODE_GROUP IN ('21')"
+    );
+    assert!(
+        table
+            .unclassified_where()
+            .unwrap()
+            .starts_with("CODE_GROUP IS NULL OR CODE_GROUP NOT IN ('1', ")
+    );
+
+    let mut duplicate = table.clone();
+    duplicate.classes[1].codes.push("1".to_string());
+    assert!(duplicate.validate().is_err());
+
+    let mut generic_color = table.clone();
+    generic_color.classes[0].color = colormap::RPG;
+    assert!(generic_color.validate().is_err());
+
+    let mut injected = table.clone();
+    injected.attribute = "CODE_GROUP; DROP TABLE".to_string();
+    assert!(injected.validate().is_err());
+}
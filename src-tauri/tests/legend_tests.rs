@@ -0,0 +1,128 @@
+use firefront_gis_lib::gis_operation::colormap::{self, Rgb, RpgClassTable};
+use firefront_gis_lib::gis_operation::layer_config::{LayerConfig, LayerGroup};
+use firefront_gis_lib::gis_operation::legend::{
+    LegendEntry, PRIORITY_REGIONAL, PRIORITY_TOPO, build_legend,
+};
+use firefront_gis_lib::gis_operation::oso::load_reclass_table;
+use std::path::Path;
+
+mod common;
+
+use common::default_layer_config;
+
+fn default_legend(config: &LayerConfig) -> Vec<LegendEntry> {
+    legend_with_land(config, colormap::REGIONAL)
+}
+
+fn legend_with_land(config: &LayerConfig, land_color: Rgb) -> Vec<LegendEntry> {
+    let rpg_classes = RpgClassTable::load_from(Path::new("resources/rpg_classes.json")).unwrap();
+    build_legend(config, &rpg_classes, land_color)
+}
+
+#[test]
+fn test_every_rasterized_color_appears_once_in_legend() {
+    let config = default_layer_config();
+    let legend = default_legend(&config);
+
+    let rpg_classes = RpgClassTable::load_from(Path::new("resources/rpg_classes.json")).unwrap();
+    let oso_table = load_reclass_table(Path::new("resources/oso_reclass.json")).unwrap();
+    let mut rasterized: Vec<Rgb> = vec![
+        colormap::REGIONAL,
+        colormap::FEUILLUS,
+        colormap::UNDEFINED_VEGETATION,
+        colormap::OTHER_VEGETATION,
+        colormap::RPG,
+        colormap::TOPO,
+        colormap::HYDRO,
+        colormap::BUILDING_HIGH,
+    ];
+    rasterized.extend(rpg_classes.classes.iter().map(|class| class.color));
+    rasterized.extend(oso_table.values().copied());
+    rasterized.extend(
+        config
+            .layers
+            .iter()
+            .filter(|layer| layer.enabled)
+            .map(|layer| layer.color()),
+    );
+
+    for color in &rasterized {
+        let count = legend.iter().filter(|entry| entry.rgb == *color).count();
+        assert_eq!(count, 1, "La couleur {:?} doit apparaître une fois", color);
+    }
+    for entry in &legend {
+        assert!(
+            rasterized.contains(&entry.rgb),
+            "La couleur {:?} de {} n'est pas appliquée",
+            entry.rgb,
+            entry.class_key
+        );
+        assert!(!entry.label_fr.is_empty());
+        assert!(
+            !entry.label_en.contains("class."),
+            "Libellé anglais absent pour {}",
+            entry.class_key
+        );
+    }
+}
+
+#[test]
+fn test_legend_follows_layer_priority() {
+    let mut config = default_layer_config();
+    let legend = default_legend(&config);
+
+    assert!(
+        legend
+            .windows(2)
+            .all(|pair| pair[0].priority >= pair[1].priority),
+        "La légende doit être triée de la plus prioritaire à la moins prioritaire"
+    );
+
+    // Le fond départemental des nouveaux projets a sa propre entrée.
+    let land = legend
+        .iter()
+        .find(|entry| entry.rgb == colormap::REGIONAL)
+        .unwrap();
+    assert_eq!(land.class_key, "land");
+    assert_eq!(land.priority, PRIORITY_REGIONAL);
+    let black = legend
+        .iter()
+        .find(|entry| entry.rgb == colormap::TOPO)
+        .unwrap();
+    assert_eq!(black.priority, PRIORITY_TOPO);
+    assert!(!black.label_fr.contains("Terres"), "{}", black.label_fr);
+
+    // Dans la palette des projets antérieurs, le noir des couches topographiques
+    // recouvre le fond départemental.
+    let legacy = legend_with_land(&config, colormap::LEGACY_REGIONAL);
+    let black = legacy
+        .iter()
+        .find(|entry| entry.rgb == colormap::LEGACY_REGIONAL)
+        .unwrap();
+    assert_eq!(black.priority, PRIORITY_TOPO);
+    assert!(black.label_fr.contains("Bâtiments"), "{}", black.label_fr);
+    assert!(
+        black.label_fr.ends_with("Terres sans couverture connue"),
+        "{}",
+        black.label_fr
+    );
+    assert!(black.source_layer.contains("regions.geojson"));
+
+    // Sans couche topographique, le noir n'est plus que le fond départemental.
+    let topo_names: Vec<String> = config
+        .enabled_layers(LayerGroup::Topo)
+        .iter()
+        .map(|layer| layer.name.clone())
+        .collect();
+    for name in topo_names {
+        config.set_enabled(&name, false).unwrap();
+    }
+    let legend = legend_with_land(&config, colormap::LEGACY_REGIONAL);
+    let black = legend
+        .iter()
+        .find(|entry| entry.rgb == colormap::LEGACY_REGIONAL)
+        .unwrap();
+    assert_eq!(black.class_key, "land");
+    assert_eq!(black.priority, PRIORITY_REGIONAL);
+    assert_eq!(black.label_en, "Land without known cover");
+}
@@ -0,0 +1,101 @@
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gis_operation::processing::rasterize_layer;
+use firefront_gis_lib::logging::{parse_log_level, recent_log_lines};
+use firefront_gis_lib::utils::WorkDir;
+use gdal::DriverManager;
+use std::fs;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Tampon partagé recevant les enregistrements du subscriber de test.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
+    }
+}
+
+#[test]
+fn test_failing_rasterize_logs_error_with_layer_name() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "logging_rasterize").unwrap();
+    let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+    let mut project = driver
+        .create_with_band_type::<u8, _>(work_dir.path_for("projet.tiff"), 10, 10, 3)
+        .unwrap();
+    project
+        .set_geo_transform(&[1210000.0, 10.0, 0.0, 6095000.0, 0.0, -10.0])
+        .unwrap();
+
+    let buffer = SharedBuffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+
+    let result = tracing::subscriber::with_default(subscriber, || {
+        rasterize_layer(
+            &project,
+            &work_dir.path_str("ABSENTE.gpkg"),
+            "TRONCON_TEST",
+            &work_dir.path_str("projet.tiff"),
+            [255, 0, 0],
+            None,
+            None,
+        )
+    });
+
+    assert!(result.is_err());
+    let logs = buffer.contents();
+    assert!(logs.contains("ERROR"), "{}", logs);
+    assert!(logs.contains("TRONCON_TEST"), "{}", logs);
+    assert!(logs.contains("ABSENTE.gpkg"), "{}", logs);
+}
+
+#[test]
+fn test_recent_log_lines_reads_latest_file() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "logging_recent").unwrap();
+    fs::write(
+        work_dir.path_for("firefront.2025-01-01.log"),
+        "ancien 1\nancien 2\n",
+    )
+    .unwrap();
+    fs::write(
+        work_dir.path_for("firefront.2025-01-02.log"),
+        "ligne 1\nligne 2\nligne 3\n",
+    )
+    .unwrap();
+    fs::write(work_dir.path_for("autre.txt"), "ignoré\n").unwrap();
+
+    assert_eq!(
+        recent_log_lines(work_dir.path(), 2).unwrap(),
+        vec!["ligne 2", "ligne 3"]
+    );
+    assert_eq!(recent_log_lines(work_dir.path(), 10).unwrap().len(), 3);
+}
+
+#[test]
+fn test_recent_log_lines_without_logs() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "logging_empty").unwrap();
+
+    assert!(recent_log_lines(work_dir.path(), 50).unwrap().is_empty());
+}
+
+#[test]
+fn test_parse_log_level() {
+    assert_eq!(parse_log_level(" DEBUG ").unwrap(), "debug");
+    assert_eq!(parse_log_level("warn").unwrap(), "warn");
+    assert!(parse_log_level("verbeux").is_err());
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::{CONFIG, RunConfig};
+use firefront_gis_lib::gis_operation::create_project;
+use firefront_gis_lib::gis_operation::layers::ortho_tiff_to_jpeg;
+use firefront_gis_lib::gis_operation::ortho::{
+    OrthoSource, default_ortho_sources, gdal_http_env, prefer_source, sources_by_priority, wms_xml,
+};
+use firefront_gis_lib::utils::{BoundingBox, WorkDir};
+use gdal::raster::Buffer;
+use gdal::{Dataset, DriverManager};
+use std::path::Path;
+
+fn custom_source(name: &str, priority: i32) -> OrthoSource {
+    OrthoSource {
+        name: name.to_string(),
+        server_url: "https://wms.exemple.fr/service?map=ortho&v=2".to_string(),
+        layer: "HR.ORTHO<2024>".to_string(),
+        crs: "EPSG:3857".to_string(),
+        image_format: "image/png".to_string(),
+        api_key: Some("secret".to_string()),
+        http_header: Some("Authorization: Bearer jeton".to_string()),
+        priority,
+    }
+}
+
+fn names(sources: &[&OrthoSource]) -> Vec<String> {
+    sources.iter().map(|source| source.name.clone()).collect()
+}
+
+#[test]
+fn test_wms_xml_custom_source() {
+    let source = custom_source("Mon WMS", 0);
+    let bbox = get_test_bounding_box();
+    let xml = wms_xml(&source, &bbox, (2500, 2500), "/tmp/wms_cache");
+
+    assert!(xml.contains(
+        "<ServerUrl>https://wms.exemple.fr/service?map=ortho&amp;v=2&amp;apikey=secret</ServerUrl>"
+    ));
+    assert!(xml.contains("<CRS>EPSG:3857</CRS>"));
+    assert!(xml.contains("<ImageFormat>image/png</ImageFormat>"));
+    assert!(xml.contains("<Layers>HR.ORTHO&lt;2024&gt;</Layers>"));
+    assert!(xml.contains("<UpperLeftX>1210000</UpperLeftX>"));
+    assert!(xml.contains("<UpperLeftY>6095000</UpperLeftY>"));
+    assert!(xml.contains("<LowerRightX>1235000</LowerRightX>"));
+    assert!(xml.contains("<LowerRightY>6070000</LowerRightY>"));
+    assert!(xml.contains("<SizeX>2500</SizeX>"));
+    assert!(xml.contains("<SizeY>2500</SizeY>"));
+    assert!(xml.contains("<Path>/tmp/wms_cache</Path>"));
+}
+
+#[test]
+fn test_wms_xml_without_api_key() {
+    let source = &default_ortho_sources()[0];
+    let xml = wms_xml(source, &get_test_bounding_box(), (100, 100), "cache");
+
+    assert!(xml.contains("<ServerUrl>https://data.geopf.fr/wms-r/wms</ServerUrl>"));
+    assert!(xml.contains("<Layers>ORTHOIMAGERY.ORTHOPHOTOS</Layers>"));
+    assert!(xml.contains("<CRS>EPSG:2154</CRS>"));
+    assert!(!xml.contains("apikey"));
+}
+
+#[test]
+fn test_sources_fallback_order() {
+    let sources = vec![
+        custom_source("Secours", 5),
+        custom_source("Principale", 0),
+        custom_source("Intermédiaire A", 2),
+        custom_source("Intermédiaire B", 2),
+    ];
+
+    assert_eq!(
+        names(&sources_by_priority(&sources)),
+        vec![
+            "Principale",
+            "Intermédiaire A",
+            "Intermédiaire B",
+            "Secours"
+        ]
+    );
+}
+
+#[test]
+fn test_prefer_source() {
+    let mut sources = default_ortho_sources();
+    sources.push(custom_source("Mon WMS", 2));
+
+    assert!(prefer_source(&mut sources, "Mon WMS"));
+    assert_eq!(
+        names(&sources_by_priority(&sources)),
+        vec!["Mon WMS", "IGN - Orthophotos", "IGN - BD ORTHO"]
+    );
+
+    let before = sources.clone();
+    assert!(!prefer_source(&mut sources, "Inconnue"));
+    assert_eq!(sources, before);
+}
+
+#[test]
+fn test_gdal_http_env() {
+    assert_eq!(
+        gdal_http_env(&custom_source("Mon WMS", 0)),
+        vec![(
+            "GDAL_HTTP_HEADERS".to_string(),
+            "Authorization: Bearer jeton".to_string()
+        )]
+    );
+    assert!(gdal_http_env(&default_ortho_sources()[0]).is_empty());
+}
+
+/// Écrit un GeoTIFF de `bands` bandes de type `T` rempli d'un dégradé horizontal.
+fn write_satellite_tiff<T: gdal::raster::GdalType + Copy + From<u8>>(
+    path: &Path,
+    (width, height): (usize, usize),
+    bands: usize,
+) {
+    let dataset = DriverManager::get_driver_by_name("GTiff")
+        .unwrap()
+        .create_with_band_type::<T, _>(path, width, height, bands)
+        .unwrap();
+    let row: Vec<T> = (0..width).map(|x| T::from((x % 256) as u8)).collect();
+    let data: Vec<T> = row.iter().cycle().take(width * height).copied().collect();
+    for band in 1..=bands {
+        dataset
+            .rasterband(band)
+            .unwrap()
+            .write(
+                (0, 0),
+                (width, height),
+                &mut Buffer::new((width, height), data.clone()),
+            )
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_ortho_tiff_to_jpeg_matches_project_grid() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_ortho_jpeg").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1211000.0, 6071000.0);
+    let project_path = work_dir.path_str("project.tiff");
+    create_project(&project_path, &project_bb).unwrap();
+    let project = Dataset::open(&project_path).unwrap();
+    let size = project.raster_size();
+
+    for (name, bands) in [("rgb", 3), ("rgba", 4), ("grey", 1)] {
+        let tiff = work_dir.path_for(format!("{}.tif", name));
+        write_satellite_tiff::<u8>(&tiff, size, bands);
+        let jpeg = work_dir.path_str(format!("{}.jpeg", name));
+
+        let result = ortho_tiff_to_jpeg(&tiff, &jpeg, &project_bb, &RunConfig::snapshot());
+        assert_result_ok(&result, &format!("Conversion of the {} ortho failed", name));
+
+        let output = Dataset::open(&jpeg).unwrap();
+        assert_eq!(output.raster_size(), size);
+        assert_eq!(output.raster_count(), 3);
+        assert_eq!(
+            output.geo_transform().unwrap(),
+            project.geo_transform().unwrap()
+        );
+    }
+
+    let tiff = work_dir.path_for("uint16.tif");
+    write_satellite_tiff::<u16>(&tiff, size, 3);
+    let jpeg = work_dir.path_str("uint16.jpeg");
+    ortho_tiff_to_jpeg(&tiff, &jpeg, &project_bb, &RunConfig::snapshot()).unwrap();
+    let image = image::open(&jpeg).unwrap().to_rgb8();
+    assert!(image.get_pixel(0, 0)[0] < 10);
+    assert!(image.get_pixel(size.0 as u32 - 1, 0)[0] > 245);
+}
+
+#[test]
+fn test_ortho_tiff_to_jpeg_rejects_unexpected_size() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_ortho_jpeg_size").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1211000.0, 6071000.0);
+    let tiff = work_dir.path_for("satellite.tif");
+    write_satellite_tiff::<u8>(&tiff, (90, 100), 3);
+    let jpeg = work_dir.path_str("satellite.jpeg");
+
+    let error = ortho_tiff_to_jpeg(&tiff, &jpeg, &project_bb, &RunConfig::snapshot()).unwrap_err();
+
+    assert!(error.to_string().contains("90x100"), "{}", error);
+    assert!(error.to_string().contains("100x100"), "{}", error);
+    assert!(!Path::new(&jpeg).exists());
+}
+
+#[test]
+fn test_low_ortho_quality_makes_smaller_jpeg() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_ortho_jpeg_quality").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1211000.0, 6071000.0);
+    let size = (100, 100);
+    let tiff = work_dir.path_for("satellite.tif");
+    let dataset = DriverManager::get_driver_by_name("GTiff")
+        .unwrap()
+        .create_with_band_type::<u8, _>(&tiff, size.0, size.1, 3)
+        .unwrap();
+    // Du bruit, que la compression JPEG ne peut pas représenter à faible coût.
+    let mut seed: u32 = 12345;
+    for band in 1..=3 {
+        let data: Vec<u8> = (0..size.0 * size.1)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        dataset
+            .rasterband(band)
+            .unwrap()
+            .write((0, 0), size, &mut Buffer::new(size, data))
+            .unwrap();
+    }
+    dataset.close().unwrap();
+
+    let previous = CONFIG.lock().unwrap().image_quality.ortho_jpeg_quality;
+    let mut sizes = Vec::new();
+    for quality in [95, 20] {
+        CONFIG.lock().unwrap().image_quality.ortho_jpeg_quality = quality;
+        let jpeg = work_dir.path_str(format!("ortho_{}.jpeg", quality));
+        let result = ortho_tiff_to_jpeg(&tiff, &jpeg, &project_bb, &RunConfig::snapshot());
+        sizes.push(std::fs::metadata(&jpeg).map(|m| m.len()));
+        assert_result_ok(&result, "Conversion of the ortho failed");
+    }
+    CONFIG.lock().unwrap().image_quality.ortho_jpeg_quality = previous;
+
+    let (high, low) = (*sizes[0].as_ref().unwrap(), *sizes[1].as_ref().unwrap());
+    assert!(
+        low * 2 < high,
+        "Quality 20 should at least halve the file: {} vs {} bytes",
+        low,
+        high
+    );
+}
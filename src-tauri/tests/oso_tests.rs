@@ -0,0 +1,138 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::{Config, RunConfig, SettingsUpdate};
+use firefront_gis_lib::gis_operation::colormap;
+use firefront_gis_lib::gis_operation::create_project;
+use firefront_gis_lib::gis_operation::oso::{
+    VegetationSource, add_oso_raster, load_reclass_table, reclassify,
+};
+use firefront_gis_lib::utils::{BoundingBox, WorkDir, create_directory_if_not_exists};
+use gdal::spatial_ref::SpatialRef;
+use gdal::{Dataset, DriverManager};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const RECLASS_TABLE: &str = "resources/oso_reclass.json";
+
+/// Crée un raster OSO synthétique de 500x500 pixels découpé en quatre quadrants
+/// (feuillus, conifères, eau, sans donnée).
+fn create_synthetic_oso(path: &str, project_bb: &BoundingBox) {
+    let size = 500;
+    let driver = DriverManager::get_driver_by_name("GTiff").unwrap();
+    let mut dataset = driver
+        .create_with_band_type::<u8, _>(path, size, size, 1)
+        .unwrap();
+    dataset
+        .set_geo_transform(&[project_bb.xmin, 10.0, 0.0, project_bb.ymax, 0.0, -10.0])
+        .unwrap();
+    dataset
+        .set_spatial_ref(&SpatialRef::from_epsg(2154).unwrap())
+        .unwrap();
+
+    let data: Vec<u8> = (0..size * size)
+        .map(|i| match (i % size < size / 2, i / size < size / 2) {
+            (true, true) => 16,
+            (false, true) => 17,
+            (true, false) => 23,
+            (false, false) => 0,
+        })
+        .collect();
+    dataset
+        .rasterband(1)
+        .unwrap()
+        .write(
+            (0, 0),
+            (size, size),
+            &mut gdal::raster::Buffer::new((size, size), data),
+        )
+        .unwrap();
+}
+
+fn pixel_color(dataset: &Dataset, x: usize, y: usize) -> [u8; 3] {
+    let mut color = [0u8; 3];
+    for (i, value) in color.iter_mut().enumerate() {
+        *value = dataset
+            .rasterband(i + 1)
+            .unwrap()
+            .read_as::<u8>((x as isize, y as isize), (1, 1), (1, 1), None)
+            .unwrap()
+            .data()[0];
+    }
+    color
+}
+
+#[test]
+fn test_vegetation_source_parse() {
+    assert_eq!(
+        VegetationSource::parse("bdforet"),
+        Ok(VegetationSource::BdForet)
+    );
+    assert_eq!(VegetationSource::parse(""), Ok(VegetationSource::BdForet));
+    assert_eq!(VegetationSource::parse("oso"), Ok(VegetationSource::Oso));
+    assert_eq!(
+        VegetationSource::parse("file:/data/oso.tif"),
+        Ok(VegetationSource::File(PathBuf::from("/data/oso.tif")))
+    );
+    assert!(VegetationSource::parse("file:").is_err());
+    assert!(VegetationSource::parse("corine").is_err());
+}
+
+#[test]
+fn test_oso_url_setting() {
+    let url = "https://example.org/oso.tif".to_string();
+    let mut config = Config::default();
+    config
+        .apply_settings(
+            SettingsUpdate {
+                oso_url: Some(url.clone()),
+                ..SettingsUpdate::default()
+            },
+            false,
+        )
+        .unwrap();
+    assert_eq!(config.oso_url, Some(url));
+
+    // Un champ vidé retire l'URL : la source OSO redevient indisponible.
+    config
+        .apply_settings(SettingsUpdate::default(), false)
+        .unwrap();
+    assert_eq!(config.oso_url, None);
+}
+
+#[test]
+fn test_reclassify_codes() {
+    let table = load_reclass_table(Path::new(RECLASS_TABLE)).unwrap();
+    let [red, green, blue] = reclassify(&[16, 17, 23, 0], &table);
+
+    assert_eq!([red[0], green[0], blue[0]], colormap::FEUILLUS);
+    assert_eq!([red[1], green[1], blue[1]], colormap::OTHER_VEGETATION);
+    assert_eq!([red[2], green[2], blue[2]], [0, 0, 0]);
+    assert_eq!([red[3], green[3], blue[3]], [0, 0, 0]);
+}
+
+#[test]
+fn test_add_oso_raster_colors() {
+    create_directory_if_not_exists("tmp/oso").unwrap();
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_oso").unwrap();
+    let project_bb = BoundingBox::new(1210000.0, 6070000.0, 1215000.0, 6075000.0);
+    let project_file_path = "tmp/oso/test_oso.tiff";
+    let oso_path = "tmp/oso/synthetic_oso.tif";
+
+    create_project(project_file_path, &project_bb).unwrap();
+    create_synthetic_oso(oso_path, &project_bb);
+    let table = load_reclass_table(Path::new(RECLASS_TABLE)).unwrap();
+
+    let result = add_oso_raster(&work_dir, project_file_path, oso_path, &table);
+    assert_result_ok(&result, "Adding OSO layer failed");
+
+    let dataset = Dataset::open(project_file_path).unwrap();
+    assert_eq!(pixel_color(&dataset, 100, 100), colormap::FEUILLUS);
+    assert_eq!(pixel_color(&dataset, 400, 100), colormap::OTHER_VEGETATION);
+    assert_eq!(pixel_color(&dataset, 100, 400), [0, 0, 0]);
+    assert_eq!(pixel_color(&dataset, 400, 400), [0, 0, 0]);
+
+    dataset.close().unwrap();
+    fs::remove_dir_all("tmp/oso").unwrap();
+}
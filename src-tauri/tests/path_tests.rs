@@ -0,0 +1,188 @@
+use firefront_gis_lib::utils::{
+    MAX_PROJECT_NAME_LEN, NameError, project_dir, project_ortho_jpeg, project_tiff,
+    project_veget_jpeg, rename_project, resource_gpkg, sanitize_project_name,
+    validate_project_name, zip_entry_name,
+};
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+
+/// Vérifie qu'un chemin n'utilise que le séparateur de la plateforme, sans doublon.
+fn assert_clean_separators(path: &Path) {
+    let path = path.to_string_lossy();
+    let other_separator = if MAIN_SEPARATOR == '/' { '\\' } else { '/' };
+    let doubled = format!("{}{}", MAIN_SEPARATOR, MAIN_SEPARATOR);
+
+    assert!(
+        !path.contains(other_separator),
+        "séparateurs mélangés : {}",
+        path
+    );
+    assert!(
+        !path[1..].contains(&doubled),
+        "séparateur dupliqué : {}",
+        path
+    );
+}
+
+#[test]
+fn test_project_file_helpers() {
+    let name = "porto-vecchio";
+    let folder = project_dir(name);
+
+    assert_eq!(project_tiff(name), folder.join("porto-vecchio.tiff"));
+    assert_eq!(
+        project_veget_jpeg(name),
+        folder.join("porto-vecchio_VEGET.jpeg")
+    );
+    assert_eq!(
+        project_ortho_jpeg(name),
+        folder.join("porto-vecchio_ORTHO.jpeg")
+    );
+
+    for path in [
+        project_tiff(name),
+        project_veget_jpeg(name),
+        project_ortho_jpeg(name),
+    ] {
+        assert_eq!(path.parent(), Some(folder.as_path()));
+        assert_clean_separators(&path);
+    }
+}
+
+#[test]
+fn test_resource_gpkg_path() {
+    let folder = PathBuf::from("projets").join("porto-vecchio");
+    let path = resource_gpkg(&folder, "Vegetation");
+
+    let components: Vec<String> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(
+        components,
+        vec!["projets", "porto-vecchio", "resources", "Vegetation.gpkg"]
+    );
+    assert_clean_separators(&path);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_windows_paths_use_backslashes() {
+    let folder = Path::new(r"C:\Users\Jean Dupont\firefront\projets\porto-vecchio");
+    let path = resource_gpkg(folder, "Routes");
+
+    assert_eq!(
+        path,
+        PathBuf::from(
+            r"C:\Users\Jean Dupont\firefront\projets\porto-vecchio\resources\Routes.gpkg"
+        )
+    );
+    assert_clean_separators(&path);
+    assert_clean_separators(&project_tiff("porto-vecchio"));
+}
+
+#[test]
+fn test_zip_entry_names_use_forward_slashes() {
+    let relative = Path::new("slices")
+        .join("vegetation")
+        .join("slice_0_0.jpeg");
+
+    assert_eq!(
+        zip_entry_name(&relative),
+        "slices/vegetation/slice_0_0.jpeg"
+    );
+}
+
+#[test]
+fn test_validate_project_name_rejects_each_class() {
+    assert_eq!(validate_project_name("  "), Err(NameError::Empty));
+    assert!(matches!(
+        validate_project_name("feux/2024"),
+        Err(NameError::InvalidCharacter { character: '/', .. })
+    ));
+    assert!(matches!(
+        validate_project_name("feux\\2024"),
+        Err(NameError::InvalidCharacter {
+            character: '\\',
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate_project_name("feux\n2024"),
+        Err(NameError::InvalidCharacter {
+            character: '\n',
+            ..
+        })
+    ));
+    for reserved in ["CON", "nul", "Com1", "LPT9.txt", "aux "] {
+        assert!(
+            validate_project_name(reserved).is_err(),
+            "'{}' doit être refusé",
+            reserved
+        );
+    }
+    assert!(matches!(
+        validate_project_name("NUL.tiff"),
+        Err(NameError::Reserved { .. })
+    ));
+    for trailing in ["porto-vecchio.", "porto-vecchio ", ".", ".."] {
+        assert!(matches!(
+            validate_project_name(trailing),
+            Err(NameError::TrailingDotOrSpace { .. })
+        ));
+    }
+    let long_name = "a".repeat(MAX_PROJECT_NAME_LEN + 1);
+    assert!(matches!(
+        validate_project_name(&long_name),
+        Err(NameError::TooLong { length, .. }) if length == MAX_PROJECT_NAME_LEN + 1
+    ));
+
+    for valid in [
+        "porto-vecchio",
+        "Incendie 2024 (Corse)",
+        "console",
+        "é".repeat(100).as_str(),
+    ] {
+        assert_eq!(validate_project_name(valid).unwrap().as_str(), valid);
+    }
+}
+
+#[test]
+fn test_project_name_suggestions_are_valid() {
+    assert_eq!(sanitize_project_name("feux/2024: test"), "feux-2024- test");
+    assert_eq!(sanitize_project_name("CON"), "CON_");
+    assert_eq!(sanitize_project_name("nul.tiff"), "nul_.tiff");
+    assert_eq!(sanitize_project_name("porto-vecchio.. "), "porto-vecchio");
+    assert_eq!(sanitize_project_name(".."), "projet");
+
+    let error = validate_project_name("feux/2024").unwrap_err();
+    assert_eq!(error.suggestion().as_deref(), Some("feux-2024"));
+    assert!(error.to_string().contains("feux-2024"), "{}", error);
+    assert_eq!(NameError::Empty.suggestion(), None);
+
+    for name in ["a/b\\c", "COM3.", &"x".repeat(300), " \t ", "AUX . txt"] {
+        let suggestion = sanitize_project_name(name);
+        assert!(
+            validate_project_name(&suggestion).is_ok(),
+            "suggestion invalide pour '{}': '{}'",
+            name,
+            suggestion
+        );
+    }
+}
+
+#[test]
+fn test_path_traversal_names_are_rejected() {
+    for name in ["../../etc", "..", "..\\..\\Windows", "/etc/passwd"] {
+        assert!(
+            validate_project_name(name).is_err(),
+            "'{}' doit être refusé",
+            name
+        );
+    }
+    let outside = project_dir("../traversal-test");
+    assert!(rename_project("porto-vecchio", "../traversal-test").is_err());
+    assert!(!outside.exists());
+    // L'ancien nom est validé de la même façon.
+    assert!(rename_project("../projects/porto-vecchio", "traversal-test").is_err());
+    assert!(!project_dir("traversal-test").exists());
+}
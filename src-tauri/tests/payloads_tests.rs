@@ -0,0 +1,150 @@
+use firefront_gis_lib::commands::get_settings;
+use firefront_gis_lib::events::ProgressUpdate;
+use firefront_gis_lib::i18n::Language;
+use firefront_gis_lib::payloads::{
+    BoundingBox, CommandArgs, ConflictMode, CreateProjectArgs, ExportArgs, ExportOptions,
+    ExportTarget, ImageQuality, ProjectSummary, SettingsPayload, TiffCompression,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::fmt::Debug;
+
+/// Sérialise puis relit une valeur, comme lors d'un appel de commande.
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let serialized = serde_json::to_value(value).unwrap();
+    assert_eq!(&serde_json::from_value::<T>(serialized).unwrap(), value);
+}
+
+#[test]
+fn test_create_project_args() {
+    let bb = BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6095000.0);
+    let args = CreateProjectArgs {
+        on_conflict: Some(ConflictMode::Rename),
+        layers: Some(vec!["FORMATION_VEGETALE".to_string()]),
+        allow_oversize: Some(true),
+        template_project: Some("modele".to_string()),
+        ..CreateProjectArgs::new("porto-vecchio", bb)
+    };
+    round_trip(&args);
+
+    let serialized = serde_json::to_value(CommandArgs { args: args.clone() }).unwrap();
+    assert_eq!(serialized["args"]["project_bb"]["xmin"], json!(1210000.0));
+    assert_eq!(serialized["args"]["on_conflict"], json!("rename"));
+    round_trip(&CommandArgs { args });
+
+    // Seuls le nom et l'emprise sont obligatoires.
+    let minimal: CreateProjectArgs = serde_json::from_value(json!({
+        "name": "ajaccio",
+        "project_bb": { "xmin": 0.0, "ymin": 0.0, "xmax": 5000.0, "ymax": 5000.0 },
+    }))
+    .unwrap();
+    assert_eq!(
+        minimal,
+        CreateProjectArgs::new("ajaccio", BoundingBox::new(0.0, 0.0, 5000.0, 5000.0))
+    );
+
+    // L'ancien format du formulaire (code et coordonnées à plat) est refusé.
+    assert!(
+        serde_json::from_value::<CreateProjectArgs>(json!({
+            "name": "ajaccio",
+            "code": "2A",
+            "xmin": 0.0,
+            "ymin": 0.0,
+            "xmax": 5000.0,
+            "ymax": 5000.0,
+        }))
+        .is_err()
+    );
+}
+
+#[test]
+fn test_export_args() {
+    let args = ExportArgs {
+        project_name: "porto-vecchio".to_string(),
+        target: ExportTarget::Simulator,
+        override_output: Some("/tmp/exports".to_string()),
+        options: ExportOptions {
+            include_resources: true,
+            include_full_images: false,
+            include_qgis_project: true,
+        },
+    };
+    round_trip(&args);
+
+    let minimal: ExportArgs =
+        serde_json::from_value(json!({ "project_name": "porto-vecchio" })).unwrap();
+    assert_eq!(minimal.target, ExportTarget::Zip);
+    assert_eq!(minimal.override_output, None);
+    assert_eq!(minimal.options, ExportOptions::default());
+}
+
+#[test]
+fn test_event_and_list_payloads() {
+    round_trip(&ProgressUpdate::step_in(
+        Language::Fr,
+        "stage.download",
+        "step.extract",
+        &["2A"],
+        1,
+        3,
+    ));
+    round_trip(&ProgressUpdate::stage_in(Language::En, "stage.init"));
+
+    round_trip(&ProjectSummary {
+        name: "porto-vecchio".to_string(),
+        preview_path: "/projets/porto-vecchio/preview.jpg".to_string(),
+        folder: "/projets/porto-vecchio".to_string(),
+        vintages: "BDTOPO 2025-03".to_string(),
+        created_at: None,
+        size_bytes: 1024,
+        regions: vec!["2A".to_string()],
+        center: Some((1222500.0, 6082500.0)),
+        complete: true,
+        external: false,
+    });
+}
+
+#[test]
+fn test_settings_payload() {
+    let settings = get_settings().unwrap();
+    round_trip(&settings);
+    assert!(
+        settings
+            .languages
+            .iter()
+            .any(|language| language.code == "fr")
+    );
+    assert!(settings.log_levels.contains(&settings.log_level));
+
+    // Une réponse incomplète garde les valeurs par défaut des champs absents.
+    let partial: SettingsPayload = serde_json::from_value(json!({
+        "output_location": "/tmp",
+        "image_quality": { "tiff_compression": "lzw" },
+    }))
+    .unwrap();
+    assert_eq!(partial.output_location, "/tmp");
+    assert_eq!(
+        partial.image_quality,
+        ImageQuality {
+            tiff_compression: TiffCompression::Lzw,
+            ..ImageQuality::default()
+        }
+    );
+    assert!(partial.languages.is_empty());
+}
+
+#[test]
+fn test_tiff_compression_codes_match_serde() {
+    for compression in TiffCompression::ALL {
+        assert_eq!(
+            serde_json::to_value(compression).unwrap(),
+            json!(compression.code())
+        );
+        assert_eq!(
+            TiffCompression::from_code(compression.code()),
+            Some(compression)
+        );
+    }
+    assert_eq!(TiffCompression::from_code("zstd"), None);
+}
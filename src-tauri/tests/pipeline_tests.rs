@@ -0,0 +1,153 @@
+use firefront_gis_lib::gis_operation::layer_config::LayerConfig;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::pipeline::{
+    PIPELINE_STATE_FILE, PipelineContext, PipelineState, Stage, StageRunner, run_pipeline,
+};
+use firefront_gis_lib::utils::PROJECT_COMPLETE_MARKER;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod common;
+
+/// Étapes simulées : chaque étape écrit un fichier témoin dans le dossier du projet,
+/// et l'étape `fail_at` échoue comme le ferait une interruption.
+struct RecordingStages {
+    executed: Vec<Stage>,
+    fail_at: Option<Stage>,
+}
+
+impl RecordingStages {
+    fn new(fail_at: Option<Stage>) -> Self {
+        RecordingStages {
+            executed: Vec::new(),
+            fail_at,
+        }
+    }
+}
+
+impl StageRunner for RecordingStages {
+    async fn run_stage(&mut self, stage: Stage, ctx: &mut PipelineContext) -> Result<(), String> {
+        if self.fail_at == Some(stage) {
+            return Err(format!("Échec simulé de l'étape {:?}", stage));
+        }
+        if stage == Stage::AddLayers {
+            assert!(
+                ctx.project_folder.join("Fuse.done").exists(),
+                "La reprise doit réutiliser les données fusionnées"
+            );
+        }
+        fs::write(ctx.project_folder.join(format!("{:?}.done", stage)), b"").unwrap();
+        self.executed.push(stage);
+        Ok(())
+    }
+}
+
+fn create_pipeline_folder(name: &str) -> PathBuf {
+    let folder = PathBuf::from(format!("tests/tmp_pipeline_{}", name));
+    if folder.exists() {
+        fs::remove_dir_all(&folder).unwrap();
+    }
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+fn new_context(folder: &Path, name: &str) -> PipelineContext {
+    let state = PipelineState::new(
+        name,
+        &common::get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.save(folder).unwrap();
+    PipelineContext::new(folder.to_path_buf(), state, LayerConfig::default())
+}
+
+#[tokio::test]
+async fn test_pipeline_runs_all_stages_in_order() {
+    let folder = create_pipeline_folder("complete");
+    let mut ctx = new_context(&folder, "complete");
+    assert_eq!(ctx.state.next_stage(), Some(Stage::Download));
+
+    let mut stages = RecordingStages::new(None);
+    run_pipeline(&mut stages, &mut ctx).await.unwrap();
+
+    assert_eq!(stages.executed, Stage::ALL.to_vec());
+    let saved = PipelineState::load(&folder).unwrap();
+    assert!(saved.is_finished());
+    assert_eq!(saved.completed, Stage::ALL.to_vec());
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[tokio::test]
+async fn test_pipeline_resumes_after_failure() {
+    let folder = create_pipeline_folder("resume");
+    let mut ctx = new_context(&folder, "resume");
+
+    let mut failing = RecordingStages::new(Some(Stage::AddLayers));
+    let result = run_pipeline(&mut failing, &mut ctx).await;
+    assert!(
+        result.is_err(),
+        "L'étape injectée doit interrompre la création"
+    );
+    assert_eq!(
+        failing.executed,
+        vec![Stage::Download, Stage::Prepare, Stage::Fuse]
+    );
+
+    let saved = PipelineState::load(&folder).unwrap();
+    assert_eq!(
+        saved.completed,
+        vec![Stage::Download, Stage::Prepare, Stage::Fuse]
+    );
+    assert_eq!(saved.next_stage(), Some(Stage::AddLayers));
+    assert!(
+        !folder.join(PROJECT_COMPLETE_MARKER).exists(),
+        "Une création interrompue ne doit pas être marquée comme terminée"
+    );
+
+    let mut resumed_ctx = PipelineContext::new(folder.clone(), saved, LayerConfig::default());
+    let mut resumed = RecordingStages::new(None);
+    run_pipeline(&mut resumed, &mut resumed_ctx).await.unwrap();
+
+    assert_eq!(
+        resumed.executed,
+        vec![
+            Stage::AddLayers,
+            Stage::ExportJpeg,
+            Stage::Ortho,
+            Stage::Cleanup
+        ]
+    );
+    assert!(PipelineState::load(&folder).unwrap().is_finished());
+    assert!(folder.join(PROJECT_COMPLETE_MARKER).exists());
+
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn test_pipeline_state_roundtrip() {
+    let folder = create_pipeline_folder("state");
+    let mut state = PipelineState::new(
+        "state",
+        &common::get_test_bounding_box(),
+        &VegetationSource::File(PathBuf::from("/data/oso.tif")),
+        vec!["2A".to_string(), "2B".to_string()],
+    );
+    state.mark_completed(Stage::Download);
+    state.mark_completed(Stage::Download);
+    state.save(&folder).unwrap();
+
+    common::assert_file_exists(
+        folder.join(PIPELINE_STATE_FILE).to_str().unwrap(),
+        "L'état de la création doit être enregistré",
+    );
+    let loaded = PipelineState::load(&folder).unwrap();
+    assert_eq!(loaded, state);
+    assert_eq!(loaded.completed, vec![Stage::Download]);
+    assert_eq!(loaded.vegetation_source, "file:/data/oso.tif");
+    assert_eq!(loaded.next_stage(), Some(Stage::Prepare));
+
+    fs::remove_dir_all(&folder).unwrap();
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::events::ProgressUpdate;
+use firefront_gis_lib::fetcher::{DataFetcher, FixtureFetcher};
+use firefront_gis_lib::gis_operation::layer_config::LayerConfig;
+use firefront_gis_lib::gis_operation::layers::prepare_layers;
+use firefront_gis_lib::progress::{ProgressCollector, ProgressReport, ProgressSink};
+use firefront_gis_lib::timings::StageTimings;
+use firefront_gis_lib::utils::WorkDir;
+use firefront_gis_lib::web_request::DownloadJob;
+
+fn step(detail: &str, current: u64, total: u64) -> ProgressReport {
+    ProgressReport::new("stage.prepare", Some(detail), current, total)
+}
+
+#[test]
+fn test_collector_keeps_update_keys() {
+    let collector = ProgressCollector::default();
+
+    collector.report_update(ProgressUpdate::stage("stage.download"));
+    collector.report_update(ProgressUpdate::step(
+        "stage.download",
+        "step.download_file",
+        &["BDTOPO 2A"],
+        1,
+        3,
+    ));
+
+    assert_eq!(
+        collector.reports(),
+        vec![
+            ProgressReport::new("stage.download", None, 0, 0),
+            ProgressReport::new("stage.download", Some("step.download_file"), 1, 3),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_prepare_layers_reports_each_step_in_order() {
+    let fetcher = FixtureFetcher::new("tests/res");
+    let jobs = ["BDFORET", "RPG"]
+        .map(|file_type| DownloadJob {
+            file_type: file_type.to_string(),
+            code: "2A".to_string(),
+            url: String::new(),
+        })
+        .to_vec();
+    let result = fetcher.download_archives(jobs, &|_| {}).await;
+    assert_result_ok(&result, "Copying fixture archives failed");
+
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_prepare_progress").unwrap();
+    // Sans couche BDTOPO : seules la végétation et les parcelles agricoles sont préparées.
+    let layer_config = LayerConfig::default();
+    let collector = ProgressCollector::default();
+    let mut warnings = Vec::new();
+    let mut timings = StageTimings::default();
+
+    let result = prepare_layers(
+        &collector,
+        &work_dir,
+        &get_test_bounding_box(),
+        "2A",
+        &layer_config,
+        &mut warnings,
+        &mut timings,
+        &RunConfig::snapshot(),
+    )
+    .await;
+    assert_result_ok(&result, "Preparing layers failed");
+
+    assert_eq!(
+        collector.reports(),
+        vec![
+            step("step.regional_extent", 1, 3),
+            step("step.prepare_group", 2, 3),
+            step("step.extract", 1, 1),
+            step("step.convert", 1, 1),
+            step("step.clip", 1, 1),
+            step("step.prepare_group", 3, 3),
+            step("step.extract", 1, 1),
+            step("step.convert", 1, 1),
+            step("step.clip", 1, 1),
+        ]
+    );
+}
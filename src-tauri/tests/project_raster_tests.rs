@@ -0,0 +1,166 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::gis_operation::create_project;
+use firefront_gis_lib::gis_operation::processing::{
+    ProjectRaster, ProjectRasterError, RasterWindow, validate_project_raster,
+    validate_project_raster_at,
+};
+use firefront_gis_lib::utils::{BoundingBox, WorkDir, export_to_jpg};
+use gdal::raster::GdalType;
+use gdal::spatial_ref::SpatialRef;
+use gdal::{Dataset, DriverManager};
+
+/// Crée un GeoTIFF de 20 x 20 pixels dans `work_dir`.
+fn create_raster<T: GdalType>(
+    work_dir: &WorkDir,
+    name: &str,
+    bands: usize,
+    pixel_size: f64,
+    epsg: Option<u32>,
+) -> String {
+    let geo_transform = [1210000.0, pixel_size, 0.0, 6075000.0, 0.0, -pixel_size];
+    create_raster_with_transform::<T>(work_dir, name, bands, geo_transform, epsg)
+}
+
+/// Crée un GeoTIFF de 20 x 20 pixels dans `work_dir`, avec la géotransformation donnée.
+fn create_raster_with_transform<T: GdalType>(
+    work_dir: &WorkDir,
+    name: &str,
+    bands: usize,
+    geo_transform: [f64; 6],
+    epsg: Option<u32>,
+) -> String {
+    let path = work_dir.path_str(name);
+    let dataset = DriverManager::get_driver_by_name("GTiff")
+        .unwrap()
+        .create_with_band_type::<T, _>(&path, 20, 20, bands)
+        .unwrap();
+    dataset.set_geo_transform(&geo_transform).unwrap();
+    if let Some(epsg) = epsg {
+        dataset
+            .set_projection(&SpatialRef::from_epsg(epsg).unwrap().to_wkt().unwrap())
+            .unwrap();
+    }
+    dataset.close().unwrap();
+    path
+}
+
+fn assert_rejected(path: &str, expected: fn(&ProjectRasterError) -> bool) {
+    let error = validate_project_raster(&Dataset::open(path).unwrap()).unwrap_err();
+    assert!(expected(&error), "Unexpected error: {:?}", error);
+    let message = ProjectRaster::open_read_only(path)
+        .err()
+        .expect("The raster should be rejected")
+        .to_string();
+    assert!(
+        message.contains("not a Firefront project raster"),
+        "Unexpected error: {}",
+        message
+    );
+}
+
+#[test]
+fn test_rgb_raster_is_rejected() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_project_raster_bands").unwrap();
+    let path = create_raster::<u8>(&work_dir, "rgb.tif", 3, 10.0, Some(2154));
+    assert_rejected(&path, |e| *e == ProjectRasterError::BandCount(3));
+
+    let output_jpeg = work_dir.path_str("rgb.jpeg");
+    assert!(export_to_jpg(&path, &output_jpeg, &RunConfig::snapshot()).is_err());
+    assert!(!std::path::Path::new(&output_jpeg).exists());
+}
+
+#[test]
+fn test_non_byte_raster_is_rejected() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_project_raster_type").unwrap();
+    let path = create_raster::<u16>(&work_dir, "uint16.tif", 4, 10.0, Some(2154));
+    assert_rejected(&path, |e| {
+        matches!(e, ProjectRasterError::BandType { band: 1, .. })
+    });
+}
+
+#[test]
+fn test_foreign_crs_is_rejected() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_project_raster_crs").unwrap();
+    let wgs84 = create_raster::<u8>(&work_dir, "wgs84.tif", 4, 10.0, Some(4326));
+    assert_rejected(&wgs84, |e| *e == ProjectRasterError::Crs(Some(4326)));
+    let unknown = create_raster::<u8>(&work_dir, "unknown.tif", 4, 10.0, None);
+    assert_rejected(&unknown, |e| *e == ProjectRasterError::Crs(None));
+}
+
+#[test]
+fn test_other_resolution_is_read_with_its_own_pixel_size() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_project_raster_resolution").unwrap();
+    let path = create_raster::<u8>(&work_dir, "coarse.tif", 4, 20.0, Some(2154));
+    let dataset = Dataset::open(&path).unwrap();
+    assert_eq!(validate_project_raster(&dataset), Ok(20.0));
+    assert_eq!(
+        validate_project_raster_at(&dataset, 10.0),
+        Err(ProjectRasterError::Resolution {
+            pixel_size: (20.0, 20.0),
+            expected: 10.0,
+        })
+    );
+    assert!(ProjectRaster::open_read_only(&path).is_ok());
+    assert!(ProjectRaster::open_at(&path, 10.0).is_err());
+}
+
+#[test]
+fn test_pixels_off_grid_are_rejected() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_project_raster_grid").unwrap();
+    let geo_transform = [1210000.0, 10.0, 0.0, 6075000.0, 0.0, -20.0];
+    let path = create_raster_with_transform::<u8>(
+        &work_dir,
+        "rectangular.tif",
+        4,
+        geo_transform,
+        Some(2154),
+    );
+    assert_rejected(&path, |e| matches!(e, ProjectRasterError::PixelGrid { .. }));
+
+    let geo_transform = [1210003.0, 10.0, 0.0, 6075000.0, 0.0, -10.0];
+    let path =
+        create_raster_with_transform::<u8>(&work_dir, "shifted.tif", 4, geo_transform, Some(2154));
+    assert_rejected(&path, |e| {
+        *e == ProjectRasterError::PixelGrid {
+            pixel_size: (10.0, 10.0),
+            origin: (1210003.0, 6075000.0),
+        }
+    });
+}
+
+#[test]
+fn test_project_raster_stripes_round_trip() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_project_raster_round_trip").unwrap();
+    let path = work_dir.path_str("project.tiff");
+    create_project(
+        &path,
+        &BoundingBox::new(1210000.0, 6070000.0, 1211000.0, 6071000.0),
+    )
+    .unwrap();
+
+    let project = ProjectRaster::open(&path).unwrap();
+    assert_eq!(project.size(), (100, 100));
+    let window = RasterWindow {
+        x: 10,
+        y: 20,
+        width: 30,
+        height: 5,
+    };
+    let pixels = window.width * window.height;
+    let rgb = [vec![200u8; pixels], vec![120; pixels], vec![40; pixels]];
+    let alpha: Vec<u8> = (0..pixels).map(|i| (i % 2) as u8 * 255).collect();
+    project.write_rgb_stripe(window, rgb.clone()).unwrap();
+    project.write_alpha_stripe(window, alpha.clone()).unwrap();
+    project.close().unwrap();
+
+    let project = ProjectRaster::open_read_only(&path).unwrap();
+    assert_eq!(project.read_rgb_stripe(window).unwrap(), rgb);
+    assert_eq!(project.read_alpha_stripe(window).unwrap(), alpha);
+    let [red, _, _] = project.rgb_bands().unwrap();
+    let outside = red.read_as::<u8>((0, 0), (1, 1), (1, 1), None).unwrap();
+    assert_ne!(outside.data()[0], 200);
+}
@@ -0,0 +1,839 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::commands::clone_project_resources;
+use firefront_gis_lib::gis_operation::creation::settle_replaced_project;
+use firefront_gis_lib::gis_operation::gpkg_covers_bb;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::pipeline::{PIPELINE_STATE_FILE, PipelineState, Stage};
+use firefront_gis_lib::timings::StageTimings;
+use firefront_gis_lib::utils::{
+    BoundingBox, CreationParams, DataVintage, ImportMode, MAX_PROJECT_NAME_LEN, PREVIEW_MAX_SIZE,
+    PROJECT_COMPLETE_MARKER, ProjectMetadata, ProjectSort, ProjectSummary, TRASH_DIR, WorkDir,
+    copy_project, external_project_dir, format_vintages, free_project_name,
+    generate_project_preview, get_incomplete_projects, get_previous_projects,
+    get_project_bounding_box, get_project_details, import_project, in_projects_dir,
+    is_project_complete, list_projects, load_project_metadata, mark_project_complete,
+    move_project_to_trash, project_conflict, project_creation_params, project_dir,
+    project_ortho_jpeg, project_regions, project_size_bytes, project_tiff, refresh_project_ortho,
+    rename_project, save_project_metadata, sort_projects, trash_dir, unregister_external_project,
+    update_project_size, validate_project_name,
+};
+use gdal::DriverManager;
+use gdal::spatial_ref::SpatialRef;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_rename_project() {
+    let source = project_dir("porto-vecchio");
+    let old_name = "porto-vecchio-copy";
+    let new_name = "porto-vecchio-renamed";
+    let _ = fs::remove_dir_all(project_dir(old_name));
+    let _ = fs::remove_dir_all(project_dir(new_name));
+
+    copy_dir_recursive(&source, &project_dir(old_name));
+    fs::rename(
+        project_dir(old_name).join("porto-vecchio.tiff"),
+        project_dir(old_name).join(format!("{}.tiff", old_name)),
+    )
+    .unwrap();
+    for suffix in ["_VEGET.jpeg", "_ORTHO.jpeg"] {
+        fs::rename(
+            project_dir(old_name).join(format!("porto-vecchio{}", suffix)),
+            project_dir(old_name).join(format!("{}{}", old_name, suffix)),
+        )
+        .unwrap();
+    }
+    fs::create_dir_all(project_dir(old_name).join("resources")).unwrap();
+    fs::write(
+        project_dir(old_name)
+            .join("resources")
+            .join(format!("{}.gpkg", old_name)),
+        b"gpkg",
+    )
+    .unwrap();
+
+    let result = rename_project(old_name, new_name);
+    assert_result_ok(&result, "Renaming project failed");
+
+    let new_dir = result.unwrap();
+    assert_eq!(new_dir, project_dir(new_name));
+    assert!(!project_dir(old_name).exists(), "Old folder still exists");
+    for artifact in [
+        format!("{}.tiff", new_name),
+        format!("{}_VEGET.jpeg", new_name),
+        format!("{}_ORTHO.jpeg", new_name),
+        format!("resources/{}.gpkg", new_name),
+    ] {
+        assert_file_exists(
+            &new_dir.join(&artifact).to_string_lossy(),
+            &format!("{} missing after rename", artifact),
+        );
+    }
+
+    fs::remove_dir_all(new_dir).unwrap();
+}
+
+#[test]
+fn test_rename_project_rejects_invalid_names() {
+    assert!(rename_project("porto-vecchio", "porto/vecchio").is_err());
+    assert!(rename_project("porto-vecchio", "..").is_err());
+    assert!(rename_project("porto-vecchio", "porto-vecchio").is_err());
+    assert!(project_dir("porto-vecchio").exists());
+}
+
+#[test]
+fn test_copy_project() {
+    let new_name = "porto-vecchio-copied";
+    let _ = fs::remove_dir_all(project_dir(new_name));
+
+    let result = copy_project("porto-vecchio", new_name);
+    assert_result_ok(&result, "Copying project failed");
+
+    let new_dir = result.unwrap();
+    assert!(project_dir("porto-vecchio").exists());
+    assert_file_exists(
+        &new_dir.join(format!("{}.tiff", new_name)).to_string_lossy(),
+        "Project raster missing in copy",
+    );
+    assert!(!new_dir.join("porto-vecchio.tiff").exists());
+
+    fs::remove_dir_all(new_dir).unwrap();
+}
+
+#[test]
+fn test_clone_sub_extent_reuses_resources() {
+    let new_name = "porto-vecchio-sub";
+    let _ = fs::remove_dir_all(project_dir(new_name));
+    let sub_bb = BoundingBox::new(1210000.0, 6080000.0, 1215000.0, 6085000.0);
+
+    let result =
+        clone_project_resources("porto-vecchio", new_name, &sub_bb, &RunConfig::snapshot());
+    assert_result_ok(&result, "Cloning resources failed");
+    assert!(
+        result.unwrap(),
+        "Source resources should cover the sub-extent"
+    );
+
+    let resources = project_dir(new_name).join("resources");
+    let regional = resources.join(format!("{}.gpkg", new_name));
+    assert_file_exists(&regional.to_string_lossy(), "Regional layer not clipped");
+    assert_file_exists(
+        &resources.join("FORMATION_VEGETALE.gpkg").to_string_lossy(),
+        "Vegetation layer not clipped",
+    );
+    assert_file_exists(
+        &project_dir(new_name)
+            .join(format!("{}.tiff", new_name))
+            .to_string_lossy(),
+        "Project raster not created",
+    );
+    assert!(
+        !gpkg_covers_bb(
+            &regional.to_string_lossy(),
+            &BoundingBox::new(1205000.0, 6080000.0, 1215000.0, 6085000.0)
+        )
+        .unwrap()
+    );
+
+    fs::remove_dir_all(project_dir(new_name)).unwrap();
+}
+
+#[test]
+fn test_generate_project_preview() {
+    let new_name = "porto-vecchio-preview";
+    let _ = fs::remove_dir_all(project_dir(new_name));
+    copy_project("porto-vecchio", new_name).unwrap();
+    let _ = fs::remove_file(project_dir(new_name).join("preview.jpg"));
+
+    let preview = generate_project_preview(new_name).unwrap();
+    let (width, height) = image::image_dimensions(&preview).unwrap();
+    assert!(width <= PREVIEW_MAX_SIZE && height <= PREVIEW_MAX_SIZE);
+    assert!(width == PREVIEW_MAX_SIZE || height == PREVIEW_MAX_SIZE);
+
+    let first_mtime = fs::metadata(&preview).unwrap().modified().unwrap();
+    generate_project_preview(new_name).unwrap();
+    assert_eq!(
+        fs::metadata(&preview).unwrap().modified().unwrap(),
+        first_mtime,
+        "Preview was regenerated"
+    );
+
+    fs::remove_dir_all(project_dir(new_name)).unwrap();
+}
+
+#[test]
+fn test_preview_placeholder_without_images() {
+    let new_name = "porto-vecchio-no-images";
+    let _ = fs::remove_dir_all(project_dir(new_name));
+    fs::create_dir_all(project_dir(new_name)).unwrap();
+
+    let preview = generate_project_preview(new_name).unwrap();
+    assert_eq!(
+        image::image_dimensions(&preview).unwrap(),
+        (PREVIEW_MAX_SIZE, PREVIEW_MAX_SIZE)
+    );
+
+    fs::remove_dir_all(project_dir(new_name)).unwrap();
+}
+
+fn vintage(data_type: &str, region: &str, date: Option<&str>) -> DataVintage {
+    DataVintage {
+        data_type: data_type.to_string(),
+        region: region.to_string(),
+        date: date.map(str::to_string),
+        url: format!("https://data.geopf.fr/{}_{}.7z", data_type, region),
+    }
+}
+
+#[test]
+fn test_project_metadata_roundtrip_with_vintages() {
+    let project_name = "metadata-roundtrip";
+    let _ = fs::remove_dir_all(project_dir(project_name));
+    fs::create_dir_all(project_dir(project_name)).unwrap();
+
+    let mut timings = StageTimings::default();
+    timings.add("download", 12.5);
+    let metadata = ProjectMetadata {
+        vegetation_source: "bdforet".to_string(),
+        created_at: Some("2025-04-02T10:15:00+02:00".to_string()),
+        vintages: vec![
+            vintage("BDTOPO", "2A", Some("2025-03-15")),
+            vintage("BDFORET", "2A", Some("2017-05-10")),
+            vintage("RPG", "2A", Some("2023-01-01")),
+        ],
+        timings,
+        data_warnings: Vec::new(),
+        derived_from: None,
+        layers: None,
+        size_limit_override: None,
+        custom_layers: Vec::new(),
+        epsg: Some(2154),
+        regions: vec!["2A".to_string()],
+        size_bytes: Some(52_428_800),
+        ..ProjectMetadata::default()
+    };
+    save_project_metadata(project_name, &metadata).unwrap();
+    assert_eq!(load_project_metadata(project_name), metadata);
+
+    let details = get_project_details(project_name).unwrap();
+    assert_eq!(details.metadata, metadata);
+    assert_eq!(
+        details.vintage_summary,
+        "BDTOPO 2025-03, BDFORET 2017-05, RPG 2023"
+    );
+
+    fs::remove_dir_all(project_dir(project_name)).unwrap();
+    assert!(get_project_details(project_name).is_err());
+}
+
+#[test]
+fn test_legacy_metadata_has_no_vintages() {
+    let project_name = "metadata-legacy";
+    let _ = fs::remove_dir_all(project_dir(project_name));
+    fs::create_dir_all(project_dir(project_name)).unwrap();
+    fs::write(
+        project_dir(project_name).join("project.json"),
+        r#"{"vegetation_source":"oso"}"#,
+    )
+    .unwrap();
+
+    let metadata = load_project_metadata(project_name);
+    assert_eq!(metadata.vegetation_source, "oso");
+    assert_eq!(metadata.created_at, None);
+    assert!(metadata.vintages.is_empty());
+    assert_eq!(format_vintages(&metadata.vintages), "");
+
+    fs::remove_dir_all(project_dir(project_name)).unwrap();
+}
+
+#[test]
+fn test_format_vintages_lists_differing_departments() {
+    let vintages = vec![
+        vintage("BDTOPO", "2A", Some("2025-03-15")),
+        vintage("BDTOPO", "2B", Some("2024-06-15")),
+        vintage("BDFORET", "2A", Some("2017-05-10")),
+        vintage("BDFORET", "2B", Some("2017-05-10")),
+        vintage("RPG", "2A", None),
+    ];
+
+    assert_eq!(
+        format_vintages(&vintages),
+        "BDTOPO 2025-03/2024-06, BDFORET 2017-05"
+    );
+}
+
+#[test]
+fn test_incomplete_projects_are_tagged() {
+    let interrupted = "test-interrupted-project";
+    let complete = "test-complete-project";
+    let legacy = "test-legacy-project";
+    for name in [interrupted, complete, legacy] {
+        let _ = fs::remove_dir_all(project_dir(name));
+        fs::create_dir_all(project_dir(name)).unwrap();
+        fs::write(project_dir(name).join(format!("{}.tiff", name)), b"").unwrap();
+    }
+    mark_project_complete(&project_dir(complete)).unwrap();
+
+    // Projet créé avant l'ajout du marqueur : seul l'état de la création indique qu'il est terminé.
+    let mut state = PipelineState::new(
+        legacy,
+        &get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    for stage in Stage::ALL {
+        state.mark_completed(stage);
+    }
+    state.save(&project_dir(legacy)).unwrap();
+
+    assert!(!is_project_complete(&project_dir(interrupted)));
+    assert!(is_project_complete(&project_dir(complete)));
+    assert!(is_project_complete(&project_dir(legacy)));
+
+    let incomplete = get_incomplete_projects().unwrap();
+    assert!(incomplete.contains(&interrupted.to_string()));
+    assert!(!incomplete.contains(&complete.to_string()));
+    assert!(!incomplete.contains(&legacy.to_string()));
+
+    let projects = get_previous_projects().unwrap();
+    assert_eq!(projects[interrupted][3], "incomplete");
+    assert_eq!(projects[complete][3], "complete");
+
+    for name in [interrupted, complete, legacy] {
+        fs::remove_dir_all(project_dir(name)).unwrap();
+    }
+}
+
+/// Copie le projet de test sous un autre nom, comme un projet existant terminé.
+fn create_existing_project(name: &str) {
+    let _ = fs::remove_dir_all(project_dir(name));
+    copy_project("porto-vecchio", name).unwrap();
+    mark_project_complete(&project_dir(name)).unwrap();
+}
+
+#[test]
+fn test_conflict_abort_describes_existing_project() {
+    let name = "test-conflict-abort";
+    create_existing_project(name);
+    let metadata = ProjectMetadata {
+        created_at: Some("2025-06-01T10:00:00+02:00".to_string()),
+        ..load_project_metadata(name)
+    };
+    save_project_metadata(name, &metadata).unwrap();
+
+    let conflict = project_conflict(name).expect("Le projet existant doit être signalé");
+    assert_eq!(conflict.name, name);
+    assert_eq!(
+        conflict.created_at.as_deref(),
+        Some("2025-06-01T10:00:00+02:00")
+    );
+    assert!(conflict.size_bytes >= fs::metadata(project_tiff(name)).unwrap().len());
+    assert_eq!(conflict.bbox, get_project_bounding_box(name).ok());
+    assert_eq!(conflict.suggested_name, format!("{}-2", name));
+
+    // Une création interrompue n'est pas un conflit : elle est remplacée sans confirmation.
+    fs::remove_file(project_dir(name).join(PROJECT_COMPLETE_MARKER)).unwrap();
+    let _ = fs::remove_file(project_dir(name).join(PIPELINE_STATE_FILE));
+    assert!(project_conflict(name).is_none());
+    assert!(project_conflict("test-conflict-missing").is_none());
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
+
+#[test]
+fn test_conflict_rename_suggests_free_name() {
+    let name = "test-conflict-rename";
+    for existing in [name.to_string(), format!("{}-2", name)] {
+        let _ = fs::remove_dir_all(project_dir(&existing));
+        fs::create_dir_all(project_dir(&existing)).unwrap();
+    }
+    assert_eq!(free_project_name(name), format!("{}-3", name));
+
+    let long_name = "n".repeat(MAX_PROJECT_NAME_LEN);
+    let suggested = free_project_name(&long_name);
+    assert!(suggested.ends_with("-2"));
+    assert!(validate_project_name(&suggested).is_ok(), "{}", suggested);
+
+    for existing in [name.to_string(), format!("{}-2", name)] {
+        fs::remove_dir_all(project_dir(&existing)).unwrap();
+    }
+}
+
+#[test]
+fn test_conflict_overwrite_deletes_replaced_project_after_creation() {
+    let name = "test-conflict-overwrite";
+    create_existing_project(name);
+
+    let trashed = move_project_to_trash(name).unwrap();
+    assert!(trashed.starts_with(trash_dir()));
+    assert!(!project_dir(name).exists());
+    assert!(!get_previous_projects().unwrap().contains_key(TRASH_DIR));
+
+    // Nouvelle création terminée à la place de l'ancien projet.
+    fs::create_dir_all(project_dir(name)).unwrap();
+    fs::write(project_dir(name).join("new.txt"), b"nouveau").unwrap();
+    settle_replaced_project(name, &trashed, true).unwrap();
+
+    assert!(!trashed.exists());
+    assert!(project_dir(name).join("new.txt").exists());
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
+
+#[test]
+fn test_conflict_overwrite_restores_project_on_failure() {
+    let name = "test-conflict-rollback";
+    create_existing_project(name);
+    let original_size = fs::metadata(project_tiff(name)).unwrap().len();
+
+    let trashed = move_project_to_trash(name).unwrap();
+    // Création échouée : le dossier ne contient qu'un état de création inachevé.
+    fs::create_dir_all(project_dir(name)).unwrap();
+    fs::write(project_dir(name).join(PIPELINE_STATE_FILE), b"{}").unwrap();
+    settle_replaced_project(name, &trashed, false).unwrap();
+
+    assert!(!trashed.exists());
+    assert!(!project_dir(name).join(PIPELINE_STATE_FILE).exists());
+    assert_eq!(
+        fs::metadata(project_tiff(name)).unwrap().len(),
+        original_size
+    );
+    assert!(is_project_complete(&project_dir(name)));
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
+
+/// Téléchargement simulé : écrit une orthophoto uniforme de la taille du projet et son world file.
+fn mock_ortho_download(
+    _work_dir: &WorkDir,
+    output_path: &str,
+    project_bb: &BoundingBox,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = project_bb.raster_size(10.0)?;
+    let image = image::RgbImage::from_pixel(width as u32, height as u32, image::Rgb([10, 200, 30]));
+    image.save(output_path)?;
+    fs::write(
+        Path::new(output_path).with_extension("wld"),
+        "10\n0\n0\n-10\n0\n0\n",
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_refresh_ortho_replaces_image() {
+    let new_name = "porto-vecchio-ortho-refresh";
+    let _ = fs::remove_dir_all(project_dir(new_name));
+    copy_project("porto-vecchio", new_name).unwrap();
+    let steps = std::cell::RefCell::new(Vec::new());
+
+    let run_config = RunConfig::snapshot();
+    let result = refresh_project_ortho(
+        new_name,
+        &run_config,
+        mock_ortho_download,
+        &|step, done, total| {
+            steps
+                .borrow_mut()
+                .push(format!("{}|{}/{}", step, done, total))
+        },
+    );
+    assert_result_ok(&result, "Refreshing the orthophoto failed");
+
+    let ortho = image::open(project_ortho_jpeg(new_name)).unwrap().to_rgb8();
+    let pixel = ortho.get_pixel(0, 0).0;
+    assert!(
+        pixel[1] > 180 && pixel[0] < 40,
+        "Orthophoto not replaced: {:?}",
+        pixel
+    );
+    assert_file_exists(
+        &project_ortho_jpeg(new_name)
+            .with_extension("wld")
+            .to_string_lossy(),
+        "World file not replaced",
+    );
+    let preview = project_dir(new_name).join("preview.jpg");
+    assert!(
+        fs::metadata(&preview).unwrap().modified().unwrap()
+            >= fs::metadata(project_ortho_jpeg(new_name))
+                .unwrap()
+                .modified()
+                .unwrap()
+    );
+    assert_eq!(steps.borrow().len(), 3);
+    let leftovers: Vec<_> = fs::read_dir(project_dir(new_name))
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".part"))
+        .collect();
+    assert!(leftovers.is_empty());
+
+    fs::remove_dir_all(project_dir(new_name)).unwrap();
+}
+
+#[test]
+fn test_refresh_ortho_failure_keeps_existing_image() {
+    let new_name = "porto-vecchio-ortho-failure";
+    let _ = fs::remove_dir_all(project_dir(new_name));
+    copy_project("porto-vecchio", new_name).unwrap();
+    let before = fs::read(project_ortho_jpeg(new_name)).unwrap();
+
+    let result = refresh_project_ortho(
+        new_name,
+        &RunConfig::snapshot(),
+        |work_dir, output_path, _| {
+            // Téléchargement interrompu : un fichier partiel reste dans le dossier de travail.
+            fs::write(output_path, b"partiel")?;
+            assert!(work_dir.path().exists());
+            Err("Service WMS indisponible".into())
+        },
+        &|_, _, _| {},
+    );
+
+    assert!(result.is_err());
+    assert_eq!(fs::read(project_ortho_jpeg(new_name)).unwrap(), before);
+
+    fs::remove_dir_all(project_dir(new_name)).unwrap();
+}
+
+/// Crée une copie du projet de test hors du dossier des projets, comme sur un lecteur partagé.
+fn create_external_project(name: &str) -> PathBuf {
+    let folder = Path::new("tests/tmp_external").join(name);
+    let _ = fs::remove_dir_all(&folder);
+    let _ = fs::remove_dir_all(project_dir(name));
+    fs::create_dir_all(folder.parent().unwrap()).unwrap();
+    copy_project("porto-vecchio", name).unwrap();
+    fs::rename(project_dir(name), &folder).unwrap();
+    folder
+}
+
+#[test]
+fn test_import_project_by_reference() {
+    let name = "porto-vecchio-shared";
+    let folder = create_external_project(name);
+
+    let (imported, imported_folder) = import_project(&folder, ImportMode::Reference).unwrap();
+    assert_eq!(imported, name);
+    assert_eq!(imported_folder, folder.canonicalize().unwrap());
+    assert_eq!(project_dir(name), imported_folder);
+    assert!(
+        !in_projects_dir(name).exists(),
+        "Folder should stay in place"
+    );
+
+    let projects = get_previous_projects().unwrap();
+    let entry = projects.get(name).expect("External project is not listed");
+    assert_eq!(entry[1], imported_folder.to_string_lossy());
+    assert_eq!(entry[3], "complete");
+    assert_eq!(entry[4], "external");
+    assert!(Path::new(&entry[0]).starts_with(&imported_folder));
+    assert!(Path::new(&entry[0]).exists(), "Preview was not generated");
+    assert_eq!(projects["porto-vecchio"][4], "local");
+
+    assert!(
+        rename_project(name, "porto-vecchio-shared-renamed").is_err(),
+        "External projects must not be renamed"
+    );
+    assert!(
+        import_project(&folder, ImportMode::Reference).is_err(),
+        "A project cannot be imported twice"
+    );
+
+    assert!(unregister_external_project(name).unwrap());
+    assert!(external_project_dir(name).is_none());
+    assert!(!get_previous_projects().unwrap().contains_key(name));
+    assert!(
+        folder.join(format!("{}.tiff", name)).exists(),
+        "Removing an external project must keep its folder"
+    );
+    assert!(!unregister_external_project(name).unwrap());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_import_project_by_copy() {
+    let name = "porto-vecchio-imported";
+    let folder = create_external_project(name);
+
+    let (_, imported_folder) = import_project(&folder, ImportMode::Copy).unwrap();
+
+    assert_eq!(imported_folder, in_projects_dir(name));
+    assert!(project_tiff(name).exists());
+    assert!(folder.exists(), "Copy must keep the source folder");
+    assert!(external_project_dir(name).is_none());
+    assert_eq!(get_previous_projects().unwrap()[name][4], "local");
+
+    fs::remove_dir_all(folder).unwrap();
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
+
+#[test]
+fn test_import_project_rejects_invalid_folders() {
+    let empty = Path::new("tests/tmp_external/empty-project");
+    fs::create_dir_all(empty).unwrap();
+    let error = import_project(empty, ImportMode::Reference).unwrap_err();
+    assert!(
+        error.to_string().contains("empty-project.tiff"),
+        "{}",
+        error
+    );
+
+    let wrong_crs = Path::new("tests/tmp_external/wgs84-project");
+    fs::create_dir_all(wrong_crs).unwrap();
+    let mut dataset = DriverManager::get_driver_by_name("GTiff")
+        .unwrap()
+        .create(wrong_crs.join("wgs84-project.tiff"), 10, 10, 1)
+        .unwrap();
+    dataset
+        .set_spatial_ref(&SpatialRef::from_epsg(4326).unwrap())
+        .unwrap();
+    drop(dataset);
+    let error = import_project(wrong_crs, ImportMode::Reference).unwrap_err();
+    assert!(error.to_string().contains("EPSG:2154"), "{}", error);
+
+    let error = import_project(&project_dir("porto-vecchio"), ImportMode::Reference).unwrap_err();
+    assert!(error.to_string().contains("porto-vecchio"), "{}", error);
+
+    fs::remove_dir_all(empty).unwrap();
+    fs::remove_dir_all(wrong_crs).unwrap();
+}
+
+fn summary(
+    name: &str,
+    created_at: Option<&str>,
+    size_bytes: u64,
+    regions: &[&str],
+) -> ProjectSummary {
+    ProjectSummary {
+        name: name.to_string(),
+        preview_path: String::new(),
+        folder: String::new(),
+        vintages: String::new(),
+        created_at: created_at.map(|date| date.to_string()),
+        size_bytes,
+        regions: regions.iter().map(|code| code.to_string()).collect(),
+        center: None,
+        complete: true,
+        external: false,
+    }
+}
+
+fn names(projects: &[ProjectSummary]) -> Vec<&str> {
+    projects
+        .iter()
+        .map(|project| project.name.as_str())
+        .collect()
+}
+
+#[test]
+fn test_sort_projects_breaks_ties_by_name() {
+    let projects = vec![
+        summary("delta", Some("2025-06-01T10:00:00+02:00"), 300, &["13"]),
+        summary("alpha", None, 100, &[]),
+        summary("charlie", Some("2025-06-01T09:30:00+01:00"), 300, &["2A"]),
+        summary(
+            "bravo",
+            Some("2024-01-15T08:00:00+01:00"),
+            100,
+            &["2A", "2B"],
+        ),
+    ];
+    let sorted = |sort_by: ProjectSort| {
+        // Le résultat ne dépend pas de l'ordre de départ.
+        let mut forward = projects.clone();
+        let mut backward: Vec<ProjectSummary> = projects.iter().rev().cloned().collect();
+        sort_projects(&mut forward, sort_by);
+        sort_projects(&mut backward, sort_by);
+        assert_eq!(forward, backward);
+        forward
+    };
+
+    assert_eq!(
+        names(&sorted(ProjectSort::Name)),
+        ["alpha", "bravo", "charlie", "delta"]
+    );
+    // 09:30+01:00 est postérieur à 10:00+02:00 ; les projets sans date sont à la fin.
+    assert_eq!(
+        names(&sorted(ProjectSort::CreatedAt)),
+        ["charlie", "delta", "bravo", "alpha"]
+    );
+    assert_eq!(
+        names(&sorted(ProjectSort::Size)),
+        ["charlie", "delta", "alpha", "bravo"]
+    );
+    assert_eq!(
+        names(&sorted(ProjectSort::Department)),
+        ["delta", "bravo", "charlie", "alpha"]
+    );
+    assert_eq!(
+        serde_json::from_str::<ProjectSort>("\"created_at\"").unwrap(),
+        ProjectSort::CreatedAt
+    );
+}
+
+#[test]
+fn test_filter_projects_by_name_or_department() {
+    let corsica = summary("Porto-Vecchio", None, 0, &["2A"]);
+    assert!(corsica.matches(""));
+    assert!(corsica.matches("porto"));
+    assert!(corsica.matches(" 2a "));
+    assert!(!corsica.matches("2"));
+    assert!(!corsica.matches("13"));
+
+    let corsica_name = "test-list-corsica";
+    let provence_name = "test-list-provence";
+    for (name, region) in [(corsica_name, "2A"), (provence_name, "13")] {
+        create_existing_project(name);
+        let metadata = ProjectMetadata {
+            regions: vec![region.to_string()],
+            ..load_project_metadata(name)
+        };
+        save_project_metadata(name, &metadata).unwrap();
+    }
+
+    let listed = list_projects(ProjectSort::Name, Some("2A")).unwrap();
+    assert!(listed.iter().any(|project| project.name == corsica_name));
+    assert!(listed.iter().all(|project| project.name != provence_name));
+    let corsica = listed
+        .iter()
+        .find(|project| project.name == corsica_name)
+        .unwrap();
+    assert!(corsica.complete);
+    assert_eq!(corsica.regions, ["2A"]);
+    let bb = get_project_bounding_box(corsica_name).unwrap();
+    assert_eq!(
+        corsica.center,
+        Some(((bb.xmin + bb.xmax) / 2.0, (bb.ymin + bb.ymax) / 2.0))
+    );
+
+    let all = list_projects(ProjectSort::Name, None).unwrap();
+    assert!(all.iter().any(|project| project.name == provence_name));
+    assert!(all.windows(2).all(|pair| pair[0].name <= pair[1].name));
+
+    for name in [corsica_name, provence_name] {
+        fs::remove_dir_all(project_dir(name)).unwrap();
+    }
+}
+
+#[test]
+fn test_project_regions_fall_back_to_vintages() {
+    let legacy = ProjectMetadata {
+        vintages: vec![
+            vintage("BDTOPO", "2B", None),
+            vintage("BDTOPO", "2A", None),
+            vintage("RPG", "2A", None),
+        ],
+        ..ProjectMetadata::default()
+    };
+    assert_eq!(project_regions(&legacy), ["2A", "2B"]);
+    let recorded = ProjectMetadata {
+        regions: vec!["13".to_string()],
+        ..legacy
+    };
+    assert_eq!(project_regions(&recorded), ["13"]);
+}
+
+fn walk_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            if path.is_dir() {
+                walk_size(&path)
+            } else {
+                fs::metadata(&path).unwrap().len()
+            }
+        })
+        .sum()
+}
+
+#[test]
+fn test_project_size_uses_cached_value() {
+    let name = "test-list-size";
+    create_existing_project(name);
+    let metadata = ProjectMetadata {
+        size_bytes: None,
+        ..load_project_metadata(name)
+    };
+    save_project_metadata(name, &metadata).unwrap();
+
+    // Sans valeur enregistrée, le dossier est parcouru.
+    let walked = walk_size(&project_dir(name));
+    assert!(walked > 0);
+    assert_eq!(project_size_bytes(name, &metadata), walked);
+
+    // Une valeur enregistrée est reprise telle quelle.
+    let cached = ProjectMetadata {
+        size_bytes: Some(42),
+        ..metadata
+    };
+    save_project_metadata(name, &cached).unwrap();
+    let listed = list_projects(ProjectSort::Size, Some(name)).unwrap();
+    assert_eq!(names(&listed), [name]);
+    assert_eq!(listed[0].size_bytes, 42);
+
+    let measured = update_project_size(name).unwrap();
+    assert!(measured >= fs::metadata(project_tiff(name)).unwrap().len());
+    assert_eq!(load_project_metadata(name).size_bytes, Some(measured));
+    let listed = list_projects(ProjectSort::Size, Some(name)).unwrap();
+    assert_eq!(listed[0].size_bytes, measured);
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
+
+#[test]
+fn test_creation_params_round_trip() {
+    let params = CreationParams {
+        vegetation_source: Some("oso".to_string()),
+        layers: Some(vec![
+            "FORMATION_VEGETALE".to_string(),
+            "TRONCON_DE_ROUTE".to_string(),
+        ]),
+        resolution: Some(5.0),
+        ortho_source: Some("IGN".to_string()),
+    };
+    let metadata = ProjectMetadata {
+        creation_params: Some(params.clone()),
+        ..ProjectMetadata::default()
+    };
+    let json = serde_json::to_string(&metadata).unwrap();
+    let parsed: ProjectMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.creation_params, Some(params.clone()));
+
+    // Les arguments renseignés l'emportent sur ceux du modèle.
+    let explicit = CreationParams {
+        vegetation_source: Some("bdforet".to_string()),
+        resolution: Some(10.0),
+        ..CreationParams::default()
+    };
+    let merged = explicit.or_template(&params);
+    assert_eq!(merged.vegetation_source.as_deref(), Some("bdforet"));
+    assert_eq!(merged.layers, params.layers);
+    assert_eq!(merged.resolution, Some(10.0));
+    assert_eq!(merged.ortho_source.as_deref(), Some("IGN"));
+}
+
+#[test]
+fn test_creation_params_of_legacy_project() {
+    let name = "test-creation-params-legacy";
+    create_existing_project(name);
+    let legacy = ProjectMetadata {
+        vegetation_source: "oso".to_string(),
+        layers: Some(vec!["FORMATION_VEGETALE".to_string()]),
+        ..ProjectMetadata::default()
+    };
+    save_project_metadata(name, &legacy).unwrap();
+
+    let params = project_creation_params(name).unwrap();
+    assert_eq!(params.vegetation_source.as_deref(), Some("oso"));
+    assert_eq!(params.layers, legacy.layers);
+    assert_eq!(params.resolution, None);
+    assert!(project_creation_params("test-creation-params-missing").is_err());
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
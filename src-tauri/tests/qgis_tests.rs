@@ -0,0 +1,148 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::app_setup::RunConfig;
+use firefront_gis_lib::busy::CancelToken;
+use firefront_gis_lib::fetcher::FixtureFetcher;
+use firefront_gis_lib::gis_operation::layer_names;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::gis_operation::qgis::{
+    generate_qgis_project, qgis_layers, qgis_project_path,
+};
+use firefront_gis_lib::pipeline::{create_project_pipeline, creation_layer_config};
+use firefront_gis_lib::progress::NoProgress;
+use firefront_gis_lib::utils::{ExportOptions, project_dir};
+use gdal::Dataset;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::fs;
+use std::path::Path;
+
+/// Éléments d'un document XML : chemin de chaque élément (`qgis/projectlayers/maplayer`)
+/// et texte qu'il contient.
+fn xml_elements(xml: &str) -> Vec<(String, String)> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut path: Vec<String> = Vec::new();
+    let mut elements = Vec::new();
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Start(element) => {
+                path.push(String::from_utf8_lossy(element.name().as_ref()).to_string());
+                elements.push((path.join("/"), String::new()));
+            }
+            Event::Empty(element) => {
+                let name = String::from_utf8_lossy(element.name().as_ref()).to_string();
+                elements.push((format!("{}/{}", path.join("/"), name), String::new()));
+            }
+            Event::Text(text) => {
+                if let Some(last) = elements.last_mut() {
+                    last.1 = text.unescape().unwrap().to_string();
+                }
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    elements
+}
+
+fn texts<'a>(elements: &'a [(String, String)], path: &str) -> Vec<&'a str> {
+    elements
+        .iter()
+        .filter(|(element, _)| element == path)
+        .map(|(_, text)| text.as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_qgis_project_references_project_files() {
+    let name = "qgis-porto-vecchio";
+    let _ = fs::remove_dir_all(project_dir(name));
+    create_project_pipeline(
+        name,
+        &get_test_bounding_box(),
+        &creation_layer_config(VegetationSource::BdForet, None).unwrap(),
+        CancelToken::default(),
+        &mut Vec::new(),
+        &NoProgress,
+        &FixtureFetcher::new("tests/res"),
+        RunConfig::snapshot(),
+    )
+    .await
+    .unwrap();
+
+    let path = generate_qgis_project(name).unwrap();
+    assert_eq!(path, qgis_project_path(name));
+    let elements = xml_elements(&fs::read_to_string(&path).unwrap());
+
+    // Orthophoto, GeoTIFF et raster de classes, puis une couche par couche de chaque GeoPackage.
+    let resources = project_dir(name).join("resources");
+    let vector_layers: usize = fs::read_dir(&resources)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "gpkg"))
+        .map(|path| layer_names(&Dataset::open(path).unwrap()).len())
+        .sum();
+    let sources = texts(&elements, "qgis/projectlayers/maplayer/datasource");
+    assert_eq!(sources.len(), 3 + vector_layers);
+    assert_eq!(sources.len(), qgis_layers(name).unwrap().len());
+    assert_eq!(
+        texts(&elements, "qgis/layer-tree-group/layer-tree-layer").len(),
+        sources.len()
+    );
+
+    // Chemins relatifs au dossier du projet, qui reste déplaçable.
+    assert!(
+        sources
+            .iter()
+            .all(|source| source.starts_with("./") && !Path::new(source).is_absolute()),
+        "{:?}",
+        sources
+    );
+    for expected in [
+        format!("./{}.tiff", name),
+        format!("./{}_CLASSES.tif", name),
+        format!("./{}_ORTHO.jpeg", name),
+        "./resources/FORMATION_VEGETALE.gpkg|layername=FORMATION_VEGETALE".to_string(),
+    ] {
+        assert!(
+            sources.contains(&expected.as_str()),
+            "{} absent de {:?}",
+            expected,
+            sources
+        );
+    }
+
+    assert_eq!(
+        texts(&elements, "qgis/projectCrs/spatialrefsys/authid"),
+        ["EPSG:2154"]
+    );
+    let extent = get_test_bounding_box();
+    assert_eq!(
+        texts(&elements, "qgis/mapcanvas/extent/xmin"),
+        [extent.xmin.to_string()]
+    );
+    assert_eq!(
+        texts(&elements, "qgis/properties/Paths/Absolute"),
+        ["false"]
+    );
+
+    // Le projet QGIS n'est exporté qu'à la demande.
+    let qgs = format!("{}.qgs", name);
+    let qgs = Path::new(&qgs);
+    assert!(!ExportOptions::default().includes(name, qgs));
+    assert!(
+        ExportOptions {
+            include_qgis_project: true,
+            ..ExportOptions::default()
+        }
+        .includes(name, qgs)
+    );
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
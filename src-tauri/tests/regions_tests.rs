@@ -3,15 +3,119 @@ mod common;
 use common::*;
 use firefront_gis_lib::{
     gis_operation::regions::{
+        REGIONS_GEOJSON_FILE, REGIONS_GRAPH_FILE, RegionDirectory, RegionsIndex,
         build_regions_graph, find_intersecting_regions, get_neighbors, get_region,
+        load_regions_graph, regions_graph_reads, regions_source_hash, with_regions_index,
     },
+    pipeline::covered_regions,
     utils::BoundingBox,
 };
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Écrit un GeoJSON de départements carrés de 10 km, indexés par code et coin inférieur gauche.
+fn write_regions_geojson(path: &Path, squares: &[(&str, f64, f64)]) {
+    let features: Vec<serde_json::Value> = squares
+        .iter()
+        .map(|(code, x, y)| {
+            serde_json::json!({
+                "type": "Feature",
+                "properties": { "code": code, "nom": format!("Département {}", code) },
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[
+                        [x, y],
+                        [x + 10000.0, y],
+                        [x + 10000.0, y + 10000.0],
+                        [x, y + 10000.0],
+                        [x, y]
+                    ]]
+                }
+            })
+        })
+        .collect();
+    let collection = serde_json::json!({ "type": "FeatureCollection", "features": features });
+    fs::write(path, collection.to_string()).unwrap();
+}
 
 #[test]
 fn test_build_regions_graph() {
-    let result = build_regions_graph(Some("resources/regions_graph.json"));
+    let result = build_regions_graph(
+        Path::new(REGIONS_GEOJSON_FILE),
+        Path::new(REGIONS_GRAPH_FILE),
+        false,
+    );
     assert_result_ok(&result, "Building regions graph failed");
+
+    let report = result.unwrap();
+    assert!(
+        !report.rebuilt,
+        "The committed graph should match resources/regions.geojson"
+    );
+    assert!(report.regions >= 96, "{:?}", report);
+    assert!(report.edges > report.regions, "{:?}", report);
+}
+
+#[test]
+fn test_regions_graph_rebuilt_when_geojson_changes() {
+    let folder = PathBuf::from("tests/tmp_regions_graph");
+    if folder.exists() {
+        fs::remove_dir_all(&folder).unwrap();
+    }
+    fs::create_dir_all(&folder).unwrap();
+    let source = folder.join("regions.geojson");
+    let output = folder.join("regions_graph.json");
+
+    write_regions_geojson(&source, &[("01", 0.0, 0.0), ("02", 10000.0, 0.0)]);
+    let first = build_regions_graph(&source, &output, false).unwrap();
+    assert!(first.rebuilt);
+    assert_eq!((first.regions, first.edges), (2, 1));
+    assert_eq!(
+        first.source_hash,
+        regions_source_hash(&fs::read(&source).unwrap())
+    );
+
+    let cached = build_regions_graph(&source, &output, false).unwrap();
+    assert!(!cached.rebuilt, "An up-to-date graph should not be rebuilt");
+    assert_eq!(cached.source_hash, first.source_hash);
+    assert_eq!((cached.regions, cached.edges), (2, 1));
+
+    write_regions_geojson(
+        &source,
+        &[
+            ("01", 0.0, 0.0),
+            ("02", 10000.0, 0.0),
+            ("03", 0.0, 10000.0),
+            ("04", 50000.0, 0.0),
+        ],
+    );
+    let rebuilt = build_regions_graph(&source, &output, false).unwrap();
+    assert!(
+        rebuilt.rebuilt,
+        "A modified GeoJSON should trigger a rebuild"
+    );
+    assert_ne!(rebuilt.source_hash, first.source_hash);
+    // 01-02 et 01-03 sont côte à côte, 02-03 se touchent par un coin, 04 est isolé.
+    assert_eq!((rebuilt.regions, rebuilt.edges), (4, 3));
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(saved["source_hash"], rebuilt.source_hash.as_str());
+    assert_eq!(saved["regions"]["04"]["neighbors"], serde_json::json!([]));
+
+    let forced = build_regions_graph(&source, &output, true).unwrap();
+    assert!(forced.rebuilt);
+    assert_eq!(forced.source_hash, rebuilt.source_hash);
+
+    // Un graphe de l'ancien format, sans empreinte, est reconstruit.
+    fs::write(&output, "{}").unwrap();
+    assert!(
+        build_regions_graph(&source, &output, false)
+            .unwrap()
+            .rebuilt
+    );
+
+    fs::remove_dir_all(&folder).unwrap();
 }
 
 #[test]
@@ -47,6 +151,37 @@ fn test_find_multiple_intersecting_regions() {
     }
 }
 
+#[test]
+fn test_covered_regions() {
+    // Cozzano, à cheval sur la limite 2A/2B, et son extension vers l'ouest, en Corse-du-Sud
+    let source_bb = BoundingBox::new(1199000.0, 6105000.0, 1219000.0, 6120000.0);
+    let project_bb = BoundingBox::new(1189000.0, 6105000.0, 1219000.0, 6120000.0);
+    let regions = vec!["2A".to_string(), "2B".to_string()];
+
+    assert_eq!(
+        project_bb.difference(&source_bb),
+        vec![BoundingBox::new(1189000.0, 6105000.0, 1199000.0, 6120000.0)]
+    );
+    assert!(source_bb.difference(&project_bb).is_empty());
+    assert_eq!(
+        covered_regions(&source_bb, &project_bb, &regions).unwrap(),
+        vec!["2B".to_string()]
+    );
+    assert_eq!(
+        covered_regions(&project_bb, &source_bb, &regions).unwrap(),
+        regions
+    );
+
+    // Sans recouvrement, aucun département n'est couvert.
+    let elsewhere = BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6095000.0);
+    assert_eq!(project_bb.difference(&elsewhere), vec![project_bb]);
+    assert!(
+        covered_regions(&elsewhere, &project_bb, &regions)
+            .unwrap()
+            .is_empty()
+    );
+}
+
 #[test]
 fn test_no_intersecting_regions() {
     let bb = BoundingBox::new(0.0, 0.0, 1.0, 1.0);
@@ -54,3 +189,77 @@ fn test_no_intersecting_regions() {
 
     assert_eq!(result.len(), 0, "Should have no intersecting regions");
 }
+
+#[test]
+fn test_repeated_intersection_queries_do_not_reread_graph() {
+    with_regions_index(|_| ()).unwrap();
+    let reads = regions_graph_reads();
+
+    let bb = BoundingBox::new(1199000.0, 6104000.0, 1219000.0, 6120000.0);
+    for _ in 0..50 {
+        let result = find_intersecting_regions(&bb).unwrap();
+        assert!(result.len() >= 2);
+    }
+    get_region("2A").unwrap();
+    get_neighbors("2B").unwrap();
+
+    assert_eq!(
+        regions_graph_reads(),
+        reads,
+        "Queries should use the loaded index instead of reading the graph file"
+    );
+}
+
+#[test]
+fn test_regions_index_envelope_prefilter() {
+    let graph = load_regions_graph().unwrap();
+    let index = RegionsIndex::from_graph(graph);
+
+    let bb = get_test_bounding_box();
+    let codes: Vec<&str> = index
+        .find_intersecting(&bb)
+        .iter()
+        .map(|region| region.code.as_str())
+        .collect();
+    assert_eq!(codes, vec!["2A"]);
+
+    let far_away = BoundingBox::new(0.0, 0.0, 1.0, 1.0);
+    assert!(index.find_intersecting(&far_away).is_empty());
+    assert_eq!(index.neighbors("2A").unwrap()[0].code, "2B");
+    assert!(index.get("999").is_none());
+}
+
+#[test]
+fn test_region_directory_names() {
+    let directory = RegionDirectory::load().unwrap();
+
+    assert_eq!(
+        directory.names().get("2A").map(String::as_str),
+        Some("Corse-du-Sud")
+    );
+    assert_eq!(
+        directory.names().get("2B").map(String::as_str),
+        Some("Haute-Corse")
+    );
+    assert_eq!(directory.neighbors("2A"), Some(&["2B".to_string()][..]));
+    assert_eq!(directory.neighbors("999"), None);
+}
+
+#[test]
+fn test_region_directory_neighbors_are_symmetric() {
+    let directory = RegionDirectory::load().unwrap();
+
+    for code in directory.names().keys() {
+        for neighbor in directory.neighbors(code).unwrap() {
+            let reverse = directory
+                .neighbors(neighbor)
+                .unwrap_or_else(|| panic!("Neighbor {} of {} is unknown", neighbor, code));
+            assert!(
+                reverse.contains(code),
+                "{} lists {} as a neighbor but not the reverse",
+                code,
+                neighbor
+            );
+        }
+    }
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::gis_operation::report::{
+    Orientation, ViewMode, export_map_pdf, page_orientation, scale_bar_length,
+};
+use firefront_gis_lib::utils::{BoundingBox, output_location};
+use regex::bytes::Regex;
+use std::fs;
+
+#[test]
+fn test_export_map_pdf_embeds_project_image() {
+    let project_name = "porto-vecchio";
+
+    let result = export_map_pdf(project_name, ViewMode::Vegetation);
+    assert_result_ok(&result, "PDF export failed");
+
+    let pdf_path = result.unwrap();
+    assert_file_exists(&pdf_path.to_string_lossy(), "PDF not found");
+    assert_eq!(pdf_path.parent().unwrap(), output_location().as_path());
+
+    let content = fs::read(&pdf_path).unwrap();
+    assert!(!content.is_empty());
+    assert!(content.starts_with(b"%PDF"));
+
+    let page = Regex::new(r"/Type\s*/Page\b").unwrap();
+    assert_eq!(
+        page.find_iter(&content).count(),
+        1,
+        "Expected a single page"
+    );
+    let image = Regex::new(r"/Subtype\s*/Image\b").unwrap();
+    assert!(
+        image.is_match(&content),
+        "The project image should be embedded as an image XObject"
+    );
+
+    fs::remove_file(pdf_path).unwrap();
+}
+
+#[test]
+fn test_page_orientation_follows_aspect_ratio() {
+    let wide = BoundingBox::new(0.0, 0.0, 10000.0, 5000.0);
+    let tall = BoundingBox::new(0.0, 0.0, 5000.0, 10000.0);
+
+    assert_eq!(page_orientation(&wide), Orientation::Landscape);
+    assert_eq!(page_orientation(&tall), Orientation::Portrait);
+    assert_eq!(Orientation::Landscape.page_size_mm(), (297.0, 210.0));
+}
+
+#[test]
+fn test_scale_bar_length_is_rounded() {
+    assert_eq!(scale_bar_length(2500.0), 2000.0);
+    assert_eq!(scale_bar_length(1250.0), 1000.0);
+    assert_eq!(scale_bar_length(600.0), 500.0);
+    assert_eq!(scale_bar_length(5000.0), 5000.0);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use firefront_gis_lib::app_setup::{recheck_setup, setup_check};
+use firefront_gis_lib::dependency::{Dependency, find_command_path};
+use firefront_gis_lib::utils::create_directory_if_not_exists;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Le PATH du processus est modifié : ce test reste seul dans son binaire.
+#[cfg(unix)]
+#[test]
+fn test_missing_binary_is_reported_until_recheck() {
+    let original_path = std::env::var_os("PATH").unwrap();
+    // PATH réduit à des liens vers GDAL, Python et `which` : 7-Zip devient introuvable.
+    let fake_bin = Path::new("tmp/setup_fake_bin");
+    let _ = fs::remove_dir_all(fake_bin);
+    create_directory_if_not_exists(&fake_bin.to_string_lossy()).unwrap();
+    for command in ["gdalinfo", "python3", "which"] {
+        let target = find_command_path(command).expect("dependency missing from PATH");
+        std::os::unix::fs::symlink(target, fake_bin.join(command)).unwrap();
+    }
+    // SAFETY: seul test du binaire, aucun autre fil ne lit l'environnement.
+    unsafe { std::env::set_var("PATH", fs::canonicalize(fake_bin).unwrap()) };
+
+    let state = RwLock::new(setup_check());
+    let report = state.read().unwrap().clone();
+    assert!(!report.ready);
+    assert_eq!(report.missing(), vec!["7-Zip".to_string()]);
+    let status = |dependency| {
+        report
+            .dependencies
+            .iter()
+            .find(|status| status.dependency == dependency)
+            .unwrap()
+            .clone()
+    };
+    assert!(status(Dependency::Gdal).found);
+    assert!(status(Dependency::Gdal).install_hint.is_none());
+    let seven_zip = status(Dependency::SevenZip);
+    assert!(!seven_zip.found);
+    assert!(seven_zip.path.is_none());
+    assert_eq!(
+        seven_zip.install_hint.as_deref(),
+        Some(Dependency::SevenZip.install_hint())
+    );
+    let refused = report.ensure_ready().unwrap_err();
+    assert!(refused.contains("7-Zip"), "Unexpected error: {}", refused);
+
+    // SAFETY: voir plus haut.
+    unsafe { std::env::set_var("PATH", &original_path) };
+    let rechecked = recheck_setup(&state);
+    assert!(rechecked.ready, "Recheck failed: {:?}", rechecked.missing());
+    assert!(rechecked.ensure_ready().is_ok());
+    assert_eq!(*state.read().unwrap(), rechecked);
+
+    fs::remove_dir_all(fake_bin).unwrap();
+}
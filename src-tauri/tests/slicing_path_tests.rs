@@ -0,0 +1,45 @@
+use firefront_gis_lib::{
+    app_setup::RunConfig,
+    gis_operation::slicing::{SLICE_INDEX_FILE, SLICE_STATE_FILE, slice_images},
+    utils::projects_dir,
+};
+
+/// Le PATH du processus est modifié : ce test reste seul dans son binaire.
+#[test]
+fn test_slice_images_without_imagemagick() {
+    let project_name = "porto-vecchio";
+    let slice_dir = projects_dir().join(project_name).join("slices");
+    let _ = std::fs::remove_dir_all(&slice_dir);
+    let empty_bin_dir = std::env::temp_dir().join("firefront_empty_path");
+    std::fs::create_dir_all(&empty_bin_dir).unwrap();
+    let config = RunConfig {
+        slice_factor: 500,
+        ..RunConfig::snapshot()
+    };
+
+    let original_path = std::env::var_os("PATH");
+    // SAFETY: seul test du binaire, aucun autre fil ne lit l'environnement.
+    unsafe { std::env::set_var("PATH", &empty_bin_dir) };
+    let result = slice_images(project_name, &config);
+    if let Some(path) = original_path {
+        unsafe { std::env::set_var("PATH", path) };
+    }
+    result.expect("Slicing should not require ImageMagick");
+
+    let mut slice_count = 0;
+    for entry in std::fs::read_dir(&slice_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.ends_with(SLICE_STATE_FILE) || path.ends_with(SLICE_INDEX_FILE) {
+            continue;
+        }
+        let (width, height) = image::image_dimensions(&path).unwrap();
+        assert_eq!(
+            (width, height),
+            (500, 500),
+            "Unexpected slice size for {:?}",
+            path
+        );
+        slice_count += 1;
+    }
+    assert!(slice_count > 0, "No slices were produced");
+}
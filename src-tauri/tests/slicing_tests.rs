@@ -1,9 +1,53 @@
 mod common;
 
 use firefront_gis_lib::{
-    gis_operation::slicing::slice_images,
-    utils::{get_project_bounding_box, projects_dir},
+    app_setup::{CONFIG, Config, RunConfig},
+    gis_operation::colormap,
+    gis_operation::slicing::{
+        DEFAULT_SLICE_NAME_TEMPLATE, OVERVIEW_LINE_WIDTH, OVERVIEW_MAX_SIZE, PixelWindow,
+        SLICE_INDEX_FILE, SLICE_STATE_FILE, SliceIndex, SliceIndexEntry, SliceKind, TileSource,
+        invalidate_ortho_slices, overview_file_name, process_slice, read_slice_index,
+        render_overview, slice_coords, slice_file_name, slice_images,
+    },
+    utils::{
+        BoundingBox, ExportOptions, WorkDir, export_inventory, get_project_bounding_box,
+        project_veget_jpeg, projects_dir,
+    },
 };
+use gdal::raster::Buffer;
+use gdal::{Dataset, DatasetOptions, GdalOpenFlags};
+use image::{DynamicImage, Rgb, RgbImage};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Les tests de découpage partagent le dossier `slices/` du projet de test.
+static SLICES_LOCK: Mutex<()> = Mutex::new(());
+
+/// Paramètres actuels, avec la taille de tuiles donnée.
+fn slice_config(slice_factor: u32) -> RunConfig {
+    RunConfig {
+        slice_factor,
+        ..RunConfig::snapshot()
+    }
+}
+
+fn slice_dir(project_name: &str) -> PathBuf {
+    projects_dir().join(project_name).join("slices")
+}
+
+fn slice_mtimes(project_name: &str) -> HashMap<PathBuf, SystemTime> {
+    std::fs::read_dir(slice_dir(project_name))
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+            (path, modified)
+        })
+        .collect()
+}
 
 #[test]
 fn test_project_bounding_box() {
@@ -19,8 +63,9 @@ fn test_project_bounding_box() {
 
 #[test]
 fn test_slice_images() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let project_name = "porto-vecchio";
-    slice_images(project_name, 500).unwrap();
+    slice_images(project_name, &slice_config(500)).unwrap();
     assert!(
         std::path::Path::new(&format!(
             "{}/{}/slices",
@@ -30,3 +75,655 @@ fn test_slice_images() {
         .exists()
     );
 }
+
+#[test]
+fn test_incremental_slicing() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let project_name = "porto-vecchio";
+
+    // Premier export : le dossier n'existe pas encore.
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+    slice_images(project_name, &slice_config(500)).expect("First slicing failed");
+    assert!(slice_dir(project_name).join(SLICE_STATE_FILE).exists());
+    let first_mtimes = slice_mtimes(project_name);
+    assert!(first_mtimes.len() > 1, "No slices were produced");
+
+    // Réexport sans modification : aucune découpe n'est régénérée.
+    slice_images(project_name, &slice_config(500)).expect("Unchanged re-slicing failed");
+    assert_eq!(slice_mtimes(project_name), first_mtimes);
+
+    // Changement de facteur : les découpes sont régénérées avec le nouveau facteur.
+    slice_images(project_name, &slice_config(250)).expect("Re-slicing with a new factor failed");
+    let names: Vec<String> = slice_mtimes(project_name)
+        .keys()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .filter(|name| name != SLICE_STATE_FILE && name != SLICE_INDEX_FILE)
+        .collect();
+    assert!(!names.is_empty());
+    assert!(names.iter().all(|name| name.ends_with("_250.jpg")));
+
+    slice_images(project_name, &slice_config(500)).unwrap();
+}
+
+/// Découpe de 40 x 40 pixels en bandes verticales de couleurs de classes.
+fn class_stripes() -> DynamicImage {
+    let colors = [
+        colormap::FEUILLUS,
+        colormap::HYDRO,
+        colormap::TOPO,
+        colormap::OTHER_VEGETATION,
+    ];
+    DynamicImage::ImageRgb8(RgbImage::from_fn(40, 40, |x, _| {
+        Rgb(colors[(x / 10) as usize])
+    }))
+}
+
+#[test]
+fn test_veget_slices_keep_class_colors() {
+    let slice = class_stripes();
+    for enhance_ortho in [false, true] {
+        let processed = process_slice(&slice, SliceKind::Veget, enhance_ortho);
+        assert_eq!(processed.to_rgb8(), slice.to_rgb8());
+    }
+
+    assert_eq!(
+        process_slice(&slice, SliceKind::Ortho, false).to_rgb8(),
+        slice.to_rgb8()
+    );
+    // Le débruitage mélange les couleurs de part et d'autre des limites de bandes.
+    let enhanced = process_slice(&slice, SliceKind::Ortho, true).to_rgb8();
+    assert_ne!(enhanced, slice.to_rgb8());
+    assert_ne!(enhanced.get_pixel(10, 20).0, colormap::HYDRO);
+}
+
+#[test]
+fn test_enhance_flag_redoes_ortho_slices_only() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let project_name = "porto-vecchio";
+    let previous = CONFIG.lock().unwrap().enhance_ortho_slices;
+    assert!(!Config::default().enhance_ortho_slices);
+
+    CONFIG.lock().unwrap().enhance_ortho_slices = false;
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+    slice_images(project_name, &slice_config(500)).expect("First slicing failed");
+    let first_mtimes = slice_mtimes(project_name);
+
+    CONFIG.lock().unwrap().enhance_ortho_slices = true;
+    let result = slice_images(project_name, &slice_config(500));
+    CONFIG.lock().unwrap().enhance_ortho_slices = previous;
+    result.expect("Enhanced re-slicing failed");
+
+    let second_mtimes = slice_mtimes(project_name);
+    for (path, modified) in &first_mtimes {
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name.contains("_veget_") {
+            assert_eq!(second_mtimes[path], *modified, "{} was redone", name);
+        } else if name.contains("_ortho_") {
+            assert!(second_mtimes[path] > *modified, "{} was not redone", name);
+        }
+    }
+    let state: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(slice_dir(project_name).join(SLICE_STATE_FILE)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(state["enhance"], true);
+
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+}
+
+#[test]
+fn test_slice_file_name_template() {
+    assert_eq!(
+        slice_file_name(
+            DEFAULT_SLICE_NAME_TEMPLATE,
+            1210,
+            6070,
+            SliceKind::Veget,
+            500
+        ),
+        Ok("01210_06070_veget_500.jpg".to_string())
+    );
+    assert_eq!(
+        slice_file_name(
+            DEFAULT_SLICE_NAME_TEMPLATE,
+            1210,
+            6070,
+            SliceKind::Ortho,
+            250
+        ),
+        Ok("01210_06070_ortho_250.jpg".to_string())
+    );
+    assert_eq!(
+        slice_file_name("{kind}/{x}-{y}.png", 1210, 6070, SliceKind::Ortho, 500),
+        Ok("ortho/1210-6070.png".to_string())
+    );
+    // Sans {kind}, les tuiles ORTHO et VEGET porteraient le même nom.
+    assert!(slice_file_name("{x}_{y}_{factor}.jpg", 1210, 6070, SliceKind::Ortho, 500).is_err());
+    assert!(
+        slice_file_name(
+            "{x}_{y}_{kind}_{zoom}.jpg",
+            1210,
+            6070,
+            SliceKind::Ortho,
+            500
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_slice_index_matches_tiles() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let project_name = "porto-vecchio";
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+    slice_images(project_name, &slice_config(500)).unwrap();
+
+    let bb = get_project_bounding_box(project_name).unwrap();
+    let index = read_slice_index(project_name).expect("Missing slices/index.json");
+    assert_eq!(index.slice_factor, 500);
+    assert_eq!(index.name_template, DEFAULT_SLICE_NAME_TEMPLATE);
+    let width = (bb.width() / index.resolution).round() as u32;
+    let height = (bb.height() / index.resolution).round() as u32;
+    assert_eq!(
+        index.tiles.len() as u32,
+        width.div_ceil(500) * height.div_ceil(500)
+    );
+
+    let mut files: Vec<String> = Vec::new();
+    for tile in &index.tiles {
+        let tile_size_m = 500.0 * index.resolution;
+        let xmin = bb.xmin + tile.window.x as f64 * index.resolution;
+        let ymax = bb.ymax - tile.window.y as f64 * index.resolution;
+        assert_eq!(
+            tile.extent,
+            BoundingBox::new(xmin, ymax - tile_size_m, xmin + tile_size_m, ymax)
+        );
+        assert_eq!((tile.window.width, tile.window.height), (500, 500));
+        assert_eq!(
+            slice_coords(
+                tile.window.x,
+                tile.window.y,
+                width,
+                height,
+                500,
+                index.resolution,
+                &bb
+            ),
+            Ok((tile.x, tile.y))
+        );
+        assert_eq!((tile.extent.xmin / 1000.0) as u32, tile.x);
+        assert_eq!((tile.extent.ymin / 1000.0) as u32, tile.y);
+
+        // Les tuiles ORTHO et VEGET partagent les mêmes clés x/y.
+        let key = |name: &str| name.split('_').take(2).collect::<Vec<&str>>().join("_");
+        assert_eq!(key(&tile.ortho), key(&tile.veget));
+        assert_eq!(key(&tile.ortho), format!("{:05}_{:05}", tile.x, tile.y));
+        assert!(tile.veget.contains("_veget_") && tile.ortho.contains("_ortho_"));
+        files.push(tile.ortho.clone());
+        files.push(tile.veget.clone());
+    }
+
+    let mut sliced: Vec<String> = std::fs::read_dir(slice_dir(project_name))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".jpg"))
+        .collect();
+    sliced.sort();
+    files.sort();
+    assert_eq!(sliced, files);
+}
+
+#[test]
+fn test_slicing_replaces_tiles_with_legacy_names() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let project_name = "porto-vecchio";
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+    slice_images(project_name, &slice_config(500)).unwrap();
+
+    // État d'un découpage antérieur au modèle de nom, avec ses anciens noms de tuiles.
+    let state_path = slice_dir(project_name).join(SLICE_STATE_FILE);
+    let mut state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&state_path).unwrap()).unwrap();
+    state.as_object_mut().unwrap().remove("name_template");
+    std::fs::write(&state_path, state.to_string()).unwrap();
+    std::fs::remove_file(slice_dir(project_name).join(SLICE_INDEX_FILE)).unwrap();
+    let legacy_tile = slice_dir(project_name).join("1210_6070_500.jpg");
+    std::fs::write(&legacy_tile, b"legacy").unwrap();
+
+    slice_images(project_name, &slice_config(500)).unwrap();
+
+    assert!(!legacy_tile.exists(), "Legacy tile was not replaced");
+    assert!(read_slice_index(project_name).is_some());
+    assert!(
+        slice_dir(project_name)
+            .join("01210_06070_ortho_500.jpg")
+            .exists()
+    );
+}
+
+fn porto_vecchio_bb() -> BoundingBox {
+    BoundingBox::new(1210000.0, 6070000.0, 1235000.0, 6095000.0)
+}
+
+#[test]
+fn test_slice_coords_porto_vecchio_10m() {
+    let bb = porto_vecchio_bb();
+
+    assert_eq!(
+        slice_coords(0, 0, 2500, 2500, 500, 10.0, &bb),
+        Ok((1210, 6090))
+    );
+    assert_eq!(
+        slice_coords(500, 2000, 2500, 2500, 500, 10.0, &bb),
+        Ok((1215, 6070))
+    );
+    assert_eq!(
+        slice_coords(2000, 2000, 2500, 2500, 500, 10.0, &bb),
+        Ok((1230, 6070))
+    );
+
+    assert_eq!(
+        slice_coords(0, 2250, 2500, 2500, 250, 10.0, &bb),
+        Ok((1210, 6070))
+    );
+    assert_eq!(
+        slice_coords(250, 0, 2500, 2500, 250, 10.0, &bb),
+        Ok((1212, 6092))
+    );
+    assert_eq!(
+        slice_coords(2250, 1000, 2500, 2500, 250, 10.0, &bb),
+        Ok((1232, 6082))
+    );
+}
+
+#[test]
+fn test_slice_coords_porto_vecchio_5m() {
+    let bb = porto_vecchio_bb();
+
+    assert_eq!(
+        slice_coords(0, 0, 5000, 5000, 500, 5.0, &bb),
+        Ok((1210, 6092))
+    );
+    assert_eq!(
+        slice_coords(1000, 4500, 5000, 5000, 500, 5.0, &bb),
+        Ok((1215, 6070))
+    );
+    assert_eq!(
+        slice_coords(4500, 4500, 5000, 5000, 500, 5.0, &bb),
+        Ok((1232, 6070))
+    );
+    assert_eq!(
+        slice_coords(4750, 4750, 5000, 5000, 250, 5.0, &bb),
+        Ok((1233, 6070))
+    );
+}
+
+#[test]
+fn test_slice_coords_are_unique_on_the_grid() {
+    let bb = porto_vecchio_bb();
+
+    for (resolution, slice_factor) in [(10.0, 500), (10.0, 250), (5.0, 500), (5.0, 250)] {
+        let size = (bb.width() / resolution) as u32;
+        let mut names = std::collections::HashSet::new();
+        for img_y in (0..size).step_by(slice_factor as usize) {
+            for img_x in (0..size).step_by(slice_factor as usize) {
+                let coords =
+                    slice_coords(img_x, img_y, size, size, slice_factor, resolution, &bb).unwrap();
+                assert!(
+                    names.insert(coords),
+                    "Duplicate tile name {:?} at {}m / {}px",
+                    coords,
+                    resolution,
+                    slice_factor
+                );
+            }
+        }
+        let per_side = size.div_ceil(slice_factor);
+        assert_eq!(names.len() as u32, per_side * per_side);
+    }
+}
+
+#[test]
+fn test_slice_coords_landscape_extent() {
+    let bb = BoundingBox::new(1210000.0, 6070000.0, 1240000.0, 6085000.0);
+
+    assert_eq!(
+        slice_coords(0, 0, 3000, 1500, 500, 10.0, &bb),
+        Ok((1210, 6080))
+    );
+    assert_eq!(
+        slice_coords(2500, 1000, 3000, 1500, 500, 10.0, &bb),
+        Ok((1235, 6070))
+    );
+    assert_eq!(
+        slice_coords(5750, 2750, 6000, 3000, 250, 5.0, &bb),
+        Ok((1238, 6070))
+    );
+    assert!(slice_coords(3000, 0, 3000, 1500, 500, 10.0, &bb).is_err());
+    assert!(slice_coords(0, 1500, 3000, 1500, 500, 10.0, &bb).is_err());
+}
+
+#[test]
+fn test_slice_coords_non_divisible_extent_keeps_edge_tiles() {
+    let bb = BoundingBox::new(1210000.0, 6070000.0, 1235500.0, 6095500.0);
+
+    // La dernière ligne (2500..2550) n'est plus ignorée : la tuile est complétée au sud.
+    assert_eq!(
+        slice_coords(0, 2500, 2550, 2550, 500, 10.0, &bb),
+        Ok((1210, 6065))
+    );
+    assert_eq!(
+        slice_coords(2500, 0, 2550, 2550, 500, 10.0, &bb),
+        Ok((1235, 6090))
+    );
+}
+
+#[test]
+fn test_slice_coords_rejects_invalid_tiles() {
+    let bb = porto_vecchio_bb();
+
+    assert!(slice_coords(100, 0, 2500, 2500, 500, 10.0, &bb).is_err());
+    assert!(slice_coords(0, 0, 2500, 2500, 0, 10.0, &bb).is_err());
+    assert!(slice_coords(0, 0, 2500, 2500, 500, 0.0, &bb).is_err());
+    // Des tuiles de 500 m porteraient le même nom sur la grille kilométrique.
+    assert!(slice_coords(0, 0, 5000, 5000, 100, 5.0, &bb).is_err());
+}
+
+#[test]
+fn test_invalidated_ortho_slices_are_redone_alone() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let project_name = "porto-vecchio";
+
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+    slice_images(project_name, &slice_config(500)).expect("First slicing failed");
+    let first_mtimes = slice_mtimes(project_name);
+
+    invalidate_ortho_slices(project_name).unwrap();
+    slice_images(project_name, &slice_config(500)).expect("Ortho re-slicing failed");
+    let second_mtimes = slice_mtimes(project_name);
+
+    assert_eq!(first_mtimes.len(), second_mtimes.len());
+    for (path, modified) in &first_mtimes {
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name == SLICE_STATE_FILE {
+            continue;
+        }
+        if name.contains("_veget_") {
+            assert_eq!(second_mtimes[path], *modified, "{} was redone", name);
+        } else {
+            assert!(second_mtimes[path] > *modified, "{} was not redone", name);
+        }
+    }
+
+    // L'état est à jour : un nouveau découpage ne refait rien.
+    slice_images(project_name, &slice_config(500)).unwrap();
+    assert_eq!(slice_mtimes(project_name), second_mtimes);
+}
+
+/// Crée un GeoTIFF creux (quelques Ko sur le disque) de grandes dimensions.
+fn create_sparse_geotiff(path: &Path, size: usize, bands: usize) {
+    let _ = std::fs::remove_file(path);
+    let status = Command::new("gdal_create")
+        .args(["-of", "GTiff", "-ot", "Byte", "-outsize"])
+        .args([size.to_string(), size.to_string()])
+        .args(["-bands", &bands.to_string()])
+        .args(["-co", "SPARSE_OK=TRUE", "-co", "TILED=YES"])
+        .arg(path)
+        .status()
+        .unwrap();
+    assert!(status.success(), "gdal_create failed");
+}
+
+#[test]
+fn test_windowed_tiles_match_decoded_tiles() {
+    let veget = project_veget_jpeg("porto-vecchio");
+    let decoded = TileSource::open(&veget, "VEGET", u64::MAX).unwrap();
+    let windowed = TileSource::open(&veget, "VEGET", 0).unwrap();
+    assert!(matches!(decoded, TileSource::Decoded(_)));
+    assert!(matches!(windowed, TileSource::Windowed(_)));
+    assert_eq!(decoded.dimensions(), windowed.dimensions());
+
+    // 300 ne divise pas la largeur de l'image : la dernière tuile est complétée.
+    let (width, height) = decoded.dimensions();
+    let last = |size: u32| (size - 1) / 300 * 300;
+    for (img_x, img_y) in [(0, 0), (900, 1200), (last(width), last(height))] {
+        let expected = decoded.crop_tile(img_x, img_y, 300).unwrap().to_rgb8();
+        let actual = windowed.crop_tile(img_x, img_y, 300).unwrap().to_rgb8();
+        assert_eq!(expected.dimensions(), actual.dimensions());
+
+        // Les décodeurs JPEG de GDAL et de la crate `image` peuvent différer d'une unité.
+        let total_diff: u64 = expected
+            .as_raw()
+            .iter()
+            .zip(actual.as_raw())
+            .map(|(a, b)| a.abs_diff(*b) as u64)
+            .sum();
+        let mean_diff = total_diff as f64 / expected.as_raw().len() as f64;
+        assert!(
+            mean_diff < 2.0,
+            "Tile ({}, {}) differs by {} on average",
+            img_x,
+            img_y,
+            mean_diff
+        );
+    }
+}
+
+#[test]
+fn test_large_image_is_sliced_by_windows() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_windowed_slicing").unwrap();
+    let path = work_dir.path_for("large.tif");
+    create_sparse_geotiff(&path, 30000, 3);
+    let raster = Dataset::open_ex(
+        &path,
+        DatasetOptions {
+            open_flags: GdalOpenFlags::GDAL_OF_UPDATE | GdalOpenFlags::GDAL_OF_RASTER,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for (band_idx, value) in [(1, 200u8), (2, 100), (3, 50)] {
+        raster
+            .rasterband(band_idx)
+            .unwrap()
+            .write(
+                (20010, 10020),
+                (10, 10),
+                &mut Buffer::new((10, 10), vec![value; 100]),
+            )
+            .unwrap();
+    }
+    raster.close().unwrap();
+
+    // 30000 x 30000 px en RGB : près de 2,6 Go une fois décodée.
+    let source = TileSource::open(&path, "ORTHO", 2048).unwrap();
+    assert!(matches!(source, TileSource::Windowed(_)));
+    assert_eq!(source.dimensions(), (30000, 30000));
+
+    let tile = source.crop_tile(20000, 10000, 500).unwrap().to_rgb8();
+    assert_eq!(tile.dimensions(), (500, 500));
+    assert_eq!(tile.get_pixel(15, 25).0, [200, 100, 50]);
+    assert_eq!(tile.get_pixel(0, 0).0, [0, 0, 0]);
+
+    let edge = source.crop_tile(29800, 29800, 500).unwrap().to_rgb8();
+    assert_eq!(edge.dimensions(), (500, 500));
+}
+
+#[test]
+fn test_unreadable_large_image_reports_size_and_limit() {
+    let work_dir = WorkDir::new(&RunConfig::snapshot(), "test_windowed_slicing_error").unwrap();
+    let path = work_dir.path_for("grey.tif");
+    create_sparse_geotiff(&path, 30000, 1);
+
+    let error = match TileSource::open(&path, "VEGET", 2048) {
+        Ok(_) => panic!("A single band image cannot be sliced"),
+        Err(e) => e,
+    };
+    assert!(error.contains("30000x30000"), "{}", error);
+    assert!(error.contains("2048 MB"), "{}", error);
+}
+
+/// Part des pixels d'une ligne de l'aperçu proches du blanc de la grille.
+fn grid_row_ratio(overview: &RgbImage, y: u32) -> f64 {
+    let bright = (0..overview.width())
+        .filter(|&x| {
+            overview
+                .get_pixel(x, y)
+                .0
+                .iter()
+                .all(|&channel| channel >= 200)
+        })
+        .count();
+    bright as f64 / overview.width() as f64
+}
+
+/// Position de la ligne de la grille tracée à `position` pixels, dans une image de `size` pixels.
+fn grid_line(position: u32, size: u32) -> u32 {
+    position.min(size - OVERVIEW_LINE_WIDTH)
+}
+
+#[test]
+fn test_slice_overview_is_written_and_exported() {
+    let _lock = SLICES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let project_name = "porto-vecchio";
+    let previous = CONFIG.lock().unwrap().slice_overview;
+    assert!(!Config::default().slice_overview);
+
+    CONFIG.lock().unwrap().slice_overview = true;
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+    let result = slice_images(project_name, &slice_config(500));
+    CONFIG.lock().unwrap().slice_overview = previous;
+    result.expect("Slicing with overviews failed");
+
+    let index = read_slice_index(project_name).unwrap();
+    let bb = get_project_bounding_box(project_name).unwrap();
+    let width = (bb.width() / index.resolution).round() as u32;
+    let height = (bb.height() / index.resolution).round() as u32;
+    let (grid_width, grid_height) = (width.div_ceil(500) * 500, height.div_ceil(500) * 500);
+    let scale = (OVERVIEW_MAX_SIZE as f64 / grid_width.max(grid_height) as f64).min(1.0);
+    let scaled = |pixels: u32| (pixels as f64 * scale).round() as u32;
+
+    for kind in [SliceKind::Veget, SliceKind::Ortho] {
+        let path = slice_dir(project_name).join(overview_file_name(kind));
+        let overview = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(
+            overview.dimensions(),
+            (scaled(grid_width), scaled(grid_height))
+        );
+        assert!(overview.width().max(overview.height()) <= OVERVIEW_MAX_SIZE);
+
+        // Une ligne de la grille toutes les 500 pixels de l'image découpée.
+        for row in (0..=grid_height).step_by(500) {
+            let y = grid_line(scaled(row), overview.height());
+            assert!(
+                grid_row_ratio(&overview, y) > 0.9,
+                "No grid line at row {} of {:?}",
+                y,
+                path
+            );
+        }
+    }
+
+    // Les aperçus sont exportés avec les découpes.
+    let inventory =
+        export_inventory(project_name, &ExportOptions::default(), &slice_config(500)).unwrap();
+    for kind in [SliceKind::Veget, SliceKind::Ortho] {
+        let overview = Path::new("slices").join(overview_file_name(kind));
+        assert!(
+            inventory.files.contains(&overview),
+            "{:?} not exported",
+            overview
+        );
+    }
+
+    // Sans l'option, les aperçus sont supprimés au découpage suivant, sans refaire les tuiles.
+    let overview_path = slice_dir(project_name).join(overview_file_name(SliceKind::Ortho));
+    let mut before = slice_mtimes(project_name);
+    let overviews = [SliceKind::Veget, SliceKind::Ortho]
+        .map(|kind| slice_dir(project_name).join(overview_file_name(kind)));
+    before.retain(|path, _| {
+        path.extension().is_some_and(|ext| ext == "jpg") && !overviews.contains(path)
+    });
+    slice_images(project_name, &slice_config(500)).unwrap();
+    assert!(!overview_path.exists());
+    let after = slice_mtimes(project_name);
+    for (path, modified) in &before {
+        assert_eq!(after.get(path), Some(modified), "{:?} was re-sliced", path);
+    }
+
+    // Le réactiver écrit les aperçus d'après l'index existant.
+    CONFIG.lock().unwrap().slice_overview = true;
+    let result = slice_images(project_name, &slice_config(500));
+    CONFIG.lock().unwrap().slice_overview = previous;
+    result.expect("Enabling overviews failed");
+    assert!(overview_path.exists());
+    let after = slice_mtimes(project_name);
+    for (path, modified) in &before {
+        assert_eq!(after.get(path), Some(modified), "{:?} was re-sliced", path);
+    }
+    let _ = std::fs::remove_dir_all(slice_dir(project_name));
+}
+
+/// Index d'un découpage de `width` x `height` pixels, avec des coordonnées fictives.
+fn grid_index(width: u32, height: u32, slice_factor: u32) -> SliceIndex {
+    let mut tiles = Vec::new();
+    for (row, img_y) in (0..height).step_by(slice_factor as usize).enumerate() {
+        for (column, img_x) in (0..width).step_by(slice_factor as usize).enumerate() {
+            tiles.push(SliceIndexEntry {
+                x: 1210 + column as u32,
+                y: 6095 - row as u32,
+                window: PixelWindow {
+                    x: img_x,
+                    y: img_y,
+                    width: slice_factor,
+                    height: slice_factor,
+                },
+                extent: BoundingBox::new(0.0, 0.0, 1.0, 1.0),
+                ortho: String::new(),
+                veget: String::new(),
+            });
+        }
+    }
+    SliceIndex {
+        slice_factor,
+        resolution: 10.0,
+        name_template: DEFAULT_SLICE_NAME_TEMPLATE.to_string(),
+        tiles,
+    }
+}
+
+#[test]
+fn test_overview_of_non_square_image_with_edge_tiles() {
+    // 1300 x 700 pixels : trois colonnes et deux lignes de tuiles, celles du bord
+    // est et du bord sud dépassent l'image.
+    let grey = RgbImage::from_pixel(1300, 700, Rgb([100, 100, 100]));
+    let source = TileSource::Decoded(DynamicImage::ImageRgb8(grey));
+    let overview = render_overview(&source, SliceKind::Ortho, &grid_index(1300, 700, 500)).unwrap();
+
+    assert_eq!(overview.dimensions(), (1500, 1000));
+    for row in [0, 500, 1000] {
+        assert_eq!(grid_row_ratio(&overview, grid_line(row, 1000)), 1.0);
+    }
+    for column in [0, 500, 1000, 1500] {
+        let x = grid_line(column, 1500);
+        assert!((0..1000).all(|y| overview.get_pixel(x, y).0 == [255, 255, 255]));
+    }
+    // L'image est reproduite, et la partie des tuiles de bord hors de l'image est noire.
+    assert_eq!(overview.get_pixel(250, 250).0, [100, 100, 100]);
+    assert_eq!(overview.get_pixel(1400, 250).0, [0, 0, 0]);
+    assert_eq!(overview.get_pixel(250, 800).0, [0, 0, 0]);
+    // Chaque tuile porte son nom, sur un fond noir dans son coin supérieur gauche.
+    for (x, y) in [(0, 0), (1000, 0), (0, 500), (1000, 500)] {
+        let corner = overview.get_pixel(
+            x + 2 * OVERVIEW_LINE_WIDTH + 1,
+            y + 2 * OVERVIEW_LINE_WIDTH + 1,
+        );
+        assert_eq!(corner.0, [0, 0, 0]);
+    }
+
+    // Au-delà de OVERVIEW_MAX_SIZE, la grille est réduite en gardant ses proportions.
+    let large = TileSource::Decoded(DynamicImage::new_rgb8(5000, 2600));
+    let overview = render_overview(&large, SliceKind::Veget, &grid_index(5000, 2600, 500)).unwrap();
+    assert_eq!(overview.dimensions(), (2000, 1200));
+    assert_eq!(grid_row_ratio(&overview, 200), 1.0);
+}
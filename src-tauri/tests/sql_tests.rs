@@ -0,0 +1,95 @@
+use firefront_gis_lib::gis_operation::colormap::{RpgClass, RpgClassTable};
+use firefront_gis_lib::gis_operation::layers::EssenceClassification;
+use firefront_gis_lib::gis_operation::sql::{
+    IN_CLAUSE_CHUNK, build_in_clause, build_not_in_clause, sql_literal,
+};
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[test]
+fn test_sql_literal_escapes_quotes_and_rejects_control_characters() {
+    assert_eq!(sql_literal("Hêtre").unwrap(), "'Hêtre'");
+    assert_eq!(
+        sql_literal("Chêne d'Amérique").unwrap(),
+        "'Chêne d''Amérique'"
+    );
+    assert_eq!(
+        sql_literal("'); DROP TABLE x; --").unwrap(),
+        "'''); DROP TABLE x; --'"
+    );
+    assert!(sql_literal("Pin\nmaritime").is_err());
+    assert!(sql_literal("Pin\0").is_err());
+}
+
+#[test]
+fn test_in_clause_with_quotes_and_empty_lists() {
+    assert_eq!(
+        build_in_clause("ESSENCE", &strings(&["Chêne d'Amérique", "Hêtre"])).unwrap(),
+        "ESSENCE IN ('Chêne d''Amérique', 'Hêtre')"
+    );
+    // Une liste vide ne sélectionne rien, sans produire de SQL invalide.
+    assert_eq!(build_in_clause("ESSENCE", &[]).unwrap(), "0 = 1");
+    assert_eq!(build_not_in_clause("ESSENCE", &[]).unwrap(), "1 = 1");
+    assert!(build_in_clause("ESSENCE", &strings(&["ok", "tab\tulation"])).is_err());
+}
+
+#[test]
+fn test_long_in_clause_is_chunked() {
+    let values: Vec<String> = (0..IN_CLAUSE_CHUNK * 2 + 1)
+        .map(|index| format!("code {}", index))
+        .collect();
+
+    let clause = build_in_clause("CODE", &values).unwrap();
+    assert!(clause.starts_with("(CODE IN ('code 0', "));
+    assert_eq!(clause.matches("CODE IN (").count(), 3);
+    assert_eq!(clause.matches(" OR ").count(), 2);
+    assert!(clause.ends_with("'code 1000'))"));
+
+    let clause = build_not_in_clause("CODE", &values).unwrap();
+    assert_eq!(clause.matches("CODE NOT IN (").count(), 3);
+    assert_eq!(clause.matches(" AND ").count(), 2);
+}
+
+#[test]
+fn test_vegetation_and_rpg_filters_escape_values() {
+    let classification = EssenceClassification {
+        attribute: "ESSENCE".to_string(),
+        feuillus: strings(&["Chêne d'Amérique"]),
+        undefined: strings(&["NR"]),
+        ..EssenceClassification::default()
+    };
+    assert_eq!(
+        classification.feuillus_where().unwrap(),
+        "ESSENCE IN ('Chêne d''Amérique')"
+    );
+    assert_eq!(
+        classification.other_where().unwrap(),
+        "ESSENCE NOT IN ('Chêne d''Amérique', 'NR')"
+    );
+
+    let table = RpgClassTable {
+        attribute: "CODE_CULTU".to_string(),
+        classes: vec![RpgClass {
+            key: "orchards".to_string(),
+            label: "Vergers".to_string(),
+            label_en: None,
+            color: [10, 20, 30],
+            codes: strings(&["VRG", "PRU d'ente"]),
+        }],
+    };
+    assert!(table.validate().is_ok());
+    assert_eq!(
+        table.where_clause(&table.classes[0]).unwrap(),
+        "CODE_CULTU IN ('VRG', 'PRU d''ente')"
+    );
+    assert_eq!(
+        table.unclassified_where().unwrap(),
+        "CODE_CULTU IS NULL OR CODE_CULTU NOT IN ('VRG', 'PRU d''ente')"
+    );
+
+    let mut control = table.clone();
+    control.classes[0].codes.push("VRG\r".to_string());
+    assert!(control.validate().is_err());
+}
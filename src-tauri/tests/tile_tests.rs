@@ -0,0 +1,70 @@
+mod common;
+
+use common::*;
+
+use firefront_gis_lib::gis_operation::report::ViewMode;
+use firefront_gis_lib::gis_operation::tiles::{
+    TILE_SIZE, TILES_DIR, TileGrid, build_project_overviews, get_project_tile, project_tile_grid,
+};
+use firefront_gis_lib::utils::{copy_project, project_dir, project_tiff};
+use gdal::Dataset;
+use std::fs;
+
+#[test]
+fn test_tile_grid_levels() {
+    let grid = TileGrid::new(2500, 2000);
+    assert_eq!(grid.max_zoom, 4);
+    assert_eq!(grid.scale(4), 1);
+    assert_eq!(grid.scale(0), 16);
+    assert_eq!(grid.tile_count(0), (1, 1));
+    assert_eq!(grid.tile_count(4), (10, 8));
+    assert_eq!(TileGrid::new(200, 100).max_zoom, 0);
+}
+
+#[test]
+fn test_project_tiles_use_cache() {
+    let name = "porto-vecchio-tiles";
+    let _ = fs::remove_dir_all(project_dir(name));
+    copy_project("porto-vecchio", name).unwrap();
+
+    let tiff = project_tiff(name);
+    assert_result_ok(&build_project_overviews(&tiff), "Building overviews failed");
+    let project = Dataset::open(&tiff).unwrap();
+    assert!(project.rasterband(1).unwrap().overview_count().unwrap() > 0);
+    let geo_transform = project.geo_transform().unwrap();
+    drop(project);
+
+    // Pixel du point (1217500, 6077500) dans l'image du projet.
+    let px = ((1217500.0 - geo_transform[0]) / geo_transform[1]) as usize;
+    let py = ((6077500.0 - geo_transform[3]) / geo_transform[5]) as usize;
+
+    for view in [ViewMode::Vegetation, ViewMode::Satellite] {
+        let grid = project_tile_grid(name, view).unwrap();
+        for z in [grid.max_zoom - 2, grid.max_zoom] {
+            let (x, y) = grid.tile_at(z, px, py);
+
+            let tile = get_project_tile(name, view, z, x, y).unwrap();
+            assert!(!tile.cached, "Tile {}/{}/{} should be generated", z, x, y);
+            assert!(tile.path.starts_with(project_dir(name).join(TILES_DIR)));
+            let image = image::open(&tile.path).unwrap();
+            assert_eq!(
+                (image.width(), image.height()),
+                (TILE_SIZE as u32, TILE_SIZE as u32)
+            );
+
+            let again = get_project_tile(name, view, z, x, y).unwrap();
+            assert!(
+                again.cached,
+                "Tile {}/{}/{} should come from the cache",
+                z, x, y
+            );
+            assert_eq!(again.path, tile.path);
+        }
+
+        let (columns, _) = grid.tile_count(grid.max_zoom);
+        assert!(get_project_tile(name, view, grid.max_zoom, columns, 0).is_err());
+        assert!(get_project_tile(name, view, grid.max_zoom + 1, 0, 0).is_err());
+    }
+
+    fs::remove_dir_all(project_dir(name)).unwrap();
+}
@@ -0,0 +1,130 @@
+use firefront_gis_lib::gis_operation::layer_config::LayerConfig;
+use firefront_gis_lib::gis_operation::oso::VegetationSource;
+use firefront_gis_lib::pipeline::{
+    PipelineContext, PipelineState, Stage, StageRunner, run_pipeline,
+};
+use firefront_gis_lib::timings::{
+    StageTimings, processing_timer, stage_timer, take_processing_timings,
+};
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod common;
+
+const STAGE_SLEEP: Duration = Duration::from_millis(30);
+
+/// Étapes simulées : chaque étape attend un moment, dont une partie est mesurée comme détail.
+struct SleepingStages;
+
+impl StageRunner for SleepingStages {
+    async fn run_stage(&mut self, stage: Stage, ctx: &mut PipelineContext) -> Result<(), String> {
+        {
+            let _timer = stage_timer(&mut ctx.state.timings, format!("{}/detail", stage.key()));
+            thread::sleep(STAGE_SLEEP);
+        }
+        tokio::time::sleep(STAGE_SLEEP).await;
+        Ok(())
+    }
+}
+
+fn create_timings_folder(name: &str) -> PathBuf {
+    let folder = PathBuf::from(format!("tests/tmp_timings_{}", name));
+    if folder.exists() {
+        fs::remove_dir_all(&folder).unwrap();
+    }
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+#[tokio::test]
+async fn test_pipeline_records_stage_timings() {
+    let folder = create_timings_folder("pipeline");
+    let state = PipelineState::new(
+        "timings",
+        &common::get_test_bounding_box(),
+        &VegetationSource::BdForet,
+        vec!["2A".to_string()],
+    );
+    state.save(&folder).unwrap();
+    let mut ctx = PipelineContext::new(folder.clone(), state, LayerConfig::default());
+
+    let start = Instant::now();
+    run_pipeline(&mut SleepingStages, &mut ctx).await.unwrap();
+    let wall_clock = start.elapsed().as_secs_f64();
+
+    let timings = &ctx.state.timings;
+    for stage in Stage::ALL {
+        let seconds = timings.get(stage.key()).expect("Durée de l'étape absente");
+        assert!(seconds >= 2.0 * STAGE_SLEEP.as_secs_f64(), "{:?}", stage);
+        let detail = timings
+            .get(&format!("{}/detail", stage.key()))
+            .expect("Détail de l'étape absent");
+        assert!(detail >= STAGE_SLEEP.as_secs_f64() && detail < seconds);
+    }
+
+    let total = timings.stages_total();
+    assert!(total <= wall_clock, "{} > {}", total, wall_clock);
+    assert!(
+        total >= wall_clock * 0.8,
+        "Les étapes doivent couvrir la durée de la création : {} / {}",
+        total,
+        wall_clock
+    );
+
+    // Les durées sont enregistrées avec l'état, pour être conservées par une reprise.
+    let saved = PipelineState::load(&folder).unwrap();
+    assert_eq!(&saved.timings, timings);
+
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn test_stage_timings_accumulate_and_summarize() {
+    let mut timings = StageTimings::default();
+    timings.add("download", 1.5);
+    timings.add("download", 1.0);
+    timings.add("download/BDTOPO 2A", 2.0);
+    timings.add("ortho", 0.5);
+
+    assert_eq!(timings.get("download"), Some(2.5));
+    assert_eq!(timings.stages_total(), 3.0);
+    assert_eq!(timings.summary(), "download 2.5 s, ortho 0.5 s");
+
+    let mut processing = StageTimings::default();
+    processing.add("rasterize", 3.0);
+    timings.extend(processing.with_prefix("add_layers"));
+    assert_eq!(timings.get("add_layers/rasterize"), Some(3.0));
+    assert_eq!(timings.stages_total(), 3.0);
+
+    let payload = serde_json::to_value(&timings).unwrap();
+    assert_eq!(payload["download/BDTOPO 2A"], 2.0);
+    let parsed: StageTimings = serde_json::from_value(payload).unwrap();
+    assert_eq!(parsed, timings);
+}
+
+#[test]
+fn test_processing_timers_are_collected_per_thread() {
+    take_processing_timings();
+    {
+        let _timer = processing_timer("rasterize");
+        thread::sleep(Duration::from_millis(10));
+    }
+    {
+        let _timer = processing_timer("overlay");
+    }
+    thread::spawn(|| {
+        let _timer = processing_timer("rasterize");
+    })
+    .join()
+    .unwrap();
+
+    let timings = take_processing_timings();
+    assert_eq!(
+        timings.keys().collect::<Vec<&str>>(),
+        vec!["overlay", "rasterize"]
+    );
+    assert!(timings.get("rasterize").unwrap() >= 0.01);
+    assert!(take_processing_timings().is_empty());
+}
@@ -1,3 +1,4 @@
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
 use crate::documentation::Documentation;
@@ -6,12 +7,32 @@ use crate::loading::Loading;
 use crate::new_project::NewProject;
 use crate::project::Project;
 use crate::settings::SettingsComponent as Settings;
+use crate::setup::{SetupRequired, fetch_setup_report};
 use crate::sidebar::Sidebar;
-use crate::types::AppView;
+use crate::types::{AppView, SetupReport};
 
 #[function_component(App)]
 pub fn app() -> Html {
     let app_view = use_state(|| AppView::Home);
+    let setup_report = use_state(|| None::<SetupReport>);
+
+    {
+        let setup_report = setup_report.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                setup_report.set(fetch_setup_report("get_setup_report").await);
+            });
+            || ()
+        });
+    }
+
+    if let Some(report) = (*setup_report).clone().filter(|report| !report.ready) {
+        let on_report = {
+            let setup_report = setup_report.clone();
+            Callback::from(move |report: SetupReport| setup_report.set(Some(report)))
+        };
+        return html! { <SetupRequired report={report} on_report={on_report} /> };
+    }
 
     let on_view_change = {
         let app_view = app_view.clone();
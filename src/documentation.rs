@@ -1,5 +1,7 @@
 use yew::prelude::*;
 
+use crate::legend::Legend;
+
 #[function_component(Documentation)]
 pub fn documentation() -> Html {
     html! {
@@ -30,6 +32,11 @@ pub fn documentation() -> Html {
                     <li>{"Parcelles agricoles (données RPG)"}</li>
                 </ul>
             </div>
+            <div class="doc-section">
+                <h3>{"Légende de la vue végétation"}</h3>
+                <p>{"Couleurs appliquées sur l'image de végétation, de la couche la plus prioritaire à la moins prioritaire. Les classes de même couleur ne se distinguent pas sur l'image et sont regroupées."}</p>
+                <Legend />
+            </div>
             <div class="doc-section">
                 <h3>{"Exportation"}</h3>
                 <p>{"En vous rendant sur la page d'un projet vous pouvez exporter vos données. L'exportation produit un fichier ZIP contenant toutes les données du projet (découpage des carte de végetation et orthographique,fichier de ressources gpkg, photos originales). Pour modifier l'emplacement de sortie des exportations rendez-vous sur la page des paramètres."}</p>
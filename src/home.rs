@@ -1,5 +1,8 @@
-use crate::types::{AppView, Project, ProjectData, ViewMode};
-use std::collections::HashMap;
+use crate::loading::wait_timeout;
+use crate::new_project::format_bytes;
+use crate::types::{AppView, ExportJob, ProjectData, ProjectSummary, ViewMode, busy_label};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
@@ -12,10 +15,37 @@ extern "C" {
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
     async fn invoke(cmd: &str, args: JsValue) -> JsValue;
 
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke, catch)]
+    async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"])]
     fn convertFileSrc(filePath: &str, protocol: Option<&str>) -> String;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "dialog"])]
+    async fn open(args: JsValue) -> JsValue;
 }
 
+#[derive(Serialize)]
+struct DialogOptions {
+    directory: bool,
+    title: String,
+}
+
+/// Tri et recherche de la liste des projets, appliqués par `list_projects`.
+#[derive(Clone, Default, PartialEq, Serialize)]
+struct ProjectQuery {
+    /// `name`, `created_at`, `size` ou `department`.
+    sort_by: String,
+    filter: String,
+}
+
+const SORT_OPTIONS: [(&str, &str); 4] = [
+    ("name", "Nom"),
+    ("created_at", "Date de création"),
+    ("size", "Taille"),
+    ("department", "Département"),
+];
+
 #[derive(Properties, PartialEq)]
 pub struct HomeProps {
     pub on_view_change: Callback<AppView>,
@@ -23,24 +53,133 @@ pub struct HomeProps {
 
 #[function_component(Home)]
 pub fn home(props: &HomeProps) -> Html {
-    let projects = use_state(Vec::<Project>::new);
+    let projects = use_state(Vec::<ProjectSummary>::new);
     let delete_in_progress = use_state(|| false);
+    let selected = use_state(BTreeSet::<String>::new);
+    let export_jobs = use_state(Vec::<ExportJob>::new);
+    let busy_projects = use_state(HashMap::<String, String>::new);
+    let query = use_state(|| ProjectQuery {
+        sort_by: "name".to_string(),
+        filter: String::new(),
+    });
 
     {
-        let projects = projects.clone();
+        let export_jobs = export_jobs.clone();
+        let busy_projects = busy_projects.clone();
         use_effect_with((), move |_| {
-            load_projects(projects);
+            // La file vit côté backend : les exports lancés avant un changement de vue sont repris.
+            follow_export_queue(export_jobs, busy_projects);
             || ()
         });
     }
 
+    {
+        let projects = projects.clone();
+        use_effect_with((*query).clone(), move |query| {
+            load_projects(projects, query.clone());
+            || ()
+        });
+    }
+
+    let on_sort_change = {
+        let query = query.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            query.set(ProjectQuery {
+                sort_by: select.value(),
+                ..(*query).clone()
+            });
+        })
+    };
+
+    let on_filter_input = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            query.set(ProjectQuery {
+                filter: input.value(),
+                ..(*query).clone()
+            });
+        })
+    };
+
+    let on_toggle_selection = {
+        let selected = selected.clone();
+        Callback::from(move |project_name: String| {
+            let mut names = (*selected).clone();
+            if !names.remove(&project_name) {
+                names.insert(project_name);
+            }
+            selected.set(names);
+        })
+    };
+
+    let on_export_selection = {
+        let selected = selected.clone();
+        let export_jobs = export_jobs.clone();
+        let busy_projects = busy_projects.clone();
+        Callback::from(move |_: MouseEvent| {
+            let project_names: Vec<String> = selected.iter().cloned().collect();
+            if project_names.is_empty() {
+                return;
+            }
+            let selected = selected.clone();
+            let export_jobs = export_jobs.clone();
+            let busy_projects = busy_projects.clone();
+
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "project_names": project_names
+                }))
+                .unwrap();
+
+                match try_invoke("queue_export", args).await {
+                    Ok(_) => {
+                        selected.set(BTreeSet::new());
+                        follow_export_queue(export_jobs, busy_projects);
+                    }
+                    Err(e) => {
+                        web_sys::window()
+                            .unwrap()
+                            .alert_with_message(&e.as_string().unwrap_or_else(|| {
+                                "Erreur lors de l'ajout à la file d'exports".to_string()
+                            }))
+                            .unwrap();
+                    }
+                }
+            });
+        })
+    };
+
+    let on_cancel_export = {
+        let export_jobs = export_jobs.clone();
+        Callback::from(move |project: String| {
+            let export_jobs = export_jobs.clone();
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "project": project
+                }))
+                .unwrap();
+
+                match try_invoke("cancel_export", args).await {
+                    Ok(jobs) => {
+                        if let Ok(jobs) = serde_wasm_bindgen::from_value::<Vec<ExportJob>>(jobs) {
+                            export_jobs.set(jobs);
+                        }
+                    }
+                    Err(e) => web_sys::console::error_1(&e),
+                }
+            });
+        })
+    };
+
     let on_open_project = {
         let on_view_change = props.on_view_change.clone();
-        Callback::from(move |project: Project| {
+        Callback::from(move |project: ProjectSummary| {
             let on_view_change = on_view_change.clone();
             on_view_change.emit(AppView::Project(ProjectData {
                 name: project.name.clone(),
-                file_path: project.file_path.clone(),
+                file_path: project.folder.clone(),
                 view_mode: ViewMode::Vegetation,
             }));
         })
@@ -48,42 +187,202 @@ pub fn home(props: &HomeProps) -> Html {
 
     let on_delete_project = {
         let projects = projects.clone();
+        let query = (*query).clone();
         let delete_in_progress = delete_in_progress.clone();
-        Callback::from(move |project_name: String| {
+        let busy_projects = busy_projects.clone();
+        Callback::from(move |project: ProjectSummary| {
             let projects = projects.clone();
+            let query = query.clone();
             let delete_in_progress = delete_in_progress.clone();
+            let busy_projects = busy_projects.clone();
 
             if *delete_in_progress {
                 return;
             }
 
+            // Un projet externe est seulement retiré de la liste, sauf confirmation explicite.
+            let delete_files = project.external
+                && web_sys::window()
+                    .unwrap()
+                    .confirm_with_message(&format!(
+                        "Le projet {} est un projet externe ({}).\n\nSupprimer aussi son dossier ? Sinon, il est seulement retiré de la liste.",
+                        project.name, project.folder
+                    ))
+                    .unwrap_or(false);
+
             delete_in_progress.set(true);
 
             spawn_local(async move {
                 let args = serde_wasm_bindgen::to_value(&serde_json::json!({
-                    "project_name": project_name
+                    "project_name": project.name,
+                    "delete_files": delete_files
                 }))
                 .unwrap();
 
-                let response = invoke("delete_project", args).await;
-                if let Ok(result) = serde_wasm_bindgen::from_value::<String>(response) {
-                    if result == "success" {
-                        load_projects(projects.clone());
-                    } else {
-                        web_sys::console::error_1(
-                            &format!("Erreur lors de la suppression: {}", result).into(),
-                        );
+                match try_invoke("delete_project", args).await {
+                    Ok(_) => load_projects(projects.clone(), query),
+                    Err(e) => {
+                        // Le projet a pu devenir occupé depuis le dernier affichage.
+                        load_busy_projects(busy_projects);
+                        web_sys::window()
+                            .unwrap()
+                            .alert_with_message(&e.as_string().unwrap_or_else(|| {
+                                "Erreur lors de la suppression du projet".to_string()
+                            }))
+                            .unwrap();
                     }
-                };
+                }
 
                 delete_in_progress.set(false);
             });
         })
     };
 
+    let on_import_project = {
+        let projects = projects.clone();
+        let query = (*query).clone();
+        Callback::from(move |_: MouseEvent| {
+            let projects = projects.clone();
+            let query = query.clone();
+            spawn_local(async move {
+                let options = DialogOptions {
+                    directory: true,
+                    title: String::from("Sélectionner le dossier d'un projet"),
+                };
+                let Ok(options) = serde_wasm_bindgen::to_value(&options) else {
+                    return;
+                };
+                let Some(path) = open(options).await.as_string() else {
+                    return;
+                };
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "path": path
+                }))
+                .unwrap();
+
+                match try_invoke("import_project", args).await {
+                    Ok(_) => load_projects(projects.clone(), query),
+                    Err(e) => {
+                        web_sys::window()
+                            .unwrap()
+                            .alert_with_message(&e.as_string().unwrap_or_else(|| {
+                                "Erreur lors de l'import du projet".to_string()
+                            }))
+                            .unwrap();
+                    }
+                }
+            });
+        })
+    };
+
+    let on_resume_project = {
+        let on_view_change = props.on_view_change.clone();
+        Callback::from(move |project_name: String| {
+            on_view_change.emit(AppView::Loading(project_name.clone()));
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "project_name": project_name,
+                }))
+                .unwrap();
+                // L'issue de la reprise est transmise par les événements de création.
+                let _ = invoke("resume_project_creation", args).await;
+            });
+        })
+    };
+
+    let on_rename_project = {
+        let projects = projects.clone();
+        let query = (*query).clone();
+        Callback::from(move |old_name: String| {
+            let projects = projects.clone();
+            let query = query.clone();
+            let window = web_sys::window().unwrap();
+            let new_name = match window
+                .prompt_with_message_and_default("Nouveau nom du projet :", &old_name)
+            {
+                Ok(Some(name)) if !name.trim().is_empty() && name != old_name => name,
+                _ => return,
+            };
+
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "old_name": old_name,
+                    "new_name": new_name
+                }))
+                .unwrap();
+
+                match try_invoke("rename_project", args).await {
+                    Ok(_) => load_projects(projects.clone(), query),
+                    Err(e) => {
+                        web_sys::window()
+                            .unwrap()
+                            .alert_with_message(&e.as_string().unwrap_or_else(|| {
+                                "Erreur lors du renommage du projet".to_string()
+                            }))
+                            .unwrap();
+                    }
+                }
+            });
+        })
+    };
+
     html! {
         <div class="home-view">
-            <h2>{"Projets précédents"}</h2>
+            <div class="home-header">
+                <h2>{"Projets précédents"}</h2>
+                <div class="project-filters">
+                    <input
+                        type="search"
+                        class="project-search"
+                        placeholder="Rechercher un projet ou un département"
+                        value={query.filter.clone()}
+                        oninput={on_filter_input}
+                    />
+                    <select class="project-sort" onchange={on_sort_change}>
+                        { for SORT_OPTIONS.iter().map(|(value, label)| html! {
+                            <option value={*value} selected={query.sort_by == *value}>{*label}</option>
+                        }) }
+                    </select>
+                </div>
+                <button class="import-project-btn" onclick={on_import_project}>
+                    {"Importer un projet"}
+                </button>
+                if !selected.is_empty() {
+                    <button class="export-selection-btn" onclick={on_export_selection}>
+                        {format!("Exporter la sélection ({})", selected.len())}
+                    </button>
+                }
+            </div>
+            if !export_jobs.is_empty() {
+                <div class="export-queue">
+                    <h3>{"File d'exports"}</h3>
+                    <ul>
+                        { for export_jobs.iter().map(|job| {
+                            let on_cancel = {
+                                let on_cancel_export = on_cancel_export.clone();
+                                let project = job.project.clone();
+                                Callback::from(move |_: MouseEvent| {
+                                    on_cancel_export.emit(project.clone());
+                                })
+                            };
+                            html! {
+                                <li class={format!("export-job export-job-{}", job.state)}>
+                                    <span class="export-job-name">{&job.project}</span>
+                                    <span class="export-job-state">{job.state_label()}</span>
+                                    if let Some(error) = &job.error {
+                                        <span class="export-job-detail">{error}</span>
+                                    } else if let Some(path) = &job.zip_path {
+                                        <span class="export-job-detail">{path}</span>
+                                    }
+                                    if job.state == "queued" {
+                                        <button class="cancel-export-btn" onclick={on_cancel}>{"Annuler"}</button>
+                                    }
+                                </li>
+                            }
+                        }) }
+                    </ul>
+                </div>
+            }
             <div class="project-grid">
                 {
                     (*projects).iter().map(|project| {
@@ -98,18 +397,75 @@ pub fn home(props: &HomeProps) -> Html {
                         };
                         let on_delete = {
                             let on_delete_project = on_delete_project.clone();
+                            let project = project_clone.clone();
+                            Callback::from(move |_: MouseEvent| {
+                                on_delete_project.emit(project.clone());
+                            })
+                        };
+                        let on_resume = {
+                            let on_resume_project = on_resume_project.clone();
+                            let project_name = project.name.clone();
+                            Callback::from(move |_: MouseEvent| {
+                                on_resume_project.emit(project_name.clone());
+                            })
+                        };
+                        let on_rename = {
+                            let on_rename_project = on_rename_project.clone();
                             let project_name = project.name.clone();
                             Callback::from(move |_: MouseEvent| {
-                                on_delete_project.emit(project_name.clone());
+                                on_rename_project.emit(project_name.clone());
+                            })
+                        };
+                        let on_select = {
+                            let on_toggle_selection = on_toggle_selection.clone();
+                            let project_name = project.name.clone();
+                            Callback::from(move |_: Event| {
+                                on_toggle_selection.emit(project_name.clone());
                             })
                         };
+                        let busy_kind = busy_projects.get(&project.name);
                         html! {
                             <div class="project-card">
+                                if project.complete {
+                                    <input
+                                        type="checkbox"
+                                        class="project-select"
+                                        title="Sélectionner pour l'export"
+                                        checked={selected.contains(&project.name)}
+                                        onchange={on_select}
+                                    />
+                                }
                                 <img src={converted_preview_path} alt={format!("Aperçu de {}", project.name)} />
-                                <h3>{&project.name}</h3>
+                                <h3>
+                                    {&project.name}
+                                    if project.external {
+                                        <span class="project-external-badge" title={project.folder.clone()}>{"Externe"}</span>
+                                    }
+                                </h3>
+                                if !project.vintages.is_empty() {
+                                    <p class="project-vintages">{&project.vintages}</p>
+                                }
+                                <p class="project-info">{project_info(project)}</p>
+                                if !project.complete {
+                                    <p class="project-incomplete">{"Incomplet — reprendre ou supprimer"}</p>
+                                }
                                 <div class="project-card-actions">
-                                    <button class="open-btn" onclick={on_click}>{"Ouvrir"}</button>
-                                    <button class="delete-btn" onclick={on_delete}>{"Supprimer"}</button>
+                                    if !project.complete {
+                                        <button class="resume-btn" onclick={on_resume}>{"Reprendre"}</button>
+                                    } else {
+                                        <button class="open-btn" onclick={on_click}>{"Ouvrir"}</button>
+                                    }
+                                    if !project.external {
+                                        <button class="rename-btn" onclick={on_rename}>{"Renommer"}</button>
+                                    }
+                                    <button
+                                        class="delete-btn"
+                                        onclick={on_delete}
+                                        disabled={busy_kind.is_some()}
+                                        title={busy_kind.map(|kind| busy_label(kind))}
+                                    >
+                                        {"Supprimer"}
+                                    </button>
                                 </div>
                             </div>
                         }
@@ -120,32 +476,63 @@ pub fn home(props: &HomeProps) -> Html {
     }
 }
 
-fn load_projects(projects: UseStateHandle<Vec<Project>>) {
+fn load_projects(projects: UseStateHandle<Vec<ProjectSummary>>, query: ProjectQuery) {
     spawn_local(async move {
-        let result = invoke_without_args("get_projects").await;
-        if let Ok(projects_map) =
-            serde_wasm_bindgen::from_value::<HashMap<String, Vec<String>>>(result)
-        {
-            let loaded_projects = projects_map
-                .into_iter()
-                .filter_map(|(name, paths)| {
-                    if paths.len() >= 2 {
-                        Some(Project {
-                            name,
-                            preview_path: paths[0].clone(),
-                            file_path: paths[1].clone(),
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<Project>>();
+        let args = serde_wasm_bindgen::to_value(&query).unwrap();
+        match try_invoke("list_projects", args).await {
+            Ok(result) => match serde_wasm_bindgen::from_value::<Vec<ProjectSummary>>(result) {
+                Ok(loaded_projects) => projects.set(loaded_projects),
+                Err(_) => web_sys::console::error_1(&"Échec de l'analyse des projets".into()),
+            },
+            Err(e) => web_sys::console::error_1(&e),
+        }
+    });
+}
+
+/// Date de création, taille et départements d'un projet, affichés sur sa carte.
+fn project_info(project: &ProjectSummary) -> String {
+    let mut parts = Vec::new();
+    if let Some(date) = project.created_at.as_deref().and_then(|date| date.get(..10)) {
+        parts.push(date.to_string());
+    }
+    parts.push(format_bytes(project.size_bytes));
+    if !project.regions.is_empty() {
+        parts.push(project.regions.join(", "));
+    }
+    parts.join(" · ")
+}
 
-            if !loaded_projects.is_empty() {
-                projects.set(loaded_projects);
+/// Relit les projets occupés, dont la suppression est désactivée.
+fn load_busy_projects(busy_projects: UseStateHandle<HashMap<String, String>>) {
+    spawn_local(async move {
+        let result = invoke_without_args("get_busy_projects").await;
+        match serde_wasm_bindgen::from_value::<HashMap<String, String>>(result) {
+            Ok(busy) => busy_projects.set(busy),
+            Err(_) => web_sys::console::error_1(&"Échec de l'analyse des projets occupés".into()),
+        }
+    });
+}
+
+/// Relit la file d'exports et les projets occupés chaque seconde
+/// tant qu'un export est en attente ou en cours.
+fn follow_export_queue(
+    export_jobs: UseStateHandle<Vec<ExportJob>>,
+    busy_projects: UseStateHandle<HashMap<String, String>>,
+) {
+    spawn_local(async move {
+        loop {
+            load_busy_projects(busy_projects.clone());
+            let result = invoke_without_args("get_export_queue").await;
+            let Ok(jobs) = serde_wasm_bindgen::from_value::<Vec<ExportJob>>(result) else {
+                web_sys::console::error_1(&"Échec de l'analyse de la file d'exports".into());
+                return;
+            };
+            let pending = jobs.iter().any(ExportJob::is_pending);
+            export_jobs.set(jobs);
+            if !pending {
+                return;
             }
-        } else {
-            web_sys::console::error_1(&"Échec de l'analyse des projets".into());
+            wait_timeout(1000).await;
         }
     });
 }
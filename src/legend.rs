@@ -0,0 +1,78 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::types::LegendEntry;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke, catch)]
+    async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+}
+
+#[derive(Serialize)]
+struct LegendArgs {
+    project_name: Option<String>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LegendProps {
+    /// Projet affiché, dont la légende reprend la couleur du fond départemental.
+    #[prop_or_default]
+    pub project_name: Option<String>,
+}
+
+/// Légende de l'image de végétation, chargée depuis la commande `get_legend`.
+#[function_component(Legend)]
+pub fn legend(props: &LegendProps) -> Html {
+    let entries = use_state(Vec::<LegendEntry>::new);
+    let error = use_state(|| None::<String>);
+
+    {
+        let entries = entries.clone();
+        let error = error.clone();
+        use_effect_with(props.project_name.clone(), move |project_name| {
+            let args = LegendArgs {
+                project_name: project_name.clone(),
+            };
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&args).unwrap();
+                match try_invoke("get_legend", args).await {
+                    Ok(result) => {
+                        match serde_wasm_bindgen::from_value::<Vec<LegendEntry>>(result) {
+                            Ok(legend) => entries.set(legend),
+                            Err(e) => error.set(Some(format!("Légende invalide: {:?}", e))),
+                        }
+                    }
+                    Err(e) => error.set(Some(
+                        e.as_string()
+                            .unwrap_or_else(|| "Légende indisponible".to_string()),
+                    )),
+                }
+            });
+            || ()
+        });
+    }
+
+    if let Some(message) = (*error).clone() {
+        return html! { <p class="legend-error">{message}</p> };
+    }
+
+    html! {
+        <ul class="legend">
+            { for entries.iter().map(|entry| {
+                let [r, g, b] = entry.rgb;
+                html! {
+                    <li class="legend-entry" title={entry.source_layer.clone()}>
+                        <span
+                            class="legend-swatch"
+                            style={format!("background-color: rgb({}, {}, {})", r, g, b)}
+                        />
+                        <span class="legend-label">{&entry.label_fr}</span>
+                    </li>
+                }
+            })}
+        </ul>
+    }
+}
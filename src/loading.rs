@@ -2,7 +2,15 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
-use crate::types::{AppView, ProjectData, ViewMode};
+use crate::types::{
+    AppView, ProgressUpdate, ProjectCreated, ProjectCreationFailed, ProjectData, ViewMode,
+};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
+    async fn invoke_with_args(cmd: &str, args: JsValue) -> JsValue;
+}
 
 #[derive(Properties, PartialEq)]
 pub struct LoadingProps {
@@ -17,6 +25,8 @@ struct ProgressState {
     error: Option<String>,
     subtask: Option<String>,
     subtask_count: Option<(usize, usize)>, // (current, total)
+    warnings: Vec<String>,
+    resumable: bool,
 }
 
 impl Default for ProgressState {
@@ -27,6 +37,8 @@ impl Default for ProgressState {
             error: None,
             subtask: None,
             subtask_count: None,
+            warnings: Vec::new(),
+            resumable: false,
         }
     }
 }
@@ -46,6 +58,27 @@ pub fn loading(props: &LoadingProps) -> Html {
         });
     }
 
+    let on_resume = {
+        let project_name = props.project_name.clone();
+        let progress_state = progress_state.clone();
+        Callback::from(move |_| {
+            let project_name = project_name.clone();
+            progress_state.set(ProgressState {
+                message: "Reprise de la création...".to_string(),
+                percentage: progress_state.percentage,
+                ..ProgressState::default()
+            });
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "project_name": project_name,
+                }))
+                .unwrap();
+                // L'issue de la reprise est transmise par les événements de création.
+                let _ = invoke_with_args("resume_project_creation", args).await;
+            });
+        })
+    };
+
     html! {
         <div class="loading-view">
             <h2>{"Création du projet"}</h2>
@@ -75,6 +108,18 @@ pub fn loading(props: &LoadingProps) -> Html {
                 {progress_state.error.as_ref().map(|error| html! {
                     <p class="error-message">{error}</p>
                 }).unwrap_or_default()}
+                {
+                    if progress_state.error.is_some() && progress_state.resumable {
+                        html! {
+                            <button class="resume-btn" onclick={on_resume}>{"Reprendre"}</button>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {for progress_state.warnings.iter().map(|warning| html! {
+                    <p class="warning-message">{warning}</p>
+                })}
             </div>
         </div>
     }
@@ -97,80 +142,101 @@ struct LoadingProgressBarProps {
     percentage: u8,
 }
 
-fn get_progress_percentage(message: &str) -> u8 {
-    match message {
-        "Recherche des fichiers" => 10,
-        "Téléchargement des données" => 25,
-        "Initialisation du projet" => 35,
-        "Préparation des Couches" => 50,
-        "Fusion des données" => 60,
-        "Ajout des Couches" => 70,
-        "Finalisation" => 85,
-        "Nettoyage" => 95,
-        "Projet créé avec succès" => 100,
+fn get_progress_percentage(stage: &str) -> u8 {
+    match stage {
+        "stage.search_files" => 10,
+        "stage.download" => 25,
+        "stage.init" => 35,
+        "stage.prepare" => 50,
+        "stage.fuse" => 60,
+        "stage.add_layers" => 70,
+        "stage.finalize" => 85,
+        "stage.cleanup" => 95,
+        "stage.created" => 100,
         _ => 0,
     }
 }
 
-fn parse_progress_message(payload: &str) -> (String, Option<String>, Option<(usize, usize)>) {
-    let parts: Vec<&str> = payload.split('|').collect();
-    let main_message = parts.first().map_or("", |s| *s).to_string();
-    let subtask = if parts.len() > 1 {
-        Some(parts[1].to_string())
-    } else {
-        None
-    };
-
-    let count = if parts.len() > 2 {
-        if let Some((current_str, total_str)) = parts[2].split_once('/') {
-            if let (Ok(current), Ok(total)) =
-                (current_str.parse::<usize>(), total_str.parse::<usize>())
-            {
-                Some((current, total))
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-
-    (main_message, subtask, count)
-}
-
 fn setup_progress_tracking(
     project_name: String,
     on_view_change: Callback<AppView>,
     progress_state: UseStateHandle<ProgressState>,
 ) -> Box<dyn FnOnce()> {
     let progress_state_clone = progress_state.clone();
-    let project_name_clone = project_name.clone();
-    let on_view_change_clone = on_view_change.clone();
 
-    let closure = Closure::<dyn FnMut(String)>::new(move |payload: String| {
-        let (main_message, subtask, count) = parse_progress_message(&payload);
-        let percentage = get_progress_percentage(&main_message);
-
-        web_sys::console::log_1(&format!("Progress update: {}", payload).into());
+    let progress_closure = Closure::<dyn FnMut(JsValue)>::new(move |payload: JsValue| {
+        match serde_wasm_bindgen::from_value::<ProgressUpdate>(payload) {
+            Ok(update) => {
+                web_sys::console::log_1(&format!("Progress update: {:?}", update).into());
+                progress_state_clone.set(ProgressState {
+                    message: update.stage_text,
+                    percentage: get_progress_percentage(&update.stage),
+                    error: None,
+                    subtask: update.step_text,
+                    subtask_count: update.count,
+                    warnings: Vec::new(),
+                    resumable: false,
+                });
+            }
+            Err(e) => web_sys::console::error_1(
+                &format!("Invalid progress-update payload: {:?}", e).into(),
+            ),
+        }
+    });
 
-        progress_state_clone.set(ProgressState {
-            message: main_message.clone(),
-            percentage,
-            error: None,
-            subtask,
-            subtask_count: count,
-        });
+    let progress_state_clone = progress_state.clone();
+    let created_closure = Closure::<dyn FnMut(JsValue)>::new(move |payload: JsValue| {
+        match serde_wasm_bindgen::from_value::<ProjectCreated>(payload) {
+            Ok(created) if created.name == project_name => {
+                progress_state_clone.set(ProgressState {
+                    message: "Projet créé avec succès".to_string(),
+                    percentage: 100,
+                    error: None,
+                    subtask: Some(format!(
+                        "{} département(s) en {:.0} s",
+                        created.regions.len(),
+                        created.duration_seconds
+                    )),
+                    subtask_count: None,
+                    warnings: created.warnings.clone(),
+                    resumable: false,
+                });
+                handle_project_success(created, on_view_change.clone());
+            }
+            Ok(_) => {}
+            Err(e) => web_sys::console::error_1(
+                &format!("Invalid project-created payload: {:?}", e).into(),
+            ),
+        }
+    });
 
-        if main_message == "Projet créé avec succès" {
-            handle_project_success(project_name_clone.clone(), on_view_change_clone.clone());
+    let progress_state_clone = progress_state.clone();
+    let failed_closure = Closure::<dyn FnMut(JsValue)>::new(move |payload: JsValue| {
+        match serde_wasm_bindgen::from_value::<ProjectCreationFailed>(payload) {
+            Ok(failed) => progress_state_clone.set(ProgressState {
+                message: if failed.cancelled {
+                    "Création annulée".to_string()
+                } else {
+                    "Échec de la création du projet".to_string()
+                },
+                percentage: progress_state_clone.percentage,
+                error: Some(failed.message),
+                subtask: None,
+                subtask_count: None,
+                warnings: failed.warnings,
+                resumable: failed.resumable,
+            }),
+            Err(e) => web_sys::console::error_1(
+                &format!("Invalid project-creation-failed payload: {:?}", e).into(),
+            ),
         }
     });
 
-    match setup_tauri_listener(&closure) {
+    match setup_tauri_listener(&progress_closure, &created_closure, &failed_closure) {
         Ok(cleanup) => {
-            closure.forget();
+            progress_closure.forget();
+            created_closure.forget();
+            failed_closure.forget();
             cleanup
         }
         Err(error) => {
@@ -180,24 +246,31 @@ fn setup_progress_tracking(
                 percentage: progress_state.percentage,
                 subtask: progress_state.subtask.clone(),
                 subtask_count: progress_state.subtask_count,
+                warnings: progress_state.warnings.clone(),
+                resumable: false,
             });
             Box::new(|| {})
         }
     }
 }
 
-fn handle_project_success(project_name: String, on_view_change: Callback<AppView>) {
+fn handle_project_success(created: ProjectCreated, on_view_change: Callback<AppView>) {
     spawn_local(async move {
-        wait_timeout(1000).await;
+        let delay = if created.warnings.is_empty() {
+            1000
+        } else {
+            3000
+        };
+        wait_timeout(delay).await;
         on_view_change.emit(AppView::Project(ProjectData {
-            name: project_name.clone(),
-            file_path: format!("projects/{}/{}_VEGET.jpeg", project_name, project_name),
+            name: created.name,
+            file_path: created.veget_path,
             view_mode: ViewMode::Vegetation,
         }));
     });
 }
 
-async fn wait_timeout(ms: i32) {
+pub(crate) async fn wait_timeout(ms: i32) {
     let promise = js_sys::Promise::new(&mut |resolve, _| {
         web_sys::window()
             .unwrap()
@@ -207,29 +280,40 @@ async fn wait_timeout(ms: i32) {
     let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
 }
 
-fn setup_tauri_listener(closure: &Closure<dyn FnMut(String)>) -> Result<Box<dyn FnOnce()>, String> {
+fn setup_tauri_listener(
+    progress_closure: &Closure<dyn FnMut(JsValue)>,
+    created_closure: &Closure<dyn FnMut(JsValue)>,
+    failed_closure: &Closure<dyn FnMut(JsValue)>,
+) -> Result<Box<dyn FnOnce()>, String> {
     let window = web_sys::window().ok_or("Failed to get window object")?;
-    js_sys::Reflect::set(
-        &window,
-        &"__tauri_progress_callback".into(),
-        closure.as_ref().unchecked_ref(),
-    )
-    .map_err(|_| "Failed to set up callback")?;
+    for (name, closure) in [
+        ("__tauri_progress_callback", progress_closure.as_ref()),
+        ("__tauri_project_created_callback", created_closure.as_ref()),
+        ("__tauri_project_failed_callback", failed_closure.as_ref()),
+    ] {
+        js_sys::Reflect::set(&window, &name.into(), closure.unchecked_ref())
+            .map_err(|_| "Failed to set up callback")?;
+    }
 
     let js_code = r#"
-        const callback = (event) => {
-            console.log('Tauri event received:', event);
-            if (event && event.payload) {
-                window.__tauri_progress_callback(event.payload);
-            }
-        };
-        window.__TAURI__.event.listen('progress-update', callback)
-            .then(unlisten => {
-                console.log('Tauri listener registered successfully');
-                window.__tauri_unlisten = unlisten;
+        const listen = (event_name, callback_name) =>
+            window.__TAURI__.event.listen(event_name, (event) => {
+                console.log('Tauri event received:', event);
+                if (event && event.payload) {
+                    window[callback_name](event.payload);
+                }
+            });
+        Promise.all([
+            listen('progress-update', '__tauri_progress_callback'),
+            listen('project-created', '__tauri_project_created_callback'),
+            listen('project-creation-failed', '__tauri_project_failed_callback'),
+        ])
+            .then(unlisteners => {
+                console.log('Tauri listeners registered successfully');
+                window.__tauri_unlisten = () => unlisteners.forEach(unlisten => unlisten());
             })
             .catch(err => {
-                console.error('Error registering Tauri listener:', err);
+                console.error('Error registering Tauri listeners:', err);
             });
     "#;
 
@@ -239,7 +323,13 @@ fn setup_tauri_listener(closure: &Closure<dyn FnMut(String)>) -> Result<Box<dyn
         if let Some(win) = web_sys::window() {
             let cleanup_js = "if (window.__tauri_unlisten) window.__tauri_unlisten();";
             let _ = js_sys::eval(cleanup_js);
-            let _ = js_sys::Reflect::delete_property(&win, &"__tauri_progress_callback".into());
+            for name in [
+                "__tauri_progress_callback",
+                "__tauri_project_created_callback",
+                "__tauri_project_failed_callback",
+            ] {
+                let _ = js_sys::Reflect::delete_property(&win, &name.into());
+            }
         }
     }))
 }
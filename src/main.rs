@@ -1,11 +1,18 @@
 pub mod app;
 pub mod documentation;
 pub mod home;
+pub mod legend;
 pub mod loading;
 pub mod new_project;
+// Compilé aussi dans le backend : l'interface n'en utilise pas chaque élément.
+#[allow(dead_code)]
+#[path = "../shared/payloads.rs"]
+pub mod payloads;
 pub mod project;
 pub mod settings;
+pub mod setup;
 pub mod sidebar;
+pub mod tile_map;
 pub mod types;
 
 use crate::app::App;
@@ -1,9 +1,14 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
-use crate::types::AppView;
+use crate::payloads::{
+    BoundingBox, CommandArgs, ConflictMode, CreateProjectArgs, SettingsPayload,
+};
+use crate::types::{AppView, ProjectSummary};
 
 #[wasm_bindgen]
 extern "C" {
@@ -12,20 +17,260 @@ extern "C" {
 
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"])]
     async fn invoke(cmd: &str, args: JsValue) -> JsValue;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke, catch)]
+    async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+}
+
+#[derive(Serialize)]
+struct ListProjectsArgs {
+    sort_by: String,
+}
+
+#[derive(Serialize)]
+struct CreationParamsArgs {
+    project_name: String,
+}
+
+/// Paramètres de création d'un projet, renvoyés par `get_creation_params`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+struct CreationParams {
+    vegetation_source: Option<String>,
+    layers: Option<Vec<String>>,
+}
+
+/// Couche BDTOPO de la configuration renvoyée par `get_layer_config`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct LayerDefinition {
+    name: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct LayerConfig {
+    layers: Vec<LayerDefinition>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Copy)]
-pub struct ProjectBoundingBox {
-    pub xmin: f64,
-    pub ymin: f64,
-    pub xmax: f64,
-    pub ymax: f64,
+/// Couches hors BDTOPO proposées à la création, avec leur libellé.
+const BASE_LAYERS: [(&str, &str); 2] = [
+    ("FORMATION_VEGETALE", "Végétation (BD Forêt)"),
+    ("PARCELLES_GRAPHIQUES", "Parcelles agricoles (RPG)"),
+];
+
+#[derive(Serialize)]
+struct ConflictArgs {
+    name: String,
 }
 
-#[derive(Serialize, Deserialize)]
-struct NewProjectArgs {
+/// Projet existant du même nom, renvoyé par `check_project_conflict`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ProjectConflict {
     name: String,
-    project_bb: ProjectBoundingBox,
+    created_at: Option<String>,
+    size_bytes: u64,
+    bbox: Option<BoundingBox>,
+    suggested_name: String,
+}
+
+/// Réponse de `create_project_com` ; l'issue de la création arrive par les événements.
+#[derive(Debug, Deserialize)]
+struct CreationResponse {
+    status: String,
+    /// Projet contenant l'emprise, pour le statut `reuse_available`.
+    #[serde(default)]
+    source: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EstimateArgs {
+    project_bb: BoundingBox,
+}
+
+#[derive(Serialize)]
+struct SnapArgs {
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64,
+    mode: String,
+}
+
+/// Emprise corrigée renvoyée par `suggest_valid_bbox`, avec le déplacement de chaque bord.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct GridSnap {
+    bbox: BoundingBox,
+    delta: BoundingBox,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ArchiveEstimate {
+    cached: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ProjectEstimate {
+    regions: Vec<String>,
+    archives: Vec<ArchiveEstimate>,
+    download_bytes: u64,
+    unknown_sizes: usize,
+    width: u64,
+    height: u64,
+    tiff_size_bytes: u64,
+    slice_count: u64,
+    duration_secs: u64,
+    #[serde(default)]
+    area_km2: f64,
+    /// Limite de taille dépassée par l'emprise.
+    #[serde(default)]
+    size_error: Option<String>,
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1_073_741_824 {
+        format!("{:.1} Go", bytes as f64 / 1_073_741_824.0)
+    } else {
+        format!("{:.0} Mo", bytes as f64 / 1_048_576.0)
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    if secs < 60 {
+        "moins d'une minute".to_string()
+    } else if secs < 3600 {
+        format!("environ {} min", secs.div_ceil(60))
+    } else {
+        format!("environ {} h {:02} min", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+fn estimate_summary(estimate: &ProjectEstimate) -> Html {
+    let to_download = estimate.archives.iter().filter(|a| !a.cached).count();
+    let cached = estimate.archives.len() - to_download;
+    let download = if to_download == 0 {
+        "rien à télécharger".to_string()
+    } else if estimate.unknown_sizes == to_download {
+        format!("{} archive(s), taille inconnue", to_download)
+    } else if estimate.unknown_sizes > 0 {
+        format!(
+            "{} archive(s), plus de {} ({} de taille inconnue)",
+            to_download,
+            format_bytes(estimate.download_bytes),
+            estimate.unknown_sizes
+        )
+    } else {
+        format!(
+            "{} archive(s), {}",
+            to_download,
+            format_bytes(estimate.download_bytes)
+        )
+    };
+
+    html! {
+        <>
+        <ul>
+            <li>{format!("Surface : {:.0} km²", estimate.area_km2)}</li>
+            <li>{format!("Départements : {}", estimate.regions.join(", "))}</li>
+            <li>{format!("À télécharger : {}", download)}</li>
+            <li>{format!("Déjà en cache : {} archive(s)", cached)}</li>
+            <li>{format!(
+                "Raster : {} × {} px (~{})",
+                estimate.width,
+                estimate.height,
+                format_bytes(estimate.tiff_size_bytes)
+            )}</li>
+            <li>{format!("Découpes : {}", estimate.slice_count)}</li>
+            <li>{format!("Durée estimée : {}", format_duration(estimate.duration_secs))}</li>
+        </ul>
+        if let Some(size_error) = &estimate.size_error {
+            <p class="error-message">{format!("Projet trop grand : {}", size_error)}</p>
+        }
+        </>
+    }
+}
+
+/// Affiche la vue de chargement et lance la création.
+///
+/// # Arguments
+///
+/// * `args` - Les paramètres de la création.
+/// * `display_name` - Le nom du projet suivi par la vue de chargement, qui diffère
+///   du nom demandé quand le projet est créé sous un nom libre.
+/// * `on_view_change` - Le changement de vue de l'application.
+fn start_creation(
+    args: CreateProjectArgs,
+    display_name: String,
+    on_view_change: Callback<AppView>,
+) {
+    on_view_change.emit(AppView::Loading(display_name.clone()));
+
+    spawn_local(async move {
+        let serialized_args =
+            serde_wasm_bindgen::to_value(&CommandArgs { args: args.clone() }).unwrap();
+        match try_invoke("create_project_com", serialized_args).await {
+            Ok(value) => match serde_wasm_bindgen::from_value::<CreationResponse>(value) {
+                // Projet du même nom créé depuis la vérification : retour au formulaire.
+                Ok(response) if response.status == "conflict" => {
+                    on_view_change.emit(AppView::NewProject);
+                }
+                // Un projet existant contient l'emprise : sa découpe évite tout téléchargement.
+                Ok(response) if response.status == "reuse_available" => {
+                    let allow_reuse = web_sys::window()
+                        .unwrap()
+                        .confirm_with_message(&format!(
+                            "Le projet {} contient déjà cette emprise.\n\nCréer le projet en découpant ses données, sans téléchargement ? Sinon, la création complète est lancée.",
+                            response.source.unwrap_or_default()
+                        ))
+                        .unwrap_or(false);
+                    let args = CreateProjectArgs {
+                        allow_reuse: Some(allow_reuse),
+                        ..args
+                    };
+                    start_creation(args, display_name, on_view_change);
+                }
+                // `in_progress` : la même création est déjà en cours et suivie par la vue
+                // de chargement.
+                _ => {}
+            },
+            // L'erreur est affichée par la vue de chargement (`project-creation-failed`).
+            Err(e) => web_sys::console::log_1(&format!("Error: {:?}", e).into()),
+        }
+    });
+}
+
+fn conflict_summary(existing: &ProjectConflict) -> Html {
+    let created_at = existing
+        .created_at
+        .as_deref()
+        .map(|date| date.get(..10).unwrap_or(date).to_string())
+        .unwrap_or_else(|| "inconnue".to_string());
+
+    html! {
+        <ul>
+            <li>{format!("Créé le : {}", created_at)}</li>
+            <li>{format!("Taille : {}", format_bytes(existing.size_bytes))}</li>
+            if let Some(bbox) = existing.bbox {
+                <li>{format!(
+                    "Emprise : {} {} {} {}",
+                    bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax
+                )}</li>
+            }
+        </ul>
+    }
+}
+
+/// Couches retenues pour la création : `None` si aucune couche n'est décochée.
+fn selected_layers(available: &[String], excluded: &HashSet<String>) -> Option<Vec<String>> {
+    if excluded.is_empty() {
+        return None;
+    }
+    Some(
+        available
+            .iter()
+            .filter(|name| !excluded.contains(*name))
+            .cloned()
+            .collect(),
+    )
 }
 
 #[derive(Properties, PartialEq)]
@@ -36,6 +281,9 @@ pub struct NewProjectProps {
 #[function_component(NewProject)]
 pub fn new_project(props: &NewProjectProps) -> Html {
     let is_loading = use_state(|| false);
+    // Lu au moment de l'envoi, contrairement à `is_loading` : un double clic ne lance
+    // qu'une création (le backend écarte de toute façon une seconde demande).
+    let submitting = use_mut_ref(|| false);
     let project_name = use_state(String::new);
 
     let xmin_str = use_state(String::new);
@@ -44,6 +292,136 @@ pub fn new_project(props: &NewProjectProps) -> Html {
     let ymax_str = use_state(String::new);
 
     let validation_errors = use_state(Vec::<String>::new);
+    let snap_mode = use_state(|| "expand".to_string());
+    let snap_note = use_state(|| None::<String>);
+    let conflict = use_state(|| None::<(ProjectConflict, CreateProjectArgs)>);
+    // Couches proposées (végétation, RPG puis couches BDTOPO activées) et couches décochées.
+    let available_layers = use_state(Vec::<String>::new);
+    let excluded_layers = use_state(HashSet::<String>::new);
+    let allow_oversize = use_state(|| false);
+    // Projets terminés proposés comme modèles, et modèle choisi.
+    let template_projects = use_state(Vec::<String>::new);
+    let template_project = use_state(|| None::<String>);
+    // Source de végétation choisie ; OSO n'est proposée qu'une fois son URL renseignée.
+    let vegetation_source = use_state(|| None::<String>);
+    let oso_available = use_state(|| false);
+    {
+        let oso_available = oso_available.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let result = invoke_without_args("get_settings").await;
+                if let Ok(settings) = serde_wasm_bindgen::from_value::<SettingsPayload>(result) {
+                    oso_available.set(settings.oso_url.is_some());
+                }
+            });
+            || ()
+        });
+    }
+    {
+        let template_projects = template_projects.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&ListProjectsArgs {
+                    sort_by: "name".to_string(),
+                })
+                .unwrap();
+                let projects = try_invoke("list_projects", args)
+                    .await
+                    .ok()
+                    .and_then(|value| {
+                        serde_wasm_bindgen::from_value::<Vec<ProjectSummary>>(value).ok()
+                    })
+                    .unwrap_or_default();
+                template_projects.set(
+                    projects
+                        .into_iter()
+                        .filter(|project| project.complete)
+                        .map(|project| project.name)
+                        .collect(),
+                );
+            });
+            || ()
+        });
+    }
+    {
+        let available_layers = available_layers.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let result = invoke_without_args("get_layer_config").await;
+                let mut layers: Vec<String> =
+                    BASE_LAYERS.iter().map(|(name, _)| name.to_string()).collect();
+                if let Ok(config) = serde_wasm_bindgen::from_value::<LayerConfig>(result) {
+                    layers.extend(
+                        config
+                            .layers
+                            .into_iter()
+                            .filter(|layer| layer.enabled)
+                            .map(|layer| layer.name),
+                    );
+                }
+                available_layers.set(layers);
+            });
+            || ()
+        });
+    }
+
+    // Les couches et la source de végétation du modèle remplacent la sélection en cours.
+    let on_template_change = {
+        let template_project = template_project.clone();
+        let vegetation_source = vegetation_source.clone();
+        let available_layers = available_layers.clone();
+        let excluded_layers = excluded_layers.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            let project_name = select.value();
+            if project_name.is_empty() {
+                template_project.set(None);
+                return;
+            }
+            template_project.set(Some(project_name.clone()));
+
+            let vegetation_source = vegetation_source.clone();
+            let available_layers = available_layers.clone();
+            let excluded_layers = excluded_layers.clone();
+            spawn_local(async move {
+                let args =
+                    serde_wasm_bindgen::to_value(&CreationParamsArgs { project_name }).unwrap();
+                let Ok(value) = try_invoke("get_creation_params", args).await else {
+                    return;
+                };
+                let params =
+                    serde_wasm_bindgen::from_value::<CreationParams>(value).unwrap_or_default();
+                vegetation_source.set(params.vegetation_source);
+                excluded_layers.set(match params.layers {
+                    Some(layers) => available_layers
+                        .iter()
+                        .filter(|name| !layers.contains(*name))
+                        .cloned()
+                        .collect(),
+                    None => HashSet::new(),
+                });
+            });
+        })
+    };
+
+    let on_vegetation_source_change = {
+        let vegetation_source = vegetation_source.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            vegetation_source.set(Some(select.value()));
+        })
+    };
+
+    let on_toggle_layer = {
+        let excluded_layers = excluded_layers.clone();
+        Callback::from(move |name: String| {
+            let mut excluded = (*excluded_layers).clone();
+            if !excluded.remove(&name) {
+                excluded.insert(name);
+            }
+            excluded_layers.set(excluded);
+        })
+    };
 
     fn parse_coordinate(s: &str) -> Option<f64> {
         if s.trim().is_empty() {
@@ -83,6 +461,48 @@ pub fn new_project(props: &NewProjectProps) -> Html {
         }
     };
 
+    let valid_bb = match (
+        parse_coordinate(&xmin_str),
+        parse_coordinate(&ymin_str),
+        parse_coordinate(&xmax_str),
+        parse_coordinate(&ymax_str),
+    ) {
+        (Some(xmin), Some(ymin), Some(xmax), Some(ymax)) if is_valid_shape != "invalid" => {
+            Some(BoundingBox {
+                xmin,
+                ymin,
+                xmax,
+                ymax,
+            })
+        }
+        _ => None,
+    };
+
+    // L'estimation est associée à l'emprise demandée, pour ignorer les réponses
+    // arrivant après une nouvelle saisie.
+    let estimate = use_state(|| None::<(BoundingBox, Result<ProjectEstimate, String>)>);
+    {
+        let estimate = estimate.clone();
+        use_effect_with(valid_bb, move |valid_bb| {
+            if let Some(project_bb) = *valid_bb {
+                spawn_local(async move {
+                    let args = EstimateArgs { project_bb };
+                    if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                        let result = match try_invoke("estimate_project", serialized_args).await {
+                            Ok(value) => serde_wasm_bindgen::from_value::<ProjectEstimate>(value)
+                                .map_err(|e| e.to_string()),
+                            Err(e) => Err(e
+                                .as_string()
+                                .unwrap_or_else(|| "Estimation indisponible".to_string())),
+                        };
+                        estimate.set(Some((project_bb, result)));
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
     let create_coordinate_handler = |state: UseStateHandle<String>| {
         Callback::from(move |e: InputEvent| {
             let input: web_sys::HtmlInputElement = e.target_unchecked_into();
@@ -124,15 +544,94 @@ pub fn new_project(props: &NewProjectProps) -> Html {
     let on_xmax_input = create_coordinate_handler(xmax_str.clone());
     let on_ymax_input = create_coordinate_handler(ymax_str.clone());
 
+    let on_snap_mode_change = {
+        let snap_mode = snap_mode.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            snap_mode.set(select.value());
+        })
+    };
+
+    let on_auto_correct = {
+        let validation_errors = validation_errors.clone();
+        let snap_mode = snap_mode.clone();
+        let snap_note = snap_note.clone();
+        let xmin_str = xmin_str.clone();
+        let ymin_str = ymin_str.clone();
+        let xmax_str = xmax_str.clone();
+        let ymax_str = ymax_str.clone();
+
+        Callback::from(move |_: MouseEvent| {
+            let (Some(xmin), Some(ymin), Some(xmax), Some(ymax)) = (
+                parse_coordinate(&xmin_str),
+                parse_coordinate(&ymin_str),
+                parse_coordinate(&xmax_str),
+                parse_coordinate(&ymax_str),
+            ) else {
+                return;
+            };
+            let args = SnapArgs {
+                xmin,
+                ymin,
+                xmax,
+                ymax,
+                mode: (*snap_mode).clone(),
+            };
+            let validation_errors = validation_errors.clone();
+            let snap_note = snap_note.clone();
+            let xmin_str = xmin_str.clone();
+            let ymin_str = ymin_str.clone();
+            let xmax_str = xmax_str.clone();
+            let ymax_str = ymax_str.clone();
+
+            spawn_local(async move {
+                let serialized_args = serde_wasm_bindgen::to_value(&args).unwrap();
+                match try_invoke("suggest_valid_bbox", serialized_args).await {
+                    Ok(value) => match serde_wasm_bindgen::from_value::<GridSnap>(value) {
+                        Ok(snap) => {
+                            snap_note.set(Some(format!(
+                                "Emprise corrigée : ouest {:+} m, sud {:+} m, est {:+} m, nord {:+} m",
+                                snap.delta.xmin, snap.delta.ymin, snap.delta.xmax, snap.delta.ymax
+                            )));
+                            xmin_str.set(snap.bbox.xmin.to_string());
+                            ymin_str.set(snap.bbox.ymin.to_string());
+                            xmax_str.set(snap.bbox.xmax.to_string());
+                            ymax_str.set(snap.bbox.ymax.to_string());
+                            validation_errors.set(Vec::new());
+                        }
+                        Err(e) => validation_errors.set(vec![e.to_string()]),
+                    },
+                    Err(e) => validation_errors
+                        .set(vec![e.as_string().unwrap_or_else(|| {
+                            "Impossible de corriger l'emprise".to_string()
+                        })]),
+                }
+            });
+        })
+    };
+
+    let can_auto_correct = is_valid_shape == "invalid"
+        && [&xmin_str, &ymin_str, &xmax_str, &ymax_str]
+            .iter()
+            .all(|value| parse_coordinate(value).is_some());
+
     let on_submit = {
         let is_loading = is_loading.clone();
+        let submitting = submitting.clone();
         let validation_errors = validation_errors.clone();
+        let conflict = conflict.clone();
         let on_view_change = props.on_view_change.clone();
         let project_name = project_name.clone();
         let xmin_str = xmin_str.clone();
         let ymin_str = ymin_str.clone();
         let xmax_str = xmax_str.clone();
         let ymax_str = ymax_str.clone();
+        let available_layers = available_layers.clone();
+        let excluded_layers = excluded_layers.clone();
+        let estimate = estimate.clone();
+        let allow_oversize = allow_oversize.clone();
+        let template_project = template_project.clone();
+        let vegetation_source = vegetation_source.clone();
 
         Callback::from(move |e: SubmitEvent| {
             e.prevent_default();
@@ -179,46 +678,109 @@ pub fn new_project(props: &NewProjectProps) -> Html {
                 }
             }
 
+            // La création refuserait un projet trop grand sans dérogation.
+            if let Some((_, Ok(estimate))) = &*estimate {
+                if let (Some(size_error), false) = (&estimate.size_error, *allow_oversize) {
+                    errors.push(format!("Projet trop grand : {}", size_error));
+                }
+            }
+
             if !errors.is_empty() {
                 validation_errors.set(errors.clone());
                 return;
             }
 
+            if submitting.replace(true) {
+                return;
+            }
             validation_errors.set(Vec::new());
             is_loading.set(true);
 
-            let args = NewProjectArgs {
+            let args = CreateProjectArgs {
                 name: (*project_name).clone(),
-                project_bb: ProjectBoundingBox {
+                project_bb: BoundingBox {
                     xmin: xmin.unwrap(),
                     ymin: ymin.unwrap(),
                     xmax: xmax.unwrap(),
                     ymax: ymax.unwrap(),
                 },
+                vegetation_source: (*vegetation_source).clone(),
+                on_conflict: Some(ConflictMode::Abort),
+                // Avec un modèle, la sélection affichée est toujours transmise : sinon les
+                // couches du modèle s'appliqueraient même toutes cochées.
+                layers: match &*template_project {
+                    Some(_) => Some(
+                        available_layers
+                            .iter()
+                            .filter(|name| !excluded_layers.contains(*name))
+                            .cloned()
+                            .collect(),
+                    ),
+                    None => selected_layers(&available_layers, &excluded_layers),
+                },
+                allow_reuse: None,
+                allow_oversize: Some(*allow_oversize),
+                template_project: (*template_project).clone(),
             };
 
-            let project_name_clone = (*project_name).clone();
             let on_view_change = on_view_change.clone();
             let is_loading = is_loading.clone();
-            let validation_errors = validation_errors.clone();
-
-            on_view_change.emit(AppView::Loading(project_name_clone.clone()));
+            let submitting = submitting.clone();
+            let conflict = conflict.clone();
 
+            // Un projet du même nom est signalé avant de quitter le formulaire.
             spawn_local(async move {
-                let serialized_args = serde_wasm_bindgen::to_value(&args).unwrap();
-                let result = invoke("create_project_com", serialized_args).await;
-
-                if let Err(e) = serde_wasm_bindgen::from_value::<()>(result) {
-                    web_sys::console::log_1(&format!("Error: {:?}", e).into());
-                    validation_errors.set(vec![
-                        "Une erreur est survenue lors de la création du projet".to_string(),
-                    ]);
-                    is_loading.set(false);
+                let check_args = serde_wasm_bindgen::to_value(&ConflictArgs {
+                    name: args.name.clone(),
+                })
+                .unwrap();
+                let existing = match try_invoke("check_project_conflict", check_args).await {
+                    Ok(value) => serde_wasm_bindgen::from_value::<Option<ProjectConflict>>(value)
+                        .unwrap_or(None),
+                    Err(_) => None,
+                };
+
+                match existing {
+                    Some(existing) => {
+                        is_loading.set(false);
+                        *submitting.borrow_mut() = false;
+                        conflict.set(Some((existing, args)));
+                    }
+                    None => {
+                        let display_name = args.name.clone();
+                        start_creation(args, display_name, on_view_change);
+                    }
                 }
             });
         })
     };
 
+    let on_conflict_choice = |mode: ConflictMode| {
+        let conflict = conflict.clone();
+        let on_view_change = props.on_view_change.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some((existing, args)) = (*conflict).clone() else {
+                return;
+            };
+            conflict.set(None);
+            let display_name = if mode == ConflictMode::Rename {
+                existing.suggested_name
+            } else {
+                args.name.clone()
+            };
+            let args = CreateProjectArgs {
+                on_conflict: Some(mode),
+                ..args
+            };
+            start_creation(args, display_name, on_view_change.clone());
+        })
+    };
+
+    let on_conflict_abort = {
+        let conflict = conflict.clone();
+        Callback::from(move |_: MouseEvent| conflict.set(None))
+    };
+
     html! {
         <div class="new-project-view">
             <h2>{"Créer un nouveau projet"}</h2>
@@ -245,6 +807,23 @@ pub fn new_project(props: &NewProjectProps) -> Html {
                     />
                 </div>
 
+                if !template_projects.is_empty() {
+                    <div class="form-group">
+                        <label for="template-project">{"Dupliquer les paramètres de..."}</label>
+                        <select id="template-project" onchange={on_template_change}>
+                            <option value="" selected={template_project.is_none()}>{"Aucun projet"}</option>
+                            {for template_projects.iter().map(|name| html! {
+                                <option
+                                    value={name.clone()}
+                                    selected={template_project.as_deref() == Some(name.as_str())}
+                                >
+                                    {name}
+                                </option>
+                            })}
+                        </select>
+                    </div>
+                }
+
                 <div class="form-group">
                     <label>{"Coordonnées"}<span class="required">{"*"}</span></label>
                     <div class="coordinates-cross">
@@ -320,10 +899,128 @@ pub fn new_project(props: &NewProjectProps) -> Html {
                     </div>
                     <div class="coordinate-note">
                         <p>{"Note : Les dimensions de la zone (largeur et hauteur) doivent être des multiples de 500"}</p>
+                        if can_auto_correct {
+                            <div class="auto-correct">
+                                <select onchange={on_snap_mode_change}>
+                                    <option value="expand" selected={*snap_mode == "expand"}>{"Agrandir"}</option>
+                                    <option value="shrink" selected={*snap_mode == "shrink"}>{"Réduire"}</option>
+                                    <option value="center" selected={*snap_mode == "center"}>{"Arrondir autour du centre"}</option>
+                                </select>
+                                <button type="button" class="auto-correct-btn" onclick={on_auto_correct}>
+                                    {"Corriger automatiquement"}
+                                </button>
+                            </div>
+                        }
+                        if let Some(note) = &*snap_note {
+                            <p class="snap-note">{note}</p>
+                        }
                         <p>{"Le système déterminera automatiquement les régions qui intersectent cette zone."}</p>
                     </div>
                 </div>
 
+                <div class="form-group">
+                    <label for="vegetation-source">{"Source de végétation"}</label>
+                    <select id="vegetation-source" onchange={on_vegetation_source_change}>
+                        <option
+                            value="bdforet"
+                            selected={matches!(vegetation_source.as_deref(), None | Some("bdforet"))}
+                        >
+                            {"BD Forêt"}
+                        </option>
+                        <option
+                            value="oso"
+                            selected={vegetation_source.as_deref() == Some("oso")}
+                            disabled={!*oso_available}
+                            title={(!*oso_available).then_some("Renseignez l'URL du GeoTIFF OSO dans les paramètres")}
+                        >
+                            {"Occupation des sols (OSO)"}
+                        </option>
+                        // Une source fichier ne vient que d'un modèle : elle reste sélectionnable.
+                        if let Some(source) = vegetation_source.as_deref().filter(|source| source.starts_with("file:")) {
+                            <option value={source.to_string()} selected=true>{source}</option>
+                        }
+                    </select>
+                </div>
+
+                <details class="form-group advanced-layers">
+                    <summary>{"Couches avancées"}</summary>
+                    <p class="advanced-layers-note">
+                        {"Les archives dont aucune couche n'est cochée ne sont pas téléchargées."}
+                    </p>
+                    <div class="advanced-layers-list">
+                        {for available_layers.iter().map(|name| {
+                            let label = BASE_LAYERS
+                                .iter()
+                                .find(|(key, _)| key == name)
+                                .map(|(_, label)| label.to_string())
+                                .unwrap_or_else(|| name.clone());
+                            let on_toggle_layer = on_toggle_layer.clone();
+                            let toggled = name.clone();
+                            html! {
+                                <label class="advanced-layer">
+                                    <input
+                                        type="checkbox"
+                                        checked={!excluded_layers.contains(name)}
+                                        onchange={Callback::from(move |_| on_toggle_layer.emit(toggled.clone()))}
+                                    />
+                                    {label}
+                                </label>
+                            }
+                        })}
+                    </div>
+                </details>
+
+                if let Some(project_bb) = valid_bb {
+                    <div class="project-estimate">
+                        <h3>{"Estimation"}</h3>
+                        {
+                            match &*estimate {
+                                Some((estimated_bb, Ok(estimate))) if *estimated_bb == project_bb => {
+                                    let allow_oversize = allow_oversize.clone();
+                                    html! {
+                                        <>
+                                        {estimate_summary(estimate)}
+                                        if estimate.size_error.is_some() {
+                                            <label class="oversize-override">
+                                                <input
+                                                    type="checkbox"
+                                                    checked={*allow_oversize}
+                                                    onchange={Callback::from(move |_| allow_oversize.set(!*allow_oversize))}
+                                                />
+                                                {"Créer malgré tout (utilisateurs avancés)"}
+                                            </label>
+                                        }
+                                        </>
+                                    }
+                                }
+                                Some((estimated_bb, Err(error))) if *estimated_bb == project_bb => {
+                                    html! { <p class="error-message">{error}</p> }
+                                }
+                                _ => html! { <p>{"Calcul de l'estimation..."}</p> },
+                            }
+                        }
+                    </div>
+                }
+
+                if let Some((existing, _)) = &*conflict {
+                    <div class="conflict-dialog">
+                        <h4>{format!("Un projet nommé « {} » existe déjà", existing.name)}</h4>
+                        {conflict_summary(existing)}
+                        <p>{"Le projet remplacé n'est supprimé qu'une fois le nouveau projet créé."}</p>
+                        <div class="conflict-dialog-actions">
+                            <button type="button" onclick={on_conflict_choice(ConflictMode::Overwrite)} class="delete-btn">
+                                {"Remplacer"}
+                            </button>
+                            <button type="button" onclick={on_conflict_choice(ConflictMode::Rename)} class="export-btn">
+                                {format!("Créer « {} »", existing.suggested_name)}
+                            </button>
+                            <button type="button" onclick={on_conflict_abort} class="return-btn">
+                                {"Annuler"}
+                            </button>
+                        </div>
+                    </div>
+                }
+
                 <button
                     type="submit"
                     disabled={*is_loading}
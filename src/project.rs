@@ -1,17 +1,101 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
+use crate::legend::Legend;
+use crate::new_project::format_bytes;
+use crate::payloads::{CommandArgs, ExportArgs, ExportOptions, ExportTarget};
+use crate::tile_map::TileMap;
 use crate::types::{AppView, ProjectData, ViewMode};
 
 #[wasm_bindgen]
 extern "C" {
-    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"])]
-    async fn invoke(cmd: &str, args: JsValue) -> JsValue;
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
+    async fn invoke_without_args(cmd: &str) -> JsValue;
 
-    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"])]
-    fn convertFileSrc(filePath: &str, protocol: Option<&str>) -> String;
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke, catch)]
+    async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "dialog"])]
+    async fn open(args: JsValue) -> JsValue;
+}
+
+#[derive(Serialize)]
+struct DialogOptions {
+    directory: bool,
+    title: String,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct ExportTargetOption {
+    target: ExportTarget,
+    label: String,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct ExportReport {
+    zip_path: String,
+    files: Vec<String>,
+    total_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct IdentifyArgs {
+    project_name: String,
+    px: u32,
+    py: u32,
+}
+
+/// Contenu d'un pixel du projet, renvoyé par `identify_pixel`.
+#[derive(Clone, PartialEq, Deserialize)]
+struct PixelInfo {
+    px: u32,
+    py: u32,
+    x: f64,
+    y: f64,
+    epsg: u32,
+    rgba: [u8; 4],
+    class_code: Option<u8>,
+    label_fr: String,
+    in_zone: bool,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct ProjectDetails {
+    created_at: Option<String>,
+    vintage_summary: String,
+    /// Orthophoto non téléchargée à la création hors ligne : seule la vue végétation est disponible.
+    #[serde(default)]
+    ortho_skipped: bool,
+}
+
+#[derive(Serialize)]
+struct CustomLayerArgs {
+    project_name: String,
+    file_path: String,
+    color: [u8; 3],
+    priority: u8,
+    filter: Option<String>,
+}
+
+/// Priorités proposées pour une couche personnalisée : elle recouvre les couches
+/// du projet de priorité inférieure ou égale (voir `legend::PRIORITY_*`).
+const LAYER_PRIORITIES: [(u8, &str); 4] = [
+    (1, "Au-dessus de la végétation"),
+    (2, "Au-dessus des parcelles agricoles"),
+    (3, "Au-dessus de l'hydrographie"),
+    (4, "Au-dessus de toutes les couches"),
+];
+
+/// Couleur `#rrggbb` d'un champ de saisie de couleur.
+fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
 }
 
 #[derive(Properties, PartialEq)]
@@ -26,12 +110,8 @@ pub fn project(props: &ProjectProps) -> Html {
     let view_mode = project_data.view_mode.clone();
     let project_name = project_data.name.clone();
 
-    let file_path = match view_mode {
-        ViewMode::Vegetation => format!("projects/{}/{}_VEGET.jpeg", project_name, project_name),
-        ViewMode::Satellite => format!("projects/{}/{}_ORTHO.jpeg", project_name, project_name),
-    };
-
-    let image_path = convertFileSrc(&file_path, None);
+    // Incrémenté après la mise à jour de l'orthophoto, pour recharger les tuiles affichées.
+    let image_version = use_state(|| 0u32);
 
     let on_toggle_view = {
         let project_data = project_data.clone();
@@ -53,57 +133,524 @@ pub fn project(props: &ProjectProps) -> Html {
     };
 
     #[derive(Serialize)]
-    struct ExportArgs {
+    struct ProjectArgs {
         project_name: String,
     }
 
+    #[derive(Serialize)]
+    struct MapPdfArgs {
+        project_name: String,
+        view: ViewMode,
+    }
+
+    let export_targets = use_state(Vec::<ExportTargetOption>::new);
+    let export_target = use_state(ExportTarget::default);
+
+    {
+        let export_targets = export_targets.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let result = invoke_without_args("list_export_targets").await;
+                if let Ok(targets) =
+                    serde_wasm_bindgen::from_value::<Vec<ExportTargetOption>>(result)
+                {
+                    export_targets.set(targets);
+                }
+            });
+            || ()
+        });
+    }
+
+    let details = use_state(|| None::<ProjectDetails>);
+
+    {
+        let details = details.clone();
+        let project_name = project_name.clone();
+        use_effect_with(project_name.clone(), move |_| {
+            spawn_local(async move {
+                let args = ProjectArgs { project_name };
+                if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                    match try_invoke("get_project_details", serialized_args).await {
+                        Ok(result) => {
+                            if let Ok(info) =
+                                serde_wasm_bindgen::from_value::<ProjectDetails>(result)
+                            {
+                                details.set(Some(info));
+                            }
+                        }
+                        Err(e) => web_sys::console::error_1(&e),
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    let on_target_change = {
+        let export_target = export_target.clone();
+        let export_targets = export_targets.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let Some(option) = usize::try_from(select.selected_index())
+                .ok()
+                .and_then(|index| export_targets.get(index))
+            {
+                export_target.set(option.target);
+            }
+        })
+    };
+
+    #[derive(Serialize)]
+    struct RevealArgs {
+        path: String,
+    }
+
+    let export_path = use_state(|| None::<String>);
+    let export_size = use_state(|| None::<(usize, u64)>);
+    let show_export_dialog = use_state(|| false);
+    let include_resources = use_state(|| false);
+    let include_full_images = use_state(|| false);
+    let include_qgis_project = use_state(|| false);
+
+    let on_open_export_dialog = {
+        let show_export_dialog = show_export_dialog.clone();
+        Callback::from(move |_: MouseEvent| show_export_dialog.set(true))
+    };
+
+    let on_cancel_export = {
+        let show_export_dialog = show_export_dialog.clone();
+        Callback::from(move |_: MouseEvent| show_export_dialog.set(false))
+    };
+
+    let on_checkbox_toggle = |state: &UseStateHandle<bool>| {
+        let state = state.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            state.set(input.checked());
+        })
+    };
+
     let on_export = {
         let project_name = project_data.name.clone();
+        let export_path = export_path.clone();
+        let export_size = export_size.clone();
+        let export_target = export_target.clone();
+        let show_export_dialog = show_export_dialog.clone();
+        let include_resources = include_resources.clone();
+        let include_full_images = include_full_images.clone();
+        let include_qgis_project = include_qgis_project.clone();
         Callback::from(move |_: MouseEvent| {
             let project_name = project_name.clone();
+            let export_path = export_path.clone();
+            let export_size = export_size.clone();
+            let target = *export_target;
+            show_export_dialog.set(false);
+            let include_resources = *include_resources;
+            let include_full_images = *include_full_images;
+            let include_qgis_project = *include_qgis_project;
             spawn_local(async move {
-                let args = ExportArgs {
+                let mut args = ExportArgs {
                     project_name: project_name.clone(),
+                    target,
+                    override_output: None,
+                    options: ExportOptions {
+                        include_resources,
+                        include_full_images,
+                        include_qgis_project,
+                    },
                 };
-                if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
-                    if let Some(result) = invoke("export", serialized_args).await.as_string() {
-                        match result.as_str() {
-                            "success" => {
-                                web_sys::window()
-                                    .unwrap()
-                                    .alert_with_message("Exportation réussie")
-                                    .unwrap();
+                loop {
+                    let Ok(serialized_args) =
+                        serde_wasm_bindgen::to_value(&CommandArgs { args: args.clone() })
+                    else {
+                        break;
+                    };
+                    match try_invoke("export", serialized_args).await {
+                        Ok(result) => {
+                            match serde_wasm_bindgen::from_value::<ExportReport>(result) {
+                                Ok(report) => {
+                                    export_size.set(Some((report.files.len(), report.total_bytes)));
+                                    export_path.set(Some(report.zip_path));
+                                }
+                                Err(e) => web_sys::console::error_1(
+                                    &format!("Réponse d'export invalide: {:?}", e).into(),
+                                ),
                             }
-                            "error" => {
-                                web_sys::window()
-                                    .unwrap()
-                                    .alert_with_message("Erreur lors de l'exportation")
-                                    .unwrap();
+                        }
+                        Err(e) => {
+                            let message = e
+                                .as_string()
+                                .unwrap_or_else(|| "Erreur lors de l'exportation".to_string());
+                            let window = web_sys::window().unwrap();
+                            // Dossier d'export inutilisable : proposer d'en choisir un autre.
+                            if message.contains("Dossier d'export")
+                                && window
+                                    .confirm_with_message(&format!(
+                                        "{}\n\nChoisir un autre dossier pour cet export ?",
+                                        message
+                                    ))
+                                    .unwrap_or(false)
+                            {
+                                let options = DialogOptions {
+                                    directory: true,
+                                    title: String::from("Sélectionner un dossier d'export"),
+                                };
+                                if let Ok(options) = serde_wasm_bindgen::to_value(&options) {
+                                    if let Some(folder) = open(options).await.as_string() {
+                                        args.override_output = Some(folder);
+                                        continue;
+                                    }
+                                }
+                            } else {
+                                window.alert_with_message(&message).unwrap();
+                            }
+                        }
+                    }
+                    break;
+                }
+            });
+        })
+    };
+
+    let on_export_pdf = {
+        let project_name = project_data.name.clone();
+        let view_mode = project_data.view_mode.clone();
+        let export_path = export_path.clone();
+        let export_size = export_size.clone();
+        Callback::from(move |_: MouseEvent| {
+            let args = MapPdfArgs {
+                project_name: project_name.clone(),
+                view: view_mode.clone(),
+            };
+            let export_path = export_path.clone();
+            let export_size = export_size.clone();
+            spawn_local(async move {
+                if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                    match try_invoke("export_map_pdf", serialized_args).await {
+                        Ok(result) => {
+                            export_size.set(None);
+                            export_path.set(result.as_string());
+                        }
+                        Err(e) => {
+                            let message = e.as_string().unwrap_or_else(|| {
+                                "Erreur lors de l'export de la carte PDF".to_string()
+                            });
+                            web_sys::window()
+                                .unwrap()
+                                .alert_with_message(&message)
+                                .unwrap();
+                        }
+                    }
+                }
+            });
+        })
+    };
+
+    let on_reveal_export = {
+        let export_path = export_path.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(path) = (*export_path).clone() {
+                spawn_local(async move {
+                    let args = RevealArgs { path };
+                    if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                        if let Err(e) = try_invoke("reveal_export", serialized_args).await {
+                            web_sys::window()
+                                .unwrap()
+                                .alert_with_message(&e.as_string().unwrap_or_default())
+                                .unwrap();
+                        }
+                    }
+                });
+            }
+        })
+    };
+
+    let on_close_toast = {
+        let export_path = export_path.clone();
+        Callback::from(move |_: MouseEvent| export_path.set(None))
+    };
+
+    let on_open_folder = {
+        let project_name = project_data.name.clone();
+        Callback::from(move |_: MouseEvent| {
+            let project_name = project_name.clone();
+            spawn_local(async move {
+                let args = ProjectArgs { project_name };
+                if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                    if let Err(e) = try_invoke("open_project_folder", serialized_args).await {
+                        web_sys::window()
+                            .unwrap()
+                            .alert_with_message(&e.as_string().unwrap_or_default())
+                            .unwrap();
+                    }
+                }
+            });
+        })
+    };
+
+    let show_legend = use_state(|| false);
+
+    let on_toggle_legend = {
+        let show_legend = show_legend.clone();
+        Callback::from(move |_: MouseEvent| show_legend.set(!*show_legend))
+    };
+
+    let pixel_info = use_state(|| Option::<PixelInfo>::None);
+
+    let on_pixel_click = {
+        let project_name = project_data.name.clone();
+        let pixel_info = pixel_info.clone();
+        Callback::from(move |(px, py): (u32, u32)| {
+            let pixel_info = pixel_info.clone();
+            let args = IdentifyArgs {
+                project_name: project_name.clone(),
+                px,
+                py,
+            };
+            spawn_local(async move {
+                let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) else {
+                    return;
+                };
+                match try_invoke("identify_pixel", serialized_args).await {
+                    Ok(result) => {
+                        pixel_info.set(serde_wasm_bindgen::from_value::<PixelInfo>(result).ok())
+                    }
+                    Err(_) => pixel_info.set(None),
+                }
+            });
+        })
+    };
+
+    let on_close_pixel_info = {
+        let pixel_info = pixel_info.clone();
+        Callback::from(move |_: MouseEvent| pixel_info.set(None))
+    };
+
+    let refreshing_ortho = use_state(|| false);
+
+    let on_refresh_ortho = {
+        let project_name = project_data.name.clone();
+        let refreshing_ortho = refreshing_ortho.clone();
+        let image_version = image_version.clone();
+        let details = details.clone();
+        Callback::from(move |_: MouseEvent| {
+            let window = web_sys::window().unwrap();
+            if !window
+                .confirm_with_message(
+                    "Télécharger à nouveau l'orthophoto du projet ? Le découpage ORTHO sera refait au prochain export.",
+                )
+                .unwrap_or(false)
+            {
+                return;
+            }
+            let project_name = project_name.clone();
+            let refreshing_ortho = refreshing_ortho.clone();
+            let image_version = image_version.clone();
+            let details = details.clone();
+            refreshing_ortho.set(true);
+            spawn_local(async move {
+                let args = ProjectArgs { project_name };
+                if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                    match try_invoke("refresh_ortho", serialized_args).await {
+                        Ok(_) => {
+                            image_version.set(*image_version + 1);
+                            if let Some(mut info) = (*details).clone() {
+                                info.ortho_skipped = false;
+                                details.set(Some(info));
                             }
-                            _ => {}
+                        }
+                        Err(e) => {
+                            let message = e.as_string().unwrap_or_else(|| {
+                                "Erreur lors de la mise à jour de l'orthophoto".to_string()
+                            });
+                            web_sys::window()
+                                .unwrap()
+                                .alert_with_message(&message)
+                                .unwrap();
                         }
                     }
                 }
+                refreshing_ortho.set(false);
             });
         })
     };
 
+    let show_layer_dialog = use_state(|| false);
+    let layer_color = use_state(|| "#ff8c00".to_string());
+    let layer_priority = use_state(|| 4u8);
+    let layer_filter = use_state(String::new);
+    let adding_layer = use_state(|| false);
+
+    let on_toggle_layer_dialog = {
+        let show_layer_dialog = show_layer_dialog.clone();
+        Callback::from(move |_: MouseEvent| show_layer_dialog.set(!*show_layer_dialog))
+    };
+
+    let on_layer_color_change = {
+        let layer_color = layer_color.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            layer_color.set(input.value());
+        })
+    };
+
+    let on_layer_priority_change = {
+        let layer_priority = layer_priority.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            layer_priority.set(select.value().parse().unwrap_or(4));
+        })
+    };
+
+    let on_layer_filter_change = {
+        let layer_filter = layer_filter.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            layer_filter.set(input.value());
+        })
+    };
+
+    let on_add_layer = {
+        let project_name = project_data.name.clone();
+        let layer_filter = layer_filter.clone();
+        let layer_color = layer_color.clone();
+        let layer_priority = layer_priority.clone();
+        let adding_layer = adding_layer.clone();
+        let show_layer_dialog = show_layer_dialog.clone();
+        let image_version = image_version.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some(color) = parse_hex_color(&layer_color) else {
+                return;
+            };
+            let project_name = project_name.clone();
+            let priority = *layer_priority;
+            let filter = Some(layer_filter.trim().to_string()).filter(|filter| !filter.is_empty());
+            let adding_layer = adding_layer.clone();
+            let show_layer_dialog = show_layer_dialog.clone();
+            let image_version = image_version.clone();
+            spawn_local(async move {
+                let options = DialogOptions {
+                    directory: false,
+                    title: String::from("Sélectionner une couche (GeoPackage, Shapefile...)"),
+                };
+                let Ok(options) = serde_wasm_bindgen::to_value(&options) else {
+                    return;
+                };
+                let Some(file_path) = open(options).await.as_string() else {
+                    return;
+                };
+                adding_layer.set(true);
+                let args = CustomLayerArgs {
+                    project_name,
+                    file_path,
+                    color,
+                    priority,
+                    filter,
+                };
+                if let Ok(serialized_args) = serde_wasm_bindgen::to_value(&args) {
+                    match try_invoke("add_custom_layer", serialized_args).await {
+                        Ok(_) => {
+                            image_version.set(*image_version + 1);
+                            show_layer_dialog.set(false);
+                        }
+                        Err(e) => {
+                            let message = e.as_string().unwrap_or_else(|| {
+                                "Erreur lors de l'ajout de la couche".to_string()
+                            });
+                            web_sys::window()
+                                .unwrap()
+                                .alert_with_message(&message)
+                                .unwrap();
+                        }
+                    }
+                }
+                adding_layer.set(false);
+            });
+        })
+    };
+
+    // Projet créé hors ligne : la vue satellite reste indisponible jusqu'à la mise à jour
+    // de l'orthophoto.
+    let ortho_missing = (*details).as_ref().is_some_and(|info| info.ortho_skipped);
+
     html! {
         <div class="project-view">
             <div class="project-sidebar">
                 <h3>{&project_data.name}</h3>
 
-                <button onclick={on_toggle_view.clone()} class="view-toggle-btn">
+                if let Some(info) = (*details).clone() {
+                    <div class="project-details">
+                        if let Some(created_at) = info.created_at.as_deref() {
+                            <p>{format!("Créé le {}", created_at.get(..10).unwrap_or(created_at))}</p>
+                        }
+                        if !info.vintage_summary.is_empty() {
+                            <p>{format!("Données : {}", info.vintage_summary)}</p>
+                        }
+                        if info.ortho_skipped {
+                            <p class="diag-warning">
+                                {"Projet créé hors ligne : l'orthophoto n'a pas été téléchargée. Utilisez « Mettre à jour l'orthophoto » une fois la connexion rétablie."}
+                            </p>
+                        }
+                    </div>
+                }
+
+                <button
+                    onclick={on_toggle_view.clone()}
+                    class="view-toggle-btn"
+                    disabled={ortho_missing && project_data.view_mode == ViewMode::Vegetation}
+                >
                     { match project_data.view_mode {
                         ViewMode::Vegetation => "Passer à la vue satellite",
                         ViewMode::Satellite => "Passer à la vue végétation",
                     }}
                 </button>
 
-                <button onclick={on_export.clone()} class="export-btn">
+                if project_data.view_mode == ViewMode::Vegetation {
+                    <button onclick={on_toggle_legend} class="legend-toggle-btn">
+                        { if *show_legend { "Masquer la légende" } else { "Afficher la légende" } }
+                    </button>
+                }
+
+                <select class="export-target-select" onchange={on_target_change}>
+                    { for export_targets.iter().map(|option| html! {
+                        <option selected={option.target == *export_target}>
+                            {&option.label}
+                        </option>
+                    })}
+                </select>
+
+                <button onclick={on_open_export_dialog} class="export-btn">
                     {"Exporter"}
                 </button>
 
+                <button onclick={on_export_pdf.clone()} class="export-pdf-btn">
+                    {"Exporter la carte (PDF)"}
+                </button>
+
+                <button
+                    onclick={on_refresh_ortho}
+                    class="refresh-ortho-btn"
+                    disabled={*refreshing_ortho}
+                >
+                    { if *refreshing_ortho {
+                        "Mise à jour de l'orthophoto..."
+                    } else {
+                        "Mettre à jour l'orthophoto"
+                    }}
+                </button>
+
+                <button
+                    onclick={on_toggle_layer_dialog.clone()}
+                    class="add-layer-btn"
+                    disabled={*adding_layer}
+                >
+                    { if *adding_layer { "Ajout de la couche..." } else { "Ajouter une couche" } }
+                </button>
+
+                <button onclick={on_open_folder.clone()} class="open-folder-btn">
+                    {"Ouvrir le dossier du projet"}
+                </button>
+
                 <button onclick={on_return.clone()} class="return-btn">
                     {"Retour à l'accueil"}
                 </button>
@@ -111,9 +658,131 @@ pub fn project(props: &ProjectProps) -> Html {
 
             <div class="project-content">
                 <div class="map-container">
-                    <img src={image_path.clone()} alt={format!("Vue cartographique de {}", project_data.name)} />
+                    <TileMap
+                        project_name={project_name.clone()}
+                        view_mode={view_mode.clone()}
+                        version={*image_version}
+                        on_pixel_click={on_pixel_click}
+                    />
+                    if let Some(info) = (*pixel_info).clone() {
+                        <div class="pixel-info">
+                            <button onclick={on_close_pixel_info} class="toast-close">{"×"}</button>
+                            <h4>
+                                <span
+                                    class="pixel-swatch"
+                                    style={format!("background-color: rgb({}, {}, {})", info.rgba[0], info.rgba[1], info.rgba[2])}
+                                />
+                                { info.label_fr.clone() }
+                            </h4>
+                            <p>{ format!("Pixel ({}, {})", info.px, info.py) }</p>
+                            <p>{ format!("X {:.1} m, Y {:.1} m (EPSG:{})", info.x, info.y, info.epsg) }</p>
+                            if info.in_zone {
+                                <p>
+                                    { format!("RVB {}, {}, {}", info.rgba[0], info.rgba[1], info.rgba[2]) }
+                                    { info.class_code.map(|code| format!(" — classe {}", code)).unwrap_or_default() }
+                                </p>
+                            }
+                        </div>
+                    }
+                    if *show_legend && project_data.view_mode == ViewMode::Vegetation {
+                        <div class="legend-overlay">
+                            <h4>{"Légende"}</h4>
+                            <Legend project_name={Some(project_data.name.clone())} />
+                        </div>
+                    }
                 </div>
             </div>
+
+            if *show_export_dialog {
+                <div class="export-dialog">
+                    <h4>{"Options d'export"}</h4>
+                    if *export_target == ExportTarget::Zip {
+                        <label>
+                            <input
+                                type="checkbox"
+                                checked={*include_resources}
+                                onchange={on_checkbox_toggle(&include_resources)}
+                            />
+                            {"Inclure les ressources (GeoPackages)"}
+                        </label>
+                        <label>
+                            <input
+                                type="checkbox"
+                                checked={*include_full_images}
+                                onchange={on_checkbox_toggle(&include_full_images)}
+                            />
+                            {"Inclure l'image complète (GeoTIFF)"}
+                        </label>
+                        <label>
+                            <input
+                                type="checkbox"
+                                checked={*include_qgis_project}
+                                onchange={on_checkbox_toggle(&include_qgis_project)}
+                            />
+                            {"Inclure un projet QGIS"}
+                        </label>
+                    } else {
+                        <p>{"Export des tuiles et du manifeste du simulateur."}</p>
+                    }
+                    <div class="export-dialog-actions">
+                        <button onclick={on_export.clone()} class="export-btn">{"Exporter"}</button>
+                        <button onclick={on_cancel_export} class="return-btn">{"Annuler"}</button>
+                    </div>
+                </div>
+            }
+
+            if *show_layer_dialog {
+                <div class="export-dialog custom-layer-dialog">
+                    <h4>{"Ajouter une couche"}</h4>
+                    <label>
+                        {"Couleur "}
+                        <input type="color" value={(*layer_color).clone()} onchange={on_layer_color_change} />
+                    </label>
+                    <label>
+                        {"Priorité "}
+                        <select onchange={on_layer_priority_change}>
+                            { for LAYER_PRIORITIES.iter().map(|(priority, label)| html! {
+                                <option
+                                    value={priority.to_string()}
+                                    selected={*priority == *layer_priority}
+                                >
+                                    {label}
+                                </option>
+                            })}
+                        </select>
+                    </label>
+                    <label>
+                        {"Filtre "}
+                        <input
+                            type="text"
+                            placeholder="NATURE = 'Coupe-feu'"
+                            value={(*layer_filter).clone()}
+                            oninput={on_layer_filter_change}
+                        />
+                    </label>
+                    <div class="export-dialog-actions">
+                        <button onclick={on_add_layer} class="export-btn" disabled={*adding_layer}>
+                            {"Choisir le fichier"}
+                        </button>
+                        <button onclick={on_toggle_layer_dialog} class="return-btn">{"Annuler"}</button>
+                    </div>
+                </div>
+            }
+
+            if let Some(path) = (*export_path).clone() {
+                <div class="toast">
+                    <span class="toast-message">
+                        { match *export_size {
+                            Some((files, size)) => format!("Exportation réussie : {} ({} fichiers, {})", path, files, format_bytes(size)),
+                            None => format!("Exportation réussie : {}", path),
+                        }}
+                    </span>
+                    <button onclick={on_reveal_export.clone()} class="toast-action">
+                        {"Afficher dans le dossier"}
+                    </button>
+                    <button onclick={on_close_toast.clone()} class="toast-close">{"×"}</button>
+                </div>
+            }
         </div>
     }
 }
@@ -1,6 +1,9 @@
 use gloo_utils::format::JsValueSerdeExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::new_project::format_bytes;
+use crate::payloads::{ImageQuality, SettingsPayload, TiffCompression};
+use crate::types::DataUpdateStatus;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::{console, window};
@@ -14,6 +17,9 @@ extern "C" {
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
     async fn invoke_with_args(cmd: &str, args: JsValue) -> JsValue;
 
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke, catch)]
+    async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "dialog"])]
     async fn open(args: JsValue) -> JsValue;
 }
@@ -26,14 +32,220 @@ struct DialogOptions {
     title: String,
 }
 
+#[derive(Clone, PartialEq, Deserialize)]
+struct BinaryStatus {
+    name: String,
+    found: bool,
+    path: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct DirectoryStatus {
+    path: String,
+    writable: bool,
+    free_space_bytes: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct NetworkProbe {
+    reachable: bool,
+    latency_ms: Option<u64>,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct DiagnosticsReport {
+    gdal_version: String,
+    #[serde(default)]
+    gdal_warning: Option<String>,
+    binaries: Vec<BinaryStatus>,
+    projects_dir: DirectoryStatus,
+    output_location: DirectoryStatus,
+    regions_geojson_loaded: bool,
+    regions_graph_loaded: bool,
+    network: Option<NetworkProbe>,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct RegionsGraphReport {
+    regions: usize,
+    edges: usize,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+struct CleanupReport {
+    removed: usize,
+    freed_bytes: u64,
+}
+
+/// Compressions du GeoTIFF des projets et leur libellé.
+const TIFF_COMPRESSIONS: [(TiffCompression, &str); 4] = [
+    (TiffCompression::None, "Aucune"),
+    (TiffCompression::Deflate, "Deflate (sans perte)"),
+    (TiffCompression::Lzw, "LZW (sans perte)"),
+    (TiffCompression::JpegInTiff, "JPEG (avec pertes)"),
+];
+
+/// Arguments de `save_settings` : les champs texte, les paramètres numériques
+/// du traitement, la section `image_quality` et le mode hors ligne.
+#[derive(Serialize)]
+struct SettingsArgs<'a> {
+    #[serde(flatten)]
+    fields: HashMap<&'a str, Option<String>>,
+    image_quality: Option<ImageQuality>,
+    resolution: Option<f64>,
+    slice_factor: Option<u32>,
+    offline_mode: Option<bool>,
+}
+
+/// Résultat de `save_settings` : les paramètres du traitement ne sont pas modifiés
+/// tant qu'un projet est en cours de traitement.
+#[derive(Clone, PartialEq, Deserialize)]
+struct SavedSettings {
+    message: String,
+    blocked: Vec<String>,
+}
+
+/// Périmètres de `clear_cache` et leur libellé.
+const CACHE_SCOPES: [(&str, &str); 4] = [
+    ("archives", "Archives téléchargées"),
+    ("prepared", "Couches préparées"),
+    ("wms", "Tuiles WMS"),
+    ("tmp", "Fichiers temporaires"),
+];
+
+/// Résume l'espace libéré par `clear_cache`, au total et par périmètre.
+fn format_cleanup(reports: &BTreeMap<String, CleanupReport>) -> String {
+    let total: u64 = reports.values().map(|report| report.freed_bytes).sum();
+    let details: Vec<String> = CACHE_SCOPES
+        .iter()
+        .filter_map(|(scope, label)| {
+            reports.get(*scope).map(|report| {
+                format!(
+                    "{} : {} ({} élément(s))",
+                    label,
+                    format_bytes(report.freed_bytes),
+                    report.removed
+                )
+            })
+        })
+        .collect();
+    format!(
+        "Cache vidé, {} libérés — {}",
+        format_bytes(total),
+        details.join(", ")
+    )
+}
+
+fn format_free_space(bytes: Option<u64>) -> String {
+    match bytes {
+        Some(bytes) => format!("{:.1} Go libres", bytes as f64 / 1_073_741_824.0),
+        None => "espace libre inconnu".to_string(),
+    }
+}
+
+fn status_icon(ok: bool) -> &'static str {
+    if ok { "✓" } else { "✗" }
+}
+
+fn render_directory_status(label: &str, status: &DirectoryStatus) -> Html {
+    html! {
+        <li class={if status.writable { "diag-ok" } else { "diag-error" }}>
+            {format!(
+                "{} {} : {} ({})",
+                status_icon(status.writable),
+                label,
+                status.path,
+                format_free_space(status.free_space_bytes)
+            )}
+        </li>
+    }
+}
+
+fn render_diagnostics(report: &DiagnosticsReport) -> Html {
+    html! {
+        <div class="diagnostics-report">
+            <h3>{"Diagnostic"}</h3>
+            <p>{format!("Version de GDAL : {}", report.gdal_version)}</p>
+            if let Some(warning) = &report.gdal_warning {
+                <p class="diag-warning">{warning}</p>
+            }
+            <ul>
+                {for report.binaries.iter().map(|binary| html! {
+                    <li class={if binary.found { "diag-ok" } else { "diag-error" }}>
+                        {format!(
+                            "{} {} {}",
+                            status_icon(binary.found),
+                            binary.name,
+                            binary.path.clone().unwrap_or_else(|| "introuvable".to_string())
+                        )}
+                    </li>
+                })}
+                {render_directory_status("Dossier des projets", &report.projects_dir)}
+                {render_directory_status("Emplacement de sortie", &report.output_location)}
+                <li class={if report.regions_geojson_loaded { "diag-ok" } else { "diag-error" }}>
+                    {format!("{} regions.geojson", status_icon(report.regions_geojson_loaded))}
+                </li>
+                <li class={if report.regions_graph_loaded { "diag-ok" } else { "diag-error" }}>
+                    {format!("{} regions_graph.json", status_icon(report.regions_graph_loaded))}
+                </li>
+                {
+                    if let Some(network) = &report.network {
+                        html! {
+                            <li class={if network.reachable { "diag-ok" } else { "diag-error" }}>
+                                {
+                                    match (network.latency_ms, &network.error) {
+                                        (Some(latency), None) => format!("{} geoservices.ign.fr ({} ms)", status_icon(true), latency),
+                                        (_, Some(error)) => format!("{} geoservices.ign.fr : {}", status_icon(false), error),
+                                        _ => format!("{} geoservices.ign.fr", status_icon(network.reachable)),
+                                    }
+                                }
+                            </li>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </ul>
+        </div>
+    }
+}
+
 #[function_component(SettingsComponent)]
 pub fn settings_component() -> Html {
     let os = use_state(|| String::from("Inconnu"));
     let output_location = use_state(String::new);
     let gdal_path = use_state(String::new);
     let python_path = use_state(String::new);
+    let proxy_url = use_state(String::new);
+    let proxy_username = use_state(String::new);
+    let proxy_password = use_state(String::new);
+    let no_proxy = use_state(String::new);
+    let oso_url = use_state(String::new);
+    let ortho_sources = use_state(Vec::<String>::new);
+    let preferred_ortho_source = use_state(String::new);
+    let log_levels = use_state(Vec::<String>::new);
+    let log_level = use_state(String::new);
+    let log_lines = use_state(|| Option::<Vec<String>>::None);
+    let languages = use_state(Vec::<(String, String)>::new);
+    let language = use_state(String::new);
+    let image_quality = use_state(|| Option::<ImageQuality>::None);
+    let resolution = use_state(String::new);
+    let slice_factor = use_state(String::new);
+    let temp_dir = use_state(String::new);
+    let offline_mode = use_state(|| false);
     let app_settings_loaded = use_state(|| false);
     let status_message = use_state(|| Option::<(String, bool)>::None);
+    let cache_scopes = use_state(|| {
+        CACHE_SCOPES
+            .iter()
+            .map(|(scope, _)| scope.to_string())
+            .collect::<BTreeSet<String>>()
+    });
+    let diagnostics = use_state(|| Option::<DiagnosticsReport>::None);
+    let diagnostics_running = use_state(|| false);
+    let data_updates = use_state(DataUpdateStatus::default);
+    let refreshing_cache = use_state(|| false);
 
     {
         let os = os.clone();
@@ -47,10 +259,39 @@ pub fn settings_component() -> Html {
         });
     }
 
+    {
+        let data_updates = data_updates.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let result = invoke_without_args("get_data_update_status").await;
+                if let Ok(status) = result.into_serde::<DataUpdateStatus>() {
+                    data_updates.set(status);
+                }
+            });
+            || ()
+        });
+    }
+
     {
         let output_location = output_location.clone();
         let gdal_path = gdal_path.clone();
         let python_path = python_path.clone();
+        let proxy_url = proxy_url.clone();
+        let proxy_username = proxy_username.clone();
+        let proxy_password = proxy_password.clone();
+        let no_proxy = no_proxy.clone();
+        let oso_url = oso_url.clone();
+        let ortho_sources = ortho_sources.clone();
+        let preferred_ortho_source = preferred_ortho_source.clone();
+        let log_levels = log_levels.clone();
+        let log_level = log_level.clone();
+        let languages = languages.clone();
+        let language = language.clone();
+        let image_quality = image_quality.clone();
+        let resolution = resolution.clone();
+        let slice_factor = slice_factor.clone();
+        let temp_dir = temp_dir.clone();
+        let offline_mode = offline_mode.clone();
         let settings_loaded = app_settings_loaded.clone();
 
         use_effect_with((), move |_| {
@@ -60,32 +301,53 @@ pub fn settings_component() -> Html {
 
                     console::log_1(&format!("Settings response: {:?}", result.as_string()).into());
 
-                    match result.into_serde::<serde_json::Value>() {
+                    match result.into_serde::<SettingsPayload>() {
                         Ok(settings) => {
                             console::log_1(&format!("Parsed settings: {:?}", settings).into());
 
-                            if let Some(output) =
-                                settings.get("output_location").and_then(|v| v.as_str())
-                            {
-                                output_location.set(output.to_string());
+                            output_location.set(settings.output_location);
+                            if let Some(path) = settings.gdal_path {
+                                gdal_path.set(path);
+                            }
+                            if let Some(path) = settings.python_path {
+                                python_path.set(path);
                             }
 
-                            if let Some(gdal) = settings.get("gdal_path") {
-                                if !gdal.is_null() {
-                                    if let Some(path) = gdal.as_str() {
-                                        gdal_path.set(path.to_string());
-                                    }
+                            for (value, state) in [
+                                (settings.proxy_url, &proxy_url),
+                                (settings.proxy_username, &proxy_username),
+                                (settings.proxy_password, &proxy_password),
+                                (settings.no_proxy, &no_proxy),
+                                (settings.oso_url, &oso_url),
+                            ] {
+                                if let Some(value) = value {
+                                    state.set(value);
                                 }
                             }
 
-                            if let Some(python) = settings.get("python_path") {
-                                if !python.is_null() {
-                                    if let Some(path) = python.as_str() {
-                                        python_path.set(path.to_string());
-                                    }
-                                }
+                            ortho_sources.set(settings.ortho_sources);
+                            if let Some(preferred) = settings.preferred_ortho_source {
+                                preferred_ortho_source.set(preferred);
                             }
 
+                            log_levels.set(settings.log_levels);
+                            log_level.set(settings.log_level);
+
+                            languages.set(
+                                settings
+                                    .languages
+                                    .into_iter()
+                                    .map(|entry| (entry.code, entry.label))
+                                    .collect(),
+                            );
+                            language.set(settings.language);
+
+                            image_quality.set(Some(settings.image_quality));
+                            resolution.set(settings.resolution.to_string());
+                            slice_factor.set(settings.slice_factor.to_string());
+                            temp_dir.set(settings.temp_dir);
+                            offline_mode.set(settings.offline_mode);
+
                             settings_loaded.set(true);
                         }
                         Err(e) => web_sys::console::error_1(
@@ -173,16 +435,195 @@ pub fn settings_component() -> Html {
         })
     };
 
+    let on_toggle_cache_scope = {
+        let cache_scopes = cache_scopes.clone();
+        Callback::from(move |scope: String| {
+            let mut scopes = (*cache_scopes).clone();
+            if !scopes.remove(&scope) {
+                scopes.insert(scope);
+            }
+            cache_scopes.set(scopes);
+        })
+    };
+
     let on_clear_cache = {
         let status_message = status_message.clone();
+        let cache_scopes = cache_scopes.clone();
+
+        Callback::from(move |_| {
+            let status_message = status_message.clone();
+            let scopes: Vec<String> = cache_scopes.iter().cloned().collect();
+            if scopes.is_empty() {
+                return;
+            }
+
+            spawn_local(async move {
+                let args =
+                    serde_wasm_bindgen::to_value(&serde_json::json!({ "scopes": scopes })).unwrap();
+                let message = match try_invoke("clear_cache", args).await {
+                    Ok(result) => match result.into_serde::<BTreeMap<String, CleanupReport>>() {
+                        Ok(reports) => (format_cleanup(&reports), true),
+                        Err(_) => ("Cache vidé avec succès".to_string(), true),
+                    },
+                    Err(e) => (
+                        e.as_string()
+                            .unwrap_or_else(|| "Échec du vidage du cache".to_string()),
+                        false,
+                    ),
+                };
+                status_message.set(Some(message));
+
+                if let Some(window) = window() {
+                    let status_clone = status_message.clone();
+                    let closure = Closure::once(move || {
+                        status_clone.set(None);
+                    });
+                    let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                        closure.as_ref().unchecked_ref(),
+                        5000,
+                    );
+                    closure.forget();
+                }
+            });
+        })
+    };
+
+    let on_repair_cache = {
+        let status_message = status_message.clone();
+
+        Callback::from(move |_| {
+            let status_message = status_message.clone();
+
+            spawn_local(async move {
+                let args =
+                    serde_wasm_bindgen::to_value(&serde_json::json!({ "repair": true })).unwrap();
+                let result = invoke_with_args("verify_cache", args).await;
+
+                let message = match result.into_serde::<Vec<String>>() {
+                    Ok(corrupt) if corrupt.is_empty() => {
+                        ("Aucune archive corrompue dans le cache".to_string(), true)
+                    }
+                    Ok(corrupt) => (
+                        format!(
+                            "{} archive(s) corrompue(s) supprimée(s) : {}",
+                            corrupt.len(),
+                            corrupt.join(", ")
+                        ),
+                        true,
+                    ),
+                    Err(_) => ("Erreur lors de la vérification du cache".to_string(), false),
+                };
+                status_message.set(Some(message));
+
+                if let Some(window) = window() {
+                    let status_clone = status_message.clone();
+                    let closure = Closure::once(move || {
+                        status_clone.set(None);
+                    });
+                    let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                        closure.as_ref().unchecked_ref(),
+                        3000,
+                    );
+                    closure.forget();
+                }
+            });
+        })
+    };
+
+    let on_check_data_updates = {
+        let status_message = status_message.clone();
+        let data_updates = data_updates.clone();
+
+        Callback::from(move |_| {
+            let status_message = status_message.clone();
+            let data_updates = data_updates.clone();
+            let mut checking = (*data_updates).clone();
+            checking.checking = true;
+            data_updates.set(checking);
+
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({})).unwrap();
+                match try_invoke("check_data_updates", args).await {
+                    Ok(result) => {
+                        if let Ok(status) = result.into_serde::<DataUpdateStatus>() {
+                            data_updates.set(status);
+                        }
+                    }
+                    Err(e) => {
+                        let mut status = (*data_updates).clone();
+                        status.checking = false;
+                        data_updates.set(status);
+                        status_message.set(Some((
+                            e.as_string().unwrap_or_else(|| {
+                                "Échec de la recherche des nouvelles données".to_string()
+                            }),
+                            false,
+                        )));
+                    }
+                }
+            });
+        })
+    };
+
+    let on_refresh_cache = {
+        let status_message = status_message.clone();
+        let data_updates = data_updates.clone();
+        let refreshing_cache = refreshing_cache.clone();
+
+        Callback::from(move |_| {
+            let status_message = status_message.clone();
+            let data_updates = data_updates.clone();
+            let refreshing_cache = refreshing_cache.clone();
+            let departments = data_updates.departments();
+            refreshing_cache.set(true);
+
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(
+                    &serde_json::json!({ "departments": departments }),
+                )
+                .unwrap();
+                let message = match try_invoke("refresh_cache", args).await {
+                    Ok(result) => {
+                        if let Ok(status) = result.into_serde::<DataUpdateStatus>() {
+                            data_updates.set(status);
+                        }
+                        ("Cache mis à jour".to_string(), true)
+                    }
+                    Err(e) => (
+                        e.as_string()
+                            .unwrap_or_else(|| "Échec de la mise à jour du cache".to_string()),
+                        false,
+                    ),
+                };
+                refreshing_cache.set(false);
+                status_message.set(Some(message));
+            });
+        })
+    };
+
+    let on_rebuild_regions = {
+        let status_message = status_message.clone();
 
         Callback::from(move |_| {
             let status_message = status_message.clone();
 
             spawn_local(async move {
-                let _ = invoke_without_args("clear_cache").await;
+                let result = invoke_without_args("rebuild_regions_graph").await;
 
-                status_message.set(Some(("Cache vidé avec succès".to_string(), true)));
+                let message = match result.into_serde::<RegionsGraphReport>() {
+                    Ok(report) => (
+                        format!(
+                            "Graphe des départements reconstruit : {} départements, {} voisinages",
+                            report.regions, report.edges
+                        ),
+                        true,
+                    ),
+                    Err(_) => (
+                        "Erreur lors de la reconstruction du graphe des départements".to_string(),
+                        false,
+                    ),
+                };
+                status_message.set(Some(message));
 
                 if let Some(window) = window() {
                     let status_clone = status_message.clone();
@@ -199,10 +640,143 @@ pub fn settings_component() -> Html {
         })
     };
 
+    let on_run_diagnostics = {
+        let diagnostics = diagnostics.clone();
+        let diagnostics_running = diagnostics_running.clone();
+
+        Callback::from(move |_| {
+            let diagnostics = diagnostics.clone();
+            let diagnostics_running = diagnostics_running.clone();
+
+            if *diagnostics_running {
+                return;
+            }
+            diagnostics_running.set(true);
+
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "network_probe": true
+                }))
+                .unwrap();
+
+                let result = invoke_with_args("run_diagnostics", args).await;
+                match result.into_serde::<DiagnosticsReport>() {
+                    Ok(report) => diagnostics.set(Some(report)),
+                    Err(e) => web_sys::console::error_1(
+                        &format!("Failed to parse diagnostics: {:?}", e).into(),
+                    ),
+                }
+
+                diagnostics_running.set(false);
+            });
+        })
+    };
+
+    let on_text_input = |state: &UseStateHandle<String>| {
+        let state = state.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            state.set(input.value());
+        })
+    };
+
+    let on_ortho_source_change = {
+        let preferred_ortho_source = preferred_ortho_source.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            preferred_ortho_source.set(select.value());
+        })
+    };
+
+    let on_offline_mode_change = {
+        let offline_mode = offline_mode.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            offline_mode.set(input.checked());
+        })
+    };
+
+    let on_log_level_change = {
+        let log_level = log_level.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            log_level.set(select.value());
+        })
+    };
+
+    let on_language_change = {
+        let language = language.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            language.set(select.value());
+        })
+    };
+
+    let on_quality_input = |field: fn(&mut ImageQuality) -> &mut u8| {
+        let image_quality = image_quality.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            if let (Some(mut quality), Ok(value)) =
+                ((*image_quality).clone(), input.value().parse::<u8>())
+            {
+                *field(&mut quality) = value.clamp(1, 100);
+                image_quality.set(Some(quality));
+            }
+        })
+    };
+
+    let on_tiff_compression_change = {
+        let image_quality = image_quality.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let (Some(mut quality), Some(compression)) = (
+                (*image_quality).clone(),
+                TiffCompression::from_code(&select.value()),
+            ) {
+                quality.tiff_compression = compression;
+                image_quality.set(Some(quality));
+            }
+        })
+    };
+
+    let on_show_logs = {
+        let log_lines = log_lines.clone();
+        Callback::from(move |_| {
+            let log_lines = log_lines.clone();
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "lines": 200
+                }))
+                .unwrap();
+
+                let result = invoke_with_args("get_recent_logs", args).await;
+                match result.into_serde::<Vec<String>>() {
+                    Ok(lines) => log_lines.set(Some(lines)),
+                    Err(e) => {
+                        web_sys::console::error_1(&format!("Failed to parse logs: {:?}", e).into())
+                    }
+                }
+            });
+        })
+    };
+
     let on_submit = {
         let output_location = output_location.clone();
         let gdal_path = gdal_path.clone();
         let python_path = python_path.clone();
+        let proxy_url = proxy_url.clone();
+        let proxy_username = proxy_username.clone();
+        let proxy_password = proxy_password.clone();
+        let no_proxy = no_proxy.clone();
+        let oso_url = oso_url.clone();
+        let preferred_ortho_source = preferred_ortho_source.clone();
+        let log_level = log_level.clone();
+        let language = language.clone();
+        let image_quality = image_quality.clone();
+        let resolution = resolution.clone();
+        let slice_factor = slice_factor.clone();
+        let temp_dir = temp_dir.clone();
+        let offline_mode = offline_mode.clone();
         let status_message = status_message.clone();
 
         Callback::from(move |e: SubmitEvent| {
@@ -211,6 +785,19 @@ pub fn settings_component() -> Html {
             let output_location = output_location.clone();
             let gdal_path = gdal_path.clone();
             let python_path = python_path.clone();
+            let proxy_url = proxy_url.clone();
+            let proxy_username = proxy_username.clone();
+            let proxy_password = proxy_password.clone();
+            let no_proxy = no_proxy.clone();
+            let oso_url = oso_url.clone();
+            let preferred_ortho_source = preferred_ortho_source.clone();
+            let log_level = log_level.clone();
+            let language = language.clone();
+            let image_quality = (*image_quality).clone();
+            let resolution = resolution.trim().parse::<f64>().ok();
+            let slice_factor = slice_factor.trim().parse::<u32>().ok();
+            let temp_dir = temp_dir.clone();
+            let offline_mode = Some(*offline_mode);
             let status_message = status_message.clone();
 
             spawn_local(async move {
@@ -232,15 +819,49 @@ pub fn settings_component() -> Html {
                         Some((*python_path).clone())
                     },
                 );
+                for (key, state) in [
+                    ("proxy_url", &proxy_url),
+                    ("proxy_username", &proxy_username),
+                    ("proxy_password", &proxy_password),
+                    ("no_proxy", &no_proxy),
+                    ("oso_url", &oso_url),
+                    ("preferred_ortho_source", &preferred_ortho_source),
+                    ("log_level", &log_level),
+                    ("language", &language),
+                    ("temp_dir", &temp_dir),
+                ] {
+                    map.insert(
+                        key,
+                        if state.is_empty() {
+                            None
+                        } else {
+                            Some((**state).clone())
+                        },
+                    );
+                }
 
-                let args = serde_wasm_bindgen::to_value(&map).unwrap();
-
-                let _ = invoke_with_args("save_settings", args).await;
+                let args = serde_wasm_bindgen::to_value(&SettingsArgs {
+                    fields: map,
+                    image_quality,
+                    resolution,
+                    slice_factor,
+                    offline_mode,
+                })
+                .unwrap();
 
-                status_message.set(Some((
-                    "Paramètres sauvegardés avec succès".to_string(),
-                    true,
-                )));
+                // Les paramètres du traitement refusés sont signalés comme un échec partiel.
+                let message = match try_invoke("save_settings", args).await {
+                    Ok(result) => match result.into_serde::<SavedSettings>() {
+                        Ok(saved) => (saved.message, saved.blocked.is_empty()),
+                        Err(_) => ("Paramètres sauvegardés avec succès".to_string(), true),
+                    },
+                    Err(e) => (
+                        e.as_string()
+                            .unwrap_or_else(|| "Échec de la sauvegarde des paramètres".to_string()),
+                        false,
+                    ),
+                };
+                status_message.set(Some(message));
 
                 if let Some(window) = window() {
                     let status_clone = status_message.clone();
@@ -314,17 +935,313 @@ pub fn settings_component() -> Html {
                         <button type="button" onclick={on_browse_python}>{"Parcourir"}</button>
                     </div>
                 </div>
+                <div class="form-group">
+                    <label for="proxy-url">{"Proxy HTTP(S)"}</label>
+                    <input
+                        type="text"
+                        id="proxy-url"
+                        placeholder="http://proxy.exemple.fr:8080 (aucun si vide)"
+                        value={(*proxy_url).clone()}
+                        oninput={on_text_input(&proxy_url)}
+                    />
+                </div>
+                <div class="form-group proxy-credentials">
+                    <input
+                        type="text"
+                        id="proxy-username"
+                        placeholder="Identifiant du proxy"
+                        value={(*proxy_username).clone()}
+                        oninput={on_text_input(&proxy_username)}
+                    />
+                    <input
+                        type="password"
+                        id="proxy-password"
+                        placeholder="Mot de passe du proxy"
+                        value={(*proxy_password).clone()}
+                        oninput={on_text_input(&proxy_password)}
+                    />
+                </div>
+                <div class="form-group">
+                    <label for="no-proxy">{"Hôtes sans proxy"}</label>
+                    <input
+                        type="text"
+                        id="no-proxy"
+                        placeholder="localhost, intranet.exemple.fr"
+                        value={(*no_proxy).clone()}
+                        oninput={on_text_input(&no_proxy)}
+                    />
+                </div>
+                <div class="form-group">
+                    <label for="ortho-source">{"Source des orthophotos"}</label>
+                    <select
+                        id="ortho-source"
+                        title="Les autres sources sont essayées si celle-ci est indisponible"
+                        onchange={on_ortho_source_change}
+                    >
+                        { for ortho_sources.iter().map(|name| html! {
+                            <option
+                                value={name.clone()}
+                                selected={*name == *preferred_ortho_source}
+                            >
+                                { name }
+                            </option>
+                        }) }
+                    </select>
+                </div>
+                <div class="form-group">
+                    <label for="oso-url">{"URL du GeoTIFF OSO"}</label>
+                    <input
+                        type="text"
+                        id="oso-url"
+                        placeholder="https://exemple.fr/OCS_2023.tif (source OSO indisponible si vide)"
+                        title="Occupation des sols OSO, lue à distance pour la source de végétation OSO"
+                        value={(*oso_url).clone()}
+                        oninput={on_text_input(&oso_url)}
+                    />
+                </div>
+                <div class="form-group">
+                    <label for="resolution">{"Résolution des projets (m/pixel)"}</label>
+                    <input
+                        type="number"
+                        id="resolution"
+                        min="0.1"
+                        step="0.1"
+                        title="Non modifiable pendant la création ou l'export d'un projet"
+                        value={(*resolution).clone()}
+                        oninput={on_text_input(&resolution)}
+                    />
+                </div>
+                <div class="form-group">
+                    <label for="slice-factor">{"Taille des découpes (pixels)"}</label>
+                    <input
+                        type="number"
+                        id="slice-factor"
+                        min="1"
+                        title="Non modifiable pendant la création ou l'export d'un projet"
+                        value={(*slice_factor).clone()}
+                        oninput={on_text_input(&slice_factor)}
+                    />
+                </div>
+                <div class="form-group">
+                    <label for="temp-dir">{"Dossier des fichiers temporaires"}</label>
+                    <input
+                        type="text"
+                        id="temp-dir"
+                        title="Non modifiable pendant la création ou l'export d'un projet"
+                        value={(*temp_dir).clone()}
+                        oninput={on_text_input(&temp_dir)}
+                    />
+                </div>
+                <div class="form-group">
+                    <label for="offline-mode">
+                        <input
+                            type="checkbox"
+                            id="offline-mode"
+                            checked={*offline_mode}
+                            onchange={on_offline_mode_change}
+                        />
+                        {"Mode hors ligne (projets créés à partir du cache, sans orthophoto)"}
+                    </label>
+                </div>
+                if let Some(quality) = (*image_quality).clone() {
+                    <>
+                        <div class="form-group">
+                            <label for="ortho-jpeg-quality">{"Qualité JPEG de l'orthophoto (1-100)"}</label>
+                            <input
+                                type="number"
+                                id="ortho-jpeg-quality"
+                                min="1"
+                                max="100"
+                                value={quality.ortho_jpeg_quality.to_string()}
+                                oninput={on_quality_input(|q| &mut q.ortho_jpeg_quality)}
+                            />
+                        </div>
+                        <div class="form-group">
+                            <label for="veget-jpeg-quality">{"Qualité JPEG de l'image de végétation (1-100)"}</label>
+                            <input
+                                type="number"
+                                id="veget-jpeg-quality"
+                                min="1"
+                                max="100"
+                                value={quality.veget_jpeg_quality.to_string()}
+                                oninput={on_quality_input(|q| &mut q.veget_jpeg_quality)}
+                            />
+                        </div>
+                        <div class="form-group">
+                            <label for="slice-jpeg-quality">{"Qualité JPEG des découpes (1-100)"}</label>
+                            <input
+                                type="number"
+                                id="slice-jpeg-quality"
+                                min="1"
+                                max="100"
+                                value={quality.slice_jpeg_quality.to_string()}
+                                oninput={on_quality_input(|q| &mut q.slice_jpeg_quality)}
+                            />
+                        </div>
+                        <div class="form-group">
+                            <label for="tiff-compression">{"Compression du GeoTIFF des projets"}</label>
+                            <select id="tiff-compression" onchange={on_tiff_compression_change}>
+                                { for TIFF_COMPRESSIONS.iter().map(|(value, label)| html! {
+                                    <option
+                                        value={value.code()}
+                                        selected={*value == quality.tiff_compression}
+                                    >
+                                        { label }
+                                    </option>
+                                }) }
+                            </select>
+                        </div>
+                    </>
+                }
+                <div class="form-group">
+                    <label for="language">{"Langue des messages de traitement"}</label>
+                    <select id="language" onchange={on_language_change}>
+                        { for languages.iter().map(|(code, label)| html! {
+                            <option value={code.clone()} selected={*code == *language}>
+                                { label }
+                            </option>
+                        }) }
+                    </select>
+                </div>
+                <div class="form-group">
+                    <label for="log-level">{"Niveau de journalisation"}</label>
+                    <select
+                        id="log-level"
+                        title="Appliqué au prochain démarrage de l'application"
+                        onchange={on_log_level_change}
+                    >
+                        { for log_levels.iter().map(|level| html! {
+                            <option value={level.clone()} selected={*level == *log_level}>
+                                { level }
+                            </option>
+                        }) }
+                    </select>
+                </div>
                 <div class="button-group">
                     <div class="primary-action">
                         <button type="submit" class="save-btn">{"Sauvegarder les paramètres"}</button>
                     </div>
                     <div class="secondary-action">
-                        <button type="button" onclick={on_clear_cache} class="clear-cache-btn">
+                        <button
+                            type="button"
+                            onclick={on_run_diagnostics}
+                            class="diagnostics-btn"
+                            disabled={*diagnostics_running}
+                        >
+                            {if *diagnostics_running { "Diagnostic en cours..." } else { "Diagnostic" }}
+                        </button>
+                        <div class="cache-scopes">
+                            { for CACHE_SCOPES.iter().map(|(scope, label)| {
+                                let on_change = {
+                                    let on_toggle_cache_scope = on_toggle_cache_scope.clone();
+                                    let scope = scope.to_string();
+                                    Callback::from(move |_: Event| on_toggle_cache_scope.emit(scope.clone()))
+                                };
+                                html! {
+                                    <label class="cache-scope">
+                                        <input
+                                            type="checkbox"
+                                            checked={cache_scopes.contains(*scope)}
+                                            onchange={on_change}
+                                        />
+                                        { label }
+                                    </label>
+                                }
+                            }) }
+                        </div>
+                        <button
+                            type="button"
+                            onclick={on_clear_cache}
+                            class="clear-cache-btn"
+                            disabled={cache_scopes.is_empty()}
+                        >
                             {"Vider le cache"}
                         </button>
+                        <button type="button" onclick={on_repair_cache} class="repair-cache-btn">
+                            {"Réparer le cache"}
+                        </button>
+                        <button type="button" onclick={on_rebuild_regions} class="rebuild-regions-btn">
+                            {"Reconstruire les départements"}
+                        </button>
+                        <button type="button" onclick={on_show_logs} class="show-logs-btn">
+                            {"Afficher le journal"}
+                        </button>
                     </div>
                 </div>
             </form>
+            <div class="data-updates">
+                <h3>{"Nouvelles données IGN"}</h3>
+                {
+                    if data_updates.updates.is_empty() {
+                        html! {
+                            <p>
+                                {
+                                    match &data_updates.checked_at {
+                                        Some(checked_at) => format!(
+                                            "Aucune nouvelle donnée (dernière recherche : {})",
+                                            checked_at.get(..16).unwrap_or(checked_at).replace('T', " ")
+                                        ),
+                                        None => "Aucune recherche effectuée".to_string(),
+                                    }
+                                }
+                            </p>
+                        }
+                    } else {
+                        html! {
+                            <ul>
+                                { for data_updates.updates.iter().map(|update| html! {
+                                    <li>
+                                        { format!(
+                                            "{} {} : {} → {}",
+                                            update.data_type, update.region, update.current, update.latest
+                                        ) }
+                                    </li>
+                                }) }
+                            </ul>
+                        }
+                    }
+                }
+                <button
+                    type="button"
+                    onclick={on_check_data_updates}
+                    disabled={data_updates.checking}
+                >
+                    {if data_updates.checking { "Recherche en cours..." } else { "Rechercher" }}
+                </button>
+                <button
+                    type="button"
+                    onclick={on_refresh_cache}
+                    class="refresh-cache-btn"
+                    disabled={data_updates.updates.is_empty() || *refreshing_cache}
+                >
+                    {if *refreshing_cache { "Mise à jour..." } else { "Mettre à jour le cache" }}
+                </button>
+            </div>
+            {
+                if let Some(report) = &*diagnostics {
+                    render_diagnostics(report)
+                } else {
+                    html! {}
+                }
+            }
+            {
+                if let Some(lines) = &*log_lines {
+                    html! {
+                        <div class="log-panel">
+                            <h3>{"Journal récent"}</h3>
+                            {
+                                if lines.is_empty() {
+                                    html! { <p>{"Aucun enregistrement"}</p> }
+                                } else {
+                                    html! { <pre>{ lines.join("\n") }</pre> }
+                                }
+                            }
+                        </div>
+                    }
+                } else {
+                    html! {}
+                }
+            }
         </div>
     }
 }
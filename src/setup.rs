@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::types::SetupReport;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
+    async fn invoke_without_args(cmd: &str) -> JsValue;
+}
+
+/// Lit un rapport de configuration renvoyé par `get_setup_report` ou `recheck_setup`.
+pub async fn fetch_setup_report(command: &str) -> Option<SetupReport> {
+    serde_wasm_bindgen::from_value(invoke_without_args(command).await).ok()
+}
+
+fn status_icon(ok: bool) -> &'static str {
+    if ok { "✓" } else { "✗" }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SetupRequiredProps {
+    pub report: SetupReport,
+    /// Reçoit le rapport de chaque nouvelle vérification.
+    pub on_report: Callback<SetupReport>,
+}
+
+/// Écran bloquant affiché tant qu'une dépendance, un dossier ou le graphe des départements
+/// manque au démarrage : il liste les éléments manquants avec leur indication d'installation.
+#[function_component(SetupRequired)]
+pub fn setup_required(props: &SetupRequiredProps) -> Html {
+    let checking = use_state(|| false);
+
+    let on_recheck = {
+        let checking = checking.clone();
+        let on_report = props.on_report.clone();
+        Callback::from(move |_| {
+            let checking = checking.clone();
+            let on_report = on_report.clone();
+            checking.set(true);
+            spawn_local(async move {
+                if let Some(report) = fetch_setup_report("recheck_setup").await {
+                    on_report.emit(report);
+                }
+                checking.set(false);
+            });
+        })
+    };
+
+    let report = &props.report;
+    html! {
+        <div class="setup-view">
+            <div class="setup-card">
+                <h2>{"Configuration requise"}</h2>
+                <p>{"Les éléments suivants doivent être installés ou corrigés avant de créer ou d'exporter des projets."}</p>
+                <ul class="setup-items">
+                    {for report.dependencies.iter().map(|dependency| html! {
+                        <li class={if dependency.found { "diag-ok" } else { "diag-error" }}>
+                            <span>
+                                {format!(
+                                    "{} {} ({})",
+                                    status_icon(dependency.found),
+                                    dependency.label,
+                                    dependency.path.clone().unwrap_or_else(|| dependency.command.clone())
+                                )}
+                            </span>
+                            if let Some(hint) = &dependency.install_hint {
+                                <code class="setup-hint">{hint}</code>
+                            }
+                        </li>
+                    })}
+                    {for report.directories.iter().filter_map(|directory| directory.error.as_ref().map(|error| html! {
+                        <li class="diag-error">
+                            {format!("{} Dossier {} : {}", status_icon(false), directory.path, error)}
+                        </li>
+                    }))}
+                    if let Some(error) = &report.regions_graph_error {
+                        <li class="diag-error">
+                            {format!("{} Graphe des départements : {}", status_icon(false), error)}
+                        </li>
+                    }
+                    if let Some(error) = &report.layer_config_error {
+                        <li class="diag-error">
+                            {format!("{} Configuration des couches : {}", status_icon(false), error)}
+                        </li>
+                    }
+                    if let Some(warning) = &report.gdal_warning {
+                        <li class="diag-warning">{warning}</li>
+                    }
+                </ul>
+                <button class="recheck-btn" onclick={on_recheck} disabled={*checking}>
+                    {if *checking { "Vérification..." } else { "Re-vérifier" }}
+                </button>
+            </div>
+        </div>
+    }
+}
@@ -1,6 +1,15 @@
-use crate::types::AppView;
+use crate::types::{AppView, DataUpdateStatus};
+use gloo_utils::format::JsValueSerdeExt;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke)]
+    async fn invoke_without_args(cmd: &str) -> JsValue;
+}
+
 #[derive(Properties, PartialEq)]
 pub struct SidebarProps {
     pub current_view: AppView,
@@ -9,6 +18,23 @@ pub struct SidebarProps {
 
 #[function_component(Sidebar)]
 pub fn sidebar(props: &SidebarProps) -> Html {
+    let pending_updates = use_state(|| 0usize);
+
+    // La recherche des nouveaux millésimes tourne en arrière-plan au démarrage :
+    // le badge est relu à chaque changement de vue.
+    {
+        let pending_updates = pending_updates.clone();
+        use_effect_with(props.current_view.clone(), move |_| {
+            spawn_local(async move {
+                let result = invoke_without_args("get_data_update_status").await;
+                if let Ok(status) = result.into_serde::<DataUpdateStatus>() {
+                    pending_updates.set(status.departments().len());
+                }
+            });
+            || ()
+        });
+    }
+
     let on_home_click = {
         let on_view_change = props.on_view_change.clone();
         Callback::from(move |_| {
@@ -69,6 +95,20 @@ pub fn sidebar(props: &SidebarProps) -> Html {
                     class={if props.current_view == AppView::Settings { "active" } else { "" }}
                 >
                     {"Paramètres"}
+                    {
+                        if *pending_updates > 0 {
+                            html! {
+                                <span
+                                    class="update-badge"
+                                    title={format!("Nouvelles données IGN pour {} département(s)", *pending_updates)}
+                                >
+                                    { *pending_updates }
+                                </span>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
                 </button>
             </div>
         </div>
@@ -0,0 +1,256 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::types::ViewMode;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"], js_name = invoke, catch)]
+    async fn try_invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "core"])]
+    fn convertFileSrc(filePath: &str, protocol: Option<&str>) -> String;
+}
+
+/// Pyramide de tuiles renvoyée par `get_tile_grid`.
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+struct TileGrid {
+    width: usize,
+    height: usize,
+    tile_size: usize,
+    max_zoom: u32,
+}
+
+impl TileGrid {
+    fn scale(&self, zoom: u32) -> usize {
+        1 << self.max_zoom.saturating_sub(zoom)
+    }
+
+    /// Taille en pixels de l'image affichée au niveau `zoom`.
+    fn image_size(&self, zoom: u32) -> (usize, usize) {
+        let scale = self.scale(zoom);
+        (self.width.div_ceil(scale), self.height.div_ceil(scale))
+    }
+
+    fn tile_count(&self, zoom: u32) -> (usize, usize) {
+        let (width, height) = self.image_size(zoom);
+        (
+            width.div_ceil(self.tile_size),
+            height.div_ceil(self.tile_size),
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct TileGridArgs {
+    project_name: String,
+    kind: ViewMode,
+}
+
+#[derive(Serialize)]
+struct TileArgs {
+    project_name: String,
+    kind: ViewMode,
+    z: u32,
+    x: usize,
+    y: usize,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct TileMapProps {
+    pub project_name: String,
+    pub view_mode: ViewMode,
+    /// Incrémentée quand l'image du projet change, pour recharger les tuiles.
+    pub version: u32,
+    /// Appelée avec la colonne et la ligne, dans l'image entière, du pixel cliqué.
+    #[prop_or_default]
+    pub on_pixel_click: Option<Callback<(u32, u32)>>,
+}
+
+/// Carte tuilée d'une image de projet : les tuiles du niveau de zoom affiché sont demandées
+/// une à une à `get_project_tile`, qui les découpe à la demande et les garde en cache.
+#[function_component(TileMap)]
+pub fn tile_map(props: &TileMapProps) -> Html {
+    let grid = use_state(|| None::<TileGrid>);
+    let zoom = use_state(|| 0u32);
+    let tiles = use_state(HashMap::<(usize, usize), String>::new);
+    let error = use_state(|| None::<String>);
+
+    {
+        let grid = grid.clone();
+        let zoom = zoom.clone();
+        let error = error.clone();
+        let project_name = props.project_name.clone();
+        let view_mode = props.view_mode.clone();
+        use_effect_with((project_name.clone(), view_mode.clone()), move |_| {
+            spawn_local(async move {
+                let args = TileGridArgs {
+                    project_name,
+                    kind: view_mode,
+                };
+                let serialized_args = serde_wasm_bindgen::to_value(&args).unwrap();
+                match try_invoke("get_tile_grid", serialized_args).await {
+                    Ok(result) => match serde_wasm_bindgen::from_value::<TileGrid>(result) {
+                        Ok(loaded) => {
+                            zoom.set(loaded.max_zoom.saturating_sub(2));
+                            grid.set(Some(loaded));
+                            error.set(None);
+                        }
+                        Err(e) => error.set(Some(format!("Carte invalide: {:?}", e))),
+                    },
+                    Err(e) => error.set(Some(
+                        e.as_string()
+                            .unwrap_or_else(|| "Carte indisponible".to_string()),
+                    )),
+                }
+            });
+            || ()
+        });
+    }
+
+    {
+        let tiles = tiles.clone();
+        let error = error.clone();
+        let project_name = props.project_name.clone();
+        let view_mode = props.view_mode.clone();
+        use_effect_with(
+            (
+                *grid,
+                *zoom,
+                project_name.clone(),
+                view_mode.clone(),
+                props.version,
+            ),
+            move |(grid, zoom, _, _, _)| {
+                // Les tuiles d'un niveau de zoom abandonné ne sont plus affichées.
+                let cancelled = Rc::new(Cell::new(false));
+                tiles.set(HashMap::new());
+                if let Some(grid) = *grid {
+                    let zoom = *zoom;
+                    let cancelled = cancelled.clone();
+                    spawn_local(async move {
+                        let (columns, rows) = grid.tile_count(zoom);
+                        let mut loaded = HashMap::new();
+                        for y in 0..rows {
+                            for x in 0..columns {
+                                let args = TileArgs {
+                                    project_name: project_name.clone(),
+                                    kind: view_mode.clone(),
+                                    z: zoom,
+                                    x,
+                                    y,
+                                };
+                                let serialized_args = serde_wasm_bindgen::to_value(&args).unwrap();
+                                let result = try_invoke("get_project_tile", serialized_args).await;
+                                if cancelled.get() {
+                                    return;
+                                }
+                                match result {
+                                    Ok(path) => {
+                                        if let Some(path) = path.as_string() {
+                                            loaded.insert((x, y), path);
+                                            tiles.set(loaded.clone());
+                                        }
+                                    }
+                                    Err(e) => {
+                                        error.set(Some(
+                                            e.as_string().unwrap_or_else(|| {
+                                                "Tuile indisponible".to_string()
+                                            }),
+                                        ));
+                                        return;
+                                    }
+                                }
+                            }
+                        }
+                    });
+                }
+                move || cancelled.set(true)
+            },
+        );
+    }
+
+    if let Some(message) = (*error).clone() {
+        return html! { <p class="tile-map-error">{message}</p> };
+    }
+    let Some(loaded_grid) = *grid else {
+        return html! { <p class="tile-map-loading">{"Chargement de la carte..."}</p> };
+    };
+
+    let on_zoom_in = {
+        let zoom = zoom.clone();
+        Callback::from(move |_| zoom.set((*zoom + 1).min(loaded_grid.max_zoom)))
+    };
+    let on_zoom_out = {
+        let zoom = zoom.clone();
+        Callback::from(move |_| zoom.set(zoom.saturating_sub(1)))
+    };
+
+    let (width, height) = loaded_grid.image_size(*zoom);
+    // L'image affichée est réduite selon le zoom et peut être redimensionnée par le CSS :
+    // la position du clic est ramenée à la taille de l'image entière.
+    let on_layer_click = props.on_pixel_click.clone().map(|on_pixel_click| {
+        Callback::from(move |e: MouseEvent| {
+            let Some(layer) = e
+                .current_target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+            else {
+                return;
+            };
+            let rect = layer.get_bounding_client_rect();
+            if rect.width() <= 0.0 || rect.height() <= 0.0 {
+                return;
+            }
+            let ratio_x = (e.client_x() as f64 - rect.left()) / rect.width();
+            let ratio_y = (e.client_y() as f64 - rect.top()) / rect.height();
+            let px = (ratio_x * loaded_grid.width as f64).floor();
+            let py = (ratio_y * loaded_grid.height as f64).floor();
+            if px < 0.0 || py < 0.0 {
+                return;
+            }
+            on_pixel_click.emit((
+                (px as u32).min(loaded_grid.width as u32 - 1),
+                (py as u32).min(loaded_grid.height as u32 - 1),
+            ));
+        })
+    });
+    let tile_size = loaded_grid.tile_size;
+    let tile_src = |path: &str| match props.version {
+        0 => convertFileSrc(path, None),
+        version => format!("{}?v={}", convertFileSrc(path, None), version),
+    };
+
+    html! {
+        <div class="tile-map">
+            <div class="tile-zoom-controls">
+                <button onclick={on_zoom_in} disabled={*zoom >= loaded_grid.max_zoom}>{"+"}</button>
+                <button onclick={on_zoom_out} disabled={*zoom == 0}>{"−"}</button>
+            </div>
+            <div
+                class="tile-layer"
+                style={format!("width: {}px; height: {}px", width, height)}
+                onclick={on_layer_click}
+            >
+                { for tiles.iter().map(|((x, y), path)| html! {
+                    <img
+                        key={format!("{}-{}", x, y)}
+                        src={tile_src(path)}
+                        style={format!(
+                            "left: {}px; top: {}px; width: {}px; height: {}px",
+                            x * tile_size,
+                            y * tile_size,
+                            tile_size,
+                            tile_size
+                        )}
+                    />
+                })}
+            </div>
+        </div>
+    }
+}
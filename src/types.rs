@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+pub use crate::payloads::{ProgressUpdate, ProjectSummary};
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum AppView {
     Home,
@@ -10,13 +12,6 @@ pub enum AppView {
     Project(ProjectData),
 }
 
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-pub struct Project {
-    pub name: String,
-    pub preview_path: String,
-    pub file_path: String,
-}
-
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ProjectData {
     pub name: String,
@@ -29,3 +24,141 @@ pub enum ViewMode {
     Vegetation,
     Satellite,
 }
+
+/// Entrée de la légende de l'image de végétation, renvoyée par `get_legend`.
+/// Les classes de même couleur sont regroupées dans une seule entrée.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LegendEntry {
+    pub class_key: String,
+    pub label_fr: String,
+    pub label_en: String,
+    pub rgb: [u8; 3],
+    pub source_layer: String,
+    pub priority: u8,
+}
+
+/// Résumé transmis par l'événement `project-created`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProjectCreated {
+    pub name: String,
+    pub folder: String,
+    pub veget_path: String,
+    pub ortho_path: String,
+    pub duration_seconds: f64,
+    pub regions: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Erreur transmise par l'événement `project-creation-failed`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProjectCreationFailed {
+    pub name: String,
+    pub message: String,
+    pub cancelled: bool,
+    #[serde(default)]
+    pub resumable: bool,
+    pub warnings: Vec<String>,
+}
+
+/// Export de la file d'exports, renvoyé par `get_export_queue`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ExportJob {
+    pub project: String,
+    /// `queued`, `running`, `done`, `failed` ou `cancelled`.
+    pub state: String,
+    pub zip_path: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ExportJob {
+    pub fn is_pending(&self) -> bool {
+        self.state == "queued" || self.state == "running"
+    }
+
+    pub fn state_label(&self) -> &'static str {
+        match self.state.as_str() {
+            "queued" => "En attente",
+            "running" => "En cours",
+            "done" => "Terminé",
+            "failed" => "Échec",
+            _ => "Annulé",
+        }
+    }
+}
+
+/// Libellé de la tâche en cours sur un projet, renvoyée par `get_busy_projects`.
+pub fn busy_label(kind: &str) -> &'static str {
+    match kind {
+        "creating" => "Création en cours",
+        "exporting" => "Export en cours",
+        "refreshing_ortho" => "Mise à jour de l'orthophoto en cours",
+        "adding_layer" => "Ajout d'une couche en cours",
+        "cloning" => "Duplication en cours",
+        "renaming" => "Renommage en cours",
+        "deleting" => "Suppression en cours",
+        _ => "Projet occupé",
+    }
+}
+
+/// État d'une dépendance externe dans le rapport de `get_setup_report`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DependencyStatus {
+    pub label: String,
+    pub command: String,
+    pub found: bool,
+    pub path: Option<String>,
+    pub install_hint: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DirectorySetup {
+    pub path: String,
+    pub error: Option<String>,
+}
+
+/// Rapport des vérifications du démarrage, renvoyé par `get_setup_report` et `recheck_setup`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SetupReport {
+    pub dependencies: Vec<DependencyStatus>,
+    pub directories: Vec<DirectorySetup>,
+    pub regions_graph_error: Option<String>,
+    pub layer_config_error: Option<String>,
+    #[serde(default)]
+    pub gdal_version: Option<String>,
+    /// Version de GDAL antérieure à la version minimale prise en charge, sans bloquer l'application.
+    #[serde(default)]
+    pub gdal_warning: Option<String>,
+    pub ready: bool,
+}
+
+/// Archive dont un millésime plus récent est publié, renvoyée par `get_data_update_status`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DataUpdate {
+    pub data_type: String,
+    pub region: String,
+    pub current: String,
+    pub latest: String,
+    pub url: String,
+}
+
+/// Résultat de la dernière recherche de nouveaux millésimes.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct DataUpdateStatus {
+    pub checked_at: Option<String>,
+    pub checking: bool,
+    pub updates: Vec<DataUpdate>,
+}
+
+impl DataUpdateStatus {
+    /// Départements ayant au moins une archive à mettre à jour, triés.
+    pub fn departments(&self) -> Vec<String> {
+        let mut departments: Vec<String> = self
+            .updates
+            .iter()
+            .map(|update| update.region.clone())
+            .collect();
+        departments.sort();
+        departments.dedup();
+        departments
+    }
+}